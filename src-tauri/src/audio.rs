@@ -1,28 +1,186 @@
 //! Audio recording module for WhisperTray
 //!
-//! Handles microphone capture using cpal (which supports PipeWire, PulseAudio, ALSA)
+//! Handles microphone capture using cpal (which supports PipeWire, PulseAudio, ALSA),
+//! or natively through PipeWire when built with the `pipewire` feature
 
 use crate::error::{AppError, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, SampleFormat, StreamConfig};
 use hound::{SampleFormat as HoundSampleFormat, WavSpec, WavWriter};
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
 /// Audio sample rate for whisper.cpp (16kHz required)
 pub const WHISPER_SAMPLE_RATE: u32 = 16000;
 
+/// PulseAudio/PipeWire alias for the default output's monitor source
+pub const DEFAULT_MONITOR: &str = "@DEFAULT_MONITOR@";
+
 /// Audio input device information
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AudioDevice {
     pub name: String,
+    /// Human-readable name, when the backend knows one (PipeWire node description)
+    #[serde(default)]
+    pub label: Option<String>,
     pub is_default: bool,
+    /// Whether this is a monitor (loopback) of an output device
+    #[serde(default)]
+    pub is_monitor: bool,
+}
+
+/// How audio devices are listed and opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioBackend {
+    /// cpal through ALSA (and the pulse plugin for monitors)
+    #[default]
+    Cpal,
+    /// PipeWire nodes, addressed by name (needs the `pipewire` feature)
+    PipeWire,
+}
+
+/// Backends that can be chosen in this build
+pub fn available_audio_backends() -> Vec<AudioBackend> {
+    let mut backends = vec![AudioBackend::Cpal];
+    if cfg!(feature = "pipewire") && crate::features::enabled(crate::features::Flag::PipeWireBackend) {
+        backends.push(AudioBackend::PipeWire);
+    }
+    backends
+}
+
+/// Configured audio backend, as an `AudioBackend` discriminant
+static AUDIO_BACKEND: AtomicU8 = AtomicU8::new(AudioBackend::Cpal as u8);
+
+/// Choose how audio devices are listed and opened
+pub fn set_audio_backend(backend: AudioBackend) {
+    AUDIO_BACKEND.store(backend as u8, Ordering::SeqCst);
+}
+
+/// Whether devices go through PipeWire directly
+pub(crate) fn use_pipewire() -> bool {
+    if AUDIO_BACKEND.load(Ordering::SeqCst) != AudioBackend::PipeWire as u8 {
+        return false;
+    }
+    if !cfg!(feature = "pipewire") {
+        log::warn!("PipeWire backend selected but not built in (feature \"pipewire\"); using cpal");
+        return false;
+    }
+    if !crate::features::enabled(crate::features::Flag::PipeWireBackend) {
+        log::warn!("PipeWire backend selected but its feature flag is off; using cpal");
+        return false;
+    }
+    true
+}
+
+/// Which cpal host lists and opens devices under the cpal backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioHost {
+    /// cpal's default (ALSA on Linux)
+    #[default]
+    Default,
+    /// ALSA, named explicitly
+    Alsa,
+    /// A running JACK server (needs the `jack` feature)
+    Jack,
+}
+
+impl AudioHost {
+    const ALL: [AudioHost; 3] = [AudioHost::Default, AudioHost::Alsa, AudioHost::Jack];
+
+    fn host_id(self) -> Option<cpal::HostId> {
+        match self {
+            AudioHost::Default => None,
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+            AudioHost::Alsa => Some(cpal::HostId::Alsa),
+            #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "freebsd")))]
+            AudioHost::Jack => Some(cpal::HostId::Jack),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// Whether the host is built in and can be used here
+    fn is_available(self) -> bool {
+        match self {
+            AudioHost::Default => true,
+            host => host
+                .host_id()
+                .is_some_and(|id| cpal::available_hosts().contains(&id)),
+        }
+    }
+
+    /// The cpal host, or the default one when this one cannot be opened
+    fn open(self) -> cpal::Host {
+        let Some(id) = self.host_id() else {
+            if self != AudioHost::Default {
+                log::warn!("Audio host {:?} is not built in; using the default host", self);
+            }
+            return cpal::default_host();
+        };
+        cpal::host_from_id(id).unwrap_or_else(|e| {
+            log::warn!("Audio host {:?} unavailable ({}); using the default host", self, e);
+            cpal::default_host()
+        })
+    }
+}
+
+/// Configured cpal host, as an `AudioHost` discriminant
+static AUDIO_HOST: AtomicU8 = AtomicU8::new(AudioHost::Default as u8);
+
+/// Choose which cpal host lists and opens devices
+pub fn set_audio_host(host: AudioHost) {
+    AUDIO_HOST.store(host as u8, Ordering::SeqCst);
+}
+
+fn audio_host() -> AudioHost {
+    AudioHost::ALL[AUDIO_HOST.load(Ordering::SeqCst) as usize]
+}
+
+/// The configured cpal host
+pub(crate) fn cpal_host() -> cpal::Host {
+    audio_host().open()
+}
+
+/// Hosts that can be chosen in this build on this system
+pub fn available_audio_hosts() -> Vec<AudioHost> {
+    AudioHost::ALL.into_iter().filter(|host| host.is_available()).collect()
+}
+
+/// Where recorded audio comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureSource {
+    /// Microphone / regular input device
+    #[default]
+    Microphone,
+    /// System output monitor (what is playing through the speakers)
+    System,
+    /// Microphone and system output recorded together
+    Both,
 }
 
 /// Get list of available input devices
 pub fn get_input_devices() -> Result<Vec<AudioDevice>> {
-    let host = cpal::default_host();
+    if use_pipewire() {
+        #[cfg(feature = "pipewire")]
+        match crate::pipewire_backend::list_devices() {
+            Ok(devices) => return Ok(devices),
+            Err(e) => log::warn!("Listing PipeWire devices failed, using cpal: {}", e),
+        }
+    }
+    get_host_input_devices(audio_host())
+}
+
+/// Input devices of one cpal host, whichever host is configured
+pub fn get_host_input_devices(host: AudioHost) -> Result<Vec<AudioDevice>> {
+    let host = host.open();
     let default_device = host.default_input_device();
     let default_name = default_device
         .as_ref()
@@ -37,16 +195,164 @@ pub fn get_input_devices() -> Result<Vec<AudioDevice>> {
             result.push(AudioDevice {
                 is_default: name == default_name,
                 name,
+                label: None,
+                is_monitor: false,
             });
         }
     }
 
+    // Monitor sources are not exposed through ALSA, list them via PulseAudio/PipeWire
+    for name in get_monitor_sources() {
+        result.push(AudioDevice {
+            name,
+            label: None,
+            is_default: false,
+            is_monitor: true,
+        });
+    }
+
     Ok(result)
 }
 
+/// List monitor (loopback) sources using `pactl` (PulseAudio or pipewire-pulse)
+pub fn get_monitor_sources() -> Vec<String> {
+    match Command::new("pactl").args(["list", "short", "sources"]).output() {
+        Ok(output) if output.status.success() => {
+            parse_monitor_sources(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(_) | Err(_) => {
+            log::debug!("pactl not available, no monitor sources listed");
+            Vec::new()
+        }
+    }
+}
+
+/// Extract monitor source names from `pactl list short sources` output
+fn parse_monitor_sources(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .filter(|name| name.ends_with(".monitor"))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Find the `pulse` ALSA device used to record monitor sources
+///
+/// The `pulse` ALSA device records from the default source; its stream is
+/// moved to the monitor once open, see `open_on_monitor`.
+fn get_monitor_device() -> Result<Device> {
+    // The pulse plugin is an ALSA device, whichever host records the microphone
+    let host = cpal::default_host();
+    host.input_devices()?
+        .find(|d| d.name().map_or(false, |n| n == "pulse"))
+        .ok_or_else(|| {
+            AppError::Audio(
+                "System audio capture requires the PulseAudio ALSA plugin (pulse device)".to_string(),
+            )
+        })
+}
+
+/// How long a newly opened stream may take to show up in the audio server
+const STREAM_APPEAR_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Open a stream with `open`, then have the audio server record it from `monitor_name`
+///
+/// Pointing `PULSE_SOURCE` at the monitor instead would change the
+/// environment of the whole process while other threads may read it. Our
+/// streams already open, e.g. the microphone's, are left where they are.
+fn open_on_monitor<T>(monitor_name: Option<&str>, open: impl FnOnce() -> Result<T>) -> Result<T> {
+    let Some(monitor_name) = monitor_name else {
+        return open();
+    };
+    let unavailable = || AppError::Audio("System audio capture requires pactl (PulseAudio or pipewire-pulse)".to_string());
+
+    let before = crate::mic_conflict::own_capture_streams().ok_or_else(unavailable)?;
+    let stream = open()?;
+    let started = Instant::now();
+    let opened = loop {
+        let opened: Vec<u32> = crate::mic_conflict::own_capture_streams()
+            .ok_or_else(unavailable)?
+            .into_iter()
+            .filter(|id| !before.contains(id))
+            .collect();
+        if !opened.is_empty() || started.elapsed() > STREAM_APPEAR_TIMEOUT {
+            break opened;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    if opened.is_empty() {
+        return Err(AppError::Audio(format!("The stream to record {} from did not appear", monitor_name)));
+    }
+    for id in opened {
+        if !crate::mic_conflict::move_capture_stream(id, monitor_name) {
+            return Err(AppError::Audio(format!("Failed to record from {}", monitor_name)));
+        }
+    }
+    Ok(stream)
+}
+
+/// A device to open for recording
+pub enum CaptureInput {
+    /// A cpal device, with the monitor to select if any
+    Cpal { device: Device, monitor: Option<String> },
+    /// A PipeWire node, opened natively
+    #[cfg(feature = "pipewire")]
+    PipeWire(crate::pipewire_backend::Target),
+}
+
+impl CaptureInput {
+    /// Resolve a device by name; monitor names are routed through PulseAudio
+    pub fn from_name(name: &str) -> Result<Self> {
+        if name.ends_with(".monitor") || name == DEFAULT_MONITOR {
+            return Self::monitor(name);
+        }
+        #[cfg(feature = "pipewire")]
+        if use_pipewire() {
+            return Ok(Self::PipeWire(crate::pipewire_backend::Target::source(name)));
+        }
+        Ok(Self::Cpal {
+            device: get_device_by_name(name)?,
+            monitor: None,
+        })
+    }
+
+    /// System output monitor (empty name means the default output)
+    pub fn monitor(name: &str) -> Result<Self> {
+        let name = if name.is_empty() { DEFAULT_MONITOR } else { name };
+        log::info!("Capturing system audio from monitor: {}", name);
+        #[cfg(feature = "pipewire")]
+        if use_pipewire() {
+            return Ok(Self::PipeWire(crate::pipewire_backend::Target::monitor(name)));
+        }
+        Ok(Self::Cpal {
+            device: get_monitor_device()?,
+            monitor: Some(name.to_string()),
+        })
+    }
+}
+
+/// Resolve the devices to record from for a capture source
+///
+/// The first input is the primary track (the microphone when recording both).
+pub fn get_capture_inputs(
+    source: CaptureSource,
+    input_device: &str,
+    monitor_device: &str,
+) -> Result<Vec<CaptureInput>> {
+    match source {
+        CaptureSource::Microphone => Ok(vec![CaptureInput::from_name(input_device)?]),
+        CaptureSource::System => Ok(vec![CaptureInput::monitor(monitor_device)?]),
+        CaptureSource::Both => Ok(vec![
+            CaptureInput::from_name(input_device)?,
+            CaptureInput::monitor(monitor_device)?,
+        ]),
+    }
+}
+
 /// Get a specific input device by name
 pub fn get_device_by_name(name: &str) -> Result<Device> {
-    let host = cpal::default_host();
+    let host = cpal_host();
 
     if name.is_empty() || name == "default" {
         return host
@@ -59,26 +365,348 @@ pub fn get_device_by_name(name: &str) -> Result<Device> {
         .ok_or_else(|| AppError::Audio(format!("Device not found: {}", name)))
 }
 
+/// Number of tracks a recording can capture (microphone + system audio)
+const MAX_TRACKS: usize = 2;
+
+/// Software gain range, in dB
+pub const MIN_GAIN_DB: f32 = -20.0;
+pub const MAX_GAIN_DB: f32 = 30.0;
+
+/// Which channel of a multi-channel input is recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelSelect {
+    /// Average of all channels
+    #[default]
+    Mix,
+    /// The first channel
+    Left,
+    /// The second channel
+    Right,
+    /// One channel by index, counting from 0
+    Channel(u16),
+}
+
+impl ChannelSelect {
+    /// Channel to keep out of `channels`, None to mix them (also when it does not exist)
+    fn index(self, channels: usize) -> Option<usize> {
+        let index = match self {
+            ChannelSelect::Mix => return None,
+            ChannelSelect::Left => 0,
+            ChannelSelect::Right => 1,
+            ChannelSelect::Channel(index) => index as usize,
+        };
+        (index < channels).then_some(index)
+    }
+
+    /// Channels a stream needs for the selected channel to exist
+    fn channels_needed(self) -> u16 {
+        match self {
+            ChannelSelect::Mix | ChannelSelect::Left => 1,
+            ChannelSelect::Right => 2,
+            ChannelSelect::Channel(index) => index.saturating_add(1),
+        }
+    }
+}
+
+/// Samples at or above this magnitude count as clipped
+const FULL_SCALE: f32 = 0.999;
+
+/// Linear factor for a gain in dB, limited to the supported range
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db.clamp(MIN_GAIN_DB, MAX_GAIN_DB) / 20.0)
+}
+
+/// Apply `gain` to raw input, clamping to full scale as a real preamp would
+fn apply_gain(data: &[f32], gain: f32) -> Vec<f32> {
+    data.iter().map(|&sample| (sample * gain).clamp(-1.0, 1.0)).collect()
+}
+
+/// How long an input may deliver nothing before its device counts as gone
+const STREAM_STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A change of input device during a recording
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeviceChange {
+    /// The microphone went away and recording continued on the default device
+    FellBack { lost: String, now: String },
+    /// System audio went away; recording continues from the microphone
+    Dropped { device: String },
+    /// The microphone went away and no device could take over
+    Lost { device: String },
+}
+
+/// When each track last delivered audio, and whether its stream reported the device gone
+#[derive(Debug, Default)]
+struct InputWatch {
+    last_input: [Option<Instant>; MAX_TRACKS],
+    failed: [bool; MAX_TRACKS],
+}
+
+/// Longest a stop waits for the streams to deliver what they buffered
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Longest `stop_recording` waits for the capture thread to close its streams
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How far a recording's capture is from stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaptureState {
+    /// Streams deliver into the recording
+    Running,
+    /// Stop was requested at this instant; streams deliver what they still hold
+    Stopping(Instant),
+    /// Streams are closed or detached, nothing more is added
+    Done,
+}
+
+/// The capture state of one recording, numbered so a late capture thread
+/// can't touch the next recording's
+#[derive(Debug, Clone, Copy)]
+struct CaptureSlot {
+    generation: u64,
+    state: CaptureState,
+}
+
+/// Handshake between `stop_recording` and the thread that owns the streams
+struct Capture {
+    slot: Mutex<CaptureSlot>,
+    changed: Condvar,
+}
+
+/// Marks the capture done when the thread owning the streams drops it
+///
+/// Created before the streams so it is dropped after them, on every way
+/// out of the thread. A thread slow to close its streams may drop it after
+/// the next recording started; only its own recording is marked done.
+pub(crate) struct CaptureDone {
+    handle: RecordingHandle,
+    generation: u64,
+}
+
+impl CaptureDone {
+    pub(crate) fn new(handle: &RecordingHandle) -> Self {
+        Self {
+            handle: handle.clone(),
+            generation: handle.generation(),
+        }
+    }
+}
+
+impl Drop for CaptureDone {
+    fn drop(&mut self) {
+        self.handle.finish_capture(self.generation);
+    }
+}
+
+/// Waveform peaks per second of audio, as drawn by the recording indicator
+pub const WAVEFORM_RATE: u32 = 50;
+
+/// Peaks kept when nobody takes them, so the buffer stays bounded (5s)
+const MAX_PENDING_PEAKS: usize = 5 * WAVEFORM_RATE as usize;
+
+/// Downsampled primary-track peaks for drawing a scrolling waveform
+#[derive(Debug, Default)]
+struct Waveform {
+    /// Finished peaks not yet taken
+    peaks: Vec<f32>,
+    /// Peak of the window being filled
+    current: f32,
+    /// Samples in the window being filled
+    filled: usize,
+}
+
+impl Waveform {
+    /// Samples (16kHz) summarized by one peak
+    const WINDOW: usize = (WHISPER_SAMPLE_RATE / WAVEFORM_RATE) as usize;
+
+    fn push(&mut self, samples: &[f32]) {
+        for sample in samples {
+            self.current = self.current.max(sample.abs());
+            self.filled += 1;
+            if self.filled == Self::WINDOW {
+                self.peaks.push(self.current.min(1.0));
+                self.current = 0.0;
+                self.filled = 0;
+            }
+        }
+        if self.peaks.len() > MAX_PENDING_PEAKS {
+            let excess = self.peaks.len() - MAX_PENDING_PEAKS;
+            self.peaks.drain(..excess);
+        }
+    }
+}
+
+/// In-memory audio kept before single-track recordings are spooled to disk (60s)
+const SPOOL_CHUNK_SAMPLES: usize = 60 * WHISPER_SAMPLE_RATE as usize;
+
+/// Temporary WAV file that long recordings are streamed into
+struct AudioSpool {
+    path: PathBuf,
+    writer: WavWriter<BufWriter<File>>,
+    sample_count: usize,
+}
+
+impl AudioSpool {
+    /// Start a spool, as 32-bit float when `lossless` and 16-bit otherwise
+    fn create(lossless: bool) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("whispertray-{}.wav", uuid::Uuid::new_v4()));
+        let spec = if lossless {
+            WavSpec {
+                channels: 1,
+                sample_rate: WHISPER_SAMPLE_RATE,
+                bits_per_sample: 32,
+                sample_format: HoundSampleFormat::Float,
+            }
+        } else {
+            WavSpec {
+                channels: 1,
+                sample_rate: WHISPER_SAMPLE_RATE,
+                bits_per_sample: 16,
+                sample_format: HoundSampleFormat::Int,
+            }
+        };
+        let writer = WavWriter::create(&path, spec)?;
+        log::info!("Spooling long recording to {:?}", path);
+        Ok(Self {
+            path,
+            writer,
+            sample_count: 0,
+        })
+    }
+
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        let lossless = self.writer.spec().sample_format == HoundSampleFormat::Float;
+        for &sample in samples {
+            if lossless {
+                self.writer.write_sample(sample)?;
+            } else {
+                self.writer
+                    .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+            }
+        }
+        self.writer.flush()?;
+        self.sample_count += samples.len();
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(PathBuf, usize)> {
+        self.writer.finalize()?;
+        Ok((self.path, self.sample_count))
+    }
+}
+
+/// Audio captured by a finished recording
+pub enum RecordedAudio {
+    /// Short recording held in memory
+    Memory(Vec<f32>),
+    /// Long recording streamed to a WAV file on disk
+    Spooled { path: PathBuf, sample_count: usize },
+}
+
+impl RecordedAudio {
+    /// Number of 16kHz samples recorded
+    pub fn sample_count(&self) -> usize {
+        match self {
+            RecordedAudio::Memory(samples) => samples.len(),
+            RecordedAudio::Spooled { sample_count, .. } => *sample_count,
+        }
+    }
+
+    /// Read a range of samples without loading the whole recording
+    pub fn read_range(&self, start: usize, len: usize) -> Result<Vec<f32>> {
+        match self {
+            RecordedAudio::Memory(samples) => {
+                let start = start.min(samples.len());
+                let end = (start + len).min(samples.len());
+                Ok(samples[start..end].to_vec())
+            }
+            RecordedAudio::Spooled { path, .. } => read_wav_range(path, start, len),
+        }
+    }
+}
+
 /// Shared recording state (Send + Sync safe)
 #[derive(Clone)]
 pub struct RecordingHandle {
-    /// Audio samples buffer (f32 normalized)
-    samples: Arc<Mutex<Vec<f32>>>,
+    /// Audio samples buffer per track (f32 normalized, 16kHz mono)
+    tracks: Arc<Mutex<[Vec<f32>; MAX_TRACKS]>>,
+    /// Offset (in samples) of each track's first audio from recording start
+    track_offsets: Arc<Mutex<[Option<usize>; MAX_TRACKS]>>,
+    /// When the current recording started
+    started_at: Arc<Mutex<Option<Instant>>>,
     /// Recording flag
     is_recording: Arc<AtomicBool>,
+    /// Input is dropped while paused, e.g. while another app uses the mic
+    paused: Arc<AtomicBool>,
+    /// Paused by the user, apart from pauses for a microphone conflict
+    held: Arc<AtomicBool>,
+    /// Whether the primary track delivered anything but digital silence
+    signal: Arc<AtomicBool>,
     /// Current audio level (RMS, 0.0 to 1.0)
     current_level: Arc<Mutex<f32>>,
     /// Peak level
     peak_level: Arc<Mutex<f32>>,
+    /// Bookmarks dropped during the current recording (offsets in ms)
+    bookmarks: Arc<Mutex<Vec<u64>>>,
+    /// Whether the primary track may be spooled to disk (single-track only)
+    spool_enabled: Arc<AtomicBool>,
+    /// Whether the spool keeps float samples rather than 16-bit ones
+    spool_lossless: Arc<AtomicBool>,
+    /// Disk spool for long recordings
+    spool: Arc<Mutex<Option<AudioSpool>>>,
+    /// Samples already moved from memory into the spool
+    offloaded_samples: Arc<AtomicUsize>,
+    /// Liveness of each track's input, to notice unplugged devices
+    input_watch: Arc<Mutex<InputWatch>>,
+    /// Device changes not yet reported to the user
+    device_changes: Arc<Mutex<Vec<DeviceChange>>>,
+    /// Software gain of each track's input, as a linear factor
+    gains: Arc<Mutex<[f32; MAX_TRACKS]>>,
+    /// Channel recorded from each track's input
+    channel_select: Arc<Mutex<[ChannelSelect; MAX_TRACKS]>>,
+    /// Raw primary-track samples received this recording
+    input_samples: Arc<AtomicUsize>,
+    /// Of those, the ones at full scale after gain
+    clipped_samples: Arc<AtomicUsize>,
+    /// Whether the streams are still adding to the recording
+    capture: Arc<Capture>,
+    /// Primary-track peaks for the indicator's waveform
+    waveform: Arc<Mutex<Waveform>>,
 }
 
 impl RecordingHandle {
     pub fn new() -> Self {
         Self {
-            samples: Arc::new(Mutex::new(Vec::new())),
+            tracks: Arc::new(Mutex::new(Default::default())),
+            track_offsets: Arc::new(Mutex::new([None; MAX_TRACKS])),
+            started_at: Arc::new(Mutex::new(None)),
             is_recording: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+            held: Arc::new(AtomicBool::new(false)),
+            signal: Arc::new(AtomicBool::new(false)),
             current_level: Arc::new(Mutex::new(0.0)),
             peak_level: Arc::new(Mutex::new(0.0)),
+            bookmarks: Arc::new(Mutex::new(Vec::new())),
+            spool_enabled: Arc::new(AtomicBool::new(false)),
+            spool_lossless: Arc::new(AtomicBool::new(false)),
+            spool: Arc::new(Mutex::new(None)),
+            offloaded_samples: Arc::new(AtomicUsize::new(0)),
+            input_watch: Arc::new(Mutex::new(InputWatch::default())),
+            device_changes: Arc::new(Mutex::new(Vec::new())),
+            gains: Arc::new(Mutex::new([1.0; MAX_TRACKS])),
+            channel_select: Arc::new(Mutex::new([ChannelSelect::Mix; MAX_TRACKS])),
+            input_samples: Arc::new(AtomicUsize::new(0)),
+            clipped_samples: Arc::new(AtomicUsize::new(0)),
+            capture: Arc::new(Capture {
+                slot: Mutex::new(CaptureSlot {
+                    generation: 0,
+                    state: CaptureState::Done,
+                }),
+                changed: Condvar::new(),
+            }),
+            waveform: Arc::new(Mutex::new(Waveform::default())),
         }
     }
 
@@ -87,22 +715,464 @@ impl RecordingHandle {
     }
 
     pub fn set_recording(&self, recording: bool) {
+        if recording {
+            let mut slot = self.capture_slot();
+            slot.generation += 1;
+            slot.state = CaptureState::Running;
+        }
         self.is_recording.store(recording, Ordering::SeqCst);
     }
 
+    /// Number of the current (or last) recording, counting up from 1
+    ///
+    /// Threads and tasks working for one recording compare it to tell when
+    /// a newer recording has taken over the handle.
+    pub fn generation(&self) -> u64 {
+        self.capture_slot().generation
+    }
+
+    /// Whether recording `generation` is still going on
+    ///
+    /// Watchers spawned for one recording check this rather than
+    /// `is_recording`, which stays true when a new recording starts right
+    /// after.
+    pub fn is_recording_generation(&self, generation: u64) -> bool {
+        self.is_recording() && self.generation() == generation
+    }
+
+    fn capture_slot(&self) -> std::sync::MutexGuard<'_, CaptureSlot> {
+        self.capture.slot.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Mark recording `generation`'s capture done, if it is still the current one
+    fn finish_capture(&self, generation: u64) {
+        let mut slot = self.capture_slot();
+        if slot.generation == generation {
+            slot.state = CaptureState::Done;
+            self.capture.changed.notify_all();
+        }
+    }
+
+    /// Ask the capture thread to flush and close its streams
+    fn request_stop(&self) {
+        let mut slot = self.capture_slot();
+        if slot.state == CaptureState::Running {
+            slot.state = CaptureState::Stopping(Instant::now());
+        }
+    }
+
+    /// Wait until the capture thread closed its streams; false on timeout
+    fn wait_capture_done(&self, timeout: Duration) -> bool {
+        let slot = self.capture_slot();
+        let (_, result) = self
+            .capture
+            .changed
+            .wait_timeout_while(slot, timeout, |slot| slot.state != CaptureState::Done)
+            .unwrap_or_else(|e| e.into_inner());
+        !result.timed_out()
+    }
+
+    /// Whether a stop was requested and every stream delivered audio since
+    ///
+    /// A stream hands over audio in blocks, so the last words before the
+    /// stop are still in flight when it is requested. A stream that stays
+    /// silent (a dead device) is given up on after `DRAIN_TIMEOUT`.
+    fn capture_flushed(&self) -> bool {
+        let state = self.capture_slot().state;
+        let CaptureState::Stopping(requested) = state else {
+            return false;
+        };
+        if requested.elapsed() >= DRAIN_TIMEOUT {
+            return true;
+        }
+        self.input_watch
+            .lock()
+            .map(|watch| watch.last_input.iter().flatten().all(|&at| at >= requested))
+            .unwrap_or(true)
+    }
+
+    /// Whether audio is being left out, for the user or a microphone conflict
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst) || self.is_held()
+    }
+
+    /// Pause or resume taking in audio, without ending the recording
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    /// Whether the user paused the recording
+    pub fn is_held(&self) -> bool {
+        self.held.load(Ordering::SeqCst)
+    }
+
+    /// Leave audio out of the recording until `resume_recording`
+    ///
+    /// Independent of pauses for a microphone conflict, so the other app
+    /// letting go of the microphone does not resume it.
+    pub fn pause_recording(&self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(AppError::NoRecordingInProgress);
+        }
+        self.held.store(true, Ordering::SeqCst);
+        // The meter shows silence rather than the last level heard
+        self.update_level(&[0.0]);
+        Ok(())
+    }
+
+    /// Take in audio again after `pause_recording`
+    pub fn resume_recording(&self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(AppError::NoRecordingInProgress);
+        }
+        self.held.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Whether the primary track received any non-zero sample
+    ///
+    /// Even a quiet room gives a real microphone some noise; exact zeros mean
+    /// the device is muted in hardware or access to it is blocked.
+    pub fn has_signal(&self) -> bool {
+        self.signal.load(Ordering::SeqCst)
+    }
+
     pub fn clear_samples(&self) {
-        if let Ok(mut samples) = self.samples.lock() {
-            samples.clear();
+        if let Ok(mut tracks) = self.tracks.lock() {
+            for track in tracks.iter_mut() {
+                track.clear();
+            }
+        }
+        if let Ok(mut offsets) = self.track_offsets.lock() {
+            *offsets = [None; MAX_TRACKS];
+        }
+        if let Ok(mut started_at) = self.started_at.lock() {
+            *started_at = Some(Instant::now());
+        }
+        if let Ok(mut bookmarks) = self.bookmarks.lock() {
+            bookmarks.clear();
+        }
+        if let Ok(mut spool) = self.spool.lock() {
+            // Discard a spool left over from an abandoned recording
+            if let Some(old) = spool.take() {
+                let _ = std::fs::remove_file(&old.path);
+            }
         }
+        if let Ok(mut watch) = self.input_watch.lock() {
+            *watch = InputWatch::default();
+        }
+        if let Ok(mut changes) = self.device_changes.lock() {
+            changes.clear();
+        }
+        if let Ok(mut waveform) = self.waveform.lock() {
+            *waveform = Waveform::default();
+        }
+        self.offloaded_samples.store(0, Ordering::SeqCst);
+        self.input_samples.store(0, Ordering::SeqCst);
+        self.clipped_samples.store(0, Ordering::SeqCst);
+        self.paused.store(false, Ordering::SeqCst);
+        self.held.store(false, Ordering::SeqCst);
+        self.signal.store(false, Ordering::SeqCst);
+    }
+
+    /// Set the software gain of each track's input (linear factors)
+    ///
+    /// Kept across recordings, so a pre-roll stream uses it too.
+    pub fn set_gains(&self, gains: [f32; MAX_TRACKS]) {
+        if let Ok(mut current) = self.gains.lock() {
+            *current = gains;
+        }
+    }
+
+    /// Set the channel recorded from each track's input; kept across recordings like the gains
+    pub fn set_channels(&self, channels: [ChannelSelect; MAX_TRACKS]) {
+        if let Ok(mut current) = self.channel_select.lock() {
+            *current = channels;
+        }
+    }
+
+    /// Channel recorded from a track's input
+    pub(crate) fn channel_select(&self, track: usize) -> ChannelSelect {
+        self.channel_select
+            .lock()
+            .map(|channels| channels[track])
+            .unwrap_or_default()
+    }
+
+    /// `data` with the track's gain applied
+    pub(crate) fn amplify<'a>(&self, track: usize, data: &'a [f32]) -> std::borrow::Cow<'a, [f32]> {
+        let gain = self.gains.lock().map(|gains| gains[track]).unwrap_or(1.0);
+        if gain == 1.0 {
+            std::borrow::Cow::Borrowed(data)
+        } else {
+            std::borrow::Cow::Owned(apply_gain(data, gain))
+        }
+    }
+
+    /// Fraction (0-1) of the primary track at full scale, None before any audio
+    pub fn clipped_fraction(&self) -> Option<f32> {
+        let total = self.input_samples.load(Ordering::SeqCst);
+        (total > 0).then(|| self.clipped_samples.load(Ordering::SeqCst) as f32 / total as f32)
+    }
+
+    /// Note that a track's stream reported its device gone
+    pub(crate) fn mark_input_failed(&self, track: usize) {
+        if let Ok(mut watch) = self.input_watch.lock() {
+            watch.failed[track] = true;
+        }
+    }
+
+    /// Whether a track's device is gone: its stream said so, or it went quiet
+    ///
+    /// Streams deliver buffers even from a silent or paused microphone, so
+    /// none arriving for a while means the device stopped.
+    pub(crate) fn input_stalled(&self, track: usize) -> bool {
+        let started_at = self.started_at.lock().ok().and_then(|s| *s);
+        let Ok(watch) = self.input_watch.lock() else {
+            return false;
+        };
+        watch.failed[track]
+            || watch.last_input[track]
+                .or(started_at)
+                .is_some_and(|at| at.elapsed() > STREAM_STALL_TIMEOUT)
+    }
+
+    /// Watch a track afresh after its stream was replaced
+    fn reset_input_watch(&self, track: usize) {
+        if let Ok(mut watch) = self.input_watch.lock() {
+            watch.failed[track] = false;
+            watch.last_input[track] = Some(Instant::now());
+        }
+    }
+
+    pub(crate) fn push_device_change(&self, change: DeviceChange) {
+        if let Ok(mut changes) = self.device_changes.lock() {
+            changes.push(change);
+        }
+    }
+
+    /// Device changes since the last call
+    pub fn take_device_changes(&self) -> Vec<DeviceChange> {
+        self.device_changes
+            .lock()
+            .map(|mut changes| std::mem::take(&mut *changes))
+            .unwrap_or_default()
+    }
+
+    /// Current position in the recording, in milliseconds
+    pub fn elapsed_ms(&self) -> u64 {
+        let len = self.tracks.lock().map(|t| t[0].len()).unwrap_or(0);
+        calculate_duration_ms(len + self.offloaded_samples.load(Ordering::SeqCst))
+    }
+
+    /// Move buffered audio to the disk spool once it exceeds the chunk size
+    ///
+    /// Called from the recording thread rather than the audio callback so
+    /// disk writes never block the stream.
+    pub(crate) fn spool_if_needed(&self) -> Result<()> {
+        if !self.spool_enabled.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        self.offload_primary(|buffer| {
+            if buffer.len() >= SPOOL_CHUNK_SAMPLES {
+                Some(buffer.len())
+            } else {
+                None
+            }
+        })
+        .map(|_| ())
+    }
+
+    /// Take the start of the primary track as a segment, keeping it in the spool
+    ///
+    /// `find_cut` picks how many buffered samples form the segment (None to
+    /// wait for more audio). Returns None once the recording has stopped.
+    pub fn take_segment(&self, find_cut: impl FnOnce(&[f32]) -> Option<usize>) -> Result<Option<Vec<f32>>> {
+        self.offload_primary(find_cut)
+    }
+
+    /// Disable spooling of the primary track (segmenting offloads it instead)
+    pub fn set_spool_enabled(&self, enabled: bool) {
+        self.spool_enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Spool float samples, for recordings kept at full precision
+    pub fn set_spool_lossless(&self, lossless: bool) {
+        self.spool_lossless.store(lossless, Ordering::SeqCst);
+    }
+
+    /// Drain the start of the primary track into the disk spool
+    ///
+    /// The spool lock is held across the drain and write so audio always
+    /// reaches the file in order, and `take_recording` cannot interleave.
+    fn offload_primary(&self, find_cut: impl FnOnce(&[f32]) -> Option<usize>) -> Result<Option<Vec<f32>>> {
+        let mut spool = self
+            .spool
+            .lock()
+            .map_err(|_| AppError::Audio("Spool lock poisoned".to_string()))?;
+        if !self.is_recording() {
+            return Ok(None);
+        }
+
+        let chunk: Vec<f32> = {
+            let mut tracks = self
+                .tracks
+                .lock()
+                .map_err(|_| AppError::Audio("Track lock poisoned".to_string()))?;
+            match find_cut(&tracks[0]) {
+                Some(cut) => tracks[0].drain(..cut.min(tracks[0].len())).collect(),
+                None => return Ok(None),
+            }
+        };
+
+        if spool.is_none() {
+            *spool = Some(AudioSpool::create(self.spool_lossless.load(Ordering::SeqCst))?);
+        }
+        if let Some(spool) = spool.as_mut() {
+            spool.write(&chunk)?;
+        }
+        self.offloaded_samples.fetch_add(chunk.len(), Ordering::SeqCst);
+        Ok(Some(chunk))
+    }
+
+    /// Take the finished recording, from memory or from the disk spool
+    pub fn take_recording(&self) -> Result<RecordedAudio> {
+        let spool = self.spool.lock().ok().and_then(|mut s| s.take());
+
+        match spool {
+            None => Ok(RecordedAudio::Memory(self.get_samples())),
+            Some(mut spool) => {
+                let tail = self
+                    .tracks
+                    .lock()
+                    .map(|mut t| std::mem::take(&mut t[0]))
+                    .unwrap_or_default();
+                spool.write(&tail)?;
+                let (path, sample_count) = spool.finish()?;
+                Ok(RecordedAudio::Spooled { path, sample_count })
+            }
+        }
+    }
+
+    /// Drop a bookmark at the current position, returning its offset in ms
+    pub fn add_bookmark(&self) -> u64 {
+        let offset_ms = self.elapsed_ms();
+        if let Ok(mut bookmarks) = self.bookmarks.lock() {
+            bookmarks.push(offset_ms);
+        }
+        offset_ms
+    }
+
+    /// Bookmark offsets (ms) recorded so far
+    pub fn get_bookmarks(&self) -> Vec<u64> {
+        self.bookmarks.lock().map(|b| b.clone()).unwrap_or_default()
     }
 
+    /// Get the recorded audio, mixing tracks when more than one was captured
     pub fn get_samples(&self) -> Vec<f32> {
-        self.samples.lock().map(|s| s.clone()).unwrap_or_default()
+        let (primary, secondary) = self.get_tracks();
+        if secondary.is_empty() {
+            primary
+        } else {
+            mix_tracks(&primary, &secondary)
+        }
+    }
+
+    /// Get the primary and secondary tracks, time-aligned to the same length
+    ///
+    /// The secondary track is empty for single-source recordings.
+    pub fn get_tracks(&self) -> (Vec<f32>, Vec<f32>) {
+        let tracks = self.tracks.lock().map(|t| t.clone()).unwrap_or_default();
+        let offsets = self
+            .track_offsets
+            .lock()
+            .map(|o| *o)
+            .unwrap_or([None; MAX_TRACKS]);
+        let [primary, secondary] = tracks;
+
+        if secondary.is_empty() {
+            return (primary, secondary);
+        }
+
+        align_tracks(
+            primary,
+            offsets[0].unwrap_or(0),
+            secondary,
+            offsets[1].unwrap_or(0),
+        )
+    }
+
+    /// Put audio captured before the recording started ahead of the primary track
+    pub(crate) fn prepend_primary(&self, samples: Vec<f32>) {
+        if !self.has_signal() && samples.iter().any(|&sample| sample != 0.0) {
+            self.signal.store(true, Ordering::SeqCst);
+        }
+        if let Ok(mut tracks) = self.tracks.lock() {
+            let recorded = std::mem::replace(&mut tracks[0], samples);
+            tracks[0].extend(recorded);
+        }
     }
 
     pub fn append_samples(&self, new_samples: Vec<f32>) {
-        if let Ok(mut samples) = self.samples.lock() {
-            samples.extend(new_samples);
+        if let Ok(mut tracks) = self.tracks.lock() {
+            tracks[0].extend(new_samples);
+        }
+    }
+
+    /// Handle a buffer delivered by an input stream callback
+    pub(crate) fn push_input(&self, track: usize, data: &[f32], source_rate: u32, channels: usize) {
+        if !self.is_recording() {
+            return;
+        }
+        if let Ok(mut watch) = self.input_watch.lock() {
+            watch.last_input[track] = Some(Instant::now());
+        }
+        if self.is_paused() {
+            return;
+        }
+        let data = self.amplify(track, data);
+        if track == 0 {
+            if !self.has_signal() && data.iter().any(|&sample| sample != 0.0) {
+                self.signal.store(true, Ordering::SeqCst);
+            }
+            let clipped = data.iter().filter(|sample| sample.abs() >= FULL_SCALE).count();
+            self.input_samples.fetch_add(data.len(), Ordering::SeqCst);
+            self.clipped_samples.fetch_add(clipped, Ordering::SeqCst);
+        }
+
+        let processed = process_audio_data(&data, source_rate, channels, self.channel_select(track));
+        self.update_level(&processed);
+        if track == 0 {
+            if let Ok(mut waveform) = self.waveform.lock() {
+                waveform.push(&processed);
+            }
+        }
+
+        if let Ok(mut offsets) = self.track_offsets.lock() {
+            if offsets[track].is_none() {
+                let elapsed = self
+                    .started_at
+                    .lock()
+                    .ok()
+                    .and_then(|s| *s)
+                    .map(|s| s.elapsed())
+                    .unwrap_or_default();
+                // The first callback delivers audio captured just before it fired
+                let captured = processed.len() as f64 / WHISPER_SAMPLE_RATE as f64;
+                let offset = (elapsed.as_secs_f64() - captured).max(0.0);
+                offsets[track] = Some((offset * WHISPER_SAMPLE_RATE as f64) as usize);
+            }
+        }
+
+        // Checked under the lock `Done` is set with, so audio is either in
+        // the recording `stop_recording` returns or not added at all
+        let capture = self.capture_slot();
+        if capture.state == CaptureState::Done {
+            return;
+        }
+        if let Ok(mut tracks) = self.tracks.lock() {
+            tracks[track].extend(processed);
         }
     }
 
@@ -130,6 +1200,14 @@ impl RecordingHandle {
         }
     }
 
+    /// Take the waveform peaks gathered since the last call
+    pub fn take_waveform(&self) -> Vec<f32> {
+        self.waveform
+            .lock()
+            .map(|mut waveform| std::mem::take(&mut waveform.peaks))
+            .unwrap_or_default()
+    }
+
     /// Get current audio level
     pub fn get_level(&self) -> (f32, f32) {
         let level = self.current_level.lock().map(|l| *l).unwrap_or(0.0);
@@ -144,183 +1222,525 @@ impl Default for RecordingHandle {
     }
 }
 
+/// A point of interest marked during a recording
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Bookmark {
+    /// Offset from the start of the recording
+    pub offset_ms: u64,
+    /// Short label dictated right after the bookmark was dropped
+    pub label: Option<String>,
+}
+
 /// Callback type for audio level updates
 pub type LevelCallback = Box<dyn Fn(f32) + Send + 'static>;
 
+/// How often the capture thread spools audio and checks for the end of a recording
+pub(crate) const TICK_INTERVAL: Duration = Duration::from_millis(30);
+
+/// Periodic work of the capture thread while recording
+pub(crate) struct RecordingTicker {
+    handle: RecordingHandle,
+    generation: u64,
+    level_callback: Option<LevelCallback>,
+    last_level_update: Instant,
+}
+
+impl RecordingTicker {
+    pub(crate) fn new(handle: RecordingHandle, level_callback: Option<LevelCallback>) -> Self {
+        Self {
+            generation: handle.generation(),
+            handle,
+            level_callback,
+            last_level_update: Instant::now(),
+        }
+    }
+
+    /// Spool audio and emit the level; returns false once recording stopped
+    /// and the streams delivered what they held, so they can be closed
+    pub(crate) fn tick(&mut self) -> bool {
+        if !self.handle.is_recording()
+            || self.handle.generation() != self.generation
+            || self.handle.capture_flushed()
+        {
+            return false;
+        }
+
+        if let Err(e) = self.handle.spool_if_needed() {
+            log::warn!("Failed to spool audio to disk: {}", e);
+        }
+
+        // Emit level callback every ~100ms
+        if self.last_level_update.elapsed() >= Duration::from_millis(100) {
+            if let Some(ref cb) = self.level_callback {
+                let (level, _peak) = self.handle.get_level();
+                cb(level);
+            }
+            self.last_level_update = Instant::now();
+        }
+        true
+    }
+}
+
+/// Reset the handle for a recording of `tracks` tracks and mark it started
+pub(crate) fn begin_recording(handle: &RecordingHandle, tracks: usize) {
+    handle.clear_samples();
+    handle.spool_enabled.store(tracks == 1, Ordering::SeqCst);
+    handle.set_recording(true);
+}
+
 /// Start recording in a separate thread (returns immediately)
-/// The stream is managed in the spawned thread
+/// The streams are managed in the spawned thread
 /// Optional level_callback is called with audio level (0.0-1.0) periodically
 pub fn start_recording(
     handle: RecordingHandle,
-    device_name: &str,
+    inputs: Vec<CaptureInput>,
     level_callback: Option<LevelCallback>,
 ) -> Result<()> {
     if handle.is_recording() {
         return Err(AppError::RecordingInProgress);
     }
 
-    let device = get_device_by_name(device_name)?;
-    let config = device.default_input_config()?;
+    let mut devices = Vec::with_capacity(inputs.len());
+    #[cfg(feature = "pipewire")]
+    let mut targets = Vec::new();
+    for input in inputs {
+        match input {
+            CaptureInput::Cpal { device, monitor } => devices.push((device, monitor)),
+            #[cfg(feature = "pipewire")]
+            CaptureInput::PipeWire(target) => targets.push(target),
+        }
+    }
 
-    log::info!(
-        "Starting recording on device: {} (format: {:?}, rate: {}, channels: {})",
-        device.name().unwrap_or_default(),
-        config.sample_format(),
-        config.sample_rate().0,
-        config.channels()
-    );
+    #[cfg(feature = "pipewire")]
+    if !targets.is_empty() {
+        begin_recording(&handle, targets.len());
+        crate::pipewire_backend::start_capture(handle, targets, level_callback);
+        return Ok(());
+    }
 
-    handle.clear_samples();
-    handle.set_recording(true);
+    let mut configs = Vec::with_capacity(devices.len());
+    for (track, (device, monitor)) in devices.iter().enumerate() {
+        let config = preferred_input_config(device, handle.channel_select(track))?;
+        log::info!(
+            "Starting recording on device: {}{} (format: {:?}, rate: {}, channels: {})",
+            device.name().unwrap_or_default(),
+            monitor
+                .as_ref()
+                .map(|m| format!(" [{}]", m))
+                .unwrap_or_default(),
+            config.sample_format(),
+            config.sample_rate().0,
+            config.channels()
+        );
+        configs.push(config);
+    }
+
+    begin_recording(&handle, devices.len());
 
-    let source_sample_rate = config.sample_rate().0;
-    let channels = config.channels() as usize;
-    let sample_format = config.sample_format();
     let handle_clone = handle.clone();
 
-    // Spawn a thread to manage the stream (Stream is not Send)
+    // Spawn a thread to manage the streams (Stream is not Send)
     std::thread::spawn(move || {
-        let err_fn = |err| {
-            log::error!("Audio stream error: {}", err);
-        };
-
-        let stream_config: StreamConfig = config.into();
-
-        let samples_ref = handle_clone.samples.clone();
-        let is_recording_ref = handle_clone.is_recording.clone();
-        let level_handle = handle_clone.clone();
-        let level_handle2 = handle_clone.clone();
-        let level_handle3 = handle_clone.clone();
-
-        let stream_result = match sample_format {
-            SampleFormat::F32 => device.build_input_stream(
-                &stream_config,
-                move |data: &[f32], _: &_| {
-                    if is_recording_ref.load(Ordering::SeqCst) {
-                        let processed = process_audio_data(data, source_sample_rate, channels);
-                        level_handle.update_level(&processed);
-                        if let Ok(mut samples) = samples_ref.lock() {
-                            samples.extend(processed);
-                        }
-                    }
-                },
-                err_fn,
-                None,
-            ),
-            SampleFormat::I16 => {
-                let samples_ref = handle_clone.samples.clone();
-                let is_recording_ref = handle_clone.is_recording.clone();
-                device.build_input_stream(
-                    &stream_config,
-                    move |data: &[i16], _: &_| {
-                        if is_recording_ref.load(Ordering::SeqCst) {
-                            let float_data: Vec<f32> =
-                                data.iter().map(|&s| s as f32 / i16::MAX as f32).collect();
-                            let processed = process_audio_data(&float_data, source_sample_rate, channels);
-                            level_handle2.update_level(&processed);
-                            if let Ok(mut samples) = samples_ref.lock() {
-                                samples.extend(processed);
-                            }
-                        }
-                    },
-                    err_fn,
-                    None,
-                )
-            }
-            SampleFormat::U16 => {
-                let samples_ref = handle_clone.samples.clone();
-                let is_recording_ref = handle_clone.is_recording.clone();
-                device.build_input_stream(
-                    &stream_config,
-                    move |data: &[u16], _: &_| {
-                        if is_recording_ref.load(Ordering::SeqCst) {
-                            let float_data: Vec<f32> = data
-                                .iter()
-                                .map(|&s| (s as f32 / u16::MAX as f32) * 2.0 - 1.0)
-                                .collect();
-                            let processed = process_audio_data(&float_data, source_sample_rate, channels);
-                            level_handle3.update_level(&processed);
-                            if let Ok(mut samples) = samples_ref.lock() {
-                                samples.extend(processed);
-                            }
-                        }
-                    },
-                    err_fn,
-                    None,
-                )
-            }
-            _ => {
-                log::error!("Unsupported sample format: {:?}", sample_format);
-                return;
-            }
-        };
+        let _done = CaptureDone::new(&handle_clone);
+        let names: Vec<String> = devices
+            .iter()
+            .map(|(device, monitor)| {
+                monitor
+                    .clone()
+                    .unwrap_or_else(|| device.name().unwrap_or_default())
+            })
+            .collect();
+        let mut streams = Vec::with_capacity(devices.len());
+
+        for (track, ((device, monitor), config)) in devices.into_iter().zip(configs).enumerate() {
+            let stream_result = open_on_monitor(monitor.as_deref(), || {
+                open_capture_stream(&device, config, &handle_clone, track)
+            });
 
-        match stream_result {
-            Ok(stream) => {
-                if let Err(e) = stream.play() {
-                    log::error!("Failed to play stream: {}", e);
-                    handle_clone.set_recording(false);
+            match stream_result {
+                Ok(stream) => streams.push(Some(stream)),
+                Err(e) => {
+                    log::error!("Failed to start stream: {}", e);
+                    // Still recording, so the app stops it and resets as for any recording
+                    handle_clone.push_device_change(DeviceChange::Lost {
+                        device: names[track].clone(),
+                    });
                     return;
                 }
+            }
+        }
 
-                // Keep the thread alive while recording
-                // Also emit level updates via callback
-                let mut last_level_update = std::time::Instant::now();
-                while handle_clone.is_recording() {
-                    std::thread::sleep(std::time::Duration::from_millis(30));
-
-                    // Emit level callback every ~100ms
-                    if last_level_update.elapsed() >= std::time::Duration::from_millis(100) {
-                        if let Some(ref cb) = level_callback {
-                            let (level, _peak) = handle_clone.get_level();
-                            cb(level);
-                        }
-                        last_level_update = std::time::Instant::now();
-                    }
-                }
+        // Keep the thread alive while recording
+        // Also emit level updates via callback
+        let mut ticker = RecordingTicker::new(handle_clone.clone(), level_callback);
+        let mut fell_back = false;
+        loop {
+            std::thread::sleep(TICK_INTERVAL);
+            if !ticker.tick() {
+                break;
+            }
 
-                // Stream will be dropped here, stopping the recording
-                log::info!("Recording thread finished");
+            let stalled = (0..streams.len())
+                .find(|&track| streams[track].is_some() && handle_clone.input_stalled(track));
+            let Some(track) = stalled else {
+                continue;
+            };
+            // Close the dead stream first; some backends hold the device until then
+            streams[track] = None;
+            if track > 0 {
+                log::warn!(
+                    "System audio from {} stopped; recording continues from the microphone",
+                    names[track]
+                );
+                handle_clone.push_device_change(DeviceChange::Dropped {
+                    device: names[track].clone(),
+                });
+                continue;
             }
-            Err(e) => {
-                log::error!("Failed to build stream: {}", e);
-                handle_clone.set_recording(false);
+            match recover_primary(&handle_clone, &names[0], fell_back) {
+                Some(stream) => {
+                    streams[0] = Some(stream);
+                    fell_back = true;
+                }
+                None => break,
             }
         }
+
+        // Closing the streams waits for their callbacks, so nothing arrives after this
+        drop(streams);
+        log::info!("Recording thread finished");
     });
 
     Ok(())
 }
 
-/// Stop recording and return samples
-pub fn stop_recording(handle: &RecordingHandle) -> Result<Vec<f32>> {
+/// Open the default input device for the primary track after `lost` went away
+///
+/// Reports the change; None (reported as lost) when the default device
+/// already took over once or cannot be opened either.
+pub(crate) fn recover_primary(
+    handle: &RecordingHandle,
+    lost: &str,
+    fell_back: bool,
+) -> Option<cpal::Stream> {
+    let fallback = if fell_back {
+        Err(AppError::Audio("the default device stopped too".to_string()))
+    } else {
+        get_device_by_name("").and_then(|device| {
+            let config = preferred_input_config(&device, handle.channel_select(0))?;
+            let stream = open_capture_stream(&device, config, handle, 0)?;
+            Ok((stream, device.name().unwrap_or_default()))
+        })
+    };
+
+    match fallback {
+        Ok((stream, now)) => {
+            log::warn!("Input device {} stopped; recording continues on {}", lost, now);
+            handle.reset_input_watch(0);
+            handle.push_device_change(DeviceChange::FellBack {
+                lost: lost.to_string(),
+                now,
+            });
+            Some(stream)
+        }
+        Err(e) => {
+            log::error!("Input device {} stopped and no other took over: {}", lost, e);
+            handle.push_device_change(DeviceChange::Lost {
+                device: lost.to_string(),
+            });
+            None
+        }
+    }
+}
+
+/// Build a capture stream for one track and start it
+fn open_capture_stream(
+    device: &Device,
+    config: cpal::SupportedStreamConfig,
+    handle: &RecordingHandle,
+    track: usize,
+) -> Result<cpal::Stream> {
+    let stream = build_capture_stream(device, config, handle, track)?;
+    stream.play()?;
+    Ok(stream)
+}
+
+/// Build an input stream that feeds one track of the recording handle
+fn build_capture_stream(
+    device: &Device,
+    config: cpal::SupportedStreamConfig,
+    handle: &RecordingHandle,
+    track: usize,
+) -> Result<cpal::Stream> {
+    let (handle, failed) = (handle.clone(), handle.clone());
+    build_input_stream(
+        device,
+        config,
+        move |data, source_rate, channels| handle.push_input(track, data, source_rate, channels),
+        move |err| {
+            log::error!("Audio stream error: {}", err);
+            if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                failed.mark_input_failed(track);
+            }
+        },
+    )
+}
+
+/// Sample formats `build_input_stream` converts, most preferred first
+const SAMPLE_FORMATS: [SampleFormat; 6] = [
+    SampleFormat::F32,
+    SampleFormat::I32,
+    SampleFormat::I16,
+    SampleFormat::F64,
+    SampleFormat::U16,
+    SampleFormat::I8,
+];
+
+/// How well a supported config range suits whisper, lower is better
+///
+/// 16kHz needs no resampling and exactly `channels` channels (1 unless a
+/// channel is selected) no downmix; after that the more precise sample
+/// formats win. None for formats that cannot be read, and for ranges with
+/// fewer than `channels` channels.
+fn config_rank(range: &cpal::SupportedStreamConfigRange, channels: u16) -> Option<(bool, bool, usize)> {
+    if range.channels() < channels {
+        return None;
+    }
+    let format = SAMPLE_FORMATS
+        .iter()
+        .position(|&format| format == range.sample_format())?;
+    let target = cpal::SampleRate(WHISPER_SAMPLE_RATE);
+    let has_target_rate = range.min_sample_rate() <= target && target <= range.max_sample_rate();
+    Some((!has_target_rate, range.channels() != channels, format))
+}
+
+/// Config to open an input device with, keeping the `channel` selected
+///
+/// A 16kHz config with no more channels than needed is taken when the
+/// device offers one, so the audio needs no resampling. Otherwise the
+/// device's default config is used, as long as its sample format can be
+/// read and it has the selected channel. Audio servers downmix to mono when
+/// asked, so a selected channel must be asked for explicitly.
+pub(crate) fn preferred_input_config(device: &Device, channel: ChannelSelect) -> Result<cpal::SupportedStreamConfig> {
+    let needed = channel.channels_needed();
+    let best = device.supported_input_configs().ok().and_then(|ranges| {
+        ranges
+            .filter_map(|r| config_rank(&r, needed).map(|rank| (rank, r)))
+            .min_by_key(|(rank, _)| *rank)
+    });
+    match best {
+        Some(((false, _, _), range)) => {
+            Ok(range.with_sample_rate(cpal::SampleRate(WHISPER_SAMPLE_RATE)))
+        }
+        best => {
+            let default = device.default_input_config()?;
+            let readable = SAMPLE_FORMATS.contains(&default.sample_format());
+            if readable && default.channels() >= needed {
+                return Ok(default);
+            }
+            // The default config cannot be read or lacks the channel; take the best one at its highest rate
+            best.map(|(_, range)| range.with_max_sample_rate())
+                .ok_or_else(|| {
+                    if readable {
+                        AppError::Audio(format!(
+                            "The input device has fewer than the {} channels needed to record {:?}",
+                            needed, channel
+                        ))
+                    } else {
+                        AppError::Audio(format!(
+                            "Unsupported sample format: {:?}",
+                            default.sample_format()
+                        ))
+                    }
+                })
+        }
+    }
+}
+
+/// Build an input stream passing `on_data` normalized f32 samples with the
+/// stream's sample rate and channel count, whatever the device's format
+pub(crate) fn build_input_stream(
+    device: &Device,
+    config: cpal::SupportedStreamConfig,
+    on_data: impl Fn(&[f32], u32, usize) + Send + 'static,
+    err_fn: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream> {
+    let source_sample_rate = config.sample_rate().0;
+    let channels = config.channels() as usize;
+    let sample_format = config.sample_format();
+    let stream_config: StreamConfig = config.into();
+
+    let stream = match sample_format {
+        SampleFormat::F32 => device.build_input_stream(
+            &stream_config,
+            move |data: &[f32], _: &_| {
+                on_data(data, source_sample_rate, channels);
+            },
+            err_fn,
+            None,
+        )?,
+        SampleFormat::F64 => device.build_input_stream(
+            &stream_config,
+            move |data: &[f64], _: &_| {
+                let float_data: Vec<f32> = data.iter().map(|&s| s as f32).collect();
+                on_data(&float_data, source_sample_rate, channels);
+            },
+            err_fn,
+            None,
+        )?,
+        SampleFormat::I32 => device.build_input_stream(
+            &stream_config,
+            move |data: &[i32], _: &_| {
+                let float_data: Vec<f32> = data
+                    .iter()
+                    .map(|&s| (s as f64 / i32::MAX as f64) as f32)
+                    .collect();
+                on_data(&float_data, source_sample_rate, channels);
+            },
+            err_fn,
+            None,
+        )?,
+        SampleFormat::I16 => device.build_input_stream(
+            &stream_config,
+            move |data: &[i16], _: &_| {
+                let float_data: Vec<f32> =
+                    data.iter().map(|&s| s as f32 / i16::MAX as f32).collect();
+                on_data(&float_data, source_sample_rate, channels);
+            },
+            err_fn,
+            None,
+        )?,
+        SampleFormat::I8 => device.build_input_stream(
+            &stream_config,
+            move |data: &[i8], _: &_| {
+                let float_data: Vec<f32> =
+                    data.iter().map(|&s| s as f32 / i8::MAX as f32).collect();
+                on_data(&float_data, source_sample_rate, channels);
+            },
+            err_fn,
+            None,
+        )?,
+        SampleFormat::U16 => device.build_input_stream(
+            &stream_config,
+            move |data: &[u16], _: &_| {
+                let float_data: Vec<f32> = data
+                    .iter()
+                    .map(|&s| (s as f32 / u16::MAX as f32) * 2.0 - 1.0)
+                    .collect();
+                on_data(&float_data, source_sample_rate, channels);
+            },
+            err_fn,
+            None,
+        )?,
+        _ => {
+            return Err(AppError::Audio(format!(
+                "Unsupported sample format: {:?}",
+                sample_format
+            )));
+        }
+    };
+
+    Ok(stream)
+}
+
+/// Stop recording and return the captured audio
+pub fn stop_recording(handle: &RecordingHandle) -> Result<RecordedAudio> {
     if !handle.is_recording() {
         return Err(AppError::NoRecordingInProgress);
     }
 
+    handle.request_stop();
+    if !handle.wait_capture_done(STOP_TIMEOUT) {
+        log::warn!("Capture streams did not close in time; audio still arriving is dropped");
+    }
     handle.set_recording(false);
+    handle.finish_capture(handle.generation());
 
-    // Give the recording thread time to finish
-    std::thread::sleep(std::time::Duration::from_millis(100));
+    let audio = handle.take_recording()?;
+    log::info!("Recording stopped. {} samples captured", audio.sample_count());
 
-    let samples = handle.get_samples();
-    log::info!("Recording stopped. {} samples captured", samples.len());
-
-    Ok(samples)
+    Ok(audio)
 }
 
 /// Process incoming audio data: convert to mono and resample to 16kHz
-fn process_audio_data(data: &[f32], source_rate: u32, channels: usize) -> Vec<f32> {
-    // Convert to mono by averaging channels
-    let mono: Vec<f32> = data
-        .chunks(channels)
-        .map(|chunk| chunk.iter().sum::<f32>() / channels as f32)
-        .collect();
+pub(crate) fn process_audio_data(
+    data: &[f32],
+    source_rate: u32,
+    channels: usize,
+    select: ChannelSelect,
+) -> Vec<f32> {
+    // Convert to mono by taking the selected channel or averaging them all
+    let mono: Vec<f32> = match select.index(channels) {
+        Some(index) => data
+            .chunks(channels)
+            .map(|chunk| chunk.get(index).copied().unwrap_or_default())
+            .collect(),
+        None => data
+            .chunks(channels)
+            .map(|chunk| chunk.iter().sum::<f32>() / channels as f32)
+            .collect(),
+    };
 
     // Simple linear resampling to 16kHz
     resample(&mono, source_rate, WHISPER_SAMPLE_RATE)
 }
 
+/// Pad two tracks so they share a timeline and have equal length
+///
+/// Offsets are in samples from the start of the recording; the earliest
+/// track is placed at zero.
+fn align_tracks(
+    primary: Vec<f32>,
+    primary_offset: usize,
+    secondary: Vec<f32>,
+    secondary_offset: usize,
+) -> (Vec<f32>, Vec<f32>) {
+    let base = primary_offset.min(secondary_offset);
+    let primary_pad = primary_offset - base;
+    let secondary_pad = secondary_offset - base;
+    let len = (primary_pad + primary.len()).max(secondary_pad + secondary.len());
+
+    let pad = |track: Vec<f32>, lead: usize| {
+        let mut out = vec![0.0; lead];
+        out.extend(track);
+        out.resize(len, 0.0);
+        out
+    };
+
+    (pad(primary, primary_pad), pad(secondary, secondary_pad))
+}
+
+/// Mix two aligned tracks into one, clamping to avoid wrap-around
+fn mix_tracks(primary: &[f32], secondary: &[f32]) -> Vec<f32> {
+    primary
+        .iter()
+        .zip(secondary)
+        .map(|(a, b)| (a + b).clamp(-1.0, 1.0))
+        .collect()
+}
+
+/// Save two aligned tracks as a stereo WAV file (primary left, secondary right)
+pub fn save_wav_stereo(primary: &[f32], secondary: &[f32], path: &PathBuf) -> Result<()> {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: WHISPER_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: HoundSampleFormat::Int,
+    };
+
+    let mut writer = WavWriter::create(path, spec)?;
+
+    for (&left, &right) in primary.iter().zip(secondary) {
+        writer.write_sample((left.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+        writer.write_sample((right.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+
+    writer.finalize()?;
+
+    log::info!("Saved two-track WAV file: {:?}", path);
+    Ok(())
+}
+
 /// Simple linear interpolation resampling
 fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
     if from_rate == to_rate {
@@ -370,6 +1790,28 @@ pub fn save_wav(samples: &[f32], path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
+/// Save audio samples to a 32-bit float WAV file, exactly as captured
+///
+/// Used for verbatim dictation, where the recording is the record and
+/// must not lose precision to 16-bit conversion.
+pub fn save_wav_lossless(samples: &[f32], path: &PathBuf) -> Result<()> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: WHISPER_SAMPLE_RATE,
+        bits_per_sample: 32,
+        sample_format: HoundSampleFormat::Float,
+    };
+
+    let mut writer = WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+
+    log::info!("Saved lossless WAV file: {:?}", path);
+    Ok(())
+}
+
 /// Load audio samples from a WAV file (for reprocessing)
 pub fn load_wav(path: &PathBuf) -> Result<Vec<f32>> {
     let mut reader = hound::WavReader::open(path)?;
@@ -387,23 +1829,51 @@ pub fn load_wav(path: &PathBuf) -> Result<Vec<f32>> {
         }
     };
 
-    // Resample if necessary
-    let samples = if spec.sample_rate != WHISPER_SAMPLE_RATE {
-        resample(&samples, spec.sample_rate, WHISPER_SAMPLE_RATE)
-    } else {
-        samples
-    };
+    Ok(to_whisper_format(samples, spec.sample_rate, spec.channels))
+}
 
+/// Mix interleaved samples down to mono and resample them to 16kHz
+pub(crate) fn to_whisper_format(samples: Vec<f32>, sample_rate: u32, channels: u16) -> Vec<f32> {
     // Convert to mono if necessary
-    let samples = if spec.channels > 1 {
+    let samples = if channels > 1 {
         samples
-            .chunks(spec.channels as usize)
-            .map(|chunk| chunk.iter().sum::<f32>() / spec.channels as f32)
+            .chunks(channels as usize)
+            .map(|chunk| chunk.iter().sum::<f32>() / channels as f32)
             .collect()
     } else {
         samples
     };
 
+    // Resample if necessary
+    if sample_rate != WHISPER_SAMPLE_RATE {
+        resample(&samples, sample_rate, WHISPER_SAMPLE_RATE)
+    } else {
+        samples
+    }
+}
+
+/// Read `len` samples starting at `start` from a 16kHz mono WAV file
+pub fn read_wav_range(path: &Path, start: usize, len: usize) -> Result<Vec<f32>> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let total = reader.duration() as usize;
+    let start = start.min(total);
+    reader.seek(start as u32)?;
+
+    let len = len.min(total - start);
+    let samples = match spec.sample_format {
+        HoundSampleFormat::Float => reader.samples::<f32>().take(len).filter_map(|s| s.ok()).collect(),
+        HoundSampleFormat::Int => {
+            let max_val = (1 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .take(len)
+                .filter_map(|s| s.ok())
+                .map(|s| s as f32 / max_val)
+                .collect()
+        }
+    };
+
     Ok(samples)
 }
 
@@ -412,6 +1882,81 @@ pub fn calculate_duration_ms(sample_count: usize) -> u64 {
     (sample_count as u64 * 1000) / WHISPER_SAMPLE_RATE as u64
 }
 
+/// Length of each microphone calibration clip
+pub const CALIBRATION_SECS: u64 = 3;
+
+/// Frame length the calibration measures levels over, matching the VAD's default
+const CALIBRATION_FRAME_MS: u32 = 30;
+
+/// Speech must be at least this many times louder than silence to calibrate from
+const MIN_CALIBRATION_RATIO: f32 = 2.0;
+
+/// Noise floor and speech level measured by the calibration wizard
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Calibration {
+    /// RMS of the loud end of the silent clip (90th percentile frame)
+    pub noise_floor: f32,
+    /// RMS of typical speech (75th percentile frame of the speech clip)
+    pub speech_level: f32,
+    /// Speech level over the noise floor, in dB
+    pub snr_db: f32,
+    /// VAD threshold between the two, for `vad_threshold` in settings
+    pub suggested_threshold: f32,
+}
+
+/// Record a calibration clip from a microphone and return its frame RMS levels
+///
+/// Blocks for `CALIBRATION_SECS`, on a handle of its own so a pre-roll
+/// stream or the level meter are not disturbed.
+pub fn record_calibration_clip(device: &str, gain: f32, channel: ChannelSelect) -> Result<Vec<f32>> {
+    let handle = RecordingHandle::new();
+    handle.set_gains([gain, 1.0]);
+    handle.set_channels([channel, ChannelSelect::Mix]);
+    start_recording(handle.clone(), vec![CaptureInput::from_name(device)?], None)?;
+    std::thread::sleep(Duration::from_secs(CALIBRATION_SECS));
+    let audio = stop_recording(&handle)?;
+    let samples = audio.read_range(0, audio.sample_count())?;
+    if samples.is_empty() {
+        return Err(AppError::Audio("The microphone delivered no audio".to_string()));
+    }
+    let frame_len = (WHISPER_SAMPLE_RATE * CALIBRATION_FRAME_MS / 1000) as usize;
+    Ok(crate::vad::frame_levels(&samples, frame_len))
+}
+
+/// Value below which `fraction` (0-1) of the levels lie
+fn percentile(levels: &[f32], fraction: f32) -> f32 {
+    let mut sorted = levels.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let index = ((sorted.len() - 1) as f32 * fraction).round() as usize;
+    sorted[index]
+}
+
+/// Derive the noise floor and a VAD threshold from a silent and a spoken clip
+///
+/// The threshold is the geometric mean of the two levels, the midpoint on
+/// the dB scale the ear (and the meter) uses, so it sits as far above the
+/// noise as below the speech.
+pub fn calibrate(silence: &[f32], speech: &[f32]) -> Result<Calibration> {
+    if silence.is_empty() || speech.is_empty() {
+        return Err(AppError::Audio("A calibration clip is empty".to_string()));
+    }
+    // A cough in the silent clip or pauses in the spoken one should not skew the result
+    let noise_floor = percentile(silence, 0.9).max(1e-5);
+    let speech_level = percentile(speech, 0.75);
+    if speech_level < noise_floor * MIN_CALIBRATION_RATIO {
+        return Err(AppError::Audio(
+            "Speech was barely louder than the silence; move closer to the microphone or raise its gain"
+                .to_string(),
+        ));
+    }
+    Ok(Calibration {
+        noise_floor,
+        speech_level,
+        snr_db: 20.0 * (speech_level / noise_floor).log10(),
+        suggested_threshold: (noise_floor * speech_level).sqrt(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -432,6 +1977,267 @@ mod tests {
         assert!(resampled.len() > samples.len() / 4);
     }
 
+    #[test]
+    fn test_parse_monitor_sources() {
+        let output = "49\talsa_input.pci-0000_00_1f.3.analog-stereo\tPipeWire\ts32le 2ch 48000Hz\tSUSPENDED\n\
+                      50\talsa_output.pci-0000_00_1f.3.analog-stereo.monitor\tPipeWire\ts32le 2ch 48000Hz\tIDLE\n";
+        let monitors = parse_monitor_sources(output);
+        assert_eq!(
+            monitors,
+            vec!["alsa_output.pci-0000_00_1f.3.analog-stereo.monitor".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_align_tracks_pads_late_track() {
+        let (a, b) = align_tracks(vec![1.0, 1.0], 0, vec![0.5], 3);
+        assert_eq!(a, vec![1.0, 1.0, 0.0, 0.0]);
+        assert_eq!(b, vec![0.0, 0.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn test_mix_tracks_clamps() {
+        let mixed = mix_tracks(&[0.8, -0.2], &[0.5, 0.1]);
+        assert_eq!(mixed[0], 1.0);
+        assert!((mixed[1] + 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_read_wav_range() {
+        let path = std::env::temp_dir().join(format!("whispertray-test-{}.wav", uuid::Uuid::new_v4()));
+        let samples: Vec<f32> = (0..1000).map(|i| i as f32 / 2000.0).collect();
+        save_wav(&samples, &path).unwrap();
+
+        let range = read_wav_range(&path, 100, 10).unwrap();
+        assert_eq!(range.len(), 10);
+        assert!((range[0] - samples[100]).abs() < 0.001);
+
+        let tail = read_wav_range(&path, 995, 100).unwrap();
+        assert_eq!(tail.len(), 5);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_save_wav_lossless() {
+        let path = std::env::temp_dir().join(format!("whispertray-test-{}.wav", uuid::Uuid::new_v4()));
+        let samples: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.0137).sin() * 0.3).collect();
+        save_wav_lossless(&samples, &path).unwrap();
+
+        assert_eq!(load_wav(&path).unwrap(), samples);
+        assert_eq!(read_wav_range(&path, 100, 10).unwrap(), samples[100..110]);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_digital_silence_has_no_signal() {
+        let handle = RecordingHandle::new();
+        handle.clear_samples();
+        handle.set_recording(true);
+        handle.push_input(0, &[0.0; 480], WHISPER_SAMPLE_RATE, 1);
+        // A second track playing audio does not count
+        handle.push_input(1, &[0.2; 480], WHISPER_SAMPLE_RATE, 1);
+        assert!(!handle.has_signal());
+
+        handle.push_input(0, &[0.0, 0.0001], WHISPER_SAMPLE_RATE, 1);
+        assert!(handle.has_signal());
+        handle.clear_samples();
+        assert!(!handle.has_signal());
+    }
+
+    #[test]
+    fn test_gain_and_clipping() {
+        let handle = RecordingHandle::new();
+        handle.clear_samples();
+        handle.set_recording(true);
+        assert_eq!(handle.clipped_fraction(), None);
+
+        handle.set_gains([db_to_gain(20.0), 1.0]);
+        handle.push_input(0, &[0.05; 300], WHISPER_SAMPLE_RATE, 1);
+        handle.push_input(0, &[0.2; 100], WHISPER_SAMPLE_RATE, 1);
+        assert_eq!(handle.clipped_fraction(), Some(0.25));
+        // The gain is clamped to the supported range
+        assert_eq!(db_to_gain(100.0), db_to_gain(MAX_GAIN_DB));
+
+        handle.clear_samples();
+        assert_eq!(handle.clipped_fraction(), None);
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let silence: Vec<f32> = (0..100).map(|i| if i == 50 { 0.3 } else { 0.002 }).collect();
+        let speech: Vec<f32> = (0..100).map(|i| if i % 4 == 0 { 0.001 } else { 0.08 }).collect();
+        let calibration = calibrate(&silence, &speech).unwrap();
+        // The cough in the silent clip is ignored
+        assert_eq!(calibration.noise_floor, 0.002);
+        assert_eq!(calibration.speech_level, 0.08);
+        assert!((calibration.suggested_threshold - 0.01265).abs() < 1e-4);
+        assert!((calibration.snr_db - 32.04).abs() < 0.01);
+
+        assert!(calibrate(&silence, &silence).is_err());
+        assert!(calibrate(&[], &speech).is_err());
+    }
+
+    #[test]
+    fn test_stop_waits_for_capture() {
+        let handle = RecordingHandle::new();
+        begin_recording(&handle, 1);
+        let owner = handle.clone();
+        let capture = std::thread::spawn(move || {
+            let _done = CaptureDone::new(&owner);
+            let mut ticker = RecordingTicker::new(owner.clone(), None);
+            while ticker.tick() {
+                std::thread::sleep(TICK_INTERVAL);
+                owner.push_input(0, &[0.1; 160], WHISPER_SAMPLE_RATE, 1);
+            }
+        });
+        std::thread::sleep(Duration::from_millis(100));
+
+        let audio = stop_recording(&handle).unwrap();
+        capture.join().unwrap();
+        // The block delivered after the stop request is kept
+        assert!(audio.sample_count() >= 2 * 160);
+
+        // Audio arriving once the capture is done is not added
+        let kept = handle.tracks.lock().unwrap()[0].len();
+        handle.is_recording.store(true, Ordering::SeqCst);
+        handle.push_input(0, &[0.1; 160], WHISPER_SAMPLE_RATE, 1);
+        assert_eq!(handle.tracks.lock().unwrap()[0].len(), kept);
+    }
+
+    #[test]
+    fn test_lossless_spool_keeps_samples() {
+        let handle = RecordingHandle::new();
+        begin_recording(&handle, 1);
+        handle.set_spool_lossless(true);
+        let sample = 1.0 / 3.0_f32;
+        handle.tracks.lock().unwrap()[0] = vec![sample; SPOOL_CHUNK_SAMPLES];
+        handle.spool_if_needed().unwrap();
+        handle.tracks.lock().unwrap()[0] = vec![-sample; 10];
+
+        let audio = handle.take_recording().unwrap();
+        let RecordedAudio::Spooled { ref path, sample_count } = audio else {
+            panic!("recording was not spooled");
+        };
+        assert_eq!(sample_count, SPOOL_CHUNK_SAMPLES + 10);
+        assert_eq!(audio.read_range(0, 1).unwrap(), vec![sample]);
+        assert_eq!(audio.read_range(SPOOL_CHUNK_SAMPLES, 10).unwrap(), vec![-sample; 10]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_late_capture_thread_leaves_next_recording_alone() {
+        let handle = RecordingHandle::new();
+        begin_recording(&handle, 1);
+        let slow_close = CaptureDone::new(&handle);
+        // The capture thread is still closing its streams when the stop times out
+        handle.request_stop();
+        handle.set_recording(false);
+        handle.finish_capture(handle.generation());
+
+        begin_recording(&handle, 1);
+        drop(slow_close);
+        handle.push_input(0, &[0.1; 160], WHISPER_SAMPLE_RATE, 1);
+        assert_eq!(handle.tracks.lock().unwrap()[0].len(), 160);
+        assert!(RecordingTicker::new(handle.clone(), None).tick());
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let handle = RecordingHandle::new();
+        assert!(handle.pause_recording().is_err());
+        begin_recording(&handle, 1);
+        handle.push_input(0, &[0.1; 160], WHISPER_SAMPLE_RATE, 1);
+
+        handle.pause_recording().unwrap();
+        handle.push_input(0, &[0.1; 160], WHISPER_SAMPLE_RATE, 1);
+        // The other app letting go of the microphone does not resume
+        handle.set_paused(false);
+        assert!(handle.is_paused());
+        assert_eq!(handle.elapsed_ms(), 10);
+
+        handle.resume_recording().unwrap();
+        handle.push_input(0, &[0.1; 160], WHISPER_SAMPLE_RATE, 1);
+        assert_eq!(handle.elapsed_ms(), 20);
+    }
+
+    #[test]
+    fn test_audio_host_setting() {
+        for host in AudioHost::ALL {
+            assert_eq!(AudioHost::ALL[host as usize], host);
+        }
+        assert_eq!(serde_json::to_string(&AudioHost::Jack).unwrap(), "\"jack\"");
+        assert!(available_audio_hosts().contains(&AudioHost::Default));
+    }
+
+    #[test]
+    fn test_config_rank_prefers_16k_mono() {
+        let range = |channels, min, max, format| {
+            cpal::SupportedStreamConfigRange::new(
+                channels,
+                cpal::SampleRate(min),
+                cpal::SampleRate(max),
+                cpal::SupportedBufferSize::Unknown,
+                format,
+            )
+        };
+        let stereo_48k = range(2, 48_000, 48_000, SampleFormat::F32);
+        let stereo_any = range(2, 8_000, 96_000, SampleFormat::I16);
+        let mono_any = range(1, 8_000, 96_000, SampleFormat::I32);
+        let ranges = [&stereo_48k, &stereo_any, &mono_any];
+        let best = ranges.iter().min_by_key(|r| config_rank(r, 1)).unwrap();
+        assert_eq!(best.channels(), 1);
+
+        assert!(config_rank(&stereo_any, 1) < config_rank(&stereo_48k, 1));
+        assert_eq!(
+            config_rank(&range(1, 16_000, 16_000, SampleFormat::U32), 1),
+            None
+        );
+
+        // A selected channel rules out configs without it
+        let needed = ChannelSelect::Right.channels_needed();
+        assert_eq!(config_rank(&mono_any, needed), None);
+        let best = ranges.iter().min_by_key(|r| config_rank(r, needed)).unwrap();
+        assert_eq!(best.channels(), 2);
+        assert_eq!(ChannelSelect::Channel(3).channels_needed(), 4);
+    }
+
+    #[test]
+    fn test_channel_select() {
+        // Interleaved stereo: a quiet left channel and a loud right one
+        let stereo = [0.1, 0.5, 0.1, 0.5];
+        let rate = WHISPER_SAMPLE_RATE;
+        assert_eq!(process_audio_data(&stereo, rate, 2, ChannelSelect::Mix), [0.3, 0.3]);
+        assert_eq!(process_audio_data(&stereo, rate, 2, ChannelSelect::Right), [0.5, 0.5]);
+        assert_eq!(process_audio_data(&stereo, rate, 2, ChannelSelect::Channel(0)), [0.1, 0.1]);
+        // A channel the device does not have mixes instead
+        assert_eq!(process_audio_data(&stereo, rate, 2, ChannelSelect::Channel(5)), [0.3, 0.3]);
+        assert_eq!(
+            serde_json::to_value(ChannelSelect::Channel(2)).unwrap(),
+            serde_json::json!({"channel": 2})
+        );
+    }
+
+    #[test]
+    fn test_waveform_peaks() {
+        let handle = RecordingHandle::new();
+        begin_recording(&handle, 2);
+        // One and a half peak windows: a loud first window, a quiet rest
+        let window = Waveform::WINDOW;
+        let mut samples = vec![0.1; window + window / 2];
+        samples[10] = -0.8;
+        handle.push_input(0, &samples, WHISPER_SAMPLE_RATE, 1);
+        assert_eq!(handle.take_waveform(), [0.8]);
+        // The partial window carries over into the next buffer
+        handle.push_input(0, &vec![0.2; window / 2], WHISPER_SAMPLE_RATE, 1);
+        assert_eq!(handle.take_waveform(), [0.2]);
+        assert!(handle.take_waveform().is_empty());
+        // Other tracks do not feed the waveform
+        handle.push_input(1, &vec![0.5; window], WHISPER_SAMPLE_RATE, 1);
+        assert!(handle.take_waveform().is_empty());
+    }
+
     #[test]
     fn test_calculate_duration() {
         // 16000 samples at 16kHz = 1 second = 1000 ms
@@ -0,0 +1,352 @@
+//! Compressed formats for kept recordings
+//!
+//! Recordings are captured and transcribed as 16-bit WAV, which takes about
+//! 115 MB an hour. Once a dictation is done, the recording kept in history
+//! can be compressed to FLAC (lossless, roughly half the size) or Opus
+//! (speech quality at 24 kbit/s, about 11 MB an hour; needs the `opus`
+//! feature and libopus). Everything that reads recordings back goes through
+//! `load_audio`, which decodes by file extension. Phone formats (AAC/M4A,
+//! MP3, AMR) are only ever read, and are decoded with ffmpeg.
+
+use crate::audio::{load_wav, to_whisper_format, WHISPER_SAMPLE_RATE};
+use crate::error::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Format recordings are kept in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    /// 16-bit PCM, as recorded
+    #[default]
+    Wav,
+    /// Lossless compression
+    Flac,
+    /// Lossy speech compression (needs the `opus` feature)
+    Opus,
+}
+
+impl RecordingFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            RecordingFormat::Wav => "wav",
+            RecordingFormat::Flac => "flac",
+            RecordingFormat::Opus => "opus",
+        }
+    }
+}
+
+/// Load a recording in any supported format as 16kHz mono samples
+pub fn load_audio(path: &PathBuf) -> Result<Vec<f32>> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "flac" => decode_flac(path),
+        #[cfg(feature = "opus")]
+        "opus" | "ogg" => opus_codec::decode(path),
+        #[cfg(not(feature = "opus"))]
+        "opus" | "ogg" => Err(AppError::Audio(
+            "This build cannot read Opus files (feature \"opus\")".to_string(),
+        )),
+        "m4a" | "mp3" | "aac" | "amr" => decode_ffmpeg(path),
+        _ => load_wav(path),
+    }
+}
+
+/// Compress a finished 16-bit WAV recording, returning the file now kept
+///
+/// The WAV is replaced on success and kept as is on failure, or when it is
+/// not 16-bit PCM (lossless verbatim recordings are float).
+pub async fn compress(wav: PathBuf, format: RecordingFormat) -> PathBuf {
+    let format = match format {
+        RecordingFormat::Opus if !cfg!(feature = "opus") => {
+            log::warn!("Opus recordings selected but not built in (feature \"opus\"); using FLAC");
+            RecordingFormat::Flac
+        }
+        format => format,
+    };
+    if format == RecordingFormat::Wav {
+        return wav;
+    }
+
+    let target = wav.with_extension(format.extension());
+    let (source, encoded) = (wav.clone(), target.clone());
+    let result = tokio::task::spawn_blocking(move || encode_file(&source, &encoded, format)).await;
+    match result {
+        Ok(Ok(true)) => {
+            let _ = std::fs::remove_file(&wav);
+            log::info!("Compressed recording to {:?}", target);
+            target
+        }
+        Ok(Ok(false)) => wav,
+        Ok(Err(e)) => {
+            log::warn!("Failed to compress recording {:?}: {}", wav, e);
+            let _ = std::fs::remove_file(&target);
+            wav
+        }
+        Err(e) => {
+            log::warn!("Failed to compress recording {:?}: {}", wav, e);
+            wav
+        }
+    }
+}
+
+/// Encode `wav` into `target`; false when the WAV is not 16-bit PCM
+fn encode_file(wav: &Path, target: &Path, format: RecordingFormat) -> Result<bool> {
+    let mut reader = hound::WavReader::open(wav)?;
+    let spec = reader.spec();
+    if spec.sample_format != hound::SampleFormat::Int || spec.bits_per_sample != 16 {
+        return Ok(false);
+    }
+    let samples: Vec<i32> = reader
+        .samples::<i32>()
+        .collect::<std::result::Result<_, _>>()?;
+    match format {
+        RecordingFormat::Flac => encode_flac(&samples, spec.channels, spec.sample_rate, target)?,
+        #[cfg(feature = "opus")]
+        RecordingFormat::Opus => opus_codec::encode(&samples, spec.channels, target)?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Write interleaved 16-bit samples as FLAC
+fn encode_flac(samples: &[i32], channels: u16, sample_rate: u32, path: &Path) -> Result<()> {
+    use flacenc::component::BitRepr;
+
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .map_err(|(_, e)| AppError::Audio(format!("FLAC encoder config: {:?}", e)))?;
+    let source = flacenc::source::MemSource::from_samples(
+        samples,
+        channels as usize,
+        16,
+        sample_rate as usize,
+    );
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| AppError::Audio(format!("FLAC encoding failed: {:?}", e)))?;
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream
+        .write(&mut sink)
+        .map_err(|e| AppError::Audio(format!("FLAC encoding failed: {:?}", e)))?;
+    std::fs::write(path, sink.as_slice())?;
+    Ok(())
+}
+
+fn decode_flac(path: &Path) -> Result<Vec<f32>> {
+    let mut reader = claxon::FlacReader::open(path)
+        .map_err(|e| AppError::Audio(format!("FLAC error: {}", e)))?;
+    let info = reader.streaminfo();
+    let scale = (1i64 << (info.bits_per_sample - 1)) as f32;
+    let samples = reader
+        .samples()
+        .map(|sample| sample.map(|s| s as f32 / scale))
+        .collect::<std::result::Result<Vec<f32>, _>>()
+        .map_err(|e| AppError::Audio(format!("FLAC error: {}", e)))?;
+    Ok(to_whisper_format(
+        samples,
+        info.sample_rate,
+        info.channels as u16,
+    ))
+}
+
+/// Any format ffmpeg reads, resampled by ffmpeg to 16kHz mono
+fn decode_ffmpeg(path: &Path) -> Result<Vec<f32>> {
+    let output = std::process::Command::new("ffmpeg")
+        .args(["-nostdin", "-v", "error", "-i"])
+        .arg(path)
+        .args(["-f", "f32le", "-ac", "1", "-ar"])
+        .arg(WHISPER_SAMPLE_RATE.to_string())
+        .arg("-")
+        .output()
+        .map_err(|e| AppError::Audio(format!("Reading {} needs ffmpeg: {}", path.display(), e)))?;
+    if !output.status.success() {
+        return Err(AppError::Audio(format!(
+            "ffmpeg could not decode {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect())
+}
+
+/// Ogg Opus (RFC 7845), encoded and decoded at 16kHz
+#[cfg(feature = "opus")]
+mod opus_codec {
+    use super::WHISPER_SAMPLE_RATE;
+    use crate::error::{AppError, Result};
+    use ogg::writing::PacketWriteEndInfo;
+    use std::fs::File;
+    use std::io::{BufReader, BufWriter};
+    use std::path::Path;
+
+    /// 20ms frames
+    const FRAME_SAMPLES: usize = WHISPER_SAMPLE_RATE as usize / 50;
+
+    /// Ogg Opus granule positions count 48kHz samples
+    const GRANULE_PER_SAMPLE: u64 = 48_000 / WHISPER_SAMPLE_RATE as u64;
+
+    /// libopus's encoder delay, in 48kHz samples
+    const PRE_SKIP: u16 = 312;
+
+    const BITRATE: i32 = 24_000;
+
+    /// Longest Opus packet (120ms), in samples per channel
+    const MAX_PACKET_SAMPLES: usize = WHISPER_SAMPLE_RATE as usize * 120 / 1000;
+
+    const SERIAL: u32 = 1;
+
+    fn opus_error(e: opus::Error) -> AppError {
+        AppError::Audio(format!("Opus error: {}", e))
+    }
+
+    fn opus_channels(channels: u16) -> Result<opus::Channels> {
+        match channels {
+            1 => Ok(opus::Channels::Mono),
+            2 => Ok(opus::Channels::Stereo),
+            n => Err(AppError::Audio(format!(
+                "Opus recordings have one or two channels, not {}",
+                n
+            ))),
+        }
+    }
+
+    pub fn encode(samples: &[i32], channels: u16, path: &Path) -> Result<()> {
+        let mut encoder = opus::Encoder::new(
+            WHISPER_SAMPLE_RATE,
+            opus_channels(channels)?,
+            opus::Application::Voip,
+        )
+        .map_err(opus_error)?;
+        encoder
+            .set_bitrate(opus::Bitrate::Bits(BITRATE))
+            .map_err(opus_error)?;
+        let mut writer = ogg::writing::PacketWriter::new(BufWriter::new(File::create(path)?));
+
+        let mut head = b"OpusHead".to_vec();
+        head.push(1);
+        head.push(channels as u8);
+        head.extend_from_slice(&PRE_SKIP.to_le_bytes());
+        head.extend_from_slice(&WHISPER_SAMPLE_RATE.to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+        writer.write_packet(head, SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+        let vendor = b"WhisperTray";
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor);
+        tags.extend_from_slice(&0u32.to_le_bytes());
+        writer.write_packet(tags, SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+        let frame_len = FRAME_SAMPLES * channels as usize;
+        let total = (samples.len() / channels as usize) as u64;
+        let frames = samples.len().div_ceil(frame_len).max(1);
+        let mut packet = vec![0u8; 4000];
+        for i in 0..frames {
+            // The last frame is padded with silence, cut again by the final granule position
+            let start = (i * frame_len).min(samples.len());
+            let end = (start + frame_len).min(samples.len());
+            let mut frame: Vec<f32> = samples[start..end]
+                .iter()
+                .map(|&s| s as f32 / 32768.0)
+                .collect();
+            frame.resize(frame_len, 0.0);
+            let len = encoder
+                .encode_float(&frame, &mut packet)
+                .map_err(opus_error)?;
+
+            let last = i + 1 == frames;
+            let decoded = if last {
+                total
+            } else {
+                ((i + 1) * FRAME_SAMPLES) as u64
+            };
+            let granule = PRE_SKIP as u64 + decoded * GRANULE_PER_SAMPLE;
+            let end = if last {
+                PacketWriteEndInfo::EndStream
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+            writer.write_packet(packet[..len].to_vec(), SERIAL, end, granule)?;
+        }
+        Ok(())
+    }
+
+    pub fn decode(path: &Path) -> Result<Vec<f32>> {
+        let mut reader = ogg::reading::PacketReader::new(BufReader::new(File::open(path)?));
+        let read_error = |e: ogg::OggReadError| AppError::Audio(format!("Ogg error: {}", e));
+
+        let head = reader
+            .read_packet()
+            .map_err(read_error)?
+            .filter(|packet| packet.data.starts_with(b"OpusHead") && packet.data.len() >= 19)
+            .ok_or_else(|| AppError::Audio("Not an Ogg Opus file".to_string()))?;
+        let channels = head.data[9] as u16;
+        let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as u64;
+        // OpusTags
+        reader.read_packet().map_err(read_error)?;
+
+        let mut decoder = opus::Decoder::new(WHISPER_SAMPLE_RATE, opus_channels(channels)?)
+            .map_err(opus_error)?;
+        let mut samples = Vec::new();
+        let mut buffer = vec![0.0f32; MAX_PACKET_SAMPLES * channels as usize];
+        let mut final_granule = None;
+        while let Some(packet) = reader.read_packet().map_err(read_error)? {
+            let n = decoder
+                .decode_float(&packet.data, &mut buffer, false)
+                .map_err(opus_error)?;
+            samples.extend_from_slice(&buffer[..n * channels as usize]);
+            if packet.last_in_stream() {
+                final_granule = Some(packet.absgp_page());
+            }
+        }
+
+        // Drop the encoder delay and the padding of the last frame
+        let skip = (pre_skip / GRANULE_PER_SAMPLE) as usize * channels as usize;
+        let mut samples = samples.split_off(skip.min(samples.len()));
+        if let Some(granule) = final_granule {
+            let len = (granule.saturating_sub(pre_skip) / GRANULE_PER_SAMPLE) as usize
+                * channels as usize;
+            samples.truncate(len);
+        }
+        Ok(super::to_whisper_format(
+            samples,
+            WHISPER_SAMPLE_RATE,
+            channels,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_flac_round_trip() {
+        let wav =
+            std::env::temp_dir().join(format!("whispertray-test-{}.wav", uuid::Uuid::new_v4()));
+        let samples: Vec<f32> = (0..16_000).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        crate::audio::save_wav(&samples, &wav).unwrap();
+
+        let flac = compress(wav.clone(), RecordingFormat::Flac).await;
+        assert_eq!(flac.extension().unwrap(), "flac");
+        assert!(!wav.exists());
+
+        // Lossless: the same 16-bit samples as the WAV held
+        let decoded = load_audio(&flac).unwrap();
+        assert_eq!(decoded.len(), samples.len());
+        assert!(decoded
+            .iter()
+            .zip(&samples)
+            .all(|(a, b)| (a - b).abs() < 1e-4));
+
+        let _ = std::fs::remove_file(flac);
+    }
+}
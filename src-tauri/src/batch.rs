@@ -0,0 +1,302 @@
+//! Background queue for reprocessing many history items
+//!
+//! A batch re-runs a mode on, or re-transcribes, a list of history items a
+//! few at a time. Batches and the state of each item are stored in the
+//! history database, so a backfill interrupted by a restart carries on where
+//! it left off. Items run as background work (see `priority`), so dictations
+//! never wait behind a batch. Progress is emitted as "batch-progress".
+
+use crate::cancel::CancelToken;
+use crate::database::{BatchJob, BatchStatus, BatchTask, Database};
+use crate::error::{AppError, Result};
+use crate::state::SharedState;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Wait after startup before resuming batches, once the database is open
+const RESUME_DELAY: Duration = Duration::from_secs(10);
+
+/// Stops a running batch
+#[derive(Default)]
+struct Control {
+    /// Take no further items
+    stop: AtomicBool,
+    /// Abort the items in progress too
+    cancel: CancelToken,
+}
+
+/// Batches running in this process
+#[derive(Clone, Default)]
+pub struct BatchQueue {
+    running: Arc<Mutex<HashMap<String, Arc<Control>>>>,
+}
+
+impl BatchQueue {
+    /// Queue `task` for each of `history_ids` and start it
+    pub async fn start(
+        &self,
+        app: &AppHandle,
+        db: Arc<Database>,
+        task: BatchTask,
+        history_ids: Vec<String>,
+    ) -> Result<BatchJob> {
+        let job = db
+            .run(move |db| db.create_batch(&task, &history_ids))
+            .await?;
+        log::info!("Starting batch {} of {} items", job.id, job.total);
+        self.spawn(app.clone(), db, job.clone());
+        Ok(job)
+    }
+
+    /// Continue a paused batch
+    pub async fn resume(&self, app: &AppHandle, db: Arc<Database>, id: String) -> Result<BatchJob> {
+        if self.control(&id).is_some() {
+            return Err(AppError::Config(
+                "The batch is still finishing the items in progress".to_string(),
+            ));
+        }
+        let job = self.set_status(&db, id, BatchStatus::Running).await?;
+        self.spawn(app.clone(), db, job.clone());
+        Ok(job)
+    }
+
+    /// Stop taking items from a batch; those in progress finish
+    pub async fn pause(&self, db: Arc<Database>, id: String) -> Result<BatchJob> {
+        if let Some(control) = self.control(&id) {
+            control.stop.store(true, Ordering::SeqCst);
+        }
+        self.set_status(&db, id, BatchStatus::Paused).await
+    }
+
+    /// Stop a batch for good, aborting the items in progress
+    pub async fn cancel(&self, db: Arc<Database>, id: String) -> Result<BatchJob> {
+        if let Some(control) = self.control(&id) {
+            control.stop.store(true, Ordering::SeqCst);
+            control.cancel.cancel();
+        }
+        self.set_status(&db, id, BatchStatus::Cancelled).await
+    }
+
+    /// Start the batches that were running when the app last quit
+    pub async fn resume_interrupted(&self, app: &AppHandle, db: Arc<Database>) -> Result<()> {
+        let batches = db.run(|db| db.get_batches()).await?;
+        for job in batches
+            .into_iter()
+            .filter(|job| job.status == BatchStatus::Running)
+        {
+            log::info!(
+                "Resuming batch {} ({} of {} done)",
+                job.id,
+                job.done,
+                job.total
+            );
+            self.spawn(app.clone(), db.clone(), job);
+        }
+        Ok(())
+    }
+
+    fn control(&self, id: &str) -> Option<Arc<Control>> {
+        self.running.lock().unwrap().get(id).cloned()
+    }
+
+    async fn set_status(
+        &self,
+        db: &Arc<Database>,
+        id: String,
+        status: BatchStatus,
+    ) -> Result<BatchJob> {
+        db.run(move |db| {
+            db.set_batch_status(&id, status)?;
+            db.get_batch(&id)?
+                .ok_or_else(|| AppError::Config(format!("Batch {} not found", id)))
+        })
+        .await
+    }
+
+    /// Run a batch's pending items, unless it is running already
+    fn spawn(&self, app: AppHandle, db: Arc<Database>, job: BatchJob) {
+        let control = {
+            let mut running = self.running.lock().unwrap();
+            if running.contains_key(&job.id) {
+                return;
+            }
+            let control = Arc::new(Control::default());
+            running.insert(job.id.clone(), control.clone());
+            control
+        };
+
+        let queue = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let id = job.id.clone();
+            if let Err(e) = run(app, db, job, control).await {
+                log::error!("Batch {} failed: {}", id, e);
+            }
+            queue.running.lock().unwrap().remove(&id);
+        });
+    }
+}
+
+/// Process a batch's pending items with `batch_concurrency` workers
+async fn run(
+    app: AppHandle,
+    db: Arc<Database>,
+    job: BatchJob,
+    control: Arc<Control>,
+) -> Result<()> {
+    let Some(state) = app.try_state::<SharedState>() else {
+        return Ok(());
+    };
+    let state = state.inner().clone();
+    let concurrency = state.lock().await.settings.batch_concurrency.max(1);
+
+    let id = job.id.clone();
+    let pending = db.run(move |db| db.pending_batch_items(&id)).await?;
+    let worker = Worker {
+        app: app.clone(),
+        state: state.clone(),
+        db: db.clone(),
+        job: Arc::new(job.clone()),
+        control: control.clone(),
+        pending: Arc::new(Mutex::new(VecDeque::from(pending))),
+    };
+    // Background scope does not carry over to spawned tasks, so each worker enters it
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| tauri::async_runtime::spawn(crate::priority::background(worker.clone().run())))
+        .collect();
+    for handle in workers {
+        handle
+            .await
+            .map_err(|e| AppError::Config(format!("Batch worker failed: {}", e)))??;
+    }
+
+    let id = job.id.clone();
+    let remaining = db.run(move |db| db.pending_batch_items(&id)).await?;
+    if !control.stop.load(Ordering::SeqCst) {
+        if remaining.is_empty() {
+            let id = job.id.clone();
+            db.run(move |db| db.set_batch_status(&id, BatchStatus::Done))
+                .await?;
+            log::info!("Batch {} finished", job.id);
+        } else if state.lock().await.is_read_only() {
+            // Nothing resumes it when read-only mode is turned off; the user does
+            let id = job.id.clone();
+            db.run(move |db| db.set_batch_status(&id, BatchStatus::Paused))
+                .await?;
+            log::info!("Batch {} paused by read-only mode", job.id);
+        }
+    }
+    emit_progress(&app, &db, &job.id).await;
+    Ok(())
+}
+
+/// Takes a batch's items one at a time until none are left or it is stopped
+#[derive(Clone)]
+struct Worker {
+    app: AppHandle,
+    state: SharedState,
+    db: Arc<Database>,
+    job: Arc<BatchJob>,
+    control: Arc<Control>,
+    pending: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Worker {
+    async fn run(self) -> Result<()> {
+        loop {
+            crate::priority::yield_to_interactive().await;
+            // Items left when read-only mode is turned on stay pending, the batch paused
+            if self.control.stop.load(Ordering::SeqCst) || self.state.lock().await.is_read_only() {
+                return Ok(());
+            }
+            let Some(history_id) = self.pending.lock().unwrap().pop_front() else {
+                return Ok(());
+            };
+
+            let result = process(
+                &self.state,
+                &self.db,
+                &self.job.task,
+                &history_id,
+                &self.control.cancel,
+            )
+            .await;
+            // Cancelled items stay pending
+            if self.control.cancel.is_cancelled() {
+                return Ok(());
+            }
+            if let Err(e) = &result {
+                log::warn!("Batch {}: {} failed: {}", self.job.id, history_id, e);
+            }
+            let (id, error) = (self.job.id.clone(), result.err());
+            self.db
+                .run(move |db| db.finish_batch_item(&id, &history_id, error.as_deref()))
+                .await?;
+            emit_progress(&self.app, &self.db, &self.job.id).await;
+        }
+    }
+}
+
+async fn process(
+    state: &SharedState,
+    db: &Arc<Database>,
+    task: &BatchTask,
+    history_id: &str,
+    cancel: &CancelToken,
+) -> std::result::Result<(), String> {
+    match task {
+        BatchTask::Reprocess { mode_key } => {
+            crate::commands::reprocess_item(
+                state,
+                db,
+                history_id.to_string(),
+                mode_key.clone(),
+                cancel,
+            )
+            .await?
+        }
+        BatchTask::Retranscribe { stt } => {
+            crate::commands::retranscribe_item(state, db, history_id.to_string(), stt, cancel)
+                .await?
+        }
+    };
+    Ok(())
+}
+
+async fn emit_progress(app: &AppHandle, db: &Arc<Database>, id: &str) {
+    let id = id.to_string();
+    if let Ok(Some(job)) = db.run(move |db| db.get_batch(&id)).await {
+        let _ = app.emit("batch-progress", job);
+    }
+}
+
+/// Resume interrupted batches once the app has started
+pub fn spawn_resume(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(RESUME_DELAY).await;
+        let (Some(state), Some(queue)) = (
+            app.try_state::<SharedState>(),
+            app.try_state::<BatchQueue>(),
+        ) else {
+            return;
+        };
+        let (db, read_only) = {
+            let state = state.lock().await;
+            (state.database.clone(), state.is_read_only())
+        };
+        let Some(db) = db else {
+            return;
+        };
+        // Lockdown writes nothing to history; the batches stay interrupted
+        // until the app starts without it
+        if read_only {
+            log::info!("Read-only mode is on, not resuming interrupted batches");
+            return;
+        }
+        if let Err(e) = queue.resume_interrupted(&app, db).await {
+            log::error!("Failed to resume batches: {}", e);
+        }
+    });
+}
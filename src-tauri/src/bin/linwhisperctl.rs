@@ -0,0 +1,61 @@
+//! Command-line control of a running WhisperTray over its control socket
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::process::ExitCode;
+use whispertray_lib::control::{parse_args, socket_path, Response};
+
+const USAGE: &str = "Usage: linwhisperctl <command>
+
+Commands:
+  status          Print the recording status
+  start           Start recording
+  stop            Stop recording, process it and print the output
+  toggle          Start or stop recording, like the hotkey
+  cancel          Cancel the recording or its processing
+  modes           List modes; the active one is marked with *
+  mode <key>      Switch the active mode
+  last [--copy]   Print the latest dictation, optionally copying it";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if matches!(args.first().map(String::as_str), Some("-h" | "--help" | "help")) {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let request = match parse_args(&args) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match send(&request) {
+        Ok(Ok(output)) => {
+            if !output.is_empty() {
+                println!("{}", output);
+            }
+            ExitCode::SUCCESS
+        }
+        Ok(Err(e)) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Could not reach WhisperTray at {}: {}", socket_path().display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn send(request: &whispertray_lib::control::Request) -> std::io::Result<Response> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(serde_json::from_str(&reply)?)
+}
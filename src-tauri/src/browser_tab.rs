@@ -0,0 +1,131 @@
+//! The browser tab a dictation was made in
+//!
+//! A companion browser extension posts the active tab to the local HTTP API
+//! (`POST /browser-tab` on `health_port`) whenever it changes, and again
+//! every few minutes. When a recording starts with a browser focused, the
+//! last tab reported is stored with the dictation. Query strings and
+//! fragments, which can hold session tokens, are dropped. Requests must be
+//! `application/json`, which web pages cannot send across origins without a
+//! preflight the endpoint never answers.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// A report older than this is from a browser no longer focused, or an extension gone
+const MAX_AGE_SECS: i64 = 10 * 60;
+
+/// Window classes of browsers, matched case-insensitively
+const BROWSER_CLASSES: &[&str] = &[
+    "firefox",
+    "librewolf",
+    "waterfox",
+    "floorp",
+    "zen",
+    "chromium",
+    "chrome",
+    "brave",
+    "vivaldi",
+    "microsoft-edge",
+    "opera",
+    "epiphany",
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static LAST_TAB: Mutex<Option<Tab>> = Mutex::new(None);
+
+#[derive(Debug, Clone)]
+struct Tab {
+    url: String,
+    reported_at: DateTime<Utc>,
+}
+
+/// Body of `POST /browser-tab`
+#[derive(Debug, Deserialize)]
+struct Report {
+    url: String,
+}
+
+/// Accept tab reports or not; a disabled capture forgets the last tab
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        *LAST_TAB.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// `url` without credentials, query string or fragment; None unless http(s)
+fn clean_url(url: &str) -> Option<String> {
+    let mut url = reqwest::Url::parse(url.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    url.set_query(None);
+    url.set_fragment(None);
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    Some(url.to_string())
+}
+
+/// Store a tab reported by the extension; false when the body is not a report
+pub fn report(body: &str) -> bool {
+    let Some(url) = serde_json::from_str::<Report>(body)
+        .ok()
+        .and_then(|report| clean_url(&report.url))
+    else {
+        return false;
+    };
+    *LAST_TAB.lock().unwrap_or_else(|e| e.into_inner()) = Some(Tab {
+        url,
+        reported_at: Utc::now(),
+    });
+    true
+}
+
+fn is_browser(class: &str) -> bool {
+    let class = class.to_lowercase();
+    BROWSER_CLASSES
+        .iter()
+        .any(|browser| class.contains(browser))
+}
+
+/// The URL of the tab being dictated in, when a browser window is focused
+pub fn current(window: Option<&crate::window_info::WindowInfo>) -> Option<String> {
+    if !is_enabled()
+        || !window
+            .and_then(|w| w.class.as_deref())
+            .is_some_and(is_browser)
+    {
+        return None;
+    }
+    LAST_TAB
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .filter(|tab| Utc::now() - tab.reported_at < Duration::seconds(MAX_AGE_SECS))
+        .map(|tab| tab.url.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_url() {
+        assert_eq!(
+            clean_url("https://user:pw@docs.example.com/d/abc/edit?token=secret#heading")
+                .as_deref(),
+            Some("https://docs.example.com/d/abc/edit")
+        );
+        assert_eq!(clean_url("about:blank"), None);
+        assert_eq!(clean_url("not a url"), None);
+        assert!(is_browser("org.mozilla.firefox") && is_browser("Google-chrome"));
+        assert!(!is_browser("Alacritty"));
+    }
+}
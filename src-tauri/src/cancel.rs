@@ -0,0 +1,151 @@
+//! Cancellation of in-progress recording and processing
+//!
+//! The token lives outside the state mutex (which is held for the whole
+//! processing pipeline), so a cancel request can reach a running pipeline.
+
+use crate::error::{AppError, Result};
+use crate::state::SharedState;
+use crate::tray::{update_tray_icon, update_tray_menu};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+/// How long to wait for the state lock before assuming processing holds it
+const STATE_LOCK_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Shared cancellation flag for the dictation pipeline
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of whatever is in progress
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    /// Clear a previous cancellation before starting new work
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// The raw flag, for blocking code that polls it (e.g. whisper's abort callback)
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+
+    /// Fail with `AppError::Cancelled` if cancellation was requested
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(AppError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Wait until cancellation is requested
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking so a cancel in between is not missed
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Run a future, dropping it and returning `AppError::Cancelled` on cancel
+    ///
+    /// Dropping aborts in-flight HTTP requests; blocking work must poll the flag.
+    pub async fn run<T, F>(&self, future: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        self.check()?;
+        tokio::select! {
+            result = future => result,
+            _ = self.cancelled() => Err(AppError::Cancelled),
+        }
+    }
+}
+
+/// Cancel the in-progress recording or processing, discarding its output
+///
+/// A recording is stopped here directly. Processing holds the state lock
+/// for its whole run, so it is only signalled and unwinds on its own with
+/// `AppError::Cancelled`.
+pub async fn cancel_pipeline(handle: &AppHandle) {
+    if let Some(token) = handle.try_state::<CancelToken>() {
+        token.cancel();
+    }
+
+    if let Some(state_arc) = handle.try_state::<SharedState>() {
+        if let Ok(mut state) = tokio::time::timeout(STATE_LOCK_TIMEOUT, state_arc.lock()).await {
+            if state.is_recording() {
+                if let Err(e) = state.cancel_recording() {
+                    log::error!("Failed to cancel recording: {}", e);
+                }
+            }
+            let _ = update_tray_icon(handle, state.status);
+            let _ = update_tray_menu(handle, &state).await;
+        }
+    }
+
+    crate::indicator::emit_processing(handle, false);
+    let _ = crate::indicator::hide_indicator(handle);
+    let _ = handle.emit("recording-cancelled", ());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_completes_without_cancel() {
+        let token = CancelToken::new();
+        let result = token.run(async { Ok(42) }).await;
+        assert_eq!(result.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_aborts_pending_future() {
+        let token = CancelToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            canceller.cancel();
+        });
+
+        let result: Result<()> = token
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(())
+            })
+            .await;
+        assert!(matches!(result, Err(AppError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_reset_clears_cancel() {
+        let token = CancelToken::new();
+        token.cancel();
+        assert!(token.check().is_err());
+        token.reset();
+        assert!(token.run(async { Ok(()) }).await.is_ok());
+    }
+}
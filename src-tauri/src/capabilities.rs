@@ -0,0 +1,439 @@
+//! Capability introspection
+//!
+//! Reports which optional integrations are active, and which of the more
+//! invasive ones the user has opted in to. Also reports features running in
+//! a reduced form because an optional binary is missing, with how to fix it.
+
+use crate::modes::{LlmProvider, Mode, SttProvider};
+use crate::paste::{self, ClipboardBackend, PasteBackend, YdotoolStatus};
+use crate::punctuation::PunctuationRestore;
+use crate::state::Settings;
+use crate::template::ContextSource;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// One optional integration and its current state
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Capability {
+    pub key: &'static str,
+    pub name: &'static str,
+    /// Whether the integration is in use right now
+    pub active: bool,
+    /// Whether it stays off until enabled in settings
+    pub requires_opt_in: bool,
+    /// Whether the user enabled it (always true when no opt-in is needed)
+    pub opted_in: bool,
+    /// What is used, or why it is inactive
+    pub detail: String,
+}
+
+/// Report every optional integration
+pub fn introspect(settings: &Settings, modes: &HashMap<String, Mode>) -> Vec<Capability> {
+    let backend = paste::detect_backend();
+    let network = network_providers(settings, modes);
+    let mut shell_hooks: Vec<String> = settings
+        .hooks
+        .configured()
+        .into_iter()
+        .map(|event| event.name().to_string())
+        .collect();
+    if let Some(command) = settings.punctuation_command() {
+        shell_hooks.push(format!("Punctuation model: {}", command));
+    }
+    let mut filtered: Vec<&Mode> = modes
+        .values()
+        .filter(|mode| !mode.disabled && settings.output_command(mode).is_some())
+        .collect();
+    filtered.sort_by(|a, b| a.key.cmp(&b.key));
+    let output_commands = filtered.len();
+    for mode in filtered {
+        shell_hooks.push(format!("Output command of {}", mode.name));
+    }
+
+    vec![
+        Capability {
+            key: "input_simulation",
+            name: "Input simulation",
+            active: settings.auto_paste && backend != PasteBackend::ClipboardOnly,
+            requires_opt_in: false,
+            opted_in: true,
+            detail: match backend {
+                PasteBackend::Enigo => "enigo (X11 XTest)".to_string(),
+                PasteBackend::Wtype => "wtype (Wayland virtual keyboard)".to_string(),
+                PasteBackend::Ydotool => "ydotool (/dev/uinput)".to_string(),
+                PasteBackend::ClipboardOnly => "Clipboard only".to_string(),
+            },
+        },
+        Capability {
+            key: "evdev",
+            name: "Kernel input injection (/dev/uinput)",
+            active: settings.auto_paste && backend == PasteBackend::Ydotool,
+            requires_opt_in: true,
+            opted_in: settings.allow_evdev_input,
+            detail: if settings.allow_evdev_input {
+                match paste::ydotool_status().hint {
+                    Some(hint) if backend == PasteBackend::Ydotool => hint,
+                    _ => "ydotool may be used when wtype is unavailable".to_string(),
+                }
+            } else {
+                "Disabled; enable to let ydotool type via /dev/uinput".to_string()
+            },
+        },
+        Capability {
+            key: "clipboard_context",
+            name: "Clipboard context",
+            active: settings.context_awareness || context_modes(modes) > 0,
+            requires_opt_in: false,
+            opted_in: true,
+            detail: match context_modes(modes) {
+                0 => "Clipboard text and selection are read when recording starts".to_string(),
+                n => format!("Read when recording starts; {} mode(s) always read their context source", n),
+            },
+        },
+        Capability {
+            key: "network_providers",
+            name: "Network providers",
+            active: !network.is_empty(),
+            requires_opt_in: false,
+            opted_in: true,
+            detail: if network.is_empty() {
+                "All enabled modes run locally".to_string()
+            } else {
+                network.join(", ")
+            },
+        },
+        Capability {
+            key: "health_endpoint",
+            name: "Health endpoint",
+            active: settings.health_port.is_some(),
+            requires_opt_in: false,
+            opted_in: true,
+            detail: match settings.health_port {
+                Some(port) => format!("http://127.0.0.1:{}/health", port),
+                None => "Disabled".to_string(),
+            },
+        },
+        Capability {
+            key: "http_api",
+            name: "HTTP API",
+            active: settings.api_port.is_some() && settings.api_token.is_some(),
+            requires_opt_in: false,
+            opted_in: true,
+            detail: match (settings.api_port, &settings.api_token) {
+                (Some(port), Some(_)) => format!("http://127.0.0.1:{} and MCP at /mcp (token required)", port),
+                (Some(_), None) => "Disabled until a token is set".to_string(),
+                (None, _) => "Disabled".to_string(),
+            },
+        },
+        Capability {
+            key: "shell_hooks",
+            name: "Shell hooks",
+            active: settings.allow_shell_hooks
+                && (!settings.hooks.configured().is_empty()
+                    || output_commands > 0
+                    || (settings.punctuation == PunctuationRestore::Model && settings.punctuation_command().is_some())),
+            requires_opt_in: true,
+            opted_in: settings.allow_shell_hooks,
+            detail: if shell_hooks.is_empty() {
+                "No shell hooks configured".to_string()
+            } else {
+                shell_hooks.join(", ")
+            },
+        },
+        Capability {
+            key: "pre_roll",
+            name: "Pre-roll (microphone kept open)",
+            active: settings.pre_roll_ms > 0,
+            requires_opt_in: true,
+            opted_in: settings.pre_roll_ms > 0,
+            detail: if settings.pre_roll_ms > 0 {
+                format!(
+                    "The last {}ms before each recording are kept in memory, never on disk",
+                    settings.pre_roll_ms.min(crate::preroll::MAX_PRE_ROLL_MS)
+                )
+            } else {
+                "Off".to_string()
+            },
+        },
+        Capability {
+            key: "screenshots",
+            name: "Screenshot access",
+            active: false,
+            requires_opt_in: true,
+            opted_in: settings.allow_screenshots,
+            detail: "Not used by any feature".to_string(),
+        },
+    ]
+}
+
+/// A feature running in a reduced form because an optional binary is missing
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Degradation {
+    pub key: &'static str,
+    pub feature: &'static str,
+    /// What happens instead
+    pub impact: String,
+    /// How to restore the feature
+    pub hint: String,
+}
+
+/// Optional binaries and services found on this system
+#[derive(Debug, Clone, Default)]
+pub struct Tooling {
+    pub wayland: bool,
+    pub wtype: bool,
+    pub wl_clipboard: bool,
+    /// The focused window can be looked up (see `window_info`)
+    pub window_info: bool,
+    pub ydotoold_installed: bool,
+    pub ydotool: YdotoolStatus,
+    /// Media players can be paused over the session bus
+    pub gdbus: bool,
+}
+
+impl Tooling {
+    pub fn detect() -> Self {
+        Self {
+            wayland: paste::is_wayland(),
+            wtype: paste::is_command_available("wtype"),
+            wl_clipboard: paste::is_command_available("wl-copy") && paste::is_command_available("wl-paste"),
+            window_info: crate::window_info::detect_backend().is_some(),
+            ydotoold_installed: paste::is_command_available("ydotoold"),
+            ydotool: paste::ydotool_status(),
+            gdbus: paste::is_command_available("gdbus"),
+        }
+    }
+}
+
+/// Report the features degraded by missing binaries on this system
+pub fn degradations(settings: &Settings) -> Vec<Degradation> {
+    let mut degraded = degradations_for(settings, &Tooling::detect());
+    if let Some(reason) = crate::gpu::last_placement().and_then(|placement| placement.fallback_reason) {
+        degraded.push(Degradation {
+            key: "gpu",
+            feature: "GPU transcription",
+            impact: match settings.gpu_fallback {
+                crate::gpu::GpuFallback::Cpu => format!("{}, so it runs on the CPU", reason),
+                crate::gpu::GpuFallback::Smaller => format!("{}, so a smaller model or the CPU is used", reason),
+            },
+            hint: "Close other programs using the GPU, or choose a smaller whisper model.".to_string(),
+        });
+    }
+    degraded
+}
+
+fn degradations_for(settings: &Settings, tooling: &Tooling) -> Vec<Degradation> {
+    let mut degraded = Vec::new();
+    let ydotool = &tooling.ydotool;
+    // A managed daemon is started on first use, so it need not run yet
+    let ydotool_ready = ydotool.installed
+        && ydotool.allowed
+        && (ydotool.daemon_running
+            || (settings.manage_ydotoold && tooling.ydotoold_installed && ydotool.uinput_writable));
+
+    if settings.auto_paste && tooling.wayland && !tooling.wtype {
+        if ydotool.installed && ydotool.allowed && !ydotool_ready {
+            degraded.push(Degradation {
+                key: "typing",
+                feature: "Typing with ydotool",
+                impact: "ydotool cannot type, so text is only copied to the clipboard".to_string(),
+                hint: ydotool.hint.clone().unwrap_or_default(),
+            });
+        } else if !ydotool_ready {
+            degraded.push(Degradation {
+                key: "auto_paste",
+                feature: "Auto-paste",
+                impact: "No Wayland input backend, so text is only copied to the clipboard".to_string(),
+                hint: "Install wtype, or install ydotool and enable /dev/uinput input in settings.".to_string(),
+            });
+        }
+    }
+
+    if tooling.wayland && !tooling.wl_clipboard && settings.clipboard_backend != ClipboardBackend::Arboard {
+        degraded.push(Degradation {
+            key: "clipboard",
+            feature: "Wayland clipboard",
+            impact: "Setting the clipboard may fail while no WhisperTray window is focused".to_string(),
+            hint: "Install wl-clipboard (wl-copy and wl-paste).".to_string(),
+        });
+    }
+
+    if settings.audio_backend == crate::audio::AudioBackend::PipeWire && !cfg!(feature = "pipewire") {
+        degraded.push(Degradation {
+            key: "audio_backend",
+            feature: "PipeWire audio backend",
+            impact: "This build has no PipeWire backend, so devices are opened through cpal".to_string(),
+            hint: "Rebuild with `--features pipewire` (needs the libpipewire-0.3 development files).".to_string(),
+        });
+    }
+
+    if settings.audio_host == crate::audio::AudioHost::Jack && !cfg!(feature = "jack") {
+        degraded.push(Degradation {
+            key: "audio_host",
+            feature: "JACK audio host",
+            impact: "This build has no JACK host, so devices are opened through the default host".to_string(),
+            hint: "Rebuild with `--features jack` (needs the JACK development files).".to_string(),
+        });
+    }
+
+    if settings.recording_format == crate::audio_codec::RecordingFormat::Opus && !cfg!(feature = "opus") {
+        degraded.push(Degradation {
+            key: "recording_format",
+            feature: "Opus recordings",
+            impact: "This build has no Opus encoder, so recordings are kept as FLAC".to_string(),
+            hint: "Rebuild with `--features opus` (needs the libopus development files).".to_string(),
+        });
+    }
+
+    if settings.capture_browser_url && (settings.health_port.is_none() || !tooling.window_info) {
+        degraded.push(Degradation {
+            key: "browser_url",
+            feature: "Browser tab URLs",
+            impact: "Dictations are stored without the browser tab they were made in".to_string(),
+            hint: if settings.health_port.is_none() {
+                "Set health_port so the browser extension can report tabs to the local HTTP API.".to_string()
+            } else {
+                "Install xprop (X11), or swaymsg, hyprctl or kdotool, so a focused browser can be detected.".to_string()
+            },
+        });
+    }
+
+    if !tooling.window_info && !(settings.app_char_limits.is_empty() && settings.paste_rules.is_empty()) {
+        degraded.push(Degradation {
+            key: "app_detection",
+            feature: "Per-app limits and paste rules",
+            impact: "The focused app cannot be detected, so app limits and paste rules never apply".to_string(),
+            hint: "Install xprop (X11), or swaymsg, hyprctl or kdotool for your Wayland compositor.".to_string(),
+        });
+    }
+
+    if settings.pause_media && !tooling.gdbus {
+        degraded.push(Degradation {
+            key: "pause_media",
+            feature: "Pausing media while recording",
+            impact: "Media players keep playing while you dictate".to_string(),
+            hint: "Install gdbus (part of GLib, usually the libglib2.0-bin package).".to_string(),
+        });
+    }
+
+    degraded
+}
+
+/// Enabled modes that read the clipboard or selection regardless of settings
+fn context_modes(modes: &HashMap<String, Mode>) -> usize {
+    modes
+        .values()
+        .filter(|mode| !mode.disabled)
+        .filter(|mode| matches!(mode.context_source, ContextSource::Clipboard | ContextSource::Selection))
+        .count()
+}
+
+/// Network endpoints used by enabled modes, e.g. "OpenAI (STT)"
+fn network_providers(settings: &Settings, modes: &HashMap<String, Mode>) -> Vec<String> {
+    let mut providers = Vec::new();
+    let mut add = |provider: String| {
+        if !providers.contains(&provider) {
+            providers.push(provider);
+        }
+    };
+
+    let mut enabled: Vec<&Mode> = modes.values().filter(|m| !m.disabled).collect();
+    enabled.sort_by(|a, b| a.key.cmp(&b.key));
+
+    for mode in enabled {
+        match &mode.stt_provider {
+            SttProvider::WhisperCpp => {}
+            SttProvider::WhisperServer => add(format!(
+                "Whisper server {} (STT)",
+                settings.whisper_server_url.as_deref().unwrap_or("(not configured)")
+            )),
+            SttProvider::OpenAI => add("OpenAI (STT)".to_string()),
+            SttProvider::Groq => add("Groq (STT)".to_string()),
+            SttProvider::Deepgram => add("Deepgram (STT)".to_string()),
+            SttProvider::Wyoming => add(format!(
+                "Wyoming server {} (STT)",
+                settings.wyoming_url.as_deref().unwrap_or(crate::providers::wyoming::DEFAULT_ADDRESS)
+            )),
+            SttProvider::Custom(name) => add(format!("{} (STT)", name)),
+        }
+
+        for provider in mode.llm_providers() {
+            match provider {
+                LlmProvider::OpenAI => add("OpenAI (LLM)".to_string()),
+                LlmProvider::Anthropic => add("Anthropic (LLM)".to_string()),
+                LlmProvider::Ollama => add(format!(
+                    "Ollama {} (LLM)",
+                    settings.ollama_url.as_deref().unwrap_or("http://localhost:11434")
+                )),
+                LlmProvider::Custom(name) => add(format!("{} (LLM)", name)),
+            }
+        }
+    }
+
+    providers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(key: &str, stt: SttProvider, llm: Option<LlmProvider>) -> Mode {
+        Mode {
+            key: key.to_string(),
+            stt_provider: stt,
+            ai_processing: llm.is_some(),
+            llm_provider: llm.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_network_providers_from_enabled_modes() {
+        let mut modes = HashMap::new();
+        modes.insert("a".to_string(), mode("a", SttProvider::WhisperCpp, None));
+        modes.insert("b".to_string(), mode("b", SttProvider::OpenAI, Some(LlmProvider::Anthropic)));
+        let mut disabled = mode("c", SttProvider::Deepgram, None);
+        disabled.disabled = true;
+        modes.insert("c".to_string(), disabled);
+
+        let providers = network_providers(&Settings::default(), &modes);
+        assert_eq!(providers, vec!["OpenAI (STT)", "Anthropic (LLM)"]);
+    }
+
+    #[test]
+    fn test_degradations_on_wayland() {
+        let settings = Settings::default();
+        let mut tooling = Tooling {
+            wayland: true,
+            ..Default::default()
+        };
+        let keys = |tooling: &Tooling| {
+            degradations_for(&settings, tooling)
+                .iter()
+                .map(|d| d.key)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&tooling), vec!["auto_paste", "clipboard"]);
+
+        tooling.ydotool = YdotoolStatus {
+            installed: true,
+            allowed: true,
+            hint: Some("ydotoold is not running.".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(keys(&tooling), vec!["typing", "clipboard"]);
+
+        tooling.wtype = true;
+        tooling.wl_clipboard = true;
+        assert!(keys(&tooling).is_empty());
+    }
+
+    #[test]
+    fn test_invasive_integrations_off_by_default() {
+        let capabilities = introspect(&Settings::default(), &HashMap::new());
+        for capability in capabilities.iter().filter(|c| c.requires_opt_in) {
+            assert!(!capability.opted_in, "{} should need opt-in", capability.key);
+            assert!(!capability.active, "{} should be inactive", capability.key);
+        }
+    }
+}
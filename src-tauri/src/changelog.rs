@@ -0,0 +1,127 @@
+//! What's new after an update
+//!
+//! Release notes are compiled in from `resources/whats_new.json`, newest
+//! first. Notes for the release in the making are listed under its version
+//! without a date, and only show once the app is built as that version. The last version whose notes were dismissed is remembered in the
+//! data directory; the UI shows the notes of every release since then, up to
+//! the running version. A fresh install shows the running version's notes.
+
+use crate::error::{AppError, Result};
+use crate::features::ReleaseChannel;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const RELEASES_JSON: &str = include_str!("../resources/whats_new.json");
+
+/// Notes for one release
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    /// Release date, `YYYY-MM-DD`; None while it is not released yet
+    pub date: Option<String>,
+    pub notes: Vec<String>,
+}
+
+/// Sent to the UI after an update
+#[derive(Debug, Clone, Serialize)]
+pub struct WhatsNew {
+    pub version: String,
+    pub channel: ReleaseChannel,
+    /// Releases not yet dismissed, newest first; empty when there is nothing to show
+    pub releases: Vec<Release>,
+}
+
+/// Version as numbers for ordering; non-numeric parts (pre-release tags) count as 0
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Releases after `seen` up to `current`, or just `current` when nothing was seen
+fn unseen(releases: Vec<Release>, seen: Option<&str>, current: &str) -> Vec<Release> {
+    let current = version_key(current);
+    let seen = seen.map(version_key);
+    releases
+        .into_iter()
+        .filter(|release| {
+            let version = version_key(&release.version);
+            match &seen {
+                Some(seen) => version > *seen && version <= current,
+                None => version == current,
+            }
+        })
+        .collect()
+}
+
+fn releases() -> Vec<Release> {
+    serde_json::from_str(RELEASES_JSON).unwrap_or_else(|e| {
+        log::warn!("Failed to read the release notes: {}", e);
+        Vec::new()
+    })
+}
+
+fn seen_path() -> Result<PathBuf> {
+    let data_dir = directories::ProjectDirs::from("com", "whispertray", "WhisperTray")
+        .ok_or_else(|| AppError::Config("Could not determine data directory".to_string()))?
+        .data_dir()
+        .to_path_buf();
+    Ok(data_dir.join("whats_new_seen"))
+}
+
+/// Release notes the user has not dismissed yet
+pub fn whats_new() -> WhatsNew {
+    let version = env!("CARGO_PKG_VERSION");
+    let seen = seen_path()
+        .and_then(|path| Ok(std::fs::read_to_string(path)?))
+        .ok();
+    WhatsNew {
+        version: version.to_string(),
+        channel: ReleaseChannel::current(),
+        releases: unseen(releases(), seen.as_deref().map(str::trim), version),
+    }
+}
+
+/// Remember that the running version's notes were seen
+pub fn dismiss() -> Result<()> {
+    let path = seen_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unseen() {
+        let release = |version: &str| Release {
+            version: version.to_string(),
+            date: Some("2026-01-01".to_string()),
+            notes: vec![],
+        };
+        let all = vec![
+            release("0.10.0"),
+            release("0.9.1"),
+            release("0.9.0"),
+            release("0.8.0"),
+        ];
+        let versions =
+            |releases: Vec<Release>| releases.into_iter().map(|r| r.version).collect::<Vec<_>>();
+
+        assert_eq!(
+            versions(unseen(all.clone(), Some("0.9.0"), "0.10.0")),
+            ["0.10.0", "0.9.1"]
+        );
+        assert!(unseen(all.clone(), Some("0.10.0"), "0.10.0").is_empty());
+        assert_eq!(versions(unseen(all.clone(), None, "0.9.1")), ["0.9.1"]);
+        // The bundled notes parse, and the next release's don't show yet
+        assert!(!releases().is_empty());
+        assert!(unseen(releases(), Some("0.1.0"), "0.1.0").is_empty());
+    }
+}
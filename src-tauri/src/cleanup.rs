@@ -0,0 +1,155 @@
+//! Rule-based transcript cleanup, the LLM-free post-processor
+//!
+//! Tidies a transcript without it leaving the machine: drops hesitations
+//! ("um", "uh") and words repeated by a stumble ("the the"), fixes spacing
+//! around punctuation, capitalizes sentences and the pronoun "I", and ends
+//! the text with a full stop. Sentences are found from the punctuation the
+//! STT model produced; nothing is reworded.
+
+/// Hesitation sounds dropped from English unless filler words are kept
+const HESITATIONS: &[&str] = &["um", "umm", "uh", "uhh", "uhm", "er", "erm", "hmm"];
+
+/// English words that are often correctly doubled ("I had had enough")
+const INTENTIONAL_REPEATS: &[&str] = &["had", "that", "is"];
+
+/// English abbreviations whose full stop does not end a sentence
+const ABBREVIATIONS: &[&str] = &["mr.", "mrs.", "ms.", "dr.", "st.", "vs.", "e.g.", "i.e.", "approx."];
+
+const SENTENCE_END: &[char] = &['.', '!', '?', '…'];
+
+/// Punctuation that attaches to the preceding word
+const TRAILING_PUNCTUATION: &[char] = &[',', '.', '!', '?', ';', ':', '…'];
+
+/// Word with surrounding punctuation removed, for comparisons
+pub(crate) fn key(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+/// The punctuation a word ends with, e.g. "," for "well,"
+pub(crate) fn trailing(word: &str) -> &str {
+    &word[word.trim_end_matches(TRAILING_PUNCTUATION).len()..]
+}
+
+pub(crate) fn ends_sentence(word: &str, english: bool) -> bool {
+    let word = word.trim_end_matches(['"', '\'', ')', '”', '’', '»']);
+    word.ends_with(SENTENCE_END) && !(english && ABBREVIATIONS.contains(&word.to_lowercase().as_str()))
+}
+
+/// Uppercase the first letter of a word, leaving any opening quote alone
+pub(crate) fn capitalize(word: &str) -> String {
+    match word.char_indices().find(|(_, c)| c.is_alphanumeric()) {
+        Some((i, c)) => format!("{}{}{}", &word[..i], c.to_uppercase(), &word[i + c.len_utf8()..]),
+        None => word.to_string(),
+    }
+}
+
+/// Clean up a transcript in `language` ("en", "de-AT", ...)
+///
+/// Hesitations are English-only, and kept with `keep_fillers`.
+pub fn clean(text: &str, language: &str, keep_fillers: bool) -> String {
+    let english = language.to_lowercase().starts_with("en");
+    let mut words: Vec<String> = Vec::new();
+    // The comma before the last dropped hesitation was removed with it
+    let mut comma_dropped = false;
+
+    for word in text.split_whitespace() {
+        let word_key = key(word);
+
+        // Lone punctuation belongs to the word before it
+        if word_key.is_empty() && word.chars().all(|c| TRAILING_PUNCTUATION.contains(&c)) {
+            if let Some(prev) = words.last_mut() {
+                if trailing(prev).is_empty() {
+                    prev.push_str(word);
+                }
+                continue;
+            }
+        }
+
+        // A hesitation takes the comma setting it off with it ("is, uh, ready"),
+        // and a dropped word hands a sentence end on to the word before it
+        let hesitation = english && !keep_fillers && HESITATIONS.contains(&word_key.as_str());
+        let stumble = words.last().is_some_and(|prev| {
+            trailing(prev).is_empty()
+                && !word_key.is_empty()
+                && key(prev) == word_key
+                && !(english && INTENTIONAL_REPEATS.contains(&word_key.as_str()))
+        });
+        if hesitation || (stumble && !comma_dropped) {
+            let punctuation = trailing(word);
+            if let Some(prev) = words.last_mut() {
+                if hesitation && trailing(prev) == "," {
+                    prev.pop();
+                    comma_dropped = true;
+                }
+                if trailing(prev).is_empty() && punctuation.ends_with(SENTENCE_END) {
+                    prev.push_str(punctuation);
+                }
+            }
+            continue;
+        }
+
+        // A repeat across a dropped hesitation was deliberate ("no, um, no")
+        if stumble && comma_dropped {
+            if let Some(prev) = words.last_mut() {
+                prev.push(',');
+            }
+        }
+        comma_dropped = false;
+        words.push(word.to_string());
+    }
+
+    let mut sentence_start = true;
+    for word in &mut words {
+        let pronoun = english && matches!(word.as_str(), "i" | "i'm" | "i've" | "i'll" | "i'd");
+        if sentence_start || pronoun {
+            *word = capitalize(word);
+        }
+        sentence_start = ends_sentence(word, english);
+    }
+
+    if let Some(last) = words.last_mut() {
+        if last.ends_with(|c: char| c.is_alphanumeric()) {
+            last.push('.');
+        }
+    }
+
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_sentences() {
+        assert_eq!(
+            clean("so i think we should go. what do you think", "en", false),
+            "So I think we should go. What do you think."
+        );
+        assert_eq!(clean("meet dr. smith at noon", "en", false), "Meet dr. smith at noon.");
+        assert_eq!(clean("", "en", false), "");
+    }
+
+    #[test]
+    fn test_clean_stumbles() {
+        assert_eq!(
+            clean("um the the report is , uh, ready", "en", false),
+            "The report is ready."
+        );
+        assert_eq!(clean("so, uh. next", "en", false), "So. Next.");
+        assert_eq!(clean("no, um, no", "en", false), "No, no.");
+        assert_eq!(clean("we need to to go go.", "en", false), "We need to go.");
+        // Deliberate repeats are separated by punctuation or common in English
+        assert_eq!(clean("no, no, no", "en", false), "No, no, no.");
+        assert_eq!(clean("she had had enough", "en", false), "She had had enough.");
+        // A dropped word does not lose the sentence end
+        assert_eq!(clean("that is it um. next", "en", false), "That is it. Next.");
+    }
+
+    #[test]
+    fn test_clean_keeps_fillers_and_other_languages() {
+        assert_eq!(clean("um, well", "en", true), "Um, well.");
+        // "er" is a German word, and "i" is not a pronoun outside English
+        assert_eq!(clean("er kommt i guess", "de", false), "Er kommt i guess.");
+    }
+}
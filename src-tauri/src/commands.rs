@@ -1,11 +1,33 @@
 //! Tauri command handlers
 
-use crate::audio::{get_input_devices as get_audio_devices, AudioDevice};
-use crate::database::HistoryItem;
-use crate::modes::Mode;
-use crate::state::{RecordingStatus, Settings, SharedState};
+use crate::audio::{get_input_devices as get_audio_devices, AudioBackend, AudioDevice, AudioHost, CaptureSource};
+use crate::batch::BatchQueue;
+use crate::capabilities::{Capability, Degradation};
+use crate::changelog::WhatsNew;
+use crate::config_bundle::BundleImport;
+use crate::database::{
+    BatchJob, BatchStatus, BatchTask, Database, HistoryItem, HistoryRevision, HistoryStats, JournalEntry,
+    MonthlySpend, StatsRange,
+};
+use crate::dictionary::{ConflictPolicy, ImportReport};
+use crate::error::ErrorStage;
+use crate::features::FeatureFlag;
+use crate::health::{HealthMonitor, HealthStatus};
+use crate::modes::{Mode, ModeIssue};
+use crate::pacing::{Pacing, PacingPreset};
+use crate::paste::PasteInfo;
+use crate::phrases::PhraseSuggestion;
+use crate::picker::{ModelRecommendation, PickRequest, Picker};
+use crate::pipeline::DryRunResult;
+use crate::playback::PlaybackState;
+use crate::providers::stt::{SegmentAlternatives, SttSelection};
+use crate::samples::{ModeTestResult, SampleClip};
+use crate::state::{AppState, RecordingStatus, Settings, SharedState};
+use crate::style::StyleProfile;
+use crate::template::{TemplatePreview, TemplateVars};
 use crate::tray::{update_tray_icon, update_tray_menu};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use tauri::State;
 
 /// Recording status response
@@ -13,6 +35,10 @@ use tauri::State;
 pub struct RecordingStatusResponse {
     pub status: RecordingStatus,
     pub is_recording: bool,
+    /// Whether new dictations are kept off disk
+    pub incognito: bool,
+    /// Whether the user paused the current recording
+    pub paused: bool,
 }
 
 /// Start recording
@@ -20,10 +46,13 @@ pub struct RecordingStatusResponse {
 pub async fn start_recording(
     state: State<'_, SharedState>,
     app_handle: tauri::AppHandle,
+    source: Option<CaptureSource>,
 ) -> Result<(), String> {
     let mut state = state.lock().await;
 
-    state.start_recording().map_err(|e| e.to_string())?;
+    state
+        .start_recording_from(source, None)
+        .map_err(|e| e.to_string())?;
     update_tray_icon(&app_handle, RecordingStatus::Recording).map_err(|e| e.to_string())?;
     update_tray_menu(&app_handle, &state)
         .await
@@ -38,11 +67,10 @@ pub async fn stop_recording(
     state: State<'_, SharedState>,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    let mut state = state.lock().await;
-
     update_tray_icon(&app_handle, RecordingStatus::Processing).map_err(|e| e.to_string())?;
 
-    let result = state.stop_recording().await;
+    let result = crate::state::stop_recording(&state).await;
+    let state = state.lock().await;
 
     // We need to update tray here to match the reset to Ready state, because GUI button path doesn't emit events (frontend handles its own error).
     let _ = update_tray_icon(&app_handle, state.status);
@@ -51,6 +79,29 @@ pub async fn stop_recording(
     result.map_err(|e| e.to_string())
 }
 
+/// Cancel the in-progress recording or processing, discarding its output
+#[tauri::command]
+pub async fn cancel_recording(app_handle: tauri::AppHandle) -> Result<(), String> {
+    crate::cancel::cancel_pipeline(&app_handle).await;
+    Ok(())
+}
+
+/// Ask the sandbox's Device portal for microphone access
+#[tauri::command]
+pub async fn request_mic_access() -> Result<(), String> {
+    tokio::task::spawn_blocking(crate::portal::request_access)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Drop a bookmark in the current recording, returning its offset in ms
+#[tauri::command]
+pub async fn add_bookmark(state: State<'_, SharedState>) -> Result<u64, String> {
+    let state = state.lock().await;
+    state.add_bookmark().map_err(|e| e.to_string())
+}
+
 /// Get current recording status
 #[tauri::command]
 pub async fn get_recording_status(
@@ -61,9 +112,39 @@ pub async fn get_recording_status(
     Ok(RecordingStatusResponse {
         status: state.status,
         is_recording: state.is_recording(),
+        incognito: state.incognito,
+        paused: state.recording_handle.is_held(),
     })
 }
 
+/// Pause the current recording; audio until `resume_recording` is left out
+#[tauri::command]
+pub async fn pause_recording(state: State<'_, SharedState>) -> Result<(), String> {
+    let state = state.lock().await;
+    state.set_recording_paused(true).map_err(|e| e.to_string())
+}
+
+/// Resume a paused recording
+#[tauri::command]
+pub async fn resume_recording(state: State<'_, SharedState>) -> Result<(), String> {
+    let state = state.lock().await;
+    state.set_recording_paused(false).map_err(|e| e.to_string())
+}
+
+/// Turn incognito on or off: no recording, history or journal entry is saved
+#[tauri::command]
+pub async fn set_incognito(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut state = state.lock().await;
+    state.set_incognito(enabled);
+    update_tray_menu(&app_handle, &state)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Get all available modes
 #[tauri::command]
 pub async fn get_modes(state: State<'_, SharedState>) -> Result<Vec<Mode>, String> {
@@ -95,10 +176,206 @@ pub async fn get_active_mode(state: State<'_, SharedState>) -> Result<Option<Mod
     Ok(state.get_active_mode().cloned())
 }
 
-/// Get available input devices
+/// Check a mode without saving it
+#[tauri::command]
+pub async fn validate_mode(mode: Mode) -> Result<Vec<ModeIssue>, String> {
+    Ok(crate::modes::validate(&mode))
+}
+
+fn ensure_valid(mode: &Mode) -> Result<(), String> {
+    let issues = crate::modes::validate(mode);
+    if issues.is_empty() {
+        return Ok(());
+    }
+    let issues: Vec<_> = issues
+        .iter()
+        .map(|issue| format!("{}: {}", issue.field, issue.message))
+        .collect();
+    Err(format!("Invalid mode: {}", issues.join("; ")))
+}
+
+/// Create a custom mode under a key not yet in use
+#[tauri::command]
+pub async fn create_mode(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    mode: Mode,
+) -> Result<Mode, String> {
+    let mut state = state.lock().await;
+    state.ensure_writable("changing modes").map_err(|e| e.to_string())?;
+    ensure_valid(&mode)?;
+    if state.modes.contains_key(&mode.key) {
+        return Err(format!("A mode with the key {} already exists", mode.key));
+    }
+
+    let mode = Mode { builtin: false, ..mode };
+    crate::modes::save_mode(&mode).await.map_err(|e| e.to_string())?;
+    state.modes.insert(mode.key.clone(), mode.clone());
+    update_tray_menu(&app_handle, &state)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(mode)
+}
+
+/// Replace mode `key`; a custom mode may be given a new key
+#[tauri::command]
+pub async fn update_mode(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    key: String,
+    mode: Mode,
+) -> Result<Mode, String> {
+    let mut state = state.lock().await;
+    state.ensure_writable("changing modes").map_err(|e| e.to_string())?;
+    ensure_valid(&mode)?;
+    let builtin = state
+        .modes
+        .get(&key)
+        .ok_or_else(|| format!("Mode not found: {}", key))?
+        .builtin;
+    let renamed = mode.key != key;
+    if renamed && builtin {
+        return Err("Built-in modes keep their key; duplicate the mode instead".to_string());
+    }
+    if renamed && state.modes.contains_key(&mode.key) {
+        return Err(format!("A mode with the key {} already exists", mode.key));
+    }
+
+    let mode = Mode { builtin, ..mode };
+    crate::modes::save_mode(&mode).await.map_err(|e| e.to_string())?;
+    state.modes.insert(mode.key.clone(), mode.clone());
+    if renamed {
+        crate::modes::delete_mode(&key).await.map_err(|e| e.to_string())?;
+        state.modes.remove(&key);
+        if state.active_mode_key == key {
+            state.set_active_mode(&mode.key).map_err(|e| e.to_string())?;
+        }
+    }
+    update_tray_menu(&app_handle, &state)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(mode)
+}
+
+/// Delete a custom mode; built-in modes can only be disabled
+#[tauri::command]
+pub async fn delete_mode(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    key: String,
+) -> Result<(), String> {
+    let mut state = state.lock().await;
+    state.ensure_writable("changing modes").map_err(|e| e.to_string())?;
+    let mode = state
+        .modes
+        .get(&key)
+        .ok_or_else(|| format!("Mode not found: {}", key))?;
+    if mode.builtin {
+        return Err("Built-in modes cannot be deleted; disable them instead".to_string());
+    }
+
+    crate::modes::delete_mode(&key).await.map_err(|e| e.to_string())?;
+    state.modes.remove(&key);
+    if state.active_mode_key == key {
+        state.set_active_mode("voice_to_text").map_err(|e| e.to_string())?;
+    }
+    update_tray_menu(&app_handle, &state)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Copy a mode into a new custom mode, under `new_key` or `<key>_copy`
+#[tauri::command]
+pub async fn duplicate_mode(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    key: String,
+    new_key: Option<String>,
+) -> Result<Mode, String> {
+    let mut state = state.lock().await;
+    state.ensure_writable("changing modes").map_err(|e| e.to_string())?;
+    let source = state
+        .modes
+        .get(&key)
+        .cloned()
+        .ok_or_else(|| format!("Mode not found: {}", key))?;
+    let new_key = match new_key {
+        Some(new_key) if state.modes.contains_key(&new_key) => {
+            return Err(format!("A mode with the key {} already exists", new_key));
+        }
+        Some(new_key) => new_key,
+        None => crate::modes::unique_key(&format!("{}_copy", source.key), |key| {
+            state.modes.contains_key(key)
+        }),
+    };
+
+    let mode = Mode {
+        key: new_key,
+        name: format!("{} (copy)", source.name),
+        builtin: false,
+        ..source
+    };
+    ensure_valid(&mode)?;
+    crate::modes::save_mode(&mode).await.map_err(|e| e.to_string())?;
+    state.modes.insert(mode.key.clone(), mode.clone());
+    update_tray_menu(&app_handle, &state)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(mode)
+}
+
+/// Get available input devices, of the configured backend or of one cpal host
+#[tauri::command]
+pub async fn get_input_devices(host: Option<AudioHost>) -> Result<Vec<AudioDevice>, String> {
+    match host {
+        Some(host) => crate::audio::get_host_input_devices(host),
+        None => get_audio_devices(),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Audio backends that can be chosen in this build
+#[tauri::command]
+pub async fn get_audio_backends() -> Result<Vec<AudioBackend>, String> {
+    Ok(crate::audio::available_audio_backends())
+}
+
+/// cpal hosts that can be chosen in this build
 #[tauri::command]
-pub async fn get_input_devices() -> Result<Vec<AudioDevice>, String> {
-    get_audio_devices().map_err(|e| e.to_string())
+pub async fn get_audio_hosts() -> Result<Vec<AudioHost>, String> {
+    Ok(crate::audio::available_audio_hosts())
+}
+
+/// Record a calibration clip from the selected microphone, returning its frame levels
+#[tauri::command]
+pub async fn record_calibration_clip(state: State<'_, SharedState>) -> Result<Vec<f32>, String> {
+    let state_guard = state.lock().await;
+    if state_guard.is_recording() {
+        return Err("Stop the recording before calibrating".to_string());
+    }
+    let device = state_guard.settings.input_device.clone();
+    let gain = state_guard.settings.input_gain(&device);
+    let channel = state_guard.settings.input_channel(&device);
+    drop(state_guard);
+
+    tokio::task::spawn_blocking(move || crate::audio::record_calibration_clip(&device, gain, channel))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Noise floor and suggested VAD threshold from a silent and a spoken calibration clip
+#[tauri::command]
+pub async fn compute_calibration(
+    silence: Vec<f32>,
+    speech: Vec<f32>,
+) -> Result<crate::audio::Calibration, String> {
+    crate::audio::calibrate(&silence, &speech).map_err(|e| e.to_string())
 }
 
 /// Set the input device
@@ -109,9 +386,11 @@ pub async fn set_input_device(
     device_name: String,
 ) -> Result<(), String> {
     let mut state = state.lock().await;
+    state.ensure_writable("changing settings").map_err(|e| e.to_string())?;
 
     state.settings.input_device = device_name;
     state.save_settings().map_err(|e| e.to_string())?;
+    state.update_pre_roll();
 
     update_tray_menu(&app_handle, &state)
         .await
@@ -120,6 +399,9 @@ pub async fn set_input_device(
     Ok(())
 }
 
+/// Files at least this long are run through VAD before transcription
+const VAD_MIN_FILE_MS: u64 = 60_000;
+
 /// Transcribe a file
 #[tauri::command]
 pub async fn transcribe_file(
@@ -133,7 +415,7 @@ pub async fn transcribe_file(
 
     // Load audio from file
     let path = std::path::PathBuf::from(&file_path);
-    let samples = crate::audio::load_wav(&path).map_err(|e| e.to_string())?;
+    let samples = crate::audio_codec::load_audio(&path).map_err(|e| e.to_string())?;
 
     // Get active mode
     let mode = state_guard
@@ -142,26 +424,186 @@ pub async fn transcribe_file(
         .ok_or_else(|| "No active mode".to_string())?;
 
     let language = state_guard.settings.language.clone();
-    let api_key = state_guard.get_stt_api_key(&mode.stt_provider).map_err(|e| e.to_string())?;
-    let server_url = state_guard.settings.whisper_server_url.clone();
+    let stt = state_guard.stt_selection(&mode);
+    let api_key = state_guard.get_stt_api_key(&stt.provider).map_err(|e| e.to_string())?;
+    let server_url = state_guard.settings.stt_endpoint(&stt.provider);
+    let vad_skip_silence = state_guard.settings.vad_skip_silence;
+    let vad_config = state_guard.settings.vad_config();
     drop(state_guard);
 
     // Transcribe
     let provider =
-        crate::providers::stt::create_stt_provider(&mode.stt_provider, &mode.stt_model, api_key, server_url)
+        crate::providers::stt::create_stt_provider(&stt.provider, &stt.model, api_key, server_url)
             .await
             .map_err(|e| e.to_string())?;
 
-    let transcript = provider
-        .transcribe(&samples, Some(&language))
-        .await
-        .map_err(|e| e.to_string())?;
+    let handle = app_handle.clone();
+    let progress = crate::providers::stt::Progress::new(move |percent| {
+        crate::indicator::emit_progress(&handle, "file", percent)
+    });
+
+    let long_file = crate::audio::calculate_duration_ms(samples.len()) >= VAD_MIN_FILE_MS;
+    // File transcription is background work, paused while a dictation runs
+    let transcript = crate::priority::background(async {
+        if vad_skip_silence && long_file {
+            // Skip silence in long files and only transcribe the speech
+            crate::providers::stt::transcribe_speech_only(
+                provider.as_ref(),
+                &samples,
+                Some(&language),
+                &vad_config,
+                &progress,
+            )
+            .await
+            .map(|segments| {
+                segments
+                    .into_iter()
+                    .map(|segment| segment.text)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+        } else {
+            provider
+                .transcribe_chunks(std::slice::from_ref(&samples), Some(&language), &progress)
+                .await
+                .map(|mut texts| texts.remove(0))
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?;
 
     update_tray_icon(&app_handle, RecordingStatus::Ready).map_err(|e| e.to_string())?;
 
     Ok(transcript)
 }
 
+/// Outcome of an STT provider swap, emitted as "stt-swapped"
+#[derive(Debug, Clone, Serialize)]
+pub struct SttSwapResult {
+    /// The selection now in use (None: each mode's own provider)
+    pub selection: Option<SttSelection>,
+    /// Whether the requested provider passed its health check and is in use
+    pub ready: bool,
+    pub error: Option<String>,
+}
+
+/// Switch the STT provider for the rest of the session, between dictations
+///
+/// `None` goes back to each mode's own provider. The new provider is created
+/// (downloading its model if needed) and health-checked before it replaces
+/// the current one, so a failed swap leaves the previous provider in use.
+#[tauri::command]
+pub async fn swap_stt_provider(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    selection: Option<SttSelection>,
+) -> Result<SttSwapResult, String> {
+    use tauri::Emitter;
+
+    let (api_key, server_url) = {
+        let mut state = state.lock().await;
+        if matches!(state.status, RecordingStatus::Recording | RecordingStatus::Processing) {
+            return Err("Finish the current dictation before switching STT provider".to_string());
+        }
+        let api_key = match &selection {
+            Some(selection) => state.get_stt_api_key(&selection.provider).map_err(|e| e.to_string())?,
+            None => None,
+        };
+        state.status = RecordingStatus::Loading;
+        let server_url = selection.as_ref().and_then(|s| state.settings.stt_endpoint(&s.provider));
+        (api_key, server_url)
+    };
+    let _ = update_tray_icon(&app_handle, RecordingStatus::Loading);
+
+    // Mode providers are created per dictation; only a swap-in needs checking
+    let check = match &selection {
+        Some(selection) => {
+            match crate::providers::stt::create_stt_provider(&selection.provider, &selection.model, api_key, server_url)
+                .await
+            {
+                Ok(provider) => provider.health_check().await,
+                Err(e) => Err(e),
+            }
+        }
+        None => Ok(()),
+    };
+
+    let result = {
+        let mut state = state.lock().await;
+        // A dictation may have started meanwhile; leave its status alone
+        if state.status == RecordingStatus::Loading {
+            state.status = RecordingStatus::Ready;
+            let _ = update_tray_icon(&app_handle, RecordingStatus::Ready);
+        }
+        match check {
+            Ok(()) => {
+                log::info!("STT provider swapped to {:?}", selection);
+                state.stt_override = selection;
+                SttSwapResult {
+                    selection: state.stt_override.clone(),
+                    ready: true,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                log::warn!("STT provider swap failed, keeping the current one: {}", e);
+                SttSwapResult {
+                    selection: state.stt_override.clone(),
+                    ready: false,
+                    error: Some(e.to_string()),
+                }
+            }
+        }
+    };
+
+    let _ = app_handle.emit("stt-swapped", &result);
+    Ok(result)
+}
+
+/// Bundled sample clips directory
+fn bundled_samples_dir(app_handle: &tauri::AppHandle) -> Option<std::path::PathBuf> {
+    use tauri::Manager;
+    app_handle
+        .path()
+        .resource_dir()
+        .ok()
+        .map(|dir| dir.join("resources").join("samples"))
+}
+
+/// List sample clips available for mode tests
+#[tauri::command]
+pub async fn list_samples(app_handle: tauri::AppHandle) -> Result<Vec<SampleClip>, String> {
+    Ok(crate::samples::list_samples(bundled_samples_dir(&app_handle).as_deref()))
+}
+
+/// Run a mode on a sample clip without pasting, returning each stage's output
+#[tauri::command]
+pub async fn test_mode(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    mode_key: String,
+    sample_id: String,
+) -> Result<ModeTestResult, String> {
+    let sample = crate::samples::find_sample(bundled_samples_dir(&app_handle).as_deref(), &sample_id)
+        .map_err(|e| e.to_string())?;
+
+    let state = state.lock().await;
+    state.test_mode(&mode_key, &sample).await.map_err(|e| e.to_string())
+}
+
+/// Run a mode's processing on a sample transcript without recording or pasting
+///
+/// Takes the mode itself rather than a key, so unsaved edits can be tried.
+#[tauri::command]
+pub async fn dry_run_mode(
+    state: State<'_, SharedState>,
+    mode: Mode,
+    transcript: String,
+) -> Result<DryRunResult, String> {
+    let state = state.lock().await;
+    state.dry_run(&mode, &transcript).await.map_err(|e| e.to_string())
+}
+
 /// History query parameters
 #[derive(Debug, Deserialize)]
 pub struct HistoryQuery {
@@ -170,20 +612,23 @@ pub struct HistoryQuery {
     pub search: Option<String>,
 }
 
+/// The history database, so commands can use it without holding the state lock
+async fn database(state: &SharedState) -> Result<Arc<Database>, String> {
+    state
+        .lock()
+        .await
+        .database
+        .clone()
+        .ok_or_else(|| "Database not initialized".to_string())
+}
+
 /// Get history items
 #[tauri::command]
 pub async fn get_history(
     state: State<'_, SharedState>,
     query: Option<HistoryQuery>,
 ) -> Result<Vec<HistoryItem>, String> {
-    let state = state.lock().await;
-
-    let db = state
-        .database
-        .as_ref()
-        .ok_or_else(|| "Database not initialized".to_string())?;
-
-    let db = db.lock().unwrap();
+    let db = database(&state).await?;
 
     let query = query.unwrap_or(HistoryQuery {
         limit: Some(50),
@@ -191,13 +636,27 @@ pub async fn get_history(
         search: None,
     });
 
-    if let Some(search) = &query.search {
-        db.search_history(search, query.limit.unwrap_or(50))
-            .map_err(|e| e.to_string())
-    } else {
-        db.get_history(query.limit.unwrap_or(50), query.offset.unwrap_or(0))
-            .map_err(|e| e.to_string())
-    }
+    db.run(move |db| match &query.search {
+        Some(search) => db.search_history(search, query.limit.unwrap_or(50)),
+        None => db.get_history(query.limit.unwrap_or(50), query.offset.unwrap_or(0)),
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Get failed dictations, optionally only those that failed in one stage
+#[tauri::command]
+pub async fn get_failed_history(
+    state: State<'_, SharedState>,
+    stage: Option<ErrorStage>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<HistoryItem>, String> {
+    let db = database(&state).await?;
+
+    db.run(move |db| db.get_failed_history(stage, limit.unwrap_or(50), offset.unwrap_or(0)))
+        .await
+        .map_err(|e| e.to_string())
 }
 
 /// Get a single history item
@@ -206,15 +665,61 @@ pub async fn get_history_item(
     state: State<'_, SharedState>,
     id: String,
 ) -> Result<Option<HistoryItem>, String> {
-    let state = state.lock().await;
+    let db = database(&state).await?;
 
-    let db = state
-        .database
-        .as_ref()
-        .ok_or_else(|| "Database not initialized".to_string())?;
+    db.run(move |db| db.get_history_item(&id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Play a history item's recording, resuming it if it is paused
+#[tauri::command]
+pub async fn play_history_audio(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    id: String,
+) -> Result<PlaybackState, String> {
+    if let Some(playback) = crate::playback::resume(&id) {
+        return Ok(playback);
+    }
+    let db = database(&state).await?;
+    let lookup = id.clone();
+    let item = db
+        .run(move |db| db.get_history_item(&lookup))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "History item not found".to_string())?;
+    let path = item
+        .audio_path
+        .map(std::path::PathBuf::from)
+        .ok_or_else(|| "This history item has no recording".to_string())?;
+
+    let samples = tokio::task::spawn_blocking(move || crate::audio_codec::load_audio(&path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    crate::playback::play(app_handle, id, samples)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Pause the history recording being played
+#[tauri::command]
+pub async fn pause_history_audio() -> Result<Option<PlaybackState>, String> {
+    Ok(crate::playback::pause())
+}
+
+/// Jump to a position (ms) in the history recording being played
+#[tauri::command]
+pub async fn seek_history_audio(ms: u64) -> Result<Option<PlaybackState>, String> {
+    Ok(crate::playback::seek(ms))
+}
 
-    let db = db.lock().unwrap();
-    db.get_history_item(&id).map_err(|e| e.to_string())
+/// Stop playing a history recording
+#[tauri::command]
+pub async fn stop_history_audio() -> Result<(), String> {
+    crate::playback::stop();
+    Ok(())
 }
 
 /// Reprocess a history item with a different mode
@@ -225,110 +730,719 @@ pub async fn reprocess_history_item(
     id: String,
     mode_key: String,
 ) -> Result<String, String> {
-    let state_guard = state.lock().await;
-
     update_tray_icon(&app_handle, RecordingStatus::Processing).map_err(|e| e.to_string())?;
 
-    // Get history item
-    let db = state_guard
-        .database
-        .as_ref()
-        .ok_or_else(|| "Database not initialized".to_string())?;
-
-    let item = {
-        let db_guard = db.lock().unwrap();
-        db_guard
-            .get_history_item(&id)
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| "History item not found".to_string())?
-    };
-    let mut item = item;
+    let db = database(&state).await?;
+    let item = reprocess_item(&state, &db, id, mode_key, &crate::cancel::CancelToken::new()).await?;
 
-    // Get mode
-    let mode = state_guard
-        .modes
-        .get(&mode_key)
-        .cloned()
-        .ok_or_else(|| "Mode not found".to_string())?;
+    update_tray_icon(&app_handle, RecordingStatus::Ready).map_err(|e| e.to_string())?;
 
-    let language = state_guard.settings.language.clone();
-    let ollama_url = state_guard.settings.ollama_url.clone();
-    let api_key = state_guard.get_api_key(&mode.llm_provider).map_err(|e| e.to_string())?;
-    drop(state_guard);
+    Ok(item.output_final)
+}
 
-    // Reprocess
-    let output = if mode.ai_processing && !mode.prompt_template.is_empty() {
-        let provider = crate::providers::llm::create_llm_provider(
-            &mode.llm_provider,
-            &mode.llm_model,
-            api_key.as_deref(),
-            ollama_url,
-        )
+/// Transcribe a history item's recording again with another STT model, then
+/// run its mode on the new transcript
+///
+/// The old transcript and output are kept as a revision.
+#[tauri::command]
+pub async fn retranscribe_history_item(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    id: String,
+    stt: SttSelection,
+) -> Result<HistoryItem, String> {
+    state
+        .lock()
+        .await
+        .ensure_writable("re-transcribing history")
         .map_err(|e| e.to_string())?;
+    let db = database(&state).await?;
 
-        let prompt = crate::modes::render_prompt(
-            &mode.prompt_template,
-            &item.transcript_raw,
-            None,
-            &language,
-        );
+    update_tray_icon(&app_handle, RecordingStatus::Processing).map_err(|e| e.to_string())?;
+    let cancel = crate::cancel::CancelToken::new();
+    let item = retranscribe_item(&state, &db, id, &stt, &cancel).await?;
+    update_tray_icon(&app_handle, RecordingStatus::Ready).map_err(|e| e.to_string())?;
 
-        provider.complete(&prompt).await.map_err(|e| e.to_string())?
-    } else {
-        item.transcript_raw.clone()
-    };
+    Ok(item)
+}
+
+/// Run a history item through a mode again and save it
+pub(crate) async fn reprocess_item(
+    state: &SharedState,
+    db: &Arc<Database>,
+    id: String,
+    mode_key: String,
+    cancel: &crate::cancel::CancelToken,
+) -> Result<HistoryItem, String> {
+    let mut item = db
+        .run(move |db| db.get_history_item(&id))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "History item not found".to_string())?;
+    if item.summary_only {
+        return Err("Only the summary of this dictation was kept; there is no transcript to reprocess".to_string());
+    }
+
+    apply_mode(state, &mut item, mode_key, cancel).await?;
+    let updated = item.clone();
+    db.run(move |db| db.update_history(&item))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+/// Transcribe a history item's recording again, run its mode and save it
+///
+/// The state is not held while transcribing, so dictations are not kept waiting.
+pub(crate) async fn retranscribe_item(
+    state: &SharedState,
+    db: &Arc<Database>,
+    id: String,
+    stt: &SttSelection,
+    cancel: &crate::cancel::CancelToken,
+) -> Result<HistoryItem, String> {
+    let mut item = db
+        .run(move |db| db.get_history_item(&id))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "History item not found".to_string())?;
+    let path = item
+        .audio_path
+        .clone()
+        .filter(|_| !item.summary_only)
+        .map(std::path::PathBuf::from)
+        .ok_or_else(|| "The recording of this dictation was not kept".to_string())?;
+
+    let samples = tokio::task::spawn_blocking(move || crate::audio_codec::load_audio(&path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let retranscriber = {
+        let state_guard = state.lock().await;
+        let mode = state_guard
+            .modes
+            .get(&item.mode_key)
+            .ok_or_else(|| "Mode not found".to_string())?;
+        state_guard.retranscriber(mode, stt).map_err(|e| e.to_string())?
+    };
+    let retranscriber = retranscriber.load().await.map_err(|e| e.to_string())?;
+    let scored = retranscriber
+        .transcribe(&samples, cancel)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    item.transcript_raw = scored.text;
+    item.stt_provider = format!("{:?}", stt.provider).to_lowercase();
+    item.stt_model = stt.model_used();
+    // A failed transcription is resolved by a new one
+    if item.error.as_ref().is_some_and(|e| e.stage == ErrorStage::Stt) {
+        item.error = None;
+    }
+    let mode_key = item.mode_key.clone();
+    apply_mode(state, &mut item, mode_key, cancel).await?;
+
+    let (updated, alternatives) = (item.clone(), scored.alternatives);
+    db.run(move |db| {
+        db.retranscribe_history(&item)?;
+        db.set_alternatives(&item.id, &alternatives)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    log::info!("Re-transcribed history item {} with {}", updated.id, updated.stt_model);
+    Ok(updated)
+}
+
+/// Run a history item's transcript through a mode, updating its output
+///
+/// `cancel` aborts the LLM requests in flight.
+pub(crate) async fn apply_mode(
+    state: &SharedState,
+    item: &mut HistoryItem,
+    mode_key: String,
+    cancel: &crate::cancel::CancelToken,
+) -> Result<(), String> {
+    let state_guard = state.lock().await;
+    let mode = state_guard
+        .modes
+        .get(&mode_key)
+        .cloned()
+        .ok_or_else(|| "Mode not found".to_string())?;
+
+    let language = state_guard.settings.language.clone();
+    let typography = state_guard.settings.typography;
+    let ollama_url = state_guard.settings.ollama_url.clone();
+    let pacing = state_guard.settings.pacing.clone();
+    let output_command = state_guard.settings.output_command(&mode).map(str::to_string);
+    let command_timeout = state_guard.settings.hooks.timeout();
+    let api_key = state_guard.get_api_key(&mode.llm_provider).map_err(|e| e.to_string())?;
+    let pipeline = if mode.uses_pipeline() {
+        let pipeline = crate::pipeline::Pipeline::prepare(&mode.steps, |provider, model| {
+            state_guard.create_llm_chain(provider, model, &mode.llm_fallbacks)
+        })
+        .map_err(|e| e.to_string())?;
+        Some(pipeline)
+    } else {
+        None
+    };
+    drop(state_guard);
+
+    let mut usage = None;
+    let mut pipeline_llm = None;
+    let mut pipeline_cost = None;
+    let output = if let Some(pipeline) = pipeline {
+        // Step timings of reprocessing are not kept; the waterfall stays the dictation's
+        let mut trace = crate::latency::LatencyTrace::start("reprocess");
+        let result = pipeline
+            .run(
+                &item.transcript_raw,
+                &language,
+                pacing.keep_filler_words,
+                |template, text| pacing.apply_to_prompt(crate::modes::render_prompt(template, text, None, &language)),
+                cancel,
+                &mut trace,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        // As when a mode's single LLM fails, reprocessing fails rather than keep half a result
+        if let Some(step) = result.failed_step() {
+            return Err(format!("Pipeline {} failed: {}", step.name, step.error.as_deref().unwrap_or_default()));
+        }
+        if result.llm.is_some() {
+            usage = Some(result.usage);
+            pipeline_llm = result.llm;
+            pipeline_cost = result.cost_usd;
+        }
+        result.text
+    } else if mode.uses_llm() && !mode.prompt_template.is_empty() {
+        let provider = crate::providers::llm::create_llm_provider(
+            &mode.llm_provider,
+            &mode.llm_model,
+            api_key.as_deref(),
+            ollama_url,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let prompt = pacing.apply_to_prompt(crate::modes::render_prompt(
+            &mode.prompt_template,
+            &item.transcript_raw,
+            None,
+            &language,
+        ));
+
+        let completion = match &mode.json_schema {
+            Some(schema) => {
+                crate::providers::llm::complete_structured(provider.as_ref(), &prompt, schema, cancel).await
+            }
+            None => cancel.run(provider.complete_with_usage(&prompt)).await,
+        }
+        .map_err(|e| e.to_string())?;
+        usage = Some(completion.usage);
+        completion.text
+    } else if mode.post_processor() == crate::modes::PostProcessor::Rules {
+        crate::cleanup::clean(&item.transcript_raw, &language, pacing.keep_filler_words)
+    } else {
+        item.transcript_raw.clone()
+    };
+
+    let structured = mode.uses_llm() && mode.json_schema.is_some();
+    let output = if typography && !structured && !mode.verbatim {
+        crate::typography::apply(&output, &language)
+    } else {
+        output
+    };
+
+    // The target app is unknown when reprocessing, so only the mode's limit applies
+    let (output, truncated) = match mode.max_output_chars {
+        Some(max_chars) if !structured && !mode.verbatim && crate::limit::exceeds(&output, max_chars) => {
+            (crate::limit::truncate(&output, max_chars), true)
+        }
+        _ => (output, false),
+    };
+
+    // As when dictating, a failed output command keeps the text and is recorded
+    let mut command_error = None;
+    let output = match output_command {
+        Some(command) => {
+            let vars = crate::hooks::HookVars {
+                id: item.id.clone(),
+                mode: mode.key.clone(),
+                ..Default::default()
+            };
+            match crate::hooks::filter(&command, &output, &vars, command_timeout).await {
+                Ok(filtered) => filtered,
+                Err(e) => {
+                    log::warn!("Output command failed, keeping the output: {}", e);
+                    command_error = Some(crate::state::output_command_error(&e));
+                    output
+                }
+            }
+        }
+        None => output,
+    };
 
-    // Update history item
     item.mode_key = mode_key;
     item.output_final = output.clone();
-    item.llm_provider = if mode.ai_processing {
+    item.stats = crate::text_stats::TextStats::of(&output);
+    item.truncated = truncated;
+    // Transcripts of other modes had replacements applied, so are not verbatim
+    item.verbatim = mode.verbatim && item.verbatim;
+    item.llm_provider = if mode.uses_pipeline() {
+        pipeline_llm.as_ref().map(|(provider, _)| format!("{:?}", provider).to_lowercase())
+    } else if mode.uses_llm() {
         Some(format!("{:?}", mode.llm_provider).to_lowercase())
     } else {
         None
     };
-    item.llm_model = if mode.ai_processing {
+    item.llm_model = if mode.uses_pipeline() {
+        pipeline_llm.map(|(_, model)| model)
+    } else if mode.uses_llm() {
         Some(mode.llm_model.clone())
     } else {
         None
     };
+    item.prompt_tokens = usage.map(|u| u.prompt_tokens);
+    item.completion_tokens = usage.map(|u| u.completion_tokens);
+    item.cost_usd = if mode.uses_pipeline() {
+        pipeline_cost
+    } else {
+        usage.and_then(|u| crate::providers::llm::estimate_cost_usd(&mode.llm_provider, &mode.llm_model, &u))
+    };
+    // Reprocessing retries the LLM and output command, so only their failures are resolved by it
+    if item.error.as_ref().is_some_and(|e| matches!(e.stage, ErrorStage::Llm | ErrorStage::Output)) {
+        item.error = None;
+    }
+    if command_error.is_some() {
+        item.error = command_error;
+    }
 
-    let state_guard = state.lock().await;
-    let db = state_guard
-        .database
-        .as_ref()
-        .ok_or_else(|| "Database not initialized".to_string())?;
-    {
-        let db_guard = db.lock().unwrap();
-        db_guard.update_history(&item).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Replace a history item's output by hand
+#[tauri::command]
+pub async fn edit_history_item(
+    state: State<'_, SharedState>,
+    id: String,
+    output: String,
+) -> Result<HistoryItem, String> {
+    state
+        .lock()
+        .await
+        .ensure_writable("editing history")
+        .map_err(|e| e.to_string())?;
+    let db = database(&state).await?;
+
+    db.run(move |db| db.edit_history(&id, &output))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Get the earlier outputs of a history item, oldest first
+#[tauri::command]
+pub async fn get_revisions(state: State<'_, SharedState>, id: String) -> Result<Vec<HistoryRevision>, String> {
+    let db = database(&state).await?;
+
+    db.run(move |db| db.get_revisions(&id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Get other readings of a history item's uncertain segments, for one-click corrections
+///
+/// Dictating only notes which segments were uncertain; their other readings
+/// are sampled from the recording the first time they are asked for, in the
+/// background, and kept.
+#[tauri::command]
+pub async fn get_history_alternatives(
+    state: State<'_, SharedState>,
+    id: String,
+) -> Result<Vec<SegmentAlternatives>, String> {
+    let db = database(&state).await?;
+
+    let lookup = id.clone();
+    let segments = db
+        .run(move |db| db.get_alternatives(&lookup))
+        .await
+        .map_err(|e| e.to_string())?;
+    if segments.iter().all(|segment| !segment.alternatives.is_empty()) {
+        return Ok(segments);
     }
-    drop(state_guard);
 
-    update_tray_icon(&app_handle, RecordingStatus::Ready).map_err(|e| e.to_string())?;
+    let lookup = id.clone();
+    let item = db
+        .run(move |db| db.get_history_item(&lookup))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "History item not found".to_string())?;
+    let Some(path) = item
+        .audio_path
+        .clone()
+        .filter(|_| !item.summary_only)
+        .map(std::path::PathBuf::from)
+    else {
+        return Ok(Vec::new());
+    };
+    let language = state.lock().await.settings.language.clone();
 
-    Ok(output)
+    let segments = crate::priority::background(async move {
+        let samples = tokio::task::spawn_blocking(move || crate::audio_codec::load_audio(&path))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+        crate::providers::stt::whisper_cpp(&item.stt_model)
+            .await
+            .map_err(|e| e.to_string())?
+            .sample_alternatives(samples, Some(language), segments)
+            .await
+            .map_err(|e| e.to_string())
+    })
+    .await?;
+
+    let kept = segments.clone();
+    db.run(move |db| db.update_alternatives(&id, &kept))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(segments)
 }
 
-/// Delete a history item
+/// Move a history item to the trash
 #[tauri::command]
 pub async fn delete_history_item(state: State<'_, SharedState>, id: String) -> Result<(), String> {
-    let state = state.lock().await;
+    state
+        .lock()
+        .await
+        .ensure_writable("deleting history")
+        .map_err(|e| e.to_string())?;
+    let db = database(&state).await?;
 
-    let db = state
-        .database
-        .as_ref()
-        .ok_or_else(|| "Database not initialized".to_string())?;
+    // The audio stays until the item is purged, so it can be restored
+    db.run(move |db| db.delete_history(&id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Take a history item back out of the trash
+#[tauri::command]
+pub async fn restore_history_item(state: State<'_, SharedState>, id: String) -> Result<(), String> {
+    state
+        .lock()
+        .await
+        .ensure_writable("restoring history")
+        .map_err(|e| e.to_string())?;
+    let db = database(&state).await?;
+
+    db.run(move |db| db.restore_history(&id))
+        .await
+        .map_err(|e| e.to_string())
+}
 
-    let db_guard = db.lock().unwrap();
+/// Get history items in the trash
+#[tauri::command]
+pub async fn get_trash(
+    state: State<'_, SharedState>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<HistoryItem>, String> {
+    let db = database(&state).await?;
 
-    // Get item to find audio file
-    if let Some(item) = db_guard.get_history_item(&id).map_err(|e| e.to_string())? {
-        // Delete audio file if exists
-        if let Some(audio_path) = &item.audio_path {
+    db.run(move |db| db.get_trash(limit.unwrap_or(50), offset.unwrap_or(0)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Permanently delete one trashed item, or the whole trash
+#[tauri::command]
+pub async fn purge_history(state: State<'_, SharedState>, id: Option<String>) -> Result<(), String> {
+    state
+        .lock()
+        .await
+        .ensure_writable("purging history")
+        .map_err(|e| e.to_string())?;
+    let db = database(&state).await?;
+
+    db.run(move |db| {
+        for audio_path in db.purge_history(id.as_deref(), None)? {
             let _ = std::fs::remove_file(audio_path);
         }
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Get LLM token usage and estimated spend per month
+#[tauri::command]
+pub async fn get_monthly_spend(state: State<'_, SharedState>) -> Result<Vec<MonthlySpend>, String> {
+    let db = database(&state).await?;
+
+    db.run(|db| db.get_monthly_spend())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Suggest completions for the text before the cursor from frequently dictated phrases
+#[tauri::command]
+pub async fn suggest_phrases(
+    state: State<'_, SharedState>,
+    prefix: String,
+    limit: Option<usize>,
+) -> Result<Vec<PhraseSuggestion>, String> {
+    let db = database(&state).await?;
+
+    db.run(move |db| db.suggest_phrases(&prefix, limit.unwrap_or(5)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Whether this build can encrypt the history database
+#[tauri::command]
+pub async fn is_history_encryption_available() -> Result<bool, String> {
+    Ok(crate::database::ENCRYPTION_AVAILABLE)
+}
+
+/// Re-encrypt the history database with a new key
+#[tauri::command]
+pub async fn rotate_history_key(state: State<'_, SharedState>) -> Result<(), String> {
+    let state = state.lock().await;
+    state.rotate_history_key().map_err(|e| e.to_string())
+}
+
+/// Get dictation totals, speed and latency for a date range
+#[tauri::command]
+pub async fn get_history_stats(state: State<'_, SharedState>, range: Option<StatsRange>) -> Result<HistoryStats, String> {
+    let db = database(&state).await?;
+
+    db.run(move |db| db.get_stats(&range.unwrap_or_default()))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Get the candidates waiting in the multi-take picker, if any
+#[tauri::command]
+pub async fn get_pick_request(picker: State<'_, Picker>) -> Result<Option<PickRequest>, String> {
+    Ok(picker.current())
+}
+
+/// Choose a candidate in the multi-take picker (`None` keeps the first)
+#[tauri::command]
+pub async fn choose_candidate(picker: State<'_, Picker>, index: Option<usize>) -> Result<(), String> {
+    if picker.resolve(index) {
+        Ok(())
+    } else {
+        Err("No choice is pending".to_string())
+    }
+}
+
+/// Start a batch reprocessing or re-transcribing history items in the background
+///
+/// Without `ids`, the batch covers every history item (with a kept recording,
+/// for re-transcription), oldest first.
+#[tauri::command]
+pub async fn start_batch(
+    state: State<'_, SharedState>,
+    queue: State<'_, BatchQueue>,
+    app_handle: tauri::AppHandle,
+    task: BatchTask,
+    ids: Option<Vec<String>>,
+) -> Result<BatchJob, String> {
+    state
+        .lock()
+        .await
+        .ensure_writable("reprocessing history")
+        .map_err(|e| e.to_string())?;
+    let db = database(&state).await?;
+    let ids = match ids {
+        Some(ids) => ids,
+        None => {
+            let with_audio = matches!(task, BatchTask::Retranscribe { .. });
+            db.run(move |db| db.get_history_ids(with_audio))
+                .await
+                .map_err(|e| e.to_string())?
+        }
+    };
+    if ids.is_empty() {
+        return Err("No history items to process".to_string());
+    }
+
+    queue
+        .start(&app_handle, db, task, ids)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Get all batches with their progress, newest first
+#[tauri::command]
+pub async fn get_batches(state: State<'_, SharedState>) -> Result<Vec<BatchJob>, String> {
+    let db = database(&state).await?;
+
+    db.run(|db| db.get_batches()).await.map_err(|e| e.to_string())
+}
+
+/// Pause a batch after the items in progress
+#[tauri::command]
+pub async fn pause_batch(
+    state: State<'_, SharedState>,
+    queue: State<'_, BatchQueue>,
+    id: String,
+) -> Result<BatchJob, String> {
+    let db = database(&state).await?;
+    queue.pause(db, id).await.map_err(|e| e.to_string())
+}
+
+/// Resume a paused batch
+#[tauri::command]
+pub async fn resume_batch(
+    state: State<'_, SharedState>,
+    queue: State<'_, BatchQueue>,
+    app_handle: tauri::AppHandle,
+    id: String,
+) -> Result<BatchJob, String> {
+    state
+        .lock()
+        .await
+        .ensure_writable("reprocessing history")
+        .map_err(|e| e.to_string())?;
+    let db = database(&state).await?;
+    queue.resume(&app_handle, db, id).await.map_err(|e| e.to_string())
+}
+
+/// Cancel a batch, aborting the items in progress
+#[tauri::command]
+pub async fn cancel_batch(
+    state: State<'_, SharedState>,
+    queue: State<'_, BatchQueue>,
+    id: String,
+) -> Result<BatchJob, String> {
+    let db = database(&state).await?;
+    queue.cancel(db, id).await.map_err(|e| e.to_string())
+}
+
+/// Remove a batch that is not running from the list
+#[tauri::command]
+pub async fn delete_batch(state: State<'_, SharedState>, id: String) -> Result<(), String> {
+    let db = database(&state).await?;
+    db.run(move |db| {
+        match db.get_batch(&id)? {
+            Some(job) if job.status == BatchStatus::Running => Err(crate::error::AppError::Config(
+                "Pause or cancel the batch before removing it".to_string(),
+            )),
+            _ => db.delete_batch(&id),
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Local whisper models that are downloaded, largest first
+#[tauri::command]
+pub async fn get_downloaded_models() -> Result<Vec<String>, String> {
+    Ok(crate::providers::stt::downloaded_models())
+}
+
+/// Recommend a default model from multi-take picks (for one mode, or overall)
+#[tauri::command]
+pub async fn get_model_recommendation(
+    state: State<'_, SharedState>,
+    mode_key: Option<String>,
+) -> Result<Option<ModelRecommendation>, String> {
+    let db = database(&state).await?;
+
+    let stats = db
+        .run(move |db| db.get_model_pick_stats(mode_key.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(crate::picker::recommend(&stats))
+}
+
+/// Get the journal entry for a day (`YYYY-MM-DD`, today when omitted)
+#[tauri::command]
+pub async fn get_journal_entry(
+    state: State<'_, SharedState>,
+    date: Option<String>,
+) -> Result<Option<JournalEntry>, String> {
+    let db = database(&state).await?;
+
+    let date = date.unwrap_or_else(|| crate::journal::day_of(&chrono::Local::now()));
+    db.run(move |db| db.get_journal_entry(&date))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Get the style profile learned from corrected dictations, if any
+#[tauri::command]
+pub async fn get_style_profile(state: State<'_, SharedState>) -> Result<Option<StyleProfile>, String> {
+    let state = state.lock().await;
+    Ok(state.style.clone())
+}
+
+/// Re-derive the style profile from history now
+///
+/// Returns None until enough dictations have been corrected.
+#[tauri::command]
+pub async fn refresh_style_profile(state: State<'_, SharedState>) -> Result<Option<StyleProfile>, String> {
+    let db = database(&state).await?;
+    let language = {
+        let state = state.lock().await;
+        if !state.settings.learn_style {
+            return Err("Style learning is turned off".to_string());
+        }
+        state.settings.language.clone()
+    };
+
+    let profile = db
+        .run(move |db| crate::style::refresh(db, &language))
+        .await
+        .map_err(|e| e.to_string())?;
+    state.lock().await.style = profile.clone();
+    Ok(profile)
+}
+
+/// Write the replacement rules and glossary to a portable file
+#[tauri::command]
+pub async fn export_dictionary(state: State<'_, SharedState>, path: String) -> Result<(), String> {
+    let state = state.lock().await;
+    crate::dictionary::export(&state.settings.dictionary, std::path::Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Merge a dictionary file into the current one, reporting conflicting entries
+///
+/// With `dry_run` nothing is saved, so conflicts can be reviewed first.
+#[tauri::command]
+pub async fn import_dictionary(
+    state: State<'_, SharedState>,
+    path: String,
+    policy: Option<ConflictPolicy>,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let incoming = crate::dictionary::read(std::path::Path::new(&path)).map_err(|e| e.to_string())?;
+
+    let mut state = state.lock().await;
+    let mut dictionary = state.settings.dictionary.clone();
+    let report = dictionary.merge(incoming, policy.unwrap_or_default());
+    if !dry_run {
+        state.ensure_writable("changing settings").map_err(|e| e.to_string())?;
+        state.settings.dictionary = dictionary;
+        state.save_settings().map_err(|e| e.to_string())?;
     }
+    Ok(report)
+}
 
-    db_guard.delete_history(&id).map_err(|e| e.to_string())
+/// Dry-run render of a prompt template for the settings preview
+///
+/// Unset variables are filled with sample values.
+#[tauri::command]
+pub async fn preview_prompt_template(
+    template: String,
+    vars: Option<TemplateVars>,
+) -> Result<TemplatePreview, String> {
+    Ok(crate::template::preview(&template, vars))
+}
+
+/// Get the values of a pacing preset, for filling in the settings form
+#[tauri::command]
+pub async fn get_pacing_preset(preset: PacingPreset) -> Result<Pacing, String> {
+    Ok(Pacing::for_preset(preset))
 }
 
 /// Export format options
@@ -348,17 +1462,11 @@ pub async fn export_history_item(
     id: String,
     format: ExportFormat,
 ) -> Result<String, String> {
-    let state = state.lock().await;
+    let db = database(&state).await?;
 
-    let db = state
-        .database
-        .as_ref()
-        .ok_or_else(|| "Database not initialized".to_string())?;
-
-    let db_guard = db.lock().unwrap();
-
-    let item = db_guard
-        .get_history_item(&id)
+    let item = db
+        .run(move |db| db.get_history_item(&id))
+        .await
         .map_err(|e| e.to_string())?
         .ok_or_else(|| "History item not found".to_string())?;
 
@@ -409,10 +1517,152 @@ pub async fn update_settings(
     settings: Settings,
 ) -> Result<(), String> {
     let mut state = state.lock().await;
-    state.settings = settings;
+    state.ensure_writable("changing settings").map_err(|e| e.to_string())?;
+    if settings.encrypt_history && !state.settings.encrypt_history && !crate::database::ENCRYPTION_AVAILABLE {
+        return Err("This build can't encrypt history (it lacks the `encryption` feature)".to_string());
+    }
+    apply_settings(&mut state, settings)
+}
+
+/// Switch to new settings, updating the subsystems that keep their own copy, and save them
+fn apply_settings(state: &mut AppState, settings: Settings) -> Result<(), String> {
+    switch_settings(state, settings);
     state.save_settings().map_err(|e| e.to_string())
 }
 
+/// Switch to new settings, updating the subsystems that keep their own copy
+pub(crate) fn switch_settings(state: &mut AppState, settings: Settings) {
+    crate::paste::set_uinput_allowed(settings.allow_evdev_input);
+    crate::paste::set_clipboard_backend(settings.clipboard_backend);
+    crate::paste::set_ydotoold_managed(settings.manage_ydotoold);
+    crate::audio::set_audio_backend(settings.audio_backend);
+    crate::audio::set_audio_host(settings.audio_host);
+    crate::features::set_overrides(&settings.feature_flags);
+    #[cfg(feature = "pipewire")]
+    if crate::audio::use_pipewire() {
+        crate::pipewire_backend::spawn_device_watch(state.app_handle.clone());
+    }
+    crate::gpu::set_gpu_fallback(settings.gpu_fallback);
+    crate::providers::stt::set_beam_size(settings.whisper_beam_size);
+    crate::browser_tab::set_enabled(settings.capture_browser_url);
+    crate::shutdown::set_deadline(settings.shutdown_deadline_secs);
+    crate::retry::set_policy(settings.retry.clone());
+    if state.events.path() != settings.event_sink.as_deref().map(std::path::Path::new) {
+        state.events = crate::events::EventSink::new(settings.event_sink.as_deref());
+    }
+    // The profile is only kept while learning is on
+    if settings.learn_style != state.settings.learn_style {
+        state.style = None;
+        if let Some(db) = state.database.clone() {
+            let result = if settings.learn_style {
+                crate::style::refresh(&db, &settings.language).map(|profile| state.style = profile)
+            } else {
+                db.set_style_profile(None)
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to update the style profile: {}", e);
+            }
+        }
+    }
+    state.settings = settings;
+    state.update_pre_roll();
+}
+
+/// Write the settings and modes to one file, without secrets, for another machine
+#[tauri::command]
+pub async fn export_settings(state: State<'_, SharedState>, path: String) -> Result<(), String> {
+    let state = state.lock().await;
+    let bundle = crate::config_bundle::build(&state.settings, &state.modes);
+    crate::config_bundle::export(&bundle, std::path::Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Replace the settings and add or overwrite the modes from an exported file
+///
+/// This machine's tokens and API keys are kept; modes not in the file stay.
+#[tauri::command]
+pub async fn import_settings(
+    state: State<'_, SharedState>,
+    app_handle: tauri::AppHandle,
+    path: String,
+) -> Result<BundleImport, String> {
+    let mut bundle = crate::config_bundle::read(std::path::Path::new(&path)).map_err(|e| e.to_string())?;
+
+    let mut state = state.lock().await;
+    state.ensure_writable("changing settings").map_err(|e| e.to_string())?;
+    crate::config_bundle::prepare_modes(&mut bundle.modes, &state.modes).map_err(|e| e.to_string())?;
+    for mode in &bundle.modes {
+        crate::modes::save_mode(mode).await.map_err(|e| e.to_string())?;
+    }
+    crate::config_bundle::keep_secrets(&mut bundle.settings, &state.settings);
+    crate::config_bundle::keep_local(&mut bundle.settings, &state.settings);
+    apply_settings(&mut state, bundle.settings)?;
+    state.modes = crate::modes::load_modes().await.map_err(|e| e.to_string())?;
+    let active_mode_key = state.settings.active_mode_key.clone();
+    if state.set_active_mode(&active_mode_key).is_err() {
+        state.set_active_mode("voice_to_text").map_err(|e| e.to_string())?;
+    }
+    update_tray_menu(&app_handle, &state)
+        .await
+        .map_err(|e| e.to_string())?;
+    log::info!("Imported settings and {} modes from {}", bundle.modes.len(), path);
+
+    Ok(BundleImport {
+        modes: bundle.modes.into_iter().map(|mode| mode.key).collect(),
+        app_version: bundle.app_version,
+    })
+}
+
+/// Check whether read-only (lockdown) mode is active
+#[tauri::command]
+pub async fn is_read_only(state: State<'_, SharedState>) -> Result<bool, String> {
+    let state = state.lock().await;
+    Ok(state.is_read_only())
+}
+
+/// List the feature flags with their defaults for this release channel
+#[tauri::command]
+pub async fn get_feature_flags() -> Result<Vec<FeatureFlag>, String> {
+    Ok(crate::features::list())
+}
+
+/// Get the release notes not yet dismissed, to show after an update
+#[tauri::command]
+pub async fn get_whats_new() -> Result<WhatsNew, String> {
+    Ok(crate::changelog::whats_new())
+}
+
+/// Stop showing the release notes until the next update
+#[tauri::command]
+pub async fn dismiss_whats_new() -> Result<(), String> {
+    crate::changelog::dismiss().map_err(|e| e.to_string())
+}
+
+/// Report which optional integrations are active and opted in to
+#[tauri::command]
+pub async fn get_capabilities(state: State<'_, SharedState>) -> Result<Vec<Capability>, String> {
+    let state = state.lock().await;
+    Ok(crate::capabilities::introspect(&state.settings, &state.modes))
+}
+
+/// Report features degraded by missing optional binaries, with install hints
+#[tauri::command]
+pub async fn get_degradations(state: State<'_, SharedState>) -> Result<Vec<Degradation>, String> {
+    let state = state.lock().await;
+    Ok(crate::capabilities::degradations(&state.settings))
+}
+
+/// Get the latest watchdog health status
+#[tauri::command]
+pub async fn get_health(health: State<'_, HealthMonitor>) -> Result<HealthStatus, String> {
+    Ok(health.status())
+}
+
+/// Get the paste backend in use and ydotool diagnostics
+#[tauri::command]
+pub async fn get_paste_info() -> Result<PasteInfo, String> {
+    Ok(crate::paste::get_paste_info())
+}
+
 /// Save an API key
 #[tauri::command]
 pub async fn save_api_key(
@@ -438,17 +1688,21 @@ pub async fn has_api_key(state: State<'_, SharedState>, provider: String) -> Res
     Ok(state.has_api_key(&provider))
 }
 
-/// Test connection to a whisper server
+/// Test connection to a whisper server (OpenAI-compatible or whisper.cpp's)
 #[tauri::command]
 pub async fn test_whisper_connection(url: String) -> Result<bool, String> {
-    let client = reqwest::Client::new();
-    client
-        .get(format!("{}/v1/models", url))
-        .timeout(std::time::Duration::from_secs(5))
-        .send()
-        .await
-        .map(|r| r.status().is_success())
-        .map_err(|e| e.to_string())
+    let provider: Box<dyn crate::providers::SttProvider> = Box::new(
+        crate::providers::stt::OpenAiCompatibleSttProvider::self_hosted(url, String::new()),
+    );
+    Ok(provider.health_check().await.is_ok())
+}
+
+/// Test connection to a Wyoming server by asking it to describe itself
+#[tauri::command]
+pub async fn test_wyoming_connection(url: String) -> Result<bool, String> {
+    let provider: Box<dyn crate::providers::SttProvider> =
+        Box::new(crate::providers::wyoming::WyomingSttProvider::new(url, String::new()));
+    Ok(provider.health_check().await.is_ok())
 }
 
 /// Test connection to an Ollama server
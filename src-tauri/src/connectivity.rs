@@ -0,0 +1,314 @@
+//! Connectivity probe and local fallbacks while offline
+//!
+//! When the cloud APIs cannot be reached, a dictation in a mode configured
+//! with cloud providers would only fail after timeouts and retries. Instead
+//! it runs in degraded mode: speech goes to whisper.cpp with a model that
+//! is already downloaded, and the rewrite to a local Ollama model, or to
+//! rule-based cleanup when there is none. The probe runs while recording,
+//! so it adds no latency to the dictation, and goes through the configured
+//! proxy (`HTTPS_PROXY`), like the API requests themselves.
+
+use crate::modes::{LlmFallback, LlmProvider, Mode, PostProcessor, SttProvider};
+use crate::pipeline::PipelineStep;
+use crate::providers::stt::SttSelection;
+use serde::Serialize;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Endpoints of the cloud APIs; any answer from either counts as online
+const PROBE_URLS: [&str; 2] = ["https://api.openai.com/", "https://api.anthropic.com/"];
+
+/// Wait for a connection before counting a host unreachable
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a probe result is trusted
+const PROBE_MAX_AGE: Duration = Duration::from_secs(15);
+
+/// Latest probe result and when it was taken
+static LAST_PROBE: Mutex<Option<(Instant, bool)>> = Mutex::new(None);
+
+/// Local stand-ins used for a dictation while offline
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Fallback {
+    pub mode_key: String,
+    /// whisper.cpp model used instead of the mode's cloud STT
+    pub stt: Option<SttSelection>,
+    /// Local LLM used instead of the mode's cloud one
+    pub llm: Option<LlmFallback>,
+    /// The cloud LLM was replaced by rule-based cleanup
+    pub rules: bool,
+}
+
+impl Fallback {
+    /// Short description for logs, e.g. "whisper.cpp base.en, rule-based cleanup"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(stt) = &self.stt {
+            parts.push(format!("whisper.cpp {}", stt.model));
+        }
+        if let Some(llm) = &self.llm {
+            parts.push(format!("Ollama {}", llm.model));
+        }
+        if self.rules {
+            parts.push("rule-based cleanup".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+fn is_cloud_stt(provider: &SttProvider) -> bool {
+    matches!(provider, SttProvider::OpenAI | SttProvider::Groq | SttProvider::Deepgram)
+}
+
+fn is_cloud_llm(provider: &LlmProvider) -> bool {
+    matches!(provider, LlmProvider::OpenAI | LlmProvider::Anthropic)
+}
+
+/// Whether a dictation in `mode` with `stt` needs the internet
+///
+/// Whisper servers and Ollama are usually on the local network and are
+/// not affected by losing the internet.
+pub fn uses_cloud(mode: &Mode, stt: &SttSelection) -> bool {
+    is_cloud_stt(&stt.provider) || mode.llm_providers().into_iter().any(is_cloud_llm)
+}
+
+/// Downloaded whisper.cpp models in the order to fall back to them
+///
+/// `downloaded` is largest first, as `downloaded_models` lists them. The
+/// `configured` default comes first, then the fastest; English-only models
+/// go last unless dictating in English.
+pub fn offline_models(downloaded: &[String], configured: &str, language: &str) -> Vec<String> {
+    let mut models: Vec<String> = downloaded.iter().rev().cloned().collect();
+    models.sort_by_key(|model| (model != configured, model.ends_with(".en") && language != "en"));
+    models
+}
+
+/// A copy of `mode` using only local providers, and what was replaced
+///
+/// `downloaded` lists whisper.cpp models on disk, preferred first (see
+/// `offline_models`); without any, the cloud STT is kept since there is
+/// nothing to fall back to.
+/// `offline_llm` is an Ollama model for modes without a local fallback.
+pub fn local_mode(
+    mode: &Mode,
+    stt: &SttSelection,
+    downloaded: &[String],
+    offline_llm: Option<&str>,
+) -> Option<(Mode, Fallback)> {
+    let mut local = mode.clone();
+    let mut fallback = Fallback {
+        mode_key: mode.key.clone(),
+        stt: None,
+        llm: None,
+        rules: false,
+    };
+
+    if is_cloud_stt(&stt.provider) {
+        fallback.stt = downloaded.first().map(|model| SttSelection {
+            provider: SttProvider::WhisperCpp,
+            model: model.clone(),
+        });
+    }
+
+    if mode.uses_llm() && is_cloud_llm(&mode.llm_provider) {
+        local.llm_fallbacks.retain(|f| !is_cloud_llm(&f.provider));
+        local.compare_models.retain(|f| !is_cloud_llm(&f.provider));
+        let ollama = local
+            .llm_fallbacks
+            .iter()
+            .position(|f| f.provider == LlmProvider::Ollama)
+            .map(|i| local.llm_fallbacks.remove(i))
+            .or_else(|| {
+                offline_llm.map(|model| LlmFallback {
+                    provider: LlmProvider::Ollama,
+                    model: model.to_string(),
+                })
+            });
+        match ollama {
+            Some(llm) => {
+                local.llm_provider = llm.provider.clone();
+                local.llm_model = llm.model.clone();
+                fallback.llm = Some(llm);
+            }
+            None => {
+                local.post_processor = Some(PostProcessor::Rules);
+                fallback.rules = true;
+            }
+        }
+    }
+
+    // Cloud steps of a pipeline run on the offline model, or become the local cleanup
+    if mode.uses_pipeline() && mode.llm_providers().into_iter().any(is_cloud_llm) {
+        let ollama = offline_llm.map(|model| LlmFallback {
+            provider: LlmProvider::Ollama,
+            model: model.to_string(),
+        });
+        for step in &mut local.steps {
+            if let PipelineStep::Llm { provider, model, .. } = step {
+                if !is_cloud_llm(provider) {
+                    continue;
+                }
+                match &ollama {
+                    Some(llm) => {
+                        *provider = llm.provider.clone();
+                        *model = llm.model.clone();
+                    }
+                    None => *step = PipelineStep::Cleanup,
+                }
+            }
+        }
+        fallback.rules = ollama.is_none();
+        fallback.llm = ollama;
+    }
+
+    if fallback.stt.is_none() && fallback.llm.is_none() && !fallback.rules {
+        return None;
+    }
+    if let Some(stt) = &fallback.stt {
+        local.stt_provider = stt.provider.clone();
+        local.stt_model = stt.model.clone();
+    }
+    Some((local, fallback))
+}
+
+async fn reachable(client: &reqwest::Client, url: &str) -> bool {
+    client.head(url).send().await.is_ok()
+}
+
+/// Probe the cloud APIs now, remembering the result
+pub async fn probe() -> bool {
+    let Ok(client) = reqwest::Client::builder().timeout(PROBE_TIMEOUT).build() else {
+        return true;
+    };
+    let (openai, anthropic) = tokio::join!(reachable(&client, PROBE_URLS[0]), reachable(&client, PROBE_URLS[1]));
+    let online = openai || anthropic;
+    let previous = LAST_PROBE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace((Instant::now(), online));
+    if previous.is_some_and(|(_, was_online)| was_online != online) {
+        log::info!("Network {}", if online { "is back" } else { "is down" });
+    }
+    online
+}
+
+/// Start a probe in the background unless a recent result is known
+pub fn probe_ahead() {
+    if fresh().is_none() {
+        tauri::async_runtime::spawn(probe());
+    }
+}
+
+fn fresh() -> Option<bool> {
+    LAST_PROBE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .filter(|(at, _)| at.elapsed() < PROBE_MAX_AGE)
+        .map(|(_, online)| online)
+}
+
+/// Whether the cloud APIs are reachable, probing if the last result is stale
+pub async fn is_online() -> bool {
+    match fresh() {
+        Some(online) => online,
+        None => probe().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cloud_mode() -> Mode {
+        Mode {
+            key: "email".to_string(),
+            stt_provider: SttProvider::OpenAI,
+            stt_model: "whisper-1".to_string(),
+            ai_processing: true,
+            llm_provider: LlmProvider::OpenAI,
+            llm_model: "gpt-4o-mini".to_string(),
+            prompt_template: "Rewrite: {{transcript}}".to_string(),
+            ..Mode::default()
+        }
+    }
+
+    fn selection(mode: &Mode) -> SttSelection {
+        SttSelection {
+            provider: mode.stt_provider.clone(),
+            model: mode.stt_model.clone(),
+        }
+    }
+
+    #[test]
+    fn test_offline_models() {
+        let downloaded = vec!["large-v3".to_string(), "small.en".to_string(), "base".to_string()];
+        assert_eq!(offline_models(&downloaded, "", "en"), vec!["base", "small.en", "large-v3"]);
+        assert_eq!(offline_models(&downloaded, "large-v3", "en"), vec!["large-v3", "base", "small.en"]);
+        assert_eq!(offline_models(&downloaded, "", "de"), vec!["base", "large-v3", "small.en"]);
+    }
+
+    #[test]
+    fn test_local_mode_replaces_cloud_providers() {
+        let mode = cloud_mode();
+        let downloaded = vec!["small.en".to_string()];
+
+        let (local, fallback) = local_mode(&mode, &selection(&mode), &downloaded, None).unwrap();
+        assert_eq!(local.stt_provider, SttProvider::WhisperCpp);
+        assert_eq!(local.stt_model, "small.en");
+        assert!(!local.uses_llm());
+        assert!(fallback.rules);
+        assert_eq!(fallback.describe(), "whisper.cpp small.en, rule-based cleanup");
+
+        // A local fallback of the mode wins over the offline model
+        let mut with_ollama = cloud_mode();
+        with_ollama.llm_fallbacks = vec![
+            LlmFallback {
+                provider: LlmProvider::Anthropic,
+                model: "claude-3-5-haiku-latest".to_string(),
+            },
+            LlmFallback {
+                provider: LlmProvider::Ollama,
+                model: "qwen2.5".to_string(),
+            },
+        ];
+        let (local, fallback) = local_mode(&with_ollama, &selection(&with_ollama), &[], Some("llama3.2")).unwrap();
+        assert_eq!(local.llm_provider, LlmProvider::Ollama);
+        assert_eq!(local.llm_model, "qwen2.5");
+        assert!(local.llm_fallbacks.is_empty());
+        // No model on disk, so the cloud STT stays
+        assert_eq!(local.stt_provider, SttProvider::OpenAI);
+        assert_eq!(fallback.stt, None);
+    }
+
+    #[test]
+    fn test_local_pipeline() {
+        let mode = Mode {
+            steps: vec![
+                PipelineStep::Cleanup,
+                PipelineStep::Llm {
+                    provider: LlmProvider::Anthropic,
+                    model: "claude-3-5-haiku-latest".to_string(),
+                    prompt: "Fix: {{transcript}}".to_string(),
+                },
+            ],
+            ..Mode::default()
+        };
+        assert!(uses_cloud(&mode, &selection(&mode)));
+
+        let (local, fallback) = local_mode(&mode, &selection(&mode), &[], None).unwrap();
+        assert_eq!(local.steps, vec![PipelineStep::Cleanup, PipelineStep::Cleanup]);
+        assert!(fallback.rules);
+        assert!(!uses_cloud(&local, &selection(&local)));
+    }
+
+    #[test]
+    fn test_local_modes_are_unchanged() {
+        let mode = Mode {
+            stt_provider: SttProvider::WhisperCpp,
+            llm_provider: LlmProvider::Ollama,
+            ..cloud_mode()
+        };
+        assert!(!uses_cloud(&mode, &selection(&mode)));
+        assert!(local_mode(&mode, &selection(&mode), &["base.en".to_string()], None).is_none());
+    }
+}
@@ -0,0 +1,303 @@
+//! Control socket for `linwhisperctl`
+//!
+//! A Unix domain socket in the user's runtime directory that shell scripts and
+//! window manager bindings can drive WhisperTray through, without the HTTP
+//! API's port and token: the socket is bound inside a directory only the user
+//! can enter, and connections from other users are dropped. Each connection
+//! sends one JSON request line and gets one JSON response line back, e.g.
+//! `{"command":"mode","key":"email"}` and `{"Ok":"Switched to Email"}`. The
+//! `linwhisperctl` binary wraps this, so `linwhisperctl toggle` works like
+//! pressing the hotkey.
+
+use crate::error::{AppError, Result};
+use crate::state::SharedState;
+use serde::{Deserialize, Serialize};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+
+/// Longest request line accepted
+const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+
+/// What a client asks for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Request {
+    /// Recording status, e.g. "recording"
+    Status,
+    Start,
+    /// Stop, process and paste; answers with the output
+    Stop,
+    /// Start or stop, like the hotkey
+    Toggle,
+    /// Cancel the recording or its processing
+    Cancel,
+    /// Mode keys and names, the active one marked with `*`
+    Modes,
+    /// Switch the active mode
+    Mode { key: String },
+    /// The latest dictation's output, also put on the clipboard if `copy`
+    Last {
+        #[serde(default)]
+        copy: bool,
+    },
+}
+
+/// The reply: output text, or why the request failed
+pub type Response = std::result::Result<String, String>;
+
+/// Where the socket lives: `$XDG_RUNTIME_DIR`, or a per-user directory in the temp dir
+pub fn socket_path() -> PathBuf {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => std::env::temp_dir().join(format!("whispertray-{}", current_uid())),
+    };
+    dir.join("whispertray.sock")
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: getuid cannot fail and touches no memory
+    unsafe { libc::getuid() }
+}
+
+/// Create `dir` if needed and check only the current user can enter it
+///
+/// The temp dir is shared, so another user may have made the directory first
+/// or put a symlink there.
+fn ensure_private_dir(dir: &Path) -> Result<()> {
+    if !dir.exists() {
+        std::fs::DirBuilder::new().mode(0o700).create(dir)?;
+    }
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != current_uid() || metadata.mode() & 0o077 != 0 {
+        return Err(AppError::Config(format!(
+            "{} is not a private directory; not opening the control socket there",
+            dir.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Parse `linwhisperctl` arguments into a request
+pub fn parse_args(args: &[String]) -> std::result::Result<Request, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["status"] => Ok(Request::Status),
+        ["start"] => Ok(Request::Start),
+        ["stop"] => Ok(Request::Stop),
+        ["toggle"] => Ok(Request::Toggle),
+        ["cancel"] => Ok(Request::Cancel),
+        ["modes"] | ["mode"] => Ok(Request::Modes),
+        ["mode", key] => Ok(Request::Mode { key: key.to_string() }),
+        ["last"] => Ok(Request::Last { copy: false }),
+        ["last", "--copy"] => Ok(Request::Last { copy: true }),
+        [] => Err("No command given".to_string()),
+        _ => Err(format!("Unknown command: {}", args.join(" "))),
+    }
+}
+
+/// Listen on the control socket until the app exits
+pub async fn serve(app: AppHandle) -> Result<()> {
+    let path = socket_path();
+    if let Some(dir) = path.parent() {
+        ensure_private_dir(dir)?;
+    }
+    if path.exists() {
+        // A socket nobody answers on is left over from a crash
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            return Err(AppError::Config(format!(
+                "Another instance is listening on {}",
+                path.display()
+            )));
+        }
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    log::info!("Control socket listening on {}", path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        match stream.peer_cred() {
+            Ok(cred) if cred.uid() == current_uid() => {}
+            Ok(cred) => {
+                log::warn!("Control socket: dropping a connection from uid {}", cred.uid());
+                continue;
+            }
+            Err(e) => {
+                log::warn!("Control socket: cannot identify a client: {}", e);
+                continue;
+            }
+        }
+        let app = app.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &app).await {
+                log::warn!("Control socket request failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle(stream: UnixStream, app: &AppHandle) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    BufReader::new(reader)
+        .take(MAX_REQUEST_BYTES)
+        .read_line(&mut line)
+        .await?;
+
+    let response: Response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => run(request, app).await,
+        Err(e) => Err(format!("Invalid request: {}", e)),
+    };
+    let mut reply = serde_json::to_string(&response)?;
+    reply.push('\n');
+    writer.write_all(reply.as_bytes()).await?;
+    Ok(())
+}
+
+async fn run(request: Request, app: &AppHandle) -> Response {
+    let state = app
+        .try_state::<SharedState>()
+        .ok_or_else(|| "WhisperTray is still starting".to_string())?
+        .inner()
+        .clone();
+
+    match request {
+        Request::Status => {
+            let status = state.lock().await.status;
+            Ok(serde_json::to_value(status)
+                .ok()
+                .and_then(|status| status.as_str().map(str::to_string))
+                .unwrap_or_default())
+        }
+        Request::Start => {
+            crate::hotkey::start_recording(app, &state)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(String::new())
+        }
+        Request::Stop => {
+            if !state.lock().await.is_recording() {
+                return Err("Not recording".to_string());
+            }
+            crate::hotkey::stop_and_process(app, &state)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        Request::Toggle => {
+            let is_recording = state.lock().await.is_recording();
+            if is_recording {
+                crate::hotkey::stop_and_process(app, &state)
+                    .await
+                    .map_err(|e| e.to_string())
+            } else {
+                crate::hotkey::start_recording(app, &state)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(String::new())
+            }
+        }
+        Request::Cancel => {
+            crate::cancel::cancel_pipeline(app).await;
+            Ok(String::new())
+        }
+        Request::Modes => {
+            let state = state.lock().await;
+            let mut modes: Vec<_> = state.modes.iter().collect();
+            modes.sort_by_key(|(key, _)| key.as_str());
+            Ok(modes
+                .into_iter()
+                .map(|(key, mode)| {
+                    let marker = if *key == state.active_mode_key { "*" } else { " " };
+                    format!("{} {}\t{}", marker, key, mode.name)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Request::Mode { key } => {
+            let mut state = state.lock().await;
+            state.set_active_mode(&key).map_err(|e| e.to_string())?;
+            crate::tray::update_tray_menu(app, &state)
+                .await
+                .map_err(|e| e.to_string())?;
+            let name = state.get_active_mode().map(|mode| mode.name.clone());
+            Ok(format!("Switched to {}", name.unwrap_or(key)))
+        }
+        Request::Last { copy } => {
+            let db = state
+                .lock()
+                .await
+                .database
+                .clone()
+                .ok_or_else(|| "Database not initialized".to_string())?;
+            let items = db
+                .run(|db| db.get_history(1, 0))
+                .await
+                .map_err(|e| e.to_string())?;
+            let text = items
+                .into_iter()
+                .next()
+                .map(|item| item.output_final)
+                .ok_or_else(|| "There are no dictations yet".to_string())?;
+            if copy {
+                crate::paste::set_clipboard_text(&text).map_err(|e| e.to_string())?;
+            }
+            Ok(text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&args("toggle")), Ok(Request::Toggle));
+        assert_eq!(
+            parse_args(&args("mode email")),
+            Ok(Request::Mode {
+                key: "email".to_string()
+            })
+        );
+        assert_eq!(parse_args(&args("last --copy")), Ok(Request::Last { copy: true }));
+        assert!(parse_args(&args("last --paste")).is_err());
+        assert!(parse_args(&[]).is_err());
+    }
+
+    #[test]
+    fn test_request_wire_format() {
+        let request = Request::Mode {
+            key: "email".to_string(),
+        };
+        let line = serde_json::to_string(&request).unwrap();
+        assert_eq!(line, r#"{"command":"mode","key":"email"}"#);
+        assert_eq!(serde_json::from_str::<Request>(r#"{"command":"last"}"#).unwrap(), Request::Last { copy: false });
+
+        let response: Response = Err("Not recording".to_string());
+        assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"Err":"Not recording"}"#);
+    }
+
+    #[test]
+    fn test_ensure_private_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let private = dir.path().join("whispertray-test");
+        ensure_private_dir(&private).unwrap();
+        assert_eq!(std::fs::metadata(&private).unwrap().mode() & 0o777, 0o700);
+
+        std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(ensure_private_dir(&private).is_err());
+
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&private, &link).unwrap();
+        assert!(ensure_private_dir(&link).is_err());
+    }
+}
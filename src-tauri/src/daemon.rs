@@ -0,0 +1,71 @@
+//! Headless daemon mode
+//!
+//! `whispertray daemon` runs the recorder, providers, hotkey, control socket
+//! and HTTP API without a tray icon or any webview window, for window manager
+//! setups that drive everything through `linwhisperctl` and keybindings. It
+//! is meant to run as a systemd user service; `whispertray daemon
+//! --print-service` prints a unit for the running executable.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+/// Set whether the app runs without a tray icon and windows
+pub fn set_headless(headless: bool) {
+    HEADLESS.store(headless, Ordering::SeqCst);
+}
+
+/// Whether the app runs without a tray icon and windows
+pub fn is_headless() -> bool {
+    HEADLESS.load(Ordering::SeqCst)
+}
+
+/// The executable a service should start: the AppImage itself when run from one
+pub fn service_executable() -> std::io::Result<std::path::PathBuf> {
+    match std::env::var_os("APPIMAGE") {
+        Some(appimage) => Ok(appimage.into()),
+        None => std::env::current_exe(),
+    }
+}
+
+/// A systemd user unit that starts `exe` as a daemon with the graphical session
+pub fn service_unit(exe: &Path) -> String {
+    let exe = exe.display().to_string();
+    let exe = if exe.contains(char::is_whitespace) {
+        format!("\"{}\"", exe)
+    } else {
+        exe
+    };
+    format!(
+        "[Unit]
+Description=WhisperTray dictation daemon
+PartOf=graphical-session.target
+After=graphical-session.target
+
+[Service]
+ExecStart={} daemon
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy=graphical-session.target
+",
+        exe
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_unit() {
+        let unit = service_unit(Path::new("/usr/bin/whispertray"));
+        assert!(unit.contains("\nExecStart=/usr/bin/whispertray daemon\n"));
+        assert!(unit.contains("WantedBy=graphical-session.target"));
+
+        let unit = service_unit(Path::new("/home/me/Apps/Whisper Tray.AppImage"));
+        assert!(unit.contains("ExecStart=\"/home/me/Apps/Whisper Tray.AppImage\" daemon"));
+    }
+}
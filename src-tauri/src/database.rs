@@ -1,10 +1,19 @@
 //! SQLite database for history storage
 
-use crate::error::{AppError, Result};
+use crate::audio::Bookmark;
+use crate::error::{AppError, ErrorRecord, ErrorStage, Result};
+use crate::latency::LatencySpan;
+use crate::phrases::{self, PhraseSuggestion};
+use crate::providers::stt::{SegmentAlternatives, SttSelection};
+use crate::style::StyleProfile;
+use crate::text_stats::TextStats;
 use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 
 /// History item stored in the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,31 +29,550 @@ pub struct HistoryItem {
     pub llm_provider: Option<String>,
     pub llm_model: Option<String>,
     pub duration_ms: u64,
-    pub error: Option<String>,
+    /// Why the dictation failed; failed items keep their audio for retries
+    pub error: Option<ErrorRecord>,
+    /// Per-stage latency waterfall for this dictation
+    #[serde(default)]
+    pub latency: Vec<LatencySpan>,
+    /// Bookmarks dropped during the recording
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    /// LLM prompt tokens, when AI processing ran
+    #[serde(default)]
+    pub prompt_tokens: Option<u32>,
+    /// LLM completion tokens, when AI processing ran
+    #[serde(default)]
+    pub completion_tokens: Option<u32>,
+    /// Estimated LLM cost in USD, when the model's pricing is known
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+    /// Word count, character count and reading time of `output_final`
+    #[serde(flatten)]
+    pub stats: TextStats,
+    /// Output was shortened or cut to fit a mode or app length limit
+    #[serde(default)]
+    pub truncated: bool,
+    /// Class of the window focused when recording started
+    #[serde(default)]
+    pub window_class: Option<String>,
+    /// Title of the window focused when recording started
+    #[serde(default)]
+    pub window_title: Option<String>,
+    /// Estimated LLM cost avoided by inserting a confident transcript as is
+    #[serde(default)]
+    pub cost_saved_usd: Option<f64>,
+    /// When the item was moved to the trash
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Processed by local fallbacks because the network was down
+    #[serde(default)]
+    pub degraded: bool,
+    /// Dictated in a strict verbatim mode, output unprocessed
+    #[serde(default)]
+    pub verbatim: bool,
+    /// Only the LLM output was kept; the transcript and recording are gone
+    #[serde(default)]
+    pub summary_only: bool,
+    /// Browser tab the dictation was made in
+    #[serde(default)]
+    pub source_url: Option<String>,
+    /// Too much of the recording was at full scale, which transcribes badly
+    #[serde(default)]
+    pub clipped: bool,
+}
+
+/// LLM usage and spend for one calendar month
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MonthlySpend {
+    /// Month as `YYYY-MM` (UTC)
+    pub month: String,
+    pub cost_usd: f64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Number of dictations that used the LLM
+    pub items: u64,
+    /// Estimated spend avoided by skipping the LLM for confident transcripts
+    #[serde(default)]
+    pub saved_usd: f64,
+    /// Number of dictations that skipped the LLM
+    #[serde(default)]
+    pub skipped: u64,
+}
+
+/// Aggregated journal text for one local calendar day
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    /// Day as `YYYY-MM-DD` (local time)
+    pub date: String,
+    /// Dictations for the day, one timestamped line each
+    pub content: String,
+    pub entry_count: u32,
+    /// End-of-day LLM summary, once generated
+    pub summary: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// How an earlier output of a history item was replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionSource {
+    Edit,
+    Reprocess,
+    /// Transcribed again, possibly with another STT model
+    Retranscribe,
+}
+
+impl RevisionSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RevisionSource::Edit => "edit",
+            RevisionSource::Reprocess => "reprocess",
+            RevisionSource::Retranscribe => "retranscribe",
+        }
+    }
+}
+
+/// Earlier output of a history item, kept when it was edited or reprocessed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryRevision {
+    /// 1 for the original output, counting up
+    pub revision: u32,
+    pub output_final: String,
+    /// Mode and LLM that produced this output
+    pub mode_key: String,
+    pub llm_provider: Option<String>,
+    pub llm_model: Option<String>,
+    pub replaced_at: DateTime<Utc>,
+    pub replaced_by: RevisionSource,
+    /// Transcript and STT model replaced along with the output, for re-transcriptions
+    pub transcript_raw: Option<String>,
+    pub stt_provider: Option<String>,
+    pub stt_model: Option<String>,
+}
+
+/// Work a batch does to each of its history items
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BatchTask {
+    /// Run a mode on the kept transcript again
+    Reprocess { mode_key: String },
+    /// Transcribe the kept recording again, then run the item's mode
+    Retranscribe { stt: SttSelection },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+    Running,
+    Paused,
+    Cancelled,
+    Done,
+}
+
+impl BatchStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BatchStatus::Running => "running",
+            BatchStatus::Paused => "paused",
+            BatchStatus::Cancelled => "cancelled",
+            BatchStatus::Done => "done",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "paused" => BatchStatus::Paused,
+            "cancelled" => BatchStatus::Cancelled,
+            "done" => BatchStatus::Done,
+            _ => BatchStatus::Running,
+        }
+    }
+}
+
+/// A batch of history items being reprocessed, with its progress
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchJob {
+    pub id: String,
+    pub task: BatchTask,
+    pub status: BatchStatus,
+    pub created_at: DateTime<Utc>,
+    pub total: usize,
+    /// Items finished, failed ones included
+    pub done: usize,
+    pub failed: usize,
+}
+
+/// How often one model's output was chosen when shown in the multi-take picker
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelPickStats {
+    pub provider: String,
+    pub model: String,
+    /// Comparisons the model took part in
+    pub shown: u64,
+    /// Comparisons its output won
+    pub chosen: u64,
+}
+
+/// Date range for history statistics; open ends are unbounded
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsRange {
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Typing speed assumed when estimating the time dictation saved
+const TYPING_WPM: f64 = 40.0;
+
+/// Dictations whose transcription alternatives are kept; older ones lose theirs
+const MAX_ALTERNATIVE_ITEMS: i64 = 200;
+
+/// Dictation totals for one mode or provider
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GroupStats {
+    pub key: String,
+    pub recordings: u64,
+    pub audio_minutes: f64,
+    pub words: u64,
+    /// Output words per minute of audio
+    pub words_per_minute: Option<f64>,
+}
+
+/// Dictation totals over a date range, for the stats dashboard
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryStats {
+    pub recordings: u64,
+    pub audio_minutes: f64,
+    /// Words of final output
+    pub words: u64,
+    /// Mean time from hotkey press to insertion
+    pub avg_latency_ms: Option<f64>,
+    pub words_per_minute: Option<f64>,
+    /// Time typing the output would have taken at 40 WPM, less the time spent dictating
+    pub time_saved_minutes: f64,
+    pub by_mode: Vec<GroupStats>,
+    /// Grouped by STT provider
+    pub by_provider: Vec<GroupStats>,
+}
+
+fn words_per_minute(words: u64, minutes: f64) -> Option<f64> {
+    (minutes > 0.0).then(|| words as f64 / minutes)
+}
+
+/// Filter shared by the stats queries: successful dictations within ?1..?2
+const STATS_FILTER: &str = "error IS NULL AND deleted_at IS NULL
+    AND (?1 IS NULL OR created_at >= ?1)
+    AND (?2 IS NULL OR created_at < ?2)";
+
+/// Columns selected for every history query, in `row_to_history_item` order
+const HISTORY_COLUMNS: &str = "id, created_at, mode_key, audio_path, transcript_raw, output_final,
+    stt_provider, stt_model, llm_provider, llm_model, duration_ms, error, latency_json,
+    bookmarks_json, prompt_tokens, completion_tokens, cost_usd,
+    word_count, char_count, reading_time_secs, truncated, window_class, window_title, cost_saved_usd,
+    deleted_at, degraded, verbatim, summary_only, source_url, clipped";
+
+/// Map a row selected with `HISTORY_COLUMNS` to a history item
+fn row_to_history_item(row: &rusqlite::Row) -> rusqlite::Result<HistoryItem> {
+    let output_final: String = row.get(5)?;
+
+    // Rows saved before stats were stored get them computed on read
+    let stats = match (row.get(17)?, row.get(18)?, row.get(19)?) {
+        (Some(word_count), Some(char_count), Some(reading_time_secs)) => TextStats {
+            word_count,
+            char_count,
+            reading_time_secs,
+        },
+        _ => TextStats::of(&output_final),
+    };
+
+    Ok(HistoryItem {
+        id: row.get(0)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(1)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        mode_key: row.get(2)?,
+        audio_path: row.get(3)?,
+        transcript_raw: row.get(4)?,
+        output_final,
+        stt_provider: row.get(6)?,
+        stt_model: row.get(7)?,
+        llm_provider: row.get(8)?,
+        llm_model: row.get(9)?,
+        duration_ms: row.get::<_, i64>(10)? as u64,
+        error: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        latency: row
+            .get::<_, Option<String>>(12)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        bookmarks: row
+            .get::<_, Option<String>>(13)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        prompt_tokens: row.get(14)?,
+        completion_tokens: row.get(15)?,
+        cost_usd: row.get(16)?,
+        stats,
+        truncated: row.get::<_, Option<bool>>(20)?.unwrap_or(false),
+        window_class: row.get(21)?,
+        window_title: row.get(22)?,
+        cost_saved_usd: row.get(23)?,
+        deleted_at: row
+            .get::<_, Option<String>>(24)?
+            .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+            .map(|at| at.with_timezone(&Utc)),
+        degraded: row.get::<_, Option<bool>>(25)?.unwrap_or(false),
+        verbatim: row.get::<_, Option<bool>>(26)?.unwrap_or(false),
+        summary_only: row.get::<_, Option<bool>>(27)?.unwrap_or(false),
+        source_url: row.get(28)?,
+        clipped: row.get::<_, Option<bool>>(29)?.unwrap_or(false),
+    })
+}
+
+/// Map a journal_entries row to a journal entry
+fn row_to_journal_entry(row: &rusqlite::Row) -> rusqlite::Result<JournalEntry> {
+    Ok(JournalEntry {
+        date: row.get(0)?,
+        content: row.get(1)?,
+        entry_count: row.get(2)?,
+        summary: row.get(3)?,
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+/// First bytes of every unencrypted SQLite file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Whether the database at `path` exists and is encrypted
+///
+/// SQLCipher encrypts the whole file, header included, so anything that
+/// does not start with the SQLite header is taken to be encrypted.
+pub fn is_encrypted(path: &Path) -> bool {
+    use std::io::Read;
+    let mut header = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| header != SQLITE_HEADER)
+}
+
+/// Whether this build can encrypt the history database
+pub const ENCRYPTION_AVAILABLE: bool = cfg!(feature = "encryption");
+
+/// Whether this build's SQLite is SQLCipher
+fn cipher_available(conn: &Connection) -> bool {
+    conn.query_row("PRAGMA cipher_version", [], |row| row.get::<_, String>(0))
+        .is_ok()
+}
+
+fn require_cipher(conn: &Connection) -> Result<()> {
+    if cipher_available(conn) {
+        Ok(())
+    } else {
+        Err(AppError::Config(
+            "History encryption needs a build with the `encryption` feature".to_string(),
+        ))
+    }
+}
+
+/// Encrypt a plaintext database file in place
+///
+/// The data is exported to a new encrypted file that then replaces the
+/// original, so a failure part way leaves the plaintext file untouched.
+pub fn encrypt_file(path: &Path, key: &str) -> Result<()> {
+    export(path, None, key)
+}
+
+/// Decrypt an encrypted database file in place
+pub fn decrypt_file(path: &Path, key: &str) -> Result<()> {
+    export(path, Some(key), "")
+}
+
+/// Copy a database into a file with `to_key` (empty for plaintext) and swap it in
+fn export(path: &Path, from_key: Option<&str>, to_key: &str) -> Result<()> {
+    let tmp = path.with_extension("db.export");
+    let _ = std::fs::remove_file(&tmp);
+    {
+        let conn = Connection::open(path)?;
+        require_cipher(&conn)?;
+        if let Some(key) = from_key {
+            conn.pragma_update(None, "key", key)?;
+        }
+        // Fold the write-ahead log in, so no -wal file outlives the swap
+        set_journal_mode(&conn, "DELETE")?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS export KEY ?2",
+            params![tmp.to_string_lossy(), to_key],
+        )?;
+        conn.query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()))?;
+        conn.execute("DETACH DATABASE export", [])?;
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Idle read connections kept open
+const READ_POOL_SIZE: usize = 4;
+
+/// How long a connection waits for a lock held by another
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn set_journal_mode(conn: &Connection, mode: &str) -> Result<()> {
+    conn.pragma_update_and_check(None, "journal_mode", mode, |_| Ok(()))?;
+    Ok(())
+}
+
+/// Open a connection, unlocking it with `key` if encrypted
+fn connect(path: &Path, key: Option<&str>) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+    if let Some(key) = key {
+        require_cipher(&conn)?;
+        conn.pragma_update(None, "key", key)?;
+    }
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+struct ReadPool {
+    /// Key the idle connections were opened with
+    key: Option<String>,
+    idle: Vec<Connection>,
+}
+
+/// A read connection, returned to the pool when dropped
+struct PooledConnection<'a> {
+    conn: Option<Connection>,
+    key: Option<String>,
+    db: &'a Database,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection taken before drop")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        let mut pool = self.db.readers.lock().unwrap();
+        // Connections opened with a rotated key are closed instead
+        if pool.key == self.key && pool.idle.len() < READ_POOL_SIZE {
+            if let Some(conn) = self.conn.take() {
+                pool.idle.push(conn);
+            }
+        }
+    }
 }
 
 /// Database manager
+///
+/// Writes go through a single connection; reads use a small pool of
+/// connections that, with write-ahead logging, run alongside writes. Methods
+/// block, so async code calls them through `run`.
 pub struct Database {
-    conn: Connection,
+    path: PathBuf,
+    writer: Mutex<Connection>,
+    readers: Mutex<ReadPool>,
 }
 
 impl Database {
     /// Open or create the database
-    pub fn new(path: &PathBuf) -> Result<Self> {
+    pub fn new(path: &Path) -> Result<Self> {
+        Self::open(path, None)
+    }
+
+    /// Open or create a database encrypted with `key` (SQLCipher)
+    pub fn open_encrypted(path: &Path, key: &str) -> Result<Self> {
+        Self::open(path, Some(key))
+    }
+
+    fn open(path: &Path, key: Option<&str>) -> Result<Self> {
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        let conn = Connection::open(path)?;
-        let db = Database { conn };
+        let conn = connect(path, key)?;
+        // A wrong key only shows once the file is read
+        conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+            .map_err(|e| match key {
+                Some(_) => AppError::Config("Could not decrypt the history database".to_string()),
+                None => e.into(),
+            })?;
+        set_journal_mode(&conn, "WAL")?;
+
+        let db = Database {
+            path: path.to_path_buf(),
+            writer: Mutex::new(conn),
+            readers: Mutex::new(ReadPool {
+                key: key.map(str::to_string),
+                idle: Vec::new(),
+            }),
+        };
         db.init_schema()?;
         Ok(db)
     }
 
+    /// Run blocking database work off the async runtime
+    pub async fn run<T, F>(self: &Arc<Self>, f: F) -> Result<T>
+    where
+        F: FnOnce(&Database) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = Arc::clone(self);
+        tokio::task::spawn_blocking(move || f(&db))
+            .await
+            .map_err(|e| AppError::Config(format!("Database task failed: {}", e)))?
+    }
+
+    /// The write connection
+    fn writer(&self) -> MutexGuard<'_, Connection> {
+        self.writer.lock().unwrap()
+    }
+
+    /// A read connection from the pool, opened if none is idle
+    fn reader(&self) -> Result<PooledConnection<'_>> {
+        let (conn, key) = {
+            let mut pool = self.readers.lock().unwrap();
+            (pool.idle.pop(), pool.key.clone())
+        };
+        let conn = match conn {
+            Some(conn) => conn,
+            None => connect(&self.path, key.as_deref())?,
+        };
+        Ok(PooledConnection {
+            conn: Some(conn),
+            key,
+            db: self,
+        })
+    }
+
+    /// Re-encrypt an encrypted database with a new key
+    pub fn rekey(&self, key: &str) -> Result<()> {
+        let conn = self.writer();
+        {
+            let mut pool = self.readers.lock().unwrap();
+            pool.idle.clear();
+            pool.key = Some(key.to_string());
+        }
+        // Rekeying rewrites every page; do it outside WAL so no page is
+        // left in the log under the old key
+        set_journal_mode(&conn, "DELETE")?;
+        conn.pragma_update(None, "rekey", key)?;
+        set_journal_mode(&conn, "WAL")?;
+        Ok(())
+    }
+
     /// Initialize database schema
     fn init_schema(&self) -> Result<()> {
-        self.conn.execute(
+        let conn = self.writer();
+        conn.execute(
             "CREATE TABLE IF NOT EXISTS history_items (
                 id TEXT PRIMARY KEY,
                 created_at TEXT NOT NULL,
@@ -63,73 +591,274 @@ impl Database {
         )?;
 
         // Create index for faster queries
-        self.conn.execute(
+        conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_history_created_at ON history_items(created_at DESC)",
             [],
         )?;
 
-        self.conn.execute(
+        conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_history_mode_key ON history_items(mode_key)",
             [],
         )?;
 
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS journal_entries (
+                date TEXT PRIMARY KEY,
+                content TEXT NOT NULL,
+                entry_count INTEGER NOT NULL,
+                summary TEXT,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS model_picks (
+                history_id TEXT NOT NULL,
+                mode_key TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                chosen INTEGER NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS style_profile (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                profile_json TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS history_revisions (
+                history_id TEXT NOT NULL,
+                revision INTEGER NOT NULL,
+                output_final TEXT NOT NULL,
+                mode_key TEXT NOT NULL,
+                llm_provider TEXT,
+                llm_model TEXT,
+                replaced_at TEXT NOT NULL,
+                replaced_by TEXT NOT NULL,
+                PRIMARY KEY (history_id, revision)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS transcript_alternatives (
+                history_id TEXT PRIMARY KEY,
+                segments_json TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS batch_jobs (
+                id TEXT PRIMARY KEY,
+                task_json TEXT NOT NULL,
+                status TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS batch_items (
+                job_id TEXT NOT NULL,
+                history_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                state TEXT NOT NULL DEFAULT 'pending',
+                error TEXT,
+                PRIMARY KEY (job_id, history_id)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS watched_files (
+                path TEXT NOT NULL,
+                modified_ms INTEGER NOT NULL,
+                history_id TEXT,
+                error TEXT,
+                processed_at TEXT NOT NULL,
+                PRIMARY KEY (path, modified_ms)
+            )",
+            [],
+        )?;
+
+        let has_phrases: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'phrase_counts'",
+            [],
+            |row| row.get(0),
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS phrase_counts (
+                phrase TEXT PRIMARY KEY,
+                count INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Columns added after the initial schema
+        ensure_column(&conn, "history_items", "latency_json", "TEXT")?;
+        ensure_column(&conn, "history_items", "bookmarks_json", "TEXT")?;
+        ensure_column(&conn, "history_items", "prompt_tokens", "INTEGER")?;
+        ensure_column(&conn, "history_items", "completion_tokens", "INTEGER")?;
+        ensure_column(&conn, "history_items", "cost_usd", "REAL")?;
+        ensure_column(&conn, "history_items", "word_count", "INTEGER")?;
+        ensure_column(&conn, "history_items", "char_count", "INTEGER")?;
+        ensure_column(&conn, "history_items", "reading_time_secs", "INTEGER")?;
+        ensure_column(&conn, "history_items", "truncated", "INTEGER")?;
+        ensure_column(&conn, "history_items", "window_class", "TEXT")?;
+        ensure_column(&conn, "history_items", "window_title", "TEXT")?;
+        ensure_column(&conn, "history_items", "cost_saved_usd", "REAL")?;
+        ensure_column(&conn, "history_items", "deleted_at", "TEXT")?;
+        ensure_column(&conn, "history_items", "degraded", "INTEGER")?;
+        ensure_column(&conn, "history_items", "verbatim", "INTEGER")?;
+        ensure_column(&conn, "history_items", "summary_only", "INTEGER")?;
+        ensure_column(&conn, "history_items", "source_url", "TEXT")?;
+        ensure_column(&conn, "history_items", "clipped", "INTEGER")?;
+        ensure_column(&conn, "history_revisions", "transcript_raw", "TEXT")?;
+        ensure_column(&conn, "history_revisions", "stt_provider", "TEXT")?;
+        ensure_column(&conn, "history_revisions", "stt_model", "TEXT")?;
+
+        // Errors used to be free text
+        conn.execute(
+            "UPDATE history_items SET error = json_object(
+                'stage', 'other', 'provider', NULL, 'code', 'unknown', 'message', error,
+                'retryable', json('false'), 'at', created_at)
+             WHERE error IS NOT NULL AND NOT json_valid(error)",
+            [],
+        )?;
+
+        // Index the history saved before phrase memory existed
+        if !has_phrases {
+            rebuild_phrases(&conn)?;
+        }
+
         Ok(())
     }
 
     /// Insert a new history item
+    ///
+    /// A summary-only item never has its transcript, recording, window
+    /// title or URL written, whatever the caller left in it.
     pub fn insert_history(&self, item: &HistoryItem) -> Result<()> {
-        self.conn.execute(
+        let (audio_path, transcript_raw, window_title, source_url) = if item.summary_only {
+            (None, "", None, None)
+        } else {
+            (
+                item.audio_path.as_deref(),
+                item.transcript_raw.as_str(),
+                item.window_title.as_deref(),
+                item.source_url.as_deref(),
+            )
+        };
+        let conn = self.writer();
+        conn.execute(
             "INSERT INTO history_items (
                 id, created_at, mode_key, audio_path, transcript_raw, output_final,
-                stt_provider, stt_model, llm_provider, llm_model, duration_ms, error
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                stt_provider, stt_model, llm_provider, llm_model, duration_ms, error,
+                latency_json, bookmarks_json, prompt_tokens, completion_tokens, cost_usd,
+                word_count, char_count, reading_time_secs, truncated, window_class, window_title,
+                cost_saved_usd, degraded, verbatim, summary_only, source_url, clipped
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29)",
             params![
                 item.id,
                 item.created_at.to_rfc3339(),
                 item.mode_key,
-                item.audio_path,
-                item.transcript_raw,
+                audio_path,
+                transcript_raw,
                 item.output_final,
                 item.stt_provider,
                 item.stt_model,
                 item.llm_provider,
                 item.llm_model,
                 item.duration_ms as i64,
-                item.error,
+                item.error.as_ref().map(serde_json::to_string).transpose()?,
+                serde_json::to_string(&item.latency)?,
+                serde_json::to_string(&item.bookmarks)?,
+                item.prompt_tokens,
+                item.completion_tokens,
+                item.cost_usd,
+                item.stats.word_count,
+                item.stats.char_count,
+                item.stats.reading_time_secs,
+                item.truncated,
+                item.window_class,
+                window_title,
+                item.cost_saved_usd,
+                item.degraded,
+                item.verbatim,
+                item.summary_only,
+                source_url,
+                item.clipped,
             ],
         )?;
+        if item.error.is_none() {
+            count_phrases(&conn, &item.output_final, 1)?;
+        }
         Ok(())
     }
 
     /// Get all history items (paginated)
     pub fn get_history(&self, limit: usize, offset: usize) -> Result<Vec<HistoryItem>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, created_at, mode_key, audio_path, transcript_raw, output_final,
-                    stt_provider, stt_model, llm_provider, llm_model, duration_ms, error
-             FROM history_items
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM history_items
+             WHERE deleted_at IS NULL
              ORDER BY created_at DESC
              LIMIT ?1 OFFSET ?2",
+            HISTORY_COLUMNS
+        ))?;
+
+        let items = stmt
+            .query_map(params![limit as i64, offset as i64], row_to_history_item)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(items)
+    }
+
+    /// IDs of all history items outside the trash, oldest first, optionally
+    /// only those with a kept recording
+    pub fn get_history_ids(&self, with_audio: bool) -> Result<Vec<String>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id FROM history_items
+             WHERE deleted_at IS NULL AND (?1 = 0 OR audio_path IS NOT NULL)
+             ORDER BY created_at",
         )?;
+        let ids = stmt
+            .query_map(params![with_audio], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(ids)
+    }
+
+    /// Get failed history items, newest first, optionally only those that
+    /// failed in `stage`
+    pub fn get_failed_history(&self, stage: Option<ErrorStage>, limit: usize, offset: usize) -> Result<Vec<HistoryItem>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM history_items
+             WHERE error IS NOT NULL AND deleted_at IS NULL
+               AND (?1 IS NULL OR json_extract(error, '$.stage') = ?1)
+             ORDER BY created_at DESC
+             LIMIT ?2 OFFSET ?3",
+            HISTORY_COLUMNS
+        ))?;
 
         let items = stmt
-            .query_map(params![limit as i64, offset as i64], |row| {
-                Ok(HistoryItem {
-                    id: row.get(0)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(1)?)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    mode_key: row.get(2)?,
-                    audio_path: row.get(3)?,
-                    transcript_raw: row.get(4)?,
-                    output_final: row.get(5)?,
-                    stt_provider: row.get(6)?,
-                    stt_model: row.get(7)?,
-                    llm_provider: row.get(8)?,
-                    llm_model: row.get(9)?,
-                    duration_ms: row.get::<_, i64>(10)? as u64,
-                    error: row.get(11)?,
-                })
-            })?
+            .query_map(
+                params![stage.map(|s| s.as_str()), limit as i64, offset as i64],
+                row_to_history_item,
+            )?
             .filter_map(|r| r.ok())
             .collect();
 
@@ -138,117 +867,891 @@ impl Database {
 
     /// Get a single history item by ID
     pub fn get_history_item(&self, id: &str) -> Result<Option<HistoryItem>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, created_at, mode_key, audio_path, transcript_raw, output_final,
-                    stt_provider, stt_model, llm_provider, llm_model, duration_ms, error
-             FROM history_items
+        let conn = self.reader()?;
+        history_item(&conn, id)
+    }
+
+    /// Update a history item (for reprocessing), keeping the old output as a revision
+    pub fn update_history(&self, item: &HistoryItem) -> Result<()> {
+        let conn = self.writer();
+        let previous = history_item(&conn, &item.id)?;
+        if let Some(previous) = previous.as_ref().filter(|p| p.output_final != item.output_final) {
+            save_revision(&conn, previous, RevisionSource::Reprocess)?;
+        }
+        update_output(&conn, item, previous)
+    }
+
+    /// Update a history item transcribed again, keeping its old transcript
+    /// and output as one revision
+    pub fn retranscribe_history(&self, item: &HistoryItem) -> Result<()> {
+        let conn = self.writer();
+        let previous = history_item(&conn, &item.id)?;
+        if let Some(previous) = previous
+            .as_ref()
+            .filter(|p| p.transcript_raw != item.transcript_raw || p.output_final != item.output_final)
+        {
+            save_revision(&conn, previous, RevisionSource::Retranscribe)?;
+        }
+        conn.execute(
+            "UPDATE history_items SET transcript_raw = ?2, stt_provider = ?3, stt_model = ?4 WHERE id = ?1",
+            params![item.id, item.transcript_raw, item.stt_provider, item.stt_model],
+        )?;
+        update_output(&conn, item, previous)
+    }
+
+    /// Replace a history item's output by hand, keeping the old one as a revision
+    pub fn edit_history(&self, id: &str, output: &str) -> Result<HistoryItem> {
+        let conn = self.writer();
+        let mut item = history_item(&conn, id)?
+            .ok_or_else(|| AppError::Config(format!("History item {} not found", id)))?;
+        if item.output_final == output {
+            return Ok(item);
+        }
+
+        save_revision(&conn, &item, RevisionSource::Edit)?;
+        let stats = TextStats::of(output);
+        conn.execute(
+            "UPDATE history_items SET
+                output_final = ?2,
+                word_count = ?3,
+                char_count = ?4,
+                reading_time_secs = ?5
              WHERE id = ?1",
+            params![id, output, stats.word_count, stats.char_count, stats.reading_time_secs],
+        )?;
+        if item.error.is_none() && item.deleted_at.is_none() {
+            count_phrases(&conn, &item.output_final, -1)?;
+            count_phrases(&conn, output, 1)?;
+        }
+
+        item.output_final = output.to_string();
+        item.stats = stats;
+        Ok(item)
+    }
+
+    /// Earlier outputs of a history item, oldest first
+    pub fn get_revisions(&self, id: &str) -> Result<Vec<HistoryRevision>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT revision, output_final, mode_key, llm_provider, llm_model, replaced_at, replaced_by,
+                    transcript_raw, stt_provider, stt_model
+             FROM history_revisions
+             WHERE history_id = ?1
+             ORDER BY revision",
         )?;
 
-        let item = stmt
-            .query_row(params![id], |row| {
-                Ok(HistoryItem {
-                    id: row.get(0)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(1)?)
+        let revisions = stmt
+            .query_map(params![id], |row| {
+                Ok(HistoryRevision {
+                    revision: row.get(0)?,
+                    output_final: row.get(1)?,
+                    mode_key: row.get(2)?,
+                    llm_provider: row.get(3)?,
+                    llm_model: row.get(4)?,
+                    replaced_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
                         .map(|dt| dt.with_timezone(&Utc))
                         .unwrap_or_else(|_| Utc::now()),
-                    mode_key: row.get(2)?,
-                    audio_path: row.get(3)?,
-                    transcript_raw: row.get(4)?,
-                    output_final: row.get(5)?,
-                    stt_provider: row.get(6)?,
-                    stt_model: row.get(7)?,
-                    llm_provider: row.get(8)?,
-                    llm_model: row.get(9)?,
-                    duration_ms: row.get::<_, i64>(10)? as u64,
-                    error: row.get(11)?,
+                    replaced_by: match row.get::<_, String>(6)?.as_str() {
+                        "edit" => RevisionSource::Edit,
+                        "retranscribe" => RevisionSource::Retranscribe,
+                        _ => RevisionSource::Reprocess,
+                    },
+                    transcript_raw: row.get(7)?,
+                    stt_provider: row.get(8)?,
+                    stt_model: row.get(9)?,
                 })
-            })
-            .ok();
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
 
-        Ok(item)
+        Ok(revisions)
     }
 
-    /// Update a history item (for reprocessing)
-    pub fn update_history(&self, item: &HistoryItem) -> Result<()> {
-        self.conn.execute(
-            "UPDATE history_items SET
-                mode_key = ?2,
-                output_final = ?3,
-                llm_provider = ?4,
-                llm_model = ?5,
-                error = ?6
-             WHERE id = ?1",
-            params![
-                item.id,
-                item.mode_key,
-                item.output_final,
-                item.llm_provider,
-                item.llm_model,
-                item.error,
-            ],
+    /// Move a history item to the trash
+    pub fn delete_history(&self, id: &str) -> Result<()> {
+        let conn = self.writer();
+        let Some(item) = history_item(&conn, id)?.filter(|i| i.deleted_at.is_none()) else {
+            return Ok(());
+        };
+        conn.execute(
+            "UPDATE history_items SET deleted_at = ?2 WHERE id = ?1",
+            params![id, Utc::now().to_rfc3339()],
         )?;
+        if item.error.is_none() {
+            count_phrases(&conn, &item.output_final, -1)?;
+        }
         Ok(())
     }
 
-    /// Delete a history item
-    pub fn delete_history(&self, id: &str) -> Result<()> {
-        self.conn.execute("DELETE FROM history_items WHERE id = ?1", params![id])?;
+    /// Take a history item back out of the trash
+    pub fn restore_history(&self, id: &str) -> Result<()> {
+        let conn = self.writer();
+        let Some(item) = history_item(&conn, id)?.filter(|i| i.deleted_at.is_some()) else {
+            return Err(AppError::Config(format!("History item {} is not in the trash", id)));
+        };
+        conn.execute("UPDATE history_items SET deleted_at = NULL WHERE id = ?1", params![id])?;
+        if item.error.is_none() {
+            count_phrases(&conn, &item.output_final, 1)?;
+        }
         Ok(())
     }
 
+    /// Get trashed history items, most recently deleted first
+    pub fn get_trash(&self, limit: usize, offset: usize) -> Result<Vec<HistoryItem>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM history_items
+             WHERE deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC
+             LIMIT ?1 OFFSET ?2",
+            HISTORY_COLUMNS
+        ))?;
+
+        let items = stmt
+            .query_map(params![limit as i64, offset as i64], row_to_history_item)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(items)
+    }
+
+    /// Permanently delete trashed items, optionally only `id` or those
+    /// trashed before `before`, returning their audio paths for cleanup
+    pub fn purge_history(&self, id: Option<&str>, before: Option<DateTime<Utc>>) -> Result<Vec<String>> {
+        let conn = self.writer();
+        let filter = "deleted_at IS NOT NULL
+             AND (?1 IS NULL OR id = ?1)
+             AND (?2 IS NULL OR deleted_at < ?2)";
+        let before = before.map(|at| at.to_rfc3339());
+
+        let audio_paths = conn
+            .prepare(&format!(
+                "SELECT audio_path FROM history_items WHERE audio_path IS NOT NULL AND {}",
+                filter
+            ))?
+            .query_map(params![id, before], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        for table in ["history_revisions", "transcript_alternatives"] {
+            conn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE history_id IN (SELECT id FROM history_items WHERE {})",
+                    table, filter
+                ),
+                params![id, before],
+            )?;
+        }
+        conn.execute(&format!("DELETE FROM history_items WHERE {}", filter), params![id, before])?;
+
+        Ok(audio_paths)
+    }
+
+    /// Write the write-ahead log back into the database file
+    pub fn checkpoint(&self) -> Result<()> {
+        self.writer()
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// Recount phrases from all saved outputs
+    pub fn rebuild_phrase_index(&self) -> Result<()> {
+        rebuild_phrases(&self.writer())
+    }
+
+    /// Frequently dictated phrases continuing the text before the cursor
+    ///
+    /// Continuations of the longest matching run of context words come first,
+    /// most dictated first.
+    pub fn suggest_phrases(&self, prefix: &str, limit: usize) -> Result<Vec<PhraseSuggestion>> {
+        let conn = self.reader()?;
+        let query = phrases::parse_prefix(prefix);
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let mut stmt = conn.prepare(
+            "SELECT phrase, count FROM phrase_counts
+             WHERE phrase LIKE ?1 ESCAPE '\\' AND count >= ?2
+             ORDER BY count DESC, length(phrase) DESC
+             LIMIT ?3",
+        )?;
+
+        let mut suggestions: Vec<PhraseSuggestion> = Vec::new();
+        for n in (0..=query.context.len()).rev() {
+            let lead = query.context[query.context.len() - n..].join(" ");
+            // A lone partial word has nothing to continue from
+            if lead.is_empty() && query.partial.is_empty() {
+                continue;
+            }
+            let lead = if lead.is_empty() { lead } else { format!("{} ", lead) };
+            let pattern = format!("{}{}%", escape(&lead), escape(&query.partial));
+
+            let rows: Vec<(String, i64)> = stmt
+                .query_map(params![pattern, phrases::MIN_COUNT as i64, (limit * 4) as i64], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?
+                .filter_map(|r| r.ok())
+                .collect();
+
+            for (phrase, count) in rows {
+                let completion = phrase[lead.len() + query.partial.len()..].to_string();
+                if completion.trim().is_empty() || suggestions.iter().any(|s| s.completion == completion) {
+                    continue;
+                }
+                suggestions.push(PhraseSuggestion {
+                    phrase,
+                    completion,
+                    count: count as u64,
+                });
+                if suggestions.len() >= limit {
+                    return Ok(suggestions);
+                }
+            }
+        }
+
+        Ok(suggestions)
+    }
+
     /// Get total count of history items
     pub fn get_history_count(&self) -> Result<usize> {
-        let count: i64 = self
-            .conn
-            .query_row("SELECT COUNT(*) FROM history_items", [], |row| row.get(0))?;
+        let conn = self.reader()?;
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM history_items WHERE deleted_at IS NULL", [], |row| {
+                row.get(0)
+            })?;
         Ok(count as usize)
     }
 
     /// Search history by text
     pub fn search_history(&self, query: &str, limit: usize) -> Result<Vec<HistoryItem>> {
+        let conn = self.reader()?;
         let search_pattern = format!("%{}%", query);
-        let mut stmt = self.conn.prepare(
-            "SELECT id, created_at, mode_key, audio_path, transcript_raw, output_final,
-                    stt_provider, stt_model, llm_provider, llm_model, duration_ms, error
-             FROM history_items
-             WHERE transcript_raw LIKE ?1 OR output_final LIKE ?1
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM history_items
+             WHERE (transcript_raw LIKE ?1 OR output_final LIKE ?1) AND deleted_at IS NULL
              ORDER BY created_at DESC
              LIMIT ?2",
-        )?;
+            HISTORY_COLUMNS
+        ))?;
 
         let items = stmt
-            .query_map(params![search_pattern, limit as i64], |row| {
-                Ok(HistoryItem {
-                    id: row.get(0)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(1)?)
-                        .map(|dt| dt.with_timezone(&Utc))
+            .query_map(params![search_pattern, limit as i64], row_to_history_item)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(items)
+    }
+
+    /// LLM token usage and estimated spend per month, newest first
+    pub fn get_monthly_spend(&self) -> Result<Vec<MonthlySpend>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT substr(created_at, 1, 7) AS month,
+                    COALESCE(SUM(cost_usd), 0.0),
+                    COALESCE(SUM(prompt_tokens), 0),
+                    COALESCE(SUM(completion_tokens), 0),
+                    COUNT(prompt_tokens),
+                    COALESCE(SUM(cost_saved_usd), 0.0),
+                    COUNT(cost_saved_usd)
+             FROM history_items
+             WHERE (prompt_tokens IS NOT NULL OR cost_saved_usd IS NOT NULL) AND deleted_at IS NULL
+             GROUP BY month
+             ORDER BY month DESC",
+        )?;
+
+        let months = stmt
+            .query_map([], |row| {
+                Ok(MonthlySpend {
+                    month: row.get(0)?,
+                    cost_usd: row.get(1)?,
+                    prompt_tokens: row.get::<_, i64>(2)? as u64,
+                    completion_tokens: row.get::<_, i64>(3)? as u64,
+                    items: row.get::<_, i64>(4)? as u64,
+                    saved_usd: row.get(5)?,
+                    skipped: row.get::<_, i64>(6)? as u64,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(months)
+    }
+
+    /// Totals, speed and latency of successful dictations within `range`
+    pub fn get_stats(&self, range: &StatsRange) -> Result<HistoryStats> {
+        let conn = self.reader()?;
+        let bounds = params![
+            range.from.map(|from| from.to_rfc3339()),
+            range.to.map(|to| to.to_rfc3339())
+        ];
+
+        let (recordings, audio_ms, words, avg_latency_ms) = conn.query_row(
+            &format!(
+                "SELECT COUNT(*),
+                        COALESCE(SUM(duration_ms), 0),
+                        COALESCE(SUM(word_count), 0),
+                        AVG(json_extract(latency_json, '$[0].duration_ms'))
+                 FROM history_items
+                 WHERE {}",
+                STATS_FILTER
+            ),
+            bounds,
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u64,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, i64>(2)? as u64,
+                    row.get::<_, Option<f64>>(3)?,
+                ))
+            },
+        )?;
+
+        let audio_minutes = audio_ms as f64 / 60_000.0;
+        Ok(HistoryStats {
+            recordings,
+            audio_minutes,
+            words,
+            avg_latency_ms,
+            words_per_minute: words_per_minute(words, audio_minutes),
+            time_saved_minutes: (words as f64 / TYPING_WPM - audio_minutes).max(0.0),
+            by_mode: group_stats(&conn, "mode_key", range)?,
+            by_provider: group_stats(&conn, "stt_provider", range)?,
+        })
+    }
+
+    /// Append a line to a day's journal entry, creating the entry if needed
+    ///
+    /// A summary generated earlier is cleared so the day gets summarized again.
+    pub fn append_journal(&self, date: &str, line: &str) -> Result<()> {
+        let conn = self.writer();
+        conn.execute(
+            "INSERT INTO journal_entries (date, content, entry_count, summary, updated_at)
+             VALUES (?1, ?2, 1, NULL, ?3)
+             ON CONFLICT(date) DO UPDATE SET
+                content = content || char(10) || excluded.content,
+                entry_count = entry_count + 1,
+                summary = NULL,
+                updated_at = excluded.updated_at",
+            params![date, line, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Get the journal entry for a day
+    pub fn get_journal_entry(&self, date: &str) -> Result<Option<JournalEntry>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT date, content, entry_count, summary, updated_at
+             FROM journal_entries WHERE date = ?1",
+        )?;
+
+        let entry = stmt.query_row(params![date], row_to_journal_entry).ok();
+
+        Ok(entry)
+    }
+
+    /// Journal entries before `date` that have no summary yet, oldest first
+    pub fn get_unsummarized_journal_entries(&self, before: &str) -> Result<Vec<JournalEntry>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT date, content, entry_count, summary, updated_at
+             FROM journal_entries
+             WHERE date < ?1 AND summary IS NULL
+             ORDER BY date ASC",
+        )?;
+
+        let entries = stmt
+            .query_map(params![before], row_to_journal_entry)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Store the summary for a day's journal entry
+    pub fn set_journal_summary(&self, date: &str, summary: &str) -> Result<()> {
+        let conn = self.writer();
+        conn.execute(
+            "UPDATE journal_entries SET summary = ?2 WHERE date = ?1",
+            params![date, summary],
+        )?;
+        Ok(())
+    }
+
+    /// Record a multi-take comparison: each (provider, model) shown, and which was chosen
+    pub fn record_model_picks(
+        &self,
+        history_id: &str,
+        mode_key: &str,
+        candidates: &[(String, String)],
+        chosen: usize,
+    ) -> Result<()> {
+        let conn = self.writer();
+        let created_at = Utc::now().to_rfc3339();
+        for (i, (provider, model)) in candidates.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO model_picks (history_id, mode_key, provider, model, chosen, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![history_id, mode_key, provider, model, i == chosen, created_at],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Keep the other readings of a dictation's uncertain segments
+    ///
+    /// Only the most recent dictations keep theirs; storing one drops the oldest.
+    pub fn set_alternatives(&self, history_id: &str, segments: &[SegmentAlternatives]) -> Result<()> {
+        let conn = self.writer();
+        conn.execute(
+            "INSERT OR REPLACE INTO transcript_alternatives (history_id, segments_json, created_at)
+             VALUES (?1, ?2, ?3)",
+            params![history_id, serde_json::to_string(segments)?, Utc::now().to_rfc3339()],
+        )?;
+        conn.execute(
+            "DELETE FROM transcript_alternatives WHERE history_id NOT IN (
+                SELECT history_id FROM transcript_alternatives ORDER BY created_at DESC, rowid DESC LIMIT ?1
+             )",
+            params![MAX_ALTERNATIVE_ITEMS],
+        )?;
+        Ok(())
+    }
+
+    /// Replace the kept readings of a dictation, leaving its place in the roll
+    pub fn update_alternatives(&self, history_id: &str, segments: &[SegmentAlternatives]) -> Result<()> {
+        self.writer().execute(
+            "UPDATE transcript_alternatives SET segments_json = ?2 WHERE history_id = ?1",
+            params![history_id, serde_json::to_string(segments)?],
+        )?;
+        Ok(())
+    }
+
+    /// Other readings of a dictation's uncertain segments, empty when none are kept
+    pub fn get_alternatives(&self, history_id: &str) -> Result<Vec<SegmentAlternatives>> {
+        let conn = self.reader()?;
+        let json: Option<String> = conn
+            .query_row(
+                "SELECT segments_json FROM transcript_alternatives WHERE history_id = ?1",
+                params![history_id],
+                |row| row.get(0),
+            )
+            .ok();
+        Ok(json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default())
+    }
+
+    /// Queue a batch doing `task` to each of `history_ids`, in order
+    pub fn create_batch(&self, task: &BatchTask, history_ids: &[String]) -> Result<BatchJob> {
+        let conn = self.writer();
+        let job = BatchJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            task: task.clone(),
+            status: BatchStatus::Running,
+            created_at: Utc::now(),
+            total: 0,
+            done: 0,
+            failed: 0,
+        };
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO batch_jobs (id, task_json, status, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                job.id,
+                serde_json::to_string(task)?,
+                job.status.as_str(),
+                job.created_at.to_rfc3339()
+            ],
+        )?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO batch_items (job_id, history_id, position) VALUES (?1, ?2, ?3)",
+            )?;
+            for (position, history_id) in history_ids.iter().enumerate() {
+                stmt.execute(params![job.id, history_id, position as i64])?;
+            }
+        }
+        tx.commit()?;
+        drop(conn);
+
+        self.get_batch(&job.id)?
+            .ok_or_else(|| AppError::Config(format!("Batch {} not found", job.id)))
+    }
+
+    /// A batch with its progress
+    pub fn get_batch(&self, id: &str) -> Result<Option<BatchJob>> {
+        Ok(self.query_batches(Some(id))?.pop())
+    }
+
+    /// Every batch, newest first
+    pub fn get_batches(&self) -> Result<Vec<BatchJob>> {
+        self.query_batches(None)
+    }
+
+    fn query_batches(&self, id: Option<&str>) -> Result<Vec<BatchJob>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT j.id, j.task_json, j.status, j.created_at, COUNT(i.history_id),
+                    COALESCE(SUM(i.state != 'pending'), 0), COALESCE(SUM(i.state = 'failed'), 0)
+             FROM batch_jobs j
+             LEFT JOIN batch_items i ON i.job_id = j.id
+             WHERE ?1 IS NULL OR j.id = ?1
+             GROUP BY j.id
+             ORDER BY j.created_at DESC",
+        )?;
+
+        let batches = stmt
+            .query_map(params![id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, i64>(6)?,
+                ))
+            })?
+            .filter_map(|r| r.ok())
+            .filter_map(|(id, task_json, status, created_at, total, done, failed)| {
+                Some(BatchJob {
+                    id,
+                    // Batches of a task this version does not know are skipped
+                    task: serde_json::from_str(&task_json).ok()?,
+                    status: BatchStatus::parse(&status),
+                    created_at: DateTime::parse_from_rfc3339(&created_at)
+                        .map(|dt| dt.with_timezone(&Utc))
                         .unwrap_or_else(|_| Utc::now()),
-                    mode_key: row.get(2)?,
-                    audio_path: row.get(3)?,
-                    transcript_raw: row.get(4)?,
-                    output_final: row.get(5)?,
-                    stt_provider: row.get(6)?,
-                    stt_model: row.get(7)?,
-                    llm_provider: row.get(8)?,
-                    llm_model: row.get(9)?,
-                    duration_ms: row.get::<_, i64>(10)? as u64,
-                    error: row.get(11)?,
+                    total: total as usize,
+                    done: done as usize,
+                    failed: failed as usize,
+                })
+            })
+            .collect();
+
+        Ok(batches)
+    }
+
+    pub fn set_batch_status(&self, id: &str, status: BatchStatus) -> Result<()> {
+        self.writer().execute(
+            "UPDATE batch_jobs SET status = ?2 WHERE id = ?1",
+            params![id, status.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// History items of a batch not yet processed, in order
+    pub fn pending_batch_items(&self, id: &str) -> Result<Vec<String>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT history_id FROM batch_items WHERE job_id = ?1 AND state = 'pending' ORDER BY position",
+        )?;
+        let ids = stmt
+            .query_map(params![id], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(ids)
+    }
+
+    /// Record that a batch item was processed, or why it failed
+    pub fn finish_batch_item(&self, id: &str, history_id: &str, error: Option<&str>) -> Result<()> {
+        self.writer().execute(
+            "UPDATE batch_items SET state = ?3, error = ?4 WHERE job_id = ?1 AND history_id = ?2",
+            params![id, history_id, if error.is_some() { "failed" } else { "done" }, error],
+        )?;
+        Ok(())
+    }
+
+    /// Remove a batch and its items
+    pub fn delete_batch(&self, id: &str) -> Result<()> {
+        let conn = self.writer();
+        conn.execute("DELETE FROM batch_items WHERE job_id = ?1", params![id])?;
+        conn.execute("DELETE FROM batch_jobs WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Whether a watch folder file, as last modified at `modified_ms`, was already handled
+    pub fn is_watched_file_processed(&self, path: &str, modified_ms: i64) -> Result<bool> {
+        let conn = self.reader()?;
+        let processed = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM watched_files WHERE path = ?1 AND modified_ms = ?2",
+            params![path, modified_ms],
+            |row| row.get(0),
+        )?;
+        Ok(processed)
+    }
+
+    /// Record a handled watch folder file, with its history item or why it failed
+    pub fn record_watched_file(
+        &self,
+        path: &str,
+        modified_ms: i64,
+        history_id: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<()> {
+        self.writer().execute(
+            "INSERT OR REPLACE INTO watched_files (path, modified_ms, history_id, error, processed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![path, modified_ms, history_id, error, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Pick counts per model, optionally for one mode, most chosen first
+    pub fn get_model_pick_stats(&self, mode_key: Option<&str>) -> Result<Vec<ModelPickStats>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT provider, model, COUNT(*), SUM(chosen)
+             FROM model_picks
+             WHERE ?1 IS NULL OR mode_key = ?1
+             GROUP BY provider, model
+             ORDER BY SUM(chosen) DESC, COUNT(*) ASC",
+        )?;
+
+        let stats = stmt
+            .query_map(params![mode_key], |row| {
+                Ok(ModelPickStats {
+                    provider: row.get(0)?,
+                    model: row.get(1)?,
+                    shown: row.get::<_, i64>(2)? as u64,
+                    chosen: row.get::<_, i64>(3)? as u64,
                 })
             })?
             .filter_map(|r| r.ok())
             .collect();
 
-        Ok(items)
+        Ok(stats)
+    }
+
+    /// Final outputs of dictations the user edited by hand, newest first
+    pub fn get_corrected_outputs(&self, limit: usize) -> Result<Vec<String>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT output_final FROM history_items h
+             WHERE error IS NULL AND deleted_at IS NULL
+               AND EXISTS (SELECT 1 FROM history_revisions r WHERE r.history_id = h.id AND r.replaced_by = 'edit')
+             ORDER BY created_at DESC
+             LIMIT ?1",
+        )?;
+
+        let outputs = stmt
+            .query_map(params![limit as i64], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(outputs)
+    }
+
+    /// The stored style profile, if one was derived
+    pub fn get_style_profile(&self) -> Result<Option<StyleProfile>> {
+        let conn = self.reader()?;
+        let json: Option<String> = conn
+            .query_row("SELECT profile_json FROM style_profile WHERE id = 1", [], |row| row.get(0))
+            .ok();
+        Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+
+    /// Store the style profile, or remove it with None
+    pub fn set_style_profile(&self, profile: Option<&StyleProfile>) -> Result<()> {
+        let conn = self.writer();
+        match profile {
+            Some(profile) => conn.execute(
+                "INSERT OR REPLACE INTO style_profile (id, profile_json) VALUES (1, ?1)",
+                params![serde_json::to_string(profile)?],
+            )?,
+            None => conn.execute("DELETE FROM style_profile", [])?,
+        };
+        Ok(())
     }
 
     /// Clear all history
     pub fn clear_history(&self) -> Result<()> {
-        self.conn.execute("DELETE FROM history_items", [])?;
+        let conn = self.writer();
+        conn.execute("DELETE FROM history_items", [])?;
+        conn.execute("DELETE FROM history_revisions", [])?;
+        conn.execute("DELETE FROM transcript_alternatives", [])?;
+        conn.execute("DELETE FROM phrase_counts", [])?;
+        conn.execute("DELETE FROM style_profile", [])?;
         Ok(())
     }
 }
 
+/// Look up a history item on a given connection
+fn history_item(conn: &Connection, id: &str) -> Result<Option<HistoryItem>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM history_items WHERE id = ?1",
+        HISTORY_COLUMNS
+    ))?;
+
+    let item = stmt
+        .query_row(params![id], row_to_history_item)
+        .ok();
+
+    Ok(item)
+}
+
+/// Write a reprocessed history item's output and move its phrases in the index
+fn update_output(conn: &Connection, item: &HistoryItem, previous: Option<HistoryItem>) -> Result<()> {
+    conn.execute(
+        "UPDATE history_items SET
+            mode_key = ?2,
+            output_final = ?3,
+            llm_provider = ?4,
+            llm_model = ?5,
+            error = ?6,
+            prompt_tokens = ?7,
+            completion_tokens = ?8,
+            cost_usd = ?9,
+            word_count = ?10,
+            char_count = ?11,
+            reading_time_secs = ?12,
+            truncated = ?13,
+            verbatim = ?14
+         WHERE id = ?1",
+        params![
+            item.id,
+            item.mode_key,
+            item.output_final,
+            item.llm_provider,
+            item.llm_model,
+            item.error.as_ref().map(serde_json::to_string).transpose()?,
+            item.prompt_tokens,
+            item.completion_tokens,
+            item.cost_usd,
+            item.stats.word_count,
+            item.stats.char_count,
+            item.stats.reading_time_secs,
+            item.truncated,
+            item.verbatim,
+        ],
+    )?;
+
+    // Trashed items are not in the phrase index
+    if let Some(previous) = previous.filter(|p| p.deleted_at.is_none()) {
+        if previous.error.is_none() {
+            count_phrases(conn, &previous.output_final, -1)?;
+        }
+        if item.error.is_none() {
+            count_phrases(conn, &item.output_final, 1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Keep a history item's current output before it is replaced
+///
+/// Re-transcriptions keep the transcript and STT model too.
+fn save_revision(conn: &Connection, item: &HistoryItem, replaced_by: RevisionSource) -> Result<()> {
+    let retranscribed = replaced_by == RevisionSource::Retranscribe;
+    conn.execute(
+        "INSERT INTO history_revisions (
+            history_id, revision, output_final, mode_key, llm_provider, llm_model, replaced_at, replaced_by,
+            transcript_raw, stt_provider, stt_model
+         )
+         SELECT ?1, COALESCE(MAX(revision), 0) + 1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10
+         FROM history_revisions WHERE history_id = ?1",
+        params![
+            item.id,
+            item.output_final,
+            item.mode_key,
+            item.llm_provider,
+            item.llm_model,
+            Utc::now().to_rfc3339(),
+            replaced_by.as_str(),
+            retranscribed.then_some(&item.transcript_raw),
+            retranscribed.then_some(&item.stt_provider),
+            retranscribed.then_some(&item.stt_model),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Stats grouped by a history column, busiest first
+fn group_stats(conn: &Connection, column: &str, range: &StatsRange) -> Result<Vec<GroupStats>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {column}, COUNT(*), COALESCE(SUM(duration_ms), 0), COALESCE(SUM(word_count), 0)
+         FROM history_items
+         WHERE {filter}
+         GROUP BY {column}
+         ORDER BY COUNT(*) DESC",
+        column = column,
+        filter = STATS_FILTER
+    ))?;
+
+    let groups = stmt
+        .query_map(
+            params![
+                range.from.map(|from| from.to_rfc3339()),
+                range.to.map(|to| to.to_rfc3339())
+            ],
+            |row| {
+                let words = row.get::<_, i64>(3)? as u64;
+                let audio_minutes = row.get::<_, i64>(2)? as f64 / 60_000.0;
+                Ok(GroupStats {
+                    key: row.get(0)?,
+                    recordings: row.get::<_, i64>(1)? as u64,
+                    audio_minutes,
+                    words,
+                    words_per_minute: words_per_minute(words, audio_minutes),
+                })
+            },
+        )?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(groups)
+}
+
+/// Add a column to an existing table if it is not already present
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+        log::info!("Added column {}.{}", table, column);
+    }
+
+    Ok(())
+}
+
+/// Add `delta` to the count of every phrase in `text`
+fn count_phrases(conn: &Connection, text: &str, delta: i64) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO phrase_counts (phrase, count) VALUES (?1, ?2)
+             ON CONFLICT(phrase) DO UPDATE SET count = count + ?2",
+        )?;
+        for phrase in phrases::ngrams(text) {
+            stmt.execute(params![phrase, delta])?;
+        }
+    }
+    if delta < 0 {
+        tx.execute("DELETE FROM phrase_counts WHERE count <= 0", [])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Count phrases in every saved output, from scratch
+fn rebuild_phrases(conn: &Connection) -> Result<()> {
+    conn.execute("DELETE FROM phrase_counts", [])?;
+    let outputs: Vec<String> = conn
+        .prepare("SELECT output_final FROM history_items WHERE error IS NULL AND deleted_at IS NULL")?
+        .query_map([], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    for output in &outputs {
+        count_phrases(conn, output, 1)?;
+    }
+    Ok(())
+}
+
 /// Get the database path
 pub fn get_database_path() -> Result<PathBuf> {
     let data_dir = directories::ProjectDirs::from("com", "whispertray", "WhisperTray")
@@ -274,6 +1777,40 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    /// A finished dictation of `output`, for tests to adjust
+    fn history_item(id: &str, output: &str) -> HistoryItem {
+        HistoryItem {
+            id: id.to_string(),
+            created_at: Utc::now(),
+            mode_key: "voice_to_text".to_string(),
+            audio_path: None,
+            transcript_raw: output.to_string(),
+            output_final: output.to_string(),
+            stt_provider: "whispercpp".to_string(),
+            stt_model: "base.en".to_string(),
+            llm_provider: None,
+            llm_model: None,
+            duration_ms: 1000,
+            error: None,
+            latency: Vec::new(),
+            bookmarks: Vec::new(),
+            prompt_tokens: None,
+            completion_tokens: None,
+            cost_usd: None,
+            stats: TextStats::of(output),
+            truncated: false,
+            window_class: None,
+            window_title: None,
+            cost_saved_usd: None,
+            deleted_at: None,
+            degraded: false,
+            verbatim: false,
+            summary_only: false,
+            source_url: None,
+            clipped: false,
+        }
+    }
+
     #[test]
     fn test_database_creation() {
         let dir = tempdir().unwrap();
@@ -290,18 +1827,10 @@ mod tests {
         let db = Database::new(&path).unwrap();
 
         let item = HistoryItem {
-            id: "test-id".to_string(),
-            created_at: Utc::now(),
-            mode_key: "voice_to_text".to_string(),
             audio_path: Some("/path/to/audio.wav".to_string()),
-            transcript_raw: "Hello world".to_string(),
-            output_final: "Hello world".to_string(),
-            stt_provider: "whispercpp".to_string(),
-            stt_model: "base.en".to_string(),
-            llm_provider: None,
-            llm_model: None,
-            duration_ms: 1000,
-            error: None,
+            source_url: Some("https://docs.example.com/d/notes".to_string()),
+            clipped: true,
+            ..history_item("test-id", "Hello world")
         };
 
         db.insert_history(&item).unwrap();
@@ -309,6 +1838,37 @@ mod tests {
         let retrieved = db.get_history_item("test-id").unwrap().unwrap();
         assert_eq!(retrieved.id, "test-id");
         assert_eq!(retrieved.transcript_raw, "Hello world");
+        assert_eq!(retrieved.stats.word_count, 2);
+        assert_eq!(retrieved.stats.char_count, 11);
+        assert_eq!(retrieved.source_url, item.source_url);
+        assert!(retrieved.clipped);
+    }
+
+    #[test]
+    fn test_summary_only_never_stores_transcript() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(&dir.path().join("test.db")).unwrap();
+
+        let item = HistoryItem {
+            mode_key: "vent".to_string(),
+            audio_path: Some("/path/to/audio.wav".to_string()),
+            transcript_raw: "I told Sam the launch slips a week".to_string(),
+            llm_provider: Some("ollama".to_string()),
+            llm_model: Some("llama3.2".to_string()),
+            window_title: Some("Chat with Sam".to_string()),
+            summary_only: true,
+            source_url: Some("https://chat.example.com/sam".to_string()),
+            ..history_item("vent-id", "- Tell the team the launch slips")
+        };
+        db.insert_history(&item).unwrap();
+
+        let retrieved = db.get_history_item("vent-id").unwrap().unwrap();
+        assert!(retrieved.summary_only);
+        assert_eq!(retrieved.transcript_raw, "");
+        assert_eq!(retrieved.audio_path, None);
+        assert_eq!(retrieved.window_title, None);
+        assert_eq!(retrieved.source_url, None);
+        assert_eq!(retrieved.output_final, item.output_final);
     }
 
     #[test]
@@ -319,20 +1879,7 @@ mod tests {
 
         // Insert 5 items
         for i in 0..5 {
-            let item = HistoryItem {
-                id: format!("test-id-{}", i),
-                created_at: Utc::now(),
-                mode_key: "voice_to_text".to_string(),
-                audio_path: None,
-                transcript_raw: format!("Item {}", i),
-                output_final: format!("Item {}", i),
-                stt_provider: "whispercpp".to_string(),
-                stt_model: "base.en".to_string(),
-                llm_provider: None,
-                llm_model: None,
-                duration_ms: 1000,
-                error: None,
-            };
+            let item = history_item(&format!("test-id-{}", i), &format!("Item {}", i));
             db.insert_history(&item).unwrap();
         }
 
@@ -349,25 +1896,398 @@ mod tests {
         let path = dir.path().join("test.db");
         let db = Database::new(&path).unwrap();
 
-        let item = HistoryItem {
-            id: "test-id".to_string(),
-            created_at: Utc::now(),
-            mode_key: "voice_to_text".to_string(),
-            audio_path: None,
-            transcript_raw: "Hello".to_string(),
-            output_final: "Hello".to_string(),
-            stt_provider: "whispercpp".to_string(),
-            stt_model: "base.en".to_string(),
-            llm_provider: None,
-            llm_model: None,
-            duration_ms: 1000,
-            error: None,
-        };
+        let item = history_item("test-id", "Hello");
 
         db.insert_history(&item).unwrap();
         assert!(db.get_history_item("test-id").unwrap().is_some());
 
+        // Deleting moves the item to the trash
         db.delete_history("test-id").unwrap();
-        assert!(db.get_history_item("test-id").unwrap().is_none());
+        assert!(db.get_history_item("test-id").unwrap().unwrap().deleted_at.is_some());
+    }
+
+    #[test]
+    fn test_revisions() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        let item = HistoryItem {
+            transcript_raw: "meet at noon".to_string(),
+            ..history_item("test-id", "Meet at noon.")
+        };
+        db.insert_history(&item).unwrap();
+
+        let edited = db.edit_history("test-id", "Meet at half past noon.").unwrap();
+        assert_eq!(edited.stats.word_count, 5);
+        // Saving the same text again is not a new revision
+        db.edit_history("test-id", "Meet at half past noon.").unwrap();
+
+        let mut reprocessed = edited.clone();
+        reprocessed.mode_key = "message".to_string();
+        reprocessed.output_final = "Let's meet at 12:30.".to_string();
+        db.update_history(&reprocessed).unwrap();
+
+        let revisions = db.get_revisions("test-id").unwrap();
+        let outputs: Vec<_> = revisions
+            .iter()
+            .map(|r| (r.revision, r.output_final.as_str(), r.replaced_by))
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                (1, "Meet at noon.", RevisionSource::Edit),
+                (2, "Meet at half past noon.", RevisionSource::Reprocess),
+            ]
+        );
+        assert_eq!(db.get_history_item("test-id").unwrap().unwrap().output_final, "Let's meet at 12:30.");
+        // Edited by hand at some point, so it counts as corrected
+        assert_eq!(db.get_corrected_outputs(10).unwrap(), vec!["Let's meet at 12:30."]);
+    }
+
+    #[test]
+    fn test_retranscribe_history() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        let item = HistoryItem {
+            audio_path: Some("/audio/test-id.wav".to_string()),
+            transcript_raw: "meet at the peer".to_string(),
+            stt_model: "tiny.en".to_string(),
+            ..history_item("test-id", "Meet at the peer.")
+        };
+        db.insert_history(&item).unwrap();
+
+        let mut retranscribed = item.clone();
+        retranscribed.transcript_raw = "meet at the pier".to_string();
+        retranscribed.output_final = "Meet at the pier.".to_string();
+        retranscribed.stt_model = "large-v3".to_string();
+        db.retranscribe_history(&retranscribed).unwrap();
+
+        let stored = db.get_history_item("test-id").unwrap().unwrap();
+        assert_eq!(stored.transcript_raw, "meet at the pier");
+        assert_eq!(stored.output_final, "Meet at the pier.");
+        assert_eq!(stored.stt_model, "large-v3");
+
+        let revisions = db.get_revisions("test-id").unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].replaced_by, RevisionSource::Retranscribe);
+        assert_eq!(revisions[0].output_final, "Meet at the peer.");
+        assert_eq!(revisions[0].transcript_raw.as_deref(), Some("meet at the peer"));
+        assert_eq!(revisions[0].stt_model.as_deref(), Some("tiny.en"));
+    }
+
+    #[test]
+    fn test_monthly_spend() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        let usage = [
+            ("a", "2025-01-10T10:00:00+00:00", Some((100, 20, Some(0.01)))),
+            ("b", "2025-01-20T10:00:00+00:00", Some((50, 10, None))),
+            ("c", "2025-02-01T10:00:00+00:00", Some((10, 5, Some(0.002)))),
+            ("d", "2025-02-02T10:00:00+00:00", None),
+        ];
+        for (id, created_at, tokens) in usage {
+            let item = HistoryItem {
+                created_at: DateTime::parse_from_rfc3339(created_at).unwrap().with_timezone(&Utc),
+                prompt_tokens: tokens.map(|(p, _, _)| p),
+                completion_tokens: tokens.map(|(_, c, _)| c),
+                cost_usd: tokens.and_then(|(_, _, cost)| cost),
+                cost_saved_usd: tokens.is_none().then_some(0.001),
+                ..history_item(id, "Hello")
+            };
+            db.insert_history(&item).unwrap();
+        }
+
+        let spend = db.get_monthly_spend().unwrap();
+        assert_eq!(spend.len(), 2);
+        assert_eq!(spend[0].month, "2025-02");
+        assert_eq!(spend[0].items, 1);
+        assert_eq!(spend[0].skipped, 1);
+        assert!((spend[0].saved_usd - 0.001).abs() < 1e-9);
+        assert_eq!(spend[1].month, "2025-01");
+        assert_eq!((spend[1].prompt_tokens, spend[1].completion_tokens), (150, 30));
+        assert!((spend[1].cost_usd - 0.01).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_history_stats() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        let items = [
+            ("a", "2025-01-10T10:00:00+00:00", "message", "one two three four five six", 60_000, None),
+            ("b", "2025-01-11T10:00:00+00:00", "email", "one two three", 30_000, None),
+            ("c", "2025-01-12T10:00:00+00:00", "email", "failed", 30_000, Some(ErrorStage::Stt)),
+            ("d", "2025-03-01T10:00:00+00:00", "email", "later", 30_000, None),
+        ];
+        for (id, created_at, mode_key, output, duration_ms, error) in items {
+            let item = HistoryItem {
+                created_at: DateTime::parse_from_rfc3339(created_at).unwrap().with_timezone(&Utc),
+                mode_key: mode_key.to_string(),
+                duration_ms,
+                error: error.map(|stage| ErrorRecord::new(stage, None, &AppError::Provider("boom".to_string()))),
+                latency: vec![LatencySpan {
+                    name: "dictation".to_string(),
+                    start_ms: 0,
+                    duration_ms: if id == "a" { 400 } else { 800 },
+                    depth: 0,
+                }],
+                ..history_item(id, output)
+            };
+            db.insert_history(&item).unwrap();
+        }
+
+        let january = StatsRange {
+            from: Some(DateTime::parse_from_rfc3339("2025-01-01T00:00:00+00:00").unwrap().with_timezone(&Utc)),
+            to: Some(DateTime::parse_from_rfc3339("2025-02-01T00:00:00+00:00").unwrap().with_timezone(&Utc)),
+        };
+        let stats = db.get_stats(&january).unwrap();
+        assert_eq!(stats.recordings, 2);
+        assert_eq!(stats.words, 9);
+        assert!((stats.audio_minutes - 1.5).abs() < 1e-9);
+        assert_eq!(stats.words_per_minute, Some(6.0));
+        assert_eq!(stats.avg_latency_ms, Some(600.0));
+        assert_eq!(stats.by_mode.len(), 2);
+        assert_eq!(stats.by_provider[0].key, "whispercpp");
+
+        let all = db.get_stats(&StatsRange::default()).unwrap();
+        assert_eq!(all.recordings, 3);
+
+        let failed = db.get_failed_history(Some(ErrorStage::Stt), 10, 0).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].error.as_ref().unwrap().code, "provider");
+        assert!(db.get_failed_history(Some(ErrorStage::Llm), 10, 0).unwrap().is_empty());
+
+        // Free-text errors from older versions are converted on open
+        db.writer()
+            .execute("UPDATE history_items SET error = 'boom' WHERE id = 'd'", [])
+            .unwrap();
+        drop(db);
+        let db = Database::new(&path).unwrap();
+        let legacy = db.get_failed_history(Some(ErrorStage::Other), 10, 0).unwrap();
+        assert_eq!(legacy[0].error.as_ref().unwrap().message, "boom");
+        assert_eq!(db.get_failed_history(None, 10, 0).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_trash() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        for id in ["kept", "trashed"] {
+            let item = HistoryItem {
+                audio_path: Some(format!("/audio/{}.wav", id)),
+                transcript_raw: "see you at the station".to_string(),
+                ..history_item(id, "See you at the station.")
+            };
+            db.insert_history(&item).unwrap();
+        }
+        let suggestions = db.suggest_phrases("see you at", 5).unwrap();
+        assert!(!suggestions.is_empty());
+
+        db.delete_history("trashed").unwrap();
+        let ids: Vec<_> = db.get_history(10, 0).unwrap().into_iter().map(|i| i.id).collect();
+        assert_eq!(ids, vec!["kept"]);
+        assert_eq!(db.get_trash(10, 0).unwrap()[0].id, "trashed");
+        assert!(db.suggest_phrases("see you at", 5).unwrap().is_empty());
+
+        db.restore_history("trashed").unwrap();
+        assert_eq!(db.get_history_count().unwrap(), 2);
+        assert_eq!(db.suggest_phrases("see you at", 5).unwrap(), suggestions);
+        assert!(db.restore_history("kept").is_err());
+
+        db.delete_history("trashed").unwrap();
+        // Only items trashed before the cutoff are purged
+        let cutoff = Utc::now() - chrono::Duration::days(30);
+        assert!(db.purge_history(None, Some(cutoff)).unwrap().is_empty());
+        assert_eq!(db.purge_history(None, None).unwrap(), vec!["/audio/trashed.wav"]);
+        assert!(db.get_history_item("trashed").unwrap().is_none());
+        assert!(db.get_trash(10, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_suggest_phrases() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        let outputs = [
+            "Let me know if you have any questions.",
+            "Great, let me know if you have time tomorrow.",
+            "Let me know if you have any questions!",
+        ];
+        for (i, output) in outputs.iter().enumerate() {
+            let item = history_item(&format!("item-{}", i), output);
+            db.insert_history(&item).unwrap();
+        }
+
+        let suggestions = db.suggest_phrases("Thanks! Let me know if you ha", 3).unwrap();
+        assert_eq!((suggestions[0].completion.as_str(), suggestions[0].count), ("ve", 3));
+        assert_eq!((suggestions[1].completion.as_str(), suggestions[1].count), ("ve any questions", 2));
+        // "time tomorrow" was only dictated once
+        assert!(suggestions.iter().all(|s| !s.completion.contains("tomorrow")));
+
+        // Deleting a dictation forgets its phrases
+        db.delete_history("item-0").unwrap();
+        let suggestions = db.suggest_phrases("know if you have ", 3).unwrap();
+        assert!(suggestions.iter().all(|s| !s.completion.contains("questions")));
+    }
+
+    #[test]
+    fn test_journal_append_and_summary() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        db.append_journal("2025-03-01", "[09:00] First").unwrap();
+        db.append_journal("2025-03-01", "[17:30] Second").unwrap();
+        db.append_journal("2025-03-02", "[08:00] Next day").unwrap();
+
+        let entry = db.get_journal_entry("2025-03-01").unwrap().unwrap();
+        assert_eq!(entry.content, "[09:00] First\n[17:30] Second");
+        assert_eq!(entry.entry_count, 2);
+        assert!(db.get_journal_entry("2025-03-05").unwrap().is_none());
+
+        let pending = db.get_unsummarized_journal_entries("2025-03-02").unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].date, "2025-03-01");
+
+        db.set_journal_summary("2025-03-01", "Two things happened.").unwrap();
+        assert!(db.get_unsummarized_journal_entries("2025-03-02").unwrap().is_empty());
+
+        // A late dictation invalidates the summary
+        db.append_journal("2025-03-01", "[23:59] Late").unwrap();
+        assert!(db.get_journal_entry("2025-03-01").unwrap().unwrap().summary.is_none());
+    }
+
+    #[test]
+    fn test_model_pick_stats() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        let candidates = vec![
+            ("ollama".to_string(), "llama3.2".to_string()),
+            ("openai".to_string(), "gpt-4o-mini".to_string()),
+        ];
+        db.record_model_picks("a", "message", &candidates, 1).unwrap();
+        db.record_model_picks("b", "message", &candidates, 1).unwrap();
+        db.record_model_picks("c", "email", &candidates, 0).unwrap();
+
+        let stats = db.get_model_pick_stats(Some("message")).unwrap();
+        assert_eq!(stats[0].model, "gpt-4o-mini");
+        assert_eq!((stats[0].shown, stats[0].chosen), (2, 2));
+        assert_eq!((stats[1].shown, stats[1].chosen), (2, 0));
+
+        let all = db.get_model_pick_stats(None).unwrap();
+        assert_eq!((all[0].shown, all[0].chosen), (3, 2));
+    }
+
+    #[test]
+    fn test_batch_progress() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        let task = BatchTask::Reprocess {
+            mode_key: "message".to_string(),
+        };
+        let ids: Vec<String> = ["c", "a", "b"].iter().map(|id| id.to_string()).collect();
+        let job = db.create_batch(&task, &ids).unwrap();
+        assert_eq!((job.total, job.done, job.status), (3, 0, BatchStatus::Running));
+        assert_eq!(db.pending_batch_items(&job.id).unwrap(), ids);
+
+        db.finish_batch_item(&job.id, "c", None).unwrap();
+        db.finish_batch_item(&job.id, "a", Some("History item not found")).unwrap();
+        db.set_batch_status(&job.id, BatchStatus::Paused).unwrap();
+
+        // What a restart would pick up again
+        let job = db.get_batch(&job.id).unwrap().unwrap();
+        assert_eq!((job.done, job.failed, job.status), (2, 1, BatchStatus::Paused));
+        assert_eq!(job.task, task);
+        assert_eq!(db.pending_batch_items(&job.id).unwrap(), vec!["b"]);
+        assert_eq!(db.get_batches().unwrap().len(), 1);
+
+        db.delete_batch(&job.id).unwrap();
+        assert!(db.get_batch(&job.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_watched_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        assert!(!db.is_watched_file_processed("/memos/a.wav", 1000).unwrap());
+        db.record_watched_file("/memos/a.wav", 1000, Some("item"), None).unwrap();
+        db.record_watched_file("/memos/b.wav", 1000, None, Some("Unsupported")).unwrap();
+        assert!(db.is_watched_file_processed("/memos/a.wav", 1000).unwrap());
+        assert!(db.is_watched_file_processed("/memos/b.wav", 1000).unwrap());
+        // A file replaced since is new again
+        assert!(!db.is_watched_file_processed("/memos/a.wav", 2000).unwrap());
+    }
+
+    #[test]
+    fn test_alternatives_roll() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+
+        let segments = vec![SegmentAlternatives {
+            text: "meet at the pier".to_string(),
+            alternatives: vec!["meet at the peer".to_string()],
+            start_ms: 0,
+            end_ms: 1200,
+        }];
+        db.set_alternatives("first", &segments).unwrap();
+        assert_eq!(db.get_alternatives("first").unwrap(), segments);
+        assert!(db.get_alternatives("unknown").unwrap().is_empty());
+
+        // Only the most recent dictations keep theirs
+        for i in 0..MAX_ALTERNATIVE_ITEMS {
+            db.set_alternatives(&i.to_string(), &segments).unwrap();
+        }
+        assert!(db.get_alternatives("first").unwrap().is_empty());
+        assert_eq!(db.get_alternatives("0").unwrap(), segments);
+    }
+
+    #[test]
+    fn test_reads_alongside_write() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let db = Database::new(&path).unwrap();
+        db.append_journal("2025-03-01", "first").unwrap();
+
+        // An open write transaction does not block readers
+        let writer = db.writer();
+        writer.execute_batch("BEGIN IMMEDIATE; DELETE FROM journal_entries;").unwrap();
+        assert!(db.get_journal_entry("2025-03-01").unwrap().is_some());
+        writer.execute_batch("ROLLBACK").unwrap();
+        drop(writer);
+
+        assert_eq!(db.readers.lock().unwrap().idle.len(), 1);
+
+        db.checkpoint().unwrap();
+        let wal = std::fs::metadata(dir.path().join("test.db-wal")).unwrap();
+        assert_eq!(wal.len(), 0);
+    }
+
+    #[test]
+    fn test_is_encrypted() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        assert!(!is_encrypted(&path));
+
+        Database::new(&path).unwrap();
+        assert!(!is_encrypted(&path));
+
+        std::fs::write(&path, [0x5a; 64]).unwrap();
+        assert!(is_encrypted(&path));
     }
 }
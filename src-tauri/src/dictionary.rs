@@ -0,0 +1,314 @@
+//! Replacement rules and glossaries
+//!
+//! Replacements fix words the STT model keeps getting wrong ("cube control"
+//! to "kubectl") in every transcript. Glossary terms are domain vocabulary
+//! given to the LLM so rewrites keep their spelling. Both can be exported
+//! to a portable JSON file and merged into another installation, so a team
+//! can share one dictionary; conflicting entries are reported rather than
+//! silently overwritten.
+
+use crate::error::{AppError, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+
+/// Identifies dictionary files
+const FILE_FORMAT: &str = "whispertray-dictionary";
+
+/// Current dictionary file version
+const FILE_VERSION: u32 = 1;
+
+/// A word or phrase replaced in every transcript
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replacement {
+    /// Matched as whole words, ignoring case
+    pub from: String,
+    pub to: String,
+}
+
+/// Domain term whose spelling the LLM keeps
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GlossaryTerm {
+    pub term: String,
+    /// What the term means, when it helps the LLM use it correctly
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Replacement rules and glossary
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dictionary {
+    pub replacements: Vec<Replacement>,
+    pub glossary: Vec<GlossaryTerm>,
+}
+
+/// Portable dictionary file
+#[derive(Debug, Serialize, Deserialize)]
+struct DictionaryFile {
+    format: String,
+    version: u32,
+    #[serde(flatten)]
+    dictionary: Dictionary,
+}
+
+fn same_key(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+/// Compiled pattern for each rule seen, so a rule is compiled only once
+static PATTERNS: LazyLock<Mutex<HashMap<String, Option<Regex>>>> = LazyLock::new(Default::default);
+
+fn pattern(from: &str) -> Option<Regex> {
+    // \b only applies next to word characters, e.g. not after "C++"
+    let edge = |c: Option<char>| if c.is_some_and(|c| c.is_alphanumeric() || c == '_') { r"\b" } else { "" };
+    let from = from.trim();
+    let source = format!(
+        "{}{}{}",
+        edge(from.chars().next()),
+        regex::escape(from),
+        edge(from.chars().last())
+    );
+    RegexBuilder::new(&source).case_insensitive(true).build().ok()
+}
+
+impl Dictionary {
+    /// Apply the replacement rules to a transcript, in order
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        let mut patterns = PATTERNS.lock().unwrap_or_else(|e| e.into_inner());
+        for replacement in self.replacements.iter().filter(|r| !r.from.trim().is_empty()) {
+            let compiled = patterns
+                .entry(replacement.from.clone())
+                .or_insert_with(|| pattern(&replacement.from));
+            if let Some(pattern) = compiled {
+                text = pattern.replace_all(&text, regex::NoExpand(&replacement.to)).into_owned();
+            }
+        }
+        text
+    }
+
+    /// Add the glossary to a rendered LLM prompt
+    pub fn apply_to_prompt(&self, prompt: String) -> String {
+        if self.glossary.is_empty() {
+            return prompt;
+        }
+        let terms: Vec<String> = self
+            .glossary
+            .iter()
+            .map(|entry| match entry.description.as_deref().filter(|d| !d.trim().is_empty()) {
+                Some(description) => format!("- {}: {}", entry.term, description),
+                None => format!("- {}", entry.term),
+            })
+            .collect();
+        format!(
+            "{}\n\nKeep the spelling of these terms exactly as written:\n{}",
+            prompt,
+            terms.join("\n")
+        )
+    }
+
+    /// Merge `incoming` into this dictionary
+    ///
+    /// New entries are added and identical ones skipped. An entry with the
+    /// same key but a different value is a conflict, resolved by `policy`.
+    pub fn merge(&mut self, incoming: Dictionary, policy: ConflictPolicy) -> ImportReport {
+        let mut report = ImportReport::default();
+
+        for replacement in incoming.replacements {
+            match self.replacements.iter_mut().find(|r| same_key(&r.from, &replacement.from)) {
+                None => {
+                    self.replacements.push(replacement);
+                    report.added += 1;
+                }
+                Some(existing) if existing.to == replacement.to => report.unchanged += 1,
+                Some(existing) => {
+                    report.conflicts.push(Conflict {
+                        kind: EntryKind::Replacement,
+                        key: existing.from.clone(),
+                        existing: existing.to.clone(),
+                        incoming: replacement.to.clone(),
+                    });
+                    if policy == ConflictPolicy::Overwrite {
+                        *existing = replacement;
+                    }
+                }
+            }
+        }
+
+        for term in incoming.glossary {
+            match self.glossary.iter_mut().find(|t| same_key(&t.term, &term.term)) {
+                None => {
+                    self.glossary.push(term);
+                    report.added += 1;
+                }
+                Some(existing) if existing.term == term.term && existing.description == term.description => {
+                    report.unchanged += 1
+                }
+                Some(existing) => {
+                    let describe = |t: &GlossaryTerm| match &t.description {
+                        Some(description) => format!("{}: {}", t.term, description),
+                        None => t.term.clone(),
+                    };
+                    report.conflicts.push(Conflict {
+                        kind: EntryKind::Glossary,
+                        key: existing.term.clone(),
+                        existing: describe(existing),
+                        incoming: describe(&term),
+                    });
+                    if policy == ConflictPolicy::Overwrite {
+                        *existing = term;
+                    }
+                }
+            }
+        }
+
+        report.policy = policy;
+        report
+    }
+}
+
+/// What an import does with entries that differ from existing ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Keep the existing entry
+    #[default]
+    Keep,
+    /// Replace it with the imported one
+    Overwrite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Replacement,
+    Glossary,
+}
+
+/// An imported entry that differs from an existing one with the same key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub kind: EntryKind,
+    pub key: String,
+    pub existing: String,
+    pub incoming: String,
+}
+
+/// Outcome of merging an imported dictionary
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub added: usize,
+    /// Entries already present with the same value
+    pub unchanged: usize,
+    pub conflicts: Vec<Conflict>,
+    /// How the conflicts were resolved
+    pub policy: ConflictPolicy,
+}
+
+/// Write a dictionary to a portable file
+pub fn export(dictionary: &Dictionary, path: &Path) -> Result<()> {
+    let file = DictionaryFile {
+        format: FILE_FORMAT.to_string(),
+        version: FILE_VERSION,
+        dictionary: dictionary.clone(),
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&file)?)?;
+    Ok(())
+}
+
+/// Read a dictionary file written by `export`
+pub fn read(path: &Path) -> Result<Dictionary> {
+    let file: DictionaryFile = serde_json::from_str(&std::fs::read_to_string(path)?)
+        .map_err(|e| AppError::Config(format!("Not a dictionary file: {}", e)))?;
+    if file.format != FILE_FORMAT {
+        return Err(AppError::Config(format!("Not a dictionary file: {}", file.format)));
+    }
+    if file.version > FILE_VERSION {
+        return Err(AppError::Config(format!(
+            "Dictionary file version {} is newer than supported ({})",
+            file.version, FILE_VERSION
+        )));
+    }
+    Ok(file.dictionary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn replacement(from: &str, to: &str) -> Replacement {
+        Replacement {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    fn term(term: &str, description: Option<&str>) -> GlossaryTerm {
+        GlossaryTerm {
+            term: term.to_string(),
+            description: description.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_apply_replacements() {
+        let dictionary = Dictionary {
+            replacements: vec![replacement("cube control", "kubectl"), replacement("c++", "C++"), replacement("pt", "patient")],
+            glossary: Vec::new(),
+        };
+        assert_eq!(
+            dictionary.apply("Run Cube Control apply, write c++ code, the pt is stable, optimal"),
+            "Run kubectl apply, write C++ code, the patient is stable, optimal"
+        );
+        // Replacement text is literal
+        let dollars = Dictionary {
+            replacements: vec![replacement("bucks", "$1")],
+            glossary: Vec::new(),
+        };
+        assert_eq!(dollars.apply("ten bucks"), "ten $1");
+    }
+
+    #[test]
+    fn test_merge_reports_conflicts() {
+        let mut dictionary = Dictionary {
+            replacements: vec![replacement("bp", "blood pressure"), replacement("hr", "heart rate")],
+            glossary: vec![term("tachycardia", None)],
+        };
+        let incoming = Dictionary {
+            replacements: vec![replacement("BP", "blood pressure"), replacement("hr", "human resources"), replacement("ecg", "ECG")],
+            glossary: vec![term("tachycardia", Some("fast heart rate")), term("stat", None)],
+        };
+
+        let mut kept = dictionary.clone();
+        let report = kept.merge(incoming.clone(), ConflictPolicy::Keep);
+        assert_eq!((report.added, report.unchanged), (2, 1));
+        let keys: Vec<_> = report.conflicts.iter().map(|c| (c.kind, c.key.as_str())).collect();
+        assert_eq!(keys, vec![(EntryKind::Replacement, "hr"), (EntryKind::Glossary, "tachycardia")]);
+        assert_eq!(report.conflicts[0].incoming, "human resources");
+        assert_eq!(kept.replacements[1].to, "heart rate");
+        assert_eq!(kept.replacements.len(), 3);
+
+        dictionary.merge(incoming, ConflictPolicy::Overwrite);
+        assert_eq!(dictionary.replacements[1].to, "human resources");
+        assert_eq!(dictionary.glossary[0].description.as_deref(), Some("fast heart rate"));
+    }
+
+    #[test]
+    fn test_export_and_read() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("team.json");
+        let dictionary = Dictionary {
+            replacements: vec![replacement("cube control", "kubectl")],
+            glossary: vec![term("Kubernetes", Some("container orchestrator"))],
+        };
+        export(&dictionary, &path).unwrap();
+        assert_eq!(read(&path).unwrap(), dictionary);
+
+        std::fs::write(&path, r#"{"format": "something-else", "version": 1}"#).unwrap();
+        assert!(read(&path).is_err());
+    }
+}
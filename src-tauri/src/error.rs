@@ -1,5 +1,7 @@
 //! Error types for WhisperTray
 
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Main error type for WhisperTray
@@ -26,6 +28,16 @@ pub enum AppError {
     #[error("Provider error: {0}")]
     Provider(String),
 
+    #[error("Provider unavailable: {0}")]
+    ProviderUnavailable(String),
+
+    #[error("{provider} error ({status}): {body}")]
+    Status {
+        provider: String,
+        status: u16,
+        body: String,
+    },
+
     #[error("Configuration error: {0}")]
     Config(String),
 
@@ -52,6 +64,125 @@ pub enum AppError {
 
     #[error("Operation cancelled")]
     Cancelled,
+
+    #[error("Read-only mode: {0} is disabled")]
+    ReadOnly(String),
+}
+
+impl AppError {
+    /// Stable identifier for the kind of error, e.g. `provider` or `http_429`
+    pub fn code(&self) -> String {
+        match self {
+            AppError::Audio(_) => "audio".to_string(),
+            AppError::Transcription(_) => "transcription".to_string(),
+            AppError::Database(_) => "database".to_string(),
+            AppError::Io(_) => "io".to_string(),
+            AppError::Json(_) => "json".to_string(),
+            AppError::ModeNotFound(_) => "mode_not_found".to_string(),
+            AppError::Provider(_) => "provider".to_string(),
+            AppError::ProviderUnavailable(_) => "provider_unavailable".to_string(),
+            AppError::Status { status, .. } => format!("http_{}", status),
+            AppError::Config(_) => "config".to_string(),
+            AppError::Keyring(_) => "keyring".to_string(),
+            AppError::Clipboard(_) => "clipboard".to_string(),
+            AppError::Tauri(_) => "tauri".to_string(),
+            AppError::Http(e) => match e.status() {
+                Some(status) => format!("http_{}", status.as_u16()),
+                None => "http".to_string(),
+            },
+            AppError::RecordingInProgress => "recording_in_progress".to_string(),
+            AppError::NoRecordingInProgress => "no_recording_in_progress".to_string(),
+            AppError::ModelNotLoaded => "model_not_loaded".to_string(),
+            AppError::Cancelled => "cancelled".to_string(),
+            AppError::ReadOnly(_) => "read_only".to_string(),
+        }
+    }
+
+    /// Whether trying again unchanged may succeed (network trouble, busy or
+    /// flaky providers), as opposed to errors that need a settings change
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AppError::Http(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.status().is_some_and(|s| s.is_server_error() || s.as_u16() == 429)
+            }
+            AppError::Status { status, .. } => *status == 429 || *status >= 500,
+            AppError::Transcription(_)
+            | AppError::Provider(_)
+            | AppError::ProviderUnavailable(_)
+            | AppError::ModelNotLoaded => true,
+            _ => false,
+        }
+    }
+}
+
+/// Pipeline stage a dictation failed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorStage {
+    /// Stopping the stream or saving the recording
+    Audio,
+    Stt,
+    /// LLM processing, including the model picker and length limits
+    Llm,
+    /// Pasting, journaling or sending the output elsewhere
+    Output,
+    Other,
+}
+
+impl ErrorStage {
+    /// Stage of the latency span that was open when the error happened
+    pub fn of_span(name: &str) -> Self {
+        match name {
+            "stream_stop" | "save_audio" | "load_audio" => ErrorStage::Audio,
+            "stt" => ErrorStage::Stt,
+            "llm" | "pick" | "limit" => ErrorStage::Llm,
+            "insertion" | "journal" | "remote" => ErrorStage::Output,
+            _ => ErrorStage::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorStage::Audio => "audio",
+            ErrorStage::Stt => "stt",
+            ErrorStage::Llm => "llm",
+            ErrorStage::Output => "output",
+            ErrorStage::Other => "other",
+        }
+    }
+}
+
+/// Why a dictation failed, as stored with its history item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorRecord {
+    pub stage: ErrorStage,
+    /// Provider in use at that stage, e.g. "openai"
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// See `AppError::code`
+    pub code: String,
+    pub message: String,
+    pub retryable: bool,
+    /// Retries made before giving up, see `retry`
+    #[serde(default)]
+    pub retries: u32,
+    pub at: DateTime<Utc>,
+}
+
+impl ErrorRecord {
+    pub fn new(stage: ErrorStage, provider: Option<String>, error: &AppError) -> Self {
+        Self {
+            stage,
+            provider,
+            code: error.code(),
+            message: error.to_string(),
+            retryable: error.is_retryable(),
+            retries: 0,
+            at: Utc::now(),
+        }
+    }
 }
 
 impl From<AppError> for String {
@@ -0,0 +1,200 @@
+//! Machine-readable pipeline events
+//!
+//! With `event_sink` set, one JSON object per line is written to it as each
+//! pipeline stage completes, so external automations can react to dictations.
+//! The sink may be a FIFO, a regular file (appended to) or a Unix socket.
+//! Events are written from a background thread and dropped, never queued
+//! without bound, while nothing is reading. The same events are broadcast to
+//! the HTTP API's event stream (see `http_api`), with or without a sink.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+
+/// Events buffered for a slow or absent reader before new ones are dropped
+const QUEUE_SIZE: usize = 256;
+
+/// Events broadcast to subscribers, created by the first subscription
+static SUBSCRIBERS: OnceLock<broadcast::Sender<PipelineEvent>> = OnceLock::new();
+
+/// Receive every pipeline event from now on
+///
+/// A subscriber that falls more than `QUEUE_SIZE` events behind misses the oldest.
+pub fn subscribe() -> broadcast::Receiver<PipelineEvent> {
+    SUBSCRIBERS
+        .get_or_init(|| broadcast::channel(QUEUE_SIZE).0)
+        .subscribe()
+}
+
+/// One completed pipeline stage
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineEvent {
+    /// Stage that completed: "recording", "stt", "llm", "output", "completed",
+    /// or "cancelled"/"failed" when the pipeline stopped early; "degraded"
+    /// when local providers stand in for unreachable cloud ones
+    pub stage: String,
+    /// Dictation id, also the history item id
+    pub id: String,
+    pub mode_key: String,
+    pub timestamp: DateTime<Utc>,
+    /// Time spent in the stage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Stage details, e.g. the transcript after "stt"
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub data: Value,
+}
+
+impl PipelineEvent {
+    pub fn new(stage: &str, id: &str, mode_key: &str, duration_ms: Option<u64>, data: Value) -> Self {
+        Self {
+            stage: stage.to_string(),
+            id: id.to_string(),
+            mode_key: mode_key.to_string(),
+            timestamp: Utc::now(),
+            duration_ms,
+            data,
+        }
+    }
+}
+
+/// Destination for pipeline events; does nothing when no sink is configured
+#[derive(Clone, Default)]
+pub struct EventSink {
+    path: Option<PathBuf>,
+    sender: Option<SyncSender<String>>,
+}
+
+impl EventSink {
+    /// Start writing events to `path`, if set
+    pub fn new(path: Option<&str>) -> Self {
+        let Some(path) = path.filter(|p| !p.trim().is_empty()).map(PathBuf::from) else {
+            return Self::default();
+        };
+
+        let (sender, receiver) = mpsc::sync_channel::<String>(QUEUE_SIZE);
+        let writer_path = path.clone();
+        std::thread::spawn(move || {
+            let mut writer: Option<Box<dyn Write>> = None;
+            for line in receiver {
+                if writer.is_none() {
+                    // Opening a FIFO blocks until a reader appears; events queue meanwhile
+                    match open_sink(&writer_path) {
+                        Ok(w) => writer = Some(w),
+                        Err(e) => {
+                            log::debug!("Event sink {:?} unavailable: {}", writer_path, e);
+                            continue;
+                        }
+                    }
+                }
+                if let Some(w) = writer.as_mut() {
+                    if let Err(e) = w.write_all(line.as_bytes()).and_then(|_| w.flush()) {
+                        // The reader went away; reopen for the next event
+                        log::debug!("Event sink {:?} write failed: {}", writer_path, e);
+                        writer = None;
+                    }
+                }
+            }
+        });
+
+        Self {
+            path: Some(path),
+            sender: Some(sender),
+        }
+    }
+
+    /// Path the sink writes to
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Queue an event, dropping it if the sink is backed up
+    pub fn emit(&self, event: PipelineEvent) {
+        if let Some(subscribers) = SUBSCRIBERS.get() {
+            // Fails only when nobody is subscribed
+            let _ = subscribers.send(event.clone());
+        }
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        let line = match serde_json::to_string(&event) {
+            Ok(json) => format!("{}\n", json),
+            Err(e) => {
+                log::warn!("Failed to serialize pipeline event: {}", e);
+                return;
+            }
+        };
+        if let Err(TrySendError::Full(_)) = sender.try_send(line) {
+            log::debug!("Event sink backed up, dropping {} event", event.stage);
+        }
+    }
+}
+
+/// Open a socket, FIFO or file for writing events
+fn open_sink(path: &Path) -> std::io::Result<Box<dyn Write>> {
+    let is_socket = std::fs::metadata(path)
+        .map(|m| m.file_type().is_socket())
+        .unwrap_or(false);
+
+    if is_socket {
+        Ok(Box::new(UnixStream::connect(path)?))
+    } else {
+        let file: File = OpenOptions::new().append(true).create(true).open(path)?;
+        Ok(Box::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixListener;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_event_json_shape() {
+        let event = PipelineEvent::new("stt", "abc", "message", Some(120), json!({ "text": "hi" }));
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["stage"], "stt");
+        assert_eq!(value["duration_ms"], 120);
+        assert_eq!(value["data"]["text"], "hi");
+
+        let bare = serde_json::to_value(PipelineEvent::new("completed", "abc", "message", None, Value::Null)).unwrap();
+        assert!(bare.get("duration_ms").is_none());
+        assert!(bare.get("data").is_none());
+    }
+
+    #[test]
+    fn test_broadcast_without_sink() {
+        let mut events = subscribe();
+        EventSink::default().emit(PipelineEvent::new("stt", "a", "message", None, Value::Null));
+        assert_eq!(events.try_recv().unwrap().stage, "stt");
+    }
+
+    #[test]
+    fn test_writes_lines_to_socket() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("events.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let sink = EventSink::new(path.to_str());
+        sink.emit(PipelineEvent::new("stt", "a", "message", None, Value::Null));
+        sink.emit(PipelineEvent::new("completed", "a", "message", None, Value::Null));
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        let first: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        let second: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(first["stage"], "stt");
+        assert_eq!(second["stage"], "completed");
+    }
+}
@@ -0,0 +1,187 @@
+//! Feature flags for experimental subsystems
+//!
+//! Every flag has a default that depends on the release channel the build
+//! was made for (`WHISPERTRAY_CHANNEL` at build time: stable, beta or
+//! nightly), so a new subsystem can ship switched off on stable while beta
+//! and nightly users try it. `feature_flags` in settings overrides the
+//! default per flag. Unknown keys in it are kept, so a flag missing from one
+//! version is still set when it comes back.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Release channel of a build, least to most experimental
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    Stable,
+    Beta,
+    Nightly,
+}
+
+impl ReleaseChannel {
+    /// Channel this build was made for
+    pub fn current() -> Self {
+        Self::parse(option_env!("WHISPERTRAY_CHANNEL").unwrap_or_default())
+    }
+
+    /// Anything but "beta" or "nightly" is stable
+    fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "nightly" => ReleaseChannel::Nightly,
+            "beta" => ReleaseChannel::Beta,
+            _ => ReleaseChannel::Stable,
+        }
+    }
+}
+
+/// A subsystem that can be switched on or off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    /// Show the LLM's output in the indicator while it is generated
+    StreamingPreview,
+    /// Capture through PipeWire directly when that backend is selected
+    PipeWireBackend,
+    /// Accept text from paired instances on the bridge port
+    PasteBridge,
+    /// Keep the microphone open to catch the words spoken before the hotkey
+    PreRoll,
+    /// Warn and ask the Device portal when the microphone delivers only silence
+    PortalSignalCheck,
+}
+
+struct FlagInfo {
+    flag: Flag,
+    key: &'static str,
+    name: &'static str,
+    description: &'static str,
+    /// On by default in this channel and the more experimental ones
+    default_from: ReleaseChannel,
+}
+
+const FLAGS: &[FlagInfo] = &[
+    FlagInfo {
+        flag: Flag::StreamingPreview,
+        key: "streaming_preview",
+        name: "Streaming AI preview",
+        description: "Show AI output in the indicator as it is generated",
+        default_from: ReleaseChannel::Beta,
+    },
+    FlagInfo {
+        flag: Flag::PipeWireBackend,
+        key: "pipewire_backend",
+        name: "PipeWire audio backend",
+        description: "Record through PipeWire directly when it is chosen as the audio backend",
+        default_from: ReleaseChannel::Beta,
+    },
+    FlagInfo {
+        flag: Flag::PasteBridge,
+        key: "paste_bridge",
+        name: "Paste bridge",
+        description: "Paste text sent by a paired WhisperTray on the bridge port",
+        default_from: ReleaseChannel::Beta,
+    },
+    FlagInfo {
+        flag: Flag::PreRoll,
+        key: "pre_roll",
+        name: "Pre-roll",
+        description: "Keep the microphone open to catch words spoken just before the hotkey",
+        default_from: ReleaseChannel::Beta,
+    },
+    FlagInfo {
+        flag: Flag::PortalSignalCheck,
+        key: "portal_signal_check",
+        name: "Silent microphone check",
+        description: "Warn when the microphone delivers only silence, and ask a sandbox for access",
+        default_from: ReleaseChannel::Beta,
+    },
+];
+
+/// Flags set in settings, by key
+static OVERRIDES: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
+
+/// A flag as shown in settings
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureFlag {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Whether it is on now
+    pub enabled: bool,
+    /// Whether it is on in this channel unless set
+    pub default: bool,
+}
+
+/// Use the flags set in settings
+pub fn set_overrides(overrides: &BTreeMap<String, bool>) {
+    *OVERRIDES.lock().unwrap_or_else(|e| e.into_inner()) = overrides.clone();
+}
+
+fn info(flag: Flag) -> &'static FlagInfo {
+    FLAGS
+        .iter()
+        .find(|info| info.flag == flag)
+        .expect("every flag is listed in FLAGS")
+}
+
+fn is_enabled(
+    info: &FlagInfo,
+    channel: ReleaseChannel,
+    overrides: &BTreeMap<String, bool>,
+) -> bool {
+    overrides
+        .get(info.key)
+        .copied()
+        .unwrap_or(channel >= info.default_from)
+}
+
+/// Whether a flag is on
+pub fn enabled(flag: Flag) -> bool {
+    let overrides = OVERRIDES.lock().unwrap_or_else(|e| e.into_inner());
+    is_enabled(info(flag), ReleaseChannel::current(), &overrides)
+}
+
+/// Every flag with its state
+pub fn list() -> Vec<FeatureFlag> {
+    let channel = ReleaseChannel::current();
+    let overrides = OVERRIDES.lock().unwrap_or_else(|e| e.into_inner());
+    FLAGS
+        .iter()
+        .map(|info| FeatureFlag {
+            key: info.key,
+            name: info.name,
+            description: info.description,
+            enabled: is_enabled(info, channel, &overrides),
+            default: channel >= info.default_from,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_defaults_and_overrides() {
+        let experimental = FlagInfo {
+            flag: Flag::StreamingPreview,
+            key: "experimental",
+            name: "Experimental",
+            description: "",
+            default_from: ReleaseChannel::Beta,
+        };
+        let none = BTreeMap::new();
+        assert!(!is_enabled(&experimental, ReleaseChannel::Stable, &none));
+        assert!(is_enabled(&experimental, ReleaseChannel::Beta, &none));
+        assert!(is_enabled(&experimental, ReleaseChannel::Nightly, &none));
+
+        let off = BTreeMap::from([("experimental".to_string(), false)]);
+        assert!(!is_enabled(&experimental, ReleaseChannel::Nightly, &off));
+
+        assert_eq!(ReleaseChannel::parse("Nightly"), ReleaseChannel::Nightly);
+        assert_eq!(ReleaseChannel::parse(""), ReleaseChannel::Stable);
+        // Every flag can be looked up
+        assert!(FLAGS.iter().all(|flag| info(flag.flag).key == flag.key));
+    }
+}
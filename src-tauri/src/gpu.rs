@@ -0,0 +1,317 @@
+//! GPU memory checks before loading a whisper model
+//!
+//! Builds with a GPU backend (`cuda` or `opencl`) offload whisper.cpp to the
+//! GPU. A model that does not fit in free VRAM makes the driver fail the
+//! allocation or page memory back and forth, so before each load the free
+//! VRAM is read (from NVML through nvidia-smi, or from amdgpu's counters in
+//! sysfs) and the model runs on the CPU, or a smaller downloaded model on
+//! the GPU, when it would not fit. A changed decision is logged, notified
+//! and reported as a degradation.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+/// VRAM left free for the display and other programs
+const VRAM_HEADROOM_BYTES: u64 = 256 * 1024 * 1024;
+
+/// What to do when a model does not fit in free VRAM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GpuFallback {
+    /// Run the chosen model on the CPU: slower, as accurate
+    #[default]
+    Cpu,
+    /// Run the largest downloaded model that fits on the GPU, or the CPU if none does
+    Smaller,
+}
+
+/// Configured fallback, as a `GpuFallback` discriminant
+static GPU_FALLBACK: AtomicU8 = AtomicU8::new(GpuFallback::Cpu as u8);
+
+/// The last placement decided, for diagnostics
+static LAST_PLACEMENT: Mutex<Option<Placement>> = Mutex::new(None);
+
+/// Choose what happens when a model does not fit in free VRAM
+pub fn set_gpu_fallback(fallback: GpuFallback) {
+    GPU_FALLBACK.store(fallback as u8, Ordering::SeqCst);
+}
+
+fn gpu_fallback() -> GpuFallback {
+    if GPU_FALLBACK.load(Ordering::SeqCst) == GpuFallback::Smaller as u8 {
+        GpuFallback::Smaller
+    } else {
+        GpuFallback::Cpu
+    }
+}
+
+/// Where a whisper model is loaded
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Placement {
+    /// The model the mode asked for
+    pub requested_model: String,
+    /// The model loaded, smaller than requested after a fallback
+    pub model: String,
+    pub use_gpu: bool,
+    pub free_vram_bytes: Option<u64>,
+    /// VRAM the requested model needs
+    pub required_vram_bytes: u64,
+    /// Why the requested model is not on the GPU; None when it is
+    pub fallback_reason: Option<String>,
+}
+
+/// Whether this build can offload whisper.cpp to a GPU
+pub fn offload_available() -> bool {
+    cfg!(any(feature = "cuda", feature = "opencl"))
+}
+
+/// Free VRAM on the first GPU, when it can be read
+pub fn free_vram() -> Option<u64> {
+    nvidia_free_vram().or_else(amdgpu_free_vram)
+}
+
+fn nvidia_free_vram() -> Option<u64> {
+    let output = Command::new("nvidia-smi")
+        .args(["--query-gpu=memory.free", "--format=csv,noheader,nounits"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let mib: u64 = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(mib * 1024 * 1024)
+}
+
+fn amdgpu_free_vram() -> Option<u64> {
+    let read =
+        |path: &Path| -> Option<u64> { std::fs::read_to_string(path).ok()?.trim().parse().ok() };
+    let mut cards: Vec<_> = std::fs::read_dir("/sys/class/drm")
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path().join("device"))
+        .collect();
+    cards.sort();
+    cards.iter().find_map(|device| {
+        let total = read(&device.join("mem_info_vram_total"))?;
+        let used = read(&device.join("mem_info_vram_used"))?;
+        Some(total.saturating_sub(used))
+    })
+}
+
+/// VRAM a model of `model_bytes` needs, with its state and some headroom
+fn required_vram(model_bytes: u64) -> u64 {
+    let state = (model_bytes as f64 * crate::providers::stt::STATE_MEMORY_PER_MODEL_BYTE) as u64;
+    model_bytes + state + VRAM_HEADROOM_BYTES
+}
+
+/// Whether `model` only transcribes English, e.g. "small.en" or "base.en-q5_1"
+fn is_english_only(model: &str) -> bool {
+    model.contains(".en")
+}
+
+/// Decide where `model` runs
+///
+/// `smaller` are other downloaded models with their file sizes; only those
+/// of the same kind, English-only or multilingual, stand in for `model`.
+/// Unknown free VRAM leaves the model on the GPU, as whisper.cpp would.
+fn decide(
+    model: &str,
+    model_bytes: u64,
+    free_vram: Option<u64>,
+    fallback: GpuFallback,
+    smaller: &[(String, u64)],
+) -> Placement {
+    let required = required_vram(model_bytes);
+    let mut placement = Placement {
+        requested_model: model.to_string(),
+        model: model.to_string(),
+        use_gpu: true,
+        free_vram_bytes: free_vram,
+        required_vram_bytes: required,
+        fallback_reason: None,
+    };
+    let Some(free) = free_vram.filter(|&free| free < required) else {
+        return placement;
+    };
+
+    let reason = format!(
+        "{} needs {} MiB of VRAM but {} MiB is free",
+        model,
+        required / (1024 * 1024),
+        free / (1024 * 1024)
+    );
+    let fits = smaller
+        .iter()
+        .filter(|(name, bytes)| {
+            is_english_only(name) == is_english_only(model) && *bytes < model_bytes && required_vram(*bytes) <= free
+        })
+        .max_by_key(|(_, bytes)| *bytes);
+    match (fallback, fits) {
+        (GpuFallback::Smaller, Some((name, _))) => placement.model = name.clone(),
+        _ => placement.use_gpu = false,
+    }
+    placement.fallback_reason = Some(reason);
+    placement
+}
+
+/// Decide where the model at `model_path` runs, logging and notifying changes
+///
+/// Builds without a GPU backend always run on the CPU and decide nothing.
+pub fn place(model: &str, model_path: &Path) -> Placement {
+    let model_bytes = std::fs::metadata(model_path).map(|m| m.len()).unwrap_or(0);
+    if !offload_available() {
+        return Placement {
+            requested_model: model.to_string(),
+            model: model.to_string(),
+            use_gpu: false,
+            free_vram_bytes: None,
+            required_vram_bytes: required_vram(model_bytes),
+            fallback_reason: None,
+        };
+    }
+
+    let smaller: Vec<(String, u64)> = crate::providers::stt::downloaded_models()
+        .into_iter()
+        .filter_map(|name| {
+            let bytes = std::fs::metadata(crate::providers::stt::get_model_path(&name).ok()?)
+                .ok()?
+                .len();
+            Some((name, bytes))
+        })
+        .collect();
+    let placement = decide(model, model_bytes, free_vram(), gpu_fallback(), &smaller);
+
+    let mut last = LAST_PLACEMENT.lock().unwrap_or_else(|e| e.into_inner());
+    let changed =
+        last.as_ref().map(|p| (&p.model, p.use_gpu)) != Some((&placement.model, placement.use_gpu));
+    if changed {
+        match &placement.fallback_reason {
+            Some(reason) => {
+                let action = if placement.use_gpu {
+                    format!("using {} on the GPU instead", placement.model)
+                } else {
+                    "running it on the CPU".to_string()
+                };
+                log::warn!("{}; {}", reason, action);
+                crate::notify::send(
+                    "Not enough GPU memory",
+                    &format!("{}; {}.", reason, action),
+                );
+            }
+            None => log::info!(
+                "Loading {} on the GPU ({:?} bytes of VRAM free)",
+                placement.model,
+                placement.free_vram_bytes
+            ),
+        }
+    }
+    *last = Some(placement.clone());
+    placement
+}
+
+/// The model loaded when `requested` was last asked for, after any fallback
+pub fn loaded_model(requested: &str) -> String {
+    last_placement()
+        .filter(|placement| placement.requested_model == requested)
+        .map(|placement| placement.model)
+        .unwrap_or_else(|| requested.to_string())
+}
+
+/// The last placement decided, if a local model was loaded yet
+pub fn last_placement() -> Option<Placement> {
+    LAST_PLACEMENT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_decide() {
+        let smaller = vec![
+            ("small".to_string(), 466 * MIB),
+            ("small.en".to_string(), 466 * MIB),
+            ("base.en".to_string(), 142 * MIB),
+        ];
+
+        // Fits, or free VRAM unknown: stays on the GPU
+        let fits = decide(
+            "medium.en",
+            1500 * MIB,
+            Some(8000 * MIB),
+            GpuFallback::Cpu,
+            &smaller,
+        );
+        assert!(fits.use_gpu && fits.fallback_reason.is_none());
+        assert!(decide("medium.en", 1500 * MIB, None, GpuFallback::Cpu, &smaller).use_gpu);
+
+        let cpu = decide(
+            "medium.en",
+            1500 * MIB,
+            Some(1000 * MIB),
+            GpuFallback::Cpu,
+            &smaller,
+        );
+        assert_eq!((cpu.model.as_str(), cpu.use_gpu), ("medium.en", false));
+        assert!(cpu.fallback_reason.unwrap().contains("1000 MiB is free"));
+
+        // The largest smaller model that fits
+        let smaller_model = decide(
+            "medium.en",
+            1500 * MIB,
+            Some(1000 * MIB),
+            GpuFallback::Smaller,
+            &smaller,
+        );
+        assert_eq!(
+            (smaller_model.model.as_str(), smaller_model.use_gpu),
+            ("small.en", true)
+        );
+
+        // A multilingual model only gives way to a multilingual one
+        let multilingual = decide(
+            "medium",
+            1500 * MIB,
+            Some(1000 * MIB),
+            GpuFallback::Smaller,
+            &smaller,
+        );
+        assert_eq!(multilingual.model, "small");
+        let none_multilingual = decide(
+            "medium",
+            1500 * MIB,
+            Some(1000 * MIB),
+            GpuFallback::Smaller,
+            &smaller[1..],
+        );
+        assert_eq!(
+            (none_multilingual.model.as_str(), none_multilingual.use_gpu),
+            ("medium", false)
+        );
+
+        // Nothing fits: the CPU after all
+        let none_fit = decide(
+            "medium.en",
+            1500 * MIB,
+            Some(300 * MIB),
+            GpuFallback::Smaller,
+            &smaller,
+        );
+        assert_eq!(
+            (none_fit.model.as_str(), none_fit.use_gpu),
+            ("medium.en", false)
+        );
+    }
+}
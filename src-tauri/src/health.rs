@@ -0,0 +1,318 @@
+//! Watchdog self-check and health status
+//!
+//! Periodically verifies that the hotkey listener, audio host and tray are
+//! alive, repairs what it can, and publishes the result for the tray, the
+//! frontend and an optional localhost `/health` endpoint. The same port takes
+//! browser tab reports from the companion extension (see `browser_tab`).
+
+use crate::hotkey::DEFAULT_HOTKEY;
+use chrono::{DateTime, Utc};
+use cpal::traits::HostTrait;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Time between watchdog checks
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Delay before the first check, so setup can finish
+const WATCHDOG_STARTUP_DELAY: Duration = Duration::from_secs(5);
+
+/// Largest request read, enough for a browser tab report
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+/// Health of a single component
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ComponentHealth {
+    pub healthy: bool,
+    /// What is wrong, or what was repaired
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Whether the watchdog repaired the component during the last check
+    pub repaired: bool,
+}
+
+impl ComponentHealth {
+    fn ok() -> Self {
+        Self {
+            healthy: true,
+            detail: None,
+            repaired: false,
+        }
+    }
+
+    fn repaired(detail: impl Into<String>) -> Self {
+        Self {
+            healthy: true,
+            detail: Some(detail.into()),
+            repaired: true,
+        }
+    }
+
+    fn failed(detail: impl Into<String>) -> Self {
+        Self {
+            healthy: false,
+            detail: Some(detail.into()),
+            repaired: false,
+        }
+    }
+}
+
+impl Default for ComponentHealth {
+    fn default() -> Self {
+        Self::ok()
+    }
+}
+
+/// Result of the latest watchdog check
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HealthStatus {
+    pub hotkey: ComponentHealth,
+    pub audio: ComponentHealth,
+    pub tray: ComponentHealth,
+    /// None until the first check has run
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+impl HealthStatus {
+    pub fn is_healthy(&self) -> bool {
+        self.hotkey.healthy && self.audio.healthy && self.tray.healthy
+    }
+
+    /// Names of unhealthy components
+    pub fn problems(&self) -> Vec<&'static str> {
+        [
+            ("hotkey", &self.hotkey),
+            ("audio", &self.audio),
+            ("tray", &self.tray),
+        ]
+        .into_iter()
+        .filter(|(_, component)| !component.healthy)
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
+
+/// Latest health status, shared with commands and the HTTP endpoint
+#[derive(Debug, Clone, Default)]
+pub struct HealthMonitor(Arc<Mutex<HealthStatus>>);
+
+impl HealthMonitor {
+    pub fn status(&self) -> HealthStatus {
+        self.0.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    fn set(&self, status: HealthStatus) {
+        if let Ok(mut current) = self.0.lock() {
+            *current = status;
+        }
+    }
+}
+
+/// Start the periodic watchdog
+pub fn spawn_watchdog(handle: AppHandle, monitor: HealthMonitor) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(WATCHDOG_STARTUP_DELAY).await;
+
+        loop {
+            let status = check_and_repair(&handle).await;
+            let was_healthy = monitor.status().is_healthy();
+
+            if !status.is_healthy() {
+                log::warn!("Health check failed: {}", status.problems().join(", "));
+            }
+            if status.is_healthy() != was_healthy {
+                let _ = handle.emit("health-changed", &status);
+            }
+            let _ = crate::tray::update_tray_health(&handle, &status);
+            monitor.set(status);
+
+            tokio::time::sleep(WATCHDOG_INTERVAL).await;
+        }
+    });
+}
+
+/// Check every component once, repairing what can be repaired
+pub async fn check_and_repair(handle: &AppHandle) -> HealthStatus {
+    let audio = tokio::task::spawn_blocking(check_audio)
+        .await
+        .unwrap_or_else(|e| ComponentHealth::failed(format!("Audio check failed: {}", e)));
+
+    HealthStatus {
+        hotkey: check_hotkeys(handle),
+        audio,
+        tray: check_tray(handle),
+        checked_at: Some(Utc::now()),
+    }
+}
+
+/// Verify the global hotkeys are registered, re-registering any that were lost
+fn check_hotkeys(handle: &AppHandle) -> ComponentHealth {
+    let mut repaired = Vec::new();
+
+    for hotkey in crate::hotkey::registered() {
+        let shortcut: Shortcut = match hotkey.parse() {
+            Ok(shortcut) => shortcut,
+            Err(e) => return ComponentHealth::failed(format!("Invalid hotkey {}: {}", hotkey, e)),
+        };
+        if handle.global_shortcut().is_registered(shortcut) {
+            continue;
+        }
+
+        // The plugin handler stays installed, so registering again restores the hotkey
+        match handle.global_shortcut().register(shortcut) {
+            Ok(()) => {
+                log::info!("Watchdog re-registered hotkey {}", hotkey);
+                repaired.push(hotkey);
+            }
+            Err(e) if hotkey == DEFAULT_HOTKEY => {
+                return ComponentHealth::failed(format!("Recording hotkey not registered: {}", e));
+            }
+            Err(e) => log::warn!("Watchdog could not re-register {}: {}", hotkey, e),
+        }
+    }
+
+    if repaired.is_empty() {
+        ComponentHealth::ok()
+    } else {
+        ComponentHealth::repaired(format!("Re-registered {}", repaired.join(", ")))
+    }
+}
+
+/// Verify an audio host with an input device is available
+///
+/// A fresh host is created on every check (as recording does), so a host
+/// that failed earlier is reinitialised here.
+fn check_audio() -> ComponentHealth {
+    let host = crate::audio::cpal_host();
+    match host.input_devices() {
+        Ok(mut devices) => {
+            if host.default_input_device().is_some() || devices.next().is_some() {
+                ComponentHealth::ok()
+            } else {
+                ComponentHealth::failed("No input device available")
+            }
+        }
+        Err(e) => ComponentHealth::failed(format!("Audio host unavailable: {}", e)),
+    }
+}
+
+/// Verify the tray icon exists, recreating it if it was lost
+fn check_tray(handle: &AppHandle) -> ComponentHealth {
+    if crate::tray::tray_exists(handle) || crate::daemon::is_headless() {
+        return ComponentHealth::ok();
+    }
+
+    match crate::tray::create_tray(handle) {
+        Ok(()) => {
+            log::info!("Watchdog recreated the tray icon");
+            if let Some(state) = handle.try_state::<crate::state::SharedState>() {
+                let handle = handle.clone();
+                let state = state.inner().clone();
+                tauri::async_runtime::spawn(async move {
+                    let state = state.lock().await;
+                    let _ = crate::tray::update_tray_menu(&handle, &state).await;
+                });
+            }
+            ComponentHealth::repaired("Recreated tray icon")
+        }
+        Err(e) => ComponentHealth::failed(format!("Tray icon missing: {}", e)),
+    }
+}
+
+/// Serve `GET /health` and `POST /browser-tab` on localhost
+///
+/// `/health` responds 200 when healthy and 503 otherwise, with the status as JSON.
+pub async fn serve_health(port: u16, monitor: HealthMonitor) -> crate::error::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    log::info!("Health endpoint listening on http://127.0.0.1:{}/health", port);
+
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let monitor = monitor.clone();
+
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_REQUEST_BYTES];
+            let n = match stream.read(&mut buf).await {
+                Ok(n) => n,
+                Err(_) => return,
+            };
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let response = health_response(&request, &monitor.status());
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        });
+    }
+}
+
+/// Build the HTTP response for a raw request
+fn health_response(request: &str, status: &HealthStatus) -> String {
+    let request_line = request.lines().next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+    let (code, body) = if method == "POST" && path == "/browser-tab" && crate::browser_tab::is_enabled() {
+        let (headers, body) = request.split_once("\r\n\r\n").unwrap_or((request, ""));
+        let json = headers.lines().any(|line| {
+            line.split_once(':').is_some_and(|(name, value)| {
+                name.eq_ignore_ascii_case("content-type") && value.trim().starts_with("application/json")
+            })
+        });
+        if json && crate::browser_tab::report(body) {
+            ("200 OK", "{}".to_string())
+        } else {
+            ("400 Bad Request", "{\"error\":\"bad request\"}".to_string())
+        }
+    } else if method != "GET" || path != "/health" {
+        ("404 Not Found", "{\"error\":\"not found\"}".to_string())
+    } else {
+        let body = serde_json::to_string(status).unwrap_or_else(|_| "{}".to_string());
+        if status.is_healthy() {
+            ("200 OK", body)
+        } else {
+            ("503 Service Unavailable", body)
+        }
+    };
+
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problems_lists_unhealthy_components() {
+        let status = HealthStatus {
+            audio: ComponentHealth::failed("No input device available"),
+            tray: ComponentHealth::repaired("Recreated tray icon"),
+            ..Default::default()
+        };
+        assert!(!status.is_healthy());
+        assert_eq!(status.problems(), vec!["audio"]);
+    }
+
+    #[test]
+    fn test_health_response_codes() {
+        let healthy = HealthStatus::default();
+        assert!(health_response("GET /health HTTP/1.1\r\n", &healthy).starts_with("HTTP/1.1 200 OK"));
+        assert!(health_response("GET /other HTTP/1.1\r\n", &healthy).starts_with("HTTP/1.1 404"));
+
+        let unhealthy = HealthStatus {
+            hotkey: ComponentHealth::failed("Recording hotkey not registered"),
+            ..Default::default()
+        };
+        let response = health_response("GET /health HTTP/1.1\r\n", &unhealthy);
+        assert!(response.starts_with("HTTP/1.1 503"));
+        assert!(response.contains("Recording hotkey not registered"));
+    }
+}
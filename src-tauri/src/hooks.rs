@@ -0,0 +1,490 @@
+//! User shell commands run on dictation events
+//!
+//! `hooks` in settings maps lifecycle events to shell commands, run with
+//! `sh -c` when shell commands are allowed. `{{name}}` placeholders in a
+//! command stand for values passed as positional parameters, never pasted
+//! into the command, so they are safe anywhere, quoted or not; the same
+//! values are in `WHISPERTRAY_*` environment variables.
+//! Hooks run in the background and never hold up a dictation: their output
+//! is discarded and they are killed when they run past the timeout.
+//!
+//! A mode's output command is different: it is a filter the final text is
+//! piped through before pasting, so it holds up the dictation until it
+//! exits. It gets a bare environment, so API keys and tokens exported to the
+//! app never reach it.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// Time a hook gets when none is configured
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Environment variables output commands inherit; the rest is withheld
+const PASSED_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "TMPDIR",
+    "XDG_RUNTIME_DIR",
+];
+
+/// Shell commands for lifecycle events
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    /// Run when a recording starts
+    pub on_record_start: Option<String>,
+    /// Run when a dictation's output is ready
+    pub on_result: Option<String>,
+    /// Run when a dictation fails or is cancelled
+    pub on_error: Option<String>,
+    /// Seconds before a hook is killed
+    pub timeout_secs: Option<u64>,
+}
+
+impl Hooks {
+    /// Command configured for `event`
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::RecordStart => self.on_record_start.as_deref(),
+            HookEvent::Result => self.on_result.as_deref(),
+            HookEvent::Error => self.on_error.as_deref(),
+        }
+        .filter(|command| !command.trim().is_empty())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(1))
+    }
+
+    /// Events with a command configured
+    pub fn configured(&self) -> Vec<HookEvent> {
+        [HookEvent::RecordStart, HookEvent::Result, HookEvent::Error]
+            .into_iter()
+            .filter(|&event| self.command(event).is_some())
+            .collect()
+    }
+}
+
+/// Dictation lifecycle event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    RecordStart,
+    Result,
+    Error,
+}
+
+impl HookEvent {
+    /// Settings key, also the `{{event}}` value
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::RecordStart => "on_record_start",
+            HookEvent::Result => "on_result",
+            HookEvent::Error => "on_error",
+        }
+    }
+}
+
+/// Values for a hook's placeholders
+#[derive(Debug, Clone, Default)]
+pub struct HookVars {
+    /// Dictation id, also the history item id; empty when recording starts
+    pub id: String,
+    pub mode: String,
+    /// "recording", "completed", "failed" or "cancelled"
+    pub status: String,
+    /// Output text, written to a file passed as `{{transcript_path}}`
+    pub transcript: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Quoting in effect at a point of a shell command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quoting {
+    None,
+    Single,
+    Double,
+}
+
+/// Follows the quoting of a shell command as it is read
+///
+/// Command substitutions and subshells start over unquoted, so
+/// `"$(cat {{path}})"` puts the placeholder outside any quotes.
+struct QuoteTracker {
+    stack: Vec<Quoting>,
+    escaped: bool,
+}
+
+impl QuoteTracker {
+    fn new() -> Self {
+        Self {
+            stack: vec![Quoting::None],
+            escaped: false,
+        }
+    }
+
+    fn current(&self) -> Quoting {
+        self.stack.last().copied().unwrap_or(Quoting::None)
+    }
+
+    fn feed(&mut self, text: &str) {
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if std::mem::take(&mut self.escaped) {
+                continue;
+            }
+            match (self.current(), c) {
+                (Quoting::Single, '\'') => {
+                    self.stack.pop();
+                }
+                (Quoting::Single, _) => {}
+                (_, '\\') => self.escaped = true,
+                (Quoting::Double, '"') => {
+                    self.stack.pop();
+                }
+                (_, '$') if chars.peek() == Some(&'(') => {
+                    chars.next();
+                    self.stack.push(Quoting::None);
+                }
+                (Quoting::None, '\'') => self.stack.push(Quoting::Single),
+                (Quoting::None, '"') => self.stack.push(Quoting::Double),
+                (Quoting::None, '(') => self.stack.push(Quoting::None),
+                (Quoting::None, ')') if self.stack.len() > 1 => {
+                    self.stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Reference to positional parameter `n`, quoted as a single word wherever it is
+fn parameter(n: usize, quoting: Quoting) -> String {
+    match quoting {
+        Quoting::None => format!("\"${{{}}}\"", n),
+        Quoting::Double => format!("${{{}}}", n),
+        Quoting::Single => format!("'\"${{{}}}\"'", n),
+    }
+}
+
+/// Replace `{{name}}` placeholders with references to positional parameters
+///
+/// The i-th value is parameter i + 1, see `shell`. Unknown placeholders are
+/// left as written.
+fn render(command: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(command.len());
+    let mut quotes = QuoteTracker::new();
+    let mut rest = command;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        quotes.feed(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after.find("}}").and_then(|end| {
+            values
+                .iter()
+                .position(|(name, _)| *name == after[..end].trim())
+                .map(|i| (end, i))
+        });
+        match value {
+            Some((end, i)) => {
+                rendered.push_str(&parameter(i + 1, quotes.current()));
+                rest = &after[end + 2..];
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// `sh -c` running `command`, with `values` as its positional parameters
+fn shell(command: &str, values: &[(&str, &str)]) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(render(command, values))
+        .arg("sh")
+        .args(values.iter().map(|(_, value)| value));
+    shell
+}
+
+/// Run `command` for `event` in the background
+pub fn spawn(command: &str, event: HookEvent, vars: HookVars, timeout: Duration) {
+    let command = command.to_string();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = run(&command, event, vars, timeout).await {
+            log::warn!("Hook {} failed: {}", event.name(), e);
+        }
+    });
+}
+
+async fn run(
+    command: &str,
+    event: HookEvent,
+    vars: HookVars,
+    timeout: Duration,
+) -> std::io::Result<()> {
+    // Removed with its directory once the hook is done
+    let transcript = match &vars.transcript {
+        Some(text) => Some(write_transcript(text)?),
+        None => None,
+    };
+    let path = transcript
+        .as_ref()
+        .map(|(_, path)| path.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let error = vars.error.unwrap_or_default();
+    let values = [
+        ("event", event.name()),
+        ("id", vars.id.as_str()),
+        ("mode", vars.mode.as_str()),
+        ("status", vars.status.as_str()),
+        ("transcript_path", path.as_str()),
+        ("error", error.as_str()),
+    ];
+
+    let mut child = shell(command, &values);
+    child
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .kill_on_drop(true);
+    for (name, value) in values {
+        child.env(format!("WHISPERTRAY_{}", name.to_uppercase()), value);
+    }
+    let mut child = child.spawn()?;
+    let mut group = ProcessGroup(child.id());
+    let status = match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => status?,
+        Err(_) => {
+            return Err(std::io::Error::other(format!(
+                "timed out after {}s",
+                timeout.as_secs()
+            )))
+        }
+    };
+    group.0 = None;
+    if !status.success() {
+        return Err(std::io::Error::other(format!("exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// Pipe `text` through a mode's output `command`, returning what it prints
+///
+/// `{{id}}` and `{{mode}}` placeholders work as in hooks. The command sees
+/// only `PASSED_ENV` and `WHISPERTRAY_ID`/`WHISPERTRAY_MODE`. Fails when it
+/// exits unsuccessfully, prints nothing or runs past `timeout`; trailing
+/// newlines of its output are dropped. A command that times out or is
+/// cancelled is killed with every process it started.
+pub async fn filter(command: &str, text: &str, vars: &HookVars, timeout: Duration) -> std::io::Result<String> {
+    use tokio::io::AsyncWriteExt;
+
+    let values = [("id", vars.id.as_str()), ("mode", vars.mode.as_str())];
+    let mut child = shell(command, &values);
+    child
+        .env_clear()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .kill_on_drop(true);
+    for name in PASSED_ENV {
+        if let Some(value) = std::env::var_os(name) {
+            child.env(name, value);
+        }
+    }
+    for (name, value) in values {
+        child.env(format!("WHISPERTRAY_{}", name.to_uppercase()), value);
+    }
+    let mut child = child.spawn()?;
+    let mut group = ProcessGroup(child.id());
+
+    // Written while the output is read, so a long text cannot fill the pipe and stall
+    let mut stdin = child.stdin.take();
+    let write = async move {
+        if let Some(stdin) = stdin.as_mut() {
+            // A command that does not read its input is not an error
+            let _ = stdin.write_all(text.as_bytes()).await;
+        }
+    };
+    let output = match tokio::time::timeout(timeout, async {
+        let (_, output) = tokio::join!(write, child.wait_with_output());
+        output
+    })
+    .await
+    {
+        Ok(output) => output?,
+        Err(_) => {
+            return Err(std::io::Error::other(format!(
+                "timed out after {}s",
+                timeout.as_secs()
+            )))
+        }
+    };
+    group.0 = None;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "exited with {}",
+            output.status
+        )));
+    }
+    let filtered = String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\n', '\r'])
+        .to_string();
+    if filtered.trim().is_empty() {
+        return Err(std::io::Error::other("printed nothing"));
+    }
+    Ok(filtered)
+}
+
+/// Kills a command's process group when dropped, unless cleared first
+///
+/// `kill_on_drop` only reaches `sh`, not the pipeline it started.
+struct ProcessGroup(Option<u32>);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            // SAFETY: kill touches no memory; a negative pid names the group
+            unsafe {
+                libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Write the output to a new file in a directory only the user can open
+///
+/// The directory is removed, file and all, when the returned guard is dropped.
+fn write_transcript(text: &str) -> std::io::Result<(tempfile::TempDir, PathBuf)> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let dir = tempfile::Builder::new().prefix("whispertray-hook-").tempdir()?;
+    let path = dir.path().join("transcript.txt");
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    file.write_all(text.as_bytes())?;
+    Ok((dir, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let values = [("mode", "email"), ("transcript_path", "/tmp/it's here.txt")];
+        assert_eq!(
+            render(
+                "notify-send {{mode}} \"$(cat {{ transcript_path }})\"",
+                &values
+            ),
+            r#"notify-send "${1}" "$(cat "${2}")""#
+        );
+        assert_eq!(
+            render(r#"echo "in {{mode}}" 'in {{mode}}' \"{{mode}}"#, &values),
+            r#"echo "in ${1}" 'in '"${1}"'' \""${1}""#
+        );
+        // Values never reach the command, unknown placeholders stay
+        assert_eq!(
+            render("echo {{mode}} {{other}}", &[("mode", "{{mode}}")]),
+            r#"echo "${1}" {{other}}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_placeholder_values_are_not_run() {
+        let vars = HookVars {
+            mode: "$(echo run) `echo run` *".to_string(),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(5);
+        for command in ["echo {{mode}}", "echo \"{{mode}}\"", "echo '{{mode}}'", "echo \"$(echo {{mode}})\""] {
+            assert_eq!(
+                filter(command, "", &vars, timeout).await.unwrap(),
+                vars.mode,
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filter() {
+        let vars = HookVars {
+            id: "1".to_string(),
+            mode: "email".to_string(),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            filter("tr a-z A-Z; echo \" \"{{mode}}", "hello", &vars, timeout)
+                .await
+                .unwrap(),
+            "HELLO email"
+        );
+        // Only the passed variables reach the command
+        assert_eq!(
+            filter("echo \"$WHISPERTRAY_MODE${CARGO:-}\"", "", &vars, timeout)
+                .await
+                .unwrap(),
+            "email"
+        );
+        assert!(filter("exit 3", "hello", &vars, timeout).await.is_err());
+        assert!(filter("cat >/dev/null", "hello", &vars, timeout).await.is_err());
+        assert!(filter("sleep 5", "hello", &vars, Duration::from_millis(100))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_filter_timeout_kills_pipeline() {
+        let marker = std::env::temp_dir().join(format!("whispertray-filter-{}", uuid::Uuid::new_v4()));
+        let command = format!("(sleep 1; touch '{}') | cat", marker.display());
+        assert!(filter(&command, "", &HookVars::default(), Duration::from_millis(100))
+            .await
+            .is_err());
+        // The subshell was killed with `sh`, so it never gets to touch the file
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let vars = HookVars {
+            transcript: Some("Hello".to_string()),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(5);
+        run("test \"$(cat {{transcript_path}})\" = Hello", HookEvent::Result, vars, timeout)
+            .await
+            .unwrap();
+        assert!(run("exit 3", HookEvent::Error, HookVars::default(), timeout).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hook_timeout_kills_pipeline() {
+        let marker = std::env::temp_dir().join(format!("whispertray-hook-{}", uuid::Uuid::new_v4()));
+        let command = format!("(sleep 1; touch '{}') | cat", marker.display());
+        let timeout = Duration::from_millis(100);
+        assert!(run(&command, HookEvent::RecordStart, HookVars::default(), timeout)
+            .await
+            .is_err());
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+    }
+}
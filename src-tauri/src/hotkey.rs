@@ -4,43 +4,188 @@ use crate::error::{AppError, Result};
 use crate::state::{RecordingStatus, SharedState};
 use crate::tray::{update_tray_icon, update_tray_icon_for_level, update_tray_menu};
 use log::info;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
 
 /// Default hotkey for toggling recording
 pub const DEFAULT_HOTKEY: &str = "Ctrl+Space";
 
-/// Set up the global hotkey for recording toggle
-pub fn setup_hotkey(app: &tauri::App) -> Result<()> {
+/// Hotkeys besides the recording toggle, each off until a key is set
+///
+/// Any of them could shadow a shortcut of the app in front, so none is
+/// taken unless asked for.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hotkeys {
+    /// Drop a bookmark while recording, e.g. "Ctrl+Shift+B"
+    pub bookmark: Option<String>,
+    /// Cancel a recording or its processing, e.g. "Ctrl+Shift+Escape"
+    pub cancel: Option<String>,
+    /// Turn incognito on and off, e.g. "Ctrl+Alt+I"
+    pub incognito: Option<String>,
+    /// Pause and resume a recording, e.g. "Ctrl+Shift+Space"
+    pub pause: Option<String>,
+}
+
+/// Keys of the hotkeys set up at startup, the recording toggle first
+static REGISTERED: OnceLock<Vec<String>> = OnceLock::new();
+
+/// The hotkeys set up at startup, for the watchdog to keep registered
+pub fn registered() -> Vec<String> {
+    REGISTERED.get().cloned().unwrap_or_default()
+}
+
+/// What an optional hotkey does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Bookmark,
+    Cancel,
+    Incognito,
+    Pause,
+}
+
+impl Hotkeys {
+    /// The keys that are set, with what they do
+    fn bound(&self) -> Vec<(&str, Action)> {
+        [
+            (&self.bookmark, Action::Bookmark),
+            (&self.cancel, Action::Cancel),
+            (&self.incognito, Action::Incognito),
+            (&self.pause, Action::Pause),
+        ]
+        .into_iter()
+        .filter_map(|(key, action)| Some((key.as_deref()?.trim(), action)))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+    }
+}
+
+/// Set up the global hotkeys: the recording toggle and whichever of `hotkeys` are set
+pub fn setup_hotkey(app: &tauri::App, hotkeys: &Hotkeys) -> Result<()> {
     let handle = app.handle().clone();
 
-    // Parse the shortcut
-    let shortcut: Shortcut = DEFAULT_HOTKEY.parse()
+    // Parse the shortcuts; optional ones that don't parse are left out
+    let record_shortcut: Shortcut = DEFAULT_HOTKEY.parse()
         .map_err(|e| crate::error::AppError::Config(format!("Invalid hotkey: {}", e)))?;
+    let optional: Vec<(&str, Shortcut, Action)> = hotkeys
+        .bound()
+        .into_iter()
+        .filter_map(|(key, action)| match key.parse::<Shortcut>() {
+            Ok(shortcut) => Some((key, shortcut, action)),
+            Err(e) => {
+                log::warn!("Invalid {:?} hotkey {}: {}", action, key, e);
+                None
+            }
+        })
+        .collect();
+
+    info!(
+        "Registering global hotkeys: {}{}",
+        DEFAULT_HOTKEY,
+        optional
+            .iter()
+            .map(|(key, _, action)| format!(", {} ({:?})", key, action))
+            .collect::<String>()
+    );
 
-    info!("Registering global hotkey: {}", DEFAULT_HOTKEY);
+    let _ = REGISTERED.set(
+        std::iter::once(DEFAULT_HOTKEY.to_string())
+            .chain(optional.iter().map(|(key, _, _)| key.to_string()))
+            .collect(),
+    );
 
-    // Register the shortcut
+    // Register the shortcut handler
+    let record_id = record_shortcut.id();
+    let actions: Vec<(u32, Action)> = optional
+        .iter()
+        .map(|(_, shortcut, action)| (shortcut.id(), *action))
+        .collect();
     app.handle().plugin(
         tauri_plugin_global_shortcut::Builder::new()
             .with_handler(move |_app, shortcut_ref, event| {
                 if event.state == tauri_plugin_global_shortcut::ShortcutState::Pressed {
                     info!("Hotkey pressed: {:?}", shortcut_ref);
-                    toggle_recording(&handle);
+                    if shortcut_ref.id() == record_id {
+                        toggle_recording(&handle);
+                        return;
+                    }
+                    match actions.iter().find(|(id, _)| *id == shortcut_ref.id()).map(|(_, action)| action) {
+                        Some(Action::Bookmark) => add_bookmark(&handle),
+                        Some(Action::Cancel) => {
+                            let handle = handle.clone();
+                            tauri::async_runtime::spawn(async move {
+                                crate::cancel::cancel_pipeline(&handle).await;
+                            });
+                        }
+                        Some(Action::Incognito) => toggle_incognito(&handle),
+                        Some(Action::Pause) => toggle_pause(&handle),
+                        None => {}
+                    }
                 }
             })
             .build(),
     )?;
 
-    // Register the specific shortcut
+    // Register the specific shortcuts
     app.global_shortcut()
-        .register(shortcut)
+        .register(record_shortcut)
         .map_err(|e| AppError::Config(format!("Failed to register hotkey: {}", e)))?;
 
+    for (key, shortcut, action) in optional {
+        if let Err(e) = app.global_shortcut().register(shortcut) {
+            log::warn!("Failed to register {:?} hotkey {}: {}", action, key, e);
+        }
+    }
+
     info!("Global hotkey registered successfully");
     Ok(())
 }
 
+/// Drop a bookmark in the current recording
+fn add_bookmark(handle: &AppHandle) {
+    let handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Some(state_arc) = handle.try_state::<SharedState>() {
+            let state = state_arc.lock().await;
+            match state.add_bookmark() {
+                Ok(offset_ms) => {
+                    let _ = handle.emit("bookmark-added", offset_ms);
+                }
+                Err(e) => log::debug!("Bookmark ignored: {}", e),
+            }
+        }
+    });
+}
+
+/// Turn incognito on or off
+fn toggle_incognito(handle: &AppHandle) {
+    let handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Some(state_arc) = handle.try_state::<SharedState>() {
+            let mut state = state_arc.lock().await;
+            let incognito = !state.incognito;
+            state.set_incognito(incognito);
+            let _ = update_tray_menu(&handle, &state).await;
+        }
+    });
+}
+
+/// Pause or resume the current recording
+fn toggle_pause(handle: &AppHandle) {
+    let handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Some(state_arc) = handle.try_state::<SharedState>() {
+            let state = state_arc.lock().await;
+            let paused = state.recording_handle.is_held();
+            if let Err(e) = state.set_recording_paused(!paused) {
+                log::debug!("Pause ignored: {}", e);
+            }
+        }
+    });
+}
+
 /// Toggle recording state
 fn toggle_recording(handle: &AppHandle) {
     let handle = handle.clone();
@@ -53,57 +198,91 @@ fn toggle_recording(handle: &AppHandle) {
             };
 
             if is_recording {
-                // Stop recording - get data quickly, then release lock for processing
-                let stop_result = {
-                    let mut state = state_arc.lock().await;
-                    // Immediately show processing state
-                    let _ = update_tray_icon(&handle, RecordingStatus::Processing);
-                    state.stop_recording().await
-                };
-
-                // State resets to Ready on error; make sure UI updates immediately.
-                let state = state_arc.lock().await;
-                let _ = update_tray_icon(&handle, state.status);
-                let _ = update_tray_menu(&handle, &state).await;
-                drop(state);
-
-                match stop_result {
-                    Ok(output) => {
-                        info!("Recording stopped via hotkey. Output: {} chars", output.len());
-                        let _ = handle.emit("recording-complete", &output);
-                    }
-                    Err(e) => {
-                        log::error!("Failed to stop recording: {}", e);
-                        let _ = handle.emit("recording-error", e.to_string());
-                    }
-                }
-            } else {
-                // Start recording with level callback for tray icon updates
-                let handle_for_callback = handle.clone();
-                let level_callback: crate::audio::LevelCallback = Box::new(move |level| {
-                    let _ = update_tray_icon_for_level(&handle_for_callback, level);
-                });
-
-                let start_result = {
-                    let mut state = state_arc.lock().await;
-                    let result = state.start_recording_with_callback(Some(level_callback));
-                    if result.is_ok() {
-                        let _ = update_tray_icon(&handle, RecordingStatus::Recording);
-                        let _ = update_tray_menu(&handle, &state).await;
-                    }
-                    result
-                };
+                let _ = stop_and_process(&handle, state_arc.inner()).await;
+            } else if start_recording(&handle, state_arc.inner()).await.is_ok() {
+                info!("Recording started via hotkey");
+            }
+        }
+    });
+}
 
-                match start_result {
-                    Ok(()) => {
-                        info!("Recording started via hotkey");
-                    }
-                    Err(e) => {
-                        log::error!("Failed to start recording: {}", e);
-                        let _ = update_tray_icon(&handle, RecordingStatus::Error);
-                    }
-                }
+/// Start recording, with the tray icon following the input level
+pub(crate) async fn start_recording(handle: &AppHandle, state_arc: &SharedState) -> Result<()> {
+    let handle_for_callback = handle.clone();
+    let level_callback: crate::audio::LevelCallback = Box::new(move |level| {
+        let _ = update_tray_icon_for_level(&handle_for_callback, level);
+    });
+
+    let mut state = state_arc.lock().await;
+    match state.start_recording_with_callback(Some(level_callback)) {
+        Ok(()) => {
+            let _ = update_tray_icon(handle, RecordingStatus::Recording);
+            let _ = update_tray_menu(handle, &state).await;
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("Failed to start recording: {}", e);
+            let _ = update_tray_icon(handle, RecordingStatus::Error);
+            Err(e)
+        }
+    }
+}
+
+/// Stop the current recording and process it, as the hotkey would
+pub(crate) fn stop_recording_if_active(handle: &AppHandle) {
+    let handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Some(state_arc) = handle.try_state::<SharedState>() {
+            let is_recording = state_arc.lock().await.is_recording();
+            if is_recording {
+                let _ = stop_and_process(&handle, state_arc.inner()).await;
             }
         }
     });
 }
+
+/// Stop recording, process it and report the result
+pub(crate) async fn stop_and_process(handle: &AppHandle, state_arc: &SharedState) -> Result<String> {
+    // Immediately show processing state
+    let _ = update_tray_icon(handle, RecordingStatus::Processing);
+    let stop_result = crate::state::stop_recording(state_arc).await;
+
+    // State resets to Ready on error; make sure UI updates immediately.
+    let state = state_arc.lock().await;
+    let _ = update_tray_icon(handle, state.status);
+    let _ = update_tray_menu(handle, &state).await;
+    drop(state);
+
+    match &stop_result {
+        Ok(output) => {
+            info!("Recording stopped. Output: {} chars", output.len());
+            let _ = handle.emit("recording-complete", output);
+        }
+        Err(AppError::Cancelled) => {
+            info!("Processing cancelled, output discarded");
+            let _ = handle.emit("recording-cancelled", ());
+        }
+        Err(e) => {
+            log::error!("Failed to stop recording: {}", e);
+            let _ = handle.emit("recording-error", e.to_string());
+        }
+    }
+    stop_result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_set_hotkeys_are_bound() {
+        assert!(Hotkeys::default().bound().is_empty());
+
+        let hotkeys = Hotkeys {
+            cancel: Some(" Ctrl+Shift+Escape ".to_string()),
+            pause: Some(String::new()),
+            ..Hotkeys::default()
+        };
+        assert_eq!(hotkeys.bound(), vec![("Ctrl+Shift+Escape", Action::Cancel)]);
+    }
+}
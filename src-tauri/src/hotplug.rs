@@ -0,0 +1,84 @@
+//! Reacting to input devices coming and going
+//!
+//! A capture stream whose device is unplugged mid-recording either reports
+//! the device gone or simply stops delivering audio. The recording thread
+//! notices both (see `RecordingHandle::input_stalled`): a lost microphone is
+//! replaced by the default input device once, a lost system audio track is
+//! dropped, and when nothing can take over the dictation stops and what was
+//! recorded so far is processed as usual. This module tells the user.
+
+use crate::audio::{DeviceChange, RecordingHandle};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// How often device changes are collected while recording
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Notification text for a device change
+fn describe(change: &DeviceChange) -> (&'static str, String) {
+    match change {
+        DeviceChange::FellBack { lost, now } => (
+            "Microphone disconnected",
+            format!("{} stopped; recording continues on {}", lost, now),
+        ),
+        DeviceChange::Dropped { device } => (
+            "System audio stopped",
+            format!("{} stopped; recording continues from the microphone", device),
+        ),
+        DeviceChange::Lost { device } => (
+            "Microphone disconnected",
+            format!("{} stopped and no other input is available; the recording was stopped", device),
+        ),
+    }
+}
+
+/// Watch the current recording for its devices going away
+pub fn spawn_watch(app: AppHandle, recording: RecordingHandle) {
+    let generation = recording.generation();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            // A newer recording's changes are for its own watch
+            if !recording.is_recording_generation(generation) {
+                break;
+            }
+            for change in recording.take_device_changes() {
+                let (summary, body) = describe(&change);
+                crate::notify::send(summary, &body);
+                let _ = app.emit("audio-device-changed", &change);
+                // The device list changed too
+                let _ = app.emit("audio-devices-changed", ());
+                if matches!(change, DeviceChange::Lost { .. }) {
+                    crate::hotkey::stop_recording_if_active(&app);
+                }
+            }
+        }
+    });
+}
+
+/// Notify that the configured microphone is missing and the default is used instead
+pub(crate) fn notify_missing(device: &str) {
+    log::warn!("Input device {} not found, recording from the default device", device);
+    crate::notify::send(
+        "Microphone not found",
+        &format!("{} is not connected; recording from the default input device", device),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_change_payload() {
+        let change = DeviceChange::FellBack {
+            lost: "USB Mic".to_string(),
+            now: "Built-in".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&change).unwrap(),
+            serde_json::json!({"kind": "fell_back", "lost": "USB Mic", "now": "Built-in"})
+        );
+        assert_eq!(describe(&change).0, "Microphone disconnected");
+    }
+}
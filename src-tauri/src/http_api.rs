@@ -0,0 +1,430 @@
+//! Localhost HTTP API
+//!
+//! Lets launchers, Stream Deck plugins and scripts drive WhisperTray: start,
+//! stop and cancel recordings, transcribe uploaded audio, read history and
+//! follow pipeline events as Server-Sent Events. The server only listens on
+//! 127.0.0.1, and since web pages can send requests to localhost too, every
+//! request must carry the configured token as `Authorization: Bearer`. The
+//! token is checked on the headers, before any body is read, and only
+//! `MAX_CONNECTIONS` clients are served at once.
+//!
+//! | Method | Path                 | Does                                          |
+//! |--------|----------------------|-----------------------------------------------|
+//! | GET    | `/status`            | Recording status                              |
+//! | POST   | `/recording/start`   | Start recording                               |
+//! | POST   | `/recording/stop`    | Stop, process and paste; returns the output   |
+//! | POST   | `/recording/toggle`  | Start or stop, like the hotkey                |
+//! | POST   | `/recording/cancel`  | Cancel the recording or its processing        |
+//! | POST   | `/transcribe`        | Transcribe the WAV, FLAC or Ogg body          |
+//! | GET    | `/history`           | History items (`limit`, `offset`, `search`)   |
+//! | GET    | `/events`            | Pipeline events as they happen (SSE)          |
+//! | POST   | `/mcp`               | Model Context Protocol server (see `mcp`)     |
+
+use crate::commands::RecordingStatusResponse;
+use crate::database::HistoryItem;
+use crate::error::{AppError, Result};
+use crate::remote::{content_length, find_header_end, read_body, read_headers, tokens_match};
+use crate::state::SharedState;
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Semaphore;
+
+/// Largest audio upload accepted by `/transcribe`
+const MAX_AUDIO_BYTES: usize = 64 * 1024 * 1024;
+
+/// Clients served at once, event streams included; more are turned away
+const MAX_CONNECTIONS: usize = 16;
+
+/// How long a client may take to send its request headers
+const HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Comment sent on an idle event stream, so a closed client is noticed
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Default number of history items returned
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Route {
+    Status,
+    Start,
+    Stop,
+    Toggle,
+    Cancel,
+    Transcribe,
+    History,
+    Events,
+    Mcp,
+}
+
+/// A parsed request line and headers
+#[derive(Debug)]
+struct Head {
+    route: Route,
+    query: HashMap<String, String>,
+    /// Where the headers end; the body starts 4 bytes later
+    header_end: usize,
+    /// Length of the body
+    length: usize,
+}
+
+/// Serve the API on localhost until the app exits
+pub async fn serve(port: u16, token: String, app: AppHandle) -> Result<()> {
+    if token.trim().is_empty() {
+        return Err(AppError::Config("The HTTP API needs a token".to_string()));
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    log::info!("HTTP API listening on http://127.0.0.1:{}", port);
+
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let Ok(permit) = connections.clone().try_acquire_owned() else {
+            log::warn!("HTTP API busy, turning a connection away");
+            tokio::spawn(async move {
+                let _ = respond(&mut stream, "503 Service Unavailable", &json!({ "error": "busy" })).await;
+            });
+            continue;
+        };
+        let (token, app) = (token.clone(), app.clone());
+
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &token, &app).await {
+                log::warn!("HTTP API request failed: {}", e);
+            }
+            drop(permit);
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, token: &str, app: &AppHandle) -> Result<()> {
+    let Ok(raw) = tokio::time::timeout(HEADER_TIMEOUT, read_headers(&mut stream)).await else {
+        let code = "408 Request Timeout";
+        return respond(&mut stream, code, &json!({ "error": code })).await;
+    };
+    let mut raw = raw?;
+    let head = match parse_head(&raw, token) {
+        Ok(head) => head,
+        Err(code) => return respond(&mut stream, code, &json!({ "error": code })).await,
+    };
+    read_body(&mut stream, &mut raw, head.header_end, head.length).await?;
+    let body = &raw[head.header_end + 4..];
+    let Some(state) = app.try_state::<SharedState>() else {
+        return respond(&mut stream, "503 Service Unavailable", &json!({ "error": "starting" })).await;
+    };
+    let state = state.inner().clone();
+
+    match head.route {
+        Route::Events => stream_events(stream).await,
+        Route::Mcp => match crate::mcp::handle(body, &state).await {
+            Some(reply) => respond(&mut stream, "200 OK", &reply).await,
+            None => {
+                // A notification: nothing to reply with
+                stream.write_all(b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await?;
+                let _ = stream.shutdown().await;
+                Ok(())
+            }
+        },
+        route => {
+            let (code, body) = match run(route, &head.query, body, &state, app).await {
+                Ok(body) => ("200 OK", body),
+                Err(e) => ("400 Bad Request", json!({ "error": e })),
+            };
+            respond(&mut stream, code, &body).await
+        }
+    }
+}
+
+/// Handle every route but the event stream
+async fn run(
+    route: Route,
+    query: &HashMap<String, String>,
+    body: &[u8],
+    state: &SharedState,
+    app: &AppHandle,
+) -> std::result::Result<serde_json::Value, String> {
+    match route {
+        Route::Status => {
+            let state = state.lock().await;
+            to_json(&RecordingStatusResponse {
+                status: state.status,
+                is_recording: state.is_recording(),
+                incognito: state.incognito,
+                paused: state.recording_handle.is_held(),
+            })
+        }
+        Route::Start => {
+            crate::hotkey::start_recording(app, state).await.map_err(|e| e.to_string())?;
+            Ok(json!({}))
+        }
+        Route::Stop => {
+            if !state.lock().await.is_recording() {
+                return Err("Not recording".to_string());
+            }
+            let output = crate::hotkey::stop_and_process(app, state).await.map_err(|e| e.to_string())?;
+            Ok(json!({ "output": output }))
+        }
+        Route::Toggle => {
+            let is_recording = state.lock().await.is_recording();
+            if is_recording {
+                let output = crate::hotkey::stop_and_process(app, state).await.map_err(|e| e.to_string())?;
+                Ok(json!({ "recording": false, "output": output }))
+            } else {
+                crate::hotkey::start_recording(app, state).await.map_err(|e| e.to_string())?;
+                Ok(json!({ "recording": true }))
+            }
+        }
+        Route::Cancel => {
+            crate::cancel::cancel_pipeline(app).await;
+            Ok(json!({}))
+        }
+        Route::Transcribe => to_json(&transcribe(body, query.get("mode").map(String::as_str), state).await?),
+        Route::History => {
+            let db = state
+                .lock()
+                .await
+                .database
+                .clone()
+                .ok_or_else(|| "Database not initialized".to_string())?;
+            let limit = query
+                .get("limit")
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(DEFAULT_HISTORY_LIMIT);
+            let offset = query.get("offset").and_then(|offset| offset.parse().ok()).unwrap_or(0);
+            let search = query.get("search").cloned();
+            let items = db
+                .run(move |db| match &search {
+                    Some(search) => db.search_history(search, limit),
+                    None => db.get_history(limit, offset),
+                })
+                .await
+                .map_err(|e| e.to_string())?;
+            to_json(&items)
+        }
+        Route::Events | Route::Mcp => unreachable!("handled separately"),
+    }
+}
+
+fn to_json(value: &impl Serialize) -> std::result::Result<serde_json::Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+/// Transcribe an uploaded audio file and save it to history like a watch folder file
+async fn transcribe(body: &[u8], mode: Option<&str>, state: &SharedState) -> std::result::Result<HistoryItem, String> {
+    let extension = audio_extension(body).ok_or_else(|| "Send a WAV, FLAC or Ogg file".to_string())?;
+    let db = state
+        .lock()
+        .await
+        .database
+        .clone()
+        .ok_or_else(|| "Database not initialized".to_string())?;
+
+    // Created private (0600) and removed when dropped
+    let file = tempfile::Builder::new()
+        .prefix("whispertray-api-")
+        .suffix(&format!(".{}", extension))
+        .tempfile()
+        .map_err(|e| e.to_string())?;
+    tokio::fs::write(file.path(), body).await.map_err(|e| e.to_string())?;
+    crate::priority::background(crate::watch::transcribe_to_history(
+        state,
+        &db,
+        file.path(),
+        mode,
+        Utc::now(),
+    ))
+    .await
+}
+
+/// Extension `load_audio` decodes the audio as, from its magic bytes
+fn audio_extension(audio: &[u8]) -> Option<&'static str> {
+    match audio.get(..4)? {
+        b"RIFF" => Some("wav"),
+        b"fLaC" => Some("flac"),
+        b"OggS" => Some("ogg"),
+        _ => None,
+    }
+}
+
+/// Send pipeline events to the client until it disconnects
+async fn stream_events(mut stream: TcpStream) -> Result<()> {
+    let mut events = crate::events::subscribe();
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+        )
+        .await?;
+
+    loop {
+        let chunk = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => format!("event: {}\ndata: {}\n\n", event.stage, serde_json::to_string(&event)?),
+                Err(RecvError::Lagged(missed)) => format!(": {} events dropped\n\n", missed),
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            _ = tokio::time::sleep(KEEPALIVE_INTERVAL) => ": keepalive\n\n".to_string(),
+        };
+        if stream.write_all(chunk.as_bytes()).await.is_err() {
+            // The client went away
+            return Ok(());
+        }
+    }
+}
+
+async fn respond(stream: &mut TcpStream, code: &str, body: &serde_json::Value) -> Result<()> {
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    let _ = stream.shutdown().await;
+    Ok(())
+}
+
+/// Check the head of a raw request is authorized and routed, or the HTTP status to reply with
+fn parse_head(request: &[u8], token: &str) -> std::result::Result<Head, &'static str> {
+    let header_end = find_header_end(request).ok_or("400 Bad Request")?;
+    let headers = String::from_utf8_lossy(&request[..header_end]);
+
+    let mut request_line = headers.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let route = match (method, path) {
+        ("GET", "/status") => Route::Status,
+        ("POST", "/recording/start") => Route::Start,
+        ("POST", "/recording/stop") => Route::Stop,
+        ("POST", "/recording/toggle") => Route::Toggle,
+        ("POST", "/recording/cancel") => Route::Cancel,
+        ("POST", "/transcribe") => Route::Transcribe,
+        ("GET", "/history") => Route::History,
+        ("GET", "/events") => Route::Events,
+        ("POST", "/mcp") => Route::Mcp,
+        // MCP clients may ask for a server-initiated stream, which isn't offered
+        ("GET", "/mcp") => return Err("405 Method Not Allowed"),
+        _ => return Err("404 Not Found"),
+    };
+
+    let authorized = headers.lines().any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("authorization")
+                && value.trim().strip_prefix("Bearer ").is_some_and(|t| tokens_match(t, token))
+        })
+    });
+    if !authorized {
+        return Err("401 Unauthorized");
+    }
+
+    let length = content_length(&headers).unwrap_or(0);
+    if length > MAX_AUDIO_BYTES {
+        return Err("413 Payload Too Large");
+    }
+    Ok(Head {
+        route,
+        query: parse_query(query),
+        header_end,
+        length,
+    })
+}
+
+/// Decode `a=1&b=two%20words` into a map
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (!name.is_empty()).then(|| (percent_decode(name), percent_decode(value)))
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(line: &str, auth: &str, body: &str) -> Vec<u8> {
+        format!(
+            "{} HTTP/1.1\r\nHost: localhost\r\nAuthorization: {}\r\nContent-Length: {}\r\n\r\n{}",
+            line,
+            auth,
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_parse_head() {
+        let raw = request("GET /history?limit=5&search=weekly+sync%21", "Bearer s3cret", "");
+        let parsed = parse_head(&raw, "s3cret").unwrap();
+        assert_eq!(parsed.route, Route::History);
+        assert_eq!(parsed.query["limit"], "5");
+        assert_eq!(parsed.query["search"], "weekly sync!");
+
+        let raw = request("POST /transcribe?mode=email", "Bearer s3cret", "RIFF....");
+        let parsed = parse_head(&raw, "s3cret").unwrap();
+        assert_eq!(parsed.route, Route::Transcribe);
+        assert_eq!(&raw[parsed.header_end + 4..][..parsed.length], b"RIFF....");
+
+        let raw = request("POST /recording/start", "Bearer wrong!", "");
+        assert_eq!(parse_head(&raw, "s3cret").unwrap_err(), "401 Unauthorized");
+        let raw = request("GET /recording/start", "Bearer s3cret", "");
+        assert_eq!(parse_head(&raw, "s3cret").unwrap_err(), "404 Not Found");
+    }
+
+    #[test]
+    fn test_parse_head_before_body() {
+        // Only the head has arrived: the token and size are checked on it alone
+        let head = |auth: &str| {
+            format!("POST /transcribe HTTP/1.1\r\nAuthorization: {}\r\nContent-Length: 999999999\r\n\r\n", auth)
+        };
+        assert_eq!(parse_head(head("Bearer wrong!").as_bytes(), "s3cret").unwrap_err(), "401 Unauthorized");
+        assert_eq!(parse_head(head("Bearer s3cret").as_bytes(), "s3cret").unwrap_err(), "413 Payload Too Large");
+    }
+
+    #[test]
+    fn test_audio_extension() {
+        assert_eq!(audio_extension(b"RIFF\x24\x00\x00\x00WAVE"), Some("wav"));
+        assert_eq!(audio_extension(b"OggS\x00"), Some("ogg"));
+        assert_eq!(audio_extension(b"ID3"), None);
+    }
+}
@@ -1,12 +1,17 @@
 //! Recording indicator window management
 
+use crate::audio::{RecordingHandle, WAVEFORM_RATE};
 use crate::error::Result;
 use log::info;
 use serde::Serialize;
+use std::time::Duration;
 use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
 
 const INDICATOR_LABEL: &str = "recording";
 
+/// How often level and waveform updates are sent during a recording
+const STREAM_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Clone, Serialize)]
 pub struct AudioLevel {
     pub level: f32,
@@ -75,6 +80,84 @@ pub fn emit_audio_level(handle: &AppHandle, level: f32, peak: f32) {
     );
 }
 
+/// Waveform peaks, as sent in "audio-waveform"
+#[derive(Clone, Serialize)]
+pub struct AudioWaveform {
+    /// Peak of each window (0.0 to 1.0), oldest first
+    pub peaks: Vec<f32>,
+    /// Peaks per second of audio
+    pub rate: u32,
+}
+
+/// Emit waveform peaks recorded since the last update to the indicator
+pub fn emit_audio_waveform(handle: &AppHandle, peaks: Vec<f32>) {
+    let _ = handle.emit_to(
+        INDICATOR_LABEL,
+        "audio-waveform",
+        AudioWaveform {
+            peaks,
+            rate: WAVEFORM_RATE,
+        },
+    );
+}
+
+/// Stream the level and waveform of the current recording to the indicator
+pub fn spawn_stream(app: AppHandle, recording: RecordingHandle) {
+    let generation = recording.generation();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(STREAM_INTERVAL).await;
+            if !recording.is_recording_generation(generation) {
+                break;
+            }
+            let (level, peak) = recording.get_level();
+            emit_audio_level(&app, level, peak);
+            let peaks = recording.take_waveform();
+            if !peaks.is_empty() {
+                emit_audio_waveform(&app, peaks);
+            }
+        }
+    });
+}
+
+/// Progress of a long transcription, as sent in "transcription-progress"
+#[derive(Clone, Serialize)]
+pub struct TranscriptionProgress {
+    /// History item ID, or "file" for file transcription
+    pub job: String,
+    pub percent: u8,
+}
+
+/// Emit transcription progress to the indicator and main window
+pub fn emit_progress(handle: &AppHandle, job: &str, percent: u8) {
+    let _ = handle.emit(
+        "transcription-progress",
+        TranscriptionProgress {
+            job: job.to_string(),
+            percent,
+        },
+    );
+}
+
+/// Rewritten text so far, as sent in "llm-preview" while the LLM streams
+#[derive(Clone, Serialize)]
+pub struct LlmPreview {
+    /// History item ID
+    pub job: String,
+    pub text: String,
+}
+
+/// Emit the LLM output generated so far to the indicator and main window
+pub fn emit_llm_preview(handle: &AppHandle, job: &str, text: &str) {
+    let _ = handle.emit(
+        "llm-preview",
+        LlmPreview {
+            job: job.to_string(),
+            text: text.to_string(),
+        },
+    );
+}
+
 /// Emit processing state to the indicator
 pub fn emit_processing(handle: &AppHandle, processing: bool) {
     let _ = handle.emit_to(INDICATOR_LABEL, "recording-processing", processing);
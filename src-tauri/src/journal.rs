@@ -0,0 +1,223 @@
+//! Daily journal
+//!
+//! Dictations in a journaling mode are appended to an entry for the local
+//! day, stored in the database and optionally mirrored to a Markdown file per
+//! day. A background job summarizes each finished day with the mode's LLM.
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::providers::llm;
+use crate::state::SharedState;
+use chrono::{DateTime, Local};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Time between checks for days that need a summary
+const SUMMARY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Delay before the first summary check, so setup can finish
+const SUMMARY_STARTUP_DELAY: Duration = Duration::from_secs(60);
+
+/// Prompt used for end-of-day summaries
+const SUMMARY_PROMPT: &str = r#"You are a helpful assistant that summarizes a day's journal.
+
+Instructions:
+- Write a short summary (3-5 sentences) of the day
+- Mention notable events, decisions, and feelings
+- List any open tasks or follow-ups at the end, if there are any
+- Do not add any preamble or explanation, just output the summary
+
+Journal entries:
+{{transcript}}
+
+Summary:"#;
+
+/// The local day for a timestamp, as `YYYY-MM-DD`
+pub fn day_of(at: &DateTime<Local>) -> String {
+    at.format("%Y-%m-%d").to_string()
+}
+
+/// Format a dictation as a single timestamped journal line
+pub fn format_line(text: &str, at: &DateTime<Local>) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("[{}] {}", at.format("%H:%M"), text)
+}
+
+/// Markdown file for a day
+pub fn markdown_path(dir: &Path, date: &str) -> PathBuf {
+    dir.join(format!("{}.md", date))
+}
+
+/// Heading of the summary section in a day's Markdown file
+const SUMMARY_HEADING: &str = "## Summary";
+
+/// Whether a Markdown line is a dictation written by `append`
+fn is_entry_line(line: &str) -> bool {
+    line.strip_prefix("- **")
+        .and_then(|rest| rest.get(..7))
+        .is_some_and(|time| {
+            time.ends_with("**")
+                && time[..5]
+                    .chars()
+                    .enumerate()
+                    .all(|(i, c)| if i == 2 { c == ':' } else { c.is_ascii_digit() })
+        })
+}
+
+/// A day's Markdown with its summary section replaced by `summary`
+///
+/// The previous summary, if any, is removed up to the next heading or
+/// dictation, so entries added after it are kept.
+fn with_summary(markdown: &str, summary: &str) -> String {
+    let mut out = String::with_capacity(markdown.len() + summary.len());
+    let mut in_summary = false;
+    for line in markdown.lines() {
+        if line.trim_end() == SUMMARY_HEADING {
+            // Drop the blank lines that separated the section too
+            let kept = out.trim_end().len();
+            out.truncate(kept);
+            if kept > 0 {
+                out.push('\n');
+            }
+            in_summary = true;
+            continue;
+        }
+        if in_summary && (line.starts_with('#') || is_entry_line(line)) {
+            in_summary = false;
+        }
+        if !in_summary {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    let kept = out.trim_end().len();
+    out.truncate(kept);
+    format!("{}\n\n{}\n\n{}\n", out, SUMMARY_HEADING, summary.trim())
+}
+
+/// Append a dictation to the day's journal entry and Markdown file
+pub fn append(db: &Database, markdown_dir: Option<&Path>, text: &str, at: &DateTime<Local>) -> Result<()> {
+    let date = day_of(at);
+    db.append_journal(&date, &format_line(text, at))?;
+
+    if let Some(dir) = markdown_dir {
+        std::fs::create_dir_all(dir)?;
+        let path = markdown_path(dir, &date);
+        let is_new = !path.exists();
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        if is_new {
+            writeln!(file, "# {}\n", date)?;
+        }
+        writeln!(file, "- **{}** {}", at.format("%H:%M"), text.trim())?;
+    }
+
+    Ok(())
+}
+
+/// Start the background job that summarizes finished days
+pub fn spawn_summary_job(handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SUMMARY_STARTUP_DELAY).await;
+
+        loop {
+            if let Some(state) = handle.try_state::<SharedState>() {
+                match crate::priority::background(summarize_pending(state.inner())).await {
+                    Ok(0) => {}
+                    Ok(n) => log::info!("Summarized {} journal day(s)", n),
+                    Err(e) => log::warn!("Journal summary failed: {}", e),
+                }
+            }
+
+            tokio::time::sleep(SUMMARY_INTERVAL).await;
+        }
+    });
+}
+
+/// Summarize every day before today that has entries but no summary
+///
+/// Uses the LLM of the first journaling mode that has a model configured.
+/// Returns the number of days summarized.
+pub async fn summarize_pending(state: &SharedState) -> Result<usize> {
+    let (db, provider, language, markdown_dir) = {
+        let state = state.lock().await;
+        if !state.settings.journal_summaries || state.is_read_only() {
+            return Ok(0);
+        }
+
+        let mut modes: Vec<_> = state
+            .modes
+            .values()
+            .filter(|m| m.journal && !m.disabled && !m.llm_model.is_empty())
+            .collect();
+        modes.sort_by(|a, b| a.key.cmp(&b.key));
+        let (Some(mode), Some(db)) = (modes.first(), state.database.clone()) else {
+            return Ok(0);
+        };
+
+        let api_key = state.get_api_key(&mode.llm_provider)?;
+        let provider = llm::create_llm_provider(
+            &mode.llm_provider,
+            &mode.llm_model,
+            api_key.as_deref(),
+            state.settings.ollama_url.clone(),
+        )?;
+        (
+            db,
+            provider,
+            state.settings.language.clone(),
+            state.settings.journal_dir.clone().map(PathBuf::from),
+        )
+    };
+
+    let today = day_of(&Local::now());
+    let pending = db.get_unsummarized_journal_entries(&today)?;
+
+    for entry in &pending {
+        crate::priority::yield_to_interactive().await;
+        let prompt = crate::modes::render_prompt(SUMMARY_PROMPT, &entry.content, None, &language);
+        let summary = provider.complete(&prompt).await?;
+        db.set_journal_summary(&entry.date, &summary)?;
+
+        if let Some(dir) = &markdown_dir {
+            let path = markdown_path(dir, &entry.date);
+            if path.exists() {
+                let markdown = std::fs::read_to_string(&path)?;
+                std::fs::write(&path, with_summary(&markdown, &summary))?;
+            }
+        }
+    }
+
+    Ok(pending.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_format_line_collapses_whitespace() {
+        let at = Local.with_ymd_and_hms(2025, 3, 1, 9, 5, 0).unwrap();
+        assert_eq!(day_of(&at), "2025-03-01");
+        assert_eq!(format_line(" Went for\na run. ", &at), "[09:05] Went for a run.");
+    }
+
+    #[test]
+    fn test_with_summary_replaces_previous_summary() {
+        let markdown = "# 2025-03-01\n\n- **09:05** Went for a run.\n";
+        let first = with_summary(markdown, "A run.");
+        assert_eq!(first, "# 2025-03-01\n\n- **09:05** Went for a run.\n\n## Summary\n\nA run.\n");
+
+        // A late dictation lands after the summary and is kept when it is redone
+        let late = format!("{}- **23:50** Read a book.\n", first);
+        let second = with_summary(&late, "- **Run** in the morning\n- Reading at night");
+        assert_eq!(
+            second,
+            "# 2025-03-01\n\n- **09:05** Went for a run.\n- **23:50** Read a book.\n\n\
+             ## Summary\n\n- **Run** in the morning\n- Reading at night\n"
+        );
+        assert_eq!(with_summary(&second, "Again.").matches(SUMMARY_HEADING).count(), 1);
+    }
+}
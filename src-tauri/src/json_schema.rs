@@ -0,0 +1,154 @@
+//! Minimal JSON Schema validation for structured LLM output
+//!
+//! Supports the subset modes need to describe extracted data: `type`,
+//! `properties`, `required`, `additionalProperties: false`, `items` and
+//! `enum`. Unknown keywords are ignored.
+
+use serde_json::Value;
+
+/// Parse an LLM response as JSON, tolerating a surrounding Markdown code fence
+pub fn parse_output(text: &str) -> std::result::Result<Value, String> {
+    let trimmed = text.trim();
+    let body = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+
+    serde_json::from_str(body.trim()).map_err(|e| format!("not valid JSON ({})", e))
+}
+
+/// Validate `value` against `schema`, returning one message per violation
+pub fn validate(value: &Value, schema: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(value, schema, "$", &mut errors);
+    errors
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            errors.push(format!("{}: must be one of {}", path, Value::Array(allowed.clone())));
+        }
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            errors.push(format!("{}: expected {}, got {}", path, types.join(" or "), type_name(value)));
+            return;
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !map.contains_key(key) {
+                        errors.push(format!("{}: missing required property \"{}\"", path, key));
+                    }
+                }
+            }
+
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let closed = schema.get("additionalProperties") == Some(&Value::Bool(false));
+            for (key, child) in map {
+                match properties.and_then(|p| p.get(key)) {
+                    Some(child_schema) => {
+                        validate_at(child, child_schema, &format!("{}.{}", path, key), errors)
+                    }
+                    None if closed => {
+                        errors.push(format!("{}: unexpected property \"{}\"", path, key))
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item, item_schema, &format!("{}[{}]", path, i), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Bool(_) => "boolean",
+        Value::Null => "null",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn task_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": { "type": "string" },
+                "due": { "type": ["string", "null"] },
+                "priority": { "enum": ["low", "normal", "high"] },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["title", "due"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_valid_task() {
+        let value = json!({ "title": "Call Sam", "due": null, "priority": "high", "tags": ["work"] });
+        assert!(validate(&value, &task_schema()).is_empty());
+    }
+
+    #[test]
+    fn test_reports_each_violation() {
+        let value = json!({ "due": 5, "priority": "urgent", "tags": ["a", 1], "extra": true });
+        let mut errors = validate(&value, &task_schema());
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "$.due: expected string or null, got number",
+                "$.priority: must be one of [\"low\",\"normal\",\"high\"]",
+                "$.tags[1]: expected string, got number",
+                "$: missing required property \"title\"",
+                "$: unexpected property \"extra\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_output_strips_code_fence() {
+        assert_eq!(parse_output("```json\n{\"a\": 1}\n```").unwrap(), json!({ "a": 1 }));
+        assert_eq!(parse_output(" [1, 2] ").unwrap(), json!([1, 2]));
+        assert!(parse_output("Sure! Here it is").is_err());
+    }
+}
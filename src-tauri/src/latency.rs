@@ -0,0 +1,162 @@
+//! Per-dictation latency tracing
+//!
+//! A `LatencyTrace` records nested pipeline stages (stream stop, STT, LLM,
+//! insertion) as a waterfall that is stored with the history item.
+
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// A single timed stage of the pipeline
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LatencySpan {
+    /// Stage name (e.g. "stt", "llm")
+    pub name: String,
+    /// Offset from the start of the trace
+    pub start_ms: u64,
+    /// Time spent in this stage
+    pub duration_ms: u64,
+    /// Nesting level (0 = whole dictation)
+    pub depth: u32,
+}
+
+/// Span being recorded
+struct OpenSpan {
+    index: usize,
+    started: Instant,
+}
+
+/// Collects spans for one dictation, from hotkey press to insertion
+pub struct LatencyTrace {
+    started: Instant,
+    spans: Vec<LatencySpan>,
+    open: Vec<OpenSpan>,
+}
+
+/// Identifies a span returned by `LatencyTrace::begin`
+#[derive(Debug, Clone, Copy)]
+pub struct SpanId(usize);
+
+impl LatencyTrace {
+    /// Start a new trace with a root span covering the whole dictation
+    pub fn start(root: &str) -> Self {
+        let mut trace = Self {
+            started: Instant::now(),
+            spans: Vec::new(),
+            open: Vec::new(),
+        };
+        trace.begin(root);
+        trace
+    }
+
+    /// Open a span nested under the currently open one
+    pub fn begin(&mut self, name: &str) -> SpanId {
+        let now = Instant::now();
+        let index = self.spans.len();
+        self.spans.push(LatencySpan {
+            name: name.to_string(),
+            start_ms: now.duration_since(self.started).as_millis() as u64,
+            duration_ms: 0,
+            depth: self.open.len() as u32,
+        });
+        self.open.push(OpenSpan {
+            index,
+            started: now,
+        });
+        SpanId(index)
+    }
+
+    /// Close a span (and any spans still open inside it), returning its duration in ms
+    ///
+    /// A span that is already closed is left as it was.
+    pub fn end(&mut self, id: SpanId) -> u64 {
+        if !self.open.iter().any(|open| open.index == id.0) {
+            return self.spans[id.0].duration_ms;
+        }
+        while let Some(open) = self.open.pop() {
+            self.spans[open.index].duration_ms = open.started.elapsed().as_millis() as u64;
+            if open.index == id.0 {
+                break;
+            }
+        }
+        self.spans[id.0].duration_ms
+    }
+
+    /// Name of the innermost open span
+    pub fn current(&self) -> Option<&str> {
+        self.open.last().map(|open| self.spans[open.index].name.as_str())
+    }
+
+    /// Close all open spans and return the waterfall
+    pub fn finish(&mut self) -> Vec<LatencySpan> {
+        self.end(SpanId(0));
+
+        for span in &self.spans {
+            log::debug!(
+                "{:indent$}{}: +{}ms, {}ms",
+                "",
+                span.name,
+                span.start_ms,
+                span.duration_ms,
+                indent = span.depth as usize * 2
+            );
+        }
+
+        std::mem::take(&mut self.spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_spans() {
+        let mut trace = LatencyTrace::start("dictation");
+        let stt = trace.begin("stt");
+        trace.end(stt);
+        let llm = trace.begin("llm");
+        trace.begin("request");
+        trace.end(llm);
+        let spans = trace.finish();
+
+        let names: Vec<_> = spans.iter().map(|s| (s.name.as_str(), s.depth)).collect();
+        assert_eq!(
+            names,
+            vec![("dictation", 0), ("stt", 1), ("llm", 1), ("request", 2)]
+        );
+    }
+
+    #[test]
+    fn test_current_span() {
+        let mut trace = LatencyTrace::start("dictation");
+        let llm = trace.begin("llm");
+        trace.begin("request");
+        assert_eq!(trace.current(), Some("request"));
+        trace.end(llm);
+        assert_eq!(trace.current(), Some("dictation"));
+        trace.finish();
+        assert_eq!(trace.current(), None);
+    }
+
+    #[test]
+    fn test_end_closed_span() {
+        let mut trace = LatencyTrace::start("dictation");
+        let stt = trace.begin("stt");
+        trace.end(stt);
+        trace.begin("llm");
+        trace.end(stt);
+        assert_eq!(trace.current(), Some("llm"));
+    }
+
+    #[test]
+    fn test_root_covers_children() {
+        let mut trace = LatencyTrace::start("dictation");
+        let span = trace.begin("work");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        trace.end(span);
+        let spans = trace.finish();
+
+        assert!(spans[0].duration_ms >= spans[1].duration_ms);
+        assert!(spans[1].duration_ms >= 5);
+    }
+}
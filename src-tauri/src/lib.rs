@@ -4,16 +4,72 @@
 //! AI post-processing, all accessible from the system tray.
 
 pub mod audio;
+pub mod audio_codec;
+pub mod batch;
+pub mod browser_tab;
+pub mod cancel;
+pub mod capabilities;
+pub mod changelog;
+pub mod cleanup;
 pub mod commands;
+pub mod config_bundle;
+pub mod connectivity;
+pub mod control;
+pub mod daemon;
 pub mod database;
+pub mod dictionary;
 pub mod error;
+pub mod events;
+pub mod features;
+pub mod gpu;
+pub mod health;
+pub mod hooks;
 pub mod hotkey;
+pub mod hotplug;
+pub mod http_api;
 pub mod indicator;
+pub mod journal;
+pub mod json_schema;
+pub mod latency;
+pub mod limit;
+pub mod mcp;
+pub mod media;
+pub mod mic_conflict;
 pub mod modes;
+pub mod notify;
+pub mod output;
+pub mod pacing;
 pub mod paste;
+pub mod phrases;
+pub mod picker;
+pub mod pipeline;
+pub mod playback;
+#[cfg(feature = "pipewire")]
+pub mod pipewire_backend;
+pub mod portal;
+pub mod preroll;
+pub mod priority;
 pub mod providers;
+pub mod punctuation;
+pub mod redact;
+pub mod reload;
+pub mod remote;
+pub mod retry;
+pub mod samples;
+pub mod secrets;
+pub mod segmenter;
+pub mod shutdown;
+pub mod sounds;
 pub mod state;
+pub mod style;
+pub mod template;
+pub mod text_stats;
 pub mod tray;
+pub mod typography;
+pub mod vad;
+pub mod warmup;
+pub mod watch;
+pub mod window_info;
 
 use log::info;
 use state::AppState;
@@ -21,12 +77,64 @@ use std::sync::Arc;
 use tauri::Manager;
 use tokio::sync::Mutex;
 
+/// Print features degraded by missing binaries; returns the exit code
+///
+/// Run as `whispertray doctor`. Exits with 1 when anything is degraded.
+pub fn doctor() -> i32 {
+    let settings = AppState::load_settings().unwrap_or_default();
+    paste::set_uinput_allowed(settings.allow_evdev_input);
+    paste::set_clipboard_backend(settings.clipboard_backend);
+
+    let degraded = capabilities::degradations(&settings);
+    if degraded.is_empty() {
+        println!("All features are available.");
+        return 0;
+    }
+    for degradation in &degraded {
+        println!("{}: {}", degradation.feature, degradation.impact);
+        println!("  fix: {}", degradation.hint);
+    }
+    1
+}
+
+/// Print a systemd user unit running this executable as a daemon; returns the exit code
+///
+/// Run as `whispertray daemon --print-service`.
+pub fn print_service() -> i32 {
+    match daemon::service_executable() {
+        Ok(exe) => {
+            print!("{}", daemon::service_unit(&exe));
+            0
+        }
+        Err(e) => {
+            eprintln!("Could not find the WhisperTray executable: {}", e);
+            1
+        }
+    }
+}
+
 /// Initialize and run the Tauri application
 pub fn run() {
+    run_app(false);
+}
+
+/// Run without a tray icon or windows, e.g. as a systemd user service
+pub fn run_daemon() {
+    run_app(true);
+}
+
+fn run_app(headless: bool) {
     // Initialize logging
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    info!("Starting WhisperTray...");
+    info!("Starting WhisperTray{}...", if headless { " as a daemon" } else { "" });
+    daemon::set_headless(headless);
+
+    let mut context = tauri::generate_context!();
+    if headless {
+        // No webviews at all, not even hidden ones
+        context.config_mut().app.windows.clear();
+    }
 
     tauri::Builder::default()
         .plugin(tauri_plugin_clipboard_manager::init())
@@ -38,19 +146,78 @@ pub fn run() {
             info!("Setting up application...");
 
             // Initialize application state
-            let state = Arc::new(Mutex::new(AppState::new(app.handle().clone())?));
+            let app_state = AppState::new(app.handle().clone())?;
+
+            // The cancel token is reachable without the state lock
+            app.manage(app_state.cancel.clone());
+            app.manage(app_state.picker.clone());
+            app.manage(batch::BatchQueue::default());
+            let health_port = app_state.settings.health_port;
+            let hotkeys = app_state.settings.hotkeys.clone();
+            let bridge = app_state.settings.bridge_port.zip(app_state.settings.bridge_token.clone());
+            let bridge_lan = app_state.settings.bridge_lan;
+            let api = app_state.settings.api_port.zip(app_state.settings.api_token.clone());
+            let state = Arc::new(Mutex::new(app_state));
 
             // Store state in app
             app.manage(state.clone());
 
             // Set up system tray
-            tray::setup_tray(app)?;
+            if !daemon::is_headless() {
+                tray::setup_tray(app)?;
+            }
+
+            // Refresh device lists when PipeWire devices change
+            #[cfg(feature = "pipewire")]
+            if audio::use_pipewire() {
+                pipewire_backend::spawn_device_watch(app.handle().clone());
+            }
 
             // Set up global hotkey (Ctrl+Space by default)
-            if let Err(e) = hotkey::setup_hotkey(app) {
+            if let Err(e) = hotkey::setup_hotkey(app, &hotkeys) {
                 log::error!("Failed to set up global hotkey: {}", e);
             }
 
+            // Start the watchdog and, if configured, the health endpoint
+            let health = health::HealthMonitor::default();
+            app.manage(health.clone());
+            health::spawn_watchdog(app.handle().clone(), health.clone());
+            if let Some(port) = health_port {
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = health::serve_health(port, health).await {
+                        log::error!("Health endpoint failed: {}", e);
+                    }
+                });
+            }
+
+            // Accept text from paired instances, if configured
+            if let Some((port, token)) = bridge.filter(|_| features::enabled(features::Flag::PasteBridge)) {
+                let state = state.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = remote::serve_bridge(port, token, bridge_lan, state).await {
+                        log::error!("Paste bridge failed: {}", e);
+                    }
+                });
+            }
+
+            // Let local scripts and launchers drive recording, if configured
+            if let Some((port, token)) = api {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = http_api::serve(port, token, handle).await {
+                        log::error!("HTTP API failed: {}", e);
+                    }
+                });
+            }
+
+            // Let linwhisperctl and shell scripts drive recording
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = control::serve(handle).await {
+                    log::error!("Control socket failed: {}", e);
+                }
+            });
+
             // Load modes
             let app_handle = app.handle().clone();
             let state_clone = state.clone();
@@ -74,32 +241,125 @@ pub fn run() {
                 }
             });
 
+            // Load the last used models before the first dictation needs them
+            warmup::spawn(state.clone());
+
+            // Summarize finished journal days in the background
+            journal::spawn_summary_job(app.handle().clone());
+
+            // Carry on with batches interrupted by the last shutdown
+            batch::spawn_resume(app.handle().clone());
+
+            // Transcribe audio files dropped into the watch folder, if set
+            watch::spawn(app.handle().clone());
+
+            // Apply settings and mode files edited outside the app
+            reload::spawn(app.handle().clone());
+
+            // Keep the learned style profile up to date, when opted in
+            style::spawn_analysis_job(app.handle().clone());
+
+            // Finish work in progress on logout or Ctrl+C as on Quit
+            shutdown::spawn_signal_handler(app.handle().clone());
+
             info!("Application setup complete");
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::start_recording,
             commands::stop_recording,
+            commands::cancel_recording,
+            commands::request_mic_access,
             commands::get_recording_status,
+            commands::set_incognito,
+            commands::add_bookmark,
+            commands::pause_recording,
+            commands::resume_recording,
             commands::get_modes,
             commands::set_active_mode,
             commands::get_active_mode,
+            commands::validate_mode,
+            commands::create_mode,
+            commands::update_mode,
+            commands::delete_mode,
+            commands::duplicate_mode,
             commands::get_input_devices,
+            commands::get_audio_backends,
+            commands::get_audio_hosts,
+            commands::record_calibration_clip,
+            commands::compute_calibration,
             commands::set_input_device,
             commands::transcribe_file,
+            commands::list_samples,
+            commands::test_mode,
+            commands::dry_run_mode,
             commands::get_history,
+            commands::get_failed_history,
             commands::get_history_item,
+            commands::play_history_audio,
+            commands::pause_history_audio,
+            commands::seek_history_audio,
+            commands::stop_history_audio,
             commands::reprocess_history_item,
+            commands::retranscribe_history_item,
+            commands::edit_history_item,
+            commands::get_revisions,
+            commands::get_history_alternatives,
             commands::delete_history_item,
+            commands::restore_history_item,
+            commands::get_trash,
+            commands::purge_history,
+            commands::get_monthly_spend,
+            commands::get_history_stats,
+            commands::is_history_encryption_available,
+            commands::rotate_history_key,
+            commands::suggest_phrases,
+            commands::get_pick_request,
+            commands::choose_candidate,
+            commands::get_model_recommendation,
+            commands::get_downloaded_models,
+            commands::start_batch,
+            commands::get_batches,
+            commands::pause_batch,
+            commands::resume_batch,
+            commands::cancel_batch,
+            commands::delete_batch,
+            commands::get_journal_entry,
+            commands::get_style_profile,
+            commands::export_dictionary,
+            commands::import_dictionary,
+            commands::export_settings,
+            commands::import_settings,
+            commands::refresh_style_profile,
+            commands::preview_prompt_template,
+            commands::get_pacing_preset,
             commands::export_history_item,
             commands::get_settings,
             commands::update_settings,
+            commands::is_read_only,
+            commands::get_health,
+            commands::get_capabilities,
+            commands::get_feature_flags,
+            commands::get_whats_new,
+            commands::dismiss_whats_new,
+            commands::get_degradations,
+            commands::swap_stt_provider,
+            commands::get_paste_info,
             commands::save_api_key,
             commands::delete_api_key,
             commands::has_api_key,
             commands::test_whisper_connection,
             commands::test_ollama_connection,
+            commands::test_wyoming_connection,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(context)
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Drain work in progress before exiting
+            if let tauri::RunEvent::ExitRequested { code, api, .. } = event {
+                if !shutdown::on_exit_requested(app, code.unwrap_or(0)) {
+                    api.prevent_exit();
+                }
+            }
+        });
 }
@@ -0,0 +1,107 @@
+//! Output length caps
+//!
+//! Modes and target apps can cap output length (e.g. 280 characters for a
+//! social post). Over-long LLM output is first sent back to be shortened;
+//! whatever still does not fit is truncated at a sentence or word boundary.
+
+use std::collections::HashMap;
+
+/// Times the LLM is asked to shorten before falling back to truncation
+pub const SHORTEN_ATTEMPTS: usize = 2;
+
+/// The cap for a dictation: the stricter of the mode's and the target app's
+///
+/// App limits are keyed by window class name, compared case-insensitively.
+pub fn effective_limit(
+    mode_limit: Option<usize>,
+    app_limits: &HashMap<String, usize>,
+    app_name: Option<&str>,
+) -> Option<usize> {
+    let app_limit = app_name.and_then(|app| {
+        app_limits
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(app))
+            .map(|(_, limit)| *limit)
+    });
+
+    match (mode_limit, app_limit) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Whether `text` is longer than `max_chars` characters
+pub fn exceeds(text: &str, max_chars: usize) -> bool {
+    text.chars().count() > max_chars
+}
+
+/// Prompt asking the LLM to shorten text to fit
+pub fn shorten_prompt(text: &str, max_chars: usize) -> String {
+    format!(
+        "Shorten the following text to at most {} characters (it is {} now). Keep the meaning, tone and \
+language. Output only the shortened text.\n\nText:\n{}",
+        max_chars,
+        text.chars().count(),
+        text
+    )
+}
+
+/// Cut `text` to at most `max_chars` characters without splitting words
+///
+/// Ends after the last complete sentence when one fits in the second half of
+/// the limit; otherwise cuts at the last word boundary and adds an ellipsis.
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    if !exceeds(text, max_chars) {
+        return text.to_string();
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let window = &chars[..max_chars];
+
+    // End of the last sentence that fits, if it keeps at least half the limit
+    let sentence_end = (0..window.len()).rev().find(|&i| {
+        matches!(window[i], '.' | '!' | '?') && chars.get(i + 1).is_none_or(|c| c.is_whitespace())
+    });
+    if let Some(end) = sentence_end.filter(|&end| end + 1 >= max_chars / 2) {
+        return window[..=end].iter().collect();
+    }
+
+    // Leave room for the ellipsis, then back off to a word boundary
+    let room = max_chars.saturating_sub(1);
+    let cut = (1..=room)
+        .rev()
+        .find(|&i| chars[i].is_whitespace())
+        .unwrap_or(room);
+    let kept: String = chars[..cut].iter().collect();
+    format!("{}…", kept.trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_limit() {
+        let apps = HashMap::from([("Slack".to_string(), 500), ("X".to_string(), 280)]);
+        assert_eq!(effective_limit(Some(1000), &apps, Some("slack")), Some(500));
+        assert_eq!(effective_limit(Some(200), &apps, Some("x")), Some(200));
+        assert_eq!(effective_limit(None, &apps, Some("firefox")), None);
+        assert_eq!(effective_limit(Some(280), &HashMap::new(), None), Some(280));
+    }
+
+    #[test]
+    fn test_truncate_at_sentence() {
+        let text = "First sentence here. Second one is longer and will not fit.";
+        assert_eq!(truncate(text, 30), "First sentence here.");
+        assert_eq!(truncate(text, 100), text);
+    }
+
+    #[test]
+    fn test_truncate_at_word() {
+        let text = "one two three four five six seven";
+        let result = truncate(text, 16);
+        assert_eq!(result, "one two three…");
+        assert!(result.chars().count() <= 16);
+    }
+}
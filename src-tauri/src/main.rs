@@ -3,5 +3,15 @@
 use whispertray_lib::run;
 
 fn main() {
-    run();
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("doctor") => std::process::exit(whispertray_lib::doctor()),
+        Some("daemon") => {
+            if args.next().as_deref() == Some("--print-service") {
+                std::process::exit(whispertray_lib::print_service());
+            }
+            whispertray_lib::run_daemon();
+        }
+        _ => run(),
+    }
 }
@@ -0,0 +1,273 @@
+//! Model Context Protocol server
+//!
+//! Lets AI assistants that speak MCP search dictation history, fetch the last
+//! transcript and transcribe audio files with WhisperTray's own models. It is
+//! served as JSON-RPC over HTTP at `/mcp` on the HTTP API (see `http_api`),
+//! so it shares its localhost listener and bearer token. Every request gets a
+//! plain JSON reply; the server never starts a stream of its own.
+
+use crate::database::HistoryItem;
+use crate::state::SharedState;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Protocol revision this server implements
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// History items `search_history` returns unless asked for another number
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+/// Most history items one `search_history` call returns
+const MAX_SEARCH_LIMIT: usize = 100;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    jsonrpc: String,
+    /// Missing on notifications, which get no reply
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// What a request asks for
+#[derive(Debug, PartialEq)]
+enum Call {
+    /// Answered without touching app state
+    Answer(Value),
+    /// A tool to run
+    Tool(Tool),
+}
+
+#[derive(Debug, PartialEq)]
+enum Tool {
+    TranscribeFile { path: String, mode: Option<String> },
+    SearchHistory { query: String, limit: usize },
+    GetLastTranscript,
+}
+
+/// A JSON-RPC error code and message
+type RpcError = (i64, String);
+
+/// Handle one JSON-RPC message, returning the reply unless it was a notification
+pub async fn handle(message: &[u8], state: &SharedState) -> Option<Value> {
+    let message: Message = match serde_json::from_slice::<Value>(message) {
+        Ok(value) => match serde_json::from_value(value) {
+            Ok(message) => message,
+            Err(e) => return Some(error_reply(Value::Null, (INVALID_REQUEST, e.to_string()))),
+        },
+        Err(e) => return Some(error_reply(Value::Null, (PARSE_ERROR, e.to_string()))),
+    };
+    let id = message.id?;
+    if message.jsonrpc != "2.0" {
+        return Some(error_reply(id, (INVALID_REQUEST, "Expected JSON-RPC 2.0".to_string())));
+    }
+
+    let result = match route(&message.method, &message.params) {
+        Ok(Call::Answer(result)) => result,
+        Ok(Call::Tool(tool)) => match run_tool(tool, state).await {
+            Ok(text) => tool_result(text, false),
+            // Tool failures are results the model can read, not protocol errors
+            Err(e) => tool_result(e, true),
+        },
+        Err(e) => return Some(error_reply(id, e)),
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn route(method: &str, params: &Value) -> Result<Call, RpcError> {
+    match method {
+        "initialize" => Ok(Call::Answer(json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "whispertray", "version": env!("CARGO_PKG_VERSION") },
+            "instructions": "Tools for the user's WhisperTray voice dictations: search past transcripts, \
+                fetch the latest one, or transcribe a local audio file.",
+        }))),
+        "ping" => Ok(Call::Answer(json!({}))),
+        "tools/list" => Ok(Call::Answer(json!({ "tools": tool_definitions() }))),
+        "tools/call" => parse_tool(params).map(Call::Tool),
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+    }
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "transcribe_file",
+            "description": "Transcribe a WAV, FLAC or Ogg file on this computer with a WhisperTray mode, \
+                saving it to history. Returns the processed text.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Absolute path of the audio file" },
+                    "mode": { "type": "string", "description": "Mode key; the active mode if omitted" }
+                },
+                "required": ["path"]
+            }
+        },
+        {
+            "name": "search_history",
+            "description": "Search past dictations for words or phrases, newest first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_SEARCH_LIMIT }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "get_last_transcript",
+            "description": "Get the most recent dictation's text.",
+            "inputSchema": { "type": "object", "properties": {} }
+        }
+    ])
+}
+
+fn parse_tool(params: &Value) -> Result<Tool, RpcError> {
+    let arguments = &params["arguments"];
+    let string = |name: &str| arguments[name].as_str().map(str::to_string);
+    let invalid = |message: &str| (INVALID_PARAMS, message.to_string());
+
+    match params["name"].as_str() {
+        Some("transcribe_file") => Ok(Tool::TranscribeFile {
+            path: string("path").ok_or_else(|| invalid("transcribe_file needs a path"))?,
+            mode: string("mode"),
+        }),
+        Some("search_history") => Ok(Tool::SearchHistory {
+            query: string("query").ok_or_else(|| invalid("search_history needs a query"))?,
+            limit: arguments["limit"]
+                .as_u64()
+                .map_or(DEFAULT_SEARCH_LIMIT, |limit| (limit as usize).clamp(1, MAX_SEARCH_LIMIT)),
+        }),
+        Some("get_last_transcript") => Ok(Tool::GetLastTranscript),
+        Some(name) => Err((INVALID_PARAMS, format!("Unknown tool: {}", name))),
+        None => Err(invalid("Missing tool name")),
+    }
+}
+
+async fn run_tool(tool: Tool, state: &SharedState) -> Result<String, String> {
+    let db = state
+        .lock()
+        .await
+        .database
+        .clone()
+        .ok_or_else(|| "Database not initialized".to_string())?;
+
+    match tool {
+        Tool::TranscribeFile { path, mode } => {
+            let path = Path::new(&path);
+            if !path.is_absolute() || !path.is_file() {
+                return Err(format!("No such file: {}", path.display()));
+            }
+            let item = crate::priority::background(crate::watch::transcribe_to_history(
+                state,
+                &db,
+                path,
+                mode.as_deref(),
+                Utc::now(),
+            ))
+            .await?;
+            Ok(item.output_final)
+        }
+        Tool::SearchHistory { query, limit } => {
+            let items = db
+                .run(move |db| db.search_history(&query, limit))
+                .await
+                .map_err(|e| e.to_string())?;
+            if items.is_empty() {
+                return Ok("No matching dictations.".to_string());
+            }
+            Ok(items.iter().map(format_item).collect::<Vec<_>>().join("\n\n"))
+        }
+        Tool::GetLastTranscript => {
+            let items = db.run(|db| db.get_history(1, 0)).await.map_err(|e| e.to_string())?;
+            items
+                .first()
+                .map(|item| item.output_final.clone())
+                .ok_or_else(|| "There are no dictations yet.".to_string())
+        }
+    }
+}
+
+/// A history item as a short block of text for the model to read
+fn format_item(item: &HistoryItem) -> String {
+    format!(
+        "[{}] {} ({})\n{}",
+        item.id,
+        item.created_at.format("%Y-%m-%d %H:%M"),
+        item.mode_key,
+        item.output_final
+    )
+}
+
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+fn error_reply(id: Value, (code, message): RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        let Call::Answer(init) = route("initialize", &json!({})).unwrap() else {
+            panic!("initialize should be answered directly");
+        };
+        assert_eq!(init["protocolVersion"], PROTOCOL_VERSION);
+
+        let Call::Answer(list) = route("tools/list", &Value::Null).unwrap() else {
+            panic!("tools/list should be answered directly");
+        };
+        let names: Vec<_> = list["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["transcribe_file", "search_history", "get_last_transcript"]);
+
+        assert_eq!(route("resources/list", &Value::Null).unwrap_err().0, METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_parse_tool() {
+        let call = json!({ "name": "search_history", "arguments": { "query": "standup", "limit": 500 } });
+        assert_eq!(
+            parse_tool(&call).unwrap(),
+            Tool::SearchHistory {
+                query: "standup".to_string(),
+                limit: MAX_SEARCH_LIMIT
+            }
+        );
+
+        let call = json!({ "name": "transcribe_file", "arguments": { "path": "/tmp/memo.flac" } });
+        assert_eq!(
+            parse_tool(&call).unwrap(),
+            Tool::TranscribeFile {
+                path: "/tmp/memo.flac".to_string(),
+                mode: None
+            }
+        );
+
+        assert_eq!(parse_tool(&json!({ "name": "transcribe_file" })).unwrap_err().0, INVALID_PARAMS);
+        assert_eq!(parse_tool(&json!({ "name": "rm_rf" })).unwrap_err().0, INVALID_PARAMS);
+    }
+}
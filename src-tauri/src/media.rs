@@ -0,0 +1,134 @@
+//! Pausing media players while recording
+//!
+//! Music or a video playing through speakers bleeds into the microphone and
+//! wrecks the transcript. With `pause_media` on, every MPRIS player (Spotify,
+//! mpv, browsers) that is playing when recording starts is paused, and once
+//! the dictation has been pasted, or cancelled, the players that are still
+//! paused are resumed. Players are driven over the session bus with `gdbus`,
+//! which ships with GLib.
+
+use std::process::Command;
+use tauri::async_runtime::JoinHandle;
+
+/// Bus name prefix of MPRIS players
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// Players paused for a recording, being looked up in the background
+pub struct PausedMedia(JoinHandle<Vec<String>>);
+
+impl PausedMedia {
+    /// Resume the players once the lookup that paused them is done
+    pub fn resume(self) {
+        tauri::async_runtime::spawn(async move {
+            let Ok(players) = self.0.await else {
+                return;
+            };
+            if !players.is_empty() {
+                let _ = tauri::async_runtime::spawn_blocking(move || resume_players(&players)).await;
+            }
+        });
+    }
+}
+
+/// Pause the playing players in the background, so recording starts at once
+pub fn pause() -> PausedMedia {
+    PausedMedia(tauri::async_runtime::spawn_blocking(pause_playing))
+}
+
+fn gdbus_call(dest: &str, path: &str, method: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new("gdbus")
+        .args(["call", "--session", "--dest", dest, "--object-path", path, "--method", method])
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn playback_status(player: &str) -> Option<String> {
+    let output = gdbus_call(
+        player,
+        MPRIS_PATH,
+        "org.freedesktop.DBus.Properties.Get",
+        &["org.mpris.MediaPlayer2.Player", "PlaybackStatus"],
+    )?;
+    parse_status(&output)
+}
+
+/// Pause every player that is playing, returning their bus names
+fn pause_playing() -> Vec<String> {
+    let Some(names) = gdbus_call(
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus.ListNames",
+        &[],
+    ) else {
+        log::warn!("Could not list media players; is gdbus installed?");
+        return Vec::new();
+    };
+
+    let mut paused = Vec::new();
+    for player in parse_players(&names) {
+        if playback_status(&player).as_deref() != Some("Playing") {
+            continue;
+        }
+        if gdbus_call(&player, MPRIS_PATH, "org.mpris.MediaPlayer2.Player.Pause", &[]).is_some() {
+            log::info!("Paused {} for recording", player);
+            paused.push(player);
+        }
+    }
+    paused
+}
+
+/// Resume the players still paused; ones stopped or restarted meanwhile are left alone
+fn resume_players(players: &[String]) {
+    for player in players {
+        if playback_status(player).as_deref() == Some("Paused")
+            && gdbus_call(player, MPRIS_PATH, "org.mpris.MediaPlayer2.Player.Play", &[]).is_some()
+        {
+            log::info!("Resumed {}", player);
+        }
+    }
+}
+
+/// MPRIS player names in the output of `ListNames`, e.g. `(['org.mpris.MediaPlayer2.mpv', ...],)`
+fn parse_players(output: &str) -> Vec<String> {
+    output
+        .split('\'')
+        .skip(1)
+        .step_by(2)
+        .filter(|name| name.starts_with(MPRIS_PREFIX))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The status in a `PlaybackStatus` reply, e.g. `(<'Playing'>,)`
+fn parse_status(output: &str) -> Option<String> {
+    output.split('\'').nth(1).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_players() {
+        let output = "(['org.freedesktop.DBus', ':1.7', 'org.mpris.MediaPlayer2.spotify', \
+                      'org.gnome.Shell', 'org.mpris.MediaPlayer2.firefox.instance_1_84'],)\n";
+        assert_eq!(
+            parse_players(output),
+            vec!["org.mpris.MediaPlayer2.spotify", "org.mpris.MediaPlayer2.firefox.instance_1_84"]
+        );
+        assert!(parse_players("([],)\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status("(<'Playing'>,)\n").as_deref(), Some("Playing"));
+        assert_eq!(parse_status(""), None);
+    }
+}
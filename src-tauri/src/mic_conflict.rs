@@ -0,0 +1,558 @@
+//! Reacting to other apps taking the microphone mid-dictation
+//!
+//! While recording, the audio server's capture streams are followed: with
+//! the `pipewire` feature by watching PipeWire's stream nodes, otherwise by
+//! polling `pactl list source-outputs` (PulseAudio, or PipeWire's pulse
+//! server). Streams already open when recording started, such as a noise
+//! suppressor, are ignored; a new one means another app, usually a video
+//! call, started using the microphone. Depending on the setting the user is
+//! only notified, or our capture pauses until the app lets go, or the
+//! dictation stops, so a call is never recorded by accident.
+//!
+//! Two other ways of recording nothing are diagnosed too. A device another
+//! app opened exclusively fails to open, and the holders are named from
+//! `/proc` ("busy: used by Zoom") in the background, after the error. And
+//! when the audio server attaches none of our streams to the source
+//! selected, e.g. because of a remembered route, the user is told which
+//! source is actually being recorded.
+
+use crate::audio::RecordingHandle;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// How often capture streams are listed (or the PipeWire watch checks the recording is still on)
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Stream names of level meters (pavucontrol), which record nothing
+pub(crate) const METER_STREAMS: &[&str] = &["Peak detect"];
+
+/// Audio servers, which hold capture devices on everyone's behalf
+const AUDIO_SERVERS: &[&str] = &["pipewire", "pulseaudio", "wireplumber", "jackd", "jackdbg"];
+
+/// How long after recording starts its routing is checked, once the stream is up
+const ROUTE_CHECK_DELAY: Duration = Duration::from_millis(750);
+
+/// What happens when another app starts using the microphone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MicConflictAction {
+    /// Keep recording without telling the user
+    Ignore,
+    /// Keep recording and show a notification
+    #[default]
+    Notify,
+    /// Drop audio until the other app releases the microphone
+    Pause,
+    /// Stop and process what was dictated so far
+    Stop,
+}
+
+/// Sent with the "mic-conflict" event
+#[derive(Debug, Clone, Serialize)]
+pub struct MicConflict {
+    /// Apps that started capturing, by name
+    pub apps: Vec<String>,
+    pub action: MicConflictAction,
+}
+
+/// Why the microphone records nothing, or not the one selected
+///
+/// Sent with the "mic-diagnostic" event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MicDiagnostic {
+    /// The device could not be opened; `apps` are the ones using it
+    Busy { device: String, apps: Vec<String> },
+    /// The audio server records from `actual` rather than the selected source
+    WrongSource { expected: String, actual: String },
+}
+
+impl MicDiagnostic {
+    pub fn message(&self) -> String {
+        match self {
+            MicDiagnostic::Busy { device, apps } => {
+                let device = match device.as_str() {
+                    "" | "default" => "The microphone",
+                    device => device,
+                };
+                if apps.is_empty() {
+                    format!("{} is busy", device)
+                } else {
+                    format!("{} is busy: used by {}", device, apps.join(", "))
+                }
+            }
+            MicDiagnostic::WrongSource { expected, actual } => format!(
+                "Recording from {} instead of {}; change WhisperTray's input in your sound settings",
+                actual, expected
+            ),
+        }
+    }
+}
+
+/// A capture stream of the audio server
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CaptureStream {
+    id: u32,
+    app: String,
+}
+
+/// A `key = "value"` property of a `pactl list` block
+fn block_property(block: &str, key: &str) -> Option<String> {
+    block.lines().find_map(|line| {
+        let value = line.trim().strip_prefix(key)?.trim_start().strip_prefix('=')?;
+        Some(value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn is_own_block(block: &str, own_pid: u32) -> bool {
+    block_property(block, "application.process.id").is_some_and(|pid| pid == own_pid.to_string())
+}
+
+/// Parse `pactl list source-outputs`, leaving out streams of `own_pid`,
+/// paused (corked) streams and level meters
+fn parse_source_outputs(output: &str, own_pid: u32) -> Vec<CaptureStream> {
+    let mut streams = Vec::new();
+    for block in output.split("Source Output #").skip(1) {
+        let Some(id) = block.lines().next().and_then(|line| line.trim().parse().ok()) else {
+            continue;
+        };
+        let property = |key: &str| block_property(block, key);
+
+        let own = is_own_block(block, own_pid);
+        let corked = block.lines().any(|line| line.trim() == "Corked: yes");
+        let meter = property("media.name").is_some_and(|name| METER_STREAMS.contains(&name.as_str()));
+        if own || corked || meter {
+            continue;
+        }
+
+        let app = property("application.name")
+            .or_else(|| property("application.process.binary"))
+            .unwrap_or_else(|| "Unknown app".to_string());
+        streams.push(CaptureStream { id, app });
+    }
+    streams
+}
+
+/// Indexes of the sources `own_pid`'s capture streams are attached to
+///
+/// There can be several: the system output's monitor when recording both, or
+/// a pre-roll stream.
+fn own_sources(output: &str, own_pid: u32) -> Vec<u32> {
+    output
+        .split("Source Output #")
+        .skip(1)
+        .filter(|block| is_own_block(block, own_pid))
+        .filter_map(|block| {
+            block
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("Source:")?.trim().parse().ok())
+        })
+        .collect()
+}
+
+/// Ids of `own_pid`'s capture streams
+fn own_streams(output: &str, own_pid: u32) -> Vec<u32> {
+    output
+        .split("Source Output #")
+        .skip(1)
+        .filter(|block| is_own_block(block, own_pid))
+        .filter_map(|block| block.lines().next()?.trim().parse().ok())
+        .collect()
+}
+
+/// Ids of our capture streams, None when `pactl` is unavailable
+pub(crate) fn own_capture_streams() -> Option<Vec<u32>> {
+    Some(own_streams(&pactl(&["list", "source-outputs"])?, std::process::id()))
+}
+
+/// Have the audio server record capture stream `id` from `source` instead; false if it failed
+pub(crate) fn move_capture_stream(id: u32, source: &str) -> bool {
+    pactl(&["move-source-output", &id.to_string(), source]).is_some()
+}
+
+/// Which source is recorded instead of `expected`, unless one of our streams (`actual`) records it
+fn wrong_source(expected: String, actual: &[String]) -> Option<MicDiagnostic> {
+    if actual.is_empty() || actual.contains(&expected) {
+        return None;
+    }
+    let actual = actual
+        .iter()
+        .find(|name| !name.ends_with(".monitor"))
+        .unwrap_or(&actual[0])
+        .clone();
+    Some(MicDiagnostic::WrongSource { expected, actual })
+}
+
+/// Source names by index, from `pactl list short sources`
+fn parse_sources(output: &str) -> HashMap<u32, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let index = fields.next()?.trim().parse().ok()?;
+            Some((index, fields.next()?.to_string()))
+        })
+        .collect()
+}
+
+/// Output of a `pactl` command, None when it is unavailable or fails
+fn pactl(args: &[&str]) -> Option<String> {
+    let output = Command::new("pactl")
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Capture streams of other apps, None when `pactl` is unavailable
+fn capture_streams() -> Option<Vec<CaptureStream>> {
+    let output = pactl(&["list", "source-outputs"])?;
+    Some(parse_source_outputs(&output, std::process::id()))
+}
+
+/// Whether `path` is an ALSA capture device, e.g. `/dev/snd/pcmC1D0c`
+fn is_capture_pcm(path: &Path) -> bool {
+    path.to_str()
+        .and_then(|path| path.strip_prefix("/dev/snd/pcmC"))
+        .is_some_and(|rest| rest.ends_with('c'))
+}
+
+/// Names of the processes holding ALSA capture devices open, other than audio servers
+///
+/// An app that opened the hardware directly never shows up in `pactl`.
+pub(crate) fn alsa_capture_holders() -> Vec<String> {
+    let own_pid = std::process::id().to_string();
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut apps = Vec::new();
+    for process in processes.filter_map(|entry| entry.ok()) {
+        let pid = process.file_name().to_string_lossy().into_owned();
+        if pid == own_pid || !pid.bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        // Other users' file descriptors can't be read, and their apps don't matter
+        let Ok(fds) = std::fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+        let holds_capture = fds
+            .filter_map(|fd| fd.ok())
+            .any(|fd| std::fs::read_link(fd.path()).is_ok_and(|target| is_capture_pcm(&target)));
+        if !holds_capture {
+            continue;
+        }
+        let name = std::fs::read_to_string(process.path().join("comm")).unwrap_or_default();
+        let name = name.trim().to_string();
+        if !name.is_empty() && !AUDIO_SERVERS.contains(&name.as_str()) && !apps.contains(&name) {
+            apps.push(name);
+        }
+    }
+    apps.sort();
+    apps
+}
+
+/// Explain a failure to open `device`, if it looks like another app holds it
+fn diagnose_open_failure(device: &str, error: &str) -> Option<MicDiagnostic> {
+    let error = error.to_lowercase();
+    if !error.contains("busy") && !error.contains("not available") {
+        return None;
+    }
+    let mut apps = alsa_capture_holders();
+    for stream in capture_streams().unwrap_or_default() {
+        if !apps.contains(&stream.app) {
+            apps.push(stream.app);
+        }
+    }
+    Some(MicDiagnostic::Busy {
+        device: device.to_string(),
+        apps,
+    })
+}
+
+/// Tell the user which app holds `device` after it failed to open, if one does
+///
+/// Scanning `/proc` and asking `pactl` takes a while, so the caller returns the
+/// error right away and the diagnosis follows as a notification.
+pub fn spawn_open_failure_check(app: AppHandle, device: &str, error: &str) {
+    let (device, error) = (device.to_string(), error.to_string());
+    tauri::async_runtime::spawn(async move {
+        let diagnose = move || diagnose_open_failure(&device, &error);
+        let Ok(Some(diagnostic)) = tokio::task::spawn_blocking(diagnose).await else {
+            return;
+        };
+        let message = diagnostic.message();
+        log::warn!("{}", message);
+        crate::notify::send("Microphone unavailable", &message);
+        let _ = app.emit("mic-diagnostic", &diagnostic);
+    });
+}
+
+/// Compare the sources our streams are attached to with `expected`, the default source if None
+fn check_route(expected: Option<String>) -> Option<MicDiagnostic> {
+    let expected = match expected {
+        Some(expected) => expected,
+        None => pactl(&["get-default-source"])?.trim().to_string(),
+    };
+    let indexes = own_sources(&pactl(&["list", "source-outputs"])?, std::process::id());
+    let sources = parse_sources(&pactl(&["list", "short", "sources"])?);
+    let actual: Vec<String> = indexes.iter().filter_map(|index| sources.get(index).cloned()).collect();
+    wrong_source(expected, &actual)
+}
+
+/// Warn if the audio server records the current recording from another source than `expected`
+///
+/// `expected` is a source name, or None for the default source.
+pub fn spawn_route_check(app: AppHandle, recording: RecordingHandle, expected: Option<String>) {
+    let generation = recording.generation();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(ROUTE_CHECK_DELAY).await;
+        if !recording.is_recording_generation(generation) {
+            return;
+        }
+        let Ok(Some(diagnostic)) = tokio::task::spawn_blocking(move || check_route(expected)).await else {
+            return;
+        };
+        let message = diagnostic.message();
+        log::warn!("{}", message);
+        crate::notify::send("Wrong microphone", &message);
+        let _ = app.emit("mic-diagnostic", &diagnostic);
+    });
+}
+
+/// Send other apps' capture streams to `sender` as they change, until recording `generation` ends
+///
+/// Runs on a thread of its own. Nothing is sent when the streams can't be
+/// listed at all.
+fn follow_capture_streams(
+    recording: RecordingHandle,
+    generation: u64,
+    sender: tokio::sync::mpsc::UnboundedSender<Vec<CaptureStream>>,
+) {
+    #[cfg(feature = "pipewire")]
+    {
+        let (recording, sender) = (recording.clone(), sender.clone());
+        let result = crate::pipewire_backend::watch_capture_streams(
+            move || recording.is_recording_generation(generation),
+            POLL_INTERVAL,
+            move |streams| {
+                let streams = streams.into_iter().map(|(id, app)| CaptureStream { id, app }).collect();
+                let _ = sender.send(streams);
+            },
+        );
+        match result {
+            Ok(()) => return,
+            Err(e) => log::debug!("Not watching PipeWire streams ({}), polling pactl", e),
+        }
+    }
+
+    // Without pactl there is nothing to watch with; dropping the sender says so
+    let Some(initial) = capture_streams() else {
+        return;
+    };
+    if sender.send(initial).is_err() {
+        return;
+    }
+    while recording.is_recording_generation(generation) {
+        std::thread::sleep(POLL_INTERVAL);
+        if let Some(streams) = capture_streams() {
+            if sender.send(streams).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Watch the current recording for other apps taking the microphone
+pub fn spawn_watch(app: AppHandle, recording: RecordingHandle, action: MicConflictAction) {
+    if action == MicConflictAction::Ignore {
+        return;
+    }
+
+    let generation = recording.generation();
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn({
+        let recording = recording.clone();
+        move || follow_capture_streams(recording, generation, sender)
+    });
+
+    tauri::async_runtime::spawn(async move {
+        let Some(initial) = receiver.recv().await else {
+            log::debug!("Capture streams can't be listed, not watching for other apps using the microphone");
+            return;
+        };
+        let known: HashSet<u32> = initial.iter().map(|stream| stream.id).collect();
+        let mut conflicting: Vec<String> = Vec::new();
+
+        while let Some(streams) = receiver.recv().await {
+            let mut apps: Vec<String> = streams
+                .into_iter()
+                .filter(|stream| !known.contains(&stream.id))
+                .map(|stream| stream.app)
+                .collect();
+            apps.sort();
+            apps.dedup();
+            if !recording.is_recording_generation(generation) {
+                break;
+            }
+            if apps == conflicting {
+                continue;
+            }
+
+            if apps.is_empty() {
+                log::info!("Other apps released the microphone");
+                if action == MicConflictAction::Pause {
+                    recording.set_paused(false);
+                }
+                let _ = app.emit("mic-released", ());
+            } else {
+                let names = apps.join(", ");
+                log::info!("{} started using the microphone ({:?})", names, action);
+                let _ = app.emit(
+                    "mic-conflict",
+                    &MicConflict {
+                        apps: apps.clone(),
+                        action,
+                    },
+                );
+                match action {
+                    MicConflictAction::Pause => {
+                        recording.set_paused(true);
+                        crate::notify::send("Dictation paused", &format!("{} is using the microphone", names));
+                    }
+                    MicConflictAction::Stop => {
+                        crate::notify::send("Dictation stopped", &format!("{} is using the microphone", names));
+                        crate::hotkey::stop_recording_if_active(&app);
+                        break;
+                    }
+                    MicConflictAction::Notify | MicConflictAction::Ignore => {
+                        crate::notify::send("Microphone shared", &format!("{} is also using the microphone", names));
+                    }
+                }
+            }
+            conflicting = apps;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = r#"Source Output #41
+	Driver: PipeWire
+	Client: 40
+	Source: 55
+	Corked: no
+	Properties:
+		application.name = "WhisperTray"
+		application.process.id = "1000"
+		media.name = "ALSA Capture"
+
+Source Output #42
+	Driver: PipeWire
+	Client: 40
+	Source: 54
+	Corked: no
+	Properties:
+		application.name = "WhisperTray"
+		application.process.id = "1000"
+		media.name = "ALSA Capture"
+
+Source Output #57
+	Driver: PipeWire
+	Client: 52
+	Source: 55
+	Corked: no
+	Properties:
+		application.name = "Zoom"
+		application.process.id = "2200"
+		application.process.binary = "zoom"
+		media.name = "capture"
+
+Source Output #58
+	Driver: PipeWire
+	Corked: yes
+	Properties:
+		application.name = "Firefox"
+		application.process.id = "3100"
+
+Source Output #60
+	Driver: PipeWire
+	Corked: no
+	Properties:
+		application.name = "PulseAudio Volume Control"
+		media.name = "Peak detect"
+
+Source Output #61
+	Driver: PipeWire
+	Corked: no
+	Properties:
+		application.process.binary = "arecord"
+		application.process.id = "4000"
+"#;
+
+    #[test]
+    fn test_parse_source_outputs() {
+        let streams = parse_source_outputs(OUTPUT, 1000);
+        assert_eq!(
+            streams,
+            vec![
+                CaptureStream {
+                    id: 57,
+                    app: "Zoom".to_string()
+                },
+                CaptureStream {
+                    id: 61,
+                    app: "arecord".to_string()
+                },
+            ]
+        );
+        assert!(parse_source_outputs("", 1000).is_empty());
+    }
+
+    #[test]
+    fn test_route() {
+        assert_eq!(own_sources(OUTPUT, 1000), vec![55, 54]);
+        assert!(own_sources(OUTPUT, 9999).is_empty());
+        assert_eq!(own_streams(OUTPUT, 1000), vec![41, 42]);
+
+        let sources = parse_sources(
+            "54\talsa_output.pci-0000_00_1f.3.analog-stereo.monitor\tPipeWire\ts32le 2ch 48000Hz\tIDLE\n\
+             55\talsa_input.usb-Blue_Yeti-00.analog-stereo\tPipeWire\ts16le 2ch 48000Hz\tRUNNING\n",
+        );
+        assert_eq!(sources[&55], "alsa_input.usb-Blue_Yeti-00.analog-stereo");
+        assert_eq!(sources.len(), 2);
+
+        // Recording both: the monitor stream is ours too, and no reason to warn
+        let yeti = "alsa_input.usb-Blue_Yeti-00.analog-stereo".to_string();
+        let monitor = "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor".to_string();
+        let laptop = "alsa_input.pci-0000_00_1f.3.analog-stereo".to_string();
+        assert_eq!(wrong_source(yeti.clone(), &[yeti.clone(), monitor.clone()]), None);
+        assert_eq!(
+            wrong_source(yeti.clone(), &[monitor, laptop.clone()]),
+            Some(MicDiagnostic::WrongSource {
+                expected: yeti,
+                actual: laptop
+            })
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        assert!(is_capture_pcm(Path::new("/dev/snd/pcmC1D0c")));
+        assert!(!is_capture_pcm(Path::new("/dev/snd/pcmC0D0p")));
+        assert!(diagnose_open_failure("hw:1", "Stream error: invalid sample format").is_none());
+
+        let busy = MicDiagnostic::Busy {
+            device: "default".to_string(),
+            apps: vec!["zoom".to_string()],
+        };
+        assert_eq!(busy.message(), "The microphone is busy: used by zoom");
+    }
+}
@@ -4,6 +4,8 @@
 //! They are stored as JSON files in ~/.config/whispertray/modes/
 
 use crate::error::{AppError, Result};
+use crate::pipeline::PipelineStep;
+use crate::template::{self, ContextSource, PromptContext, TemplateVars};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -15,7 +17,9 @@ pub enum SttProvider {
     WhisperCpp,
     WhisperServer,  // Self-hosted whisper server (Speaches, faster-whisper-server, etc.)
     OpenAI,         // Cloud OpenAI Whisper API
+    Groq,           // Cloud Groq API (OpenAI-compatible, fast whisper-large-v3)
     Deepgram,
+    Wyoming,        // Wyoming protocol server (wyoming-faster-whisper, Home Assistant)
     Custom(String),
 }
 
@@ -41,6 +45,45 @@ impl Default for LlmProvider {
     }
 }
 
+/// An LLM to try when the ones before it are unreachable
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LlmFallback {
+    pub provider: LlmProvider,
+    #[serde(default)]
+    pub model: String,
+}
+
+/// When to insert the transcript as is instead of running the LLM
+///
+/// Short transcripts the STT model is confident about rarely need fixing, so
+/// the paid LLM call is skipped. Providers that report no confidence never
+/// skip.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SkipLlmRule {
+    /// Longest transcript, in words, that may skip the LLM
+    #[serde(default = "default_skip_max_words")]
+    pub max_words: usize,
+    /// Lowest STT confidence (0-1) that may skip the LLM
+    #[serde(default = "default_skip_min_confidence")]
+    pub min_confidence: f32,
+}
+
+fn default_skip_max_words() -> usize {
+    12
+}
+
+fn default_skip_min_confidence() -> f32 {
+    0.9
+}
+
+impl SkipLlmRule {
+    /// Whether a transcript with this confidence can skip the LLM
+    pub fn applies(&self, transcript: &str, confidence: Option<f32>) -> bool {
+        let words = transcript.split_whitespace().count();
+        words > 0 && words <= self.max_words && confidence.is_some_and(|c| c >= self.min_confidence)
+    }
+}
+
 /// Output format options
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[serde(rename_all = "lowercase")]
@@ -50,6 +93,34 @@ pub enum OutputFormat {
     Markdown,
 }
 
+/// How a mode turns the transcript into output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostProcessor {
+    /// The transcript as transcribed
+    None,
+    /// Local rule-based cleanup (see `cleanup`); nothing leaves the machine
+    Rules,
+    /// The mode's prompt, run by its LLM
+    Llm,
+}
+
+/// What a mode's dictations leave in the history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryStorage {
+    /// Transcript, output and recording
+    #[default]
+    Full,
+    /// Text with personal data replaced (see `redact`), no recording
+    Redacted,
+    /// Only the LLM output, such as a summary; the transcript and recording
+    /// are destroyed, and nothing is kept when the LLM gave no output
+    Summary,
+    /// Nothing; the dictation is only pasted
+    Off,
+}
+
 /// A dictation mode configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Mode {
@@ -82,8 +153,13 @@ pub struct Mode {
     #[serde(default)]
     pub llm_model: String,
 
+    /// LLMs tried in order when the primary one is unreachable or times out
+    #[serde(default)]
+    pub llm_fallbacks: Vec<LlmFallback>,
+
     /// Prompt template for LLM processing
-    /// Supports variables: {{transcript}}, {{context}}, {{language}}
+    /// Supports variables: {{transcript}}, {{clipboard}}, {{selection}}, {{date}},
+    /// {{app_name}}, {{language}} (see `template`)
     #[serde(default)]
     pub prompt_template: String,
 
@@ -91,6 +167,26 @@ pub struct Mode {
     #[serde(default)]
     pub output_format: OutputFormat,
 
+    /// JSON Schema the LLM output must match; enables the provider's JSON
+    /// mode and retries responses that fail validation
+    #[serde(default)]
+    pub json_schema: Option<serde_json::Value>,
+
+    /// Longest output in characters; longer output is shortened by the LLM
+    /// or cut at a sentence boundary (see `limit`)
+    #[serde(default)]
+    pub max_output_chars: Option<usize>,
+
+    /// Desktop text captured before recording and offered to the prompt as
+    /// `{{context}}` (`auto` follows the context awareness setting)
+    #[serde(default)]
+    pub context_source: ContextSource,
+
+    /// Other LLMs to run on the same prompt; their outputs are offered with
+    /// the primary's in the multi-take picker
+    #[serde(default)]
+    pub compare_models: Vec<LlmFallback>,
+
     /// Whether this is a built-in mode
     #[serde(default)]
     pub builtin: bool,
@@ -98,6 +194,85 @@ pub struct Mode {
     /// Whether this mode is disabled (hidden from tray menu)
     #[serde(default)]
     pub disabled: bool,
+
+    /// Transcribe and paste long dictation in segments, cut at pauses
+    #[serde(default)]
+    pub auto_segment: bool,
+
+    /// Append dictations to the daily journal instead of pasting them
+    #[serde(default)]
+    pub journal: bool,
+
+    /// Insert short, confidently transcribed dictations without the LLM
+    #[serde(default)]
+    pub skip_llm: Option<SkipLlmRule>,
+
+    /// How the transcript is post-processed; unset follows `ai_processing`
+    #[serde(default)]
+    pub post_processor: Option<PostProcessor>,
+
+    /// Strict verbatim: no LLM, cleanup, replacements, typography or length
+    /// limit, so filler words and self-corrections stay as spoken; audio is
+    /// kept lossless and history items are marked verbatim
+    #[serde(default)]
+    pub verbatim: bool,
+
+    /// What is kept in history; the text is pasted unredacted either way
+    #[serde(default)]
+    pub history_storage: HistoryStorage,
+
+    /// Processing steps run in order after transcription, replacing the
+    /// post-processor when set (see `pipeline`)
+    #[serde(default)]
+    pub steps: Vec<PipelineStep>,
+
+    /// Shell command the final text is piped through (stdin to stdout)
+    /// before pasting; needs `allow_shell_hooks` (see `hooks::filter`)
+    #[serde(default)]
+    pub output_command: Option<String>,
+}
+
+impl Mode {
+    /// Post-processor in effect, falling back to `ai_processing` for modes
+    /// saved before it could be chosen; verbatim modes have none
+    pub fn post_processor(&self) -> PostProcessor {
+        if self.verbatim {
+            return PostProcessor::None;
+        }
+        self.post_processor.unwrap_or(if self.ai_processing {
+            PostProcessor::Llm
+        } else {
+            PostProcessor::None
+        })
+    }
+
+    /// Whether the transcript is sent to the mode's LLM
+    pub fn uses_llm(&self) -> bool {
+        !self.uses_pipeline() && self.post_processor() == PostProcessor::Llm
+    }
+
+    /// Whether the transcript is processed by the mode's steps; verbatim
+    /// modes skip them like any other processing
+    pub fn uses_pipeline(&self) -> bool {
+        !self.verbatim && !self.steps.is_empty()
+    }
+
+    /// LLM providers the mode's processing sends text to
+    pub fn llm_providers(&self) -> Vec<&LlmProvider> {
+        if self.uses_pipeline() {
+            self.steps
+                .iter()
+                .filter_map(|step| match step {
+                    PipelineStep::Llm { provider, .. } => Some(provider),
+                    _ => None,
+                })
+                .collect()
+        } else if self.uses_llm() {
+            vec![&self.llm_provider]
+        } else {
+            Vec::new()
+        }
+    }
 }
 
 fn default_stt_model() -> String {
@@ -115,10 +290,23 @@ impl Default for Mode {
             ai_processing: false,
             llm_provider: LlmProvider::Ollama,
             llm_model: String::new(),
+            llm_fallbacks: Vec::new(),
             prompt_template: String::new(),
             output_format: OutputFormat::Plain,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
             builtin: true,
             disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
         }
     }
 }
@@ -145,10 +333,23 @@ pub fn create_builtin_modes() -> Vec<Mode> {
             ai_processing: false,
             llm_provider: LlmProvider::Ollama,
             llm_model: String::new(),
+            llm_fallbacks: Vec::new(),
             prompt_template: String::new(),
             output_format: OutputFormat::Plain,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
             builtin: true,
             disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
         },
         Mode {
             key: "message".to_string(),
@@ -159,6 +360,7 @@ pub fn create_builtin_modes() -> Vec<Mode> {
             ai_processing: true,
             llm_provider: LlmProvider::Ollama,
             llm_model: "llama3.2".to_string(),
+            llm_fallbacks: Vec::new(),
             prompt_template: r#"You are a helpful assistant that cleans up voice transcriptions into short, casual messages suitable for chat or SMS.
 
 Instructions:
@@ -178,8 +380,60 @@ Transcript to clean up:
 
 Cleaned message:"#.to_string(),
             output_format: OutputFormat::Plain,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
+            builtin: true,
+            disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
+        },
+        Mode {
+            key: "social_post".to_string(),
+            name: "Social Post".to_string(),
+            description: "Short post for social media, kept within 280 characters".to_string(),
+            stt_provider: SttProvider::WhisperCpp,
+            stt_model: "base.en".to_string(),
+            ai_processing: true,
+            llm_provider: LlmProvider::Ollama,
+            llm_model: "llama3.2".to_string(),
+            llm_fallbacks: Vec::new(),
+            prompt_template: r#"You are a helpful assistant that turns voice transcriptions into short social media posts.
+
+Instructions:
+- Fix any transcription errors or unclear words
+- Remove filler words (um, uh, like, you know)
+- Keep the speaker's voice and meaning
+- Stay under 280 characters
+- Do not add hashtags or emoji unless they were dictated
+- Output only the post, no explanation
+
+Transcript:
+{{transcript}}
+
+Post:"#.to_string(),
+            output_format: OutputFormat::Plain,
+            json_schema: None,
+            max_output_chars: Some(280),
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
             builtin: true,
             disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
         },
         Mode {
             key: "email".to_string(),
@@ -190,6 +444,7 @@ Cleaned message:"#.to_string(),
             ai_processing: true,
             llm_provider: LlmProvider::Ollama,
             llm_model: "llama3.2".to_string(),
+            llm_fallbacks: Vec::new(),
             prompt_template: r#"You are a helpful assistant that converts voice transcriptions into professional emails.
 
 Instructions:
@@ -213,8 +468,20 @@ Transcript:
 
 Email:"#.to_string(),
             output_format: OutputFormat::Plain,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
             builtin: true,
             disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
         },
         Mode {
             key: "note".to_string(),
@@ -225,6 +492,7 @@ Email:"#.to_string(),
             ai_processing: true,
             llm_provider: LlmProvider::Ollama,
             llm_model: "llama3.2".to_string(),
+            llm_fallbacks: Vec::new(),
             prompt_template: r#"You are a helpful assistant that converts voice transcriptions into organized notes.
 
 Instructions:
@@ -244,8 +512,20 @@ Transcript:
 
 Notes:"#.to_string(),
             output_format: OutputFormat::Markdown,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
             builtin: true,
             disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
         },
         Mode {
             key: "meeting".to_string(),
@@ -256,6 +536,7 @@ Notes:"#.to_string(),
             ai_processing: true,
             llm_provider: LlmProvider::Ollama,
             llm_model: "llama3.2".to_string(),
+            llm_fallbacks: Vec::new(),
             prompt_template: r#"You are a helpful assistant that creates meeting summaries from transcriptions.
 
 Instructions:
@@ -278,8 +559,20 @@ Transcript:
 
 Meeting Summary:"#.to_string(),
             output_format: OutputFormat::Markdown,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
             builtin: true,
             disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
         },
         Mode {
             key: "super".to_string(),
@@ -290,6 +583,7 @@ Meeting Summary:"#.to_string(),
             ai_processing: true,
             llm_provider: LlmProvider::Ollama,
             llm_model: "llama3.2".to_string(),
+            llm_fallbacks: Vec::new(),
             prompt_template: r#"You are a helpful assistant that intelligently processes voice transcriptions.
 
 Instructions:
@@ -312,8 +606,158 @@ Transcript:
 
 Output:"#.to_string(),
             output_format: OutputFormat::Plain,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
+            builtin: true,
+            disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
+        },
+        Mode {
+            key: "rewrite".to_string(),
+            name: "Rewrite Selection".to_string(),
+            description: "Rewrite the highlighted text following spoken instructions".to_string(),
+            stt_provider: SttProvider::WhisperCpp,
+            stt_model: "base.en".to_string(),
+            ai_processing: true,
+            llm_provider: LlmProvider::Ollama,
+            llm_model: "llama3.2".to_string(),
+            llm_fallbacks: Vec::new(),
+            prompt_template: r#"You are a helpful editing assistant. Rewrite the selected text following the spoken instructions.
+
+Instructions:
+- Apply only the changes that were asked for
+- Keep the original language and formatting unless told otherwise
+- If no text was selected, write what the instructions ask for
+- Output only the rewritten text, no explanation
+
+{{#if selection}}
+Selected text:
+{{selection}}
+{{/if}}
+
+Spoken instructions:
+{{transcript}}
+
+Rewritten text:"#.to_string(),
+            output_format: OutputFormat::Plain,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Selection,
+            compare_models: Vec::new(),
+            builtin: true,
+            disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
+        },
+        Mode {
+            key: "journal".to_string(),
+            name: "Journal".to_string(),
+            description: "Append dictations to today's journal, summarized at the end of the day".to_string(),
+            stt_provider: SttProvider::WhisperCpp,
+            stt_model: "base.en".to_string(),
+            ai_processing: false,
+            llm_provider: LlmProvider::Ollama,
+            llm_model: "llama3.2".to_string(),
+            llm_fallbacks: Vec::new(),
+            prompt_template: String::new(),
+            output_format: OutputFormat::Markdown,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
+            builtin: true,
+            disabled: false,
+            auto_segment: false,
+            journal: true,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
+        },
+        Mode {
+            key: "verbatim".to_string(),
+            name: "Strict Verbatim".to_string(),
+            description: "Word-for-word record for medical and legal dictation, with fillers and corrections kept"
+                .to_string(),
+            stt_provider: SttProvider::WhisperCpp,
+            stt_model: "base.en".to_string(),
+            ai_processing: false,
+            llm_provider: LlmProvider::Ollama,
+            llm_model: String::new(),
+            llm_fallbacks: Vec::new(),
+            prompt_template: String::new(),
+            output_format: OutputFormat::Plain,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
             builtin: true,
             disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: true,
+            history_storage: HistoryStorage::Full,
+            steps: Vec::new(),
+            output_command: None,
+        },
+        Mode {
+            key: "vent".to_string(),
+            name: "Vent".to_string(),
+            description: "Talk freely; only a short summary and action items are kept, the recording and transcript are destroyed"
+                .to_string(),
+            stt_provider: SttProvider::WhisperCpp,
+            stt_model: "base.en".to_string(),
+            ai_processing: true,
+            llm_provider: LlmProvider::Ollama,
+            llm_model: "llama3.2".to_string(),
+            llm_fallbacks: Vec::new(),
+            prompt_template: r#"You summarize spoken thoughts that will not be kept.
+
+Instructions:
+- Write a short summary (at most 3 sentences) of what the speaker is dealing with
+- Then list any action items the speaker mentioned or implied, one per line
+- Do not quote the speaker or repeat names, numbers or other personal details
+- Output ONLY the summary and action items
+
+Transcript:
+{{transcript}}
+
+Summary:"#
+                .to_string(),
+            output_format: OutputFormat::Markdown,
+            json_schema: None,
+            max_output_chars: None,
+            context_source: ContextSource::Auto,
+            compare_models: Vec::new(),
+            builtin: true,
+            disabled: false,
+            auto_segment: false,
+            journal: false,
+            skip_llm: None,
+            post_processor: None,
+            verbatim: false,
+            history_storage: HistoryStorage::Summary,
+            steps: Vec::new(),
+            output_command: None,
         },
     ]
 }
@@ -366,9 +810,14 @@ pub async fn load_mode_from_file(path: &PathBuf) -> Result<Mode> {
 }
 
 /// Save a mode to a JSON file
+///
+/// The file is written next to its destination and renamed over it, so a
+/// crash never leaves a half-written mode behind.
 pub async fn save_mode_to_file(mode: &Mode, path: &PathBuf) -> Result<()> {
     let content = serde_json::to_string_pretty(mode)?;
-    tokio::fs::write(path, content).await?;
+    let temp = path.with_extension("json.tmp");
+    tokio::fs::write(&temp, content).await?;
+    tokio::fs::rename(&temp, path).await?;
     Ok(())
 }
 
@@ -392,28 +841,178 @@ pub async fn delete_mode(key: &str) -> Result<()> {
     Ok(())
 }
 
-/// Render a prompt template with the given variables
-pub fn render_prompt(template: &str, transcript: &str, context: Option<&str>, language: &str) -> String {
-    let mut result = template.to_string();
+/// Longest mode key
+const MAX_KEY_LEN: usize = 64;
 
-    // Replace variables
-    result = result.replace("{{transcript}}", transcript);
-    result = result.replace("{{language}}", language);
+/// Model sizes whisper.cpp models are named after (`base.en`, `large-v3-q5_0`)
+const WHISPER_CPP_SIZES: &[&str] = &["tiny", "base", "small", "medium", "large"];
 
-    // Handle conditional context block
-    if let Some(ctx) = context {
-        result = result.replace("{{#if context}}", "");
-        result = result.replace("{{/if}}", "");
-        result = result.replace("{{context}}", ctx);
-    } else {
-        // Remove the entire context block if no context
-        let re = regex::Regex::new(r"\{\{#if context\}\}[\s\S]*?\{\{/if\}\}").ok();
-        if let Some(regex) = re {
-            result = regex.replace_all(&result, "").to_string();
+/// A problem with a mode that keeps it from being saved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModeIssue {
+    /// Mode field at fault, e.g. `llm_model` or `llm_fallbacks[1]`
+    pub field: String,
+    pub message: String,
+}
+
+impl ModeIssue {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
         }
     }
+}
+
+/// Whether a key can name a mode file: lowercase letters, digits, `_` and `-`
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= MAX_KEY_LEN
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
 
-    result.trim().to_string()
+fn is_whisper_cpp_model(model: &str) -> bool {
+    WHISPER_CPP_SIZES.iter().any(|size| model.starts_with(size))
+}
+
+/// Problems with an LLM provider and model pair
+fn check_llm(field: &str, provider: &LlmProvider, model: &str, issues: &mut Vec<ModeIssue>) {
+    let model = model.trim();
+    if model.is_empty() {
+        issues.push(ModeIssue::new(field, "No LLM model set"));
+        return;
+    }
+    let claude = model.starts_with("claude");
+    match provider {
+        LlmProvider::Anthropic if !claude => issues.push(ModeIssue::new(
+            field,
+            format!("{} is not an Anthropic model", model),
+        )),
+        LlmProvider::OpenAI if claude => issues.push(ModeIssue::new(
+            field,
+            format!("{} is an Anthropic model, not an OpenAI one", model),
+        )),
+        _ => {}
+    }
+}
+
+/// Everything keeping a mode from being saved; empty when it is valid
+pub fn validate(mode: &Mode) -> Vec<ModeIssue> {
+    let mut issues = Vec::new();
+
+    if !is_valid_key(&mode.key) {
+        issues.push(ModeIssue::new(
+            "key",
+            format!(
+                "Use 1 to {} lowercase letters, digits, _ or -",
+                MAX_KEY_LEN
+            ),
+        ));
+    }
+    if mode.name.trim().is_empty() {
+        issues.push(ModeIssue::new("name", "No name set"));
+    }
+
+    let stt_model = mode.stt_model.trim();
+    match &mode.stt_provider {
+        SttProvider::WhisperCpp if !is_whisper_cpp_model(stt_model) => issues.push(ModeIssue::new(
+            "stt_model",
+            format!(
+                "{:?} is not a whisper.cpp model (tiny, base, small, medium or large)",
+                stt_model
+            ),
+        )),
+        SttProvider::OpenAI | SttProvider::Groq | SttProvider::Deepgram
+            if is_whisper_cpp_model(stt_model) =>
+        {
+            issues.push(ModeIssue::new(
+                "stt_model",
+                format!(
+                    "{} is a local whisper.cpp model, not one of {:?}",
+                    stt_model, mode.stt_provider
+                ),
+            ))
+        }
+        _ => {}
+    }
+
+    if mode.uses_llm() {
+        check_llm("llm_model", &mode.llm_provider, &mode.llm_model, &mut issues);
+        if mode.prompt_template.trim().is_empty() {
+            issues.push(ModeIssue::new("prompt_template", "AI processing needs a prompt"));
+        }
+        for (i, fallback) in mode.llm_fallbacks.iter().enumerate() {
+            check_llm(&format!("llm_fallbacks[{}]", i), &fallback.provider, &fallback.model, &mut issues);
+        }
+        for (i, other) in mode.compare_models.iter().enumerate() {
+            check_llm(&format!("compare_models[{}]", i), &other.provider, &other.model, &mut issues);
+        }
+    }
+
+    for (i, step) in mode.steps.iter().enumerate() {
+        let field = format!("steps[{}]", i);
+        match step {
+            PipelineStep::Regex { pattern, .. } => {
+                if let Err(e) = regex::Regex::new(pattern) {
+                    issues.push(ModeIssue::new(field, format!("Invalid regex: {}", e)));
+                }
+            }
+            PipelineStep::Cleanup => {}
+            PipelineStep::Llm {
+                provider,
+                model,
+                prompt,
+            } => {
+                check_llm(&field, provider, model, &mut issues);
+                if prompt.trim().is_empty() {
+                    issues.push(ModeIssue::new(field, "LLM steps need a prompt"));
+                }
+            }
+        }
+    }
+
+    // Segments are pasted as they are transcribed, leaving nothing to process;
+    // journaling modes do not segment
+    if mode.auto_segment && !mode.journal && (mode.uses_llm() || mode.uses_pipeline()) {
+        issues.push(ModeIssue::new(
+            "auto_segment",
+            "Segments are pasted unprocessed; turn off AI processing and pipeline steps to segment",
+        ));
+    }
+
+    if mode.json_schema.as_ref().is_some_and(|schema| !schema.is_object()) {
+        issues.push(ModeIssue::new("json_schema", "The schema must be a JSON object"));
+    }
+    if mode.max_output_chars == Some(0) {
+        issues.push(ModeIssue::new("max_output_chars", "The limit must be above 0"));
+    }
+
+    issues
+}
+
+/// First of `base`, `base_2`, `base_3`... that `taken` does not hold
+pub fn unique_key(base: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}_{}", base, n))
+        .find(|key| !taken(key))
+        .unwrap_or_default()
+}
+
+/// Render a prompt template with a transcript and optional clipboard context
+///
+/// See `template::render` for the full set of variables.
+pub fn render_prompt(template: &str, transcript: &str, context: Option<&str>, language: &str) -> String {
+    let context = PromptContext {
+        clipboard: context.map(str::to_string),
+        source: ContextSource::Clipboard,
+        ..Default::default()
+    };
+    template::render(template, &TemplateVars::new(transcript, &context, language))
 }
 
 #[cfg(test)]
@@ -437,6 +1036,118 @@ mod tests {
         assert_eq!(mode.key, deserialized.key);
     }
 
+    #[test]
+    fn test_post_processor_follows_ai_processing() {
+        let mut mode = Mode {
+            ai_processing: true,
+            ..Default::default()
+        };
+        assert!(mode.uses_llm());
+        mode.post_processor = Some(PostProcessor::Rules);
+        assert!(!mode.uses_llm());
+        mode.ai_processing = false;
+        mode.post_processor = None;
+        assert_eq!(mode.post_processor(), PostProcessor::None);
+    }
+
+    #[test]
+    fn test_verbatim_disables_post_processing() {
+        let mode = create_builtin_modes().into_iter().find(|m| m.key == "verbatim").unwrap();
+        assert!(mode.verbatim);
+        assert_eq!(mode.post_processor(), PostProcessor::None);
+
+        // A verbatim mode ignores a configured LLM
+        let mode = Mode {
+            ai_processing: true,
+            post_processor: Some(PostProcessor::Llm),
+            prompt_template: "Rewrite: {{transcript}}".to_string(),
+            verbatim: true,
+            ..Default::default()
+        };
+        assert!(!mode.uses_llm());
+    }
+
+    #[test]
+    fn test_skip_llm_rule() {
+        let rule: SkipLlmRule = serde_json::from_str("{}").unwrap();
+        assert_eq!((rule.max_words, rule.min_confidence), (12, 0.9));
+
+        assert!(rule.applies("Sounds good, see you then.", Some(0.95)));
+        assert!(!rule.applies("Sounds good, see you then.", Some(0.7)));
+        assert!(!rule.applies("Sounds good, see you then.", None));
+        assert!(!rule.applies(&"word ".repeat(13), Some(0.99)));
+        assert!(!rule.applies("", Some(0.99)));
+    }
+
+    #[test]
+    fn test_validate_mode() {
+        for mode in create_builtin_modes() {
+            assert_eq!(validate(&mode), Vec::new(), "{}", mode.key);
+        }
+
+        let mode = Mode {
+            key: "My Mode".to_string(),
+            stt_provider: SttProvider::Groq,
+            stt_model: "large-v3".to_string(),
+            ai_processing: true,
+            llm_provider: LlmProvider::Anthropic,
+            llm_model: "gpt-4o".to_string(),
+            llm_fallbacks: vec![LlmFallback {
+                provider: LlmProvider::Ollama,
+                model: String::new(),
+            }],
+            ..Default::default()
+        };
+        let fields: Vec<_> = validate(&mode).into_iter().map(|issue| issue.field).collect();
+        assert_eq!(
+            fields,
+            ["key", "stt_model", "llm_model", "prompt_template", "llm_fallbacks[0]"]
+        );
+
+        // Without AI processing the LLM fields do not matter
+        let mode = Mode {
+            key: "notes-2".to_string(),
+            llm_provider: LlmProvider::OpenAI,
+            llm_model: "claude-3-5-haiku-latest".to_string(),
+            ..Default::default()
+        };
+        assert!(validate(&mode).is_empty());
+
+        let mode = Mode {
+            steps: vec![
+                PipelineStep::Regex {
+                    pattern: "[".to_string(),
+                    replacement: String::new(),
+                },
+                PipelineStep::Llm {
+                    provider: LlmProvider::Ollama,
+                    model: "llama3.2".to_string(),
+                    prompt: " ".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+        let fields: Vec<_> = validate(&mode).into_iter().map(|issue| issue.field).collect();
+        assert_eq!(fields, vec!["steps[0]", "steps[1]"]);
+
+        // Segmented dictation skips processing
+        let mode = Mode {
+            auto_segment: true,
+            steps: vec![PipelineStep::Cleanup],
+            ..Default::default()
+        };
+        let fields: Vec<_> = validate(&mode).into_iter().map(|issue| issue.field).collect();
+        assert_eq!(fields, vec!["auto_segment"]);
+        assert!(validate(&Mode { journal: true, ..mode }).is_empty());
+    }
+
+    #[test]
+    fn test_unique_key() {
+        let taken = ["email", "email_copy", "email_copy_2"];
+        assert_eq!(unique_key("notes", |key| taken.contains(&key)), "notes");
+        assert_eq!(unique_key("email_copy", |key| taken.contains(&key)), "email_copy_3");
+    }
+
     #[test]
     fn test_render_prompt_basic() {
         let template = "Transcript: {{transcript}}\nLanguage: {{language}}";
@@ -0,0 +1,16 @@
+//! Desktop notifications
+//!
+//! For things the user should hear about while the window is closed, such
+//! as a recording about to stop or a microphone taken by another app. Sent
+//! through `notify-send` when it is installed, and skipped otherwise.
+
+use std::process::Command;
+
+/// Show a desktop notification, if `notify-send` is installed
+pub fn send(summary: &str, body: &str) {
+    if crate::paste::is_command_available("notify-send") {
+        let _ = Command::new("notify-send")
+            .args(["--app-name=WhisperTray", summary, body])
+            .spawn();
+    }
+}
@@ -0,0 +1,142 @@
+//! Output routing for dictated text
+//!
+//! When several dictations are appended to the same target (e.g. the
+//! segments of one long recording), the router decides what goes between
+//! them and keeps the accumulated output.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// What is inserted between successive dictations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputDelimiter {
+    #[default]
+    Space,
+    Newline,
+    /// A "---" line between dictations
+    Separator,
+    /// A time header before every dictation
+    Timestamp,
+}
+
+impl OutputDelimiter {
+    /// Text to insert before a dictation; `first` is true for the first one
+    pub fn prefix(&self, first: bool, at: DateTime<Local>) -> String {
+        match self {
+            OutputDelimiter::Space if first => String::new(),
+            OutputDelimiter::Space => " ".to_string(),
+            OutputDelimiter::Newline if first => String::new(),
+            OutputDelimiter::Newline => "\n".to_string(),
+            OutputDelimiter::Separator if first => String::new(),
+            OutputDelimiter::Separator => "\n\n---\n\n".to_string(),
+            OutputDelimiter::Timestamp => {
+                let header = format!("[{}]\n", at.format("%H:%M:%S"));
+                if first {
+                    header
+                } else {
+                    format!("\n\n{}", header)
+                }
+            }
+        }
+    }
+}
+
+/// Accumulates dictations appended to one target
+#[derive(Debug, Clone, Default)]
+pub struct OutputRouter {
+    delimiter: OutputDelimiter,
+    output: String,
+    count: usize,
+}
+
+impl OutputRouter {
+    pub fn new(delimiter: OutputDelimiter) -> Self {
+        Self {
+            delimiter,
+            output: String::new(),
+            count: 0,
+        }
+    }
+
+    /// Append a dictation, returning the text to insert at the target
+    ///
+    /// Empty dictations are skipped and return an empty string.
+    pub fn push(&mut self, text: &str) -> String {
+        self.push_at(text, Local::now())
+    }
+
+    /// Append a dictation made at a specific time
+    pub fn push_at(&mut self, text: &str, at: DateTime<Local>) -> String {
+        let text = text.trim();
+        if text.is_empty() {
+            return String::new();
+        }
+
+        let insert = format!("{}{}", self.delimiter.prefix(self.count == 0, at), text);
+        self.output.push_str(&insert);
+        self.count += 1;
+        insert
+    }
+
+    /// Everything appended so far
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Number of dictations appended
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, 1, h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_space_joins_inline() {
+        let mut router = OutputRouter::new(OutputDelimiter::Space);
+        assert_eq!(router.push("Hello there."), "Hello there.");
+        assert_eq!(router.push("How are you?"), " How are you?");
+        assert_eq!(router.output(), "Hello there. How are you?");
+    }
+
+    #[test]
+    fn test_separator_and_newline() {
+        let mut router = OutputRouter::new(OutputDelimiter::Separator);
+        router.push("One");
+        router.push("Two");
+        assert_eq!(router.output(), "One\n\n---\n\nTwo");
+
+        let mut router = OutputRouter::new(OutputDelimiter::Newline);
+        router.push("One");
+        router.push("Two");
+        assert_eq!(router.output(), "One\nTwo");
+    }
+
+    #[test]
+    fn test_timestamp_headers() {
+        let mut router = OutputRouter::new(OutputDelimiter::Timestamp);
+        assert_eq!(router.push_at("First", at(9, 5)), "[09:05:00]\nFirst");
+        router.push_at("Second", at(9, 6));
+        assert_eq!(router.output(), "[09:05:00]\nFirst\n\n[09:06:00]\nSecond");
+    }
+
+    #[test]
+    fn test_empty_dictation_skipped() {
+        let mut router = OutputRouter::new(OutputDelimiter::Space);
+        assert_eq!(router.push("  "), "");
+        assert_eq!(router.push("Text"), "Text");
+        assert_eq!(router.len(), 1);
+    }
+}
@@ -0,0 +1,306 @@
+//! Speech pacing options
+//!
+//! Groups the timing knobs of the recording pipeline (pause detection,
+//! auto-stop, length limits) with filler-word handling, so users with speech
+//! differences can switch everything to patient values with one preset.
+
+use crate::audio::RecordingHandle;
+use crate::vad::VadConfig;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// Auto-stop never fires sooner than this under the accessible preset
+const ACCESSIBLE_MIN_AUTO_STOP_MS: u32 = 15_000;
+
+/// Input level (as reported by `RecordingHandle::get_level`) counted as speech
+const SPEECH_LEVEL: f32 = 0.03;
+
+/// Maximum recording length under the standard preset
+const STANDARD_MAX_RECORDING_SECS: u32 = 300;
+
+/// Warning given this long before a recording reaches its maximum length
+const MAX_LENGTH_WARNING_MS: u64 = 15_000;
+
+/// How often the auto-stop watcher samples the input level
+const AUTO_STOP_POLL: Duration = Duration::from_millis(250);
+
+/// Appended to LLM prompts when filler words should be kept
+const KEEP_FILLERS_INSTRUCTION: &str = "Important: keep every word as spoken, including filler \
+words, repetitions and restarts. Do not remove or smooth them out, even if told to above.";
+
+/// Named pacing presets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PacingPreset {
+    #[default]
+    Standard,
+    /// Long pauses, kept fillers, patient auto-stop and no length limits
+    Accessible,
+}
+
+/// Timing and filler-word options for recording and processing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pacing {
+    /// Preset these values started from
+    pub preset: PacingPreset,
+    /// Pauses shorter than this are treated as part of speech
+    pub vad_min_silence_ms: u32,
+    /// Keep filler words instead of letting the LLM remove them
+    pub keep_filler_words: bool,
+    /// Stop recording after this much silence following speech (off when None)
+    pub auto_stop_silence_ms: Option<u32>,
+    /// Stop recording once it is this long (unlimited when None)
+    pub max_recording_secs: Option<u32>,
+    /// Never stop or cut a recording because of its length
+    pub no_time_limits: bool,
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Self::for_preset(PacingPreset::Standard)
+    }
+}
+
+impl Pacing {
+    /// Values for a preset
+    pub fn for_preset(preset: PacingPreset) -> Self {
+        match preset {
+            PacingPreset::Standard => Self {
+                preset,
+                vad_min_silence_ms: VadConfig::default().min_silence_ms,
+                keep_filler_words: false,
+                auto_stop_silence_ms: None,
+                // Ends a recording started by a hotkey pressed by accident
+                max_recording_secs: Some(STANDARD_MAX_RECORDING_SECS),
+                no_time_limits: false,
+            },
+            PacingPreset::Accessible => Self {
+                preset,
+                vad_min_silence_ms: 2_000,
+                keep_filler_words: true,
+                auto_stop_silence_ms: None,
+                max_recording_secs: None,
+                no_time_limits: true,
+            },
+        }
+    }
+
+    /// VAD parameters with this pacing's pause length
+    pub fn vad_config(&self) -> VadConfig {
+        VadConfig {
+            min_silence_ms: self.vad_min_silence_ms,
+            ..VadConfig::default()
+        }
+    }
+
+    /// Longest segment before a forced cut, given the normal maximum
+    pub fn segment_max_ms(&self, default_ms: u32) -> u32 {
+        if self.no_time_limits {
+            u32::MAX
+        } else {
+            default_ms
+        }
+    }
+
+    /// Silence after which recording stops, if auto-stop is enabled
+    pub fn auto_stop_ms(&self) -> Option<u64> {
+        self.auto_stop_silence_ms.map(|ms| {
+            let ms = match self.preset {
+                PacingPreset::Accessible => ms.max(ACCESSIBLE_MIN_AUTO_STOP_MS),
+                PacingPreset::Standard => ms,
+            };
+            ms as u64
+        })
+    }
+
+    /// Maximum recording length, unless time limits are off
+    pub fn max_recording_ms(&self) -> Option<u64> {
+        if self.no_time_limits {
+            None
+        } else {
+            self.max_recording_secs.map(|secs| secs as u64 * 1000)
+        }
+    }
+
+    /// Adjust an LLM prompt for the filler-word preference
+    pub fn apply_to_prompt(&self, prompt: String) -> String {
+        if self.keep_filler_words {
+            format!("{}\n\n{}", prompt, KEEP_FILLERS_INSTRUCTION)
+        } else {
+            prompt
+        }
+    }
+}
+
+/// Why a recording was stopped automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoStopReason {
+    Silence,
+    MaxLength,
+}
+
+/// Decides when to stop a recording from its length and input level
+#[derive(Debug, Clone)]
+pub struct AutoStop {
+    silence_ms: Option<u64>,
+    max_ms: Option<u64>,
+    /// Position of the last speech heard, None until the user starts talking
+    last_speech_ms: Option<u64>,
+    /// Whether the maximum length warning was given
+    warned: bool,
+}
+
+impl AutoStop {
+    pub fn new(pacing: &Pacing) -> Self {
+        Self {
+            silence_ms: pacing.auto_stop_ms(),
+            max_ms: pacing.max_recording_ms(),
+            last_speech_ms: None,
+            warned: false,
+        }
+    }
+
+    /// Whether any automatic stop is configured
+    pub fn is_active(&self) -> bool {
+        self.silence_ms.is_some() || self.max_ms.is_some()
+    }
+
+    /// Time left (ms) the first time `elapsed_ms` comes within
+    /// `MAX_LENGTH_WARNING_MS` of the maximum length
+    pub fn max_length_warning(&mut self, elapsed_ms: u64) -> Option<u64> {
+        let remaining = self.max_ms?.saturating_sub(elapsed_ms);
+        if self.warned || remaining > MAX_LENGTH_WARNING_MS || remaining == 0 {
+            return None;
+        }
+        self.warned = true;
+        Some(remaining)
+    }
+
+    /// Record the level at `elapsed_ms`, returning a reason once recording should stop
+    pub fn update(&mut self, elapsed_ms: u64, level: f32) -> Option<AutoStopReason> {
+        if self.max_ms.is_some_and(|max| elapsed_ms >= max) {
+            return Some(AutoStopReason::MaxLength);
+        }
+
+        if level >= SPEECH_LEVEL {
+            self.last_speech_ms = Some(elapsed_ms);
+            return None;
+        }
+
+        // Silence before the first word never stops the recording
+        match (self.silence_ms, self.last_speech_ms) {
+            (Some(limit), Some(last)) if elapsed_ms.saturating_sub(last) >= limit => {
+                Some(AutoStopReason::Silence)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Watch the current recording and stop it when pacing says so
+pub fn spawn_auto_stop(app: AppHandle, recording: RecordingHandle, pacing: &Pacing) {
+    let mut auto_stop = AutoStop::new(pacing);
+    if !auto_stop.is_active() {
+        return;
+    }
+
+    let generation = recording.generation();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(AUTO_STOP_POLL).await;
+            // Never stop a recording started after this one
+            if !recording.is_recording_generation(generation) {
+                break;
+            }
+
+            let elapsed_ms = recording.elapsed_ms();
+            if let Some(remaining_ms) = auto_stop.max_length_warning(elapsed_ms) {
+                let secs = remaining_ms.div_ceil(1000);
+                crate::notify::send(
+                    "Recording stops soon",
+                    &format!(
+                        "The maximum length is reached in {} seconds; it is then transcribed",
+                        secs
+                    ),
+                );
+                let _ = app.emit("recording-time-warning", remaining_ms);
+            }
+            let (level, _) = recording.get_level();
+            if let Some(reason) = auto_stop.update(elapsed_ms, level) {
+                log::info!("Stopping recording automatically ({:?})", reason);
+                crate::hotkey::stop_recording_if_active(&app);
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accessible_preset_lifts_limits() {
+        let pacing = Pacing {
+            auto_stop_silence_ms: Some(3_000),
+            max_recording_secs: Some(60),
+            ..Pacing::for_preset(PacingPreset::Accessible)
+        };
+        assert_eq!(pacing.auto_stop_ms(), Some(ACCESSIBLE_MIN_AUTO_STOP_MS as u64));
+        assert_eq!(pacing.max_recording_ms(), None);
+        assert_eq!(pacing.segment_max_ms(30_000), u32::MAX);
+        assert!(pacing.apply_to_prompt("Clean up".to_string()).contains("filler"));
+    }
+
+    #[test]
+    fn test_auto_stop_waits_for_speech() {
+        let pacing = Pacing {
+            auto_stop_silence_ms: Some(2_000),
+            ..Pacing::default()
+        };
+        let mut auto_stop = AutoStop::new(&pacing);
+
+        assert_eq!(auto_stop.update(5_000, 0.0), None);
+        assert_eq!(auto_stop.update(5_250, 0.2), None);
+        assert_eq!(auto_stop.update(7_000, 0.0), None);
+        assert_eq!(auto_stop.update(7_250, 0.0), Some(AutoStopReason::Silence));
+    }
+
+    #[test]
+    fn test_auto_stop_max_length() {
+        let pacing = Pacing {
+            max_recording_secs: Some(10),
+            ..Pacing::default()
+        };
+        let mut auto_stop = AutoStop::new(&pacing);
+        assert!(auto_stop.is_active());
+        assert_eq!(auto_stop.update(9_999, 0.5), None);
+        assert_eq!(auto_stop.update(10_000, 0.5), Some(AutoStopReason::MaxLength));
+
+        let unlimited = Pacing {
+            max_recording_secs: None,
+            ..Pacing::default()
+        };
+        assert!(!AutoStop::new(&unlimited).is_active());
+        assert!(AutoStop::new(&Pacing::default()).is_active());
+    }
+
+    #[test]
+    fn test_max_length_warning_once() {
+        let pacing = Pacing {
+            max_recording_secs: Some(300),
+            ..Pacing::default()
+        };
+        let mut auto_stop = AutoStop::new(&pacing);
+        assert_eq!(auto_stop.max_length_warning(284_000), None);
+        assert_eq!(auto_stop.max_length_warning(285_250), Some(14_750));
+        assert_eq!(auto_stop.max_length_warning(286_000), None);
+        let unlimited = Pacing {
+            max_recording_secs: None,
+            ..Pacing::default()
+        };
+        assert_eq!(AutoStop::new(&unlimited).max_length_warning(285_250), None);
+    }
+}
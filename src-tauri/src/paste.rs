@@ -4,13 +4,218 @@
 //! - X11: enigo (libxdo)
 //! - Wayland: wtype or ydotool
 //! - Fallback: clipboard only
+//!
+//! The clipboard itself is set through arboard, or through wl-copy/wl-paste
+//! on Wayland compositors where arboard fails while our window is unfocused.
 
 use crate::error::{AppError, Result};
 use arboard::Clipboard;
-use std::process::Command;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::thread;
 use std::time::Duration;
 
+/// Whether input may be injected through /dev/uinput (ydotool), which
+/// requires the user to opt in
+static UINPUT_ALLOWED: AtomicBool = AtomicBool::new(false);
+
+/// Allow or forbid the ydotool (/dev/uinput) backend
+pub fn set_uinput_allowed(allowed: bool) {
+    UINPUT_ALLOWED.store(allowed, Ordering::SeqCst);
+}
+
+/// Whether the user opted in to /dev/uinput input injection
+pub fn is_uinput_allowed() -> bool {
+    UINPUT_ALLOWED.load(Ordering::SeqCst)
+}
+
+/// Whether we may start ydotoold ourselves when it is not running
+static MANAGE_YDOTOOLD: AtomicBool = AtomicBool::new(false);
+
+/// Allow or forbid starting a user-scoped ydotoold
+pub fn set_ydotoold_managed(managed: bool) {
+    MANAGE_YDOTOOLD.store(managed, Ordering::SeqCst);
+}
+
+/// How long to wait for a ydotoold we started to create its socket
+const YDOTOOLD_STARTUP: Duration = Duration::from_millis(1500);
+
+/// Timing of simulated input, for apps that drop fast keystrokes (e.g. Electron)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasteSettings {
+    /// Wait before pasting or typing, so the hotkey is released and focus settles
+    pub pre_delay_ms: u64,
+    /// Delay between typed keys (0 types as fast as the backend can)
+    pub per_key_delay_ms: u64,
+    /// Characters typed per backend call, with a short pause between calls
+    /// (0 types everything at once)
+    pub chunk_size: usize,
+}
+
+impl Default for PasteSettings {
+    fn default() -> Self {
+        Self {
+            pre_delay_ms: 200,
+            per_key_delay_ms: 0,
+            chunk_size: 0,
+        }
+    }
+}
+
+/// How text is inserted into apps matching a paste rule
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteStrategy {
+    /// Type on Wayland, paste on X11
+    #[default]
+    Auto,
+    Paste,
+    Type,
+    /// Set the primary selection and middle-click at the pointer, for apps
+    /// that intercept Ctrl+V (some games, VMs, remote desktops)
+    MiddleClick,
+    /// Only copy to the clipboard (e.g. VMs and remote desktops)
+    ClipboardOnly,
+}
+
+/// Key combination that pastes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteShortcut {
+    #[default]
+    CtrlV,
+    /// Terminals
+    CtrlShiftV,
+    ShiftInsert,
+}
+
+impl PasteShortcut {
+    fn wtype_args(self) -> &'static [&'static str] {
+        match self {
+            PasteShortcut::CtrlV => &["-M", "ctrl", "-k", "v", "-m", "ctrl"],
+            PasteShortcut::CtrlShiftV => &["-M", "ctrl", "-M", "shift", "-k", "v", "-m", "shift", "-m", "ctrl"],
+            PasteShortcut::ShiftInsert => &["-M", "shift", "-k", "Insert", "-m", "shift"],
+        }
+    }
+
+    fn ydotool_keys(self) -> &'static str {
+        match self {
+            PasteShortcut::CtrlV => "ctrl+v",
+            PasteShortcut::CtrlShiftV => "ctrl+shift+v",
+            PasteShortcut::ShiftInsert => "shift+insert",
+        }
+    }
+}
+
+/// Paste behavior for windows whose class or title matches
+///
+/// Patterns are case-insensitive regular expressions; a rule with both
+/// must match both. The first matching rule applies.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasteRule {
+    #[serde(default)]
+    pub class: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub strategy: PasteStrategy,
+    /// Replaces the global pre-paste delay
+    #[serde(default)]
+    pub pre_delay_ms: Option<u64>,
+    #[serde(default)]
+    pub shortcut: PasteShortcut,
+}
+
+impl PasteRule {
+    fn matches(&self, class: Option<&str>, title: Option<&str>) -> bool {
+        let field_matches = |pattern: &Option<String>, value: Option<&str>| match pattern {
+            None => true,
+            Some(pattern) => match regex::RegexBuilder::new(pattern).case_insensitive(true).build() {
+                Ok(re) => value.is_some_and(|v| re.is_match(v)),
+                Err(e) => {
+                    log::warn!("Invalid paste rule pattern {:?}: {}", pattern, e);
+                    false
+                }
+            },
+        };
+        (self.class.is_some() || self.title.is_some())
+            && field_matches(&self.class, class)
+            && field_matches(&self.title, title)
+    }
+}
+
+/// The first rule matching the window
+pub fn find_rule<'a>(rules: &'a [PasteRule], class: Option<&str>, title: Option<&str>) -> Option<&'a PasteRule> {
+    rules.iter().find(|rule| rule.matches(class, title))
+}
+
+/// Pause between typed chunks
+const CHUNK_PAUSE: Duration = Duration::from_millis(30);
+
+/// Split text into chunks of at most `size` characters (one chunk when 0)
+fn chunks(text: &str, size: usize) -> Vec<String> {
+    if size == 0 || text.is_empty() {
+        return vec![text.to_string()];
+    }
+    let chars: Vec<char> = text.chars().collect();
+    chars.chunks(size).map(|chunk| chunk.iter().collect()).collect()
+}
+
+/// How the clipboard is read and written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardBackend {
+    /// wl-clipboard on Wayland when installed, arboard otherwise
+    #[default]
+    Auto,
+    Arboard,
+    /// wl-copy/wl-paste (falls back to arboard when not installed)
+    WlClipboard,
+}
+
+/// Configured clipboard backend, as a `ClipboardBackend` discriminant
+static CLIPBOARD_BACKEND: AtomicU8 = AtomicU8::new(ClipboardBackend::Auto as u8);
+
+/// Choose how the clipboard is accessed
+pub fn set_clipboard_backend(backend: ClipboardBackend) {
+    CLIPBOARD_BACKEND.store(backend as u8, Ordering::SeqCst);
+}
+
+fn clipboard_backend() -> ClipboardBackend {
+    match CLIPBOARD_BACKEND.load(Ordering::SeqCst) {
+        b if b == ClipboardBackend::Arboard as u8 => ClipboardBackend::Arboard,
+        b if b == ClipboardBackend::WlClipboard as u8 => ClipboardBackend::WlClipboard,
+        _ => ClipboardBackend::Auto,
+    }
+}
+
+/// Whether to use wl-clipboard for a configured backend
+fn resolve_wl_clipboard(backend: ClipboardBackend, wayland: bool, installed: bool) -> bool {
+    match backend {
+        ClipboardBackend::Arboard => false,
+        ClipboardBackend::WlClipboard => installed,
+        ClipboardBackend::Auto => wayland && installed,
+    }
+}
+
+/// Whether clipboard access goes through wl-copy/wl-paste
+fn use_wl_clipboard() -> bool {
+    let backend = clipboard_backend();
+    if backend == ClipboardBackend::Arboard {
+        return false;
+    }
+    let installed = is_command_available("wl-copy") && is_command_available("wl-paste");
+    if backend == ClipboardBackend::WlClipboard && !installed {
+        log::warn!("wl-clipboard backend selected but wl-copy/wl-paste not found; using arboard");
+    }
+    resolve_wl_clipboard(backend, is_wayland(), installed)
+}
+
 /// Paste backend detection result
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PasteBackend {
@@ -31,9 +236,12 @@ pub fn detect_backend() -> PasteBackend {
         if is_command_available("wtype") {
             log::info!("Paste backend: wtype (Wayland)");
             PasteBackend::Wtype
-        } else if is_command_available("ydotool") {
+        } else if ydotool_available() {
             log::info!("Paste backend: ydotool (Wayland)");
             PasteBackend::Ydotool
+        } else if is_command_available("ydotool") {
+            log::warn!("ydotool found but /dev/uinput input is not enabled in settings; auto-paste disabled.");
+            PasteBackend::ClipboardOnly
         } else {
             log::warn!("No Wayland paste backend available. Install wtype or ydotool for auto-paste.");
             PasteBackend::ClipboardOnly
@@ -45,8 +253,13 @@ pub fn detect_backend() -> PasteBackend {
     }
 }
 
+/// Whether ydotool is installed and the user allowed /dev/uinput input
+fn ydotool_available() -> bool {
+    is_uinput_allowed() && is_command_available("ydotool")
+}
+
 /// Check if a command is available in PATH
-fn is_command_available(cmd: &str) -> bool {
+pub fn is_command_available(cmd: &str) -> bool {
     Command::new("which")
         .arg(cmd)
         .output()
@@ -55,49 +268,76 @@ fn is_command_available(cmd: &str) -> bool {
 }
 
 /// Copy text to clipboard and optionally paste/type it
-pub fn copy_and_paste(text: &str, should_paste: bool) -> Result<()> {
+///
+/// `rules` are matched against the focused window to pick the strategy.
+pub fn copy_and_paste(text: &str, should_paste: bool, settings: &PasteSettings, rules: &[PasteRule]) -> Result<()> {
     // Copy to clipboard first (always useful as backup)
-    let mut clipboard = Clipboard::new()
-        .map_err(|e| AppError::Clipboard(format!("Failed to access clipboard: {}", e)))?;
-
-    clipboard
-        .set_text(text)
-        .map_err(|e| AppError::Clipboard(format!("Failed to set clipboard text: {}", e)))?;
+    set_clipboard_text(text)?;
 
     log::info!("Text copied to clipboard ({} chars)", text.len());
 
-    if should_paste {
+    if !should_paste {
+        return Ok(());
+    }
+
+    let rule = if rules.is_empty() {
+        None
+    } else {
+        let window = crate::window_info::active_window().unwrap_or_default();
+        find_rule(rules, window.class.as_deref(), window.title.as_deref()).cloned()
+    };
+    let rule = rule.unwrap_or_default();
+    let settings = &PasteSettings {
+        pre_delay_ms: rule.pre_delay_ms.unwrap_or(settings.pre_delay_ms),
+        ..*settings
+    };
+
+    match rule.strategy {
+        PasteStrategy::ClipboardOnly => {
+            log::info!("Paste rule keeps text in the clipboard only");
+        }
+        PasteStrategy::Paste => paste(settings, rule.shortcut)?,
+        PasteStrategy::Type => type_text(text, settings)?,
+        PasteStrategy::MiddleClick => middle_click_paste(text, settings)?,
         // On Wayland, prefer typing directly over Ctrl+V simulation
         // as it's more reliable across different compositors
-        if is_wayland() {
+        PasteStrategy::Auto if is_wayland() => {
             log::info!("Wayland detected, typing text directly");
-            if let Err(e) = type_text(text) {
+            thread::sleep(Duration::from_millis(settings.pre_delay_ms));
+            if let Err((typed, e)) = type_chunks(text, settings) {
                 log::warn!("Direct typing failed ({}), trying paste fallback", e);
-                paste()?;
+                // Paste only what was not typed yet; the clipboard keeps
+                // the rest rather than the whole text
+                if typed > 0 {
+                    set_clipboard_text(&text[typed..])?;
+                }
+                send_paste_shortcut(rule.shortcut)?;
             }
-        } else {
-            paste()?;
         }
+        PasteStrategy::Auto => paste(settings, rule.shortcut)?,
     }
 
     Ok(())
 }
 
-/// Simulate Ctrl+V paste using the best available backend
-pub fn paste() -> Result<()> {
-    let backend = detect_backend();
-
+/// Simulate a paste shortcut using the best available backend
+pub fn paste(settings: &PasteSettings, shortcut: PasteShortcut) -> Result<()> {
     // Delay to ensure clipboard is ready and user has released hotkey
-    thread::sleep(Duration::from_millis(200));
+    thread::sleep(Duration::from_millis(settings.pre_delay_ms));
 
-    match backend {
-        PasteBackend::Enigo => paste_enigo(),
+    send_paste_shortcut(shortcut)
+}
+
+/// Send the paste shortcut right away, without the pre-paste delay
+fn send_paste_shortcut(shortcut: PasteShortcut) -> Result<()> {
+    match detect_backend() {
+        PasteBackend::Enigo => paste_enigo(shortcut),
         PasteBackend::Wtype => {
             // Try wtype, fall back to ydotool if it fails (compositor may not support virtual keyboard)
-            if let Err(e) = paste_wtype() {
+            if let Err(e) = paste_wtype(shortcut) {
                 log::warn!("wtype failed ({}), trying ydotool fallback", e);
-                if is_command_available("ydotool") {
-                    paste_ydotool()
+                if ydotool_available() {
+                    paste_ydotool(shortcut)
                 } else {
                     log::warn!("No fallback available, text is in clipboard");
                     Ok(())
@@ -106,7 +346,7 @@ pub fn paste() -> Result<()> {
                 Ok(())
             }
         }
-        PasteBackend::Ydotool => paste_ydotool(),
+        PasteBackend::Ydotool => paste_ydotool(shortcut),
         PasteBackend::ClipboardOnly => {
             log::info!("No paste backend available, text is in clipboard");
             Ok(())
@@ -114,39 +354,114 @@ pub fn paste() -> Result<()> {
     }
 }
 
+/// Insert text through the primary selection and a middle click
+///
+/// The text lands wherever the pointer is, so the click is only made when
+/// the pointer is verifiably over the focused window. Otherwise the text is
+/// left in the clipboard.
+fn middle_click_paste(text: &str, settings: &PasteSettings) -> Result<()> {
+    set_primary_selection(text)?;
+    thread::sleep(Duration::from_millis(settings.pre_delay_ms));
+
+    match crate::window_info::pointer_in_focused_window() {
+        Some(true) => {}
+        Some(false) => {
+            return Err(AppError::Clipboard(
+                "Pointer is outside the focused window; not middle-clicking".to_string(),
+            ))
+        }
+        None => {
+            return Err(AppError::Clipboard(
+                "Cannot tell where the pointer is on this desktop; not middle-clicking".to_string(),
+            ))
+        }
+    }
+
+    match detect_backend() {
+        PasteBackend::Enigo => middle_click_enigo(),
+        // wtype has no pointer support
+        PasteBackend::Wtype if ydotool_available() => middle_click_ydotool(),
+        PasteBackend::Ydotool => middle_click_ydotool(),
+        PasteBackend::Wtype | PasteBackend::ClipboardOnly => Err(AppError::Clipboard(
+            "Middle-click needs enigo (X11) or ydotool".to_string(),
+        )),
+    }
+}
+
+fn middle_click_enigo() -> Result<()> {
+    use enigo::{Button, Direction, Enigo, Mouse, Settings};
+
+    let mut enigo = Enigo::new(&Settings::default())
+        .map_err(|e| AppError::Clipboard(format!("Failed to create input simulator: {}", e)))?;
+    enigo
+        .button(Button::Middle, Direction::Click)
+        .map_err(|e| AppError::Clipboard(format!("Failed to middle-click: {}", e)))?;
+
+    log::info!("Middle-click paste completed (enigo/X11)");
+    Ok(())
+}
+
+fn middle_click_ydotool() -> Result<()> {
+    let socket = prepare_ydotool()?;
+
+    // 0xC2: middle button down (0x40) and up (0x80)
+    let output = Command::new("ydotool")
+        .args(["click", "0xC2"])
+        .env("YDOTOOL_SOCKET", &socket)
+        .output()
+        .map_err(|e| AppError::Clipboard(format!("Failed to run ydotool: {}", e)))?;
+
+    if output.status.success() {
+        log::info!("Middle-click paste completed (ydotool)");
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(AppError::Clipboard(format!("ydotool failed: {}", stderr.trim())))
+    }
+}
+
 /// Paste using enigo (X11/libxdo)
-fn paste_enigo() -> Result<()> {
-    use enigo::{Enigo, Keyboard, Settings};
+fn paste_enigo(shortcut: PasteShortcut) -> Result<()> {
+    use enigo::{Direction, Enigo, Key, Keyboard, Settings};
 
     let mut enigo = Enigo::new(&Settings::default())
         .map_err(|e| AppError::Clipboard(format!("Failed to create input simulator: {}", e)))?;
 
-    // Simulate Ctrl+V
-    enigo
-        .key(enigo::Key::Control, enigo::Direction::Press)
-        .map_err(|e| AppError::Clipboard(format!("Failed to press Ctrl: {}", e)))?;
+    let (modifiers, key): (&[Key], Key) = match shortcut {
+        PasteShortcut::CtrlV => (&[Key::Control], Key::Unicode('v')),
+        PasteShortcut::CtrlShiftV => (&[Key::Control, Key::Shift], Key::Unicode('v')),
+        PasteShortcut::ShiftInsert => (&[Key::Shift], Key::Insert),
+    };
+
+    for modifier in modifiers {
+        enigo
+            .key(*modifier, Direction::Press)
+            .map_err(|e| AppError::Clipboard(format!("Failed to press {:?}: {}", modifier, e)))?;
+    }
 
     thread::sleep(Duration::from_millis(20));
 
     enigo
-        .key(enigo::Key::Unicode('v'), enigo::Direction::Click)
-        .map_err(|e| AppError::Clipboard(format!("Failed to press V: {}", e)))?;
+        .key(key, Direction::Click)
+        .map_err(|e| AppError::Clipboard(format!("Failed to press {:?}: {}", key, e)))?;
 
     thread::sleep(Duration::from_millis(20));
 
-    enigo
-        .key(enigo::Key::Control, enigo::Direction::Release)
-        .map_err(|e| AppError::Clipboard(format!("Failed to release Ctrl: {}", e)))?;
+    for modifier in modifiers.iter().rev() {
+        enigo
+            .key(*modifier, Direction::Release)
+            .map_err(|e| AppError::Clipboard(format!("Failed to release {:?}: {}", modifier, e)))?;
+    }
 
     log::info!("Paste completed (enigo/X11)");
     Ok(())
 }
 
 /// Paste using wtype (Wayland)
-fn paste_wtype() -> Result<()> {
-    // wtype -M ctrl -k v -m ctrl
+fn paste_wtype(shortcut: PasteShortcut) -> Result<()> {
+    // e.g. wtype -M ctrl -k v -m ctrl
     let output = Command::new("wtype")
-        .args(["-M", "ctrl", "-k", "v", "-m", "ctrl"])
+        .args(shortcut.wtype_args())
         .output()
         .map_err(|e| AppError::Clipboard(format!("Failed to run wtype: {}", e)))?;
 
@@ -163,10 +478,14 @@ fn paste_wtype() -> Result<()> {
 }
 
 /// Paste using ydotool (works on both X11 and Wayland)
-fn paste_ydotool() -> Result<()> {
+fn paste_ydotool(shortcut: PasteShortcut) -> Result<()> {
+    // ydotool only looks in one place; point it at the socket we found
+    let socket = prepare_ydotool()?;
+
     // Use ydotool key with key names (works with newer versions)
     let output = Command::new("ydotool")
-        .args(["key", "ctrl+v"])
+        .args(["key", shortcut.ydotool_keys()])
+        .env("YDOTOOL_SOCKET", &socket)
         .output()
         .map_err(|e| AppError::Clipboard(format!("Failed to run ydotool: {}", e)))?;
 
@@ -182,29 +501,168 @@ fn paste_ydotool() -> Result<()> {
     }
 }
 
+/// State of the ydotool setup, with what to do when it cannot work
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct YdotoolStatus {
+    pub installed: bool,
+    /// ydotoold answered on `socket_path`
+    pub daemon_running: bool,
+    pub socket_path: Option<String>,
+    /// This user can open /dev/uinput (needed to start ydotoold ourselves)
+    pub uinput_writable: bool,
+    /// The user opted in to /dev/uinput input
+    pub allowed: bool,
+    /// Actionable fix when ydotool would fail, None when it is ready
+    pub hint: Option<String>,
+}
+
+/// Sockets ydotoold may listen on, in the order ydotool looks for them
+fn ydotool_socket_candidates() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Ok(path) = std::env::var("YDOTOOL_SOCKET") {
+        paths.push(PathBuf::from(path));
+    }
+    if let Ok(dir) = std::env::var("XDG_RUNTIME_DIR") {
+        paths.push(PathBuf::from(dir).join(".ydotool_socket"));
+    }
+    paths.push(PathBuf::from("/tmp/.ydotool_socket"));
+    paths
+}
+
+/// Socket of a running ydotoold, if any
+fn find_ydotoold_socket() -> Option<PathBuf> {
+    ydotool_socket_candidates().into_iter().find(|path| {
+        UnixDatagram::unbound()
+            .and_then(|socket| socket.connect(path))
+            .is_ok()
+    })
+}
+
+fn uinput_writable() -> bool {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/uinput")
+        .is_ok()
+}
+
+/// Diagnose the ydotool setup
+pub fn ydotool_status() -> YdotoolStatus {
+    let socket = find_ydotoold_socket();
+    let mut status = YdotoolStatus {
+        installed: is_command_available("ydotool"),
+        daemon_running: socket.is_some(),
+        socket_path: socket.map(|path| path.to_string_lossy().to_string()),
+        uinput_writable: uinput_writable(),
+        allowed: is_uinput_allowed(),
+        hint: None,
+    };
+    status.hint = ydotool_hint(&status, is_command_available("ydotoold"));
+    status
+}
+
+/// What the user needs to do before ydotool can type, if anything
+fn ydotool_hint(status: &YdotoolStatus, daemon_installed: bool) -> Option<String> {
+    if !status.installed {
+        Some("Install ydotool to type through /dev/uinput.".to_string())
+    } else if !status.allowed {
+        Some("Enable /dev/uinput input in settings to use ydotool.".to_string())
+    } else if status.daemon_running {
+        None
+    } else if !daemon_installed {
+        Some("ydotoold is not installed; install it alongside ydotool.".to_string())
+    } else if !status.uinput_writable {
+        Some("ydotoold is not running and /dev/uinput is not writable. Add yourself to the input group \
+(or add a udev rule for uinput), then log in again, or start ydotoold as a system service."
+            .to_string())
+    } else {
+        Some("ydotoold is not running. Start it (e.g. `systemctl --user start ydotoold`) or let \
+WhisperTray start it in settings."
+            .to_string())
+    }
+}
+
+/// Make sure ydotoold is reachable, returning the socket to pass to ydotool
+///
+/// Starts a user-scoped daemon when allowed; otherwise fails with the fix,
+/// since ydotool itself fails silently without its daemon.
+fn prepare_ydotool() -> Result<PathBuf> {
+    if let Some(socket) = find_ydotoold_socket() {
+        return Ok(socket);
+    }
+
+    if MANAGE_YDOTOOLD.load(Ordering::SeqCst) && uinput_writable() {
+        let dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/tmp".to_string());
+        let socket = PathBuf::from(dir).join(".ydotool_socket");
+        let child = Command::new("ydotoold")
+            .arg(format!("--socket-path={}", socket.display()))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| AppError::Clipboard(format!("Failed to start ydotoold: {}", e)))?;
+        log::info!("Started ydotoold (pid {}) on {}", child.id(), socket.display());
+
+        let started = std::time::Instant::now();
+        while started.elapsed() < YDOTOOLD_STARTUP {
+            if UnixDatagram::unbound()
+                .and_then(|s| s.connect(&socket))
+                .is_ok()
+            {
+                return Ok(socket);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        return Err(AppError::Clipboard("ydotoold started but its socket did not appear".to_string()));
+    }
+
+    let hint = ydotool_status()
+        .hint
+        .unwrap_or_else(|| "ydotoold is not running".to_string());
+    Err(AppError::Clipboard(hint))
+}
+
 /// Type text directly (alternative to paste for some applications)
-pub fn type_text(text: &str) -> Result<()> {
+pub fn type_text(text: &str, settings: &PasteSettings) -> Result<()> {
     // Delay to ensure user has released hotkey and focus is correct
-    thread::sleep(Duration::from_millis(200));
+    thread::sleep(Duration::from_millis(settings.pre_delay_ms));
+
+    type_chunks(text, settings).map_err(|(_, e)| e)
+}
 
+/// Type text chunk by chunk, without the pre-paste delay
+///
+/// On failure, returns the byte offset of the first chunk that was not
+/// fully typed along with the error.
+fn type_chunks(text: &str, settings: &PasteSettings) -> std::result::Result<(), (usize, AppError)> {
     let backend = detect_backend();
+    let delay = settings.per_key_delay_ms;
+    let mut typed = 0;
+
+    for (i, chunk) in chunks(text, settings.chunk_size).iter().enumerate() {
+        if i > 0 {
+            thread::sleep(CHUNK_PAUSE);
+        }
+        type_chunk(backend, chunk, delay).map_err(|e| (typed, e))?;
+        typed += chunk.len();
+    }
+
+    Ok(())
+}
 
+/// Type one chunk with the given backend
+fn type_chunk(backend: PasteBackend, chunk: &str, delay: u64) -> Result<()> {
     match backend {
-        PasteBackend::Enigo => type_text_enigo(text),
+        PasteBackend::Enigo => type_text_enigo(chunk, delay),
         PasteBackend::Wtype => {
             // Try wtype first, fall back to ydotool
-            if let Err(e) = type_text_wtype(text) {
-                log::warn!("wtype typing failed ({}), trying ydotool", e);
-                if is_command_available("ydotool") {
-                    type_text_ydotool(text)
-                } else {
-                    Err(e)
+            match type_text_wtype(chunk, delay) {
+                Err(e) if ydotool_available() => {
+                    log::warn!("wtype typing failed ({}), trying ydotool", e);
+                    type_text_ydotool(chunk, delay)
                 }
-            } else {
-                Ok(())
+                result => result,
             }
         }
-        PasteBackend::Ydotool => type_text_ydotool(text),
+        PasteBackend::Ydotool => type_text_ydotool(chunk, delay),
         PasteBackend::ClipboardOnly => {
             log::info!("No type backend available");
             Err(AppError::Clipboard("No typing backend available".to_string()))
@@ -213,25 +671,36 @@ pub fn type_text(text: &str) -> Result<()> {
 }
 
 /// Type text using enigo
-fn type_text_enigo(text: &str) -> Result<()> {
+fn type_text_enigo(text: &str, per_key_delay_ms: u64) -> Result<()> {
     use enigo::{Enigo, Keyboard, Settings};
 
     let mut enigo = Enigo::new(&Settings::default())
         .map_err(|e| AppError::Clipboard(format!("Failed to create input simulator: {}", e)))?;
 
-    enigo
-        .text(text)
-        .map_err(|e| AppError::Clipboard(format!("Failed to type text: {}", e)))?;
+    if per_key_delay_ms == 0 {
+        enigo
+            .text(text)
+            .map_err(|e| AppError::Clipboard(format!("Failed to type text: {}", e)))?;
+    } else {
+        // enigo has no key delay of its own; type one character at a time
+        let mut buf = [0u8; 4];
+        for c in text.chars() {
+            enigo
+                .text(c.encode_utf8(&mut buf))
+                .map_err(|e| AppError::Clipboard(format!("Failed to type text: {}", e)))?;
+            thread::sleep(Duration::from_millis(per_key_delay_ms));
+        }
+    }
 
     log::info!("Text typed ({} chars) via enigo", text.len());
     Ok(())
 }
 
 /// Type text using wtype
-fn type_text_wtype(text: &str) -> Result<()> {
+fn type_text_wtype(text: &str, per_key_delay_ms: u64) -> Result<()> {
     // wtype types text directly, use -d for delay between keys (ms)
     let output = Command::new("wtype")
-        .args(["-d", "0", text])
+        .args(["-d", &per_key_delay_ms.to_string(), text])
         .output()
         .map_err(|e| AppError::Clipboard(format!("Failed to run wtype: {}", e)))?;
 
@@ -248,11 +717,14 @@ fn type_text_wtype(text: &str) -> Result<()> {
 }
 
 /// Type text using ydotool
-fn type_text_ydotool(text: &str) -> Result<()> {
-    // Use --delay 0 to start immediately (we handle delay ourselves)
-    // Use --key-delay for reasonable typing speed
+fn type_text_ydotool(text: &str, per_key_delay_ms: u64) -> Result<()> {
+    let socket = prepare_ydotool()?;
+
+    // --delay is ydotool's own startup wait; --key-delay paces the keys
+    let key_delay = per_key_delay_ms.to_string();
     let output = Command::new("ydotool")
-        .args(["type", "--delay", "50", "--key-delay", "0", "--", text])
+        .args(["type", "--delay", "50", "--key-delay", &key_delay, "--", text])
+        .env("YDOTOOL_SOCKET", &socket)
         .output()
         .map_err(|e| AppError::Clipboard(format!("Failed to run ydotool: {}", e)))?;
 
@@ -268,8 +740,44 @@ fn type_text_ydotool(text: &str) -> Result<()> {
     }
 }
 
+/// Put text on the clipboard
+pub fn set_clipboard_text(text: &str) -> Result<()> {
+    if use_wl_clipboard() {
+        return wl_copy(text, false);
+    }
+
+    let mut clipboard = Clipboard::new()
+        .map_err(|e| AppError::Clipboard(format!("Failed to access clipboard: {}", e)))?;
+
+    clipboard
+        .set_text(text)
+        .map_err(|e| AppError::Clipboard(format!("Failed to set clipboard text: {}", e)))
+}
+
+/// Put text in the primary selection (pasted by middle-click)
+pub fn set_primary_selection(text: &str) -> Result<()> {
+    use arboard::{LinuxClipboardKind, SetExtLinux};
+
+    if use_wl_clipboard() {
+        return wl_copy(text, true);
+    }
+
+    let mut clipboard = Clipboard::new()
+        .map_err(|e| AppError::Clipboard(format!("Failed to access clipboard: {}", e)))?;
+
+    clipboard
+        .set()
+        .clipboard(LinuxClipboardKind::Primary)
+        .text(text)
+        .map_err(|e| AppError::Clipboard(format!("Failed to set primary selection: {}", e)))
+}
+
 /// Get text from clipboard
 pub fn get_clipboard_text() -> Result<String> {
+    if use_wl_clipboard() {
+        return wl_paste(false);
+    }
+
     let mut clipboard = Clipboard::new()
         .map_err(|e| AppError::Clipboard(format!("Failed to access clipboard: {}", e)))?;
 
@@ -278,6 +786,74 @@ pub fn get_clipboard_text() -> Result<String> {
         .map_err(|e| AppError::Clipboard(format!("Failed to get clipboard text: {}", e)))
 }
 
+/// Get the primary selection (highlighted text), if any
+pub fn get_primary_selection() -> Option<String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+
+    let text = if use_wl_clipboard() {
+        wl_paste(true).ok()
+    } else {
+        let mut clipboard = Clipboard::new().ok()?;
+        clipboard
+            .get()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text()
+            .ok()
+    };
+    text.filter(|text| !text.trim().is_empty())
+}
+
+/// Set the clipboard (or primary selection) with wl-copy, which keeps
+/// serving it after we return
+///
+/// wl-copy forks a daemon that inherits its output, so stderr is not piped:
+/// reading it would wait until the clipboard is next replaced.
+fn wl_copy(text: &str, primary: bool) -> Result<()> {
+    let mut child = Command::new("wl-copy")
+        .args(primary.then_some("--primary"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| AppError::Clipboard(format!("Failed to run wl-copy: {}", e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| AppError::Clipboard(format!("Failed to write to wl-copy: {}", e)))?;
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| AppError::Clipboard(format!("Failed to run wl-copy: {}", e)))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(AppError::Clipboard(format!("wl-copy failed ({})", status)))
+    }
+}
+
+/// Read the clipboard (or primary selection) with wl-paste
+fn wl_paste(primary: bool) -> Result<String> {
+    let mut command = Command::new("wl-paste");
+    command.args(["--no-newline", "--type", "text"]);
+    if primary {
+        command.arg("--primary");
+    }
+
+    let output = command
+        .output()
+        .map_err(|e| AppError::Clipboard(format!("Failed to run wl-paste: {}", e)))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(AppError::Clipboard(format!("wl-paste failed: {}", stderr.trim())))
+    }
+}
+
 /// Check if we're running under Wayland
 pub fn is_wayland() -> bool {
     std::env::var("WAYLAND_DISPLAY").is_ok()
@@ -290,6 +866,7 @@ pub fn is_wayland() -> bool {
 pub fn get_paste_info() -> PasteInfo {
     let is_wayland = is_wayland();
     let backend = detect_backend();
+    let ydotool = is_command_available("ydotool").then(ydotool_status);
 
     PasteInfo {
         is_wayland,
@@ -297,18 +874,25 @@ pub fn get_paste_info() -> PasteInfo {
         paste_supported: backend != PasteBackend::ClipboardOnly,
         type_supported: backend != PasteBackend::ClipboardOnly,
         clipboard_supported: true,
+        clipboard_backend: if use_wl_clipboard() { "wl-clipboard" } else { "arboard" }.to_string(),
         notes: match backend {
             PasteBackend::Enigo => "Using enigo (X11). Full paste simulation supported.".to_string(),
             PasteBackend::Wtype => "Using wtype (Wayland). Full paste simulation supported.".to_string(),
-            PasteBackend::Ydotool => "Using ydotool. Full paste simulation supported.".to_string(),
+            PasteBackend::Ydotool => match ydotool.as_ref().and_then(|status| status.hint.clone()) {
+                Some(hint) => format!("Using ydotool, but it will fail: {}", hint),
+                None => "Using ydotool. Full paste simulation supported.".to_string(),
+            },
             PasteBackend::ClipboardOnly => {
-                if is_wayland {
+                if is_wayland && !is_uinput_allowed() && is_command_available("ydotool") {
+                    "Wayland detected and ydotool is installed, but /dev/uinput input is not enabled in settings. Text is copied to clipboard.".to_string()
+                } else if is_wayland {
                     "Wayland detected but no paste backend available. Install wtype or ydotool for auto-paste. Text is copied to clipboard.".to_string()
                 } else {
                     "No paste backend available. Text is copied to clipboard.".to_string()
                 }
             }
         },
+        ydotool,
     }
 }
 
@@ -321,7 +905,11 @@ pub struct PasteInfo {
     pub paste_supported: bool,
     pub type_supported: bool,
     pub clipboard_supported: bool,
+    /// Tool used for the clipboard ("arboard" or "wl-clipboard")
+    pub clipboard_backend: String,
     pub notes: String,
+    /// ydotool diagnostics, when it is installed
+    pub ydotool: Option<YdotoolStatus>,
 }
 
 impl Default for PasteBackend {
@@ -353,6 +941,74 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_resolve_wl_clipboard() {
+        assert!(resolve_wl_clipboard(ClipboardBackend::Auto, true, true));
+        assert!(!resolve_wl_clipboard(ClipboardBackend::Auto, false, true));
+        assert!(!resolve_wl_clipboard(ClipboardBackend::Auto, true, false));
+        assert!(resolve_wl_clipboard(ClipboardBackend::WlClipboard, false, true));
+        assert!(!resolve_wl_clipboard(ClipboardBackend::WlClipboard, true, false));
+        assert!(!resolve_wl_clipboard(ClipboardBackend::Arboard, true, true));
+    }
+
+    #[test]
+    fn test_ydotool_hint() {
+        let ready = YdotoolStatus {
+            installed: true,
+            daemon_running: true,
+            socket_path: Some("/tmp/.ydotool_socket".to_string()),
+            uinput_writable: false,
+            allowed: true,
+            hint: None,
+        };
+        assert_eq!(ydotool_hint(&ready, true), None);
+
+        let stopped = YdotoolStatus {
+            daemon_running: false,
+            ..ready.clone()
+        };
+        assert!(ydotool_hint(&stopped, true).unwrap().contains("input group"));
+        assert!(ydotool_hint(&stopped, false).unwrap().contains("not installed"));
+
+        let not_allowed = YdotoolStatus {
+            allowed: false,
+            ..ready
+        };
+        assert!(ydotool_hint(&not_allowed, true).unwrap().contains("settings"));
+    }
+
+    #[test]
+    fn test_find_rule() {
+        let rules = vec![
+            PasteRule {
+                class: Some("^figma".to_string()),
+                strategy: PasteStrategy::Paste,
+                ..Default::default()
+            },
+            PasteRule {
+                class: Some("virt-manager|vmware".to_string()),
+                title: Some("windows".to_string()),
+                strategy: PasteStrategy::ClipboardOnly,
+                ..Default::default()
+            },
+            PasteRule::default(),
+        ];
+
+        let strategy = |class, title| find_rule(&rules, class, title).map(|rule| rule.strategy);
+        assert_eq!(strategy(Some("Figma-Linux"), None), Some(PasteStrategy::Paste));
+        assert_eq!(strategy(Some("VMware"), Some("Windows 11")), Some(PasteStrategy::ClipboardOnly));
+        assert_eq!(strategy(Some("vmware"), Some("Ubuntu")), None);
+        // A rule without patterns never matches
+        assert_eq!(strategy(Some("code"), Some("main.rs")), None);
+    }
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(chunks("hello", 0), vec!["hello"]);
+        assert_eq!(chunks("héllo!", 4), vec!["héll", "o!"]);
+        assert_eq!(chunks("", 3), vec![""]);
+    }
+
     #[test]
     fn test_get_paste_info() {
         let info = get_paste_info();
@@ -0,0 +1,122 @@
+//! Phrase memory
+//!
+//! Outputs are split into word n-grams that are counted in the database as
+//! each dictation is saved. Looking up the words just before the cursor then
+//! suggests how the user usually continues a phrase. Phrases are stored
+//! lowercased without punctuation, and never span a sentence break.
+
+use serde::Serialize;
+
+/// Shortest phrase remembered, in words
+pub const MIN_WORDS: usize = 2;
+
+/// Longest phrase remembered, in words
+pub const MAX_WORDS: usize = 6;
+
+/// Words before the cursor used to look up continuations
+pub const CONTEXT_WORDS: usize = 3;
+
+/// Times a phrase must have been dictated before it is suggested
+pub const MIN_COUNT: u64 = 2;
+
+/// A remembered phrase continuing the prefix
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PhraseSuggestion {
+    /// The whole remembered phrase
+    pub phrase: String,
+    /// Text to insert after the prefix, starting with the rest of a partly
+    /// typed word
+    pub completion: String,
+    /// Times the phrase was dictated
+    pub count: u64,
+}
+
+/// The end of a prefix, as used for lookups
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixQuery {
+    /// Last complete words, at most `CONTEXT_WORDS`
+    pub context: Vec<String>,
+    /// Word being typed, empty when the prefix ends with a space
+    pub partial: String,
+}
+
+fn normalize_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+        .to_lowercase()
+}
+
+/// Normalized words of each sentence in `text`
+fn sentences(text: &str) -> Vec<Vec<String>> {
+    text.split(['.', '!', '?', ';', ':', '\n'])
+        .map(|sentence| {
+            sentence
+                .split_whitespace()
+                .map(normalize_word)
+                .filter(|w| !w.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|words| !words.is_empty())
+        .collect()
+}
+
+/// Every phrase of `MIN_WORDS` to `MAX_WORDS` words in `text`, once per occurrence
+pub fn ngrams(text: &str) -> Vec<String> {
+    let mut phrases = Vec::new();
+    for words in sentences(text) {
+        for start in 0..words.len() {
+            for len in MIN_WORDS..=MAX_WORDS.min(words.len() - start) {
+                phrases.push(words[start..start + len].join(" "));
+            }
+        }
+    }
+    phrases
+}
+
+/// Split the text before the cursor into context words and a partial word
+pub fn parse_prefix(prefix: &str) -> PrefixQuery {
+    // Only the current sentence matters
+    let sentence = prefix
+        .rsplit(['.', '!', '?', ';', ':', '\n'])
+        .next()
+        .unwrap_or_default();
+    let mut words: Vec<String> = sentence
+        .split_whitespace()
+        .map(normalize_word)
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let partial = if sentence.ends_with(char::is_whitespace) || sentence.is_empty() {
+        String::new()
+    } else {
+        words.pop().unwrap_or_default()
+    };
+    let skip = words.len().saturating_sub(CONTEXT_WORDS);
+
+    PrefixQuery {
+        context: words.split_off(skip),
+        partial,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ngrams() {
+        let phrases = ngrams("Thanks so much. Best, Sam");
+        assert_eq!(phrases, vec!["thanks so", "thanks so much", "so much", "best sam"]);
+        assert!(ngrams("Hello").is_empty());
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        let query = parse_prefix("Done. Let me know if you ha");
+        assert_eq!(query.context, vec!["know", "if", "you"]);
+        assert_eq!(query.partial, "ha");
+
+        let query = parse_prefix("Thanks so ");
+        assert_eq!(query.context, vec!["thanks", "so"]);
+        assert_eq!(query.partial, "");
+    }
+}
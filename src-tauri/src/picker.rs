@@ -0,0 +1,206 @@
+//! Multi-take comparison picker
+//!
+//! Modes that compare models produce several candidate outputs. A small
+//! window lists them and the user picks one with 1/2/3; dismissing it keeps
+//! the first without counting as a pick. The state mutex is let go while
+//! the picker waits, and focus goes back to the window that had it, so the
+//! chosen take is pasted there.
+
+use crate::cancel::CancelToken;
+use crate::database::ModelPickStats;
+use crate::error::Result;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+use tokio::sync::oneshot;
+
+const PICKER_LABEL: &str = "picker";
+
+/// The first candidate is used when nothing is picked within this time
+const PICK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Comparisons a model must take part in before it can be recommended
+const MIN_COMPARISONS: u64 = 5;
+
+/// One output offered in the picker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    pub provider: String,
+    pub model: String,
+    pub text: String,
+}
+
+/// Candidates waiting for a choice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickRequest {
+    pub mode_key: String,
+    pub candidates: Vec<Candidate>,
+}
+
+struct Pending {
+    request: PickRequest,
+    reply: oneshot::Sender<usize>,
+}
+
+/// Hands candidates to the picker window and waits for the choice
+#[derive(Clone, Default)]
+pub struct Picker {
+    pending: Arc<Mutex<Option<Pending>>>,
+}
+
+impl Picker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show the picker and wait for a choice
+    ///
+    /// Returns the chosen index, or None when the picker is dismissed or times
+    /// out or there are no windows to show it in (daemon mode).
+    pub async fn choose(&self, handle: &AppHandle, request: PickRequest, cancel: &CancelToken) -> Result<Option<usize>> {
+        if crate::daemon::is_headless() {
+            return Ok(None);
+        }
+        let (reply, choice) = oneshot::channel();
+        *self.pending.lock().unwrap() = Some(Pending {
+            request: request.clone(),
+            reply,
+        });
+
+        let focused = tokio::task::spawn_blocking(crate::window_info::focused_window)
+            .await
+            .ok()
+            .flatten();
+        if let Err(e) = show_picker(handle) {
+            log::warn!("Failed to show picker, using the first candidate: {}", e);
+            self.pending.lock().unwrap().take();
+            return Ok(None);
+        }
+        let _ = handle.emit_to(PICKER_LABEL, "picker-candidates", &request);
+
+        let result = cancel
+            .run(async { Ok(tokio::time::timeout(PICK_TIMEOUT, choice).await) })
+            .await;
+        self.pending.lock().unwrap().take();
+        hide_picker(handle);
+        if let Some(window) = focused {
+            let restored = tokio::task::spawn_blocking(move || crate::window_info::activate(&window)).await;
+            if !matches!(restored, Ok(true)) {
+                log::warn!("Failed to give focus back after the picker");
+            }
+        }
+
+        match result? {
+            Ok(Ok(index)) if index < request.candidates.len() => Ok(Some(index)),
+            Ok(_) => Ok(None),
+            Err(_) => {
+                info!("No candidate picked in time, using the first");
+                Ok(None)
+            }
+        }
+    }
+
+    /// The candidates waiting for a choice, for a window opened late
+    pub fn current(&self) -> Option<PickRequest> {
+        self.pending
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|pending| pending.request.clone())
+    }
+
+    /// Answer the waiting pick; `None` dismisses it. Returns false if nothing was waiting.
+    pub fn resolve(&self, index: Option<usize>) -> bool {
+        match self.pending.lock().unwrap().take() {
+            Some(pending) => {
+                if let Some(index) = index {
+                    let _ = pending.reply.send(index);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn show_picker(handle: &AppHandle) -> Result<()> {
+    if let Some(window) = handle.get_webview_window(PICKER_LABEL) {
+        let _ = window.show();
+        let _ = window.set_focus();
+    } else {
+        WebviewWindowBuilder::new(handle, PICKER_LABEL, WebviewUrl::App("/picker".into()))
+            .title("Choose output")
+            .inner_size(640.0, 420.0)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .center()
+            .focused(true)
+            .visible(true)
+            .build()?;
+        info!("Picker window created");
+    }
+    Ok(())
+}
+
+fn hide_picker(handle: &AppHandle) {
+    if let Some(window) = handle.get_webview_window(PICKER_LABEL) {
+        let _ = window.hide();
+    }
+}
+
+/// A model recommended as a mode's default from past picks
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModelRecommendation {
+    pub provider: String,
+    pub model: String,
+    /// Share of its comparisons the model won
+    pub win_rate: f64,
+    pub comparisons: u64,
+}
+
+/// Recommend the model with the best win rate, once it has enough comparisons
+pub fn recommend(stats: &[ModelPickStats]) -> Option<ModelRecommendation> {
+    stats
+        .iter()
+        .filter(|s| s.shown >= MIN_COMPARISONS)
+        .map(|s| ModelRecommendation {
+            provider: s.provider.clone(),
+            model: s.model.clone(),
+            win_rate: s.chosen as f64 / s.shown as f64,
+            comparisons: s.shown,
+        })
+        .max_by(|a, b| a.win_rate.total_cmp(&b.win_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(model: &str, shown: u64, chosen: u64) -> ModelPickStats {
+        ModelPickStats {
+            provider: "ollama".to_string(),
+            model: model.to_string(),
+            shown,
+            chosen,
+        }
+    }
+
+    #[test]
+    fn test_recommend_best_win_rate() {
+        let picks = vec![stats("a", 10, 3), stats("b", 8, 6), stats("c", 2, 2)];
+        let recommendation = recommend(&picks).unwrap();
+        assert_eq!(recommendation.model, "b");
+        assert_eq!(recommendation.win_rate, 0.75);
+
+        assert_eq!(recommend(&[stats("c", 2, 2)]), None);
+    }
+
+    #[test]
+    fn test_resolve_without_pending() {
+        let picker = Picker::new();
+        assert!(picker.current().is_none());
+        assert!(!picker.resolve(Some(1)));
+    }
+}
@@ -0,0 +1,350 @@
+//! Multi-step processing for modes
+//!
+//! A mode with `steps` runs its transcript through them in order instead of
+//! its single post-processor: regex rules, the local cleanup and LLM prompts,
+//! each LLM step with its own provider, model and prompt. The text each step
+//! produces is the next one's input, and the last step's output is pasted.
+//! Every step is timed as a span of the dictation's latency trace, so the
+//! history waterfall shows where the time went. LLM steps try the mode's
+//! fallback models when their own is unreachable; a step that fails anyway
+//! passes its input on and is reported in the output.
+
+use crate::cancel::CancelToken;
+use crate::error::{AppError, Result};
+use crate::latency::LatencyTrace;
+use crate::modes::LlmProvider as LlmProviderType;
+use crate::providers::llm::{self, FallbackLlmProvider, LlmProvider, TokenUsage};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// One step of a mode's pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum PipelineStep {
+    /// Replace every match of a regex; `$1` and `${name}` refer to groups
+    Regex {
+        pattern: String,
+        #[serde(default)]
+        replacement: String,
+    },
+    /// Local rule-based cleanup (see `cleanup`)
+    Cleanup,
+    /// A prompt run by an LLM; `{{transcript}}` is the previous step's output
+    Llm {
+        provider: LlmProviderType,
+        model: String,
+        prompt: String,
+    },
+}
+
+impl PipelineStep {
+    /// Name of the step's latency span, numbered from 1
+    pub fn span_name(&self, index: usize) -> String {
+        let kind = match self {
+            PipelineStep::Regex { .. } => "regex",
+            PipelineStep::Cleanup => "cleanup",
+            PipelineStep::Llm { .. } => "llm",
+        };
+        format!("step {}: {}", index + 1, kind)
+    }
+}
+
+/// An LLM provider with the fallbacks it tries, and the provider and model
+/// of each in the order tried
+pub type LlmChain = (FallbackLlmProvider, Vec<(LlmProviderType, String)>);
+
+/// A step ready to run
+enum Prepared {
+    Regex(Regex, String),
+    Cleanup,
+    Llm {
+        provider: FallbackLlmProvider,
+        chain: Vec<(LlmProviderType, String)>,
+        prompt: String,
+    },
+}
+
+/// Text after one step, for showing how a pipeline got to its output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepOutput {
+    /// Step or stage name, e.g. "step 2: llm" or "typography"
+    pub name: String,
+    /// Prompt sent, for LLM steps
+    pub prompt: Option<String>,
+    pub text: String,
+    pub duration_ms: u64,
+    /// Why the step was skipped, passing its input on
+    pub error: Option<String>,
+}
+
+/// What a pipeline produced
+#[derive(Debug, Clone, Default)]
+pub struct PipelineOutput {
+    pub text: String,
+    /// Text after each step
+    pub steps: Vec<StepOutput>,
+    /// Token usage summed over the LLM steps that ran
+    pub usage: TokenUsage,
+    /// Provider and model of the last LLM step that ran
+    pub llm: Option<(LlmProviderType, String)>,
+    /// Cost summed over the LLM steps; None if any step's is unknown
+    pub cost_usd: Option<f64>,
+}
+
+impl PipelineOutput {
+    /// The first step that failed and was skipped, if any
+    pub fn failed_step(&self) -> Option<&StepOutput> {
+        self.steps.iter().find(|step| step.error.is_some())
+    }
+}
+
+/// A mode's processing run on a typed transcript, stage by stage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunResult {
+    pub transcript: String,
+    /// Text after each stage, in order
+    pub stages: Vec<StepOutput>,
+    /// Final text that would have been pasted
+    pub output: String,
+    /// Output was shortened or cut to fit the mode's length limit
+    pub truncated: bool,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub cost_usd: Option<f64>,
+}
+
+/// A mode's steps with their regexes compiled and LLMs created
+pub struct Pipeline {
+    steps: Vec<(String, Prepared)>,
+}
+
+impl Pipeline {
+    /// Compile the steps, creating each LLM step's provider and fallbacks with `create_llm`
+    pub fn prepare(
+        steps: &[PipelineStep],
+        create_llm: impl Fn(&LlmProviderType, &str) -> Result<LlmChain>,
+    ) -> Result<Self> {
+        let steps = steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let prepared = match step {
+                    PipelineStep::Regex {
+                        pattern,
+                        replacement,
+                    } => Prepared::Regex(
+                        Regex::new(pattern).map_err(|e| {
+                            AppError::Config(format!("Step {}: invalid regex: {}", i + 1, e))
+                        })?,
+                        replacement.clone(),
+                    ),
+                    PipelineStep::Cleanup => Prepared::Cleanup,
+                    PipelineStep::Llm {
+                        provider,
+                        model,
+                        prompt,
+                    } => {
+                        let (provider, chain) = create_llm(provider, model)?;
+                        Prepared::Llm {
+                            provider,
+                            chain,
+                            prompt: prompt.clone(),
+                        }
+                    }
+                };
+                Ok((step.span_name(i), prepared))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { steps })
+    }
+
+    /// Run the steps on a transcript
+    ///
+    /// `render` turns an LLM step's prompt template and the text so far into
+    /// the prompt sent. A failing LLM step is skipped, passing its input on,
+    /// so one unreachable provider does not lose the dictation; see
+    /// `PipelineOutput::failed_step`.
+    pub async fn run(
+        &self,
+        transcript: &str,
+        language: &str,
+        keep_filler_words: bool,
+        render: impl Fn(&str, &str) -> String,
+        cancel: &CancelToken,
+        trace: &mut LatencyTrace,
+    ) -> Result<PipelineOutput> {
+        let mut output = PipelineOutput {
+            text: transcript.to_string(),
+            cost_usd: Some(0.0),
+            ..Default::default()
+        };
+
+        for (name, step) in &self.steps {
+            let span = trace.begin(name);
+            let mut sent = None;
+            let mut error = None;
+            match step {
+                Prepared::Regex(regex, replacement) => {
+                    output.text = regex.replace_all(&output.text, replacement.as_str()).into_owned();
+                }
+                Prepared::Cleanup => {
+                    output.text = crate::cleanup::clean(&output.text, language, keep_filler_words);
+                }
+                Prepared::Llm {
+                    provider,
+                    chain,
+                    prompt,
+                } => {
+                    let prompt = render(prompt, &output.text);
+                    let result = provider.complete_cancellable(&prompt, cancel).await;
+                    sent = Some(prompt);
+                    match result {
+                        Ok(completion) => {
+                            // A fallback may have answered instead of the step's own model
+                            let (provider_type, model) = &chain[provider.used_index().unwrap_or(0)];
+                            output.text = completion.text;
+                            output.usage.add(&completion.usage);
+                            output.cost_usd = output.cost_usd.and_then(|total| {
+                                llm::estimate_cost_usd(provider_type, model, &completion.usage)
+                                    .map(|cost| total + cost)
+                            });
+                            output.llm = Some((provider_type.clone(), model.clone()));
+                        }
+                        Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+                        Err(e) => {
+                            log::warn!("Skipping pipeline {}: {}", name, e);
+                            error = Some(e.to_string());
+                        }
+                    }
+                }
+            }
+            output.steps.push(StepOutput {
+                name: name.clone(),
+                prompt: sent,
+                text: output.text.clone(),
+                duration_ms: trace.end(span),
+                error,
+            });
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_serialization() {
+        let steps: Vec<PipelineStep> = serde_json::from_value(serde_json::json!([
+            { "kind": "regex", "pattern": "(?i)\\bnew line\\b", "replacement": "\n" },
+            { "kind": "cleanup" },
+            { "kind": "llm", "provider": "ollama", "model": "llama3.2", "prompt": "Fix: {{transcript}}" }
+        ]))
+        .unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[1], PipelineStep::Cleanup);
+        assert_eq!(steps[2].span_name(2), "step 3: llm");
+    }
+
+    #[tokio::test]
+    async fn test_run_local_steps() {
+        let steps = vec![
+            PipelineStep::Regex {
+                pattern: r"(\d+) percent".to_string(),
+                replacement: "$1%".to_string(),
+            },
+            PipelineStep::Regex {
+                pattern: "sales".to_string(),
+                replacement: "revenue".to_string(),
+            },
+        ];
+        let pipeline = Pipeline::prepare(&steps, |_, _| unreachable!()).unwrap();
+        let mut trace = LatencyTrace::start("dictation");
+        let output = pipeline
+            .run(
+                "sales grew 12 percent",
+                "en",
+                false,
+                |prompt, _| prompt.to_string(),
+                &CancelToken::new(),
+                &mut trace,
+            )
+            .await
+            .unwrap();
+        assert_eq!(output.text, "revenue grew 12%");
+        assert_eq!(output.steps[0].text, "sales grew 12%");
+        assert!(output.llm.is_none());
+
+        let names: Vec<_> = trace.finish().into_iter().map(|span| span.name).collect();
+        assert_eq!(names, vec!["dictation", "step 1: regex", "step 2: regex"]);
+
+        let invalid = vec![PipelineStep::Regex {
+            pattern: "(".to_string(),
+            replacement: String::new(),
+        }];
+        assert!(Pipeline::prepare(&invalid, |_, _| unreachable!()).is_err());
+    }
+
+    struct StubProvider(fn() -> Result<String>);
+
+    #[async_trait::async_trait]
+    impl LlmProvider for StubProvider {
+        async fn complete(&self, _prompt: &str) -> Result<String> {
+            (self.0)()
+        }
+
+        fn name(&self) -> &str {
+            "Stub"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_llm_step_falls_back_then_reports_failure() {
+        let steps = vec![PipelineStep::Llm {
+            provider: LlmProviderType::Ollama,
+            model: "llama3.2".to_string(),
+            prompt: "Fix: {{transcript}}".to_string(),
+        }];
+        let run = |pipeline: Pipeline| async move {
+            let mut trace = LatencyTrace::start("dictation");
+            pipeline
+                .run("teh report", "en", false, |_, text| text.to_string(), &CancelToken::new(), &mut trace)
+                .await
+                .unwrap()
+        };
+
+        // The step's own model is unreachable, so the mode's fallback answers
+        let pipeline = Pipeline::prepare(&steps, |provider, model| {
+            let providers: Vec<Box<dyn LlmProvider>> = vec![
+                Box::new(StubProvider(|| Err(AppError::ProviderUnavailable("down".to_string())))),
+                Box::new(StubProvider(|| Ok("The report".to_string()))),
+            ];
+            let chain = vec![
+                (provider.clone(), model.to_string()),
+                (LlmProviderType::Ollama, "qwen2.5".to_string()),
+            ];
+            Ok((FallbackLlmProvider::new(providers), chain))
+        })
+        .unwrap();
+        let output = run(pipeline).await;
+        assert_eq!(output.text, "The report");
+        assert_eq!(output.llm, Some((LlmProviderType::Ollama, "qwen2.5".to_string())));
+        assert!(output.failed_step().is_none());
+
+        // A step that fails anyway passes its input on, and says so
+        let pipeline = Pipeline::prepare(&steps, |provider, model| {
+            let providers: Vec<Box<dyn LlmProvider>> =
+                vec![Box::new(StubProvider(|| Err(AppError::Provider("invalid API key".to_string()))))];
+            Ok((FallbackLlmProvider::new(providers), vec![(provider.clone(), model.to_string())]))
+        })
+        .unwrap();
+        let output = run(pipeline).await;
+        assert_eq!(output.text, "teh report");
+        assert!(output.llm.is_none());
+        let failed = output.failed_step().unwrap();
+        assert_eq!(failed.name, "step 1: llm");
+        assert!(failed.error.as_deref().unwrap().contains("invalid API key"));
+    }
+}
@@ -0,0 +1,628 @@
+//! Native PipeWire audio backend
+//!
+//! Through cpal, PipeWire is reached via ALSA and the pulse plugin: devices
+//! show up under duplicated, ambiguous ALSA names, only the default source
+//! can really be chosen, and monitor streams must be moved with pactl.
+//! Talking to PipeWire directly lists nodes under their names and
+//! descriptions, targets a node (or a sink, for its monitor) by name, asks
+//! for 16kHz mono so PipeWire does the conversion, hints a short quantum,
+//! and notices devices coming and going or the default changing, and other
+//! apps starting to capture.
+
+use crate::audio::{
+    AudioDevice, LevelCallback, RecordingHandle, RecordingTicker, DEFAULT_MONITOR, TICK_INTERVAL,
+    WHISPER_SAMPLE_RATE,
+};
+use crate::error::{AppError, Result};
+use ::pipewire as pw;
+use pw::spa;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// Samples per processing cycle asked of PipeWire (30ms at 16kHz)
+const QUANTUM_SAMPLES: u32 = 480;
+
+/// Metadata keys holding the default devices
+const DEFAULT_SOURCE_KEY: &str = "default.audio.source";
+const DEFAULT_SINK_KEY: &str = "default.audio.sink";
+
+/// A node to capture from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// Node name; None follows the default source (or sink, for monitors)
+    pub node: Option<String>,
+    /// Capture what the sink plays instead of a source
+    pub monitor: bool,
+}
+
+impl Target {
+    /// A source by node name; empty or "default" follows the default source
+    pub fn source(name: &str) -> Self {
+        Self {
+            node: Some(name.to_string()).filter(|n| !n.is_empty() && n != "default"),
+            monitor: false,
+        }
+    }
+
+    /// The monitor of a sink, named `<sink>.monitor` as `list_devices` does
+    pub fn monitor(name: &str) -> Self {
+        let sink = name.strip_suffix(".monitor").unwrap_or(name);
+        Self {
+            node: Some(sink.to_string()).filter(|n| !n.is_empty() && n != DEFAULT_MONITOR),
+            monitor: true,
+        }
+    }
+}
+
+/// An audio node of the graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Node {
+    name: String,
+    description: Option<String>,
+    sink: bool,
+}
+
+fn pw_error(e: pw::Error) -> AppError {
+    AppError::Audio(format!("PipeWire: {}", e))
+}
+
+/// Audio sources and sinks among registry globals
+fn audio_node(global: &pw::registry::GlobalObject<&spa::utils::dict::DictRef>) -> Option<Node> {
+    if global.type_ != pw::types::ObjectType::Node {
+        return None;
+    }
+    let props = global.props?;
+    let class = props.get("media.class")?;
+    let sink = match class {
+        "Audio/Sink" => true,
+        _ if class.starts_with("Audio/Source") => false,
+        _ => return None,
+    };
+    Some(Node {
+        name: props.get("node.name")?.to_string(),
+        description: props.get("node.description").map(str::to_string),
+        sink,
+    })
+}
+
+/// Node name in a default device metadata value, `{"name": "<node>"}`
+fn default_node_name(value: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(value).ok()?;
+    value["name"].as_str().map(str::to_string)
+}
+
+/// Devices as listed in settings: sources, then the monitor of each sink
+fn devices_from_nodes(nodes: &[Node], default_source: Option<&str>) -> Vec<AudioDevice> {
+    let sources = nodes
+        .iter()
+        .filter(|node| !node.sink)
+        .map(|node| AudioDevice {
+            is_default: default_source == Some(node.name.as_str()),
+            name: node.name.clone(),
+            label: node.description.clone(),
+            is_monitor: false,
+        });
+    let monitors = nodes
+        .iter()
+        .filter(|node| node.sink)
+        .map(|node| AudioDevice {
+            name: format!("{}.monitor", node.name),
+            label: node
+                .description
+                .as_ref()
+                .map(|d| format!("Monitor of {}", d)),
+            is_default: false,
+            is_monitor: true,
+        });
+    sources.chain(monitors).collect()
+}
+
+/// A connection to the PipeWire daemon; the context must outlive the core
+struct Connection {
+    mainloop: pw::main_loop::MainLoop,
+    _context: pw::context::Context,
+    core: pw::core::Core,
+}
+
+impl Connection {
+    fn open() -> Result<Self> {
+        pw::init();
+        let mainloop = pw::main_loop::MainLoop::new(None).map_err(pw_error)?;
+        let context = pw::context::Context::new(&mainloop).map_err(pw_error)?;
+        let core = context.connect(None).map_err(pw_error)?;
+        Ok(Self {
+            mainloop,
+            _context: context,
+            core,
+        })
+    }
+
+    /// Run the loop until the daemon has processed everything sent so far
+    fn roundtrip(&self) -> Result<()> {
+        let done = Rc::new(Cell::new(false));
+        let pending = self.core.sync(0).map_err(pw_error)?;
+        let _listener = self
+            .core
+            .add_listener_local()
+            .done({
+                let done = done.clone();
+                let mainloop = self.mainloop.clone();
+                move |id, seq| {
+                    if id == pw::core::PW_ID_CORE && seq == pending {
+                        done.set(true);
+                        mainloop.quit();
+                    }
+                }
+            })
+            .register();
+        while !done.get() {
+            self.mainloop.run();
+        }
+        Ok(())
+    }
+}
+
+/// Bound metadata objects, kept alive with their listeners
+type BoundMetadata = Rc<RefCell<Vec<(pw::metadata::Metadata, pw::metadata::MetadataListener)>>>;
+
+/// Follow the default devices in the "default" metadata object
+///
+/// `on_change` is called with the key and node name whenever the default
+/// source or sink changes, starting with the current values.
+fn bind_defaults(
+    registry: &Weak<pw::registry::Registry>,
+    global: &pw::registry::GlobalObject<&spa::utils::dict::DictRef>,
+    bound: &BoundMetadata,
+    on_change: impl Fn(&str, String) + 'static,
+) {
+    if global.type_ != pw::types::ObjectType::Metadata
+        || global.props.and_then(|props| props.get("metadata.name")) != Some("default")
+    {
+        return;
+    }
+    let Some(registry) = registry.upgrade() else {
+        return;
+    };
+    let metadata: pw::metadata::Metadata = match registry.bind(global) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            log::debug!("Could not bind PipeWire metadata: {}", e);
+            return;
+        }
+    };
+    let listener = metadata
+        .add_listener_local()
+        .property(move |_subject, key, _type, value| {
+            if let (Some(key @ (DEFAULT_SOURCE_KEY | DEFAULT_SINK_KEY)), Some(name)) =
+                (key, value.and_then(default_node_name))
+            {
+                on_change(key, name);
+            }
+            0
+        })
+        .register();
+    bound.borrow_mut().push((metadata, listener));
+}
+
+/// List sources and sink monitors with their descriptions
+pub fn list_devices() -> Result<Vec<AudioDevice>> {
+    let connection = Connection::open()?;
+    let registry = Rc::new(connection.core.get_registry().map_err(pw_error)?);
+    let nodes = Rc::new(RefCell::new(Vec::new()));
+    let defaults = Rc::new(RefCell::new(HashMap::new()));
+    let bound: BoundMetadata = Rc::default();
+
+    let _listener = registry
+        .add_listener_local()
+        .global({
+            let registry = Rc::downgrade(&registry);
+            let nodes = nodes.clone();
+            let defaults = defaults.clone();
+            let bound = bound.clone();
+            move |global| {
+                if let Some(node) = audio_node(global) {
+                    nodes.borrow_mut().push(node);
+                }
+                let defaults = defaults.clone();
+                bind_defaults(&registry, global, &bound, move |key, name| {
+                    defaults.borrow_mut().insert(key.to_string(), name);
+                });
+            }
+        })
+        .register();
+
+    // The first roundtrip lists the globals, the second the metadata bound meanwhile
+    connection.roundtrip()?;
+    connection.roundtrip()?;
+
+    let defaults = defaults.borrow();
+    Ok(devices_from_nodes(
+        &nodes.borrow(),
+        defaults.get(DEFAULT_SOURCE_KEY).map(String::as_str),
+    ))
+}
+
+/// Create a capture stream for one track and connect it to its target
+fn capture_stream(
+    core: &pw::core::Core,
+    target: &Target,
+    track: usize,
+    handle: &RecordingHandle,
+) -> Result<(pw::stream::Stream, pw::stream::StreamListener<()>)> {
+    let mut props = pw::properties::properties! {
+        *pw::keys::MEDIA_TYPE => "Audio",
+        *pw::keys::MEDIA_CATEGORY => "Capture",
+        *pw::keys::MEDIA_ROLE => "Communication",
+        *pw::keys::APP_NAME => "WhisperTray",
+        *pw::keys::NODE_LATENCY => format!("{}/{}", QUANTUM_SAMPLES, WHISPER_SAMPLE_RATE),
+    };
+    if let Some(node) = &target.node {
+        props.insert(*pw::keys::TARGET_OBJECT, node.as_str());
+    }
+    if target.monitor {
+        props.insert(*pw::keys::STREAM_CAPTURE_SINK, "true");
+    }
+    log::info!(
+        "Starting recording on PipeWire node: {}{}",
+        target.node.as_deref().unwrap_or("default"),
+        if target.monitor { " [monitor]" } else { "" }
+    );
+
+    let stream = pw::stream::Stream::new(core, "WhisperTray capture", props).map_err(pw_error)?;
+    let handle = handle.clone();
+    let listener = stream
+        .add_local_listener_with_user_data(())
+        .process(move |stream, _| {
+            let Some(mut buffer) = stream.dequeue_buffer() else {
+                return;
+            };
+            let Some(data) = buffer.datas_mut().first_mut() else {
+                return;
+            };
+            let size = data.chunk().size() as usize;
+            if let Some(bytes) = data.data() {
+                let samples: Vec<f32> = bytes[..size.min(bytes.len())]
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect();
+                handle.push_input(track, &samples, WHISPER_SAMPLE_RATE, 1);
+            }
+        })
+        .register()
+        .map_err(pw_error)?;
+
+    // Ask for what whisper takes, PipeWire converts from the device format
+    let mut info = spa::param::audio::AudioInfoRaw::new();
+    info.set_format(spa::param::audio::AudioFormat::F32LE);
+    info.set_rate(WHISPER_SAMPLE_RATE);
+    info.set_channels(1);
+    let format = spa::pod::Value::Object(spa::pod::Object {
+        type_: spa::utils::SpaTypes::ObjectParamFormat.as_raw(),
+        id: spa::param::ParamType::EnumFormat.as_raw(),
+        properties: info.into(),
+    });
+    let bytes =
+        spa::pod::serialize::PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &format)
+            .map_err(|e| AppError::Audio(format!("PipeWire: invalid format: {:?}", e)))?
+            .0
+            .into_inner();
+    let pod = spa::pod::Pod::from_bytes(&bytes)
+        .ok_or_else(|| AppError::Audio("PipeWire: invalid format".to_string()))?;
+
+    stream
+        .connect(
+            spa::utils::Direction::Input,
+            None,
+            pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS,
+            &mut [pod],
+        )
+        .map_err(pw_error)?;
+    Ok((stream, listener))
+}
+
+/// Capture `targets` into the tracks of `handle` until recording stops
+fn run_capture(
+    handle: &RecordingHandle,
+    targets: &[Target],
+    level_callback: Option<LevelCallback>,
+) -> Result<()> {
+    let connection = Connection::open()?;
+    let streams = targets
+        .iter()
+        .enumerate()
+        .map(|(track, target)| capture_stream(&connection.core, target, track, handle))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut ticker = RecordingTicker::new(handle.clone(), level_callback);
+    let mainloop = connection.mainloop.clone();
+    let timer = connection.mainloop.loop_().add_timer(move |_| {
+        if !ticker.tick() {
+            mainloop.quit();
+        }
+    });
+    timer
+        .update_timer(Some(TICK_INTERVAL), Some(TICK_INTERVAL))
+        .into_result()
+        .map_err(|e| AppError::Audio(format!("PipeWire: {}", e)))?;
+
+    connection.mainloop.run();
+    drop(streams);
+    Ok(())
+}
+
+/// Record from PipeWire nodes on a thread of their own (returns immediately)
+pub fn start_capture(
+    handle: RecordingHandle,
+    targets: Vec<Target>,
+    level_callback: Option<LevelCallback>,
+) {
+    std::thread::spawn(move || {
+        let _done = crate::audio::CaptureDone::new(&handle);
+        if let Err(e) = run_capture(&handle, &targets, level_callback) {
+            log::error!("Failed to record from PipeWire: {}", e);
+            handle.set_recording(false);
+        }
+        log::info!("Recording thread finished");
+    });
+}
+
+/// Whether the device watch has been started
+static DEVICE_WATCH: AtomicBool = AtomicBool::new(false);
+
+/// Emit "audio-devices-changed" when audio nodes come and go or a default
+/// device changes, e.g. a headset is plugged in
+///
+/// Started once, when the PipeWire backend is first in use; while another
+/// backend is chosen it stays quiet.
+pub fn spawn_device_watch(app: AppHandle) {
+    if DEVICE_WATCH.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(move || {
+        if let Err(e) = watch_devices(&app) {
+            log::warn!("Not watching PipeWire devices: {}", e);
+            DEVICE_WATCH.store(false, Ordering::SeqCst);
+        }
+    });
+}
+
+fn watch_devices(app: &AppHandle) -> Result<()> {
+    let connection = Connection::open()?;
+    let registry = Rc::new(connection.core.get_registry().map_err(pw_error)?);
+    let audio_nodes = Rc::new(RefCell::new(HashSet::new()));
+    let bound: BoundMetadata = Rc::default();
+    // Globals announced by the initial roundtrip are not changes
+    let ready = Rc::new(Cell::new(false));
+
+    let changed = {
+        let app = app.clone();
+        let ready = ready.clone();
+        move || {
+            if ready.get() && crate::audio::use_pipewire() {
+                log::debug!("PipeWire audio devices changed");
+                let _ = app.emit("audio-devices-changed", ());
+            }
+        }
+    };
+
+    let _listener = registry
+        .add_listener_local()
+        .global({
+            let registry = Rc::downgrade(&registry);
+            let audio_nodes = audio_nodes.clone();
+            let bound = bound.clone();
+            let changed = changed.clone();
+            move |global| {
+                if audio_node(global).is_some() {
+                    audio_nodes.borrow_mut().insert(global.id);
+                    changed();
+                }
+                let changed = changed.clone();
+                bind_defaults(&registry, global, &bound, move |_, _| changed());
+            }
+        })
+        .global_remove(move |id| {
+            if audio_nodes.borrow_mut().remove(&id) {
+                changed();
+            }
+        })
+        .register();
+
+    connection.roundtrip()?;
+    connection.roundtrip()?;
+    ready.set(true);
+    connection.mainloop.run();
+    Ok(())
+}
+
+/// Bound capture stream nodes by global id, kept alive with their listeners
+type BoundNodes = Rc<RefCell<HashMap<u32, (pw::node::Node, pw::node::NodeListener)>>>;
+
+/// Running capture streams of other apps by node id, as (id, app name)
+fn running_streams(streams: &HashMap<u32, Option<String>>) -> Vec<(u32, String)> {
+    let mut running: Vec<_> = streams
+        .iter()
+        .filter_map(|(id, app)| Some((*id, app.clone()?)))
+        .collect();
+    running.sort();
+    running
+}
+
+/// Follow other apps' capture streams until `keep_going` turns false
+///
+/// `on_change` gets the running capture streams of other processes, as
+/// (node id, app name), once they are known and then whenever one starts,
+/// stops or goes idle. Level meters are left out. `keep_going` is checked
+/// every `interval`. Fails only before `on_change` is first called.
+pub fn watch_capture_streams(
+    keep_going: impl Fn() -> bool + 'static,
+    interval: Duration,
+    on_change: impl Fn(Vec<(u32, String)>) + 'static,
+) -> Result<()> {
+    let connection = Connection::open()?;
+    let registry = Rc::new(connection.core.get_registry().map_err(pw_error)?);
+    // Each stream's app while it runs, None while it is idle, ours or a meter
+    let streams: Rc<RefCell<HashMap<u32, Option<String>>>> = Rc::default();
+    let bound: BoundNodes = Rc::default();
+    let on_change = Rc::new(on_change);
+    // Nodes announced by the initial roundtrips are reported all at once
+    let ready = Rc::new(Cell::new(false));
+    let report = {
+        let streams = streams.clone();
+        let on_change = on_change.clone();
+        let ready = ready.clone();
+        Rc::new(move || {
+            if ready.get() {
+                on_change(running_streams(&streams.borrow()));
+            }
+        })
+    };
+
+    let _listener = registry
+        .add_listener_local()
+        .global({
+            let registry = Rc::downgrade(&registry);
+            let streams = streams.clone();
+            let bound = bound.clone();
+            let report = report.clone();
+            move |global| {
+                let capture = global.type_ == pw::types::ObjectType::Node
+                    && global.props.and_then(|props| props.get("media.class")) == Some("Stream/Input/Audio");
+                let Some(registry) = registry.upgrade().filter(|_| capture) else {
+                    return;
+                };
+                let node: pw::node::Node = match registry.bind(global) {
+                    Ok(node) => node,
+                    Err(e) => {
+                        log::debug!("Could not bind PipeWire node: {}", e);
+                        return;
+                    }
+                };
+                let id = global.id;
+                let own_pid = std::process::id().to_string();
+                let streams = streams.clone();
+                let report = report.clone();
+                let listener = node
+                    .add_listener_local()
+                    .info(move |info| {
+                        let property = |key: &str| info.props().and_then(|props| props.get(key));
+                        let own = property("application.process.id") == Some(own_pid.as_str());
+                        let meter = property("media.name")
+                            .is_some_and(|name| crate::mic_conflict::METER_STREAMS.contains(&name));
+                        let running = matches!(info.state(), pw::node::NodeState::Running);
+                        let app = (running && !own && !meter).then(|| {
+                            property("application.name")
+                                .or_else(|| property("application.process.binary"))
+                                .unwrap_or("Unknown app")
+                                .to_string()
+                        });
+                        let changed = streams.borrow_mut().insert(id, app.clone()) != Some(app);
+                        if changed {
+                            report();
+                        }
+                    })
+                    .register();
+                bound.borrow_mut().insert(id, (node, listener));
+            }
+        })
+        .global_remove({
+            let streams = streams.clone();
+            let report = report.clone();
+            move |id| {
+                bound.borrow_mut().remove(&id);
+                if streams.borrow_mut().remove(&id).flatten().is_some() {
+                    report();
+                }
+            }
+        })
+        .register();
+
+    let timer = connection.mainloop.loop_().add_timer({
+        let mainloop = connection.mainloop.clone();
+        move |_| {
+            if !keep_going() {
+                mainloop.quit();
+            }
+        }
+    });
+    timer
+        .update_timer(Some(interval), Some(interval))
+        .into_result()
+        .map_err(|e| AppError::Audio(format!("PipeWire: {}", e)))?;
+
+    // The first roundtrip lists the streams, the second their state
+    connection.roundtrip()?;
+    connection.roundtrip()?;
+    ready.set(true);
+    report();
+    connection.mainloop.run();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_running_streams() {
+        let streams = HashMap::from([
+            (71, Some("Zoom".to_string())),
+            (64, None),
+            (58, Some("arecord".to_string())),
+        ]);
+        assert_eq!(
+            running_streams(&streams),
+            vec![(58, "arecord".to_string()), (71, "Zoom".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_devices_from_nodes() {
+        let nodes = vec![
+            Node {
+                name: "alsa_output.pci-0000_00_1f.3.analog-stereo".to_string(),
+                description: Some("Built-in Audio Analog Stereo".to_string()),
+                sink: true,
+            },
+            Node {
+                name: "alsa_input.usb-Blue_Yeti-00.analog-stereo".to_string(),
+                description: Some("Yeti Stereo Microphone".to_string()),
+                sink: false,
+            },
+            Node {
+                name: "alsa_input.pci-0000_00_1f.3.analog-stereo".to_string(),
+                description: None,
+                sink: false,
+            },
+        ];
+        let default = default_node_name(r#"{"name":"alsa_input.usb-Blue_Yeti-00.analog-stereo"}"#);
+        let devices = devices_from_nodes(&nodes, default.as_deref());
+
+        assert_eq!(devices.len(), 3);
+        assert_eq!(devices[0].label.as_deref(), Some("Yeti Stereo Microphone"));
+        assert!(devices[0].is_default);
+        assert!(!devices[1].is_default);
+        assert_eq!(devices[1].label, None);
+        assert_eq!(
+            devices[2].name,
+            "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor"
+        );
+        assert_eq!(
+            devices[2].label.as_deref(),
+            Some("Monitor of Built-in Audio Analog Stereo")
+        );
+        assert!(devices[2].is_monitor);
+
+        // Targets resolve back to the node names
+        assert_eq!(
+            Target::monitor(&devices[2].name).node.as_deref(),
+            Some(nodes[0].name.as_str())
+        );
+        assert_eq!(Target::monitor(DEFAULT_MONITOR).node, None);
+        assert_eq!(Target::source("default").node, None);
+    }
+}
@@ -0,0 +1,321 @@
+//! Playback of recordings kept in history
+//!
+//! A recording is decoded with `load_audio` (16kHz mono) and played on the
+//! default output device of the selected audio host. One recording plays at
+//! a time. cpal streams are not `Send`, so the output stream lives on its own
+//! thread, which reports progress to the history window in "playback-state".
+//! Once the stream closes the recording is unloaded, so playing it again
+//! opens a new stream from the start.
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use crate::error::{AppError, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{Device, FromSample, SampleFormat, SizedSample, StreamConfig};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+
+/// How often "playback-state" is emitted while playing
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Recording being played, as sent in "playback-state"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaybackState {
+    /// History item ID
+    pub id: String,
+    pub position_ms: u64,
+    pub duration_ms: u64,
+    pub playing: bool,
+    /// Played to the end or stopped; playing it again starts over
+    pub finished: bool,
+}
+
+/// Shared by the controls and the output callback
+pub(crate) struct Cursor {
+    samples: Vec<f32>,
+    /// Next sample to play
+    position: AtomicUsize,
+    paused: AtomicBool,
+    stopped: AtomicBool,
+}
+
+impl Cursor {
+    pub(crate) fn new(samples: Vec<f32>) -> Self {
+        Self {
+            samples,
+            position: AtomicUsize::new(0),
+            paused: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+            || self.position.load(Ordering::SeqCst) >= self.samples.len()
+    }
+
+    fn seek(&self, ms: u64) {
+        let sample = (ms * WHISPER_SAMPLE_RATE as u64 / 1000) as usize;
+        self.position
+            .store(sample.min(self.samples.len()), Ordering::SeqCst);
+    }
+
+    fn state(&self, id: &str) -> PlaybackState {
+        let to_ms = |samples: usize| samples as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+        let finished = self.is_finished();
+        PlaybackState {
+            id: id.to_string(),
+            position_ms: to_ms(self.position.load(Ordering::SeqCst).min(self.samples.len())),
+            duration_ms: to_ms(self.samples.len()),
+            playing: !finished && !self.paused.load(Ordering::SeqCst),
+            finished,
+        }
+    }
+
+    /// Fill an interleaved output buffer, resampling from 16kHz by `step`
+    /// recording samples per output frame
+    ///
+    /// `phase` is the fraction of a sample already played, kept between calls.
+    fn fill(&self, out: &mut [f32], channels: usize, step: f64, phase: &mut f64) {
+        let start = self.position.load(Ordering::SeqCst);
+        if self.paused.load(Ordering::SeqCst) || self.is_finished() {
+            out.fill(0.0);
+            return;
+        }
+        let mut position = start;
+        for frame in out.chunks_mut(channels) {
+            let value = match self.samples.get(position) {
+                Some(&current) => {
+                    let next = self.samples.get(position + 1).copied().unwrap_or(current);
+                    current + (next - current) * *phase as f32
+                }
+                None => 0.0,
+            };
+            frame.fill(value);
+            *phase += step;
+            position += *phase as usize;
+            *phase = phase.fract();
+        }
+        // A seek while filling wins over the position reached here
+        let _ = self
+            .position
+            .compare_exchange(start, position, Ordering::SeqCst, Ordering::SeqCst);
+    }
+}
+
+struct Playback {
+    /// History item ID
+    id: String,
+    cursor: Arc<Cursor>,
+}
+
+static CURRENT: Mutex<Option<Playback>> = Mutex::new(None);
+
+/// Run `f` on the recording being played, if any
+fn with_current<T>(f: impl FnOnce(&Playback) -> T) -> Option<T> {
+    let current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    current.as_ref().map(f)
+}
+
+/// Resume `id` if it is the recording loaded and not yet finished
+pub fn resume(id: &str) -> Option<PlaybackState> {
+    with_current(|playback| {
+        if playback.id != id || playback.cursor.is_finished() {
+            return None;
+        }
+        playback.cursor.paused.store(false, Ordering::SeqCst);
+        Some(playback.cursor.state(&playback.id))
+    })
+    .flatten()
+}
+
+/// Unload `cursor` once its stream is closed, unless another recording replaced it
+fn release(cursor: &Arc<Cursor>) {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    if current
+        .as_ref()
+        .is_some_and(|playback| Arc::ptr_eq(&playback.cursor, cursor))
+    {
+        *current = None;
+    }
+}
+
+/// Play decoded 16kHz samples of history item `id` from the start,
+/// stopping whatever was playing
+pub async fn play(app: AppHandle, id: String, samples: Vec<f32>) -> Result<PlaybackState> {
+    stop();
+    let cursor = Arc::new(Cursor::new(samples));
+    // Loaded before the stream opens, so a short recording cannot finish first
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = Some(Playback {
+        id: id.clone(),
+        cursor: cursor.clone(),
+    });
+
+    let (ready_tx, ready_rx) = oneshot::channel();
+    let thread_cursor = cursor.clone();
+    let thread_id = id.clone();
+    std::thread::spawn(move || {
+        let stream = match open_output(thread_cursor.clone()) {
+            Ok(stream) => {
+                let _ = ready_tx.send(Ok(()));
+                stream
+            }
+            Err(e) => {
+                release(&thread_cursor);
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        while !thread_cursor.is_finished() {
+            std::thread::sleep(PROGRESS_INTERVAL);
+            if !thread_cursor.paused.load(Ordering::SeqCst) {
+                let _ = app.emit("playback-state", thread_cursor.state(&thread_id));
+            }
+        }
+        drop(stream);
+        release(&thread_cursor);
+        let _ = app.emit("playback-state", thread_cursor.state(&thread_id));
+    });
+    ready_rx
+        .await
+        .map_err(|_| AppError::Audio("Playback thread exited".to_string()))??;
+
+    let state = cursor.state(&id);
+    log::info!("Playing history recording {}", state.id);
+    Ok(state)
+}
+
+/// Pause the recording being played
+pub fn pause() -> Option<PlaybackState> {
+    with_current(|playback| {
+        playback.cursor.paused.store(true, Ordering::SeqCst);
+        playback.cursor.state(&playback.id)
+    })
+}
+
+/// Jump to `ms` into the recording being played
+pub fn seek(ms: u64) -> Option<PlaybackState> {
+    with_current(|playback| {
+        playback.cursor.seek(ms);
+        playback.cursor.state(&playback.id)
+    })
+}
+
+/// Stop playback and release the output device
+pub fn stop() {
+    if let Some(playback) = CURRENT.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        playback.cursor.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Open and start an output stream playing `cursor`
+pub(crate) fn open_output(cursor: Arc<Cursor>) -> Result<cpal::Stream> {
+    let device = crate::audio::cpal_host()
+        .default_output_device()
+        .ok_or_else(|| AppError::Audio("No output device available".to_string()))?;
+    let config = device.default_output_config()?;
+    let channels = config.channels() as usize;
+    let step = WHISPER_SAMPLE_RATE as f64 / config.sample_rate().0 as f64;
+    let sample_format = config.sample_format();
+    let stream_config: StreamConfig = config.into();
+
+    let stream = match sample_format {
+        SampleFormat::F32 => {
+            build_output_stream::<f32>(&device, &stream_config, cursor, channels, step)?
+        }
+        SampleFormat::I16 => {
+            build_output_stream::<i16>(&device, &stream_config, cursor, channels, step)?
+        }
+        SampleFormat::U16 => {
+            build_output_stream::<u16>(&device, &stream_config, cursor, channels, step)?
+        }
+        SampleFormat::I32 => {
+            build_output_stream::<i32>(&device, &stream_config, cursor, channels, step)?
+        }
+        format => {
+            return Err(AppError::Audio(format!(
+                "Unsupported output sample format: {:?}",
+                format
+            )))
+        }
+    };
+    stream.play()?;
+    Ok(stream)
+}
+
+fn build_output_stream<T: SizedSample + FromSample<f32>>(
+    device: &Device,
+    config: &StreamConfig,
+    cursor: Arc<Cursor>,
+    channels: usize,
+    step: f64,
+) -> Result<cpal::Stream> {
+    let mut phase = 0.0;
+    let mut buffer = Vec::new();
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &_| {
+            buffer.resize(data.len(), 0.0);
+            cursor.fill(&mut buffer, channels, step, &mut phase);
+            for (out, &sample) in data.iter_mut().zip(&buffer) {
+                *out = T::from_sample(sample);
+            }
+        },
+        |err| log::error!("Playback stream error: {}", err),
+        None,
+    )?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_fill() {
+        let cursor = Cursor::new(vec![0.0, 0.2, 0.4, 0.6]);
+        let mut phase = 0.0;
+        // Stereo at twice the recording's rate: each sample is interpolated
+        let mut out = [0.0; 4];
+        cursor.fill(&mut out, 2, 0.5, &mut phase);
+        assert_eq!(out, [0.0, 0.0, 0.1, 0.1]);
+        assert_eq!(cursor.position.load(Ordering::SeqCst), 1);
+
+        cursor.paused.store(true, Ordering::SeqCst);
+        let mut out = [1.0; 2];
+        cursor.fill(&mut out, 1, 1.0, &mut phase);
+        assert_eq!(out, [0.0, 0.0]);
+        assert!(!cursor.state("a").playing);
+
+        cursor.paused.store(false, Ordering::SeqCst);
+        let mut out = [0.0; 4];
+        cursor.fill(&mut out, 1, 1.0, &mut phase);
+        assert_eq!(out, [0.2, 0.4, 0.6, 0.0]);
+        assert!(cursor.state("a").finished);
+
+        // Seeking past the end clamps to it
+        cursor.seek(60_000);
+        assert_eq!(cursor.state("a").position_ms, 0);
+        assert!(cursor.state("a").finished);
+    }
+
+    #[test]
+    fn test_finished_playback_is_unloaded() {
+        let cursor = Arc::new(Cursor::new(vec![0.0; 16]));
+        *CURRENT.lock().unwrap() = Some(Playback {
+            id: "a".to_string(),
+            cursor: cursor.clone(),
+        });
+        // A cursor replaced by a newer recording leaves that one loaded
+        release(&Arc::new(Cursor::new(Vec::new())));
+        assert!(seek(0).is_some());
+
+        // Once its stream closes, controls no longer report it as playing
+        release(&cursor);
+        assert_eq!(seek(0), None);
+        assert_eq!(resume("a"), None);
+    }
+}
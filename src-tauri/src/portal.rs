@@ -0,0 +1,156 @@
+//! Microphone access in sandboxes (Flatpak, Snap)
+//!
+//! When a sandbox denies the microphone, the audio server does not fail the
+//! stream; it delivers digital silence. A real microphone always picks up
+//! some noise, so a recording whose start is all exact zeros usually means
+//! access is blocked (or the device is muted in hardware). Noise gates such
+//! as RNNoise, NoiseTorch or EasyEffects also output exact zeros until
+//! someone speaks, so the recording goes on: access is requested through
+//! the Device portal when sandboxed, and the app warns and says what to do.
+
+use crate::audio::RecordingHandle;
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// How much of a recording may be digital silence before access counts as blocked
+const SIGNAL_CHECK: Duration = Duration::from_millis(1500);
+
+/// Sandbox the app runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sandbox {
+    Flatpak,
+    Snap,
+}
+
+/// Sent with the "mic-blocked" event
+#[derive(Debug, Clone, Serialize)]
+pub struct MicBlocked {
+    pub sandbox: Option<Sandbox>,
+    /// Whether access was requested through the Device portal
+    pub requested: bool,
+    /// How to give the app the microphone
+    pub hint: String,
+}
+
+/// The sandbox the app runs in, if any
+pub fn sandbox() -> Option<Sandbox> {
+    if Path::new("/.flatpak-info").exists() {
+        Some(Sandbox::Flatpak)
+    } else if std::env::var_os("SNAP").is_some() {
+        Some(Sandbox::Snap)
+    } else {
+        None
+    }
+}
+
+/// How to give the app the microphone in `sandbox`
+fn hint(sandbox: Option<Sandbox>, app_id: Option<&str>) -> String {
+    match sandbox {
+        Some(Sandbox::Flatpak) => format!(
+            "Allow WhisperTray to use the microphone in your desktop's privacy settings, or run: \
+             flatpak permission-set devices microphone {} yes",
+            app_id.unwrap_or("<app id>")
+        ),
+        Some(Sandbox::Snap) => format!(
+            "Connect the audio-record interface: snap connect {}:audio-record",
+            app_id.unwrap_or("whispertray")
+        ),
+        None => "The microphone delivers only silence. Check that it is not muted, \
+                 by a hardware switch or in the sound settings. A noise gate also \
+                 does this until you speak."
+            .to_string(),
+    }
+}
+
+/// Ask the Device portal for the microphone; the desktop shows a dialog
+///
+/// The answer is stored by the portal and applies to the next recording.
+pub fn request_access() -> crate::error::Result<()> {
+    let output = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.portal.Desktop",
+            "--object-path",
+            "/org/freedesktop/portal/desktop",
+            "--method",
+            "org.freedesktop.portal.Device.AccessDevice",
+            &std::process::id().to_string(),
+            "['microphone']",
+            "{}",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(crate::error::AppError::Audio(format!(
+            "Device portal request failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Warn if the current recording's microphone delivers only digital silence
+pub fn spawn_signal_check(app: AppHandle, recording: RecordingHandle) {
+    let generation = recording.generation();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SIGNAL_CHECK).await;
+        if !recording.is_recording_generation(generation) || recording.is_paused() || recording.has_signal() {
+            return;
+        }
+
+        let sandbox = sandbox();
+        log::warn!("Microphone delivers only digital silence so far (sandbox: {:?})", sandbox);
+
+        let requested = sandbox == Some(Sandbox::Flatpak)
+            && match tokio::task::spawn_blocking(request_access).await {
+                Ok(Ok(())) => true,
+                Ok(Err(e)) => {
+                    log::warn!("{}", e);
+                    false
+                }
+                Err(_) => false,
+            };
+        let app_id = match sandbox {
+            Some(Sandbox::Flatpak) => std::env::var("FLATPAK_ID").ok(),
+            Some(Sandbox::Snap) => std::env::var("SNAP_NAME").ok(),
+            None => None,
+        };
+        // Outside a sandbox, an app holding the hardware is the likelier cause
+        let holders = match sandbox {
+            None => tokio::task::spawn_blocking(crate::mic_conflict::alsa_capture_holders)
+                .await
+                .unwrap_or_default(),
+            Some(_) => Vec::new(),
+        };
+        let blocked = MicBlocked {
+            sandbox,
+            requested,
+            hint: if holders.is_empty() {
+                hint(sandbox, app_id.as_deref())
+            } else {
+                format!("The microphone is busy: used by {}", holders.join(", "))
+            },
+        };
+        crate::notify::send("No sound from the microphone", &blocked.hint);
+        let _ = app.emit("mic-blocked", &blocked);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint() {
+        assert!(hint(Some(Sandbox::Flatpak), Some("io.github.whispertray")).ends_with(
+            "flatpak permission-set devices microphone io.github.whispertray yes"
+        ));
+        assert!(hint(Some(Sandbox::Snap), None).contains("snap connect whispertray:audio-record"));
+        assert!(hint(None, None).contains("muted"));
+    }
+}
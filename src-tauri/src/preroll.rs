@@ -0,0 +1,204 @@
+//! Pre-roll: the audio from just before the hotkey
+//!
+//! Opening a capture stream takes long enough that the first syllable of a
+//! dictation started right at the hotkey is often lost. With `pre_roll_ms`
+//! set, the microphone stays open between dictations and its last second or
+//! two are kept in a ring buffer in memory. A recording from that microphone
+//! then takes over the open stream, starting with the buffered audio, so
+//! nothing is lost to stream startup either.
+//!
+//! The buffer is never written anywhere: it is overwritten continuously and
+//! dropped whenever pre-roll stops. An open microphone does show in the
+//! desktop's privacy indicator, which is why this is opt-in.
+
+use crate::audio::{
+    ChannelSelect, LevelCallback, RecordingHandle, RecordingTicker, TICK_INTERVAL, WHISPER_SAMPLE_RATE,
+};
+use crate::error::Result;
+use cpal::traits::{DeviceTrait, StreamTrait};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Longest pre-roll kept, in milliseconds
+pub const MAX_PRE_ROLL_MS: u32 = 2000;
+
+/// How often the stream thread checks whether pre-roll was stopped
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An open microphone keeping its last moments in memory
+pub struct PreRoll {
+    device: String,
+    /// Name of the device opened, for reports when it goes away
+    device_name: String,
+    duration_ms: u32,
+    /// Channel the stream was opened for
+    channel: ChannelSelect,
+    handle: RecordingHandle,
+    /// 16kHz mono samples, oldest first
+    buffer: Arc<Mutex<VecDeque<f32>>>,
+    /// Cleared to close the stream, or by the stream when the device fails
+    running: Arc<AtomicBool>,
+    /// Generation of the recording this stream feeds, 0 for none
+    ///
+    /// Other recordings on the handle, such as two-track ones or ones from
+    /// another device, open their own streams and must not get this one.
+    recording: Arc<AtomicU64>,
+}
+
+impl PreRoll {
+    /// Open `device` (empty for the default) and start buffering
+    ///
+    /// While a recording started with `start_recording` runs, the stream
+    /// feeds it instead.
+    pub fn start(device: &str, duration_ms: u32, handle: RecordingHandle) -> Result<Self> {
+        let duration_ms = duration_ms.min(MAX_PRE_ROLL_MS);
+        let capacity = (WHISPER_SAMPLE_RATE as u64 * duration_ms as u64 / 1000) as usize;
+        let input = crate::audio::get_device_by_name(device)?;
+        let channel = handle.channel_select(0);
+        let config = crate::audio::preferred_input_config(&input, channel)?;
+        let device_name = input.name().unwrap_or_default();
+        log::info!("Keeping {}ms of pre-roll from {}", duration_ms, device_name);
+
+        let pre_roll = Self {
+            device: device.to_string(),
+            device_name,
+            duration_ms,
+            channel,
+            handle,
+            buffer: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            running: Arc::new(AtomicBool::new(true)),
+            recording: Arc::new(AtomicU64::new(0)),
+        };
+
+        let handle = pre_roll.handle.clone();
+        let buffer = pre_roll.buffer.clone();
+        let running = pre_roll.running.clone();
+        let (fed, owner) = (pre_roll.recording.clone(), pre_roll.recording.clone());
+        // Stream is not Send, so it lives on its own thread
+        std::thread::spawn(move || {
+            let (failed, recording) = (running.clone(), handle.clone());
+            let stream = crate::audio::build_input_stream(
+                &input,
+                config,
+                move |data, source_rate, channels| {
+                    if is_fed(&handle, &fed) {
+                        handle.push_input(0, data, source_rate, channels);
+                        return;
+                    }
+                    let data = handle.amplify(0, data);
+                    let processed = crate::audio::process_audio_data(
+                        &data,
+                        source_rate,
+                        channels,
+                        handle.channel_select(0),
+                    );
+                    if let Ok(mut buffer) = buffer.lock() {
+                        buffer.extend(processed);
+                        let excess = buffer.len().saturating_sub(capacity);
+                        buffer.drain(..excess);
+                    }
+                },
+                move |err| {
+                    log::warn!("Pre-roll stream failed: {}", err);
+                    failed.store(false, Ordering::SeqCst);
+                    if is_fed(&recording, &owner) {
+                        recording.mark_input_failed(0);
+                    }
+                },
+            );
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn!("Failed to open the pre-roll stream: {}", e);
+                    running.store(false, Ordering::SeqCst);
+                    return;
+                }
+            };
+            if let Err(e) = stream.play() {
+                log::warn!("Failed to start the pre-roll stream: {}", e);
+                running.store(false, Ordering::SeqCst);
+                return;
+            }
+            while running.load(Ordering::SeqCst) {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            drop(stream);
+            log::info!("Pre-roll stopped");
+        });
+
+        Ok(pre_roll)
+    }
+
+    /// Device name as configured, empty for the default
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    pub fn duration_ms(&self) -> u32 {
+        self.duration_ms
+    }
+
+    pub fn channel(&self) -> ChannelSelect {
+        self.channel
+    }
+
+    /// Whether the stream is still open
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Start recording on the open stream, beginning with the buffered audio
+    pub fn start_recording(&self, level_callback: Option<LevelCallback>) {
+        crate::audio::begin_recording(&self.handle, 1);
+        self.recording.store(self.handle.generation(), Ordering::SeqCst);
+        // The stream feeds the recording from here on; what it buffered comes first
+        let buffered: Vec<f32> = self
+            .buffer
+            .lock()
+            .map(|mut buffer| buffer.drain(..).collect())
+            .unwrap_or_default();
+        log::info!(
+            "Recording with {}ms of pre-roll",
+            crate::audio::calculate_duration_ms(buffered.len())
+        );
+        self.handle.prepend_primary(buffered);
+
+        let handle = self.handle.clone();
+        let device_name = self.device_name.clone();
+        std::thread::spawn(move || {
+            // The pre-roll stream stays open, so the recording is done once the ticker stops
+            let _done = crate::audio::CaptureDone::new(&handle);
+            let mut ticker = RecordingTicker::new(handle.clone(), level_callback);
+            // Holds the default device's stream once the pre-roll device is gone
+            let mut fallback = None;
+            while ticker.tick() {
+                std::thread::sleep(TICK_INTERVAL);
+                if !handle.input_stalled(0) {
+                    continue;
+                }
+                let fell_back = fallback.is_some();
+                fallback = None;
+                match crate::audio::recover_primary(&handle, &device_name, fell_back) {
+                    Some(stream) => fallback = Some(stream),
+                    None => break,
+                }
+            }
+        });
+    }
+}
+
+/// Whether the recording running on `handle` is the one the stream feeds
+fn is_fed(handle: &RecordingHandle, recording: &AtomicU64) -> bool {
+    handle.is_recording() && handle.generation() == recording.load(Ordering::SeqCst)
+}
+
+impl Drop for PreRoll {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Ok(mut buffer) = self.buffer.lock() {
+            buffer.clear();
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Priority of live dictations over background work
+//!
+//! A dictation is interactive from the moment recording starts until its
+//! output is delivered. Background jobs (file transcription, journal
+//! summaries) run inside `background` and pause at their next chunk or
+//! request while any dictation is interactive. They then hold the CPU, the
+//! whisper model and provider rate limits for at most the chunk already
+//! running, and a dictation is never queued behind them.
+
+use std::future::Future;
+use std::sync::{Condvar, Mutex, OnceLock, PoisonError};
+use tokio::sync::Notify;
+
+tokio::task_local! {
+    static BACKGROUND: ();
+}
+
+/// Number of interactive dictations in progress
+static ACTIVE: Mutex<usize> = Mutex::new(0);
+/// Signalled to blocking waiters when the last dictation finishes
+static IDLE: Condvar = Condvar::new();
+
+/// Signalled to async waiters when the last dictation finishes
+fn idle_notify() -> &'static Notify {
+    static NOTIFY: OnceLock<Notify> = OnceLock::new();
+    NOTIFY.get_or_init(Notify::new)
+}
+
+/// Marks a dictation as interactive until dropped
+#[derive(Debug)]
+pub struct Interactive {
+    _private: (),
+}
+
+impl Interactive {
+    pub fn begin() -> Self {
+        *ACTIVE.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+        Self { _private: () }
+    }
+}
+
+impl Drop for Interactive {
+    fn drop(&mut self) {
+        let mut active = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+        *active -= 1;
+        if *active == 0 {
+            IDLE.notify_all();
+            idle_notify().notify_waiters();
+        }
+    }
+}
+
+fn is_idle() -> bool {
+    *ACTIVE.lock().unwrap_or_else(PoisonError::into_inner) == 0
+}
+
+/// Run `work` as a background job, yielding to dictations
+pub async fn background<F: Future>(work: F) -> F::Output {
+    BACKGROUND.scope((), work).await
+}
+
+/// Whether the current task is a background job
+///
+/// Capture this before handing work to a blocking thread, where the task's
+/// scope is not visible.
+pub fn is_background() -> bool {
+    BACKGROUND.try_with(|_| ()).is_ok()
+}
+
+/// Wait until no dictation is interactive, when called from background work
+pub async fn yield_to_interactive() {
+    if !is_background() {
+        return;
+    }
+    let mut paused = false;
+    loop {
+        // Register before checking so a dictation ending in between is not missed
+        let idle = idle_notify().notified();
+        tokio::pin!(idle);
+        idle.as_mut().enable();
+        if is_idle() {
+            break;
+        }
+        if !paused {
+            log::info!("Pausing background work for a dictation");
+            paused = true;
+        }
+        idle.await;
+    }
+    if paused {
+        log::info!("Resuming background work");
+    }
+}
+
+/// Wait on a blocking thread until no dictation is interactive
+pub fn wait_until_idle() {
+    let mut active = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    while *active > 0 {
+        active = IDLE.wait(active).unwrap_or_else(PoisonError::into_inner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_background_waits_for_dictation() {
+        // Work outside a background job never waits
+        let dictation = Interactive::begin();
+        assert!(!is_background());
+        yield_to_interactive().await;
+
+        let waiting = tokio::spawn(background(async {
+            assert!(is_background());
+            yield_to_interactive().await;
+        }));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        drop(dictation);
+        tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap();
+
+        // Blocking waiters return once idle too
+        tokio::task::spawn_blocking(wait_until_idle).await.unwrap();
+    }
+}
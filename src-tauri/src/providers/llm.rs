@@ -1,9 +1,58 @@
 //! LLM provider implementations for AI post-processing
 
+use crate::cancel::CancelToken;
 use crate::error::{AppError, Result};
 use crate::modes::LlmProvider as LlmProviderType;
+use crate::providers::sse::{SseEvent, SseParser};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Token counts for one completion
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// True when approximated locally instead of reported by the provider
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    /// Rough count for providers that do not report usage (~4 characters per token)
+    pub fn estimate(prompt: &str, completion: &str) -> Self {
+        let tokens = |text: &str| text.chars().count().div_ceil(4) as u32;
+        Self {
+            prompt_tokens: tokens(prompt),
+            completion_tokens: tokens(completion),
+            estimated: true,
+        }
+    }
+
+    /// Add another completion's usage to this one
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.estimated |= other.estimated;
+    }
+}
+
+/// A completion with its token usage
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub text: String,
+    pub usage: TokenUsage,
+}
+
+/// Receives the text of a streamed completion as it is generated
+pub type DeltaSender = mpsc::Sender<String>;
+
+/// Deltas buffered for a slow reader before the response stops being read
+pub const STREAM_BUFFER: usize = 32;
 
 /// LLM provider trait
 #[async_trait]
@@ -11,10 +60,134 @@ pub trait LlmProvider: Send + Sync {
     /// Generate a completion from the given prompt
     async fn complete(&self, prompt: &str) -> Result<String>;
 
+    /// Generate a completion along with its token usage
+    ///
+    /// Defaults to an estimate; providers that report usage override this.
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion> {
+        let text = self.complete(prompt).await?;
+        let usage = TokenUsage::estimate(prompt, &text);
+        Ok(Completion { text, usage })
+    }
+
+    /// Generate a completion, failing with `AppError::Cancelled` once `cancel` fires
+    ///
+    /// The request future is dropped on cancel, aborting the HTTP request.
+    async fn complete_cancellable(&self, prompt: &str, cancel: &CancelToken) -> Result<Completion> {
+        cancel.run(self.complete_with_usage(prompt)).await
+    }
+
+    /// Generate a completion constrained to JSON matching `schema`
+    ///
+    /// Defaults to asking for JSON in the prompt; providers with a native
+    /// JSON mode override this. The output is not validated here, see
+    /// `complete_structured`.
+    async fn complete_json(&self, prompt: &str, schema: &Value) -> Result<Completion> {
+        self.complete_with_usage(&json_prompt(prompt, schema)).await
+    }
+
+    /// Generate a completion, sending its text to `deltas` as it is generated
+    ///
+    /// Defaults to sending the whole text once done; providers that can
+    /// stream override this. The deltas add up to the untrimmed text.
+    async fn complete_streaming(&self, prompt: &str, deltas: &DeltaSender) -> Result<Completion> {
+        let completion = self.complete_with_usage(prompt).await?;
+        send_delta(deltas, &completion.text).await;
+        Ok(completion)
+    }
+
+    /// Load the model ahead of the first request, where the provider can
+    async fn warm_up(&self) -> Result<()> {
+        Ok(())
+    }
+
     /// Get the provider name
     fn name(&self) -> &str;
 }
 
+/// Append JSON output instructions with the schema to a prompt
+fn json_prompt(prompt: &str, schema: &Value) -> String {
+    format!(
+        "{}\n\nRespond with only a JSON value, no other text, matching this JSON Schema:\n{}",
+        prompt, schema
+    )
+}
+
+/// Map a failed request, flagging connection errors and timeouts so a
+/// fallback provider can be tried
+fn request_error(provider: &str, e: reqwest::Error) -> AppError {
+    if e.is_connect() || e.is_timeout() {
+        AppError::ProviderUnavailable(format!("{}: {}", provider, e))
+    } else {
+        AppError::Provider(format!("{} request failed: {}", provider, e))
+    }
+}
+
+/// Pass a delta on, waiting while the reader is behind
+///
+/// While this waits the response body is not read, so a slow reader holds
+/// back the stream instead of growing a buffer. A dropped reader is not an
+/// error; the completion still finishes.
+async fn send_delta(deltas: &DeltaSender, delta: &str) {
+    if !delta.is_empty() {
+        let _ = deltas.send(delta.to_string()).await;
+    }
+}
+
+/// What one event of a streamed response contributed
+#[derive(Debug, Default, PartialEq)]
+struct StreamUpdate {
+    delta: Option<String>,
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+    /// The provider signalled the end of the response
+    done: bool,
+}
+
+/// Read a streamed response, passing text on to `deltas`
+///
+/// `parse` turns each server-sent event into an update; usage the provider
+/// does not report is estimated.
+async fn read_stream(
+    provider: &str,
+    prompt: &str,
+    mut response: reqwest::Response,
+    deltas: &DeltaSender,
+    parse: fn(&SseEvent) -> Result<StreamUpdate>,
+) -> Result<Completion> {
+    let mut parser = SseParser::new();
+    let mut text = String::new();
+    let (mut prompt_tokens, mut completion_tokens) = (None, None);
+
+    'read: while let Some(chunk) = response.chunk().await.map_err(|e| request_error(provider, e))? {
+        for event in parser.push(&chunk) {
+            let update = parse(&event)?;
+            if let Some(delta) = update.delta {
+                send_delta(deltas, &delta).await;
+                text.push_str(&delta);
+            }
+            prompt_tokens = update.prompt_tokens.or(prompt_tokens);
+            completion_tokens = update.completion_tokens.or(completion_tokens);
+            if update.done {
+                break 'read;
+            }
+        }
+    }
+
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err(AppError::Provider(format!("No response from {}", provider)));
+    }
+    let usage = match (prompt_tokens, completion_tokens) {
+        (Some(prompt_tokens), Some(completion_tokens)) => TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            estimated: false,
+        },
+        _ => TokenUsage::estimate(prompt, &text),
+    };
+    Ok(Completion { text, usage })
+}
+
 /// Ollama provider for local LLM inference
 pub struct OllamaProvider {
     base_url: String,
@@ -37,16 +210,45 @@ struct OllamaRequest {
     model: String,
     prompt: String,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
 }
 
 #[derive(Deserialize)]
 struct OllamaResponse {
     response: String,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
 }
 
 #[async_trait]
 impl LlmProvider for OllamaProvider {
     async fn complete(&self, prompt: &str) -> Result<String> {
+        Ok(self.complete_with_usage(prompt).await?.text)
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion> {
+        self.request(prompt, None).await
+    }
+
+    async fn complete_json(&self, prompt: &str, schema: &Value) -> Result<Completion> {
+        self.request(&json_prompt(prompt, schema), Some(json!("json"))).await
+    }
+
+    async fn warm_up(&self) -> Result<()> {
+        // An empty prompt only loads the model into memory
+        self.request("", None).await.map(|_| ())
+    }
+
+    fn name(&self) -> &str {
+        "Ollama"
+    }
+}
+
+impl OllamaProvider {
+    async fn request(&self, prompt: &str, format: Option<Value>) -> Result<Completion> {
         let client = reqwest::Client::new();
         let url = format!("{}/api/generate", self.base_url);
 
@@ -54,6 +256,7 @@ impl LlmProvider for OllamaProvider {
             model: self.model.clone(),
             prompt: prompt.to_string(),
             stream: false,
+            format,
         };
 
         let response = client
@@ -62,15 +265,16 @@ impl LlmProvider for OllamaProvider {
             .timeout(std::time::Duration::from_secs(120))
             .send()
             .await
-            .map_err(|e| AppError::Provider(format!("Ollama request failed: {}", e)))?;
+            .map_err(|e| request_error("Ollama", e))?;
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(AppError::Provider(format!(
-                "Ollama error ({}): {}",
-                status, body
-            )));
+            return Err(AppError::Status {
+                provider: "Ollama".to_string(),
+                status: status.as_u16(),
+                body,
+            });
         }
 
         let result: OllamaResponse = response
@@ -78,11 +282,19 @@ impl LlmProvider for OllamaProvider {
             .await
             .map_err(|e| AppError::Provider(format!("Failed to parse Ollama response: {}", e)))?;
 
-        Ok(result.response.trim().to_string())
-    }
+        let text = result.response.trim().to_string();
 
-    fn name(&self) -> &str {
-        "Ollama"
+        // Ollama reports counts in most versions; fall back to an estimate
+        let usage = match (result.prompt_eval_count, result.eval_count) {
+            (Some(prompt_tokens), Some(completion_tokens)) => TokenUsage {
+                prompt_tokens,
+                completion_tokens,
+                estimated: false,
+            },
+            _ => TokenUsage::estimate(prompt, &text),
+        };
+
+        Ok(Completion { text, usage })
     }
 }
 
@@ -103,6 +315,12 @@ struct OpenAiRequest {
     model: String,
     messages: Vec<OpenAiMessage>,
     max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<Value>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<Value>,
 }
 
 #[derive(Serialize)]
@@ -114,6 +332,14 @@ struct OpenAiMessage {
 #[derive(Deserialize)]
 struct OpenAiResponse {
     choices: Vec<OpenAiChoice>,
+    #[serde(default)]
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
 }
 
 #[derive(Deserialize)]
@@ -126,9 +352,105 @@ struct OpenAiMessageResponse {
     content: String,
 }
 
+/// One event of a streamed chat completion
+#[derive(Deserialize)]
+struct OpenAiChunk {
+    #[serde(default)]
+    choices: Vec<OpenAiChunkChoice>,
+    /// Only on the last event, when requested with `include_usage`
+    #[serde(default)]
+    usage: Option<OpenAiUsage>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChunkChoice {
+    #[serde(default)]
+    delta: OpenAiDelta,
+}
+
+#[derive(Deserialize, Default)]
+struct OpenAiDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+fn openai_stream_event(event: &SseEvent) -> Result<StreamUpdate> {
+    if event.data == "[DONE]" {
+        return Ok(StreamUpdate {
+            done: true,
+            ..Default::default()
+        });
+    }
+    let chunk: OpenAiChunk = serde_json::from_str(&event.data)
+        .map_err(|e| AppError::Provider(format!("Failed to parse OpenAI stream: {}", e)))?;
+    if let Some(error) = chunk.error {
+        return Err(AppError::Provider(format!("OpenAI stream error: {}", error)));
+    }
+    Ok(StreamUpdate {
+        delta: chunk.choices.into_iter().next().and_then(|c| c.delta.content),
+        prompt_tokens: chunk.usage.as_ref().map(|u| u.prompt_tokens),
+        completion_tokens: chunk.usage.as_ref().map(|u| u.completion_tokens),
+        done: false,
+    })
+}
+
 #[async_trait]
 impl LlmProvider for OpenAiProvider {
     async fn complete(&self, prompt: &str) -> Result<String> {
+        Ok(self.complete_with_usage(prompt).await?.text)
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion> {
+        self.request(prompt, None).await
+    }
+
+    async fn complete_json(&self, prompt: &str, schema: &Value) -> Result<Completion> {
+        // JSON mode requires the prompt to mention JSON, which json_prompt does
+        let response_format = json!({ "type": "json_object" });
+        self.request(&json_prompt(prompt, schema), Some(response_format)).await
+    }
+
+    async fn complete_streaming(&self, prompt: &str, deltas: &DeltaSender) -> Result<Completion> {
+        let response = self.send(prompt, None, true).await?;
+        read_stream("OpenAI", prompt, response, deltas, openai_stream_event).await
+    }
+
+    fn name(&self) -> &str {
+        "OpenAI"
+    }
+}
+
+impl OpenAiProvider {
+    async fn request(&self, prompt: &str, response_format: Option<Value>) -> Result<Completion> {
+        let response = self.send(prompt, response_format, false).await?;
+
+        let result: OpenAiResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::Provider(format!("Failed to parse OpenAI response: {}", e)))?;
+
+        let text = result
+            .choices
+            .first()
+            .map(|c| c.message.content.trim().to_string())
+            .ok_or_else(|| AppError::Provider("No response from OpenAI".to_string()))?;
+
+        let usage = match result.usage {
+            Some(usage) => TokenUsage {
+                prompt_tokens: usage.prompt_tokens,
+                completion_tokens: usage.completion_tokens,
+                estimated: false,
+            },
+            None => TokenUsage::estimate(prompt, &text),
+        };
+
+        Ok(Completion { text, usage })
+    }
+
+    /// Send a chat completion request, failing on an error status
+    async fn send(&self, prompt: &str, response_format: Option<Value>, stream: bool) -> Result<reqwest::Response> {
         let client = reqwest::Client::new();
         let url = "https://api.openai.com/v1/chat/completions";
 
@@ -139,6 +461,10 @@ impl LlmProvider for OpenAiProvider {
                 content: prompt.to_string(),
             }],
             max_tokens: 2048,
+            response_format,
+            stream,
+            // Usage arrives in a final event only when asked for
+            stream_options: stream.then(|| json!({ "include_usage": true })),
         };
 
         let response = client
@@ -149,31 +475,19 @@ impl LlmProvider for OpenAiProvider {
             .timeout(std::time::Duration::from_secs(60))
             .send()
             .await
-            .map_err(|e| AppError::Provider(format!("OpenAI request failed: {}", e)))?;
+            .map_err(|e| request_error("OpenAI", e))?;
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(AppError::Provider(format!(
-                "OpenAI error ({}): {}",
-                status, body
-            )));
+            return Err(AppError::Status {
+                provider: "OpenAI".to_string(),
+                status: status.as_u16(),
+                body,
+            });
         }
 
-        let result: OpenAiResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::Provider(format!("Failed to parse OpenAI response: {}", e)))?;
-
-        result
-            .choices
-            .first()
-            .map(|c| c.message.content.trim().to_string())
-            .ok_or_else(|| AppError::Provider("No response from OpenAI".to_string()))
-    }
-
-    fn name(&self) -> &str {
-        "OpenAI"
+        Ok(response)
     }
 }
 
@@ -194,6 +508,12 @@ struct AnthropicRequest {
     model: String,
     max_tokens: u32,
     messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
 #[derive(Serialize)]
@@ -205,16 +525,177 @@ struct AnthropicMessage {
 #[derive(Deserialize)]
 struct AnthropicResponse {
     content: Vec<AnthropicContent>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicUsage {
+    input_tokens: u32,
+    output_tokens: u32,
 }
 
 #[derive(Deserialize)]
 struct AnthropicContent {
-    text: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+    /// Arguments of a `tool_use` block
+    #[serde(default)]
+    input: Option<Value>,
 }
 
+/// One event of a streamed message
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicStreamEvent {
+    MessageStart {
+        message: AnthropicStreamMessage,
+    },
+    ContentBlockDelta {
+        delta: AnthropicDelta,
+    },
+    MessageDelta {
+        #[serde(default)]
+        usage: Option<AnthropicDeltaUsage>,
+    },
+    MessageStop,
+    Error {
+        error: AnthropicStreamError,
+    },
+    /// Pings and block boundaries
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct AnthropicStreamMessage {
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicDelta {
+    /// Set on `text_delta`s only
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicDeltaUsage {
+    output_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct AnthropicStreamError {
+    #[serde(rename = "type")]
+    kind: String,
+    message: String,
+}
+
+fn anthropic_stream_event(event: &SseEvent) -> Result<StreamUpdate> {
+    let parsed: AnthropicStreamEvent = serde_json::from_str(&event.data)
+        .map_err(|e| AppError::Provider(format!("Failed to parse Anthropic stream: {}", e)))?;
+    Ok(match parsed {
+        AnthropicStreamEvent::MessageStart { message } => StreamUpdate {
+            prompt_tokens: message.usage.map(|u| u.input_tokens),
+            ..Default::default()
+        },
+        AnthropicStreamEvent::ContentBlockDelta { delta } => StreamUpdate {
+            delta: delta.text,
+            ..Default::default()
+        },
+        AnthropicStreamEvent::MessageDelta { usage } => StreamUpdate {
+            completion_tokens: usage.map(|u| u.output_tokens),
+            ..Default::default()
+        },
+        AnthropicStreamEvent::MessageStop => StreamUpdate {
+            done: true,
+            ..Default::default()
+        },
+        AnthropicStreamEvent::Error { error } => {
+            return Err(AppError::Provider(format!(
+                "Anthropic stream error ({}): {}",
+                error.kind, error.message
+            )))
+        }
+        AnthropicStreamEvent::Other => StreamUpdate::default(),
+    })
+}
+
+/// Tool the model is forced to call to return structured output
+const ANTHROPIC_JSON_TOOL: &str = "respond";
+
 #[async_trait]
 impl LlmProvider for AnthropicProvider {
     async fn complete(&self, prompt: &str) -> Result<String> {
+        Ok(self.complete_with_usage(prompt).await?.text)
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion> {
+        self.request(prompt, None).await
+    }
+
+    async fn complete_json(&self, prompt: &str, schema: &Value) -> Result<Completion> {
+        // Tool input schemas must describe an object; prompt for anything else
+        if schema.get("type").and_then(Value::as_str) != Some("object") {
+            return self.complete_with_usage(&json_prompt(prompt, schema)).await;
+        }
+        self.request(prompt, Some(schema)).await
+    }
+
+    async fn complete_streaming(&self, prompt: &str, deltas: &DeltaSender) -> Result<Completion> {
+        let response = self.send(prompt, None, true).await?;
+        read_stream("Anthropic", prompt, response, deltas, anthropic_stream_event).await
+    }
+
+    fn name(&self) -> &str {
+        "Anthropic"
+    }
+}
+
+impl AnthropicProvider {
+    /// Send a message; with a schema, the model is forced to answer through a
+    /// tool whose input is that schema, and the tool input is returned as JSON
+    async fn request(&self, prompt: &str, schema: Option<&Value>) -> Result<Completion> {
+        let response = self.send(prompt, schema, false).await?;
+
+        let result: AnthropicResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::Provider(format!("Failed to parse Anthropic response: {}", e)))?;
+
+        let text = if schema.is_some() {
+            result
+                .content
+                .iter()
+                .find(|c| c.kind == "tool_use")
+                .and_then(|c| c.input.as_ref())
+                .map(|input| input.to_string())
+        } else {
+            result
+                .content
+                .iter()
+                .find_map(|c| c.text.as_deref())
+                .map(|text| text.trim().to_string())
+        }
+        .ok_or_else(|| AppError::Provider("No response from Anthropic".to_string()))?;
+
+        let usage = match result.usage {
+            Some(usage) => TokenUsage {
+                prompt_tokens: usage.input_tokens,
+                completion_tokens: usage.output_tokens,
+                estimated: false,
+            },
+            None => TokenUsage::estimate(prompt, &text),
+        };
+
+        Ok(Completion { text, usage })
+    }
+
+    /// Send a messages request, failing on an error status
+    async fn send(&self, prompt: &str, schema: Option<&Value>, stream: bool) -> Result<reqwest::Response> {
         let client = reqwest::Client::new();
         let url = "https://api.anthropic.com/v1/messages";
 
@@ -225,6 +706,15 @@ impl LlmProvider for AnthropicProvider {
                 role: "user".to_string(),
                 content: prompt.to_string(),
             }],
+            tools: schema.map(|schema| {
+                vec![json!({
+                    "name": ANTHROPIC_JSON_TOOL,
+                    "description": "Respond with the requested structured data",
+                    "input_schema": schema,
+                })]
+            }),
+            tool_choice: schema.map(|_| json!({ "type": "tool", "name": ANTHROPIC_JSON_TOOL })),
+            stream,
         };
 
         let response = client
@@ -236,31 +726,253 @@ impl LlmProvider for AnthropicProvider {
             .timeout(std::time::Duration::from_secs(60))
             .send()
             .await
-            .map_err(|e| AppError::Provider(format!("Anthropic request failed: {}", e)))?;
+            .map_err(|e| request_error("Anthropic", e))?;
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(AppError::Provider(format!(
-                "Anthropic error ({}): {}",
-                status, body
-            )));
+            return Err(AppError::Status {
+                provider: "Anthropic".to_string(),
+                status: status.as_u16(),
+                body,
+            });
         }
 
-        let result: AnthropicResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::Provider(format!("Failed to parse Anthropic response: {}", e)))?;
+        Ok(response)
+    }
+}
 
-        result
-            .content
-            .first()
-            .map(|c| c.text.trim().to_string())
-            .ok_or_else(|| AppError::Provider("No response from Anthropic".to_string()))
+/// Time each non-final provider in a fallback chain gets before the next is tried
+const FALLBACK_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Tries providers in order, moving on when one is unreachable or times out
+///
+/// Other errors (bad API key, malformed response) are returned as-is, since
+/// another provider would not fix them.
+pub struct FallbackLlmProvider {
+    providers: Vec<Box<dyn LlmProvider>>,
+    attempt_timeout: Duration,
+    used: Mutex<Option<usize>>,
+}
+
+impl FallbackLlmProvider {
+    pub fn new(providers: Vec<Box<dyn LlmProvider>>) -> Self {
+        Self {
+            providers,
+            attempt_timeout: FALLBACK_ATTEMPT_TIMEOUT,
+            used: Mutex::new(None),
+        }
+    }
+
+    pub fn with_attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = timeout;
+        self
+    }
+
+    /// Index of the provider that produced the last completion
+    pub fn used_index(&self) -> Option<usize> {
+        self.used.lock().ok().and_then(|used| *used)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for FallbackLlmProvider {
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        Ok(self.complete_with_usage(prompt).await?.text)
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion> {
+        self.attempt(prompt, Request::Text).await
+    }
+
+    async fn complete_json(&self, prompt: &str, schema: &Value) -> Result<Completion> {
+        self.attempt(prompt, Request::Json(schema)).await
+    }
+
+    async fn complete_streaming(&self, prompt: &str, deltas: &DeltaSender) -> Result<Completion> {
+        self.attempt(prompt, Request::Streaming(deltas)).await
     }
 
     fn name(&self) -> &str {
-        "Anthropic"
+        "Fallback chain"
+    }
+}
+
+/// Kind of completion a fallback chain is asked for
+#[derive(Clone, Copy)]
+enum Request<'a> {
+    Text,
+    Json(&'a Value),
+    Streaming(&'a DeltaSender),
+}
+
+impl FallbackLlmProvider {
+    async fn attempt(&self, prompt: &str, request: Request<'_>) -> Result<Completion> {
+        let mut last_error = AppError::Provider("No LLM provider configured".to_string());
+
+        for (i, provider) in self.providers.iter().enumerate() {
+            let is_last = i + 1 == self.providers.len();
+            let timeout = (!is_last).then_some(self.attempt_timeout);
+            let streamed = AtomicBool::new(false);
+            let result = match request {
+                Request::Text => within(timeout, provider.name(), provider.complete_with_usage(prompt), || false).await,
+                Request::Json(schema) => {
+                    within(timeout, provider.name(), provider.complete_json(prompt, schema), || false).await
+                }
+                Request::Streaming(deltas) => {
+                    let (sender, mut receiver) = mpsc::channel(STREAM_BUFFER);
+                    let request = async move { provider.complete_streaming(prompt, &sender).await };
+                    let forward = async {
+                        while let Some(delta) = receiver.recv().await {
+                            streamed.store(true, Ordering::SeqCst);
+                            let _ = deltas.send(delta).await;
+                        }
+                    };
+                    let streaming = async { tokio::join!(request, forward).0 };
+                    // Only waiting for the first delta is timed; a stream that
+                    // has started is left to finish
+                    within(timeout, provider.name(), streaming, || streamed.load(Ordering::SeqCst)).await
+                }
+            };
+
+            match result {
+                Ok(completion) => {
+                    if let Ok(mut used) = self.used.lock() {
+                        *used = Some(i);
+                    }
+                    return Ok(completion);
+                }
+                // Starting over once text was passed on would repeat it
+                Err(e) if streamed.load(Ordering::SeqCst) => return Err(e),
+                Err(AppError::ProviderUnavailable(e)) => {
+                    log::warn!("LLM provider unavailable ({}), trying next", e);
+                    last_error = AppError::ProviderUnavailable(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+/// Run `request`, giving up after `timeout` unless `started` reports that
+/// output was already passed on by then
+async fn within(
+    timeout: Option<Duration>,
+    name: &str,
+    request: impl Future<Output = Result<Completion>>,
+    started: impl Fn() -> bool,
+) -> Result<Completion> {
+    let Some(timeout) = timeout else {
+        return request.await;
+    };
+    tokio::pin!(request);
+    let expired = async {
+        tokio::time::sleep(timeout).await;
+        if started() {
+            std::future::pending::<()>().await;
+        }
+    };
+    tokio::select! {
+        result = &mut request => result,
+        () = expired => Err(AppError::ProviderUnavailable(format!("{}: timed out", name))),
+    }
+}
+
+/// Attempts at getting schema-valid JSON before giving up
+const MAX_JSON_ATTEMPTS: usize = 3;
+
+/// Request JSON matching `schema`, retrying with the validation errors when
+/// the response is not valid
+///
+/// Returns the JSON re-serialized, with usage summed over all attempts.
+pub async fn complete_structured(
+    provider: &dyn LlmProvider,
+    prompt: &str,
+    schema: &Value,
+    cancel: &CancelToken,
+) -> Result<Completion> {
+    let mut usage = TokenUsage::default();
+    let mut request = prompt.to_string();
+    let mut errors = Vec::new();
+
+    for attempt in 1..=MAX_JSON_ATTEMPTS {
+        let completion = cancel.run(provider.complete_json(&request, schema)).await?;
+        usage.add(&completion.usage);
+
+        errors = match crate::json_schema::parse_output(&completion.text) {
+            Ok(value) => {
+                let errors = crate::json_schema::validate(&value, schema);
+                if errors.is_empty() {
+                    let text = serde_json::to_string_pretty(&value)?;
+                    return Ok(Completion { text, usage });
+                }
+                errors
+            }
+            Err(e) => vec![e],
+        };
+        log::warn!(
+            "Invalid JSON from LLM (attempt {}/{}): {}",
+            attempt,
+            MAX_JSON_ATTEMPTS,
+            errors.join("; ")
+        );
+
+        request = format!(
+            "{}\n\nYour previous response was:\n{}\n\nIt was invalid: {}\nRespond again with corrected JSON only.",
+            prompt,
+            completion.text,
+            errors.join("; ")
+        );
+    }
+
+    Err(AppError::Provider(format!(
+        "LLM returned invalid JSON after {} attempts: {}",
+        MAX_JSON_ATTEMPTS,
+        errors.join("; ")
+    )))
+}
+
+/// USD per million (prompt, completion) tokens, matched by model name prefix
+const PRICING: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("o3-mini", 1.10, 4.40),
+    ("o4-mini", 1.10, 4.40),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-haiku-4", 1.00, 5.00),
+    ("claude-3-5-sonnet", 3.00, 15.00),
+    ("claude-3-7-sonnet", 3.00, 15.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-3-opus", 15.00, 75.00),
+    ("claude-opus-4", 15.00, 75.00),
+];
+
+/// Estimated cost of a completion in USD
+///
+/// Local Ollama models are free; unknown cloud models return None.
+pub fn estimate_cost_usd(provider: &LlmProviderType, model: &str, usage: &TokenUsage) -> Option<f64> {
+    match provider {
+        LlmProviderType::Ollama => Some(0.0),
+        LlmProviderType::OpenAI | LlmProviderType::Anthropic => {
+            let (_, prompt_price, completion_price) = PRICING
+                .iter()
+                .filter(|(prefix, _, _)| model.starts_with(prefix))
+                .max_by_key(|(prefix, _, _)| prefix.len())?;
+            Some(
+                (usage.prompt_tokens as f64 * prompt_price
+                    + usage.completion_tokens as f64 * completion_price)
+                    / 1_000_000.0,
+            )
+        }
+        LlmProviderType::Custom(_) => None,
     }
 }
 
@@ -271,34 +983,252 @@ pub fn create_llm_provider(
     api_key: Option<&str>,
     server_url: Option<String>,
 ) -> Result<Box<dyn LlmProvider>> {
-    match provider_type {
-        LlmProviderType::Ollama => Ok(Box::new(OllamaProvider::new(model.to_string(), server_url))),
+    let provider: Box<dyn LlmProvider> = match provider_type {
+        LlmProviderType::Ollama => Box::new(OllamaProvider::new(model.to_string(), server_url)),
         LlmProviderType::OpenAI => {
             let key = api_key
                 .ok_or_else(|| AppError::Provider("OpenAI API key required".to_string()))?;
-            Ok(Box::new(OpenAiProvider::new(
+            Box::new(OpenAiProvider::new(
                 key.to_string(),
                 model.to_string(),
-            )))
+            ))
         }
         LlmProviderType::Anthropic => {
             let key = api_key
                 .ok_or_else(|| AppError::Provider("Anthropic API key required".to_string()))?;
-            Ok(Box::new(AnthropicProvider::new(
+            Box::new(AnthropicProvider::new(
                 key.to_string(),
                 model.to_string(),
-            )))
+            ))
         }
         LlmProviderType::Custom(name) => {
-            Err(AppError::Provider(format!("Unknown LLM provider: {}", name)))
+            return Err(AppError::Provider(format!("Unknown LLM provider: {}", name)))
         }
-    }
+    };
+    Ok(Box::new(crate::retry::Retrying(provider)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    struct StubProvider {
+        result: fn() -> Result<String>,
+    }
+
+    #[async_trait]
+    impl LlmProvider for StubProvider {
+        async fn complete(&self, _prompt: &str) -> Result<String> {
+            (self.result)()
+        }
+
+        fn name(&self) -> &str {
+            "Stub"
+        }
+    }
+
+    fn stub(result: fn() -> Result<String>) -> Box<dyn LlmProvider> {
+        Box::new(StubProvider { result })
+    }
+
+    /// Streams one delta, then finishes slowly with `result`
+    struct SlowStreamProvider {
+        result: fn() -> Result<String>,
+    }
+
+    #[async_trait]
+    impl LlmProvider for SlowStreamProvider {
+        async fn complete(&self, _prompt: &str) -> Result<String> {
+            (self.result)()
+        }
+
+        async fn complete_streaming(&self, prompt: &str, deltas: &DeltaSender) -> Result<Completion> {
+            send_delta(deltas, "Hello").await;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.complete_with_usage(prompt).await
+        }
+
+        fn name(&self) -> &str {
+            "Slow stream"
+        }
+    }
+
+    async fn stream_chain(result: fn() -> Result<String>) -> (Result<Completion>, Vec<String>, Option<usize>) {
+        let chain = FallbackLlmProvider::new(vec![
+            Box::new(SlowStreamProvider { result }),
+            stub(|| Ok("from fallback".to_string())),
+        ])
+        .with_attempt_timeout(Duration::from_millis(10));
+        let (sender, mut receiver) = mpsc::channel(STREAM_BUFFER);
+        let result = chain.complete_streaming("prompt", &sender).await;
+        drop(sender);
+        let mut deltas = Vec::new();
+        while let Some(delta) = receiver.recv().await {
+            deltas.push(delta);
+        }
+        (result, deltas, chain.used_index())
+    }
+
+    /// Returns the given responses in order, recording the prompts it saw
+    struct SequenceProvider {
+        responses: Mutex<Vec<&'static str>>,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl LlmProvider for SequenceProvider {
+        async fn complete(&self, prompt: &str) -> Result<String> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            Ok(self.responses.lock().unwrap().remove(0).to_string())
+        }
+
+        fn name(&self) -> &str {
+            "Sequence"
+        }
+    }
+
+    fn sequence(responses: Vec<&'static str>) -> SequenceProvider {
+        SequenceProvider {
+            responses: Mutex::new(responses),
+            prompts: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn task_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": { "title": { "type": "string" } },
+            "required": ["title"]
+        })
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let usage = TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+            estimated: false,
+        };
+        let cost = estimate_cost_usd(&LlmProviderType::OpenAI, "gpt-4o-mini-2024-07-18", &usage).unwrap();
+        assert!((cost - 0.21).abs() < 1e-9);
+        assert_eq!(estimate_cost_usd(&LlmProviderType::Ollama, "llama3.2", &usage), Some(0.0));
+        assert_eq!(estimate_cost_usd(&LlmProviderType::OpenAI, "unknown-model", &usage), None);
+    }
+
+    #[test]
+    fn test_estimate_usage() {
+        let usage = TokenUsage::estimate("abcdefgh", "abc");
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens), (2, 1));
+        assert!(usage.estimated);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_on_unavailable() {
+        let chain = FallbackLlmProvider::new(vec![
+            stub(|| Err(AppError::ProviderUnavailable("connection refused".to_string()))),
+            stub(|| Ok("from fallback".to_string())),
+        ]);
+        assert_eq!(chain.complete("prompt").await.unwrap(), "from fallback");
+        assert_eq!(chain.used_index(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_no_fallback_on_other_errors() {
+        let chain = FallbackLlmProvider::new(vec![
+            stub(|| Err(AppError::Provider("invalid API key".to_string()))),
+            stub(|| Ok("from fallback".to_string())),
+        ]);
+        assert!(matches!(chain.complete("prompt").await, Err(AppError::Provider(_))));
+        assert_eq!(chain.used_index(), None);
+    }
+
+    #[tokio::test]
+    async fn test_started_stream_outlives_attempt_timeout() {
+        let (result, deltas, used) = stream_chain(|| Ok("Hello".to_string())).await;
+        assert_eq!(result.unwrap().text, "Hello");
+        assert_eq!(deltas, vec!["Hello"]);
+        assert_eq!(used, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_no_fallback_once_streamed() {
+        let (result, deltas, used) =
+            stream_chain(|| Err(AppError::ProviderUnavailable("connection reset".to_string()))).await;
+        assert!(matches!(result, Err(AppError::ProviderUnavailable(_))));
+        assert_eq!(deltas, vec!["Hello"]);
+        assert_eq!(used, None);
+    }
+
+    #[tokio::test]
+    async fn test_structured_retries_until_valid() {
+        let provider = sequence(vec!["Sure!", r#"{"name": "x"}"#, r#"{"title": "Buy milk"}"#]);
+        let completion = complete_structured(&provider, "Extract", &task_schema(), &CancelToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(serde_json::from_str::<Value>(&completion.text).unwrap(), json!({ "title": "Buy milk" }));
+        let prompts = provider.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 3);
+        assert!(prompts[2].contains("missing required property \"title\""));
+    }
+
+    #[tokio::test]
+    async fn test_structured_gives_up() {
+        let provider = sequence(vec!["[]", "[]", "[]"]);
+        let result = complete_structured(&provider, "Extract", &task_schema(), &CancelToken::new()).await;
+        assert!(matches!(result, Err(AppError::Provider(e)) if e.contains("after 3 attempts")));
+    }
+
+    fn data(data: &str) -> SseEvent {
+        SseEvent {
+            event: None,
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_openai_stream_events() {
+        let update = openai_stream_event(&data(r#"{"choices":[{"index":0,"delta":{"content":"Hel"}}]}"#)).unwrap();
+        assert_eq!(update.delta.as_deref(), Some("Hel"));
+
+        let usage = r#"{"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":3,"total_tokens":15}}"#;
+        let update = openai_stream_event(&data(usage)).unwrap();
+        assert_eq!((update.prompt_tokens, update.completion_tokens), (Some(12), Some(3)));
+
+        assert!(openai_stream_event(&data("[DONE]")).unwrap().done);
+        assert!(openai_stream_event(&data(r#"{"error":{"message":"boom"}}"#)).is_err());
+    }
+
+    #[test]
+    fn test_anthropic_stream_events() {
+        let start = r#"{"type":"message_start","message":{"id":"m","usage":{"input_tokens":20,"output_tokens":1}}}"#;
+        assert_eq!(anthropic_stream_event(&data(start)).unwrap().prompt_tokens, Some(20));
+
+        let delta = r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#;
+        assert_eq!(anthropic_stream_event(&data(delta)).unwrap().delta.as_deref(), Some("Hi"));
+
+        let end = r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":7}}"#;
+        assert_eq!(anthropic_stream_event(&data(end)).unwrap().completion_tokens, Some(7));
+
+        assert_eq!(anthropic_stream_event(&data(r#"{"type":"ping"}"#)).unwrap(), StreamUpdate::default());
+        assert!(anthropic_stream_event(&data(r#"{"type":"message_stop"}"#)).unwrap().done);
+
+        let error = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        assert!(matches!(anthropic_stream_event(&data(error)), Err(AppError::Provider(e)) if e.contains("overloaded_error")));
+    }
+
+    #[tokio::test]
+    async fn test_default_streaming_sends_whole_text() {
+        let provider = StubProvider { result: || Ok("Done".to_string()) };
+        let (deltas, mut receiver) = mpsc::channel(STREAM_BUFFER);
+        let completion = provider.complete_streaming("prompt", &deltas).await.unwrap();
+        drop(deltas);
+
+        assert_eq!(completion.text, "Done");
+        assert_eq!(receiver.recv().await.as_deref(), Some("Done"));
+        assert_eq!(receiver.recv().await, None);
+    }
+
     #[test]
     fn test_ollama_provider_creation() {
         let provider = OllamaProvider::new("llama3.2".to_string());
@@ -1,7 +1,9 @@
 //! Provider interfaces for STT and LLM services
 
 pub mod llm;
+pub mod sse;
 pub mod stt;
+pub mod wyoming;
 
 pub use llm::LlmProvider;
 pub use stt::SttProvider;
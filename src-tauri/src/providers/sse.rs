@@ -0,0 +1,118 @@
+//! Server-sent events parsing for streamed LLM responses
+
+/// One server-sent event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The `event:` field, when the server names its events
+    pub event: Option<String>,
+    /// The `data:` lines, joined with newlines
+    pub data: String,
+}
+
+/// Incremental parser fed with response body chunks as they arrive
+///
+/// Chunks may split lines, events or UTF-8 characters anywhere; the
+/// incomplete tail is kept until the rest arrives.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Option<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a chunk, returning the events it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        // A newline byte never occurs inside a multi-byte character
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            // A blank line ends the event
+            if line.is_empty() {
+                match self.data.take() {
+                    Some(data) => events.push(SseEvent {
+                        event: self.event.take(),
+                        data,
+                    }),
+                    None => self.event = None,
+                }
+                continue;
+            }
+            // Comments, used as keep-alives
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => match &mut self.data {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(value);
+                    }
+                    None => self.data = Some(value.to_string()),
+                },
+                // `id` and `retry` only matter for reconnecting
+                _ => {}
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events() {
+        let mut parser = SseParser::new();
+        let events = parser.push(
+            b"event: message_start\ndata: {\"a\":1}\n\n: ping\n\ndata: first\ndata: second\r\n\r\n",
+        );
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("message_start".to_string()),
+                    data: "{\"a\":1}".to_string(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "first\nsecond".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_split_chunks() {
+        let body = "data: héllo\n\ndata: [DONE]\n\n".as_bytes();
+        let mut parser = SseParser::new();
+        let mut events = Vec::new();
+        // One byte at a time, splitting the two-byte character
+        for byte in body {
+            events.extend(parser.push(std::slice::from_ref(byte)));
+        }
+        let data: Vec<_> = events.into_iter().map(|e| e.data).collect();
+        assert_eq!(data, vec!["héllo", "[DONE]"]);
+
+        // An unfinished event is held back
+        assert!(parser.push(b"data: partial\n").is_empty());
+        assert_eq!(parser.push(b"\n")[0].data, "partial");
+    }
+}
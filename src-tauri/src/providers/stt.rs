@@ -1,11 +1,15 @@
 //! Speech-to-Text provider implementations
 
+use crate::cancel::CancelToken;
 use crate::error::{AppError, Result};
 use crate::modes::SttProvider as SttProviderType;
+use crate::vad;
 use async_trait::async_trait;
 use reqwest::multipart;
-use serde::Deserialize;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 
 /// STT provider trait
@@ -14,77 +18,726 @@ pub trait SttProvider: Send + Sync {
     /// Transcribe audio samples to text
     async fn transcribe(&self, samples: &[f32], language: Option<&str>) -> Result<String>;
 
+    /// Transcribe several independent chunks of audio
+    ///
+    /// Providers with expensive setup (e.g. model loading) should override
+    /// this to share it across chunks. The default reports progress as each
+    /// chunk finishes. Background jobs pause between chunks for dictations.
+    async fn transcribe_chunks(
+        &self,
+        chunks: &[Vec<f32>],
+        language: Option<&str>,
+        progress: &Progress,
+    ) -> Result<Vec<String>> {
+        let mut texts = Vec::with_capacity(chunks.len());
+        for (i, chunk) in chunks.iter().enumerate() {
+            crate::priority::yield_to_interactive().await;
+            texts.push(self.transcribe(chunk, language).await?);
+            progress.report((i + 1) as f32 / chunks.len() as f32);
+        }
+        Ok(texts)
+    }
+
+    /// Transcribe, failing with `AppError::Cancelled` once `cancel` fires
+    ///
+    /// The default drops the request future on cancel, which aborts HTTP
+    /// requests. Providers doing blocking work should override this.
+    async fn transcribe_cancellable(
+        &self,
+        samples: &[f32],
+        language: Option<&str>,
+        cancel: &CancelToken,
+    ) -> Result<String> {
+        cancel.run(self.transcribe(samples, language)).await
+    }
+
+    /// Transcribe cancellably, with the model's confidence when it reports one
+    async fn transcribe_scored(
+        &self,
+        samples: &[f32],
+        language: Option<&str>,
+        cancel: &CancelToken,
+    ) -> Result<ScoredTranscript> {
+        Ok(ScoredTranscript {
+            text: self.transcribe_cancellable(samples, language, cancel).await?,
+            confidence: None,
+            alternatives: Vec::new(),
+        })
+    }
+
+    /// Check the provider can serve requests (model loads, server answers)
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
+
     /// Get the provider name
     fn name(&self) -> &str;
 }
 
+/// STT provider and model chosen for the session, overriding each mode's
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SttSelection {
+    pub provider: SttProviderType,
+    pub model: String,
+}
+
+impl SttSelection {
+    /// The model that transcribed, a smaller local one when the GPU fell back to it
+    pub fn model_used(&self) -> String {
+        match self.provider {
+            SttProviderType::WhisperCpp => crate::gpu::loaded_model(&self.model),
+            _ => self.model.clone(),
+        }
+    }
+}
+
+/// Transcribed text with the model's confidence in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredTranscript {
+    pub text: String,
+    /// Mean probability (0-1) of the text's tokens, when the provider reports it
+    pub confidence: Option<f32>,
+    /// The less certain segments, when beam search is on, their other
+    /// readings still to be sampled
+    pub alternatives: Vec<SegmentAlternatives>,
+}
+
+/// Other readings of one segment of a transcript
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentAlternatives {
+    /// The segment as transcribed
+    pub text: String,
+    /// Other hypotheses for the same audio, most likely first; empty until
+    /// they are sampled, the first time they are asked for
+    pub alternatives: Vec<String>,
+    /// Where the segment is in the recording, in ms
+    #[serde(default)]
+    pub start_ms: u64,
+    #[serde(default)]
+    pub end_ms: u64,
+}
+
+/// Reports how far a transcription has got, in whole percent
+///
+/// Fractions passed to `report` are of the work this `Progress` covers;
+/// `part` narrows it to a slice, e.g. one of several chunks. Percentages
+/// only ever go up and each is reported once.
+#[derive(Clone, Default)]
+pub struct Progress {
+    sink: Option<Arc<ProgressSink>>,
+    start: f32,
+    span: f32,
+}
+
+struct ProgressSink {
+    report: Box<dyn Fn(u8) + Send + Sync>,
+    last: AtomicU8,
+}
+
+impl Progress {
+    pub fn new(report: impl Fn(u8) + Send + Sync + 'static) -> Self {
+        Self {
+            sink: Some(Arc::new(ProgressSink {
+                report: Box::new(report),
+                last: AtomicU8::new(0),
+            })),
+            start: 0.0,
+            span: 1.0,
+        }
+    }
+
+    /// Report that `done` (0-1) of this work is finished
+    pub fn report(&self, done: f32) {
+        if let Some(sink) = &self.sink {
+            let percent = ((self.start + done.clamp(0.0, 1.0) * self.span) * 100.0).round() as u8;
+            if sink.last.fetch_max(percent, Ordering::SeqCst) < percent {
+                (sink.report)(percent);
+            }
+        }
+    }
+
+    /// The slice of this work from `from` to `to` (fractions of it)
+    pub fn part(&self, from: f32, to: f32) -> Progress {
+        Progress {
+            sink: self.sink.clone(),
+            start: self.start + from * self.span,
+            span: (to - from) * self.span,
+        }
+    }
+}
+
+/// Transcribed text positioned on the source audio timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimedSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// Transcribe only the speech regions of a long recording
+///
+/// Silence between regions is never sent to the provider; segment times are
+/// offset back onto the original timeline.
+pub async fn transcribe_speech_only(
+    provider: &dyn SttProvider,
+    samples: &[f32],
+    language: Option<&str>,
+    vad_config: &vad::VadConfig,
+    progress: &Progress,
+) -> Result<Vec<TimedSegment>> {
+    let regions = vad::detect_speech(samples, vad_config);
+    let regions = vad::group_regions(&regions, VAD_CHUNK_MS);
+
+    log::info!(
+        "VAD: {} speech chunks covering {:.0}% of {} ms",
+        regions.len(),
+        vad::speech_ratio(&regions, samples.len()) * 100.0,
+        crate::audio::calculate_duration_ms(samples.len())
+    );
+
+    let chunks: Vec<Vec<f32>> = regions
+        .iter()
+        .map(|r| samples[r.start..r.end].to_vec())
+        .collect();
+    let texts = provider.transcribe_chunks(&chunks, language, progress).await?;
+
+    Ok(regions
+        .iter()
+        .zip(texts)
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(region, text)| TimedSegment {
+            start_ms: region.start_ms(),
+            end_ms: region.end_ms(),
+            text,
+        })
+        .collect())
+}
+
+/// Maximum length of audio grouped into one VAD chunk (whisper's window size)
+const VAD_CHUNK_MS: u32 = 30_000;
+
+/// Length of each chunk when transcribing a spooled recording
+const LONG_CHUNK_SECS: usize = 30;
+/// Audio shared between neighbouring chunks so words at the cut are not lost
+const LONG_OVERLAP_SECS: usize = 2;
+/// Chunks handed to the provider at once (bounds memory to a few minutes of audio)
+const LONG_BATCH_CHUNKS: usize = 10;
+/// Longest run of repeated words removed where two chunks overlap
+const MAX_OVERLAP_WORDS: usize = 12;
+
+/// Transcribe a long 16kHz WAV file chunk by chunk with overlap
+///
+/// Only a batch of chunks is held in memory at a time, so hour-long
+/// recordings can be transcribed without loading the whole file.
+pub async fn transcribe_wav_chunked(
+    provider: &dyn SttProvider,
+    path: &Path,
+    sample_count: usize,
+    language: Option<&str>,
+    progress: &Progress,
+) -> Result<String> {
+    let chunk_len = LONG_CHUNK_SECS * crate::audio::WHISPER_SAMPLE_RATE as usize;
+    let overlap = LONG_OVERLAP_SECS * crate::audio::WHISPER_SAMPLE_RATE as usize;
+    let total_chunks = sample_count.div_ceil(chunk_len).max(1) as f32;
+    let mut done_chunks = 0;
+
+    let mut text = String::new();
+    let mut batch = Vec::with_capacity(LONG_BATCH_CHUNKS);
+    let mut start = 0;
+
+    while start < sample_count {
+        batch.push(crate::audio::read_wav_range(path, start, chunk_len + overlap)?);
+        start += chunk_len;
+
+        if batch.len() == LONG_BATCH_CHUNKS || start >= sample_count {
+            log::info!(
+                "Transcribing chunks up to {} ms of {} ms",
+                crate::audio::calculate_duration_ms(start.min(sample_count)),
+                crate::audio::calculate_duration_ms(sample_count)
+            );
+            let batch_progress = progress.part(
+                done_chunks as f32 / total_chunks,
+                (done_chunks + batch.len()) as f32 / total_chunks,
+            );
+            for chunk_text in provider.transcribe_chunks(&batch, language, &batch_progress).await? {
+                text = merge_overlapping(&text, &chunk_text);
+            }
+            done_chunks += batch.len();
+            batch.clear();
+        }
+    }
+
+    Ok(text)
+}
+
+/// Normalize a word for overlap comparison
+fn overlap_key(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// Append `next` to `prev`, dropping words repeated because of chunk overlap
+pub fn merge_overlapping(prev: &str, next: &str) -> String {
+    let prev_words: Vec<&str> = prev.split_whitespace().collect();
+    let next_words: Vec<&str> = next.split_whitespace().collect();
+
+    if prev_words.is_empty() {
+        return next_words.join(" ");
+    }
+
+    let max = MAX_OVERLAP_WORDS.min(prev_words.len()).min(next_words.len());
+    let skip = (1..=max)
+        .rev()
+        .find(|&k| {
+            prev_words[prev_words.len() - k..]
+                .iter()
+                .zip(&next_words[..k])
+                .all(|(a, b)| overlap_key(a) == overlap_key(b))
+        })
+        .unwrap_or(0);
+
+    let mut merged = prev_words.join(" ");
+    for word in &next_words[skip..] {
+        merged.push(' ');
+        merged.push_str(word);
+    }
+    merged
+}
+
+/// Threads whisper.cpp gives each state by default
+const WHISPER_THREADS_PER_STATE: usize = 4;
+
+/// Memory a whisper state needs beyond the shared model, as a fraction of
+/// the model file's size (its KV cache and compute buffers scale with it)
+pub(crate) const STATE_MEMORY_PER_MODEL_BYTE: f64 = 0.5;
+
+/// Memory left free for the rest of the system while transcribing
+const RESERVED_MEMORY_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Memory the kernel can give out without swapping, from /proc/meminfo
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Whisper states to run side by side on `chunks` chunks
+///
+/// Bounded by the cores (each state already uses several threads) and by
+/// the memory each state needs; unknown memory allows one state per core
+/// group.
+fn parallel_states(chunks: usize, cpus: usize, state_memory: u64, available_memory: Option<u64>) -> usize {
+    let by_cpu = cpus / WHISPER_THREADS_PER_STATE;
+    let by_memory = available_memory.map_or(usize::MAX, |available| {
+        (available.saturating_sub(RESERVED_MEMORY_BYTES) / state_memory.max(1)) as usize
+    });
+    by_cpu.min(by_memory).min(chunks).max(1)
+}
+
+/// Widest beam searched by local whisper
+pub const MAX_BEAM_SIZE: u8 = 5;
+
+/// Beam width for local whisper; 0 or 1 decodes greedily
+static BEAM_SIZE: AtomicU8 = AtomicU8::new(0);
+
+/// Segments at least this confident get no alternatives
+const ALTERNATIVES_MAX_CONFIDENCE: f32 = 0.9;
+
+/// Temperature added for each further alternative decode
+const ALTERNATIVE_TEMPERATURE_STEP: f32 = 0.2;
+
+/// Audio whisper.cpp decodes at all (shorter input yields nothing)
+const MIN_DECODE_SAMPLES: usize = 17_600;
+
+/// Set the beam width for local whisper
+pub fn set_beam_size(beam_size: u8) {
+    BEAM_SIZE.store(beam_size.min(MAX_BEAM_SIZE), Ordering::SeqCst);
+}
+
+fn beam_size() -> u8 {
+    BEAM_SIZE.load(Ordering::SeqCst)
+}
+
 /// Local whisper.cpp provider
 pub struct WhisperCppProvider {
     model_path: PathBuf,
+    /// Offload to the GPU, in builds with a GPU backend
+    use_gpu: bool,
 }
 
 impl WhisperCppProvider {
     /// Create a new whisper.cpp provider
-    pub fn new(model_path: PathBuf) -> Self {
-        Self { model_path }
+    pub fn new(model_path: PathBuf, use_gpu: bool) -> Self {
+        Self { model_path, use_gpu }
+    }
+}
+
+/// Model kept loaded after use, so the next dictation and the startup
+/// warm-up do not pay for loading it again
+struct LoadedModel {
+    path: PathBuf,
+    use_gpu: bool,
+    ctx: Arc<WhisperContext>,
+}
+
+static LOADED: Mutex<Option<LoadedModel>> = Mutex::new(None);
+
+/// The context for a model, loading it unless it is already loaded
+///
+/// Only one model is kept; loading another frees it once it is no longer in use.
+fn load_context(model_path: &Path, use_gpu: bool) -> Result<Arc<WhisperContext>> {
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(model) = loaded
+        .as_ref()
+        .filter(|model| model.path == model_path && model.use_gpu == use_gpu)
+    {
+        return Ok(model.ctx.clone());
+    }
+    *loaded = None;
+
+    let mut params = WhisperContextParameters::default();
+    params.use_gpu(use_gpu);
+    let ctx = WhisperContext::new_with_params(model_path.to_str().unwrap(), params)
+        .map_err(|e| AppError::Transcription(format!("Failed to create context: {}", e)))?;
+    let ctx = Arc::new(ctx);
+    *loaded = Some(LoadedModel {
+        path: model_path.to_path_buf(),
+        use_gpu,
+        ctx: ctx.clone(),
+    });
+    Ok(ctx)
+}
+
+impl WhisperCppProvider {
+    /// Load the model if needed and run `f` with it on a blocking thread
+    async fn with_context<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&WhisperContext) -> Result<T> + Send + 'static,
+    {
+        let model_path = self.model_path.clone();
+        let use_gpu = self.use_gpu;
+
+        tokio::task::spawn_blocking(move || {
+            let ctx = load_context(&model_path, use_gpu)?;
+            f(&ctx)
+        })
+        .await
+        .map_err(|e| AppError::Transcription(format!("Task failed: {}", e)))?
+    }
+}
+
+/// Abort callback for whisper.cpp, polled between decoding steps
+///
+/// `user_data` points to the cancel flag.
+unsafe extern "C" fn abort_if_cancelled(user_data: *mut std::ffi::c_void) -> bool {
+    let flag = &*(user_data as *const AtomicBool);
+    flag.load(Ordering::SeqCst)
+}
+
+/// Run whisper on one buffer of audio using a loaded context
+///
+/// When `cancel` is set, whisper aborts as soon as the flag is raised. With
+/// `alternatives` and beam search on, the less certain segments are noted
+/// for `sample_alternatives` to find other readings of later.
+fn run_whisper(
+    ctx: &WhisperContext,
+    samples: &[f32],
+    language: Option<&str>,
+    cancel: Option<&Arc<AtomicBool>>,
+    progress: &Progress,
+    alternatives: bool,
+) -> Result<ScoredTranscript> {
+    let mut state = ctx
+        .create_state()
+        .map_err(|e| AppError::Transcription(format!("Failed to create state: {}", e)))?;
+
+    let beam_size = beam_size();
+    let mut params = if beam_size > 1 {
+        FullParams::new(SamplingStrategy::BeamSearch {
+            beam_size: beam_size as i32,
+            patience: -1.0,
+        })
+    } else {
+        FullParams::new(SamplingStrategy::Greedy { best_of: 1 })
+    };
+
+    // Set language if specified
+    let language = language.unwrap_or("en");
+    params.set_language(Some(language));
+
+    // Disable timestamps for cleaner output
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+
+    if progress.sink.is_some() {
+        let progress = progress.clone();
+        params.set_progress_callback_safe(move |percent: i32| progress.report(percent as f32 / 100.0));
+    }
+
+    if let Some(flag) = cancel {
+        // SAFETY: the flag outlives `state.full`, which is the only caller
+        // of the callback, and the callback only reads it atomically.
+        unsafe {
+            params.set_abort_callback(Some(abort_if_cancelled));
+            params.set_abort_callback_user_data(Arc::as_ptr(flag) as *mut std::ffi::c_void);
+        }
+    }
+
+    // Run transcription
+    let result = state.full(params, samples);
+    let cancelled = || cancel.is_some_and(|flag| flag.load(Ordering::SeqCst));
+    if cancelled() {
+        return Err(AppError::Cancelled);
+    }
+    result.map_err(|e| AppError::Transcription(format!("Transcription failed: {}", e)))?;
+
+    // Collect segments
+    let num_segments = state
+        .full_n_segments()
+        .map_err(|e| AppError::Transcription(format!("Failed to get segments: {}", e)))?;
+
+    let mut text = String::new();
+    let (mut prob_sum, mut token_count) = (0.0f32, 0u32);
+    let mut segment_alternatives = Vec::new();
+    for i in 0..num_segments {
+        let segment = state.full_get_segment_text(i).unwrap_or_default();
+        text.push_str(&segment);
+        let (mut segment_sum, mut segment_count) = (0.0f32, 0u32);
+        for t in 0..state.full_n_tokens(i).unwrap_or(0) {
+            // Timestamp and control tokens say nothing about the words
+            let special = state
+                .full_get_token_text(i, t)
+                .map_or(true, |token| token.starts_with("[_") || token.starts_with("<|"));
+            if let (false, Ok(prob)) = (special, state.full_get_token_prob(i, t)) {
+                segment_sum += prob;
+                segment_count += 1;
+            }
+        }
+        prob_sum += segment_sum;
+        token_count += segment_count;
+
+        let uncertain = segment_count > 0 && segment_sum / (segment_count as f32) < ALTERNATIVES_MAX_CONFIDENCE;
+        if alternatives && beam_size > 1 && uncertain && !segment.trim().is_empty() {
+            // Timestamps are in centiseconds
+            let (Ok(t0), Ok(t1)) = (state.full_get_segment_t0(i), state.full_get_segment_t1(i)) else {
+                continue;
+            };
+            segment_alternatives.push(SegmentAlternatives {
+                text: segment.trim().to_string(),
+                alternatives: Vec::new(),
+                start_ms: t0.max(0) as u64 * 10,
+                end_ms: t1.max(0) as u64 * 10,
+            });
+        }
+    }
+
+    Ok(ScoredTranscript {
+        text: text.trim().to_string(),
+        confidence: (token_count > 0).then(|| prob_sum / token_count as f32),
+        alternatives: segment_alternatives,
+    })
+}
+
+/// Other readings of one segment's audio, at most `count`
+///
+/// whisper.cpp only returns the best beam, so each alternative comes from
+/// decoding the segment again at a higher temperature; readings equal to
+/// `best` or to an earlier one are dropped.
+fn sample_segment(ctx: &WhisperContext, samples: &[f32], language: &str, best: &str, count: usize) -> Vec<String> {
+    if samples.is_empty() {
+        return Vec::new();
+    }
+    // Pad short segments with silence so whisper decodes them at all
+    let mut audio = samples.to_vec();
+    audio.resize(audio.len().max(MIN_DECODE_SAMPLES), 0.0);
+
+    let mut alternatives: Vec<String> = Vec::new();
+    for attempt in 1..=count {
+        let Ok(mut state) = ctx.create_state() else {
+            break;
+        };
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Some(language));
+        params.set_temperature(ALTERNATIVE_TEMPERATURE_STEP * attempt as f32);
+        params.set_temperature_inc(0.0);
+        params.set_single_segment(true);
+        params.set_no_context(true);
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+        if let Err(e) = state.full(params, &audio) {
+            log::debug!("Alternative decode failed: {}", e);
+            continue;
+        }
+
+        let text: String = (0..state.full_n_segments().unwrap_or(0))
+            .filter_map(|i| state.full_get_segment_text(i).ok())
+            .collect();
+        let text = text.trim();
+        if !text.is_empty() && text != best && !alternatives.iter().any(|a| a == text) {
+            alternatives.push(text.to_string());
+        }
+    }
+    alternatives
+}
+
+impl WhisperCppProvider {
+    /// Sample other readings of the `segments` of `samples` still without any
+    ///
+    /// Decoding each segment again takes several times as long as it lasts,
+    /// so it is done when the readings are first asked for rather than while
+    /// dictating. Segments that get no other reading are left out.
+    pub async fn sample_alternatives(
+        &self,
+        samples: Vec<f32>,
+        language: Option<String>,
+        segments: Vec<SegmentAlternatives>,
+    ) -> Result<Vec<SegmentAlternatives>> {
+        let count = beam_size().saturating_sub(1).max(1) as usize;
+        self.with_context(move |ctx| {
+            let language = language.as_deref().unwrap_or("en");
+            let per_ms = crate::audio::WHISPER_SAMPLE_RATE as usize / 1000;
+            Ok(segments
+                .into_iter()
+                .map(|mut segment| {
+                    if segment.alternatives.is_empty() {
+                        let start = (segment.start_ms as usize * per_ms).min(samples.len());
+                        let end = (segment.end_ms as usize * per_ms).clamp(start, samples.len());
+                        segment.alternatives =
+                            sample_segment(ctx, &samples[start..end], language, &segment.text, count);
+                    }
+                    segment
+                })
+                .filter(|segment| !segment.alternatives.is_empty())
+                .collect())
+        })
+        .await
     }
 }
 
 #[async_trait]
 impl SttProvider for WhisperCppProvider {
     async fn transcribe(&self, samples: &[f32], language: Option<&str>) -> Result<String> {
-        let model_path = self.model_path.clone();
         let samples = samples.to_vec();
         let language = language.map(|s| s.to_string());
 
-        let result = tokio::task::spawn_blocking(move || {
-            // Create context for transcription
-            let params = WhisperContextParameters::default();
-            let ctx = WhisperContext::new_with_params(model_path.to_str().unwrap(), params)
-                .map_err(|e| AppError::Transcription(format!("Failed to create context: {}", e)))?;
+        self.with_context(move |ctx| {
+            run_whisper(ctx, &samples, language.as_deref(), None, &Progress::default(), false).map(|t| t.text)
+        })
+        .await
+    }
 
-            let mut state = ctx
-                .create_state()
-                .map_err(|e| AppError::Transcription(format!("Failed to create state: {}", e)))?;
+    async fn transcribe_cancellable(
+        &self,
+        samples: &[f32],
+        language: Option<&str>,
+        cancel: &CancelToken,
+    ) -> Result<String> {
+        cancel.check()?;
+        let samples = samples.to_vec();
+        let language = language.map(|s| s.to_string());
+        let flag = cancel.flag();
 
-            let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        // Dropping the future would not stop the blocking thread, so whisper polls the flag
+        self.with_context(move |ctx| {
+            run_whisper(ctx, &samples, language.as_deref(), Some(&flag), &Progress::default(), false).map(|t| t.text)
+        })
+        .await
+    }
 
-            // Set language if specified
-            if let Some(lang) = language.as_deref() {
-                params.set_language(Some(lang));
-            } else {
-                params.set_language(Some("en"));
-            }
+    async fn transcribe_scored(
+        &self,
+        samples: &[f32],
+        language: Option<&str>,
+        cancel: &CancelToken,
+    ) -> Result<ScoredTranscript> {
+        cancel.check()?;
+        let samples = samples.to_vec();
+        let language = language.map(|s| s.to_string());
+        let flag = cancel.flag();
 
-            // Disable timestamps for cleaner output
-            params.set_print_special(false);
-            params.set_print_progress(false);
-            params.set_print_realtime(false);
-            params.set_print_timestamps(false);
+        self.with_context(move |ctx| {
+            run_whisper(ctx, &samples, language.as_deref(), Some(&flag), &Progress::default(), true)
+        })
+        .await
+    }
 
-            // Run transcription
-            state
-                .full(params, &samples)
-                .map_err(|e| AppError::Transcription(format!("Transcription failed: {}", e)))?;
+    async fn transcribe_chunks(
+        &self,
+        chunks: &[Vec<f32>],
+        language: Option<&str>,
+        progress: &Progress,
+    ) -> Result<Vec<String>> {
+        let chunks = chunks.to_vec();
+        let language = language.map(|s| s.to_string());
+        let progress = progress.clone();
+        let background = crate::priority::is_background();
 
-            // Collect segments
-            let num_segments = state.full_n_segments().map_err(|e| {
-                AppError::Transcription(format!("Failed to get segments: {}", e))
-            })?;
+        let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let model_size = std::fs::metadata(&self.model_path).map(|m| m.len()).unwrap_or(0);
+        let state_memory = (model_size as f64 * STATE_MEMORY_PER_MODEL_BYTE) as u64;
+        let states = parallel_states(chunks.len(), cpus, state_memory, available_memory());
 
-            let mut text = String::new();
-            for i in 0..num_segments {
-                if let Ok(segment) = state.full_get_segment_text(i) {
-                    text.push_str(&segment);
-                }
+        // Load the model once for all chunks
+        self.with_context(move |ctx| {
+            let n = chunks.len() as f32;
+            if states == 1 {
+                // Whisper reports progress within each chunk
+                return chunks
+                    .iter()
+                    .enumerate()
+                    .map(|(i, chunk)| {
+                        if background {
+                            crate::priority::wait_until_idle();
+                        }
+                        let part = progress.part(i as f32 / n, (i + 1) as f32 / n);
+                        run_whisper(ctx, chunk, language.as_deref(), None, &part, false).map(|t| t.text)
+                    })
+                    .collect();
             }
 
-            Ok::<String, AppError>(text.trim().to_string())
+            log::info!("Transcribing {} chunks on {} whisper states", chunks.len(), states);
+            // Each worker takes the next chunk until none are left; results keep chunk order
+            let next = std::sync::atomic::AtomicUsize::new(0);
+            let done = std::sync::atomic::AtomicUsize::new(0);
+            let failed = AtomicBool::new(false);
+            let results: Vec<std::sync::Mutex<Option<Result<String>>>> =
+                chunks.iter().map(|_| std::sync::Mutex::new(None)).collect();
+            std::thread::scope(|scope| {
+                for _ in 0..states {
+                    scope.spawn(|| loop {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        if i >= chunks.len() || failed.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        if background {
+                            crate::priority::wait_until_idle();
+                        }
+                        let result =
+                            run_whisper(ctx, &chunks[i], language.as_deref(), None, &Progress::default(), false)
+                                .map(|t| t.text);
+                        failed.fetch_or(result.is_err(), Ordering::SeqCst);
+                        *results[i].lock().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(result);
+                        progress.report((done.fetch_add(1, Ordering::SeqCst) + 1) as f32 / n);
+                    });
+                }
+            });
+
+            results
+                .into_iter()
+                .filter_map(|result| result.into_inner().unwrap_or_else(std::sync::PoisonError::into_inner))
+                .collect()
         })
         .await
-        .map_err(|e| AppError::Transcription(format!("Task failed: {}", e)))??;
+    }
 
-        Ok(result)
+    async fn health_check(&self) -> Result<()> {
+        self.with_context(|_| Ok(())).await
     }
 
     fn name(&self) -> &str {
@@ -92,13 +745,42 @@ impl SttProvider for WhisperCppProvider {
     }
 }
 
+/// Groq's OpenAI-compatible API
+const GROQ_API_URL: &str = "https://api.groq.com/openai";
+
+/// Groq model used when a mode names none
+const GROQ_DEFAULT_MODEL: &str = "whisper-large-v3";
+
+/// whisper.cpp's `server` example serves transcriptions at this path
+const WHISPER_CPP_INFERENCE_PATH: &str = "/inference";
+
+/// Transcription and health check URLs for a server URL
+///
+/// A URL ending in `/inference` (whisper.cpp's server) or
+/// `/audio/transcriptions` is the transcription endpoint itself; any other
+/// is the base of an OpenAI-style `/v1` API. whisper.cpp's server lists no
+/// models, so its root page is checked instead.
+fn server_endpoints(url: &str) -> (String, String) {
+    let url = url.trim_end_matches('/');
+    if let Some(root) = url.strip_suffix(WHISPER_CPP_INFERENCE_PATH) {
+        (url.to_string(), format!("{}/", root))
+    } else if let Some(api) = url.strip_suffix("/audio/transcriptions") {
+        (url.to_string(), format!("{}/models", api))
+    } else {
+        (format!("{}/v1/audio/transcriptions", url), format!("{}/v1/models", url))
+    }
+}
+
 /// STT provider for OpenAI-compatible APIs
 ///
 /// Works with:
-/// - Self-hosted servers (Speaches, faster-whisper-server, LocalAI)
+/// - Self-hosted servers (Speaches, faster-whisper-server, LocalAI,
+///   whisper.cpp's `server`), so no model is loaded in-process
 /// - OpenAI cloud API
+/// - Groq cloud API
 ///
-/// Uses the /v1/audio/transcriptions endpoint format.
+/// Uses the /v1/audio/transcriptions endpoint format, or the endpoint the
+/// configured URL names (see `server_endpoints`).
 pub struct OpenAiCompatibleSttProvider {
     base_url: String,
     api_key: Option<String>,
@@ -126,6 +808,16 @@ impl OpenAiCompatibleSttProvider {
             "OpenAI Cloud".to_string(),
         )
     }
+
+    /// Create for Groq cloud, at `base_url` or the public endpoint
+    pub fn groq(base_url: Option<String>, api_key: String, model: String) -> Self {
+        Self::new(
+            base_url.unwrap_or_else(|| GROQ_API_URL.to_string()),
+            Some(api_key),
+            model,
+            "Groq".to_string(),
+        )
+    }
 }
 
 /// Response format from OpenAI-compatible transcription API
@@ -140,7 +832,7 @@ impl SttProvider for OpenAiCompatibleSttProvider {
         let wav_data = samples_to_wav(samples)?;
 
         let client = reqwest::Client::new();
-        let url = format!("{}/v1/audio/transcriptions", self.base_url);
+        let (url, _) = server_endpoints(&self.base_url);
 
         let file_part = multipart::Part::bytes(wav_data)
             .file_name("audio.wav")
@@ -170,15 +862,22 @@ impl SttProvider for OpenAiCompatibleSttProvider {
         let response = request
             .send()
             .await
-            .map_err(|e| AppError::Transcription(format!("[{}] Request failed: {}", self.name, e)))?;
+            .map_err(|e| {
+                if e.is_connect() || e.is_timeout() {
+                    AppError::ProviderUnavailable(format!("[{}] {}", self.name, e))
+                } else {
+                    AppError::Transcription(format!("[{}] Request failed: {}", self.name, e))
+                }
+            })?;
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(AppError::Transcription(format!(
-                "[{}] API error ({}): {}",
-                self.name, status, body
-            )));
+            return Err(AppError::Status {
+                provider: self.name.clone(),
+                status: status.as_u16(),
+                body,
+            });
         }
 
         let result: WhisperTranscriptionResponse = response
@@ -189,6 +888,29 @@ impl SttProvider for OpenAiCompatibleSttProvider {
         Ok(result.text.trim().to_string())
     }
 
+    async fn health_check(&self) -> Result<()> {
+        let (_, health_url) = server_endpoints(&self.base_url);
+        let mut request = reqwest::Client::new()
+            .get(health_url)
+            .timeout(std::time::Duration::from_secs(5));
+        if let Some(ref api_key) = self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::ProviderUnavailable(format!("[{}] {}", self.name, e)))?;
+        if !response.status().is_success() {
+            return Err(AppError::ProviderUnavailable(format!(
+                "[{}] Health check failed ({})",
+                self.name,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -243,6 +965,23 @@ pub fn get_model_path(model_name: &str) -> Result<PathBuf> {
     Ok(models_dir.join(format!("ggml-{}.bin", model_name)))
 }
 
+/// Names of the whisper models on disk, largest (most accurate) first
+pub fn downloaded_models() -> Vec<String> {
+    let Ok(entries) = get_models_dir().and_then(|dir| Ok(std::fs::read_dir(dir)?)) else {
+        return Vec::new();
+    };
+    let mut models: Vec<(u64, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let name = file_name.strip_prefix("ggml-")?.strip_suffix(".bin")?.to_string();
+            Some((entry.metadata().ok()?.len(), name))
+        })
+        .collect();
+    models.sort_by(|a, b| b.cmp(a));
+    models.into_iter().map(|(_, name)| name).collect()
+}
+
 /// Download a whisper model if not present
 pub async fn ensure_model(model_name: &str) -> Result<PathBuf> {
     let model_path = get_model_path(model_name)?;
@@ -280,7 +1019,22 @@ pub async fn ensure_model(model_name: &str) -> Result<PathBuf> {
     Ok(model_path)
 }
 
+/// whisper.cpp with `model`, downloaded if needed, on the GPU when it fits there
+pub async fn whisper_cpp(model: &str) -> Result<WhisperCppProvider> {
+    let model_path = ensure_model(model).await?;
+    let placement = crate::gpu::place(model, &model_path);
+    let model_path = if placement.model == model {
+        model_path
+    } else {
+        get_model_path(&placement.model)?
+    };
+    Ok(WhisperCppProvider::new(model_path, placement.use_gpu))
+}
+
 /// Create an STT provider based on configuration
+///
+/// `server_url` is the endpoint of a self-hosted or Wyoming server, or of
+/// Groq when it is reached through a proxy.
 pub async fn create_stt_provider(
     provider_type: &SttProviderType,
     model: &str,
@@ -288,26 +1042,39 @@ pub async fn create_stt_provider(
     server_url: Option<String>,
 ) -> Result<Box<dyn SttProvider>> {
     match provider_type {
-        SttProviderType::WhisperCpp => {
-            let model_path = ensure_model(model).await?;
-            let provider = WhisperCppProvider::new(model_path);
-            Ok(Box::new(provider))
-        }
+        SttProviderType::WhisperCpp => Ok(Box::new(whisper_cpp(model).await?)),
         SttProviderType::WhisperServer => {
             // Self-hosted whisper server (Speaches, faster-whisper-server, etc.)
             let base_url = server_url
                 .or_else(|| std::env::var("WHISPER_API_URL").ok())
                 .unwrap_or_else(|| "http://localhost:8000".to_string());
-            let provider = OpenAiCompatibleSttProvider::self_hosted(base_url, model.to_string());
-            Ok(Box::new(provider))
+            let provider: Box<dyn SttProvider> =
+                Box::new(OpenAiCompatibleSttProvider::self_hosted(base_url, model.to_string()));
+            Ok(Box::new(crate::retry::Retrying(provider)))
         }
         SttProviderType::OpenAI => {
             // Cloud OpenAI Whisper API - requires API key
             let key = api_key.ok_or_else(|| {
                 AppError::Provider("OpenAI STT requires an API key. Add it in Settings.".to_string())
             })?;
-            let provider = OpenAiCompatibleSttProvider::openai_cloud(key, model.to_string());
-            Ok(Box::new(provider))
+            let provider: Box<dyn SttProvider> =
+                Box::new(OpenAiCompatibleSttProvider::openai_cloud(key, model.to_string()));
+            Ok(Box::new(crate::retry::Retrying(provider)))
+        }
+        SttProviderType::Groq => {
+            let key = api_key.ok_or_else(|| {
+                AppError::Provider("Groq STT requires an API key. Add it in Settings.".to_string())
+            })?;
+            let model = if model.is_empty() { GROQ_DEFAULT_MODEL } else { model };
+            let provider: Box<dyn SttProvider> =
+                Box::new(OpenAiCompatibleSttProvider::groq(server_url, key, model.to_string()));
+            Ok(Box::new(crate::retry::Retrying(provider)))
+        }
+        SttProviderType::Wyoming => {
+            let address = server_url.unwrap_or_else(|| crate::providers::wyoming::DEFAULT_ADDRESS.to_string());
+            let provider: Box<dyn SttProvider> =
+                Box::new(crate::providers::wyoming::WyomingSttProvider::new(address, model.to_string()));
+            Ok(Box::new(crate::retry::Retrying(provider)))
         }
         SttProviderType::Deepgram => {
             Err(AppError::Provider("Deepgram not yet implemented".to_string()))
@@ -322,6 +1089,71 @@ pub async fn create_stt_provider(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_progress_parts() {
+        let reported = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        let progress = Progress::new(move |percent| sink.lock().unwrap().push(percent));
+
+        let second_half = progress.part(0.5, 1.0);
+        progress.part(0.0, 0.5).report(0.5);
+        second_half.report(0.5);
+        // Going backwards or repeating a percentage reports nothing
+        progress.report(0.5);
+        second_half.report(0.5);
+        second_half.report(1.0);
+
+        assert_eq!(*reported.lock().unwrap(), vec![25, 75, 100]);
+    }
+
+    #[test]
+    fn test_merge_overlapping_drops_repeat() {
+        let merged = merge_overlapping("we should meet on Tuesday at", "Tuesday at noon, okay?");
+        assert_eq!(merged, "we should meet on Tuesday at noon, okay?");
+    }
+
+    #[test]
+    fn test_merge_overlapping_without_repeat() {
+        assert_eq!(merge_overlapping("first part.", "Second part."), "first part. Second part.");
+        assert_eq!(merge_overlapping("", "hello"), "hello");
+    }
+
+    #[test]
+    fn test_parallel_states() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        // Cores bound it: one state per four threads
+        assert_eq!(parallel_states(100, 16, GIB / 4, Some(32 * GIB)), 4);
+        // Memory bounds it: 3 GiB free after the reserve fits three 1 GiB states
+        assert_eq!(parallel_states(100, 64, GIB, Some(3 * GIB + RESERVED_MEMORY_BYTES)), 3);
+        // Never more states than chunks, and always at least one
+        assert_eq!(parallel_states(2, 64, GIB / 4, None), 2);
+        assert_eq!(parallel_states(10, 2, GIB, Some(0)), 1);
+    }
+
+    #[test]
+    fn test_server_endpoints() {
+        assert_eq!(
+            server_endpoints("http://gpu:8000/"),
+            (
+                "http://gpu:8000/v1/audio/transcriptions".to_string(),
+                "http://gpu:8000/v1/models".to_string()
+            )
+        );
+        assert_eq!(
+            server_endpoints("http://gpu:8080/inference"),
+            ("http://gpu:8080/inference".to_string(), "http://gpu:8080/".to_string())
+        );
+        assert_eq!(
+            server_endpoints("http://gpu:8000/v1/audio/transcriptions").1,
+            "http://gpu:8000/v1/models"
+        );
+        // Groq's base has a path of its own
+        assert_eq!(
+            server_endpoints("https://api.groq.com/openai").0,
+            "https://api.groq.com/openai/v1/audio/transcriptions"
+        );
+    }
+
     #[test]
     fn test_get_model_path() {
         let path = get_model_path("base.en").unwrap();
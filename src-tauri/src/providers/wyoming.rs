@@ -0,0 +1,282 @@
+//! Wyoming protocol STT client
+//!
+//! Wyoming is the protocol Home Assistant speaks to its voice services,
+//! e.g. wyoming-faster-whisper on a LAN box with a GPU. Every message is an
+//! event: a JSON header line, optionally followed by JSON data and a binary
+//! payload whose sizes the header gives. A transcription sends
+//! `transcribe`, then `audio-start`, the audio as `audio-chunk` events
+//! carrying 16-bit PCM, and `audio-stop`; the server answers `transcript`.
+
+use crate::error::{AppError, Result};
+use crate::providers::stt::SttProvider;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Address used when none is configured (wyoming-faster-whisper's default port)
+pub const DEFAULT_ADDRESS: &str = "localhost:10300";
+
+/// Samples per `audio-chunk` event, as Wyoming clients send them
+const SAMPLES_PER_CHUNK: usize = 1024;
+
+const SAMPLE_RATE: u32 = 16000;
+
+/// Wait for a connection before the server counts as unreachable
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest a transcription may take, matching the HTTP providers
+const TRANSCRIBE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Headers longer than this are not Wyoming
+const MAX_HEADER_BYTES: u64 = 64 * 1024;
+
+/// Event header line
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload_length: Option<usize>,
+}
+
+/// A received event
+#[derive(Debug, PartialEq)]
+struct Event {
+    kind: String,
+    data: Value,
+}
+
+/// Write an event; data goes inline in the header, which every protocol
+/// version reads
+async fn write_event<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    kind: &str,
+    data: Option<Value>,
+    payload: Option<&[u8]>,
+) -> std::io::Result<()> {
+    let header = Header {
+        kind: kind.to_string(),
+        data,
+        data_length: None,
+        payload_length: payload.map(<[u8]>::len),
+    };
+    let mut line = serde_json::to_vec(&header)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    if let Some(payload) = payload {
+        writer.write_all(payload).await?;
+    }
+    Ok(())
+}
+
+/// Read the next event, skipping its payload
+///
+/// Newer servers send data after the header line instead of inline; both
+/// are merged into `data`. Returns None once the server closes the stream.
+async fn read_event<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> Result<Option<Event>> {
+    let mut line = Vec::new();
+    (&mut *reader).take(MAX_HEADER_BYTES).read_until(b'\n', &mut line).await?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let header: Header = serde_json::from_slice(&line)
+        .map_err(|e| AppError::Transcription(format!("[Wyoming] Invalid event: {}", e)))?;
+
+    let mut data = header.data.unwrap_or_else(|| json!({}));
+    if let Some(length) = header.data_length.filter(|&length| length > 0) {
+        let mut bytes = vec![0; length];
+        reader.read_exact(&mut bytes).await?;
+        let extra: Value = serde_json::from_slice(&bytes)
+            .map_err(|e| AppError::Transcription(format!("[Wyoming] Invalid event data: {}", e)))?;
+        if let (Some(data), Value::Object(extra)) = (data.as_object_mut(), extra) {
+            data.extend(extra);
+        }
+    }
+    if let Some(length) = header.payload_length.filter(|&length| length > 0) {
+        tokio::io::copy(&mut (&mut *reader).take(length as u64), &mut tokio::io::sink()).await?;
+    }
+
+    Ok(Some(Event {
+        kind: header.kind,
+        data,
+    }))
+}
+
+/// Read events until one of `kinds`, failing on an `error` event
+async fn expect_event<R: AsyncRead + Unpin>(reader: &mut BufReader<R>, kinds: &[&str]) -> Result<Event> {
+    loop {
+        match read_event(reader).await? {
+            Some(event) if kinds.contains(&event.kind.as_str()) => return Ok(event),
+            Some(event) if event.kind == "error" => {
+                let message = event.data["text"].as_str().unwrap_or("unknown error");
+                return Err(AppError::Transcription(format!("[Wyoming] {}", message)));
+            }
+            Some(_) => continue,
+            None => {
+                return Err(AppError::ProviderUnavailable(
+                    "[Wyoming] Server closed the connection".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// 16-bit little-endian PCM, the format of `audio-chunk` payloads
+fn to_pcm16(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|&sample| ((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+        .collect()
+}
+
+/// Send a recording over an open connection and read the transcript
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    samples: &[f32],
+    model: Option<&str>,
+    language: Option<&str>,
+) -> Result<String> {
+    let mut stream = BufReader::new(stream);
+    let format = json!({ "rate": SAMPLE_RATE, "width": 2, "channels": 1 });
+    let at_ms = |offset: usize| (offset as u64 * 1000) / SAMPLE_RATE as u64;
+
+    let mut transcribe = serde_json::Map::new();
+    if let Some(model) = model {
+        transcribe.insert("name".to_string(), json!(model));
+    }
+    if let Some(language) = language {
+        transcribe.insert("language".to_string(), json!(language));
+    }
+    write_event(stream.get_mut(), "transcribe", Some(Value::Object(transcribe)), None).await?;
+
+    let mut start = format.clone();
+    start["timestamp"] = json!(0);
+    write_event(stream.get_mut(), "audio-start", Some(start), None).await?;
+    for (i, chunk) in samples.chunks(SAMPLES_PER_CHUNK).enumerate() {
+        let mut data = format.clone();
+        data["timestamp"] = json!(at_ms(i * SAMPLES_PER_CHUNK));
+        write_event(stream.get_mut(), "audio-chunk", Some(data), Some(&to_pcm16(chunk))).await?;
+    }
+    write_event(
+        stream.get_mut(),
+        "audio-stop",
+        Some(json!({ "timestamp": at_ms(samples.len()) })),
+        None,
+    )
+    .await?;
+    stream.get_mut().flush().await?;
+
+    let transcript = expect_event(&mut stream, &["transcript"]).await?;
+    Ok(transcript.data["text"].as_str().unwrap_or_default().trim().to_string())
+}
+
+/// STT provider for Wyoming servers (wyoming-faster-whisper, wyoming-whisper-cpp)
+pub struct WyomingSttProvider {
+    /// `host:port`; a `tcp://` prefix as Home Assistant shows it is accepted
+    address: String,
+    /// Model requested by name; empty uses the server's own
+    model: String,
+}
+
+impl WyomingSttProvider {
+    pub fn new(address: String, model: String) -> Self {
+        let address = address.trim().trim_start_matches("tcp://").trim_end_matches('/').to_string();
+        Self { address, model }
+    }
+
+    async fn connect(&self) -> Result<TcpStream> {
+        match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&self.address)).await {
+            Ok(Ok(stream)) => Ok(stream),
+            Ok(Err(e)) => Err(AppError::ProviderUnavailable(format!("[Wyoming] {}: {}", self.address, e))),
+            Err(_) => Err(AppError::ProviderUnavailable(format!(
+                "[Wyoming] {}: connection timed out",
+                self.address
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl SttProvider for WyomingSttProvider {
+    async fn transcribe(&self, samples: &[f32], language: Option<&str>) -> Result<String> {
+        let stream = self.connect().await?;
+        log::info!("[Wyoming] Sending {} samples to {}", samples.len(), self.address);
+
+        let model = Some(self.model.as_str()).filter(|m| !m.is_empty());
+        tokio::time::timeout(TRANSCRIBE_TIMEOUT, exchange(stream, samples, model, language))
+            .await
+            .map_err(|_| AppError::ProviderUnavailable(format!("[Wyoming] {}: timed out", self.address)))?
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        let mut stream = BufReader::new(self.connect().await?);
+        write_event(stream.get_mut(), "describe", None, None).await?;
+        tokio::time::timeout(CONNECT_TIMEOUT, expect_event(&mut stream, &["info"]))
+            .await
+            .map_err(|_| AppError::ProviderUnavailable(format!("[Wyoming] {}: no answer", self.address)))??;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "Wyoming"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exchange() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let samples = vec![0.25f32; SAMPLES_PER_CHUNK + 100];
+
+        let server = tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            let mut kinds = Vec::new();
+            let mut audio_chunks = 0;
+            loop {
+                let event = read_event(&mut server).await.unwrap().unwrap();
+                if event.kind == "transcribe" {
+                    assert_eq!(event.data, json!({ "name": "large-v3", "language": "en" }));
+                }
+                if event.kind == "audio-chunk" {
+                    audio_chunks += 1;
+                    assert_eq!(event.data["rate"], 16000);
+                }
+                let done = event.kind == "audio-stop";
+                kinds.push(event.kind);
+                if done {
+                    break;
+                }
+            }
+            // Data after the header, as newer servers send it
+            let data = br#"{"text": " Hello there. "}"#;
+            let header = format!("{{\"type\": \"transcript\", \"data_length\": {}}}\n", data.len());
+            server.get_mut().write_all(header.as_bytes()).await.unwrap();
+            server.get_mut().write_all(data).await.unwrap();
+            (kinds, audio_chunks)
+        });
+
+        let text = exchange(client, &samples, Some("large-v3"), Some("en")).await.unwrap();
+        assert_eq!(text, "Hello there.");
+        let (kinds, audio_chunks) = server.await.unwrap();
+        assert_eq!(kinds.first().map(String::as_str), Some("transcribe"));
+        assert_eq!(kinds.last().map(String::as_str), Some("audio-stop"));
+        assert_eq!(audio_chunks, 2);
+    }
+
+    #[tokio::test]
+    async fn test_error_event() {
+        let stream = std::io::Cursor::new(b"{\"type\": \"error\", \"data\": {\"text\": \"model not found\"}}\n".to_vec());
+        let error = expect_event(&mut BufReader::new(stream), &["transcript"]).await.unwrap_err();
+        assert!(error.to_string().contains("model not found"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Punctuation and capitalization restoration without an LLM
+//!
+//! Small whisper models (tiny, base) often return run-on, lowercase text.
+//! This stage runs on the transcript before any post-processing, so a mode
+//! without an LLM still gets commas and capitals. Rules add what can be
+//! told from the words alone; a local punctuation model, run as a command
+//! that reads the transcript on stdin and prints it punctuated, does it
+//! properly. Only punctuation and case may change: a model result with
+//! different words is thrown away, so the stage is safe for verbatim modes.
+
+use crate::cleanup::{capitalize, ends_sentence, key, trailing};
+use crate::error::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// Longest the punctuation model may take before rules are used instead
+const MODEL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// With fewer punctuation marks than one per this many words, text counts
+/// as unpunctuated and gets commas from the rules
+const WORDS_PER_MARK: usize = 20;
+
+/// English sentence openers followed by a comma ("Okay, ...")
+const INTRODUCTORY: &[&str] = &[
+    "okay", "ok", "alright", "yes", "yeah", "however", "anyway", "basically", "finally", "meanwhile",
+];
+
+/// English words that open a question
+const QUESTION_STARTS: &[&str] = &[
+    "what", "why", "how", "where", "when", "who", "which", "whose", "can", "could", "would", "should",
+    "will", "does", "did", "is", "are", "was", "were", "have", "has",
+];
+
+/// How punctuation is restored in transcripts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PunctuationRestore {
+    /// Transcripts keep the STT model's punctuation
+    #[default]
+    Off,
+    /// Rule-based commas, sentence ends and capitals
+    Rules,
+    /// The configured punctuation model command, rules when it fails
+    Model,
+}
+
+/// Restore punctuation with rules
+///
+/// Commas and question marks need English word lists; in other languages
+/// only the capitals and the final full stop are restored.
+pub fn restore_rules(text: &str, language: &str) -> String {
+    let english = language.to_lowercase().starts_with("en");
+    let mut words: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+    let Some(last) = words.len().checked_sub(1) else {
+        return String::new();
+    };
+
+    // Commas only go into text the STT model left without punctuation
+    let marks = words.iter().filter(|word| !trailing(word).is_empty()).count();
+    if english && words.len() > marks * WORDS_PER_MARK {
+        let mut position = 0;
+        for i in 0..last {
+            if trailing(&words[i]).is_empty() {
+                let introductory = position == 0 && INTRODUCTORY.contains(&key(&words[i]).as_str());
+                let before_but = position >= 2 && key(&words[i + 1]) == "but";
+                if introductory || before_but {
+                    words[i].push(',');
+                }
+            }
+            position = if ends_sentence(&words[i], english) { 0 } else { position + 1 };
+        }
+    }
+
+    if words[last].ends_with(|c: char| c.is_alphanumeric()) {
+        let start = words[..last]
+            .iter()
+            .rposition(|word| ends_sentence(word, english))
+            .map_or(0, |i| i + 1);
+        let question = english && QUESTION_STARTS.contains(&key(&words[start]).as_str());
+        words[last].push(if question { '?' } else { '.' });
+    }
+
+    let mut sentence_start = true;
+    for word in &mut words {
+        let pronoun = english && matches!(key(word).as_str(), "i" | "i'm" | "i've" | "i'll" | "i'd");
+        if sentence_start || pronoun {
+            *word = capitalize(word);
+        }
+        sentence_start = ends_sentence(word, english);
+    }
+
+    words.join(" ")
+}
+
+/// Whether two texts have the same words, ignoring punctuation and case
+fn same_words(a: &str, b: &str) -> bool {
+    let words = |text: &str| -> Vec<String> {
+        text.split_whitespace()
+            .map(key)
+            .filter(|word| !word.is_empty())
+            .collect()
+    };
+    words(a) == words(b)
+}
+
+/// Run the punctuation model command with `text` on its stdin
+async fn run_model(command: &str, text: &str) -> Result<String> {
+    let mut child = tokio::process::Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    // Write while reading, under the same timeout: a model that stops reading
+    // or fills its output pipe must not hang the dictation
+    let stdin = child.stdin.take();
+    let write = async move {
+        if let Some(mut stdin) = stdin {
+            stdin.write_all(text.as_bytes()).await?;
+        }
+        Ok::<_, std::io::Error>(())
+    };
+    let run = async {
+        let (written, output) = tokio::join!(write, child.wait_with_output());
+        written?;
+        output
+    };
+    let output = tokio::time::timeout(MODEL_TIMEOUT, run)
+        .await
+        .map_err(|_| AppError::Provider("[Punctuation] Model timed out".to_string()))??;
+    if !output.status.success() {
+        return Err(AppError::Provider(format!("[Punctuation] Model exited with {}", output.status)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Restore punctuation in a transcript
+///
+/// `command` is the punctuation model, None when none is configured or
+/// shell commands are not allowed.
+pub async fn restore(text: &str, language: &str, how: PunctuationRestore, command: Option<&str>) -> String {
+    match (how, command) {
+        (PunctuationRestore::Off, _) => text.to_string(),
+        (PunctuationRestore::Model, Some(command)) if !text.trim().is_empty() => match run_model(command, text).await {
+            Ok(restored) if same_words(text, &restored) => restored,
+            Ok(_) => {
+                log::warn!("Punctuation model changed words, using rules");
+                restore_rules(text, language)
+            }
+            Err(e) => {
+                log::warn!("Punctuation model failed, using rules: {}", e);
+                restore_rules(text, language)
+            }
+        },
+        _ => restore_rules(text, language),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_rules() {
+        assert_eq!(
+            restore_rules("okay so i tried the new build but it still crashes on startup", "en"),
+            "Okay, so I tried the new build, but it still crashes on startup."
+        );
+        assert_eq!(restore_rules("sounds good. can you send it over", "en"), "Sounds good. Can you send it over?");
+        // Punctuated text only gets capitals and the final mark
+        assert_eq!(
+            restore_rules("well, okay then. but nothing but the truth", "en"),
+            "Well, okay then. But nothing but the truth."
+        );
+        assert_eq!(restore_rules("ja aber wie geht das", "de"), "Ja aber wie geht das.");
+        assert_eq!(restore_rules("  ", "en"), "");
+    }
+
+    #[tokio::test]
+    async fn test_model_may_not_change_words() {
+        let text = "hello there how are you";
+        let restored = restore(text, "en", PunctuationRestore::Model, Some("sed 's/there /there. /'")).await;
+        assert_eq!(restored, "hello there. how are you");
+
+        // A model that rewrites falls back to the rules
+        let rewritten = restore(text, "en", PunctuationRestore::Model, Some("echo Hi, how are you?")).await;
+        assert_eq!(rewritten, "Hello there how are you.");
+        let failed = restore(text, "en", PunctuationRestore::Model, Some("exit 1")).await;
+        assert_eq!(failed, rewritten);
+    }
+}
@@ -0,0 +1,186 @@
+//! Personal data kept out of the stored history
+//!
+//! A mode can paste dictations as usual but store them redacted, with email
+//! addresses, phone numbers, payment card and social security numbers
+//! replaced by placeholders, store only the LLM's summary, or not store them
+//! at all. In every case the recording is not kept, since it holds everything
+//! the text did: it is not saved, and a spooled one is overwritten and
+//! deleted once processed. Event sinks, hooks and the journal get the same
+//! treatment as history. Detection is by
+//! pattern: names and street addresses are not caught.
+
+use crate::database::HistoryItem;
+use crate::modes::HistoryStorage;
+use regex::{Captures, Regex};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Phone numbers have at least this many digits; dates have at most eight
+const MIN_PHONE_DIGITS: usize = 9;
+
+const MAX_PHONE_DIGITS: usize = 15;
+
+struct Patterns {
+    email: Regex,
+    ssn: Regex,
+    card: Regex,
+    phone: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        // Also as transcribed from speech: "jane at example dot com"
+        email: Regex::new(r"(?i)\b[a-z0-9._%+-]+(?:@| at )[a-z0-9-]+(?:(?:\.| dot )[a-z0-9-]+)*(?:\.| dot )[a-z]{2,}\b")
+            .unwrap(),
+        ssn: Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").unwrap(),
+        card: Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").unwrap(),
+        phone: Regex::new(r"(?:\+|\b)\d[\d ().-]{6,}\d\b").unwrap(),
+    })
+}
+
+fn digits(text: &str) -> Vec<u32> {
+    text.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+/// Luhn checksum, which every payment card number passes
+// `is_multiple_of` would need Rust 1.87, newer than some distributions ship
+#[allow(clippy::manual_is_multiple_of)]
+fn luhn(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (1, doubled) if doubled > 9 => doubled - 9,
+            (1, doubled) => doubled,
+            _ => d,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+/// Replace personal data in `text` with placeholders
+pub fn redact(text: &str) -> String {
+    let patterns = patterns();
+    let text = patterns.email.replace_all(text, "[email]");
+    let text = patterns.ssn.replace_all(&text, "[SSN]");
+    let text = patterns.card.replace_all(&text, |caps: &Captures| {
+        if luhn(&digits(&caps[0])) {
+            "[card number]".to_string()
+        } else {
+            caps[0].to_string()
+        }
+    });
+    let text = patterns.phone.replace_all(&text, |caps: &Captures| {
+        if (MIN_PHONE_DIGITS..=MAX_PHONE_DIGITS).contains(&digits(&caps[0]).len()) {
+            "[phone]".to_string()
+        } else {
+            caps[0].to_string()
+        }
+    });
+    text.into_owned()
+}
+
+/// Text of a dictation as the event sink, hooks and journal may see it
+///
+/// They keep what they are sent, so they get what history keeps: the text,
+/// redacted text, or None. Summary storage keeps LLM output only, which
+/// `llm_output` says the text is.
+pub fn for_sinks(text: &str, storage: HistoryStorage, llm_output: bool) -> Option<String> {
+    match storage {
+        HistoryStorage::Full => Some(text.to_string()),
+        HistoryStorage::Redacted => Some(redact(text)),
+        HistoryStorage::Summary if llm_output => Some(text.to_string()),
+        HistoryStorage::Summary | HistoryStorage::Off => None,
+    }
+}
+
+/// Overwrite a file with zeros, then delete it
+///
+/// On copy-on-write filesystems and SSDs the old blocks may survive the
+/// overwrite; it still keeps the audio out of reach of file recovery tools.
+pub fn secure_delete(path: &Path) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+    let mut remaining = file.metadata()?.len();
+    let zeros = [0u8; 64 * 1024];
+    file.seek(SeekFrom::Start(0))?;
+    while remaining > 0 {
+        let n = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    std::fs::remove_file(path)
+}
+
+/// What of a finished dictation goes into history; None stores nothing
+///
+/// Unless everything is stored, the recording is destroyed. Summary storage
+/// keeps nothing when there is no LLM output, since the output would then be
+/// the transcript.
+pub fn for_storage(mut item: HistoryItem, storage: HistoryStorage) -> Option<HistoryItem> {
+    if storage == HistoryStorage::Full {
+        return Some(item);
+    }
+    if let Some(path) = item.audio_path.take() {
+        if let Err(e) = secure_delete(Path::new(&path)) {
+            log::warn!("Failed to delete recording {}: {}", path, e);
+        }
+    }
+    match storage {
+        HistoryStorage::Off => return None,
+        HistoryStorage::Summary if item.llm_provider.is_none() || item.error.is_some() => return None,
+        HistoryStorage::Summary => {
+            item.transcript_raw = String::new();
+            item.window_title = None;
+            item.source_url = None;
+            item.summary_only = true;
+            return Some(item);
+        }
+        _ => {}
+    }
+
+    item.transcript_raw = redact(&item.transcript_raw);
+    item.output_final = redact(&item.output_final);
+    // Window titles and URLs show email subjects and document names
+    item.window_title = None;
+    item.source_url = None;
+    if let Some(error) = item.error.as_mut() {
+        error.message = redact(&error.message);
+    }
+    Some(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact("Mail jane.doe@example.com or jane at example dot co dot uk"),
+            "Mail [email] or [email]"
+        );
+        assert_eq!(
+            redact("Call +1 (415) 555-0132 or 030 1234 5678, SSN 123-45-6789"),
+            "Call [phone] or [phone], SSN [SSN]"
+        );
+        assert_eq!(redact("Card 4111 1111 1111 1111 expires soon"), "Card [card number] expires soon");
+        // Dates, amounts and short numbers stay
+        let kept = "Due 2024-05-17, 3 items at 1,250.00, room 5551234";
+        assert_eq!(redact(kept), kept);
+    }
+
+    #[test]
+    fn test_for_sinks() {
+        let text = "Mail jane.doe@example.com";
+        assert_eq!(for_sinks(text, HistoryStorage::Full, false).as_deref(), Some(text));
+        assert_eq!(for_sinks(text, HistoryStorage::Redacted, false).as_deref(), Some("Mail [email]"));
+        assert_eq!(for_sinks(text, HistoryStorage::Summary, true).as_deref(), Some(text));
+        assert_eq!(for_sinks(text, HistoryStorage::Summary, false), None);
+        assert_eq!(for_sinks(text, HistoryStorage::Off, true), None);
+    }
+}
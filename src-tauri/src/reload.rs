@@ -0,0 +1,249 @@
+//! Reloading settings and modes edited on disk
+//!
+//! The settings file and the modes folder are polled, like the watch folder,
+//! and once a change has settled it is applied without a restart: settings as
+//! when saved from the Settings page, modes into the tray menu and pickers.
+//! A file that does not parse is reported and the running configuration is
+//! kept. Every reload emits `config-reloaded` with what changed and the
+//! problems found, so the UI can refresh and show them. Ports and the
+//! hotkeys are still only read at startup.
+
+use crate::modes::{get_modes_dir, Mode};
+use crate::state::{AppState, SharedState};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often the config files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A problem found in a config file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigIssue {
+    /// File name, e.g. "settings.json" or "email.json"
+    pub file: String,
+    pub message: String,
+}
+
+/// What a reload changed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConfigReload {
+    /// New settings were applied
+    pub settings: bool,
+    /// The modes were reloaded
+    pub modes: bool,
+    pub issues: Vec<ConfigIssue>,
+}
+
+/// Size and modification time (ms since the epoch) of a file
+type Stamp = (u64, i64);
+
+/// Stamps of the settings file and of each mode file
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    settings: Option<Stamp>,
+    modes: Vec<(PathBuf, Stamp)>,
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = std::fs::metadata(path).ok().filter(|m| m.is_file())?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_millis() as i64))
+}
+
+/// Mode files in `dir`, sorted
+fn mode_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn snapshot(settings_path: &Path, modes_dir: &Path) -> Snapshot {
+    Snapshot {
+        settings: stamp(settings_path),
+        modes: mode_files(modes_dir)
+            .into_iter()
+            .filter_map(|path| stamp(&path).map(|stamp| (path, stamp)))
+            .collect(),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Problems with the mode files in `dir`
+///
+/// Files that don't parse are left out when modes load; modes with invalid
+/// fields still load, but may fail when used.
+fn check_modes(dir: &Path) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    for path in mode_files(dir) {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str::<Mode>(&content).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(mode) => issues.extend(crate::modes::validate(&mode).into_iter().map(|issue| ConfigIssue {
+                file: file_name(&path),
+                message: format!("{}: {}", issue.field, issue.message),
+            })),
+            Err(e) => issues.push(ConfigIssue {
+                file: file_name(&path),
+                message: e,
+            }),
+        }
+    }
+    issues
+}
+
+/// Watch the settings file and modes folder, applying changes as they are made
+pub fn spawn(app: AppHandle) {
+    let (settings_path, modes_dir) = match (AppState::get_settings_path(), get_modes_dir()) {
+        (Ok(settings_path), Ok(modes_dir)) => (settings_path, modes_dir),
+        _ => {
+            log::warn!("Config directory unknown, not watching for changes");
+            return;
+        }
+    };
+
+    tauri::async_runtime::spawn(async move {
+        let mut applied = snapshot(&settings_path, &modes_dir);
+        let mut seen = applied.clone();
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            // Wait for a change to settle, so half-written files aren't read
+            let current = snapshot(&settings_path, &modes_dir);
+            if current != seen {
+                seen = current;
+                continue;
+            }
+            if current == applied {
+                continue;
+            }
+            let settings_changed = current.settings.is_some() && current.settings != applied.settings;
+            let modes_changed = current.modes != applied.modes;
+            applied = current;
+
+            let Some(state) = app.try_state::<SharedState>() else {
+                continue;
+            };
+            let state = state.inner().clone();
+            let changed_modes = modes_changed.then_some(modes_dir.as_path());
+            let reload = reload(&app, &state, settings_changed, changed_modes).await;
+            if reload.settings || reload.modes || !reload.issues.is_empty() {
+                log::info!(
+                    "Config reloaded (settings: {}, modes: {}, {} issues)",
+                    reload.settings,
+                    reload.modes,
+                    reload.issues.len()
+                );
+                let _ = app.emit("config-reloaded", &reload);
+            }
+        }
+    });
+}
+
+/// Re-read the settings and, if `modes_dir` is given, the modes, applying what differs
+async fn reload(app: &AppHandle, state: &SharedState, settings: bool, modes_dir: Option<&Path>) -> ConfigReload {
+    let mut reload = ConfigReload::default();
+
+    if settings {
+        match AppState::load_settings() {
+            Ok(settings) => {
+                let mut state = state.lock().await;
+                // Saves from the app itself land here too, and change nothing
+                // A key pasted into the file is moved to secure storage on the next lookup
+                crate::secrets::clear_cache();
+                if serde_json::to_value(&settings).ok() != serde_json::to_value(&state.settings).ok() {
+                    crate::commands::switch_settings(&mut state, settings);
+                    let active_mode_key = state.settings.active_mode_key.clone();
+                    if state.modes.contains_key(&active_mode_key) {
+                        state.active_mode_key = active_mode_key;
+                    } else {
+                        state.settings.active_mode_key = state.active_mode_key.clone();
+                    }
+                    reload.settings = true;
+                }
+            }
+            Err(e) => reload.issues.push(ConfigIssue {
+                file: "settings.json".to_string(),
+                message: format!("{}; keeping the previous settings", e),
+            }),
+        }
+    }
+
+    if let Some(dir) = modes_dir {
+        reload.issues.extend(check_modes(dir));
+        match crate::modes::load_modes().await {
+            Ok(modes) => {
+                let mut state = state.lock().await;
+                if serde_json::to_value(&modes).ok() != serde_json::to_value(&state.modes).ok() {
+                    state.modes = modes;
+                    if !state.modes.contains_key(&state.active_mode_key) {
+                        state.active_mode_key = "voice_to_text".to_string();
+                    }
+                    reload.modes = true;
+                }
+            }
+            Err(e) => reload.issues.push(ConfigIssue {
+                file: "modes".to_string(),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    if reload.settings || reload.modes {
+        let state = state.lock().await;
+        if let Err(e) = crate::tray::update_tray_menu(app, &state).await {
+            log::warn!("Failed to update tray menu: {}", e);
+        }
+    }
+    reload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_snapshot() {
+        let dir = tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+        let modes = dir.path().join("modes");
+        std::fs::create_dir(&modes).unwrap();
+        std::fs::write(modes.join("email.json"), "{}").unwrap();
+        std::fs::write(modes.join("notes.txt"), "ignored").unwrap();
+
+        let before = snapshot(&settings, &modes);
+        assert_eq!(before.settings, None);
+        assert_eq!(before.modes.len(), 1);
+        assert_eq!(snapshot(&settings, &modes), before);
+
+        std::fs::write(&settings, "{}").unwrap();
+        std::fs::write(modes.join("email.json"), "{ }").unwrap();
+        let after = snapshot(&settings, &modes);
+        assert!(after.settings.is_some());
+        assert_ne!(after.modes, before.modes);
+    }
+
+    #[test]
+    fn test_check_modes() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("broken.json"), "{ \"key\": ").unwrap();
+        let issues = check_modes(dir.path());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].file, "broken.json");
+    }
+}
@@ -0,0 +1,290 @@
+//! Remote output
+//!
+//! Sends dictation output to another machine instead of pasting it here, for
+//! dictating on a laptop into a workstation you are screen-sharing into.
+//! The text is either piped over ssh into a command on the remote host (e.g.
+//! `cat >> notes.md` or `wl-copy`), or posted to a paired WhisperTray
+//! instance, which pastes it as if it had been dictated there.
+
+use crate::error::{AppError, Result};
+use crate::state::SharedState;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Largest request body the bridge accepts
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Largest request head (request line and headers) read before giving up
+const MAX_HEADERS: usize = 16 * 1024;
+
+/// How long ssh may take to connect before the send fails
+const SSH_CONNECT_TIMEOUT_SECS: u32 = 10;
+
+/// Where output is sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum RemoteTarget {
+    /// Run `command` on `host` over ssh with the text on stdin
+    Ssh { host: String, command: String },
+    /// Post to a paired instance's bridge, e.g. `http://workstation:7710`
+    Paired { url: String, token: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct InsertRequest {
+    text: String,
+}
+
+/// Send text to the remote target
+pub async fn send(target: &RemoteTarget, text: &str) -> Result<()> {
+    match target {
+        RemoteTarget::Ssh { host, command } => {
+            let (host, command, text) = (host.clone(), command.clone(), text.to_string());
+            tokio::task::spawn_blocking(move || send_ssh(&host, &command, &text))
+                .await
+                .map_err(|e| AppError::Provider(format!("ssh task failed: {}", e)))?
+        }
+        RemoteTarget::Paired { url, token } => {
+            let response = reqwest::Client::new()
+                .post(format!("{}/insert", url.trim_end_matches('/')))
+                .bearer_auth(token)
+                .json(&InsertRequest { text: text.to_string() })
+                .timeout(Duration::from_secs(10))
+                .send()
+                .await?;
+            if response.status().is_success() {
+                Ok(())
+            } else {
+                Err(AppError::Provider(format!("Paired instance refused the text ({})", response.status())))
+            }
+        }
+    }
+}
+
+fn send_ssh(host: &str, command: &str, text: &str) -> Result<()> {
+    // A host starting with a dash would be read as an option, e.g. -oProxyCommand=...
+    if host.is_empty() || host.starts_with('-') {
+        return Err(AppError::Config(format!("Invalid ssh host: {:?}", host)));
+    }
+    // BatchMode fails instead of prompting for a password we cannot answer
+    let connect_timeout = format!("ConnectTimeout={}", SSH_CONNECT_TIMEOUT_SECS);
+    let mut child = Command::new("ssh")
+        .args(["-o", "BatchMode=yes", "-o", &connect_timeout, "--", host, command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::Provider(format!("Failed to run ssh: {}", e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(AppError::Provider(format!("ssh {} failed: {}", host, stderr.trim())))
+    }
+}
+
+/// Accept text from paired instances and paste it here
+///
+/// Listens on localhost, for instances reaching it through an ssh tunnel,
+/// unless `lan` is set. The bridge is plain HTTP, so on the network the token
+/// and text travel in the clear; every request must carry `token` either way.
+pub async fn serve_bridge(port: u16, token: String, lan: bool, state: SharedState) -> Result<()> {
+    if token.trim().is_empty() {
+        return Err(AppError::Config("The paste bridge needs a token".to_string()));
+    }
+
+    let address = if lan { "0.0.0.0" } else { "127.0.0.1" };
+    let listener = TcpListener::bind((address, port)).await?;
+    if lan {
+        log::warn!("Paste bridge listening on all interfaces, port {} (unencrypted)", port);
+    } else {
+        log::info!("Paste bridge listening on 127.0.0.1:{}", port);
+    }
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let (token, state) = (token.clone(), state.clone());
+
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &token, &state).await {
+                log::warn!("Paste bridge request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, token: &str, state: &SharedState) -> Result<()> {
+    let request = read_request(&mut stream, MAX_BODY).await?;
+
+    let (code, text) = match parse_request(&request, token) {
+        Ok(text) => ("200 OK", Some(text)),
+        Err(code) => (code, None),
+    };
+    let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", code);
+    stream.write_all(response.as_bytes()).await?;
+    let _ = stream.shutdown().await;
+
+    if let Some(text) = text {
+        let (paste_settings, rules) = {
+            let state = state.lock().await;
+            (state.settings.paste, state.settings.paste_rules.clone())
+        };
+        log::info!("Pasting {} chars from a paired instance", text.chars().count());
+        tokio::task::spawn_blocking(move || crate::paste::copy_and_paste(&text, true, &paste_settings, &rules))
+            .await
+            .map_err(|e| AppError::Clipboard(format!("Paste task failed: {}", e)))??;
+    }
+    Ok(())
+}
+
+/// Read the headers and, up to `max_body` bytes, the body of one request
+pub(crate) async fn read_request(stream: &mut TcpStream, max_body: usize) -> Result<Vec<u8>> {
+    let mut request = read_headers(stream).await?;
+    if let Some(header_end) = find_header_end(&request) {
+        let headers = String::from_utf8_lossy(&request[..header_end]).into_owned();
+        let length = content_length(&headers).unwrap_or(0).min(max_body);
+        read_body(stream, &mut request, header_end, length).await?;
+    }
+    Ok(request)
+}
+
+/// Read until the end of the headers, which may bring part of the body along
+///
+/// Stops early at `MAX_HEADERS` bytes or when the peer closes, so a request
+/// can be checked before its body is read.
+pub(crate) async fn read_headers(stream: &mut TcpStream) -> Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    while find_header_end(&request).is_none() && request.len() <= MAX_HEADERS {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    Ok(request)
+}
+
+/// Read the rest of a `length`-byte body into `request`
+pub(crate) async fn read_body(
+    stream: &mut TcpStream,
+    request: &mut Vec<u8>,
+    header_end: usize,
+    length: usize,
+) -> Result<()> {
+    let mut buf = [0u8; 4096];
+    while request.len() < header_end + 4 + length {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    request.truncate(header_end + 4 + length);
+    Ok(())
+}
+
+pub(crate) fn find_header_end(request: &[u8]) -> Option<usize> {
+    request.windows(4).position(|w| w == b"\r\n\r\n")
+}
+
+pub(crate) fn content_length(headers: &str) -> Option<usize> {
+    headers.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("content-length").then(|| value.trim().parse().ok())?
+    })
+}
+
+/// Check a raw request is an authorized `POST /insert`, returning its text
+/// or the HTTP status to reply with
+fn parse_request(request: &[u8], token: &str) -> std::result::Result<String, &'static str> {
+    let header_end = find_header_end(request).ok_or("400 Bad Request")?;
+    let headers = String::from_utf8_lossy(&request[..header_end]);
+
+    let mut request_line = headers.lines().next().unwrap_or_default().split_whitespace();
+    if (request_line.next(), request_line.next()) != (Some("POST"), Some("/insert")) {
+        return Err("404 Not Found");
+    }
+
+    let authorized = headers.lines().any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("authorization")
+                && value.trim().strip_prefix("Bearer ").is_some_and(|t| tokens_match(t, token))
+        })
+    });
+    if !authorized {
+        return Err("401 Unauthorized");
+    }
+
+    let body = &request[header_end + 4..];
+    let body = &body[..content_length(&headers).unwrap_or(body.len()).min(body.len())];
+    serde_json::from_slice::<InsertRequest>(body)
+        .map(|request| request.text)
+        .map_err(|_| "400 Bad Request")
+}
+
+/// Compare tokens without returning early on the first difference
+pub(crate) fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(auth: &str, body: &str) -> Vec<u8> {
+        format!(
+            "POST /insert HTTP/1.1\r\nHost: x\r\nAuthorization: {}\r\nContent-Length: {}\r\n\r\n{}",
+            auth,
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let body = r#"{"text":"hello there"}"#;
+        assert_eq!(parse_request(&request("Bearer s3cret", body), "s3cret"), Ok("hello there".to_string()));
+        assert_eq!(parse_request(&request("Bearer wrong!", body), "s3cret"), Err("401 Unauthorized"));
+        assert_eq!(parse_request(&request("Bearer s3cret", "nope"), "s3cret"), Err("400 Bad Request"));
+        assert_eq!(
+            parse_request(b"GET /health HTTP/1.1\r\n\r\n", "s3cret"),
+            Err("404 Not Found")
+        );
+    }
+
+    #[test]
+    fn test_send_ssh_rejects_option_hosts() {
+        let err = send_ssh("-oProxyCommand=touch /tmp/pwned", "cat", "hi").unwrap_err();
+        assert!(err.to_string().contains("Invalid ssh host"));
+    }
+
+    #[test]
+    fn test_target_serde() {
+        let target: RemoteTarget =
+            serde_json::from_str(r#"{"kind":"ssh","host":"workstation","command":"cat >> notes.md"}"#).unwrap();
+        assert_eq!(
+            target,
+            RemoteTarget::Ssh {
+                host: "workstation".to_string(),
+                command: "cat >> notes.md".to_string()
+            }
+        );
+    }
+}
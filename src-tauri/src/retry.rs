@@ -0,0 +1,310 @@
+//! Retrying transient provider failures
+//!
+//! Rate limits (429), server errors (5xx), timeouts and dropped connections
+//! usually clear up within seconds. Instead of failing the dictation, LLM
+//! and cloud STT requests are retried with exponential backoff as set by
+//! the `retry` setting. Retries made for a dictation are counted and stored
+//! with its error when it fails anyway.
+
+use crate::error::{AppError, Result};
+use crate::providers::llm::{Completion, DeltaSender, LlmProvider, STREAM_BUFFER};
+use crate::providers::stt::SttProvider;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::Cell;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+/// Kind of transient failure a policy can retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryClass {
+    /// HTTP 429
+    RateLimited,
+    /// HTTP 5xx, e.g. 503 while a provider is overloaded
+    ServerError,
+    /// Timeouts and failed connections
+    Network,
+}
+
+impl RetryClass {
+    /// Class of a transient failure; None for errors a retry will not fix
+    pub fn of(error: &AppError) -> Option<Self> {
+        let status = match error {
+            AppError::Status { status, .. } => *status,
+            AppError::Http(e) if e.is_timeout() || e.is_connect() => return Some(RetryClass::Network),
+            AppError::Http(e) => e.status()?.as_u16(),
+            AppError::ProviderUnavailable(_) => return Some(RetryClass::Network),
+            _ => return None,
+        };
+        match status {
+            429 => Some(RetryClass::RateLimited),
+            500..=599 => Some(RetryClass::ServerError),
+            _ => None,
+        }
+    }
+}
+
+/// How provider requests are retried
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Tries per request, including the first; 1 disables retrying
+    pub attempts: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub initial_backoff_ms: u64,
+    /// Longest wait between tries
+    pub max_backoff_ms: u64,
+    /// Wait a random 50-100% of each backoff, so clients hitting the same
+    /// limit do not retry in lockstep
+    pub jitter: bool,
+    /// Failures that are retried
+    pub retry_on: Vec<RetryClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 8_000,
+            jitter: true,
+            retry_on: vec![RetryClass::RateLimited, RetryClass::ServerError, RetryClass::Network],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether this policy retries `error`
+    pub fn retries(&self, error: &AppError) -> bool {
+        RetryClass::of(error).is_some_and(|class| self.retry_on.contains(&class))
+    }
+
+    /// Wait before retry number `retry`, counting from 1
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let doubling = retry.saturating_sub(1).min(16);
+        let ms = self
+            .initial_backoff_ms
+            .saturating_mul(1 << doubling)
+            .min(self.max_backoff_ms);
+        let ms = if self.jitter && ms > 1 {
+            ms / 2 + random() % (ms - ms / 2 + 1)
+        } else {
+            ms
+        };
+        Duration::from_millis(ms)
+    }
+
+    /// Run `request`, retrying transient failures
+    pub async fn run<T, F, Fut>(&self, what: &str, request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.run_while(what, request, || true).await
+    }
+
+    /// Run `request`, retrying transient failures while `may_retry` allows
+    pub async fn run_while<T, F, Fut>(&self, what: &str, mut request: F, may_retry: impl Fn() -> bool) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut tried = 0;
+        loop {
+            tried += 1;
+            let error = match request().await {
+                Err(e) if tried < self.attempts && self.retries(&e) && may_retry() => e,
+                result => return result,
+            };
+            let wait = self.backoff(tried);
+            log::warn!(
+                "{} failed ({}), retry {}/{} in {} ms",
+                what,
+                error,
+                tried,
+                self.attempts - 1,
+                wait.as_millis()
+            );
+            let _ = RETRIES.try_with(|retries| retries.set(retries.get() + 1));
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Random number for jitter; a fresh `RandomState` is randomly seeded
+fn random() -> u64 {
+    std::collections::hash_map::RandomState::new().build_hasher().finish()
+}
+
+static POLICY: RwLock<Option<RetryPolicy>> = RwLock::new(None);
+
+/// Set the policy used by all providers
+pub fn set_policy(policy: RetryPolicy) {
+    *POLICY.write().unwrap_or_else(PoisonError::into_inner) = Some(policy);
+}
+
+/// Policy currently in use
+pub fn policy() -> RetryPolicy {
+    POLICY.read().unwrap_or_else(PoisonError::into_inner).clone().unwrap_or_default()
+}
+
+tokio::task_local! {
+    static RETRIES: Cell<u32>;
+}
+
+/// Run `work`, also returning the number of retries made within it
+pub async fn counting<F: Future>(work: F) -> (F::Output, u32) {
+    RETRIES
+        .scope(Cell::new(0), async {
+            let output = work.await;
+            (output, RETRIES.with(Cell::get))
+        })
+        .await
+}
+
+/// A provider whose requests are retried per the current policy
+pub struct Retrying<P: ?Sized>(pub Box<P>);
+
+#[async_trait]
+impl LlmProvider for Retrying<dyn LlmProvider> {
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        policy().run(self.0.name(), || self.0.complete(prompt)).await
+    }
+
+    async fn complete_with_usage(&self, prompt: &str) -> Result<Completion> {
+        policy().run(self.0.name(), || self.0.complete_with_usage(prompt)).await
+    }
+
+    async fn complete_json(&self, prompt: &str, schema: &Value) -> Result<Completion> {
+        policy().run(self.0.name(), || self.0.complete_json(prompt, schema)).await
+    }
+
+    async fn complete_streaming(&self, prompt: &str, deltas: &DeltaSender) -> Result<Completion> {
+        // Starting over once text was passed on would repeat it
+        let streamed = &AtomicBool::new(false);
+        let inner = &self.0;
+        let request = move || async move {
+            let (sender, mut receiver) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+            let request = async move { inner.complete_streaming(prompt, &sender).await };
+            let forward = async {
+                while let Some(delta) = receiver.recv().await {
+                    streamed.store(true, Ordering::SeqCst);
+                    let _ = deltas.send(delta).await;
+                }
+            };
+            tokio::join!(request, forward).0
+        };
+        policy()
+            .run_while(self.0.name(), request, || !streamed.load(Ordering::SeqCst))
+            .await
+    }
+
+    async fn warm_up(&self) -> Result<()> {
+        self.0.warm_up().await
+    }
+
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+}
+
+/// Only `transcribe` is overridden; the default chunk, cancellable and
+/// scored methods go through it and so are retried too
+#[async_trait]
+impl SttProvider for Retrying<dyn SttProvider> {
+    async fn transcribe(&self, samples: &[f32], language: Option<&str>) -> Result<String> {
+        policy().run(self.0.name(), || self.0.transcribe(samples, language)).await
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.0.health_check().await
+    }
+
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    fn status(status: u16) -> AppError {
+        AppError::Status {
+            provider: "Test".to_string(),
+            status,
+            body: String::new(),
+        }
+    }
+
+    fn quick() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff_ms: 1,
+            max_backoff_ms: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_classes() {
+        assert_eq!(RetryClass::of(&status(429)), Some(RetryClass::RateLimited));
+        assert_eq!(RetryClass::of(&status(503)), Some(RetryClass::ServerError));
+        assert_eq!(RetryClass::of(&status(401)), None);
+        assert_eq!(
+            RetryClass::of(&AppError::ProviderUnavailable("timed out".to_string())),
+            Some(RetryClass::Network)
+        );
+        assert_eq!(RetryClass::of(&AppError::Provider("bad".to_string())), None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        };
+        let waits: Vec<u64> = (1..=6).map(|retry| policy.backoff(retry).as_millis() as u64).collect();
+        assert_eq!(waits, vec![500, 1_000, 2_000, 4_000, 8_000, 8_000]);
+
+        let jittered = RetryPolicy::default();
+        for _ in 0..20 {
+            let wait = jittered.backoff(2).as_millis();
+            assert!((500..=1_000).contains(&wait), "{}", wait);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_failures() {
+        let tries = AtomicU32::new(0);
+        let request = || async {
+            match tries.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(status(503)),
+                _ => Ok("done"),
+            }
+        };
+        let (result, retries) = counting(quick().run("test", request)).await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(retries, 1);
+
+        // Gives up after the last attempt, and never retries other errors
+        let (result, retries) = counting(quick().run("test", || async { Err::<(), _>(status(429)) })).await;
+        assert!(matches!(result, Err(AppError::Status { status: 429, .. })));
+        assert_eq!(retries, 2);
+
+        let (_, retries) = counting(quick().run("test", || async { Err::<(), _>(status(400)) })).await;
+        assert_eq!(retries, 0);
+
+        let only_rate_limits = RetryPolicy {
+            retry_on: vec![RetryClass::RateLimited],
+            ..quick()
+        };
+        let (_, retries) = counting(only_rate_limits.run("test", || async { Err::<(), _>(status(500)) })).await;
+        assert_eq!(retries, 0);
+    }
+}
@@ -0,0 +1,166 @@
+//! Sample audio for trying out modes
+//!
+//! Clips are bundled in `resources/samples` and can be added by the user in
+//! the `samples` data directory. A `<name>.txt` next to a clip holds its
+//! expected transcript, used to score test runs.
+
+use crate::error::{AppError, Result};
+use crate::latency::LatencySpan;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A sample clip available for mode tests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleClip {
+    /// File stem, unique across bundled and user samples
+    pub id: String,
+    pub path: PathBuf,
+    /// Expected transcript, if a `.txt` sidecar exists
+    pub expected: Option<String>,
+    /// Whether the clip ships with the app
+    pub builtin: bool,
+}
+
+/// Stage outputs of running a mode on a sample
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModeTestResult {
+    pub mode_key: String,
+    pub sample_id: String,
+    /// Raw STT output
+    pub transcript: String,
+    /// LLM output, when the mode uses AI processing
+    pub llm_output: Option<String>,
+    /// LLM provider that produced `llm_output`
+    pub llm_provider: Option<String>,
+    /// LLM error, when AI processing failed and the transcript was used
+    pub llm_error: Option<String>,
+    /// Final text that would have been pasted
+    pub output: String,
+    /// Output was shortened or cut to fit the mode's length limit
+    pub truncated: bool,
+    pub expected: Option<String>,
+    /// Word error rate of the transcript against `expected`
+    pub word_error_rate: Option<f32>,
+    pub latency: Vec<LatencySpan>,
+}
+
+/// Get the directory for user-provided samples
+pub fn get_user_samples_dir() -> Result<PathBuf> {
+    let data_dir = directories::ProjectDirs::from("com", "whispertray", "WhisperTray")
+        .ok_or_else(|| AppError::Config("Could not determine data directory".to_string()))?
+        .data_dir()
+        .to_path_buf();
+
+    Ok(data_dir.join("samples"))
+}
+
+/// List bundled and user samples; user clips replace bundled ones with the same id
+pub fn list_samples(bundled_dir: Option<&Path>) -> Vec<SampleClip> {
+    let mut samples = Vec::new();
+    if let Some(dir) = bundled_dir {
+        samples.extend(scan_dir(dir, true));
+    }
+    if let Ok(dir) = get_user_samples_dir() {
+        for sample in scan_dir(&dir, false) {
+            samples.retain(|s: &SampleClip| s.id != sample.id);
+            samples.push(sample);
+        }
+    }
+    samples.sort_by(|a, b| a.id.cmp(&b.id));
+    samples
+}
+
+/// Find a sample by id
+pub fn find_sample(bundled_dir: Option<&Path>, id: &str) -> Result<SampleClip> {
+    list_samples(bundled_dir)
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| AppError::Config(format!("Sample not found: {}", id)))
+}
+
+fn scan_dir(dir: &Path, builtin: bool) -> Vec<SampleClip> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("wav"))
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_str()?.to_string();
+            let expected = std::fs::read_to_string(path.with_extension("txt"))
+                .ok()
+                .map(|text| text.trim().to_string());
+            Some(SampleClip {
+                id,
+                path,
+                expected,
+                builtin,
+            })
+        })
+        .collect()
+}
+
+fn normalize_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '\'')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Word error rate: word-level edit distance over the expected word count
+///
+/// Case and punctuation are ignored.
+pub fn word_error_rate(expected: &str, actual: &str) -> f32 {
+    let expected = normalize_words(expected);
+    let actual = normalize_words(actual);
+    if expected.is_empty() {
+        return if actual.is_empty() { 0.0 } else { 1.0 };
+    }
+
+    let mut prev: Vec<usize> = (0..=actual.len()).collect();
+    for (i, e) in expected.iter().enumerate() {
+        let mut row = vec![i + 1; actual.len() + 1];
+        for (j, a) in actual.iter().enumerate() {
+            let substitution = prev[j] + usize::from(e != a);
+            row[j + 1] = substitution.min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        prev = row;
+    }
+
+    prev[actual.len()] as f32 / expected.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_error_rate() {
+        assert_eq!(word_error_rate("Hello, world.", "hello world"), 0.0);
+        assert_eq!(word_error_rate("the quick brown fox", "the quick brown box"), 0.25);
+        assert_eq!(word_error_rate("one two", "one two three four"), 1.0);
+        assert_eq!(word_error_rate("", ""), 0.0);
+    }
+
+    #[test]
+    fn test_scan_dir_reads_expected_transcripts() {
+        let dir = std::env::temp_dir().join(format!("whispertray-samples-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("greeting.wav"), b"").unwrap();
+        std::fs::write(dir.join("greeting.txt"), "Hello there.\n").unwrap();
+        std::fs::write(dir.join("notes.md"), "ignored").unwrap();
+
+        let samples = scan_dir(&dir, true);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].id, "greeting");
+        assert_eq!(samples[0].expected.as_deref(), Some("Hello there."));
+    }
+}
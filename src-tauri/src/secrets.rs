@@ -0,0 +1,477 @@
+//! Storage for API keys and other secrets
+//!
+//! Secrets live in the system keyring (Secret Service). Where there is none,
+//! as on a headless box or a desktop without gnome-keyring or KWallet, they
+//! go to `secrets.json` in the config directory instead, encrypted with
+//! ChaCha20-Poly1305 under a key derived from the machine ID and user ID.
+//! That keeps them out of plain sight and useless when the file is copied
+//! elsewhere, but it is no match for the keyring against someone logged in as
+//! the same user. Keys that older versions or hand-edited configs left in
+//! `settings.json` are moved into secure storage the first time they are read.
+//! Secrets are cached once read, so looking one up doesn't touch the keyring
+//! or the files again.
+
+use crate::error::{AppError, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+/// Keyring service all secrets are stored under
+const SERVICE: &str = "whispertray";
+
+/// Current encrypted file version
+const FILE_VERSION: u32 = 1;
+
+/// Set once the keyring turned out to be missing, to log it only once
+static KEYRING_MISSING: AtomicBool = AtomicBool::new(false);
+
+/// Secrets already looked up, None for ones that aren't stored
+static CACHE: LazyLock<Mutex<HashMap<String, Option<String>>>> = LazyLock::new(Default::default);
+
+fn cached(name: &str) -> Option<Option<String>> {
+    CACHE.lock().ok()?.get(name).cloned()
+}
+
+fn cache(name: &str, value: Option<&str>) {
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.insert(name.to_string(), value.map(str::to_string));
+    }
+}
+
+/// Forget the cached secrets, so the next lookups read them again
+///
+/// For when the files may have been changed outside the app.
+pub fn clear_cache() {
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.clear();
+    }
+}
+
+/// Name of the secret holding a provider's API key
+pub fn api_key_name(provider: &str) -> String {
+    format!("{}_api_key", provider.to_lowercase())
+}
+
+/// Get a provider's API key
+pub fn get_api_key(provider: &str) -> Result<Option<String>> {
+    get(&api_key_name(provider))
+}
+
+/// Save a provider's API key
+pub fn set_api_key(provider: &str, key: &str) -> Result<()> {
+    set(&api_key_name(provider), key)
+}
+
+/// Delete a provider's API key
+pub fn delete_api_key(provider: &str) -> Result<()> {
+    delete(&api_key_name(provider))
+}
+
+/// Get a secret from the keyring, the encrypted file or, once, plaintext settings
+pub fn get(name: &str) -> Result<Option<String>> {
+    if let Some(value) = cached(name) {
+        return Ok(value);
+    }
+    let value = lookup(name)?;
+    cache(name, value.as_deref());
+    Ok(value)
+}
+
+fn lookup(name: &str) -> Result<Option<String>> {
+    if let Some(value) = keyring_get(name)? {
+        return Ok(Some(value));
+    }
+    if let Some(value) = SecretsFile::load(&file_path()?)?.get(name)? {
+        return Ok(Some(value));
+    }
+
+    let settings = crate::state::AppState::get_settings_path()?;
+    let Some(value) = read_plaintext(&settings, name)? else {
+        return Ok(None);
+    };
+    log::info!("Moving {} from settings.json to secure storage", name);
+    set(name, &value)?;
+    remove_plaintext(&settings, name)?;
+    Ok(Some(value))
+}
+
+/// Save a secret to the keyring, or to the encrypted file without one
+pub fn set(name: &str, value: &str) -> Result<()> {
+    let path = file_path()?;
+    // Whatever happens next, the stored value may have changed
+    CACHE.lock().map(|mut cache| cache.remove(name)).ok();
+    match keyring_entry(name)?.set_password(value) {
+        Ok(()) => {
+            // A copy saved while the keyring was unavailable is stale now
+            let mut file = SecretsFile::load(&path)?;
+            if file.remove(name) {
+                file.save(&path)?;
+            }
+            Ok(())
+        }
+        Err(e) if is_unavailable(&e) => {
+            keyring_missing(&e);
+            let mut file = SecretsFile::load(&path)?;
+            file.set(name, value)?;
+            file.save(&path)
+        }
+        Err(e) => Err(AppError::Keyring(format!("Failed to save {}: {}", name, e))),
+    }
+}
+
+/// Delete a secret wherever it is stored
+pub fn delete(name: &str) -> Result<()> {
+    CACHE.lock().map(|mut cache| cache.remove(name)).ok();
+    match keyring_entry(name)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) if is_unavailable(&e) => keyring_missing(&e),
+        Err(e) => {
+            return Err(AppError::Keyring(format!(
+                "Failed to delete {}: {}",
+                name, e
+            )))
+        }
+    }
+
+    let path = file_path()?;
+    let mut file = SecretsFile::load(&path)?;
+    if file.remove(name) {
+        file.save(&path)?;
+    }
+    remove_plaintext(&crate::state::AppState::get_settings_path()?, name)
+}
+
+/// Whether a secret is stored
+pub fn exists(name: &str) -> bool {
+    get(name).is_ok_and(|value| value.is_some())
+}
+
+fn keyring_entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name)
+        .map_err(|e| AppError::Keyring(format!("Failed to access keyring: {}", e)))
+}
+
+/// A secret from the keyring; None when it is not there or there is no keyring
+fn keyring_get(name: &str) -> Result<Option<String>> {
+    match keyring_entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) if is_unavailable(&e) => {
+            keyring_missing(&e);
+            Ok(None)
+        }
+        Err(e) => Err(AppError::Keyring(format!("Failed to get {}: {}", name, e))),
+    }
+}
+
+/// Errors meaning there is no usable keyring, rather than a problem with the entry
+fn is_unavailable(error: &keyring::Error) -> bool {
+    matches!(
+        error,
+        keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_)
+    )
+}
+
+fn keyring_missing(error: &keyring::Error) {
+    if !KEYRING_MISSING.swap(true, Ordering::SeqCst) {
+        log::warn!(
+            "No system keyring available ({}); keeping secrets in an encrypted file",
+            error
+        );
+    }
+}
+
+fn file_path() -> Result<PathBuf> {
+    let config_dir = directories::ProjectDirs::from("com", "whispertray", "WhisperTray")
+        .ok_or_else(|| AppError::Config("Could not determine config directory".to_string()))?
+        .config_dir()
+        .to_path_buf();
+    Ok(config_dir.join("secrets.json"))
+}
+
+fn machine_id() -> String {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// What ties the encrypted file to this machine and user
+///
+/// The user ID, unlike `$USER`, is the same for the desktop session, the
+/// systemd user service and cron jobs.
+fn machine_secret() -> String {
+    use std::os::unix::fs::MetadataExt;
+    let uid = std::fs::metadata("/proc/self").map(|m| m.uid()).unwrap_or_default();
+    format!("{}:uid{}", machine_id(), uid)
+}
+
+/// What older versions tied the file to, for migrating it
+fn legacy_machine_secret() -> String {
+    let user = std::env::var("USER").unwrap_or_default();
+    format!("{}:{}", machine_id(), user)
+}
+
+/// Secrets encrypted at rest, for systems without a keyring
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SecretsFile {
+    version: u32,
+    /// Random salt mixed into the key, hex encoded
+    salt: String,
+    /// Nonce followed by ciphertext, hex encoded, by secret name
+    entries: BTreeMap<String, String>,
+    /// Machine and user the key is derived from
+    #[serde(skip)]
+    machine: String,
+}
+
+impl SecretsFile {
+    fn load(path: &Path) -> Result<Self> {
+        let mut file = Self::load_for(path, machine_secret())?;
+        if file.migrate(&legacy_machine_secret()) {
+            log::info!("Re-encrypted the secrets file for this user ID");
+            file.save(path)?;
+        }
+        Ok(file)
+    }
+
+    fn load_for(path: &Path, machine: String) -> Result<Self> {
+        let mut file = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str::<SecretsFile>(&content)
+                .map_err(|e| AppError::Keyring(format!("Unreadable secrets file: {}", e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SecretsFile {
+                version: FILE_VERSION,
+                salt: to_hex(uuid::Uuid::new_v4().as_bytes()),
+                ..Default::default()
+            },
+            Err(e) => return Err(e.into()),
+        };
+        if file.version > FILE_VERSION {
+            return Err(AppError::Keyring(format!(
+                "Secrets file version {} is newer than supported ({})",
+                file.version, FILE_VERSION
+            )));
+        }
+        file.machine = machine;
+        Ok(file)
+    }
+
+    /// Write the file, readable only by the user from the moment it exists
+    ///
+    /// It is written next to its destination and renamed over it.
+    fn save(&self, path: &Path) -> Result<()> {
+        use std::os::unix::fs::OpenOptionsExt;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_extension("json.tmp");
+        // The mode only applies to a new file
+        let _ = std::fs::remove_file(&temp);
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&temp)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    fn cipher(&self, machine: &str) -> ChaCha20Poly1305 {
+        let key = Sha256::new()
+            .chain_update(b"whispertray-secrets\0")
+            .chain_update(self.salt.as_bytes())
+            .chain_update(machine.as_bytes())
+            .finalize();
+        ChaCha20Poly1305::new(Key::from_slice(&key))
+    }
+
+    /// Decrypt an entry with the key for `machine`
+    fn decrypt(&self, entry: &str, machine: &str) -> Option<String> {
+        let bytes = from_hex(entry).filter(|bytes| bytes.len() > 12)?;
+        let (nonce, ciphertext) = bytes.split_at(12);
+        let plaintext = self
+            .cipher(machine)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        let Some(entry) = self.entries.get(name) else {
+            return Ok(None);
+        };
+        self.decrypt(entry, &self.machine).map(Some).ok_or_else(|| {
+            AppError::Keyring(format!(
+                "Cannot decrypt {}; the secrets file was written on another machine or by another user",
+                name
+            ))
+        })
+    }
+
+    /// Re-encrypt entries only readable with the `legacy` key; true if any were
+    fn migrate(&mut self, legacy: &str) -> bool {
+        let migrated: Vec<(String, String)> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| self.decrypt(entry, &self.machine).is_none())
+            .filter_map(|(name, entry)| Some((name.clone(), self.decrypt(entry, legacy)?)))
+            .collect();
+        for (name, value) in &migrated {
+            if let Err(e) = self.set(name, value) {
+                log::warn!("Failed to re-encrypt {}: {}", name, e);
+            }
+        }
+        !migrated.is_empty()
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher(&self.machine)
+            .encrypt(&nonce, value.as_bytes())
+            .map_err(|e| AppError::Keyring(format!("Failed to encrypt {}: {}", name, e)))?;
+        let mut bytes = nonce.to_vec();
+        bytes.extend(ciphertext);
+        self.entries.insert(name.to_string(), to_hex(&bytes));
+        Ok(())
+    }
+
+    /// Remove a secret, returning whether it was there
+    fn remove(&mut self, name: &str) -> bool {
+        self.entries.remove(name).is_some()
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    // An odd last digit has no pair, so fails the `get`
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A secret left as a top-level string in a settings file
+fn read_plaintext(settings: &Path, name: &str) -> Result<Option<String>> {
+    let Ok(content) = std::fs::read_to_string(settings) else {
+        return Ok(None);
+    };
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return Ok(None);
+    };
+    Ok(json
+        .get(name)
+        .and_then(|value| value.as_str())
+        .filter(|value| !value.is_empty())
+        .map(str::to_string))
+}
+
+/// Remove a plaintext secret from a settings file, leaving the rest as is
+fn remove_plaintext(settings: &Path, name: &str) -> Result<()> {
+    let Ok(content) = std::fs::read_to_string(settings) else {
+        return Ok(());
+    };
+    let Ok(mut json) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return Ok(());
+    };
+    if let Some(object) = json.as_object_mut() {
+        if object.remove(name).is_some() {
+            std::fs::write(settings, serde_json::to_string_pretty(&json)?)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_secrets_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("secrets.json");
+
+        let mut file = SecretsFile::load_for(&path, "machine:user".to_string()).unwrap();
+        file.set("openai_api_key", "sk-test").unwrap();
+        file.save(&path).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("sk-test"));
+
+        let mut file = SecretsFile::load_for(&path, "machine:user".to_string()).unwrap();
+        assert_eq!(
+            file.get("openai_api_key").unwrap().as_deref(),
+            Some("sk-test")
+        );
+        assert_eq!(file.get("groq_api_key").unwrap(), None);
+        // Copied to another machine, the file cannot be read
+        let other = SecretsFile::load_for(&path, "other:user".to_string()).unwrap();
+        assert!(other.get("openai_api_key").is_err());
+
+        assert!(file.remove("openai_api_key"));
+        assert!(!file.remove("openai_api_key"));
+    }
+
+    #[test]
+    fn test_secrets_file_permissions_and_migration() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("secrets.json");
+
+        let mut file = SecretsFile::load_for(&path, "machine:alice".to_string()).unwrap();
+        file.set("groq_api_key", "gsk-test").unwrap();
+        file.save(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // Written under the user name, read under the user ID
+        let mut file = SecretsFile::load_for(&path, "machine:uid1000".to_string()).unwrap();
+        assert!(file.get("groq_api_key").is_err());
+        assert!(file.migrate("machine:alice"));
+        assert_eq!(file.get("groq_api_key").unwrap().as_deref(), Some("gsk-test"));
+        assert!(!file.migrate("machine:alice"));
+    }
+
+    #[test]
+    fn test_plaintext_migration() {
+        let dir = tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+        std::fs::write(
+            &settings,
+            r#"{"language": "en", "openai_api_key": "sk-old", "groq_api_key": ""}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_plaintext(&settings, "openai_api_key")
+                .unwrap()
+                .as_deref(),
+            Some("sk-old")
+        );
+        assert_eq!(read_plaintext(&settings, "groq_api_key").unwrap(), None);
+        remove_plaintext(&settings, "openai_api_key").unwrap();
+        let content = std::fs::read_to_string(&settings).unwrap();
+        assert!(!content.contains("sk-old"));
+        assert!(content.contains("\"language\""));
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0, 15, 255]), "000fff");
+        assert_eq!(from_hex("000fff"), Some(vec![0, 15, 255]));
+        assert_eq!(from_hex("0g"), None);
+        assert_eq!(from_hex("abc"), None);
+    }
+}
@@ -0,0 +1,241 @@
+//! Auto-segmentation of continuous dictation
+//!
+//! While recording, audio is cut at pauses every ~20-30 seconds; each
+//! segment is transcribed and pasted as soon as it is ready instead of
+//! waiting for the recording to stop.
+
+use crate::audio::RecordingHandle;
+use crate::cancel::CancelToken;
+use crate::error::{AppError, Result};
+use crate::modes::Mode;
+use crate::output::{OutputDelimiter, OutputRouter};
+use crate::paste::{self, PasteRule, PasteSettings};
+use crate::providers::stt;
+use crate::vad::{self, VadConfig};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+
+/// Segments are cut at the first pause after this much audio
+const SEGMENT_MIN_MS: u32 = 20_000;
+/// Segments are force-cut (at the quietest point) once this long, unless
+/// pacing lifts time limits
+pub const SEGMENT_MAX_MS: u32 = 30_000;
+/// How often the buffer is checked for a cut point
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Options for a segmenting session, captured when recording starts
+pub struct SegmenterOptions {
+    pub mode: Mode,
+    pub api_key: Option<String>,
+    pub server_url: Option<String>,
+    pub language: String,
+    pub auto_paste: bool,
+    pub paste: PasteSettings,
+    pub paste_rules: Vec<PasteRule>,
+    pub typography: bool,
+    pub delimiter: OutputDelimiter,
+    pub cancel: CancelToken,
+    /// Pause detection, from the pacing settings
+    pub vad: VadConfig,
+    /// Longest segment before a forced cut
+    pub max_segment_ms: u32,
+}
+
+/// Background task transcribing segments of the current recording
+pub struct Segmenter {
+    task: JoinHandle<Segmented>,
+}
+
+/// What the segmenter transcribed before the recording stopped
+#[derive(Default)]
+pub struct Segmented {
+    pub router: OutputRouter,
+    /// Samples from the start of the recording covered by `router`; the
+    /// rest, including segments that failed, is left for the final pass
+    pub transcribed_samples: usize,
+}
+
+/// Audio cut off the recording that is not transcribed yet
+///
+/// A segment that fails to transcribe is kept and sent again in front of
+/// the next one, so its speech is not lost.
+#[derive(Default)]
+struct Backlog {
+    samples: Vec<f32>,
+    /// Samples from the start of the recording transcribed so far
+    transcribed: usize,
+}
+
+impl Backlog {
+    /// Transcribe `segment` after any audio that failed before it
+    async fn transcribe(
+        &mut self,
+        provider: &dyn stt::SttProvider,
+        segment: Vec<f32>,
+        language: &str,
+        cancel: &CancelToken,
+    ) -> Result<String> {
+        self.samples.extend(segment);
+        let text = provider
+            .transcribe_cancellable(&self.samples, Some(language), cancel)
+            .await?;
+        self.transcribed += self.samples.len();
+        self.samples.clear();
+        Ok(text)
+    }
+}
+
+impl Segmenter {
+    /// Start segmenting the recording behind `handle`
+    pub fn spawn(handle: RecordingHandle, options: SegmenterOptions) -> Self {
+        // Segments are offloaded to the spool as they are cut
+        handle.set_spool_enabled(false);
+
+        let task = tauri::async_runtime::spawn(run(handle, options));
+        Self { task }
+    }
+
+    /// Wait for the in-flight segment (if any) and return the output so far
+    ///
+    /// Call after the recording has stopped.
+    pub async fn finish(self) -> Segmented {
+        match self.task.await {
+            Ok(segmented) => segmented,
+            Err(e) => {
+                log::error!("Segmenter task failed: {}", e);
+                Segmented::default()
+            }
+        }
+    }
+}
+
+async fn run(handle: RecordingHandle, options: SegmenterOptions) -> Segmented {
+    let mut router = OutputRouter::new(options.delimiter);
+    let mut backlog = Backlog::default();
+
+    let provider = match stt::create_stt_provider(
+        &options.mode.stt_provider,
+        &options.mode.stt_model,
+        options.api_key.clone(),
+        options.server_url.clone(),
+    )
+    .await
+    {
+        Ok(provider) => provider,
+        Err(e) => {
+            log::error!("Auto-segmentation disabled, STT provider unavailable: {}", e);
+            return Segmented {
+                router,
+                transcribed_samples: 0,
+            };
+        }
+    };
+
+    while handle.is_recording() {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        let segment = match handle
+            .take_segment(|buffer| vad::find_silence_cut(buffer, SEGMENT_MIN_MS, options.max_segment_ms, &options.vad))
+        {
+            Ok(Some(segment)) => segment,
+            Ok(None) => continue,
+            Err(e) => {
+                log::warn!("Failed to cut segment: {}", e);
+                continue;
+            }
+        };
+
+        log::info!(
+            "Transcribing segment {} ({} ms)",
+            router.len() + 1,
+            crate::audio::calculate_duration_ms(segment.len())
+        );
+
+        match backlog
+            .transcribe(provider.as_ref(), segment, &options.language, &options.cancel)
+            .await
+        {
+            Ok(_) if options.cancel.is_cancelled() => break,
+            Ok(text) => {
+                let text = if options.typography {
+                    crate::typography::apply(&text, &options.language)
+                } else {
+                    text
+                };
+                let insert = router.push(&text);
+                if !insert.is_empty() {
+                    paste_segment(&insert, options.auto_paste, options.paste, options.paste_rules.clone()).await;
+                }
+            }
+            Err(AppError::Cancelled) => break,
+            Err(e) => log::warn!("Segment transcription failed, retrying with the next segment: {}", e),
+        }
+    }
+
+    Segmented {
+        router,
+        transcribed_samples: backlog.transcribed,
+    }
+}
+
+/// Paste a segment, returns whether it was pasted
+pub async fn paste_segment(text: &str, auto_paste: bool, settings: PasteSettings, rules: Vec<PasteRule>) -> bool {
+    let text = text.to_string();
+
+    // Pasting sleeps to let the hotkey release, keep it off the async runtime
+    let result = tokio::task::spawn_blocking(move || paste::copy_and_paste(&text, auto_paste, &settings, &rules)).await;
+    match result {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            log::warn!("Failed to paste segment: {}", e);
+            false
+        }
+        Err(e) => {
+            log::warn!("Paste task failed: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails its first call, then reports how many samples it was given
+    #[derive(Default)]
+    struct FlakyProvider {
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl stt::SttProvider for FlakyProvider {
+        async fn transcribe(&self, samples: &[f32], _language: Option<&str>) -> Result<String> {
+            match self.calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(AppError::Transcription("server hiccup".to_string())),
+                _ => Ok(format!("{} samples", samples.len())),
+            }
+        }
+
+        fn name(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_segment_is_sent_again() {
+        let provider = FlakyProvider::default();
+        let cancel = CancelToken::new();
+        let mut backlog = Backlog::default();
+
+        assert!(backlog.transcribe(&provider, vec![0.0; 100], "en", &cancel).await.is_err());
+        assert_eq!(backlog.transcribed, 0);
+
+        // The failed segment's audio comes along with the next one
+        let text = backlog.transcribe(&provider, vec![0.0; 50], "en", &cancel).await.unwrap();
+        assert_eq!(text, "150 samples");
+        assert_eq!(backlog.transcribed, 150);
+        assert!(backlog.samples.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+//! Graceful shutdown
+//!
+//! Quitting from the tray, logging out (SIGTERM) or Ctrl+C no longer drops a
+//! dictation on the floor. A recording in progress is stopped and processed,
+//! and work already holding the state gets until the deadline to finish.
+//! Past the deadline it is cancelled and saved to history as interrupted,
+//! with its audio kept for a retry. The database's write-ahead log is then
+//! checkpointed before the app exits.
+
+use crate::cancel::CancelToken;
+use crate::state::SharedState;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Default time given to work in progress before it is interrupted
+pub const DEFAULT_DEADLINE_SECS: u64 = 10;
+
+/// Time cancelled work gets to save itself and release the state
+const CANCEL_GRACE: Duration = Duration::from_secs(3);
+
+static DEADLINE_SECS: AtomicU64 = AtomicU64::new(DEFAULT_DEADLINE_SECS);
+static STARTED: AtomicBool = AtomicBool::new(false);
+static FINISHED: AtomicBool = AtomicBool::new(false);
+
+/// Set how long work in progress may run once exit is requested
+pub fn set_deadline(secs: u64) {
+    DEADLINE_SECS.store(secs, Ordering::SeqCst);
+}
+
+/// Whether the app is draining work before exit
+pub fn is_shutting_down() -> bool {
+    STARTED.load(Ordering::SeqCst)
+}
+
+/// Handle an exit request, returning whether the app may exit now
+///
+/// The first request starts draining and is refused; the app exits with
+/// `code` once draining is done.
+pub fn on_exit_requested(app: &AppHandle, code: i32) -> bool {
+    if FINISHED.load(Ordering::SeqCst) {
+        return true;
+    }
+    if !STARTED.swap(true, Ordering::SeqCst) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            drain(&app).await;
+            FINISHED.store(true, Ordering::SeqCst);
+            app.exit(code);
+        });
+    }
+    false
+}
+
+/// Exit gracefully on SIGTERM (logout, `systemctl stop`) and Ctrl+C
+pub fn spawn_signal_handler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+                log::warn!("Could not listen for SIGTERM");
+                return;
+            };
+            tokio::select! {
+                _ = terminate.recv() => log::info!("Received SIGTERM"),
+                _ = tokio::signal::ctrl_c() => log::info!("Received Ctrl+C"),
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+            log::info!("Received Ctrl+C");
+        }
+        app.exit(0);
+    });
+}
+
+/// Finish or interrupt work in progress, then flush the database
+async fn drain(app: &AppHandle) {
+    let state = app.state::<SharedState>().inner().clone();
+    let cancel = app.state::<CancelToken>().inner().clone();
+    let deadline = Duration::from_secs(DEADLINE_SECS.load(Ordering::SeqCst));
+    log::info!("Shutting down (deadline {}s)", deadline.as_secs());
+
+    // Waiting for the lock waits for whatever is processing now
+    let finishing = state.clone();
+    let mut work = tokio::spawn(async move {
+        let recording = finishing.lock().await.is_recording();
+        if recording {
+            log::info!("Processing the recording in progress before exit");
+            if let Err(e) = crate::state::stop_recording(&finishing).await {
+                log::warn!("Recording in progress failed at exit: {}", e);
+            }
+        }
+    });
+    if tokio::time::timeout(deadline, &mut work).await.is_err() {
+        log::warn!("Shutdown deadline passed, interrupting work in progress");
+        cancel.cancel();
+        if tokio::time::timeout(CANCEL_GRACE, work).await.is_err() {
+            log::warn!("Work in progress did not stop in time");
+        }
+    }
+
+    let Ok(mut state) = tokio::time::timeout(CANCEL_GRACE, state.lock()).await else {
+        log::warn!("State still busy, exiting without flushing history");
+        return;
+    };
+    if state.is_recording() {
+        let _ = state.cancel_recording();
+    }
+    if let Some(db) = &state.database {
+        if let Err(e) = db.checkpoint() {
+            log::warn!("Failed to flush history: {}", e);
+        }
+    }
+    log::info!("Shutdown complete");
+}
@@ -0,0 +1,210 @@
+//! Audible feedback
+//!
+//! Short tones for dictating without looking at the screen: a rising beep
+//! when recording starts, a falling one when it stops, a chime when the text
+//! is pasted and a buzz when a dictation fails. They are synthesized rather
+//! than shipped as files, and played on the default output device through
+//! the same output stream as history playback. With `respect_dnd` they stay
+//! quiet while the desktop's do-not-disturb mode is on (GNOME, KDE, dunst or
+//! mako).
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use crate::playback::{open_output, Cursor};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Fade at each end of a note, so it starts and stops without a click
+const FADE_MS: u32 = 5;
+
+/// Loudest a tone gets at full volume, leaving headroom for harmonics
+const PEAK: f32 = 0.5;
+
+/// Sound settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedbackSounds {
+    pub enabled: bool,
+    /// 0.0 to 1.0
+    pub volume: f32,
+    /// Stay quiet while do-not-disturb is on
+    pub respect_dnd: bool,
+}
+
+impl Default for FeedbackSounds {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volume: 0.5,
+            respect_dnd: true,
+        }
+    }
+}
+
+/// What a sound signals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Earcon {
+    Start,
+    Stop,
+    Success,
+    Error,
+}
+
+impl Earcon {
+    /// Notes as (frequency in Hz, length in ms)
+    fn notes(self) -> &'static [(f32, u32)] {
+        match self {
+            Earcon::Start => &[(660.0, 70), (880.0, 90)],
+            Earcon::Stop => &[(880.0, 70), (660.0, 90)],
+            Earcon::Success => &[(784.0, 80), (1047.0, 140)],
+            Earcon::Error => &[(220.0, 120), (0.0, 40), (220.0, 160)],
+        }
+    }
+
+    /// The error buzz is rough on purpose, with odd harmonics
+    fn harmonics(self) -> &'static [f32] {
+        match self {
+            Earcon::Error => &[1.0, 0.0, 0.4, 0.0, 0.2],
+            _ => &[1.0],
+        }
+    }
+
+    /// Samples at 16kHz; a note of frequency 0 is a rest
+    fn synthesize(self, volume: f32) -> Vec<f32> {
+        let rate = WHISPER_SAMPLE_RATE;
+        let harmonics = self.harmonics();
+        let scale = PEAK * volume.clamp(0.0, 1.0) / harmonics.iter().sum::<f32>();
+        let fade = (rate * FADE_MS / 1000) as usize;
+
+        let mut samples = Vec::new();
+        for &(frequency, ms) in self.notes() {
+            let length = (rate * ms / 1000) as usize;
+            for i in 0..length {
+                if frequency == 0.0 {
+                    samples.push(0.0);
+                    continue;
+                }
+                let t = i as f32 / rate as f32;
+                let tone: f32 = harmonics
+                    .iter()
+                    .enumerate()
+                    .map(|(n, amplitude)| amplitude * (TAU * frequency * (n + 1) as f32 * t).sin())
+                    .sum();
+                let envelope = (i.min(length - 1 - i) as f32 / fade as f32).min(1.0);
+                samples.push(tone * scale * envelope);
+            }
+        }
+        samples
+    }
+}
+
+/// Play `earcon` in the background, if sounds are on and do-not-disturb allows
+pub fn play(earcon: Earcon, settings: FeedbackSounds) {
+    if !settings.enabled || settings.volume <= 0.0 {
+        return;
+    }
+    std::thread::spawn(move || {
+        if settings.respect_dnd && do_not_disturb() {
+            return;
+        }
+        let cursor = Arc::new(Cursor::new(earcon.synthesize(settings.volume)));
+        let stream = match open_output(cursor.clone()) {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::debug!("Could not play {:?} sound: {}", earcon, e);
+                return;
+            }
+        };
+        while !cursor.is_finished() {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        // Let the device drain its buffer before the stream is dropped
+        std::thread::sleep(Duration::from_millis(100));
+        drop(stream);
+    });
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    if !crate::paste::is_command_available(program) {
+        return None;
+    }
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether the desktop's do-not-disturb mode is on, as far as it can be told
+fn do_not_disturb() -> bool {
+    // GNOME hides banners while do-not-disturb is on
+    if let Some(output) = command_output("gsettings", &["get", "org.gnome.desktop.notifications", "show-banners"]) {
+        if output == "false" {
+            return true;
+        }
+    }
+    // KDE and other servers implementing the Inhibited property
+    if let Some(output) = command_output(
+        "gdbus",
+        &[
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.Notifications",
+            "--object-path",
+            "/org/freedesktop/Notifications",
+            "--method",
+            "org.freedesktop.DBus.Properties.Get",
+            "org.freedesktop.Notifications",
+            "Inhibited",
+        ],
+    ) {
+        if parse_inhibited(&output) {
+            return true;
+        }
+    }
+    if command_output("dunstctl", &["is-paused"]).is_some_and(|output| output == "true") {
+        return true;
+    }
+    command_output("makoctl", &["mode"]).is_some_and(|modes| mako_dnd(&modes))
+}
+
+/// Whether a reply to `Get(Inhibited)`, e.g. `(<true>,)`, says notifications are inhibited
+fn parse_inhibited(output: &str) -> bool {
+    output.contains("<true>")
+}
+
+/// Whether mako's active modes include a do-not-disturb one
+fn mako_dnd(modes: &str) -> bool {
+    modes
+        .split_whitespace()
+        .any(|mode| mode.contains("do-not-disturb") || mode == "dnd")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthesize() {
+        let start = Earcon::Start.synthesize(1.0);
+        assert_eq!(start.len(), (WHISPER_SAMPLE_RATE * 160 / 1000) as usize);
+        assert_eq!(start[0], 0.0);
+        assert!(start.iter().all(|sample| sample.abs() <= PEAK));
+
+        let quiet = Earcon::Error.synthesize(0.2);
+        let loudest = quiet.iter().fold(0.0f32, |max, sample| max.max(sample.abs()));
+        assert!(loudest > 0.0 && loudest <= PEAK * 0.2);
+        assert!(Earcon::Success.synthesize(0.0).iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn test_dnd_parsing() {
+        assert!(parse_inhibited("(<true>,)"));
+        assert!(!parse_inhibited("(<false>,)"));
+        assert!(mako_dnd("default\ndo-not-disturb"));
+        assert!(!mako_dnd("default"));
+    }
+}
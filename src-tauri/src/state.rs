@@ -1,17 +1,34 @@
 //! Application state management
 
-use crate::audio::RecordingHandle;
-use crate::database::{get_audio_dir, get_database_path, Database, HistoryItem};
-use crate::error::{AppError, Result};
-use crate::modes::{load_modes, Mode, LlmProvider as LlmProviderType, SttProvider as SttProviderType};
-use crate::paste;
+use crate::audio::{Bookmark, CaptureInput, CaptureSource, ChannelSelect, RecordedAudio, RecordingHandle};
+use crate::cancel::CancelToken;
+use crate::database::{self, get_audio_dir, get_database_path, Database, HistoryItem};
+use crate::error::{AppError, ErrorRecord, ErrorStage, Result};
+use crate::events::{EventSink, PipelineEvent};
+use crate::features::Flag;
+use crate::hooks::{HookEvent, HookVars};
+use crate::latency::LatencyTrace;
+use crate::modes::{load_modes, HistoryStorage, Mode, LlmProvider as LlmProviderType, PostProcessor, SttProvider as SttProviderType};
+use crate::output::OutputDelimiter;
+use crate::pacing::Pacing;
+use crate::picker::{Candidate, PickRequest, Picker};
+use crate::pipeline::{DryRunResult, LlmChain, Pipeline, PipelineOutput, StepOutput};
+use crate::paste::{self, ClipboardBackend, PasteRule, PasteSettings};
 use crate::providers::{llm, stt};
-use chrono::Utc;
+use crate::remote::{self, RemoteTarget};
+use crate::samples::{ModeTestResult, SampleClip};
+use crate::segmenter::{Segmenter, SegmenterOptions};
+use crate::sounds::Earcon;
+use crate::template::{self, ContextSource, PromptContext, PromptProfile, TemplateVars};
+use crate::text_stats::TextStats;
+use crate::warmup::WarmCache;
+use chrono::{Local, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tauri::AppHandle;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
 use uuid::Uuid;
 
 /// Recording status for the tray icon
@@ -54,12 +71,327 @@ pub struct Settings {
     pub auto_paste: bool,
     pub context_awareness: bool,
     pub language: String,
-    /// URL for self-hosted whisper server (used when stt_provider is WhisperServer)
+    /// URL for self-hosted whisper server (used when stt_provider is WhisperServer);
+    /// may name the endpoint itself, e.g. whisper.cpp's `/inference`
     #[serde(default)]
     pub whisper_server_url: Option<String>,
     /// URL for Ollama server (used when llm_provider is Ollama)
     #[serde(default)]
     pub ollama_url: Option<String>,
+    /// Groq API endpoint, for a proxy or gateway (default https://api.groq.com/openai)
+    #[serde(default)]
+    pub groq_url: Option<String>,
+    /// Wyoming server address as `host:port` (default localhost:10300)
+    #[serde(default)]
+    pub wyoming_url: Option<String>,
+    /// Apply locale typography (smart quotes, decimal separators) to output
+    #[serde(default)]
+    pub typography: bool,
+    /// Lock down settings, API keys and history deletion (kiosk/demo machines).
+    /// Can also be forced with the WHISPERTRAY_READ_ONLY environment variable.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Default capture source (microphone or system audio)
+    #[serde(default)]
+    pub capture_source: CaptureSource,
+    /// Monitor source for system audio capture (empty means the default output)
+    #[serde(default)]
+    pub monitor_device: String,
+    /// When recording both sources, keep them as separate channels in the saved audio
+    #[serde(default)]
+    pub save_two_track: bool,
+    /// Skip silent regions (via VAD) when transcribing long files
+    #[serde(default)]
+    pub vad_skip_silence: bool,
+    /// Transcribe the few seconds after each bookmark as its label
+    #[serde(default)]
+    pub bookmark_labels: bool,
+    /// Optional global hotkeys (bookmark, cancel, incognito, pause), read at startup
+    #[serde(default)]
+    pub hotkeys: crate::hotkey::Hotkeys,
+    /// Joiner between successive dictations appended to the same output
+    #[serde(default)]
+    pub output_delimiter: OutputDelimiter,
+    /// Port for the localhost /health endpoint (disabled when None)
+    #[serde(default)]
+    pub health_port: Option<u16>,
+    /// Opt-in: allow typing through /dev/uinput (ydotool)
+    #[serde(default)]
+    pub allow_evdev_input: bool,
+    /// Opt-in: allow running user-configured shell commands
+    #[serde(default)]
+    pub allow_shell_hooks: bool,
+    /// Opt-in: allow capturing the screen
+    #[serde(default)]
+    pub allow_screenshots: bool,
+    /// Pause, auto-stop and filler-word options (accessibility preset)
+    #[serde(default)]
+    pub pacing: Pacing,
+    /// Directory for daily journal Markdown files (database only when None)
+    #[serde(default)]
+    pub journal_dir: Option<String>,
+    /// Summarize each finished journal day with the journaling mode's LLM
+    #[serde(default)]
+    pub journal_summaries: bool,
+    /// Output length limits by focused app (window class, case-insensitive)
+    #[serde(default)]
+    pub app_char_limits: HashMap<String, usize>,
+    /// Store the focused window's class and title with each dictation
+    #[serde(default)]
+    pub remember_window: bool,
+    /// Clipboard tool (auto picks wl-clipboard on Wayland when installed)
+    #[serde(default)]
+    pub clipboard_backend: ClipboardBackend,
+    /// FIFO, file or Unix socket receiving one JSON line per pipeline stage
+    #[serde(default)]
+    pub event_sink: Option<String>,
+    /// Start a user-scoped ydotoold when ydotool is used and it is not running
+    #[serde(default)]
+    pub manage_ydotoold: bool,
+    /// Delays and chunking for pasting and typing
+    #[serde(default)]
+    pub paste: PasteSettings,
+    /// Per-app paste behavior, matched against the focused window
+    #[serde(default)]
+    pub paste_rules: Vec<PasteRule>,
+    /// Load the last used STT model and LLM in the background at startup
+    #[serde(default)]
+    pub warm_start: bool,
+    /// Hold the startup warm-up until the system is idle
+    #[serde(default)]
+    pub warm_start_when_idle: bool,
+    /// Send output to another machine instead of pasting it here
+    #[serde(default)]
+    pub remote_output: Option<RemoteTarget>,
+    /// Port accepting text from paired instances (disabled when None)
+    #[serde(default)]
+    pub bridge_port: Option<u16>,
+    /// Token paired instances must present to the bridge
+    #[serde(default)]
+    pub bridge_token: Option<String>,
+    /// Open the bridge to other machines rather than only this one (plain HTTP)
+    #[serde(default)]
+    pub bridge_lan: bool,
+    /// Port for the localhost HTTP API (disabled when None; see `http_api`)
+    #[serde(default)]
+    pub api_port: Option<u16>,
+    /// Token HTTP API clients must present
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// Name, role, tone and signature available to every prompt
+    #[serde(default)]
+    pub prompt_profile: PromptProfile,
+    /// Encrypt the history database with a key kept in the keyring
+    /// (applied at startup, migrating the existing database; needs a build
+    /// with the `encryption` feature). Recordings, journal files and the
+    /// event sink are not encrypted.
+    #[serde(default)]
+    pub encrypt_history: bool,
+    /// Seconds work in progress may run at exit before it is interrupted
+    /// and saved for a retry
+    #[serde(default = "default_shutdown_deadline_secs")]
+    pub shutdown_deadline_secs: u64,
+    /// Days deleted history stays in the trash before it is purged at
+    /// startup (`None` keeps it until purged by hand)
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: Option<u32>,
+    /// Retrying of rate-limited, failing or timed out LLM and cloud STT requests
+    #[serde(default)]
+    pub retry: crate::retry::RetryPolicy,
+    /// Fall back to local providers when the cloud APIs are unreachable
+    #[serde(default = "default_true")]
+    pub offline_fallback: bool,
+    /// Ollama model standing in for cloud LLMs while offline, for modes
+    /// without a local fallback (rule-based cleanup when None)
+    #[serde(default)]
+    pub offline_llm_model: Option<String>,
+    /// Learn a style profile from corrected dictations and add it to LLM prompts
+    #[serde(default)]
+    pub learn_style: bool,
+    /// Replacement rules applied to transcripts and glossary given to the LLM
+    #[serde(default)]
+    pub dictionary: crate::dictionary::Dictionary,
+    /// What happens when another app starts using the microphone while recording
+    #[serde(default)]
+    pub mic_conflict: crate::mic_conflict::MicConflictAction,
+    /// Pause playing media players (MPRIS) while recording
+    #[serde(default)]
+    pub pause_media: bool,
+    /// Short sounds when recording starts and stops and when a dictation is done or fails
+    #[serde(default)]
+    pub feedback_sounds: crate::sounds::FeedbackSounds,
+    /// How audio devices are listed and opened (cpal, or PipeWire natively)
+    #[serde(default)]
+    pub audio_backend: crate::audio::AudioBackend,
+    /// cpal host used by the cpal backend (default, ALSA or JACK)
+    #[serde(default)]
+    pub audio_host: crate::audio::AudioHost,
+    /// Restore punctuation and capitals in transcripts, without an LLM
+    #[serde(default)]
+    pub punctuation: crate::punctuation::PunctuationRestore,
+    /// Local punctuation model: a shell command reading the transcript on
+    /// stdin and printing it punctuated (needs `allow_shell_hooks`)
+    #[serde(default)]
+    pub punctuation_command: Option<String>,
+    /// What runs instead when a local model does not fit in free VRAM
+    #[serde(default)]
+    pub gpu_fallback: crate::gpu::GpuFallback,
+    /// Format recordings are kept in once processed
+    #[serde(default)]
+    pub recording_format: crate::audio_codec::RecordingFormat,
+    /// Shell commands run on dictation events (need `allow_shell_hooks`)
+    #[serde(default)]
+    pub hooks: crate::hooks::Hooks,
+    /// Store the browser tab URL reported by the companion extension with dictations
+    #[serde(default)]
+    pub capture_browser_url: bool,
+    /// Microphone audio kept from before each recording starts (0 = off, max 2000)
+    #[serde(default)]
+    pub pre_roll_ms: u32,
+    /// Beam width for local whisper (0 = greedy, max 5); with a beam, history
+    /// keeps other readings of uncertain segments
+    #[serde(default)]
+    pub whisper_beam_size: u8,
+    /// Experimental subsystems switched on or off, overriding the release channel's default
+    #[serde(default)]
+    pub feature_flags: BTreeMap<String, bool>,
+    /// Software gain in dB per input device name ("" = default device)
+    #[serde(default)]
+    pub input_gain_db: HashMap<String, f32>,
+    /// Channel recorded per input device name, for multi-channel interfaces
+    #[serde(default)]
+    pub input_channels: HashMap<String, crate::audio::ChannelSelect>,
+    /// Flag recordings with more than this percent of samples at full scale
+    #[serde(default = "default_clipping_warn_percent")]
+    pub clipping_warn_percent: f32,
+    /// VAD speech threshold (frame RMS) from microphone calibration, None for the default
+    #[serde(default)]
+    pub vad_threshold: Option<f32>,
+    /// History items a batch reprocesses at once
+    #[serde(default = "default_batch_concurrency")]
+    pub batch_concurrency: usize,
+    /// Folder whose new audio files are transcribed into history
+    #[serde(default)]
+    pub watch_folder: Option<String>,
+    /// Mode run on watch folder files, None for the active mode
+    #[serde(default)]
+    pub watch_folder_mode: Option<String>,
+    /// Write each watch folder file's output next to it
+    #[serde(default)]
+    pub watch_folder_sidecar: Option<crate::watch::SidecarFormat>,
+}
+
+fn default_shutdown_deadline_secs() -> u64 {
+    crate::shutdown::DEFAULT_DEADLINE_SECS
+}
+
+fn default_trash_retention_days() -> Option<u32> {
+    Some(30)
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_clipping_warn_percent() -> f32 {
+    0.5
+}
+
+fn default_batch_concurrency() -> usize {
+    2
+}
+
+impl Settings {
+    /// Configured endpoint for an STT provider, None for the default
+    pub fn stt_endpoint(&self, provider: &SttProviderType) -> Option<String> {
+        match provider {
+            SttProviderType::WhisperServer => self.whisper_server_url.clone(),
+            SttProviderType::Groq => self.groq_url.clone(),
+            SttProviderType::Wyoming => self.wyoming_url.clone(),
+            _ => None,
+        }
+    }
+
+    /// Linear software gain for an input device
+    pub fn input_gain(&self, device: &str) -> f32 {
+        crate::audio::db_to_gain(self.input_gain_db.get(device).copied().unwrap_or(0.0))
+    }
+
+    /// VAD parameters from the pacing, with the calibrated threshold if any
+    pub fn vad_config(&self) -> crate::vad::VadConfig {
+        let config = self.pacing.vad_config();
+        crate::vad::VadConfig {
+            threshold: self.vad_threshold.unwrap_or(config.threshold),
+            ..config
+        }
+    }
+
+    /// Channel recorded from an input device
+    pub fn input_channel(&self, device: &str) -> crate::audio::ChannelSelect {
+        self.input_channels.get(device).copied().unwrap_or_default()
+    }
+
+    /// Punctuation model command, when configured and shell commands are allowed
+    pub fn punctuation_command(&self) -> Option<&str> {
+        self.punctuation_command
+            .as_deref()
+            .filter(|command| self.allow_shell_hooks && !command.trim().is_empty())
+    }
+
+    /// A mode's output command, when configured and shell commands are allowed
+    pub fn output_command<'a>(&self, mode: &'a Mode) -> Option<&'a str> {
+        mode.output_command
+            .as_deref()
+            .filter(|command| self.allow_shell_hooks && !command.trim().is_empty())
+    }
+
+    /// Hook command for `event`, when configured and shell commands are allowed
+    pub fn hook_command(&self, event: HookEvent) -> Option<&str> {
+        self.hooks.command(event).filter(|_| self.allow_shell_hooks)
+    }
+}
+
+/// Keyring entry holding the history database key
+const HISTORY_KEY_NAME: &str = "history_db_key";
+
+/// A key being rotated to, saved before the database is rekeyed with it
+const NEXT_HISTORY_KEY_NAME: &str = "history_db_key_next";
+
+fn history_key() -> Result<Option<String>> {
+    crate::secrets::get(HISTORY_KEY_NAME)
+}
+
+fn save_history_key(key: &str) -> Result<()> {
+    crate::secrets::set(HISTORY_KEY_NAME, key)
+}
+
+/// Open the encrypted database, finishing a key rotation interrupted by a crash
+///
+/// Until the rotation completes, the database opens with either the
+/// current key or the next one, depending on whether the rekey happened.
+fn open_encrypted_history(path: &std::path::Path, key: &str) -> Result<Database> {
+    let next = crate::secrets::get(NEXT_HISTORY_KEY_NAME)?;
+    match (Database::open_encrypted(path, key), next) {
+        (Ok(db), next) => {
+            if next.is_some() {
+                crate::secrets::delete(NEXT_HISTORY_KEY_NAME)?;
+            }
+            Ok(db)
+        }
+        (Err(e), None) => Err(e),
+        (Err(_), Some(next)) => {
+            let db = Database::open_encrypted(path, &next)?;
+            save_history_key(&next)?;
+            crate::secrets::delete(NEXT_HISTORY_KEY_NAME)?;
+            log::info!("Finished an interrupted history key rotation");
+            Ok(db)
+        }
+    }
+}
+
+/// A random key, hex encoded (244 random bits from two v4 UUIDs)
+fn new_history_key() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
 }
 
 impl Default for Settings {
@@ -75,11 +407,123 @@ impl Default for Settings {
             context_awareness: false,
             language: "en".to_string(),
             whisper_server_url: None,
+            groq_url: None,
+            wyoming_url: None,
             ollama_url: None,
+            typography: false,
+            read_only: false,
+            capture_source: CaptureSource::Microphone,
+            monitor_device: String::new(),
+            save_two_track: false,
+            vad_skip_silence: false,
+            bookmark_labels: false,
+            hotkeys: crate::hotkey::Hotkeys::default(),
+            output_delimiter: OutputDelimiter::Space,
+            health_port: None,
+            allow_evdev_input: false,
+            allow_shell_hooks: false,
+            allow_screenshots: false,
+            pacing: Pacing::default(),
+            journal_dir: None,
+            journal_summaries: false,
+            app_char_limits: HashMap::new(),
+            remember_window: false,
+            clipboard_backend: ClipboardBackend::Auto,
+            event_sink: None,
+            manage_ydotoold: false,
+            paste: PasteSettings::default(),
+            paste_rules: Vec::new(),
+            warm_start: false,
+            warm_start_when_idle: false,
+            remote_output: None,
+            bridge_port: None,
+            bridge_token: None,
+            bridge_lan: false,
+            api_port: None,
+            api_token: None,
+            prompt_profile: PromptProfile::default(),
+            encrypt_history: false,
+            shutdown_deadline_secs: default_shutdown_deadline_secs(),
+            trash_retention_days: default_trash_retention_days(),
+            retry: crate::retry::RetryPolicy::default(),
+            offline_fallback: true,
+            offline_llm_model: None,
+            learn_style: false,
+            dictionary: crate::dictionary::Dictionary::default(),
+            mic_conflict: crate::mic_conflict::MicConflictAction::default(),
+            pause_media: false,
+            feedback_sounds: crate::sounds::FeedbackSounds::default(),
+            audio_backend: crate::audio::AudioBackend::default(),
+            audio_host: crate::audio::AudioHost::default(),
+            punctuation: crate::punctuation::PunctuationRestore::default(),
+            punctuation_command: None,
+            gpu_fallback: crate::gpu::GpuFallback::default(),
+            recording_format: crate::audio_codec::RecordingFormat::default(),
+            hooks: crate::hooks::Hooks::default(),
+            capture_browser_url: false,
+            pre_roll_ms: 0,
+            whisper_beam_size: 0,
+            feature_flags: BTreeMap::new(),
+            input_gain_db: HashMap::new(),
+            input_channels: HashMap::new(),
+            clipping_warn_percent: default_clipping_warn_percent(),
+            vad_threshold: None,
+            batch_concurrency: default_batch_concurrency(),
+            watch_folder: None,
+            watch_folder_mode: None,
+            watch_folder_sidecar: None,
         }
     }
 }
 
+/// Length of audio after a bookmark transcribed as its label
+const BOOKMARK_LABEL_MS: u64 = 3000;
+
+/// Most outputs offered in the multi-take picker (chosen with 1/2/3)
+const MAX_CANDIDATES: usize = 3;
+
+/// A dictation between stopping its recording and delivering its output
+struct Dictation {
+    id: String,
+    trace: LatencyTrace,
+    duration_ms: u64,
+    /// The spool of a recording that is not kept, destroyed once processed
+    discarded_spool: Option<PathBuf>,
+    /// Provider retries made so far
+    retries: u32,
+}
+
+/// Outputs of a mode's LLM and its comparison models, one of them to be used
+struct Takes {
+    candidates: Vec<(llm::Completion, LlmProviderType, String)>,
+    /// What the picker offers, when there is more than one take
+    request: Option<PickRequest>,
+    llm_ms: u64,
+}
+
+/// A dictation's output as processed, before it is delivered and saved
+struct Processed {
+    mode: Mode,
+    incognito: bool,
+    keep_audio: bool,
+    audio_path: PathBuf,
+    duration_ms: u64,
+    /// Segments were pasted while recording
+    segmented: bool,
+    transcript: String,
+    output: String,
+    llm_used: Option<(LlmProviderType, String, llm::TokenUsage)>,
+    /// Takes waiting for one to be used as the output
+    takes: Option<Takes>,
+    /// The takes offered and the one the user picked
+    picks: Option<(PickRequest, usize)>,
+    cost_saved_usd: Option<f64>,
+    pipeline_cost: Option<f64>,
+    pipeline_error: Option<ErrorRecord>,
+    bookmarks: Vec<Bookmark>,
+    alternatives: Vec<stt::SegmentAlternatives>,
+}
+
 /// Main application state (Send + Sync safe)
 pub struct AppState {
     /// Tauri app handle
@@ -97,22 +541,79 @@ pub struct AppState {
     /// Recording handle (Send + Sync safe)
     pub recording_handle: RecordingHandle,
 
-    /// Database connection (wrapped in Mutex for thread safety)
-    pub database: Option<Arc<Mutex<Database>>>,
+    /// History database (pools its own connections, so it is shared as is)
+    pub database: Option<Arc<Database>>,
 
     /// Application settings
     pub settings: Settings,
 
-    /// Last context (clipboard text)
-    pub last_context: Option<String>,
+    /// Context (clipboard, selection, focused app) captured when recording started
+    pub last_context: PromptContext,
+
+    /// Segmenter for the current recording, when the mode auto-segments
+    pub segmenter: Option<Segmenter>,
+
+    /// Cancellation token for the current recording and its processing
+    pub cancel: CancelToken,
+
+    /// Multi-take picker, answered from outside the state lock
+    pub picker: Picker,
+
+    /// Where JSON pipeline events are written
+    pub events: EventSink,
+
+    /// STT provider swapped in for the session, used instead of each mode's
+    pub stt_override: Option<stt::SttSelection>,
+
+    /// Models used by the last dictation, warmed up at the next startup
+    pub warm_cache: WarmCache,
+
+    /// Transcript of the dictation being processed, saved with it if a
+    /// later stage fails
+    pub last_transcript: Option<String>,
+
+    /// Held from recording start until the dictation is done, pausing
+    /// background jobs meanwhile
+    interactive: Option<crate::priority::Interactive>,
+    /// Media players paused for the recording, resumed once it is done
+    paused_media: Option<crate::media::PausedMedia>,
+
+    /// Local providers standing in for cloud ones in the dictation being
+    /// processed, when the network is down
+    offline: Option<crate::connectivity::Fallback>,
+
+    /// Style learned from corrected dictations, when `learn_style` is on
+    pub style: Option<crate::style::StyleProfile>,
+
+    /// Nothing from new dictations is written to disk: no recording,
+    /// history, journal entry or event text. Off again at restart.
+    pub incognito: bool,
+    /// `incognito` as it was when the current dictation started, which
+    /// toggling it meanwhile does not change
+    dictation_incognito: bool,
+
+    /// Open microphone buffering the moments before a recording, when `pre_roll_ms` is set
+    pre_roll: Option<crate::preroll::PreRoll>,
 }
 
 impl AppState {
     /// Create new application state
     pub fn new(app_handle: AppHandle) -> Result<Self> {
         let settings = Self::load_settings()?;
-
-        Ok(Self {
+        paste::set_uinput_allowed(settings.allow_evdev_input);
+        paste::set_clipboard_backend(settings.clipboard_backend);
+        paste::set_ydotoold_managed(settings.manage_ydotoold);
+        crate::audio::set_audio_backend(settings.audio_backend);
+        crate::audio::set_audio_host(settings.audio_host);
+        crate::gpu::set_gpu_fallback(settings.gpu_fallback);
+        stt::set_beam_size(settings.whisper_beam_size);
+        crate::features::set_overrides(&settings.feature_flags);
+        crate::browser_tab::set_enabled(settings.capture_browser_url);
+        crate::shutdown::set_deadline(settings.shutdown_deadline_secs);
+        crate::retry::set_policy(settings.retry.clone());
+        let events = EventSink::new(settings.event_sink.as_deref());
+
+        let mut state = Self {
             app_handle,
             status: RecordingStatus::Loading,
             modes: HashMap::new(),
@@ -120,12 +621,65 @@ impl AppState {
             recording_handle: RecordingHandle::new(),
             database: None,
             settings,
-            last_context: None,
-        })
+            last_context: PromptContext::default(),
+            segmenter: None,
+            cancel: CancelToken::new(),
+            picker: Picker::new(),
+            events,
+            stt_override: None,
+            warm_cache: crate::warmup::load(),
+            last_transcript: None,
+            interactive: None,
+            paused_media: None,
+            offline: None,
+            style: None,
+            incognito: false,
+            dictation_incognito: false,
+            pre_roll: None,
+        };
+        state.update_pre_roll();
+        Ok(state)
+    }
+
+    /// Open, reopen or close the pre-roll microphone to match the settings
+    ///
+    /// A recording may be running on the pre-roll stream, so nothing changes
+    /// until it stops.
+    pub fn update_pre_roll(&mut self) {
+        if self.is_recording() {
+            return;
+        }
+        self.recording_handle
+            .set_gains([self.settings.input_gain(&self.settings.input_device), 1.0]);
+        self.recording_handle
+            .set_channels([self.settings.input_channel(&self.settings.input_device), ChannelSelect::Mix]);
+        let duration_ms = if crate::features::enabled(Flag::PreRoll) {
+            self.settings.pre_roll_ms.min(crate::preroll::MAX_PRE_ROLL_MS)
+        } else {
+            0
+        };
+        if let Some(pre_roll) = &self.pre_roll {
+            if duration_ms > 0
+                && pre_roll.is_running()
+                && pre_roll.device() == self.settings.input_device
+                && pre_roll.duration_ms() == duration_ms
+                && pre_roll.channel() == self.settings.input_channel(&self.settings.input_device)
+            {
+                return;
+            }
+        }
+        self.pre_roll = None;
+        if duration_ms == 0 {
+            return;
+        }
+        match crate::preroll::PreRoll::start(&self.settings.input_device, duration_ms, self.recording_handle.clone()) {
+            Ok(pre_roll) => self.pre_roll = Some(pre_roll),
+            Err(e) => log::warn!("Pre-roll unavailable: {}", e),
+        }
     }
 
     /// Load settings from disk
-    fn load_settings() -> Result<Settings> {
+    pub fn load_settings() -> Result<Settings> {
         let settings_path = Self::get_settings_path()?;
 
         if settings_path.exists() {
@@ -151,8 +705,25 @@ impl AppState {
         Ok(())
     }
 
+    /// Whether read-only (lockdown) mode is active
+    pub fn is_read_only(&self) -> bool {
+        self.settings.read_only
+            || std::env::var("WHISPERTRAY_READ_ONLY")
+                .map(|v| !v.is_empty() && v != "0" && v.to_lowercase() != "false")
+                .unwrap_or(false)
+    }
+
+    /// Fail with `AppError::ReadOnly` if lockdown mode is active
+    pub fn ensure_writable(&self, action: &str) -> Result<()> {
+        if self.is_read_only() {
+            log::warn!("Blocked in read-only mode: {}", action);
+            return Err(AppError::ReadOnly(action.to_string()));
+        }
+        Ok(())
+    }
+
     /// Get settings file path
-    fn get_settings_path() -> Result<PathBuf> {
+    pub(crate) fn get_settings_path() -> Result<PathBuf> {
         let config_dir = directories::ProjectDirs::from("com", "whispertray", "WhisperTray")
             .ok_or_else(|| AppError::Config("Could not determine config directory".to_string()))?
             .config_dir()
@@ -178,9 +749,74 @@ impl AppState {
     /// Initialize database
     pub async fn init_database(&mut self) -> Result<()> {
         let db_path = get_database_path()?;
-        let db = Database::new(&db_path)?;
-        self.database = Some(Arc::new(Mutex::new(db)));
+        if self.settings.encrypt_history && !database::ENCRYPTION_AVAILABLE {
+            log::warn!("History encryption is on, but this build has no `encryption` feature; not encrypting");
+        }
+        let db = if self.settings.encrypt_history && database::ENCRYPTION_AVAILABLE {
+            let key = match history_key()? {
+                Some(key) => key,
+                None => {
+                    let key = new_history_key();
+                    save_history_key(&key)?;
+                    key
+                }
+            };
+            if db_path.exists() && !database::is_encrypted(&db_path) {
+                database::encrypt_file(&db_path, &key)?;
+                log::info!("Encrypted the existing history database");
+            }
+            open_encrypted_history(&db_path, &key)?
+        } else {
+            if database::is_encrypted(&db_path) {
+                let key = history_key()?
+                    .ok_or_else(|| AppError::Keyring("History database key not found".to_string()))?;
+                database::decrypt_file(&db_path, &key)?;
+                log::info!("Decrypted the history database");
+            }
+            Database::new(&db_path)?
+        };
         log::info!("Database initialized at {:?}", db_path);
+
+        if let Some(days) = self.settings.trash_retention_days.filter(|_| !self.is_read_only()) {
+            let cutoff = Utc::now() - chrono::Duration::days(days.into());
+            match db.purge_history(None, Some(cutoff)) {
+                Ok(audio_paths) => {
+                    for path in &audio_paths {
+                        let _ = std::fs::remove_file(path);
+                    }
+                }
+                Err(e) => log::warn!("Failed to empty old items from the trash: {}", e),
+            }
+        }
+        if self.settings.learn_style {
+            self.style = db.get_style_profile().unwrap_or_default();
+        }
+        self.database = Some(Arc::new(db));
+        Ok(())
+    }
+
+    /// Re-encrypt the history database with a new key
+    pub fn rotate_history_key(&self) -> Result<()> {
+        self.ensure_writable("rotating the history key")?;
+        if !self.settings.encrypt_history {
+            return Err(AppError::Config("History encryption is not enabled".to_string()));
+        }
+        let db = self
+            .database
+            .as_ref()
+            .ok_or_else(|| AppError::Config("Database not initialized".to_string()))?;
+
+        // The new key is stored before the database uses it, so a crash at
+        // any point leaves a key that opens it (see `open_encrypted_history`)
+        let key = new_history_key();
+        crate::secrets::set(NEXT_HISTORY_KEY_NAME, &key)?;
+        if let Err(e) = db.rekey(&key) {
+            let _ = crate::secrets::delete(NEXT_HISTORY_KEY_NAME);
+            return Err(e);
+        }
+        save_history_key(&key)?;
+        crate::secrets::delete(NEXT_HISTORY_KEY_NAME)?;
+        log::info!("Rotated the history database key");
         Ok(())
     }
 
@@ -200,6 +836,14 @@ impl AppState {
         Ok(())
     }
 
+    /// Turn incognito on or off for the dictations that follow
+    pub fn set_incognito(&mut self, incognito: bool) {
+        self.incognito = incognito;
+        log::info!("Incognito {}", if incognito { "on" } else { "off" });
+        let _ = crate::tray::set_incognito(&self.app_handle, incognito, self.status);
+        let _ = self.app_handle.emit("incognito-changed", incognito);
+    }
+
     /// Check if recording is in progress
     pub fn is_recording(&self) -> bool {
         self.recording_handle.is_recording()
@@ -215,158 +859,1517 @@ impl AppState {
         &mut self,
         level_callback: Option<crate::audio::LevelCallback>,
     ) -> Result<()> {
-        if self.is_recording() {
+        self.start_recording_from(None, level_callback)
+    }
+
+    /// Start recording from a specific capture source (settings default if None)
+    pub fn start_recording_from(
+        &mut self,
+        source: Option<CaptureSource>,
+        level_callback: Option<crate::audio::LevelCallback>,
+    ) -> Result<()> {
+        // The state is let go while a take is picked; that dictation is not over yet
+        if self.is_recording() || self.status == RecordingStatus::Processing {
             return Err(AppError::RecordingInProgress);
         }
-
-        // Capture context if enabled
-        if self.settings.context_awareness {
-            self.last_context = paste::get_clipboard_text().ok();
+        self.dictation_incognito = self.incognito;
+        let result = self.begin_capture(source, level_callback);
+        if result.is_err() {
+            crate::sounds::play(Earcon::Error, self.settings.feedback_sounds);
         }
+        result
+    }
 
-        crate::audio::start_recording(
-            self.recording_handle.clone(),
+    /// Open the capture and start everything that runs alongside it
+    fn begin_capture(
+        &mut self,
+        source: Option<CaptureSource>,
+        level_callback: Option<crate::audio::LevelCallback>,
+    ) -> Result<()> {
+        self.cancel.reset();
+
+        // Capture the context the active mode's prompt can use
+        let (template, source) = self
+            .get_active_mode()
+            .map(|mode| (mode.prompt_template.clone(), mode.context_source))
+            .unwrap_or_default();
+        // The focused window is only looked up when something uses it
+        let window = self.settings.remember_window
+            || !self.settings.app_char_limits.is_empty()
+            || crate::browser_tab::is_enabled();
+        self.last_context = PromptContext::capture(
+            &template,
+            source.resolve(self.settings.context_awareness),
+            window,
+        );
+
+        let source = source.unwrap_or(self.settings.capture_source);
+        let mut microphone = self.settings.input_device.as_str();
+        let inputs = match crate::audio::get_capture_inputs(
+            source,
             &self.settings.input_device,
-            level_callback,
-        )?;
+            &self.settings.monitor_device,
+        ) {
+            // An unplugged microphone should not keep the user from dictating
+            Err(e) if source != CaptureSource::System && !self.settings.input_device.is_empty() => {
+                let inputs = crate::audio::get_capture_inputs(source, "", &self.settings.monitor_device)
+                    .map_err(|_| e)?;
+                crate::hotplug::notify_missing(&self.settings.input_device);
+                microphone = "";
+                inputs
+            }
+            inputs => inputs?,
+        };
+
+        // Segments are cut from the primary track, so only single-source recordings segment
+        let segment_options = match self.get_active_mode() {
+            // Segments are cut through the disk spool
+            Some(mode) if mode.auto_segment && !mode.journal && !self.dictation_incognito && inputs.len() == 1 => Some(SegmenterOptions {
+                mode: self.with_stt_selection(mode),
+                api_key: self.get_stt_api_key(&self.stt_selection(mode).provider)?,
+                server_url: self.settings.stt_endpoint(&self.stt_selection(mode).provider),
+                language: self.settings.language.clone(),
+                auto_paste: self.settings.auto_paste,
+                paste: self.settings.paste,
+                paste_rules: self.settings.paste_rules.clone(),
+                typography: self.settings.typography,
+                delimiter: self.settings.output_delimiter,
+                cancel: self.cancel.clone(),
+                vad: self.settings.vad_config(),
+                max_segment_ms: self.settings.pacing.segment_max_ms(crate::segmenter::SEGMENT_MAX_MS),
+            }),
+            _ => None,
+        };
+
+        let (mic_gain, monitor_gain) = (
+            self.settings.input_gain(microphone),
+            self.settings.input_gain(&self.settings.monitor_device),
+        );
+        self.recording_handle.set_gains(match source {
+            CaptureSource::Microphone => [mic_gain, 1.0],
+            CaptureSource::System => [monitor_gain, 1.0],
+            CaptureSource::Both => [mic_gain, monitor_gain],
+        });
+        let (mic_channel, monitor_channel) = (
+            self.settings.input_channel(microphone),
+            self.settings.input_channel(&self.settings.monitor_device),
+        );
+        self.recording_handle.set_channels(match source {
+            CaptureSource::Microphone => [mic_channel, ChannelSelect::Mix],
+            CaptureSource::System => [monitor_channel, ChannelSelect::Mix],
+            CaptureSource::Both => [mic_channel, monitor_channel],
+        });
+
+        // Played-back recordings would be picked up by the microphone
+        crate::playback::stop();
+        // Before the stream opens, so the beep is heard as soon as possible
+        crate::sounds::play(Earcon::Start, self.settings.feedback_sounds);
+
+        // A microphone kept open for pre-roll carries on as the recording
+        match &self.pre_roll {
+            Some(pre_roll)
+                if pre_roll.is_running()
+                    && matches!(inputs.as_slice(), [CaptureInput::Cpal { monitor: None, .. }]) =>
+            {
+                pre_roll.start_recording(level_callback);
+            }
+            _ => crate::audio::start_recording(self.recording_handle.clone(), inputs, level_callback)
+                .inspect_err(|e| {
+                    crate::mic_conflict::spawn_open_failure_check(self.app_handle.clone(), microphone, &e.to_string())
+                })?,
+        }
+        // Music playing through speakers would be transcribed along
+        if self.settings.pause_media && source != CaptureSource::System {
+            self.paused_media = Some(crate::media::pause());
+        }
+        if self.dictation_incognito {
+            // Long recordings stay in memory rather than spooling to disk
+            self.recording_handle.set_spool_enabled(false);
+        }
+        // A verbatim recording is the record, so its spool keeps full precision
+        self.recording_handle
+            .set_spool_lossless(self.get_active_mode().is_some_and(|mode| mode.verbatim));
+        self.segmenter = segment_options.map(|options| Segmenter::spawn(self.recording_handle.clone(), options));
+        crate::hotplug::spawn_watch(self.app_handle.clone(), self.recording_handle.clone());
+        crate::indicator::spawn_stream(self.app_handle.clone(), self.recording_handle.clone());
+        crate::pacing::spawn_auto_stop(
+            self.app_handle.clone(),
+            self.recording_handle.clone(),
+            &self.settings.pacing,
+        );
+        // Another app recording the system output is no conflict, and its silence is no block
+        if source != CaptureSource::System {
+            crate::mic_conflict::spawn_watch(
+                self.app_handle.clone(),
+                self.recording_handle.clone(),
+                self.settings.mic_conflict,
+            );
+            if crate::features::enabled(Flag::PortalSignalCheck) {
+                crate::portal::spawn_signal_check(self.app_handle.clone(), self.recording_handle.clone());
+            }
+            // Only PipeWire nodes and the default device are routed by the audio server
+            let route = match self.settings.audio_backend {
+                crate::audio::AudioBackend::PipeWire if !matches!(microphone, "" | "default") => {
+                    Some(Some(microphone.to_string()))
+                }
+                _ if matches!(microphone, "" | "default" | "pipewire" | "pulse") => Some(None),
+                _ => None,
+            };
+            if let Some(expected) = route {
+                crate::mic_conflict::spawn_route_check(
+                    self.app_handle.clone(),
+                    self.recording_handle.clone(),
+                    expected,
+                );
+            }
+        }
+        self.interactive = Some(crate::priority::Interactive::begin());
+        if let Some(mode) = self.get_active_mode() {
+            if self.settings.offline_fallback && crate::connectivity::uses_cloud(mode, &self.stt_selection(mode)) {
+                crate::connectivity::probe_ahead();
+            }
+        }
         self.status = RecordingStatus::Recording;
+        self.run_hook(HookEvent::RecordStart, "", &self.active_mode_key, "recording", None, None);
 
         Ok(())
     }
 
-    /// Stop recording and process
-    pub async fn stop_recording(&mut self) -> Result<String> {
+    /// Stop the capture, starting a dictation to process its audio
+    fn stop_capture(&mut self) -> Result<(Dictation, RecordedAudio)> {
         if !self.is_recording() {
             return Err(AppError::NoRecordingInProgress);
         }
 
-        let samples = crate::audio::stop_recording(&self.recording_handle)?;
+        let mut trace = LatencyTrace::start("dictation");
+        let id = Uuid::new_v4().to_string();
+
+        let span = trace.begin("stream_stop");
+        let audio = match crate::audio::stop_recording(&self.recording_handle) {
+            Ok(audio) => audio,
+            Err(e) => {
+                // The capture is over either way
+                self.segmenter = None;
+                self.end_dictation();
+                self.status = RecordingStatus::Ready;
+                return Err(e);
+            }
+        };
+        trace.end(span);
         self.status = RecordingStatus::Processing;
+        crate::sounds::play(Earcon::Stop, self.settings.feedback_sounds);
+        if let Some(fraction) = self.recording_handle.clipped_fraction().filter(|_| self.is_clipped()) {
+            log::warn!("{:.1}% of the recording was clipped", fraction * 100.0);
+            let _ = self.app_handle.emit("audio-clipping", json!({ "percent": fraction * 100.0 }));
+        }
+        self.emit_event(
+            "recording",
+            &id,
+            &self.active_mode_key,
+            Some(crate::audio::calculate_duration_ms(audio.sample_count())),
+            Value::Null,
+        );
 
-        // Helper to reset status on error
-        let result = self.process_recording(samples).await;
-        if result.is_err() {
+        let duration_ms = crate::audio::calculate_duration_ms(audio.sample_count());
+        self.last_transcript = None;
+        // A recording that is not kept was spooled all the same; it is destroyed once processed
+        let keeps_audio = !self.dictation_incognito
+            && self
+                .get_active_mode()
+                .map_or(true, |mode| mode.history_storage == HistoryStorage::Full);
+        let discarded_spool = match &audio {
+            RecordedAudio::Spooled { path, .. } if !keeps_audio => Some(path.clone()),
+            _ => None,
+        };
+        let dictation = Dictation {
+            id,
+            trace,
+            duration_ms,
+            discarded_spool,
+            retries: 0,
+        };
+        Ok((dictation, audio))
+    }
+
+    /// Wrap up a dictation however it ended, resetting the status on error
+    fn end_stop(&mut self, dictation: Dictation, result: Result<String>) -> Result<String> {
+        let Dictation {
+            id,
+            mut trace,
+            duration_ms,
+            discarded_spool,
+            retries,
+        } = dictation;
+        if let Some(path) = discarded_spool {
+            if let Err(e) = crate::redact::secure_delete(&path) {
+                log::warn!("Failed to delete recording {}: {}", path.display(), e);
+            }
+        }
+        match &result {
+            Ok(_) => crate::sounds::play(Earcon::Success, self.settings.feedback_sounds),
+            Err(AppError::Cancelled) => {}
+            Err(_) => crate::sounds::play(Earcon::Error, self.settings.feedback_sounds),
+        }
+        if let Err(e) = &result {
             self.status = RecordingStatus::Ready;
+            let stage = if matches!(e, AppError::Cancelled) { "cancelled" } else { "failed" };
+            self.emit_event(stage, &id, &self.active_mode_key, None, json!({ "error": e.to_string() }));
+            self.run_hook(HookEvent::Error, &id, &self.active_mode_key, stage, None, Some(e.to_string()));
+            // A dictation cancelled by the shutdown deadline is kept for a retry
+            if !self.dictation_incognito && (!matches!(e, AppError::Cancelled) || crate::shutdown::is_shutting_down()) {
+                self.record_failure(&id, duration_ms, e, retries, &mut trace);
+            }
         }
+        self.end_dictation();
+        // Reopen the pre-roll microphone if its device went away
+        self.update_pre_roll();
         result
     }
 
-    /// Internal: process recorded samples (transcribe, AI, save history)
-    async fn process_recording(&mut self, samples: Vec<f32>) -> Result<String> {
+    /// Whether too much of the last recording was at full scale
+    fn is_clipped(&self) -> bool {
+        self.recording_handle
+            .clipped_fraction()
+            .map_or(false, |fraction| fraction * 100.0 > self.settings.clipping_warn_percent)
+    }
+
+    /// Save a failed dictation to history so it can be found and retried
+    fn record_failure(&self, id: &str, duration_ms: u64, error: &AppError, retries: u32, trace: &mut LatencyTrace) {
+        let (Some(db), Some(mode)) = (&self.database, self.get_active_mode()) else {
+            return;
+        };
+        let stage = ErrorStage::of_span(trace.current().unwrap_or_default());
+        let stt_used = self.stt_selection(mode);
+        let provider = match stage {
+            ErrorStage::Stt => Some(format!("{:?}", stt_used.provider).to_lowercase()),
+            ErrorStage::Llm => Some(format!("{:?}", mode.llm_provider).to_lowercase()),
+            _ => None,
+        };
+        let mut error_record = ErrorRecord::new(stage, provider, error);
+        error_record.retries = retries;
+        if matches!(error, AppError::Cancelled) {
+            error_record.message = "Interrupted by shutdown".to_string();
+            error_record.retryable = true;
+        }
+        // Without the recording there is nothing to retry from
+        let audio_path = get_audio_dir()
+            .map(|dir| dir.join(format!("{}.wav", id)))
+            .ok()
+            .filter(|path| path.exists());
+
+        let item = HistoryItem {
+            id: id.to_string(),
+            created_at: Utc::now(),
+            mode_key: mode.key.clone(),
+            audio_path: audio_path.map(|path| path.to_string_lossy().to_string()),
+            transcript_raw: self.last_transcript.clone().unwrap_or_default(),
+            output_final: String::new(),
+            stt_provider: format!("{:?}", stt_used.provider).to_lowercase(),
+            stt_model: stt_used.model_used(),
+            llm_provider: None,
+            llm_model: None,
+            duration_ms,
+            error: Some(error_record),
+            latency: trace.finish(),
+            bookmarks: Vec::new(),
+            prompt_tokens: None,
+            completion_tokens: None,
+            cost_usd: None,
+            stats: TextStats::default(),
+            truncated: false,
+            window_class: self.remembered_window().and_then(|w| w.class.clone()),
+            window_title: self.remembered_window().and_then(|w| w.title.clone()),
+            cost_saved_usd: None,
+            deleted_at: None,
+            degraded: self.offline.is_some(),
+            verbatim: mode.verbatim,
+            summary_only: false,
+            source_url: self.last_context.url.clone(),
+            clipped: self.is_clipped(),
+        };
+        let Some(item) = crate::redact::for_storage(item, mode.history_storage) else {
+            return;
+        };
+        if let Err(e) = db.insert_history(&item) {
+            log::warn!("Failed to save the failed dictation: {}", e);
+        }
+    }
+
+    /// Abort the current recording, discarding its audio
+    pub fn cancel_recording(&mut self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(AppError::NoRecordingInProgress);
+        }
+
+        self.cancel.cancel();
+        let audio = crate::audio::stop_recording(&self.recording_handle);
+        // The segmenter stops on its own once recording ends and the token is cancelled
+        self.segmenter = None;
+        self.end_dictation();
+        self.status = RecordingStatus::Ready;
+        if let RecordedAudio::Spooled { path, .. } = audio? {
+            let _ = std::fs::remove_file(path);
+        }
+
+        log::info!("Recording cancelled");
+        Ok(())
+    }
+
+    /// Let background jobs and media players go on once a dictation is over, however it ended
+    fn end_dictation(&mut self) {
+        self.interactive = None;
+        self.offline = None;
+        if let Some(media) = self.paused_media.take() {
+            media.resume();
+        }
+    }
+
+    /// Internal: process recorded audio up to its output (transcribe, AI)
+    ///
+    /// Takes of a comparing mode are left for the caller to have one picked.
+    async fn process_recording(&mut self, id: &str, audio: RecordedAudio, trace: &mut LatencyTrace) -> Result<Processed> {
         // Get active mode
-        let mode = self
+        let mut mode = self
             .get_active_mode()
             .cloned()
             .ok_or_else(|| AppError::ModeNotFound(self.active_mode_key.clone()))?;
+        self.fall_back_if_offline(id, &mut mode).await;
+        let incognito = self.dictation_incognito;
+        // History that keeps no recording gets none to destroy afterwards
+        let keep_audio = !incognito && mode.history_storage == HistoryStorage::Full;
 
         // Save audio file
+        let span = trace.begin("save_audio");
         let audio_dir = get_audio_dir()?;
         tokio::fs::create_dir_all(&audio_dir).await?;
 
-        let audio_id = Uuid::new_v4().to_string();
-        let audio_path = audio_dir.join(format!("{}.wav", audio_id));
-        crate::audio::save_wav(&samples, &audio_path)?;
+        let audio_path = audio_dir.join(format!("{}.wav", id));
+        let audio = match audio {
+            // Transcribed from memory, or from the spool it is deleted with
+            audio if !keep_audio => audio,
+            RecordedAudio::Memory(samples) => {
+                let (primary, secondary) = if self.settings.save_two_track {
+                    self.recording_handle.get_tracks()
+                } else {
+                    (Vec::new(), Vec::new())
+                };
+                if mode.verbatim {
+                    // The mix is what was transcribed, so it is the record
+                    crate::audio::save_wav_lossless(&samples, &audio_path)?;
+                } else if secondary.is_empty() {
+                    crate::audio::save_wav(&samples, &audio_path)?;
+                } else {
+                    crate::audio::save_wav_stereo(&primary, &secondary, &audio_path)?;
+                }
+                RecordedAudio::Memory(samples)
+            }
+            RecordedAudio::Spooled { path, sample_count } => {
+                // The spool already is the recording; move it into place
+                if tokio::fs::rename(&path, &audio_path).await.is_err() {
+                    tokio::fs::copy(&path, &audio_path).await?;
+                    let _ = tokio::fs::remove_file(&path).await;
+                }
+                RecordedAudio::Spooled {
+                    path: audio_path.clone(),
+                    sample_count,
+                }
+            }
+        };
+        trace.end(span);
 
-        let duration_ms = crate::audio::calculate_duration_ms(samples.len());
+        let duration_ms = crate::audio::calculate_duration_ms(audio.sample_count());
 
         // Transcribe
         log::info!("Starting transcription...");
-        let transcript = self.transcribe(&samples, &mode).await?;
-        log::info!("Transcription complete: {} chars", transcript.len());
+        let span = trace.begin("stt");
+        let mut segmented = match self.segmenter.take() {
+            Some(segmenter) => Some(segmenter.finish().await),
+            None => None,
+        };
+        let mut confidence = None;
+        let mut alternatives = Vec::new();
+        let transcript = match (&mut segmented, &audio) {
+            (Some(crate::segmenter::Segmented { router, transcribed_samples }), _) => {
+                // Earlier segments are already transcribed; the tail, and any
+                // segment that failed, are left
+                let done = *transcribed_samples;
+                let tail = audio.read_range(done, audio.sample_count().saturating_sub(done))?;
+                let tail_text = if tail.is_empty() {
+                    String::new()
+                } else {
+                    self.transcribe(&tail, &mode).await?.text
+                };
+                let tail_text = if self.settings.typography && !mode.verbatim {
+                    crate::typography::apply(&tail_text, &self.settings.language)
+                } else {
+                    tail_text
+                };
+
+                let span = trace.begin("insertion");
+                let insert = router.push(&tail_text);
+                if !insert.is_empty() {
+                    crate::segmenter::paste_segment(
+                        &insert,
+                        self.settings.auto_paste,
+                        self.settings.paste,
+                        self.settings.paste_rules.clone(),
+                    )
+                    .await;
+                }
+                trace.end(span);
 
-        // AI processing if enabled
-        let output = if mode.ai_processing && !mode.prompt_template.is_empty() {
-            log::info!("Starting AI processing...");
-            match self.process_with_llm(&transcript, &mode).await {
+                router.output().to_string()
+            }
+            (None, RecordedAudio::Memory(samples)) => {
+                let scored = self.transcribe(samples, &mode).await?;
+                confidence = scored.confidence;
+                alternatives = scored.alternatives;
+                scored.text
+            }
+            (None, RecordedAudio::Spooled { path, sample_count }) => {
+                let provider = self.create_stt(&mode).await?;
+                let progress = self.transcription_progress(id, &mode.key);
+                self.cancel
+                    .run(stt::transcribe_wav_chunked(
+                        provider.as_ref(),
+                        path,
+                        *sample_count,
+                        Some(&self.settings.language),
+                        &progress,
+                    ))
+                    .await?
+            }
+        };
+        // Segments were pasted while recording, before replacements could apply
+        let transcript = if segmented.is_some() || mode.verbatim {
+            transcript
+        } else {
+            self.settings.dictionary.apply(&transcript)
+        };
+        // Only punctuation and case change, so verbatim modes get it too
+        let transcript = if segmented.is_some() {
+            transcript
+        } else {
+            crate::punctuation::restore(
+                &transcript,
+                &self.settings.language,
+                self.settings.punctuation,
+                self.settings.punctuation_command(),
+            )
+            .await
+        };
+        let bookmarks = self.collect_bookmarks(&audio, &mode).await;
+        let stt_ms = trace.end(span);
+        log::info!("Transcription complete: {} chars", transcript.len());
+        // Kept for a failed dictation's history item, so only as history would keep it
+        self.last_transcript = crate::redact::for_sinks(&transcript, mode.history_storage, false);
+        // The event sink is a log too; text history does not keep stays in memory
+        let transcript_event = match &self.last_transcript {
+            Some(shared) => json!({ "transcript": shared }),
+            None => json!({ "chars": transcript.len() }),
+        };
+        self.emit_event("stt", id, &mode.key, Some(stt_ms), transcript_event);
+
+        // AI processing if enabled (segments were pasted as-is while recording)
+        let mut llm_used = None;
+        let mut takes = None;
+        let mut cost_saved_usd = None;
+        let mut pipeline_cost = None;
+        // Why the pipeline's output is only partly processed
+        let mut pipeline_error = None;
+        let ai_processing = mode.uses_llm() && !mode.prompt_template.is_empty();
+        let output = if segmented.is_some() {
+            transcript.clone()
+        } else if mode.uses_pipeline() {
+            log::info!("Running {} pipeline steps...", mode.steps.len());
+            let span = trace.begin("pipeline");
+            let result = match self.run_pipeline(&transcript, &mode, trace).await {
                 Ok(result) => result,
+                Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+                // A step that cannot be set up, such as an LLM without its API key
                 Err(e) => {
-                    log::warn!("AI processing failed: {}, using raw transcript", e);
-                    transcript.clone()
+                    log::warn!("Pipeline could not be set up, using raw transcript: {}", e);
+                    pipeline_error = Some(ErrorRecord::new(ErrorStage::Llm, None, &e));
+                    PipelineOutput {
+                        text: transcript.clone(),
+                        ..Default::default()
+                    }
                 }
+            };
+            trace.end(span);
+            if let Some(step) = result.failed_step() {
+                let message = format!("{} failed: {}", step.name, step.error.as_deref().unwrap_or_default());
+                pipeline_error = Some(ErrorRecord::new(ErrorStage::Llm, None, &AppError::Provider(message)));
+            }
+            // Still pasted, but the user should know it is not what the mode makes
+            if let Some(error) = &pipeline_error {
+                crate::notify::send("Dictation only partly processed", &error.message);
+                self.emit_event("degraded", id, &mode.key, None, json!({ "error": error.message }));
+            }
+            if let Some((provider, model)) = result.llm {
+                llm_used = Some((provider, model, result.usage));
+                pipeline_cost = result.cost_usd;
             }
+            result.text
+        } else if ai_processing && mode.skip_llm.as_ref().is_some_and(|rule| rule.applies(&transcript, confidence)) {
+            // Nothing to fix; the saving is what the call would have cost
+            let usage = llm::TokenUsage::estimate(&self.build_prompt(&transcript, &mode), &transcript);
+            cost_saved_usd = llm::estimate_cost_usd(&mode.llm_provider, &mode.llm_model, &usage);
+            log::info!("Skipping AI processing for a confident transcript ({:?})", confidence);
+            transcript.clone()
+        } else if ai_processing {
+            log::info!("Starting AI processing...");
+            let span = trace.begin("llm");
+            // Comparison models run alongside the primary
+            let (primary, compared) = tokio::join!(
+                self.process_with_llm(id, &transcript, &mode),
+                self.compare_takes(&transcript, &mode)
+            );
+            let mut candidates = match primary {
+                Ok(take) => vec![take],
+                Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+                Err(e) => {
+                    log::warn!("AI processing failed: {}", e);
+                    Vec::new()
+                }
+            };
+            candidates.extend(compared?);
+            let llm_ms = trace.end(span);
+
+            let request = (candidates.len() > 1).then(|| PickRequest {
+                mode_key: mode.key.clone(),
+                candidates: candidates
+                    .iter()
+                    .map(|(completion, provider, model)| Candidate {
+                        provider: format!("{:?}", provider).to_lowercase(),
+                        model: model.clone(),
+                        text: completion.text.clone(),
+                    })
+                    .collect(),
+            });
+            takes = Some(Takes {
+                candidates,
+                request,
+                llm_ms,
+            });
+            // Replaced by the take used
+            String::new()
+        } else if mode.post_processor() == PostProcessor::Rules {
+            crate::cleanup::clean(&transcript, &self.settings.language, self.settings.pacing.keep_filler_words)
         } else {
             transcript.clone()
         };
 
+        Ok(Processed {
+            mode,
+            incognito,
+            keep_audio,
+            audio_path,
+            duration_ms,
+            segmented: segmented.is_some(),
+            transcript,
+            output,
+            llm_used,
+            takes,
+            picks: None,
+            cost_saved_usd,
+            pipeline_cost,
+            pipeline_error,
+            bookmarks,
+            alternatives,
+        })
+    }
+
+    /// Make the `chosen` take of a comparison the output, the first when none was picked
+    fn use_take(&self, id: &str, processed: &mut Processed, takes: Takes, chosen: Option<usize>) {
+        let Takes {
+            mut candidates,
+            request,
+            llm_ms,
+        } = takes;
+        // Every take was paid for, so usage covers all of them
+        let mut usage = llm::TokenUsage::default();
+        for (completion, _, _) in &candidates {
+            usage.add(&completion.usage);
+        }
+        if candidates.is_empty() {
+            log::warn!("No LLM output, using raw transcript");
+            processed.output = processed.transcript.clone();
+            return;
+        }
+        let (completion, provider, model) = candidates.swap_remove(chosen.unwrap_or(0));
+        self.emit_event(
+            "llm",
+            id,
+            &processed.mode.key,
+            Some(llm_ms),
+            json!({
+                "provider": format!("{:?}", provider).to_lowercase(),
+                "model": model,
+                "text": crate::redact::for_sinks(&completion.text, processed.mode.history_storage, true),
+            }),
+        );
+        processed.llm_used = Some((provider, model, usage));
+        processed.output = completion.text;
+        // Only an actual choice counts towards the model recommendation
+        processed.picks = request.zip(chosen);
+    }
+
+    /// Internal: finish processed output (limit, filter), deliver it and save history
+    async fn finish_processing(&mut self, id: &str, processed: Processed, trace: &mut LatencyTrace) -> Result<String> {
+        let Processed {
+            mode,
+            incognito,
+            keep_audio,
+            audio_path,
+            duration_ms,
+            segmented,
+            transcript,
+            output,
+            mut llm_used,
+            takes: _,
+            picks,
+            cost_saved_usd,
+            pipeline_cost,
+            pipeline_error,
+            bookmarks,
+            alternatives,
+        } = processed;
+
+        // Typography would corrupt the quotes in structured JSON output
+        let structured = mode.json_schema.is_some() && mode.uses_llm() && llm_used.is_some();
+        let output = if self.settings.typography && !segmented && !structured && !mode.verbatim {
+            crate::typography::apply(&output, &self.settings.language)
+        } else {
+            output
+        };
+
+        // Enforce the mode's or target app's length limit (never on JSON, which would break)
+        let mut truncated = false;
+        let output = match self.output_limit(&mode) {
+            Some(max_chars) if !segmented && !structured => {
+                let span = trace.begin("limit");
+                let (output, changed, usage) = self
+                    .fit_output(output, max_chars, &mode, llm_used.is_some())
+                    .await?;
+                if let Some((_, _, total)) = llm_used.as_mut() {
+                    total.add(&usage);
+                }
+                truncated = changed;
+                trace.end(span);
+                output
+            }
+            _ => output,
+        };
+
+        let mut command_error = None;
+        let output = if !segmented && self.settings.output_command(&mode).is_some() {
+            let span = trace.begin("command");
+            let (output, error) = self.run_output_command(output, id, &mode).await;
+            trace.end(span);
+            if let Some(error) = &error {
+                crate::notify::send("Output command failed", &format!("{}; the text was pasted unfiltered", error.message));
+            }
+            command_error = error;
+            output
+        } else {
+            if segmented && self.settings.output_command(&mode).is_some() {
+                log::info!("Segments were pasted while recording, so the output command was not run");
+            }
+            output
+        };
+
+        // Discard the output if cancelled while processing
+        self.cancel.check()?;
+        let shared_output = crate::redact::for_sinks(&output, mode.history_storage, llm_used.is_some());
+
+        // Journaling modes append to the day's entry instead of pasting
+        // Incognito journaling dictations, and those history keeps no text of, are pasted like any other
+        let journal_entry = shared_output.as_ref().filter(|_| mode.journal && !incognito);
+        let (target, output_ms) = if let Some(entry) = journal_entry {
+            let span = trace.begin("journal");
+            if let Some(db) = &self.database {
+                let markdown_dir = self.settings.journal_dir.as_ref().map(PathBuf::from);
+                if let Err(e) = crate::journal::append(db, markdown_dir.as_deref(), entry, &Local::now()) {
+                    log::warn!("Failed to append to journal: {}", e);
+                }
+            }
+            ("journal", Some(trace.end(span)))
+        } else if let (false, Some(target)) = (segmented, &self.settings.remote_output) {
+            let span = trace.begin("remote");
+            // Sent in the background: a slow host must not hold the state lock
+            let (target, text) = (target.clone(), output.clone());
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = remote::send(&target, &text).await {
+                    log::warn!("Failed to send output to the remote target: {}", e);
+                }
+            });
+            // Keep a local copy so nothing is lost if the remote end missed it
+            let _ = paste::copy_and_paste(&output, false, &self.settings.paste, &[]);
+            ("remote", Some(trace.end(span)))
+        } else if !segmented {
+            // Copy to clipboard and paste
+            let span = trace.begin("insertion");
+            let _ = paste::copy_and_paste(
+                &output,
+                self.settings.auto_paste,
+                &self.settings.paste,
+                &self.settings.paste_rules,
+            );
+            ("paste", Some(trace.end(span)))
+        } else {
+            ("segments", None)
+        };
+        self.emit_event(
+            "output",
+            id,
+            &mode.key,
+            output_ms,
+            json!({ "target": target, "text": shared_output, "truncated": truncated }),
+        );
+
+        // Lossless verbatim recordings stay float WAV; others only stay if stored
+        let audio_path = if mode.history_storage == HistoryStorage::Full && !mode.verbatim && !incognito {
+            crate::audio_codec::compress(audio_path, self.settings.recording_format).await
+        } else {
+            audio_path
+        };
+
         // Save to history
+        let stt_used = self.stt_selection(&mode);
         let history_item = HistoryItem {
-            id: audio_id,
+            id: id.to_string(),
             created_at: Utc::now(),
             mode_key: mode.key.clone(),
-            audio_path: Some(audio_path.to_string_lossy().to_string()),
+            audio_path: keep_audio.then(|| audio_path.to_string_lossy().to_string()),
             transcript_raw: transcript.clone(),
             output_final: output.clone(),
-            stt_provider: format!("{:?}", mode.stt_provider).to_lowercase(),
-            stt_model: mode.stt_model.clone(),
-            llm_provider: if mode.ai_processing {
-                Some(format!("{:?}", mode.llm_provider).to_lowercase())
-            } else {
-                None
-            },
-            llm_model: if mode.ai_processing {
-                Some(mode.llm_model.clone())
+            stt_provider: format!("{:?}", stt_used.provider).to_lowercase(),
+            stt_model: stt_used.model_used(),
+            llm_provider: llm_used
+                .as_ref()
+                .map(|(provider, _, _)| format!("{:?}", provider).to_lowercase()),
+            llm_model: llm_used.as_ref().map(|(_, model, _)| model.clone()),
+            duration_ms,
+            // Kept as an error, so it is listed with failed dictations and reprocessing clears it
+            error: pipeline_error.or(command_error),
+            latency: trace.finish(),
+            bookmarks,
+            prompt_tokens: llm_used.as_ref().map(|(_, _, usage)| usage.prompt_tokens),
+            completion_tokens: llm_used.as_ref().map(|(_, _, usage)| usage.completion_tokens),
+            // Pipeline steps may each use another model, so their costs are summed as they run
+            cost_usd: if mode.uses_pipeline() {
+                pipeline_cost
             } else {
-                None
+                llm_used
+                    .as_ref()
+                    .and_then(|(provider, model, usage)| llm::estimate_cost_usd(provider, model, usage))
             },
-            duration_ms,
-            error: None,
+            stats: TextStats::of(&output),
+            truncated,
+            window_class: self.remembered_window().and_then(|w| w.class.clone()),
+            window_title: self.remembered_window().and_then(|w| w.title.clone()),
+            cost_saved_usd,
+            deleted_at: None,
+            degraded: self.offline.is_some(),
+            verbatim: mode.verbatim,
+            summary_only: false,
+            source_url: self.last_context.url.clone(),
+            clipped: self.is_clipped(),
         };
 
-        if let Some(db) = &self.database {
-            let db = db.lock().unwrap();
-            let _ = db.insert_history(&history_item);
+        // The output is already pasted; history may only keep it redacted, or not at all
+        let total_ms = history_item.latency.first().map(|root| root.duration_ms);
+        let stored = if incognito {
+            None
+        } else {
+            crate::redact::for_storage(history_item, mode.history_storage)
+        };
+        if incognito {
+            self.last_transcript = None;
+        }
+        if let (Some(db), Some(history_item)) = (&self.database, &stored) {
+            if let Err(e) = db.insert_history(history_item) {
+                log::warn!("Failed to save the dictation to history: {}", e);
+                crate::notify::send("Dictation not saved", &format!("{}; the text was pasted all the same", e));
+            }
+            // Other readings hold transcript text, so only fully stored items keep them
+            if !alternatives.is_empty() && mode.history_storage == HistoryStorage::Full {
+                if let Err(e) = db.set_alternatives(&history_item.id, &alternatives) {
+                    log::warn!("Failed to store transcription alternatives: {}", e);
+                }
+            }
+            if let Some((request, chosen)) = &picks {
+                let shown: Vec<(String, String)> = request
+                    .candidates
+                    .iter()
+                    .map(|candidate| (candidate.provider.clone(), candidate.model.clone()))
+                    .collect();
+                if let Err(e) = db.record_model_picks(&history_item.id, &mode.key, &shown, *chosen) {
+                    log::warn!("Failed to record model pick: {}", e);
+                }
+            }
         }
 
-        // Copy to clipboard and paste
-        let _ = paste::copy_and_paste(&output, self.settings.auto_paste);
+        // Local stand-ins are not what the next startup should warm up
+        if self.offline.is_none() {
+            self.remember_warm(WarmCache {
+                stt: Some(self.stt_selection(&mode)),
+                llm: llm_used.as_ref().map(|(provider, model, _)| crate::modes::LlmFallback {
+                    provider: provider.clone(),
+                    model: model.clone(),
+                }),
+            });
+        }
 
         self.status = RecordingStatus::Ready;
+        self.emit_event(
+            "completed",
+            id,
+            &mode.key,
+            total_ms,
+            json!({ "output": shared_output, "duration_ms": duration_ms }),
+        );
+        self.run_hook(HookEvent::Result, id, &mode.key, "completed", shared_output, None);
 
         Ok(output)
     }
 
-    /// Transcribe audio samples
-    async fn transcribe(&self, samples: &[f32], mode: &Mode) -> Result<String> {
-        let api_key = self.get_stt_api_key(&mode.stt_provider)?;
-        let server_url = self.settings.whisper_server_url.clone();
+    /// Report a completed pipeline stage to the event sink, if one is configured
+    ///
+    /// Incognito dictations report their stages without any text.
+    fn emit_event(&self, stage: &str, id: &str, mode_key: &str, duration_ms: Option<u64>, data: Value) {
+        let data = if self.dictation_incognito { Value::Null } else { data };
+        self.events
+            .emit(PipelineEvent::new(stage, id, mode_key, duration_ms, data));
+    }
 
-        let provider = stt::create_stt_provider(
-            &mode.stt_provider,
-            &mode.stt_model,
+    /// Run the user's hook for `event` in the background, if one is configured
+    ///
+    /// Incognito dictations run hooks without their text.
+    fn run_hook(
+        &self,
+        event: HookEvent,
+        id: &str,
+        mode_key: &str,
+        status: &str,
+        transcript: Option<String>,
+        error: Option<String>,
+    ) {
+        let Some(command) = self.settings.hook_command(event) else {
+            return;
+        };
+        let vars = HookVars {
+            id: id.to_string(),
+            mode: mode_key.to_string(),
+            status: status.to_string(),
+            transcript: transcript.filter(|_| !self.dictation_incognito),
+            error: error.filter(|_| !self.dictation_incognito),
+        };
+        crate::hooks::spawn(command, event, vars, self.settings.hooks.timeout());
+    }
+
+    /// Run a mode end-to-end on a sample clip, without pasting or saving history
+    pub async fn test_mode(&self, mode_key: &str, sample: &SampleClip) -> Result<ModeTestResult> {
+        let mode = self
+            .modes
+            .get(mode_key)
+            .cloned()
+            .ok_or_else(|| AppError::ModeNotFound(mode_key.to_string()))?;
+        // Nothing else can be processing while the state is borrowed here
+        self.cancel.reset();
+
+        let mut trace = LatencyTrace::start("mode_test");
+
+        let span = trace.begin("load_audio");
+        let samples = crate::audio_codec::load_audio(&sample.path)?;
+        trace.end(span);
+
+        let span = trace.begin("stt");
+        let transcript = self.transcribe(&samples, &mode).await?.text;
+        trace.end(span);
+
+        let mut llm_output = None;
+        let mut llm_provider = None;
+        let mut llm_error = None;
+        if mode.uses_llm() && !mode.prompt_template.is_empty() {
+            let span = trace.begin("llm");
+            match self.process_with_llm("test", &transcript, &mode).await {
+                Ok((completion, provider, _)) => {
+                    llm_output = Some(completion.text);
+                    llm_provider = Some(format!("{:?}", provider).to_lowercase());
+                }
+                Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+                Err(e) => llm_error = Some(e.to_string()),
+            }
+            trace.end(span);
+        }
+
+        let structured = mode.json_schema.is_some() && llm_output.is_some();
+        let output = match &llm_output {
+            Some(output) => output.clone(),
+            None if mode.post_processor() == PostProcessor::Rules => {
+                crate::cleanup::clean(&transcript, &self.settings.language, self.settings.pacing.keep_filler_words)
+            }
+            None => transcript.clone(),
+        };
+        let output = if self.settings.typography && !structured && !mode.verbatim {
+            crate::typography::apply(&output, &self.settings.language)
+        } else {
+            output
+        };
+        let (output, truncated) = match mode.max_output_chars {
+            Some(max_chars) if !structured && !mode.verbatim => {
+                let (output, changed, _) = self
+                    .fit_output(output, max_chars, &mode, llm_output.is_some())
+                    .await?;
+                (output, changed)
+            }
+            _ => (output, false),
+        };
+
+        Ok(ModeTestResult {
+            mode_key: mode.key.clone(),
+            sample_id: sample.id.clone(),
+            word_error_rate: sample
+                .expected
+                .as_deref()
+                .map(|expected| crate::samples::word_error_rate(expected, &transcript)),
+            expected: sample.expected.clone(),
+            transcript,
+            llm_output,
+            llm_provider,
+            llm_error,
+            output,
+            truncated,
+            latency: trace.finish(),
+        })
+    }
+
+    /// Run a mode's processing on a typed transcript, without recording, pasting or saving history
+    ///
+    /// Returns the text after each stage, so a prompt can be iterated on
+    /// without dictating it every time. The mode need not be saved yet.
+    pub async fn dry_run(&self, mode: &Mode, transcript: &str) -> Result<DryRunResult> {
+        // Nothing else can be processing while the state is borrowed here
+        self.cancel.reset();
+
+        let mut trace = LatencyTrace::start("dry_run");
+        let mut stages = Vec::new();
+        let mut text = transcript.to_string();
+        let stage = |name: &str, text: &str, duration_ms: u64| StepOutput {
+            name: name.to_string(),
+            prompt: None,
+            text: text.to_string(),
+            duration_ms,
+            error: None,
+        };
+
+        if !mode.verbatim {
+            let span = trace.begin("replacements");
+            text = self.settings.dictionary.apply(&text);
+            stages.push(stage("replacements", &text, trace.end(span)));
+        }
+
+        let mut usage = None;
+        let mut cost_usd = None;
+        if mode.uses_pipeline() {
+            let result = self.run_pipeline(&text, mode, &mut trace).await?;
+            if result.llm.is_some() {
+                usage = Some(result.usage);
+                cost_usd = result.cost_usd;
+            }
+            stages.extend(result.steps);
+            text = result.text;
+        } else if mode.uses_llm() && !mode.prompt_template.is_empty() {
+            let span = trace.begin("llm");
+            let prompt = self.build_prompt(&text, mode);
+            let (provider, mut chain) = self.create_llm(mode)?;
+            let result = self.complete(&provider, &prompt, mode).await;
+            let mut error = None;
+            match result {
+                Ok(completion) => {
+                    let (provider_type, model) = chain.swap_remove(provider.used_index().unwrap_or(0));
+                    cost_usd = llm::estimate_cost_usd(&provider_type, &model, &completion.usage);
+                    usage = Some(completion.usage);
+                    text = completion.text;
+                }
+                Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+                Err(e) => error = Some(e.to_string()),
+            }
+            stages.push(StepOutput {
+                prompt: Some(prompt),
+                error,
+                ..stage("llm", &text, trace.end(span))
+            });
+        } else if mode.post_processor() == PostProcessor::Rules {
+            let span = trace.begin("cleanup");
+            text = crate::cleanup::clean(&text, &self.settings.language, self.settings.pacing.keep_filler_words);
+            stages.push(stage("cleanup", &text, trace.end(span)));
+        }
+
+        let structured = mode.json_schema.is_some() && mode.uses_llm() && usage.is_some();
+        if self.settings.typography && !structured && !mode.verbatim {
+            let span = trace.begin("typography");
+            text = crate::typography::apply(&text, &self.settings.language);
+            stages.push(stage("typography", &text, trace.end(span)));
+        }
+
+        // The target app is unknown, so only the mode's limit applies
+        let mut truncated = false;
+        if let Some(max_chars) = mode.max_output_chars.filter(|_| !structured && !mode.verbatim) {
+            let span = trace.begin("limit");
+            let (output, changed, _) = self.fit_output(text, max_chars, mode, usage.is_some()).await?;
+            text = output;
+            truncated = changed;
+            stages.push(stage("limit", &text, trace.end(span)));
+        }
+
+        if self.settings.output_command(mode).is_some() {
+            let span = trace.begin("command");
+            let (output, error) = self.run_output_command(text, "dry-run", mode).await;
+            text = output;
+            stages.push(StepOutput {
+                error: error.map(|error| error.message),
+                ..stage("command", &text, trace.end(span))
+            });
+        }
+        trace.finish();
+
+        Ok(DryRunResult {
+            transcript: transcript.to_string(),
+            stages,
+            output: text,
+            truncated,
+            prompt_tokens: usage.map(|usage| usage.prompt_tokens),
+            completion_tokens: usage.map(|usage| usage.completion_tokens),
+            cost_usd,
+        })
+    }
+
+    /// Prepare to transcribe kept recordings again with `selection`, applying
+    /// the same replacements and punctuation as a dictation in `mode`
+    ///
+    /// Only copies settings; the model is loaded by `RetranscriberSettings::load`
+    /// once the state lock is released.
+    pub fn retranscriber(&self, mode: &Mode, selection: &stt::SttSelection) -> Result<RetranscriberSettings> {
+        Ok(RetranscriberSettings {
+            selection: selection.clone(),
+            api_key: self.get_stt_api_key(&selection.provider)?,
+            server_url: self.settings.stt_endpoint(&selection.provider),
+            language: self.settings.language.clone(),
+            dictionary: (!mode.verbatim).then(|| self.settings.dictionary.clone()),
+            punctuation: self.settings.punctuation,
+            punctuation_command: self.settings.punctuation_command().map(str::to_string),
+        })
+    }
+
+    /// Remember the models a dictation used, for the next startup's warm-up
+    fn remember_warm(&mut self, cache: WarmCache) {
+        if cache == self.warm_cache {
+            return;
+        }
+        if let Err(e) = crate::warmup::save(&cache) {
+            log::warn!("Failed to save warm-up cache: {}", e);
+        }
+        self.warm_cache = cache;
+    }
+
+    /// The STT provider and model used for a mode, honouring a session swap
+    /// and any local stand-in while offline
+    pub fn stt_selection(&self, mode: &Mode) -> stt::SttSelection {
+        self.offline
+            .as_ref()
+            .and_then(|fallback| fallback.stt.clone())
+            .or_else(|| self.stt_override.clone())
+            .unwrap_or_else(|| stt::SttSelection {
+                provider: mode.stt_provider.clone(),
+                model: mode.stt_model.clone(),
+            })
+    }
+
+    /// Switch a dictation to local providers when its cloud ones are unreachable
+    async fn fall_back_if_offline(&mut self, id: &str, mode: &mut Mode) {
+        let stt = self.stt_selection(mode);
+        if !self.settings.offline_fallback
+            || !crate::connectivity::uses_cloud(mode, &stt)
+            || crate::connectivity::is_online().await
+        {
+            return;
+        }
+        let downloaded = crate::connectivity::offline_models(
+            &stt::downloaded_models(),
+            &self.settings.default_stt_model,
+            &self.settings.language,
+        );
+        let Some((local, fallback)) = crate::connectivity::local_mode(
+            mode,
+            &stt,
+            &downloaded,
+            self.settings.offline_llm_model.as_deref(),
+        ) else {
+            return;
+        };
+
+        log::warn!("Network is down, dictating in degraded mode ({})", fallback.describe());
+        let _ = self.app_handle.emit("degraded-mode", &fallback);
+        self.emit_event("degraded", id, &mode.key, None, json!({ "fallback": fallback }));
+        *mode = local;
+        self.offline = Some(fallback);
+    }
+
+    /// A copy of the mode using the session's STT selection
+    fn with_stt_selection(&self, mode: &Mode) -> Mode {
+        let selection = self.stt_selection(mode);
+        Mode {
+            stt_provider: selection.provider,
+            stt_model: selection.model,
+            ..mode.clone()
+        }
+    }
+
+    /// Create the STT provider for a mode
+    async fn create_stt(&self, mode: &Mode) -> Result<Box<dyn stt::SttProvider>> {
+        self.create_stt_for(&self.stt_selection(mode)).await
+    }
+
+    /// Create a specific STT provider and model
+    async fn create_stt_for(&self, selection: &stt::SttSelection) -> Result<Box<dyn stt::SttProvider>> {
+        let api_key = self.get_stt_api_key(&selection.provider)?;
+        let server_url = self.settings.stt_endpoint(&selection.provider);
+
+        stt::create_stt_provider(
+            &selection.provider,
+            &selection.model,
             api_key,
             server_url,
-        ).await?;
+        ).await
+    }
+
+    /// Progress of a long transcription, for the indicator and the event sink
+    fn transcription_progress(&self, id: &str, mode_key: &str) -> stt::Progress {
+        let (handle, events) = (self.app_handle.clone(), self.events.clone());
+        let (id, mode_key) = (id.to_string(), mode_key.to_string());
+        stt::Progress::new(move |percent| {
+            crate::indicator::emit_progress(&handle, &id, percent);
+            events.emit(PipelineEvent::new("progress", &id, &mode_key, None, json!({ "percent": percent })));
+        })
+    }
+
+    /// Transcribe audio samples
+    async fn transcribe(&self, samples: &[f32], mode: &Mode) -> Result<stt::ScoredTranscript> {
+        let provider = self.create_stt(mode).await?;
 
         provider
-            .transcribe(samples, Some(&self.settings.language))
+            .transcribe_scored(samples, Some(&self.settings.language), &self.cancel)
             .await
     }
 
-    /// Process transcript with LLM
-    async fn process_with_llm(&self, transcript: &str, mode: &Mode) -> Result<String> {
+    /// Collect bookmarks for the recording, transcribing a short label after each
+    async fn collect_bookmarks(&self, audio: &RecordedAudio, mode: &Mode) -> Vec<Bookmark> {
+        let offsets = self.recording_handle.get_bookmarks();
+        if offsets.is_empty() {
+            return Vec::new();
+        }
+
+        let mut labels = vec![None; offsets.len()];
+        if self.settings.bookmark_labels {
+            let window = (BOOKMARK_LABEL_MS as usize * crate::audio::WHISPER_SAMPLE_RATE as usize) / 1000;
+            let chunks: Vec<Vec<f32>> = offsets
+                .iter()
+                .map(|&offset_ms| {
+                    let start = (offset_ms as usize * crate::audio::WHISPER_SAMPLE_RATE as usize) / 1000;
+                    audio.read_range(start, window).unwrap_or_default()
+                })
+                .collect();
+
+            let result = match self.create_stt(mode).await {
+                Ok(provider) => provider
+                    .transcribe_chunks(&chunks, Some(&self.settings.language), &stt::Progress::default())
+                    .await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(texts) => {
+                    labels = texts
+                        .into_iter()
+                        .map(|t| if t.is_empty() { None } else { Some(t) })
+                        .collect();
+                }
+                Err(e) => log::warn!("Failed to transcribe bookmark labels: {}", e),
+            }
+        }
+
+        offsets
+            .into_iter()
+            .zip(labels)
+            .map(|(offset_ms, label)| Bookmark { offset_ms, label })
+            .collect()
+    }
+
+    /// Create the mode's LLM with its fallbacks
+    ///
+    /// Returns the provider with the (provider, model) of each link in the chain.
+    fn create_llm(&self, mode: &Mode) -> Result<LlmChain> {
+        self.create_llm_chain(&mode.llm_provider, &mode.llm_model, &mode.llm_fallbacks)
+    }
+
+    /// Create an LLM provider trying `fallbacks` after it, with the provider
+    /// and model of each in order
+    pub fn create_llm_chain(
+        &self,
+        provider: &LlmProviderType,
+        model: &str,
+        fallbacks: &[crate::modes::LlmFallback],
+    ) -> Result<LlmChain> {
         // Get API key if needed
-        let api_key = self.get_api_key(&mode.llm_provider)?;
+        let api_key = self.get_api_key(provider)?;
 
-        let provider = llm::create_llm_provider(
-            &mode.llm_provider,
-            &mode.llm_model,
+        let mut providers = vec![llm::create_llm_provider(
+            provider,
+            model,
             api_key.as_deref(),
             self.settings.ollama_url.clone(),
-        )?;
+        )?];
+        let mut chain = vec![(provider.clone(), model.to_string())];
+
+        // Fallbacks that cannot be created (e.g. missing API key) are skipped
+        for fallback in fallbacks {
+            let created = self.get_api_key(&fallback.provider).and_then(|api_key| {
+                llm::create_llm_provider(
+                    &fallback.provider,
+                    &fallback.model,
+                    api_key.as_deref(),
+                    self.settings.ollama_url.clone(),
+                )
+            });
+            match created {
+                Ok(provider) => {
+                    providers.push(provider);
+                    chain.push((fallback.provider.clone(), fallback.model.clone()));
+                }
+                Err(e) => log::warn!("Skipping LLM fallback {:?}: {}", fallback.provider, e),
+            }
+        }
+        Ok((llm::FallbackLlmProvider::new(providers), chain))
+    }
 
-        let prompt = crate::modes::render_prompt(
-            &mode.prompt_template,
-            transcript,
-            self.last_context.as_deref(),
-            &self.settings.language,
-        );
+    /// Render the mode's prompt for a transcript
+    fn build_prompt(&self, transcript: &str, mode: &Mode) -> String {
+        self.render_prompt(&mode.prompt_template, transcript, mode)
+    }
+
+    /// Render a prompt template of the mode (its own or a step's) for a transcript
+    fn render_prompt(&self, template: &str, transcript: &str, mode: &Mode) -> String {
+        let vars = TemplateVars::new(transcript, &self.last_context, &self.settings.language)
+            .with_profile(&self.settings.prompt_profile);
+        // Modes that pick a context source get it even if their template omits it
+        let prompt_template = match mode.context_source {
+            ContextSource::Clipboard | ContextSource::Selection => template::with_context(template),
+            ContextSource::Auto | ContextSource::None => template.to_string(),
+        };
+        let prompt = self.settings.pacing.apply_to_prompt(template::render(&prompt_template, &vars));
+        let prompt = self.settings.dictionary.apply_to_prompt(prompt);
+        match self.style.as_ref().filter(|_| self.settings.learn_style) {
+            Some(style) => style.apply_to_prompt(prompt),
+            None => prompt,
+        }
+    }
+
+    /// Pipe the output through the mode's output command, if it has one
+    ///
+    /// The output is kept as it was when the command fails, with the error
+    /// to record; a dictation is never lost to its filter.
+    async fn run_output_command(&self, output: String, id: &str, mode: &Mode) -> (String, Option<ErrorRecord>) {
+        let Some(command) = self.settings.output_command(mode) else {
+            return (output, None);
+        };
+        let vars = HookVars {
+            id: id.to_string(),
+            mode: mode.key.clone(),
+            ..Default::default()
+        };
+        match crate::hooks::filter(command, &output, &vars, self.settings.hooks.timeout()).await {
+            Ok(filtered) => (filtered, None),
+            Err(e) => {
+                log::warn!("Output command failed, keeping the output: {}", e);
+                (output, Some(output_command_error(&e)))
+            }
+        }
+    }
+
+    /// Run the mode's steps on a transcript, each timed in `trace`
+    async fn run_pipeline(&self, transcript: &str, mode: &Mode, trace: &mut LatencyTrace) -> Result<PipelineOutput> {
+        let pipeline = Pipeline::prepare(&mode.steps, |provider, model| {
+            self.create_llm_chain(provider, model, &mode.llm_fallbacks)
+        })?;
+        pipeline
+            .run(
+                transcript,
+                &self.settings.language,
+                self.settings.pacing.keep_filler_words,
+                |template, text| self.render_prompt(template, text, mode),
+                &self.cancel,
+                trace,
+            )
+            .await
+    }
+
+    /// Complete a prompt, as structured JSON when the mode has a schema
+    async fn complete(&self, provider: &dyn llm::LlmProvider, prompt: &str, mode: &Mode) -> Result<llm::Completion> {
+        match &mode.json_schema {
+            Some(schema) => llm::complete_structured(provider, prompt, schema, &self.cancel).await,
+            None => provider.complete_cancellable(prompt, &self.cancel).await,
+        }
+    }
+
+    /// Complete a prompt, previewing the text as it is generated
+    async fn complete_with_preview(&self, provider: &dyn llm::LlmProvider, prompt: &str, job: &str) -> Result<llm::Completion> {
+        let (deltas, mut receiver) = tokio::sync::mpsc::channel::<String>(llm::STREAM_BUFFER);
+        let handle = self.app_handle.clone();
+        let job = job.to_string();
+        let preview = tokio::spawn(async move {
+            let mut text = String::new();
+            while let Some(delta) = receiver.recv().await {
+                text.push_str(&delta);
+                crate::indicator::emit_llm_preview(&handle, &job, &text);
+            }
+        });
+
+        let result = self.cancel.run(provider.complete_streaming(prompt, &deltas)).await;
+        drop(deltas);
+        let _ = preview.await;
+        result
+    }
 
-        provider.complete(&prompt).await
+    /// Process transcript with LLM
+    ///
+    /// Returns the completion with the provider and model that produced it,
+    /// which differ from the mode's when a fallback was used. Plain text is
+    /// previewed under `job` as it streams in.
+    async fn process_with_llm(
+        &self,
+        job: &str,
+        transcript: &str,
+        mode: &Mode,
+    ) -> Result<(llm::Completion, LlmProviderType, String)> {
+        let (provider, mut chain) = self.create_llm(mode)?;
+        let prompt = self.build_prompt(transcript, mode);
+
+        let completion = match mode.json_schema {
+            None if crate::features::enabled(Flag::StreamingPreview) => {
+                self.complete_with_preview(&provider, &prompt, job).await?
+            }
+            _ => self.complete(&provider, &prompt, mode).await?,
+        };
+        let (provider_type, model) = chain.swap_remove(provider.used_index().unwrap_or(0));
+        Ok((completion, provider_type, model))
+    }
+
+    /// Run the mode's comparison models on the transcript, for the multi-take picker
+    ///
+    /// Models that cannot be created or fail are skipped.
+    async fn compare_takes(
+        &self,
+        transcript: &str,
+        mode: &Mode,
+    ) -> Result<Vec<(llm::Completion, LlmProviderType, String)>> {
+        let prompt = self.build_prompt(transcript, mode);
+        let mut takes = Vec::new();
+
+        for other in mode.compare_models.iter().take(MAX_CANDIDATES - 1) {
+            let result = match self.get_api_key(&other.provider).and_then(|api_key| {
+                llm::create_llm_provider(
+                    &other.provider,
+                    &other.model,
+                    api_key.as_deref(),
+                    self.settings.ollama_url.clone(),
+                )
+            }) {
+                Ok(provider) => self.complete(provider.as_ref(), &prompt, mode).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(completion) => takes.push((completion, other.provider.clone(), other.model.clone())),
+                Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+                Err(e) => log::warn!("Skipping comparison model {}: {}", other.model, e),
+            }
+        }
+
+        Ok(takes)
+    }
+
+    /// Window focused when recording started, if the settings allow storing it
+    fn remembered_window(&self) -> Option<&crate::window_info::WindowInfo> {
+        self.last_context
+            .window
+            .as_ref()
+            .filter(|_| self.settings.remember_window)
+    }
+
+    /// Output length limit for a mode in the app focused when recording started
+    fn output_limit(&self, mode: &Mode) -> Option<usize> {
+        // A verbatim record is never shortened
+        if mode.verbatim {
+            return None;
+        }
+        crate::limit::effective_limit(
+            mode.max_output_chars,
+            &self.settings.app_char_limits,
+            self.last_context.app_name.as_deref(),
+        )
+    }
+
+    /// Fit output within `max_chars`
+    ///
+    /// With `use_llm` the mode's LLM is first asked to shorten the text; whatever
+    /// is still too long is cut at a sentence or word boundary. Returns the
+    /// output, whether it was changed, and the tokens spent shortening.
+    async fn fit_output(
+        &self,
+        output: String,
+        max_chars: usize,
+        mode: &Mode,
+        use_llm: bool,
+    ) -> Result<(String, bool, llm::TokenUsage)> {
+        let mut usage = llm::TokenUsage::default();
+        if !crate::limit::exceeds(&output, max_chars) {
+            return Ok((output, false, usage));
+        }
+
+        let mut text = output;
+        if use_llm {
+            let (provider, _) = self.create_llm(mode)?;
+            for attempt in 1..=crate::limit::SHORTEN_ATTEMPTS {
+                let prompt = crate::limit::shorten_prompt(&text, max_chars);
+                match provider.complete_cancellable(&prompt, &self.cancel).await {
+                    Ok(completion) => {
+                        usage.add(&completion.usage);
+                        text = completion.text.trim().to_string();
+                        if !crate::limit::exceeds(&text, max_chars) {
+                            return Ok((text, true, usage));
+                        }
+                        log::info!(
+                            "Shortened output still over {} chars (attempt {}/{})",
+                            max_chars,
+                            attempt,
+                            crate::limit::SHORTEN_ATTEMPTS
+                        );
+                    }
+                    Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+                    Err(e) => {
+                        log::warn!("Failed to shorten output: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok((crate::limit::truncate(&text, max_chars), true, usage))
     }
 
     /// Get API key for an LLM provider from secure storage
     pub fn get_api_key(&self, provider: &LlmProviderType) -> Result<Option<String>> {
-        let service = "whispertray";
         let key_name = match provider {
             LlmProviderType::OpenAI => "openai_api_key",
             LlmProviderType::Anthropic => "anthropic_api_key",
@@ -374,89 +2377,172 @@ impl AppState {
             LlmProviderType::Custom(_) => return Ok(None),
         };
 
-        match keyring::Entry::new(service, key_name) {
-            Ok(entry) => match entry.get_password() {
-                Ok(password) => Ok(Some(password)),
-                Err(keyring::Error::NoEntry) => Ok(None),
-                Err(e) => Err(AppError::Keyring(format!("Failed to get API key: {}", e))),
-            },
-            Err(e) => Err(AppError::Keyring(format!(
-                "Failed to access keyring: {}",
-                e
-            ))),
-        }
+        crate::secrets::get(key_name)
     }
 
     /// Get API key for an STT provider from secure storage
     pub fn get_stt_api_key(&self, provider: &SttProviderType) -> Result<Option<String>> {
-        let service = "whispertray";
         let key_name = match provider {
             SttProviderType::OpenAI => "openai_api_key", // Reuse same key as LLM
+            SttProviderType::Groq => "groq_api_key",
             SttProviderType::Deepgram => "deepgram_api_key",
             SttProviderType::WhisperCpp => return Ok(None),    // Local, no key needed
             SttProviderType::WhisperServer => return Ok(None), // Self-hosted, typically no auth
+            SttProviderType::Wyoming => return Ok(None),       // LAN server, no auth
             SttProviderType::Custom(_) => return Ok(None),
         };
 
-        match keyring::Entry::new(service, key_name) {
-            Ok(entry) => match entry.get_password() {
-                Ok(password) => Ok(Some(password)),
-                Err(keyring::Error::NoEntry) => Ok(None),
-                Err(e) => Err(AppError::Keyring(format!("Failed to get STT API key: {}", e))),
-            },
-            Err(e) => Err(AppError::Keyring(format!(
-                "Failed to access keyring: {}",
-                e
-            ))),
-        }
+        crate::secrets::get(key_name)
     }
 
     /// Save an API key to secure storage
     pub fn save_api_key(&self, provider: &str, key: &str) -> Result<()> {
-        let service = "whispertray";
-        let key_name = format!("{}_api_key", provider.to_lowercase());
-
-        let entry = keyring::Entry::new(service, &key_name)
-            .map_err(|e| AppError::Keyring(format!("Failed to access keyring: {}", e)))?;
-
-        entry
-            .set_password(key)
-            .map_err(|e| AppError::Keyring(format!("Failed to save API key: {}", e)))?;
-
-        Ok(())
+        self.ensure_writable("saving API keys")?;
+        crate::secrets::set_api_key(provider, key)
     }
 
     /// Delete an API key from secure storage
     pub fn delete_api_key(&self, provider: &str) -> Result<()> {
-        let service = "whispertray";
-        let key_name = format!("{}_api_key", provider.to_lowercase());
+        self.ensure_writable("deleting API keys")?;
+        crate::secrets::delete_api_key(provider)
+    }
 
-        let entry = keyring::Entry::new(service, &key_name)
-            .map_err(|e| AppError::Keyring(format!("Failed to access keyring: {}", e)))?;
+    /// Check if an API key exists
+    pub fn has_api_key(&self, provider: &str) -> bool {
+        crate::secrets::exists(&crate::secrets::api_key_name(provider))
+    }
 
-        match entry.delete_password() {
-            Ok(_) => Ok(()),
-            Err(keyring::Error::NoEntry) => Ok(()), // Already deleted
-            Err(e) => Err(AppError::Keyring(format!("Failed to delete API key: {}", e))),
+    /// Drop a bookmark in the current recording
+    pub fn add_bookmark(&self) -> Result<u64> {
+        if !self.is_recording() {
+            return Err(AppError::NoRecordingInProgress);
         }
+        let offset_ms = self.recording_handle.add_bookmark();
+        log::info!("Bookmark added at {} ms", offset_ms);
+        Ok(offset_ms)
     }
 
-    /// Check if an API key exists
-    pub fn has_api_key(&self, provider: &str) -> bool {
-        let service = "whispertray";
-        let key_name = format!("{}_api_key", provider.to_lowercase());
+    /// Pause or resume the current recording, keeping it one dictation
+    pub fn set_recording_paused(&self, paused: bool) -> Result<()> {
+        if paused {
+            self.recording_handle.pause_recording()?;
+        } else {
+            self.recording_handle.resume_recording()?;
+        }
+        log::info!("Recording {}", if paused { "paused" } else { "resumed" });
+        let _ = self.app_handle.emit("recording-paused", paused);
+        Ok(())
+    }
+}
 
-        keyring::Entry::new(service, &key_name)
-            .and_then(|entry| entry.get_password())
-            .is_ok()
+/// What a `Retranscriber` needs from the state, copied out so the model is
+/// loaded (or downloaded) without holding the state lock
+pub struct RetranscriberSettings {
+    selection: stt::SttSelection,
+    api_key: Option<String>,
+    server_url: Option<String>,
+    language: String,
+    /// Replacements, unless the mode is verbatim
+    dictionary: Option<crate::dictionary::Dictionary>,
+    punctuation: crate::punctuation::PunctuationRestore,
+    punctuation_command: Option<String>,
+}
+
+impl RetranscriberSettings {
+    /// Load the STT model
+    pub async fn load(self) -> Result<Retranscriber> {
+        let provider = stt::create_stt_provider(
+            &self.selection.provider,
+            &self.selection.model,
+            self.api_key,
+            self.server_url,
+        )
+        .await?;
+        Ok(Retranscriber {
+            provider,
+            language: self.language,
+            dictionary: self.dictionary,
+            punctuation: self.punctuation,
+            punctuation_command: self.punctuation_command,
+        })
     }
+}
 
-    /// Cancel current recording
-    pub fn cancel_recording(&mut self) {
-        self.recording_handle.set_recording(false);
-        self.status = RecordingStatus::Ready;
+/// An STT model and transcript settings for transcribing kept recordings
+/// again, used without holding the state lock
+pub struct Retranscriber {
+    provider: Box<dyn stt::SttProvider>,
+    language: String,
+    dictionary: Option<crate::dictionary::Dictionary>,
+    punctuation: crate::punctuation::PunctuationRestore,
+    punctuation_command: Option<String>,
+}
+
+impl Retranscriber {
+    pub async fn transcribe(&self, samples: &[f32], cancel: &CancelToken) -> Result<stt::ScoredTranscript> {
+        let mut scored = self
+            .provider
+            .transcribe_scored(samples, Some(&self.language), cancel)
+            .await?;
+        if let Some(dictionary) = &self.dictionary {
+            scored.text = dictionary.apply(&scored.text);
+        }
+        scored.text = crate::punctuation::restore(
+            &scored.text,
+            &self.language,
+            self.punctuation,
+            self.punctuation_command.as_deref(),
+        )
+        .await;
+        Ok(scored)
     }
 }
 
+/// History error of an output command that failed, its input kept instead
+pub(crate) fn output_command_error(error: &std::io::Error) -> ErrorRecord {
+    let message = format!("Output command failed: {}", error);
+    ErrorRecord::new(ErrorStage::Output, None, &AppError::Config(message))
+}
+
 /// Shared state type for Tauri
 pub type SharedState = Arc<tokio::sync::Mutex<AppState>>;
+
+/// Stop recording and process it, returning the output
+///
+/// The state is held throughout, except while the multi-take picker waits
+/// for the user, which can take a minute. No dictation starts meanwhile.
+pub async fn stop_recording(state: &SharedState) -> Result<String> {
+    let mut guard = state.lock().await;
+    let (mut dictation, audio) = guard.stop_capture()?;
+    let (result, retries) =
+        crate::retry::counting(guard.process_recording(&dictation.id, audio, &mut dictation.trace)).await;
+    dictation.retries += retries;
+    let mut processed = match result {
+        Ok(processed) => processed,
+        Err(e) => return guard.end_stop(dictation, Err(e)),
+    };
+
+    if let Some(takes) = processed.takes.take() {
+        let chosen = match &takes.request {
+            Some(request) => {
+                let (picker, handle, cancel) = (guard.picker.clone(), guard.app_handle.clone(), guard.cancel.clone());
+                drop(guard);
+                let span = dictation.trace.begin("pick");
+                let chosen = picker.choose(&handle, request.clone(), &cancel).await;
+                dictation.trace.end(span);
+                guard = state.lock().await;
+                match chosen {
+                    Ok(chosen) => chosen,
+                    Err(e) => return guard.end_stop(dictation, Err(e)),
+                }
+            }
+            None => None,
+        };
+        guard.use_take(&dictation.id, &mut processed, takes, chosen);
+    }
+
+    let (result, retries) =
+        crate::retry::counting(guard.finish_processing(&dictation.id, processed, &mut dictation.trace)).await;
+    dictation.retries += retries;
+    guard.end_stop(dictation, result)
+}
@@ -0,0 +1,290 @@
+//! Dictation style profile learned from history
+//!
+//! When `learn_style` is on, a background job reads the outputs the user
+//! corrected by hand, which show how they actually want to sound, and
+//! derives their typical sentence length, vocabulary and formality. A
+//! compact description of the profile is added to every LLM prompt so
+//! rewrites match it. The profile is stored in the history database and
+//! cleared when learning is turned off.
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::state::SharedState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Time between analyses
+const ANALYSIS_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Delay before the first analysis, so setup can finish
+const ANALYSIS_STARTUP_DELAY: Duration = Duration::from_secs(90);
+
+/// Corrected outputs needed before a profile is derived
+pub const MIN_SAMPLES: usize = 5;
+
+/// Most recent corrected outputs analyzed
+const MAX_SAMPLES: usize = 500;
+
+/// Distinctive words listed in the prompt
+const MAX_TERMS: usize = 8;
+
+/// Common English words never listed as distinctive
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "also", "because", "been", "before", "being", "could", "does", "doing", "down",
+    "each", "even", "from", "have", "here", "into", "just", "know", "like", "make", "more", "much", "need",
+    "only", "other", "over", "really", "should", "some", "still", "such", "than", "that", "their", "them",
+    "then", "there", "these", "they", "thing", "think", "this", "those", "through", "very", "want", "well",
+    "were", "what", "when", "where", "which", "while", "will", "with", "would", "your", "you're",
+];
+
+/// English words that mark casual writing
+const CASUAL_MARKERS: &[&str] = &[
+    "hey", "hi", "yeah", "yep", "nope", "gonna", "wanna", "gotta", "ok", "okay", "cool", "awesome", "thanks",
+    "lol", "btw", "cheers", "stuff", "kinda",
+];
+
+/// English words that mark formal writing
+const FORMAL_MARKERS: &[&str] = &[
+    "regards", "sincerely", "dear", "therefore", "however", "furthermore", "moreover", "accordingly", "kindly",
+    "regarding", "hereby", "consequently", "nevertheless", "shall",
+];
+
+/// How formal the user's writing is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Formality {
+    Casual,
+    Neutral,
+    Formal,
+}
+
+/// Style derived from the user's corrected outputs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StyleProfile {
+    /// Number of corrected outputs analyzed
+    pub samples: usize,
+    pub avg_sentence_words: f32,
+    /// Mean letters per word, a rough measure of vocabulary
+    pub avg_word_length: f32,
+    /// Words the user uses across many dictations, most used first
+    pub terms: Vec<String>,
+    /// None outside English, where the markers do not apply
+    pub formality: Option<Formality>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn word_key(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+        .to_lowercase()
+}
+
+/// Derive a profile from outputs, None with fewer than `MIN_SAMPLES`
+pub fn analyze(outputs: &[String], language: &str) -> Option<StyleProfile> {
+    let outputs: Vec<&str> = outputs.iter().map(|o| o.trim()).filter(|o| !o.is_empty()).collect();
+    if outputs.len() < MIN_SAMPLES {
+        return None;
+    }
+    let english = language.to_lowercase().starts_with("en");
+
+    let (mut words, mut letters, mut sentences) = (0usize, 0usize, 0usize);
+    let (mut casual, mut formal) = (0usize, 0usize);
+    // Counted once per output, so one long dictation does not dominate
+    let mut spread: HashMap<String, usize> = HashMap::new();
+
+    for output in &outputs {
+        let mut seen = Vec::new();
+        let mut open_sentence = false;
+        for word in output.split_whitespace() {
+            let key = word_key(word);
+            words += 1;
+            letters += key.chars().filter(|c| c.is_alphanumeric()).count();
+            open_sentence = true;
+            if word.trim_end_matches(['"', '\'', ')']).ends_with(['.', '!', '?']) {
+                sentences += 1;
+                open_sentence = false;
+            }
+
+            if key.contains('\'') || word.ends_with('!') || CASUAL_MARKERS.contains(&key.as_str()) {
+                casual += 1;
+            }
+            if FORMAL_MARKERS.contains(&key.as_str()) {
+                formal += 1;
+            }
+            let distinctive = key.chars().count() >= 4
+                && key.chars().all(char::is_alphabetic)
+                && !STOPWORDS.contains(&key.as_str());
+            if distinctive && !seen.contains(&key) {
+                seen.push(key);
+            }
+        }
+        if open_sentence {
+            sentences += 1;
+        }
+        for key in seen {
+            *spread.entry(key).or_default() += 1;
+        }
+    }
+
+    let mut terms: Vec<(String, usize)> = spread
+        .into_iter()
+        .filter(|(_, count)| *count >= 2 && *count * 5 >= outputs.len())
+        .collect();
+    terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    // Markers per 100 words
+    let per_100 = |count: usize| count as f32 * 100.0 / words.max(1) as f32;
+    let formality = english.then(|| {
+        let (casual, formal) = (per_100(casual), per_100(formal));
+        if casual - formal >= 2.0 {
+            Formality::Casual
+        } else if formal > casual || casual < 0.5 {
+            Formality::Formal
+        } else {
+            Formality::Neutral
+        }
+    });
+
+    Some(StyleProfile {
+        samples: outputs.len(),
+        avg_sentence_words: words as f32 / sentences.max(1) as f32,
+        avg_word_length: letters as f32 / words.max(1) as f32,
+        terms: terms.into_iter().take(MAX_TERMS).map(|(term, _)| term).collect(),
+        formality,
+        updated_at: Utc::now(),
+    })
+}
+
+impl StyleProfile {
+    /// Instruction added to LLM prompts
+    pub fn describe(&self) -> String {
+        let length = match self.avg_sentence_words {
+            n if n < 10.0 => "short",
+            n if n < 18.0 => "medium-length",
+            _ => "long",
+        };
+        let vocabulary = match self.avg_word_length {
+            n if n < 4.3 => "plain",
+            n if n < 5.0 => "everyday",
+            _ => "elaborate",
+        };
+        let mut traits = vec![
+            format!(
+                "{} sentences (about {} words)",
+                length,
+                self.avg_sentence_words.round() as u32
+            ),
+            format!("{} vocabulary", vocabulary),
+        ];
+        match self.formality {
+            Some(Formality::Casual) => traits.push("a casual tone, contractions welcome".to_string()),
+            Some(Formality::Neutral) => traits.push("a neutral tone".to_string()),
+            Some(Formality::Formal) => traits.push("a formal tone without contractions or slang".to_string()),
+            None => {}
+        }
+
+        let mut description = format!("Match the user's own writing style: {}.", traits.join(", "));
+        if !self.terms.is_empty() {
+            description.push_str(&format!(" Words they often use: {}.", self.terms.join(", ")));
+        }
+        description
+    }
+
+    /// Add the style description to a rendered prompt
+    pub fn apply_to_prompt(&self, prompt: String) -> String {
+        format!("{}\n\n{}", prompt, self.describe())
+    }
+}
+
+/// Re-derive the profile from history and store it
+///
+/// Returns the new profile, or None when there are too few corrections yet.
+pub fn refresh(db: &Database, language: &str) -> Result<Option<StyleProfile>> {
+    let outputs = db.get_corrected_outputs(MAX_SAMPLES)?;
+    let profile = analyze(&outputs, language);
+    db.set_style_profile(profile.as_ref())?;
+    Ok(profile)
+}
+
+/// Start the background job that keeps the profile up to date
+pub fn spawn_analysis_job(handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(ANALYSIS_STARTUP_DELAY).await;
+
+        loop {
+            if let Some(state) = handle.try_state::<SharedState>() {
+                crate::priority::background(update(state.inner())).await;
+            }
+
+            tokio::time::sleep(ANALYSIS_INTERVAL).await;
+        }
+    });
+}
+
+async fn update(state: &SharedState) {
+    crate::priority::yield_to_interactive().await;
+    let mut state = state.lock().await;
+    if !state.settings.learn_style {
+        return;
+    }
+    let Some(db) = state.database.clone() else {
+        return;
+    };
+    match refresh(&db, &state.settings.language) {
+        Ok(profile) => {
+            if let Some(profile) = &profile {
+                log::info!("Style profile updated from {} corrected dictations", profile.samples);
+            }
+            state.style = profile;
+        }
+        Err(e) => log::warn!("Style analysis failed: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_analyze_casual_style() {
+        let casual = outputs(&[
+            "Hey, the deploy is done. Thanks!",
+            "Can't make standup today. I'll sync later.",
+            "Deploy looks good. Ping me if it breaks.",
+            "Yeah, that's fine. Ship it.",
+            "Standup moved to ten. Don't be late!",
+        ]);
+        assert!(analyze(&casual[..4], "en").is_none());
+
+        let profile = analyze(&casual, "en").unwrap();
+        assert_eq!(profile.samples, 5);
+        assert_eq!(profile.formality, Some(Formality::Casual));
+        assert_eq!(profile.terms, vec!["deploy", "standup"]);
+        assert!(profile.avg_sentence_words < 6.0);
+        assert!(profile.describe().starts_with("Match the user's own writing style: short sentences"));
+    }
+
+    #[test]
+    fn test_analyze_formal_style() {
+        let formal = outputs(&[
+            "Dear team, the quarterly report is attached for your review and approval.",
+            "However, the budget figures require further consultation with finance.",
+            "Kindly confirm receipt of the revised agreement by Friday afternoon.",
+            "The committee shall reconvene regarding the proposal next Tuesday morning.",
+            "Furthermore, the quarterly targets have been adjusted accordingly.",
+        ]);
+        let profile = analyze(&formal, "en").unwrap();
+        assert_eq!(profile.formality, Some(Formality::Formal));
+        assert_eq!(profile.terms, vec!["quarterly"]);
+        assert!(profile.describe().contains("a formal tone"));
+
+        // Formality markers are English-only
+        assert_eq!(analyze(&formal, "de").unwrap().formality, None);
+    }
+}
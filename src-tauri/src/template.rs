@@ -0,0 +1,436 @@
+//! Prompt templates
+//!
+//! Templates substitute `{{name}}` placeholders and keep `{{#if name}}...{{/if}}`
+//! blocks only when the variable is non-empty. Rendering is a single pass, so
+//! text inserted from a variable is never expanded again; `\{{` renders a
+//! literal `{{`. Unknown placeholders are left as written.
+
+use crate::window_info::WindowInfo;
+use chrono::{Local, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// Variables a template can reference
+pub const VARIABLES: &[&str] = &[
+    "transcript",
+    "clipboard",
+    "selection",
+    "date",
+    "app_name",
+    "language",
+    "context",
+    "time",
+    "time_of_day",
+    "weekday",
+    "user_name",
+    "role",
+    "tone",
+    "signature",
+];
+
+/// Values for template variables
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateVars {
+    pub transcript: String,
+    /// Clipboard text captured when the recording started
+    pub clipboard: Option<String>,
+    /// Primary selection captured when the recording started
+    pub selection: Option<String>,
+    /// Today's date, `YYYY-MM-DD`
+    pub date: String,
+    /// Application focused when the recording started
+    pub app_name: Option<String>,
+    pub language: String,
+    /// Text from the mode's context source (clipboard or selection)
+    pub context: Option<String>,
+    /// Local time, `HH:MM`
+    pub time: String,
+    /// "morning", "afternoon", "evening" or "night"
+    pub time_of_day: String,
+    /// Day of the week, e.g. "Monday"
+    pub weekday: String,
+    pub user_name: Option<String>,
+    pub role: Option<String>,
+    pub tone: Option<String>,
+    pub signature: Option<String>,
+}
+
+/// Static details about the user, available to every prompt
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptProfile {
+    pub user_name: Option<String>,
+    /// Job title or role, e.g. "engineering manager"
+    pub role: Option<String>,
+    /// Preferred tone, e.g. "friendly but brief"
+    pub tone: Option<String>,
+    /// Sign-off for messages and emails
+    pub signature: Option<String>,
+}
+
+/// Part of the day for a local hour
+pub fn time_of_day(hour: u32) -> &'static str {
+    match hour {
+        5..=11 => "morning",
+        12..=16 => "afternoon",
+        17..=21 => "evening",
+        _ => "night",
+    }
+}
+
+impl TemplateVars {
+    /// Variables for a transcript, with today's date and the captured context
+    pub fn new(transcript: &str, context: &PromptContext, language: &str) -> Self {
+        let now = Local::now();
+        Self {
+            transcript: transcript.to_string(),
+            clipboard: context.clipboard.clone(),
+            selection: context.selection.clone(),
+            date: now.format("%Y-%m-%d").to_string(),
+            app_name: context.app_name.clone(),
+            language: language.to_string(),
+            context: context.text().map(str::to_string),
+            time: now.format("%H:%M").to_string(),
+            time_of_day: time_of_day(now.hour()).to_string(),
+            weekday: now.format("%A").to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add the user's static details
+    pub fn with_profile(mut self, profile: &PromptProfile) -> Self {
+        let set = |value: &Option<String>| value.clone().filter(|v| !v.trim().is_empty());
+        self.user_name = set(&profile.user_name);
+        self.role = set(&profile.role);
+        self.tone = set(&profile.tone);
+        self.signature = set(&profile.signature);
+        self
+    }
+
+    /// Value of a variable, None when unknown or unset
+    pub fn get(&self, name: &str) -> Option<&str> {
+        match name {
+            "transcript" => Some(&self.transcript),
+            "clipboard" => self.clipboard.as_deref(),
+            "context" => self.context.as_deref(),
+            "selection" => self.selection.as_deref(),
+            "date" => Some(&self.date),
+            "app_name" => self.app_name.as_deref(),
+            "language" => Some(&self.language),
+            "time" => Some(&self.time),
+            "time_of_day" => Some(&self.time_of_day),
+            "weekday" => Some(&self.weekday),
+            "user_name" => self.user_name.as_deref(),
+            "role" => self.role.as_deref(),
+            "tone" => self.tone.as_deref(),
+            "signature" => self.signature.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Desktop text a mode reads when recording starts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextSource {
+    /// The clipboard when context awareness is enabled in settings
+    #[default]
+    Auto,
+    Clipboard,
+    /// The X11/Wayland primary selection (highlighted text)
+    Selection,
+    /// Never read desktop text
+    None,
+}
+
+impl ContextSource {
+    /// Resolve `Auto` against the global context awareness setting
+    pub fn resolve(self, context_awareness: bool) -> Self {
+        match self {
+            ContextSource::Auto if context_awareness => ContextSource::Clipboard,
+            ContextSource::Auto => ContextSource::None,
+            source => source,
+        }
+    }
+}
+
+/// Desktop context captured when a recording starts
+#[derive(Debug, Clone, Default)]
+pub struct PromptContext {
+    pub clipboard: Option<String>,
+    pub selection: Option<String>,
+    pub app_name: Option<String>,
+    /// Focused window, stored with the dictation when `remember_window` is on
+    pub window: Option<WindowInfo>,
+    /// Browser tab dictated in, when reported by the companion extension
+    pub url: Option<String>,
+    /// Source behind the `context` variable
+    pub source: ContextSource,
+}
+
+impl PromptContext {
+    /// Capture the context a template needs
+    ///
+    /// `source` (already resolved) is always read; with any source other than
+    /// `None` the clipboard and selection are also read when the template
+    /// references them. The focused window is looked up with `window` or when
+    /// the template uses `app_name`.
+    pub fn capture(template: &str, source: ContextSource, window: bool) -> Self {
+        let uses = |name: &str| uses_variable(template, name);
+        let allowed = source != ContextSource::None;
+        let window = (window || uses("app_name"))
+            .then(crate::window_info::active_window)
+            .flatten();
+        Self {
+            clipboard: (source == ContextSource::Clipboard || (allowed && uses("clipboard")))
+                .then(|| crate::paste::get_clipboard_text().ok())
+                .flatten(),
+            selection: (source == ContextSource::Selection || (allowed && uses("selection")))
+                .then(crate::paste::get_primary_selection)
+                .flatten(),
+            app_name: window.as_ref().and_then(|w| w.class.clone()),
+            url: crate::browser_tab::current(window.as_ref()),
+            window,
+            source,
+        }
+    }
+
+    /// Text from the context source, if any was captured
+    pub fn text(&self) -> Option<&str> {
+        match self.source {
+            ContextSource::Clipboard => self.clipboard.as_deref(),
+            ContextSource::Selection => self.selection.as_deref(),
+            ContextSource::Auto | ContextSource::None => None,
+        }
+        .filter(|text| !text.trim().is_empty())
+    }
+}
+
+/// Block appended to templates that do not place the context themselves
+const CONTEXT_BLOCK: &str = "\n\n{{#if context}}\nContext (for reference only):\n{{context}}\n{{/if}}";
+
+/// Make sure a template includes the captured context
+///
+/// Templates that reference none of `context`, `clipboard` or `selection`
+/// get a context block appended, so choosing a source is enough to use it.
+pub fn with_context(template: &str) -> String {
+    if ["context", "clipboard", "selection"]
+        .iter()
+        .any(|name| uses_variable(template, name))
+    {
+        template.to_string()
+    } else {
+        format!("{}{}", template, CONTEXT_BLOCK)
+    }
+}
+
+/// Whether a template references a variable, as a placeholder or condition
+pub fn uses_variable(template: &str, name: &str) -> bool {
+    template.contains(&format!("{{{{{}}}}}", name)) || template.contains(&format!("{{{{#if {}}}}}", name))
+}
+
+/// Render a template
+pub fn render(template: &str, vars: &TemplateVars) -> String {
+    let mut out = String::with_capacity(template.len());
+    render_into(template, vars, &mut out);
+    out.trim().to_string()
+}
+
+fn render_into(template: &str, vars: &TemplateVars, out: &mut String) {
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        // An escaped opening renders literally
+        if rest[..start].ends_with('\\') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        out.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let Some(end) = tag.find("}}") else {
+            out.push_str(tag);
+            return;
+        };
+        let name = tag[2..end].trim();
+        let after = &tag[end + 2..];
+
+        if let Some(condition) = name.strip_prefix("#if ") {
+            // Blocks do not nest; the first {{/if}} closes the block
+            let (body, remainder) = after.split_once("{{/if}}").unwrap_or((after, ""));
+            if vars.get(condition.trim()).is_some_and(|v| !v.trim().is_empty()) {
+                render_into(body, vars, out);
+            }
+            rest = remainder;
+        } else {
+            match vars.get(name) {
+                Some(value) => out.push_str(value),
+                None if VARIABLES.contains(&name) => {}
+                None => out.push_str(&tag[..end + 2]),
+            }
+            rest = after;
+        }
+    }
+
+    out.push_str(rest);
+}
+
+/// Placeholders in a template that are not known variables
+pub fn unknown_variables(template: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let escaped = rest[..start].ends_with('\\');
+        let tag = &rest[start + 2..];
+        let Some(end) = tag.find("}}") else {
+            break;
+        };
+        let name = tag[..end].trim();
+        let name = name.strip_prefix("#if ").map(str::trim).unwrap_or(name);
+
+        if !escaped && name != "/if" && !VARIABLES.contains(&name) && !unknown.iter().any(|u| u == name) {
+            unknown.push(name.to_string());
+        }
+        rest = &tag[end + 2..];
+    }
+
+    unknown
+}
+
+/// Result of a dry-run render, for previews
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplatePreview {
+    pub rendered: String,
+    /// Placeholders that will be left as written
+    pub unknown_variables: Vec<String>,
+}
+
+/// Render a template with sample values standing in for unset variables
+pub fn preview(template: &str, vars: Option<TemplateVars>) -> TemplatePreview {
+    let mut vars = vars.unwrap_or_default();
+    if vars.transcript.is_empty() {
+        vars.transcript = "This is a sample transcript.".to_string();
+    }
+    if vars.date.is_empty() {
+        vars.date = Local::now().format("%Y-%m-%d").to_string();
+    }
+    if vars.language.is_empty() {
+        vars.language = "en".to_string();
+    }
+    if vars.time.is_empty() {
+        let now = Local::now();
+        vars.time = now.format("%H:%M").to_string();
+        vars.time_of_day = time_of_day(now.hour()).to_string();
+        vars.weekday = now.format("%A").to_string();
+    }
+    vars.clipboard.get_or_insert_with(|| "(clipboard text)".to_string());
+    vars.selection.get_or_insert_with(|| "(selected text)".to_string());
+    vars.app_name.get_or_insert_with(|| "(focused app)".to_string());
+    vars.context.get_or_insert_with(|| "(context text)".to_string());
+    vars.user_name.get_or_insert_with(|| "(your name)".to_string());
+    vars.role.get_or_insert_with(|| "(your role)".to_string());
+    vars.tone.get_or_insert_with(|| "(preferred tone)".to_string());
+    vars.signature.get_or_insert_with(|| "(your signature)".to_string());
+
+    TemplatePreview {
+        rendered: render(template, &vars),
+        unknown_variables: unknown_variables(template),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> TemplateVars {
+        TemplateVars {
+            transcript: "call {{app_name}} later".to_string(),
+            clipboard: None,
+            selection: Some("selected".to_string()),
+            date: "2025-03-01".to_string(),
+            app_name: Some("Firefox".to_string()),
+            language: "en".to_string(),
+            context: None,
+            time: "09:30".to_string(),
+            time_of_day: "morning".to_string(),
+            weekday: "Saturday".to_string(),
+            user_name: None,
+            role: None,
+            tone: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_render_substitutes_once() {
+        let result = render("{{date}} in {{app_name}}: {{transcript}}", &vars());
+        assert_eq!(result, "2025-03-01 in Firefox: call {{app_name}} later");
+    }
+
+    #[test]
+    fn test_render_conditionals() {
+        let template = "{{#if clipboard}}Clip: {{clipboard}}\n{{/if}}{{#if selection}}Sel: {{selection}}{{/if}}";
+        assert_eq!(render(template, &vars()), "Sel: selected");
+    }
+
+    #[test]
+    fn test_render_escape_and_unknown() {
+        assert_eq!(render(r"\{{transcript}} {{other}} {{clipboard}}!", &vars()), "{{transcript}} {{other}} !");
+        assert_eq!(unknown_variables(r"{{other}} {{#if nope}}x{{/if}} \{{skip}} {{date}}"), vec!["other", "nope"]);
+    }
+
+    #[test]
+    fn test_time_and_profile() {
+        assert_eq!(time_of_day(8), "morning");
+        assert_eq!(time_of_day(14), "afternoon");
+        assert_eq!(time_of_day(19), "evening");
+        assert_eq!(time_of_day(2), "night");
+
+        let profile = PromptProfile {
+            signature: Some("Best,\nSam".to_string()),
+            tone: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let vars = vars().with_profile(&profile);
+        let template = "Good {{time_of_day}} ({{weekday}}){{#if tone}}, {{tone}}{{/if}}\n{{signature}}";
+        assert_eq!(render(template, &vars), "Good morning (Saturday)\nBest,\nSam");
+    }
+
+    #[test]
+    fn test_context_source() {
+        assert_eq!(ContextSource::Auto.resolve(true), ContextSource::Clipboard);
+        assert_eq!(ContextSource::Auto.resolve(false), ContextSource::None);
+        assert_eq!(ContextSource::Selection.resolve(false), ContextSource::Selection);
+
+        let context = PromptContext {
+            clipboard: Some("clip".to_string()),
+            selection: Some("sel".to_string()),
+            app_name: None,
+            window: None,
+            url: None,
+            source: ContextSource::Selection,
+        };
+        assert_eq!(context.text(), Some("sel"));
+        assert_eq!(TemplateVars::new("hi", &context, "en").get("context"), Some("sel"));
+    }
+
+    #[test]
+    fn test_with_context() {
+        assert_eq!(with_context("{{selection}} {{transcript}}"), "{{selection}} {{transcript}}");
+
+        let mut vars = vars();
+        vars.context = Some("the paragraph".to_string());
+        let rendered = render(&with_context("Rewrite: {{transcript}}"), &vars);
+        assert!(rendered.ends_with("Context (for reference only):\nthe paragraph"));
+    }
+
+    #[test]
+    fn test_uses_variable() {
+        assert!(uses_variable("{{#if selection}}{{/if}}", "selection"));
+        assert!(uses_variable("in {{app_name}}", "app_name"));
+        assert!(!uses_variable("{{transcript}}", "app_name"));
+    }
+}
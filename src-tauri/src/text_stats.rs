@@ -0,0 +1,50 @@
+//! Word count and reading time for dictation output
+
+use serde::{Deserialize, Serialize};
+
+/// Average silent reading speed used for the reading-time estimate
+const READING_WORDS_PER_MINUTE: u32 = 238;
+
+/// Size annotations for a piece of output text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextStats {
+    pub word_count: u32,
+    /// Characters, not bytes
+    pub char_count: u32,
+    /// Estimated reading time, rounded up to whole seconds
+    pub reading_time_secs: u32,
+}
+
+impl TextStats {
+    /// Compute stats for `text`
+    pub fn of(text: &str) -> Self {
+        let word_count = text.split_whitespace().count() as u32;
+        Self {
+            word_count,
+            char_count: text.chars().count() as u32,
+            reading_time_secs: (word_count * 60).div_ceil(READING_WORDS_PER_MINUTE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_stats() {
+        let stats = TextStats::of("Héllo  there,\nworld!");
+        assert_eq!(stats.word_count, 3);
+        assert_eq!(stats.char_count, 20);
+        assert_eq!(stats.reading_time_secs, 1);
+
+        assert_eq!(TextStats::of(""), TextStats::default());
+    }
+
+    #[test]
+    fn test_reading_time_scales_with_words() {
+        let text = vec!["word"; 476].join(" ");
+        assert_eq!(TextStats::of(&text).reading_time_secs, 120);
+    }
+}
@@ -1,20 +1,32 @@
 //! System tray management
 
 use crate::error::Result;
+use crate::health::HealthStatus;
 use crate::state::{AppState, RecordingStatus};
 use log::info;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tauri::menu::{MenuBuilder, MenuItemBuilder, SubmenuBuilder};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri::{image::Image, AppHandle, Emitter, Manager};
 
 const TRAY_ID: &str = "main-tray";
 
+/// Whether incognito is on, so every tooltip says so
+static INCOGNITO: AtomicBool = AtomicBool::new(false);
+
 /// Set up the system tray
 pub fn setup_tray(app: &tauri::App) -> Result<()> {
     info!("Setting up system tray...");
+    create_tray(app.handle())
+}
 
-    let handle = app.handle();
+/// Whether the tray icon currently exists
+pub fn tray_exists(handle: &AppHandle) -> bool {
+    handle.tray_by_id(TRAY_ID).is_some()
+}
 
+/// Create the tray icon with its initial menu
+pub fn create_tray(handle: &AppHandle) -> Result<()> {
     // Build initial menu
     let menu = build_tray_menu(handle)?;
 
@@ -40,7 +52,7 @@ pub fn setup_tray(app: &tauri::App) -> Result<()> {
                 handle_tray_click(tray.app_handle());
             }
         })
-        .build(app)?;
+        .build(handle)?;
 
     info!("System tray created");
     Ok(())
@@ -63,6 +75,8 @@ fn build_tray_menu(handle: &AppHandle) -> Result<tauri::menu::Menu<tauri::Wry>>
                 .build()?,
         )
         .separator()
+        .item(&MenuItemBuilder::with_id("incognito", "Incognito").build(handle)?)
+        .separator()
         .item(&MenuItemBuilder::with_id("transcribe_file", "Transcribe File...").build(handle)?)
         .item(&MenuItemBuilder::with_id("history", "History...").build(handle)?)
         .item(&MenuItemBuilder::with_id("settings", "Settings...").build(handle)?)
@@ -109,12 +123,15 @@ pub async fn update_tray_menu(handle: &AppHandle, state: &AppState) -> Result<()
     devices_builder =
         devices_builder.item(&MenuItemBuilder::with_id("device_default", default_label).build(handle)?);
 
-    for device in devices {
-        let id = format!("device_{}", device.name.replace(' ', "_"));
+    // Monitor sources are selected via the capture source setting, not here
+    for device in devices.into_iter().filter(|d| !d.is_monitor) {
+        // The name itself goes in the id: PipeWire node names contain underscores
+        let id = format!("device_{}", device.name);
+        let name = device.label.as_deref().unwrap_or(&device.name);
         let label = if device.name == *current_device {
-            format!("✓ {}", device.name)
+            format!("✓ {}", name)
         } else {
-            device.name.clone()
+            name.to_string()
         };
         devices_builder = devices_builder.item(&MenuItemBuilder::with_id(&id, &label).build(handle)?);
     }
@@ -128,11 +145,18 @@ pub async fn update_tray_menu(handle: &AppHandle, state: &AppState) -> Result<()
         "Start Recording"
     };
 
+    let incognito_label = if state.incognito {
+        "✓ Incognito (nothing saved)"
+    } else {
+        "Incognito"
+    };
+
     let menu = MenuBuilder::new(handle)
         .item(&MenuItemBuilder::with_id("toggle_recording", recording_label).build(handle)?)
         .separator()
         .item(&modes_menu)
         .item(&devices_menu)
+        .item(&MenuItemBuilder::with_id("incognito", incognito_label).build(handle)?)
         .separator()
         .item(&MenuItemBuilder::with_id("transcribe_file", "Transcribe File...").build(handle)?)
         .item(&MenuItemBuilder::with_id("history", "History...").build(handle)?)
@@ -164,6 +188,31 @@ pub fn update_tray_icon(handle: &AppHandle, status: RecordingStatus) -> Result<(
             RecordingStatus::Ready => "WhisperTray - Ready (click to record)",
             RecordingStatus::Error => "WhisperTray - Error",
         };
+        if INCOGNITO.load(Ordering::SeqCst) {
+            tray.set_tooltip(Some(format!("{} [incognito: nothing saved]", tooltip)))?;
+        } else {
+            tray.set_tooltip(Some(tooltip))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Show in the tray tooltip whether incognito is on
+pub fn set_incognito(handle: &AppHandle, incognito: bool, status: RecordingStatus) -> Result<()> {
+    INCOGNITO.store(incognito, Ordering::SeqCst);
+    update_tray_icon(handle, status)
+}
+
+/// Show watchdog problems in the tray tooltip
+pub fn update_tray_health(handle: &AppHandle, health: &HealthStatus) -> Result<()> {
+    let problems = health.problems();
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(tray) = handle.tray_by_id(TRAY_ID) {
+        let tooltip = format!("WhisperTray - Problem: {}", problems.join(", "));
         tray.set_tooltip(Some(tooltip))?;
     }
 
@@ -221,10 +270,10 @@ fn handle_menu_event(handle: &AppHandle, id: &str) {
         "stop_recording" => {
             let handle = handle.clone();
             tauri::async_runtime::spawn(async move {
-                if let Some(state) = handle.try_state::<crate::state::SharedState>() {
-                    let mut state = state.lock().await;
-                    if state.is_recording() {
-                        match state.stop_recording().await {
+                if let Some(state_arc) = handle.try_state::<crate::state::SharedState>() {
+                    let recording = state_arc.lock().await.is_recording();
+                    if recording {
+                        match crate::state::stop_recording(state_arc.inner()).await {
                             Ok(output) => {
                                 info!("Recording stopped. Output: {} chars", output.len());
                                 let _ = handle.emit("recording-complete", &output);
@@ -235,6 +284,7 @@ fn handle_menu_event(handle: &AppHandle, id: &str) {
                             }
                         }
                         // Ensure UI immediately updates to match state (which is reset to Ready on error)
+                        let state = state_arc.lock().await;
                         let _ = update_tray_icon(&handle, state.status);
                         let _ = update_tray_menu(&handle, &state).await;
                     }
@@ -249,7 +299,7 @@ fn handle_menu_event(handle: &AppHandle, id: &str) {
                 handle
                     .dialog()
                     .file()
-                    .add_filter("Audio Files", &["wav", "mp3", "m4a", "ogg", "flac"])
+                    .add_filter("Audio Files", &["wav", "mp3", "m4a", "ogg", "opus", "flac"])
                     .pick_file(move |path| {
                         if let Some(path) = path {
                             info!("Selected file for transcription: {:?}", path);
@@ -258,6 +308,17 @@ fn handle_menu_event(handle: &AppHandle, id: &str) {
                     });
             });
         }
+        "incognito" => {
+            let handle = handle.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Some(state) = handle.try_state::<crate::state::SharedState>() {
+                    let mut state = state.lock().await;
+                    let incognito = !state.incognito;
+                    state.set_incognito(incognito);
+                    let _ = update_tray_menu(&handle, &state).await;
+                }
+            });
+        }
         "history" => {
             show_window(handle, "main");
             // Navigate to history view
@@ -294,12 +355,17 @@ fn handle_menu_event(handle: &AppHandle, id: &str) {
                 let device_name = if device == "default" {
                     String::new()
                 } else {
-                    device.replace('_', " ")
+                    device.to_string()
                 };
                 tauri::async_runtime::spawn(async move {
                     if let Some(state) = handle.try_state::<crate::state::SharedState>() {
                         let mut state = state.lock().await;
+                        if let Err(e) = state.ensure_writable("changing settings") {
+                            log::error!("{}", e);
+                            return;
+                        }
                         state.settings.input_device = device_name.clone();
+                        state.update_pre_roll();
                         if let Err(e) = state.save_settings() {
                             log::error!("Failed to save settings: {}", e);
                         } else {
@@ -317,12 +383,14 @@ fn handle_menu_event(handle: &AppHandle, id: &str) {
 fn handle_tray_click(handle: &AppHandle) {
     let handle = handle.clone();
     tauri::async_runtime::spawn(async move {
-        if let Some(state) = handle.try_state::<crate::state::SharedState>() {
-            let mut state = state.lock().await;
+        if let Some(state_arc) = handle.try_state::<crate::state::SharedState>() {
+            let mut state = state_arc.lock().await;
 
             if state.is_recording() {
                 // Stop recording
-                let result = state.stop_recording().await;
+                drop(state);
+                let result = crate::state::stop_recording(state_arc.inner()).await;
+                let state = state_arc.lock().await;
 
                 // Ensure UI immediately updates to match state (which is reset to Ready on error)
                 let _ = update_tray_icon(&handle, state.status);
@@ -0,0 +1,263 @@
+//! Locale-aware typography post-processing
+//!
+//! Converts straight quotes to the output language's quotation marks,
+//! applies the locale decimal separator, and inserts non-breaking spaces
+//! before French high punctuation.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Non-breaking space
+const NBSP: char = '\u{00A0}';
+/// Narrow non-breaking space (used before ; ! ? in French)
+const NNBSP: char = '\u{202F}';
+
+/// Decimal numbers: grouped with commas ("1,000.50") or dotted ("3.5", "1.2.3")
+static NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b\d{1,3}(?:,\d{3})+(?:\.\d+)?\b|\d+(?:\.\d+)+").unwrap());
+
+/// Quotation marks and number formatting for one locale
+struct LocaleStyle {
+    double_open: &'static str,
+    double_close: &'static str,
+    single_open: char,
+    single_close: char,
+    decimal_comma: bool,
+    /// Thousands separator used with a decimal comma
+    group_separator: char,
+    french_spacing: bool,
+}
+
+/// Look up the style for a language code ("en", "fr-CA", "de_DE", ...)
+fn locale_style(language: &str) -> LocaleStyle {
+    let lang = language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+
+    match lang.as_str() {
+        "fr" => LocaleStyle {
+            double_open: "«\u{00A0}",
+            double_close: "\u{00A0}»",
+            single_open: '‹',
+            single_close: '›',
+            decimal_comma: true,
+            group_separator: NNBSP,
+            french_spacing: true,
+        },
+        "de" | "cs" | "sk" => LocaleStyle {
+            double_open: "„",
+            double_close: "“",
+            single_open: '‚',
+            single_close: '‘',
+            decimal_comma: true,
+            group_separator: '.',
+            french_spacing: false,
+        },
+        "es" | "it" | "pt" | "ru" | "uk" => LocaleStyle {
+            double_open: "«",
+            double_close: "»",
+            single_open: '“',
+            single_close: '”',
+            decimal_comma: true,
+            group_separator: '.',
+            french_spacing: false,
+        },
+        "nl" | "pl" | "sv" | "da" | "nb" | "no" | "fi" => LocaleStyle {
+            double_open: "”",
+            double_close: "”",
+            single_open: '’',
+            single_close: '’',
+            decimal_comma: true,
+            group_separator: '.',
+            french_spacing: false,
+        },
+        _ => LocaleStyle {
+            double_open: "“",
+            double_close: "”",
+            single_open: '‘',
+            single_close: '’',
+            decimal_comma: false,
+            group_separator: ',',
+            french_spacing: false,
+        },
+    }
+}
+
+/// Apply typography rules for the given output language
+pub fn apply(text: &str, language: &str) -> String {
+    let style = locale_style(language);
+
+    let mut result = smart_quotes(text, &style);
+    if style.decimal_comma {
+        result = decimal_commas(&result, style.group_separator);
+    }
+    if style.french_spacing {
+        result = french_spacing(&result);
+    }
+    result
+}
+
+/// Whether a quote following `prev` opens a quotation
+fn is_opening_position(prev: Option<char>) -> bool {
+    match prev {
+        None => true,
+        Some(c) => c.is_whitespace() || "([{—–-".contains(c),
+    }
+}
+
+/// Replace straight quotes with locale quotation marks
+fn smart_quotes(text: &str, style: &LocaleStyle) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut prev: Option<char> = None;
+    let mut double_open = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let opening = !double_open && is_opening_position(prev);
+                if opening {
+                    result.push_str(style.double_open);
+                } else {
+                    result.push_str(style.double_close);
+                }
+                double_open = opening;
+            }
+            '\'' => {
+                let next_is_alnum = chars.peek().is_some_and(|n| n.is_alphanumeric());
+                let prev_is_alnum = prev.is_some_and(|p| p.is_alphanumeric());
+                let next_is_digit = chars.peek().is_some_and(|n| n.is_ascii_digit());
+                if (prev_is_alnum && next_is_alnum) || (!prev_is_alnum && next_is_digit) {
+                    // Apostrophe inside a word (don't, l'homme) or eliding digits ('90s)
+                    result.push('’');
+                } else if is_opening_position(prev) && next_is_alnum {
+                    result.push(style.single_open);
+                } else {
+                    result.push(style.single_close);
+                }
+            }
+            _ => result.push(c),
+        }
+        prev = Some(c);
+    }
+
+    result
+}
+
+/// Use a decimal comma for plain decimal numbers ("3.5" -> "3,5")
+///
+/// Numbers grouped with commas swap separators ("1,000.50" -> "1.000,50").
+/// Numbers with more than one dot (versions, IP addresses) are left alone.
+fn decimal_commas(text: &str, group_separator: char) -> String {
+    NUMBER
+        .replace_all(text, |caps: &regex::Captures| {
+            let m = &caps[0];
+            if m.contains(',') {
+                m.chars()
+                    .map(|c| match c {
+                        ',' => group_separator,
+                        '.' => ',',
+                        c => c,
+                    })
+                    .collect()
+            } else if m.matches('.').count() == 1 {
+                m.replace('.', ",")
+            } else {
+                m.to_string()
+            }
+        })
+        .to_string()
+}
+
+/// Insert non-breaking spaces before French high punctuation
+fn french_spacing(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+
+    for (i, &c) in chars.iter().enumerate() {
+        let nbsp = match c {
+            ';' | '!' | '?' => Some(NNBSP),
+            ':' => Some(NBSP),
+            _ => None,
+        };
+
+        if let Some(nbsp) = nbsp {
+            // Only treat it as punctuation when it ends a word (leave URLs and times alone)
+            let at_boundary = chars.get(i + 1).is_none_or(|n| n.is_whitespace());
+            let prev = if i > 0 { Some(chars[i - 1]) } else { None };
+            if at_boundary {
+                match prev {
+                    Some(' ') => {
+                        result.pop();
+                        result.push(nbsp);
+                    }
+                    Some(p) if p != NBSP && p != NNBSP && !"!?;:".contains(p) => {
+                        result.push(nbsp);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        result.push(c);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_smart_quotes() {
+        let result = apply(r#"She said "don't go" and 'left'."#, "en");
+        assert_eq!(result, "She said “don’t go” and ‘left’.");
+    }
+
+    #[test]
+    fn test_english_keeps_decimal_point() {
+        assert_eq!(apply("It costs 3.50 dollars", "en"), "It costs 3.50 dollars");
+    }
+
+    #[test]
+    fn test_german_quotes_and_decimals() {
+        let result = apply(r#"Er sagte "ja" zu 2.5 Prozent"#, "de-DE");
+        assert_eq!(result, "Er sagte „ja“ zu 2,5 Prozent");
+    }
+
+    #[test]
+    fn test_grouped_numbers_swap_separators() {
+        assert_eq!(apply("Es kostet 1,000.50 Euro", "de"), "Es kostet 1.000,50 Euro");
+        assert_eq!(apply("Il coûte 12,000 euros", "fr"), "Il coûte 12\u{202F}000 euros");
+        assert_eq!(apply("Zahlen 1,2,3", "de"), "Zahlen 1,2,3");
+    }
+
+    #[test]
+    fn test_elided_digits_keep_apostrophe() {
+        assert_eq!(apply("music of the '90s", "en"), "music of the ’90s");
+        assert_eq!(apply("the 'best' of '24", "en"), "the ‘best’ of ’24");
+    }
+
+    #[test]
+    fn test_versions_untouched() {
+        assert_eq!(apply("Version 1.2.3 ist da", "de"), "Version 1.2.3 ist da");
+    }
+
+    #[test]
+    fn test_french_spacing() {
+        let result = apply(r#"Il a dit "bonjour" : vraiment ?"#, "fr");
+        assert_eq!(
+            result,
+            "Il a dit «\u{00A0}bonjour\u{00A0}»\u{00A0}: vraiment\u{202F}?"
+        );
+    }
+
+    #[test]
+    fn test_french_leaves_urls_alone() {
+        let result = apply("Voir https://example.com", "fr");
+        assert_eq!(result, "Voir https://example.com");
+    }
+}
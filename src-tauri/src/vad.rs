@@ -0,0 +1,295 @@
+//! Energy-based voice activity detection
+//!
+//! Finds speech regions in 16kHz mono audio so long recordings can skip
+//! silence instead of feeding it to the STT provider.
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use serde::{Deserialize, Serialize};
+
+/// VAD tuning parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VadConfig {
+    /// Analysis frame length
+    pub frame_ms: u32,
+    /// Minimum RMS treated as speech (raised automatically above the noise floor)
+    pub threshold: f32,
+    /// Silence shorter than this does not split a region
+    pub min_silence_ms: u32,
+    /// Audio kept before and after each region
+    pub padding_ms: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            frame_ms: 30,
+            threshold: 0.01,
+            min_silence_ms: 600,
+            padding_ms: 200,
+        }
+    }
+}
+
+/// A region of speech, as sample indices into the source audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpeechRegion {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl SpeechRegion {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+
+    /// Start offset in milliseconds
+    pub fn start_ms(&self) -> u64 {
+        crate::audio::calculate_duration_ms(self.start)
+    }
+
+    /// End offset in milliseconds
+    pub fn end_ms(&self) -> u64 {
+        crate::audio::calculate_duration_ms(self.end)
+    }
+}
+
+fn ms_to_samples(ms: u32) -> usize {
+    (ms as usize * WHISPER_SAMPLE_RATE as usize) / 1000
+}
+
+/// RMS of each frame
+pub(crate) fn frame_levels(samples: &[f32], frame_len: usize) -> Vec<f32> {
+    samples
+        .chunks(frame_len)
+        .map(|frame| {
+            let sum_sq: f32 = frame.iter().map(|s| s * s).sum();
+            (sum_sq / frame.len() as f32).sqrt()
+        })
+        .collect()
+}
+
+/// Speech threshold adapted to the recording's noise floor
+///
+/// The floor (10th percentile of frame RMS) is tripled, but never raised
+/// past half the median so mostly-speech audio still has speech above it.
+fn adaptive_threshold(levels: &[f32], base: f32) -> f32 {
+    if levels.is_empty() {
+        return base;
+    }
+    let mut sorted = levels.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let floor = sorted[sorted.len() / 10];
+    let median = sorted[sorted.len() / 2];
+    base.max((floor * 3.0).min(median * 0.5))
+}
+
+/// Detect speech regions in the audio
+pub fn detect_speech(samples: &[f32], config: &VadConfig) -> Vec<SpeechRegion> {
+    let frame_len = ms_to_samples(config.frame_ms).max(1);
+    let levels = frame_levels(samples, frame_len);
+    let threshold = adaptive_threshold(&levels, config.threshold);
+    let min_silence_frames = (config.min_silence_ms / config.frame_ms.max(1)) as usize;
+    let padding = ms_to_samples(config.padding_ms);
+
+    let mut regions = Vec::new();
+    let mut region_start: Option<usize> = None;
+    let mut silent_frames = 0;
+
+    for (i, &level) in levels.iter().enumerate() {
+        if level >= threshold {
+            if region_start.is_none() {
+                region_start = Some(i);
+            }
+            silent_frames = 0;
+        } else if let Some(start) = region_start {
+            silent_frames += 1;
+            if silent_frames > min_silence_frames {
+                let end = i + 1 - silent_frames;
+                regions.push((start, end));
+                region_start = None;
+                silent_frames = 0;
+            }
+        }
+    }
+    if let Some(start) = region_start {
+        regions.push((start, levels.len() - silent_frames));
+    }
+
+    // Convert frames to padded sample ranges, merging overlaps
+    let mut result: Vec<SpeechRegion> = Vec::new();
+    for (start, end) in regions {
+        let region = SpeechRegion {
+            start: (start * frame_len).saturating_sub(padding),
+            end: (end * frame_len + padding).min(samples.len()),
+        };
+        match result.last_mut() {
+            Some(last) if region.start <= last.end => last.end = region.end,
+            _ => result.push(region),
+        }
+    }
+
+    result
+}
+
+/// Group neighbouring regions into chunks no longer than `max_ms`
+///
+/// Fewer, longer chunks keep per-request overhead down while still skipping
+/// the silence between them. A single region longer than `max_ms` is kept whole.
+pub fn group_regions(regions: &[SpeechRegion], max_ms: u32) -> Vec<SpeechRegion> {
+    let max_len = ms_to_samples(max_ms);
+    let mut grouped: Vec<SpeechRegion> = Vec::new();
+
+    for &region in regions {
+        match grouped.last_mut() {
+            Some(last) if region.end - last.start <= max_len => last.end = region.end,
+            _ => grouped.push(region),
+        }
+    }
+
+    grouped
+}
+
+/// Pick where to cut a growing buffer into a segment at a pause
+///
+/// Returns None until at least `min_ms` of audio is buffered. Between
+/// `min_ms` and `max_ms` the first pause of `min_silence_ms` is used; once
+/// `max_ms` is reached without one, the quietest frame in that window is used.
+pub fn find_silence_cut(samples: &[f32], min_ms: u32, max_ms: u32, config: &VadConfig) -> Option<usize> {
+    let frame_len = ms_to_samples(config.frame_ms).max(1);
+    let min_len = ms_to_samples(min_ms);
+    let max_len = ms_to_samples(max_ms);
+    if samples.len() < min_len {
+        return None;
+    }
+
+    let window_end = samples.len().min(max_len);
+    let levels = frame_levels(&samples[min_len..window_end], frame_len);
+    let threshold = adaptive_threshold(&frame_levels(&samples[..window_end], frame_len), config.threshold);
+    let needed = (config.min_silence_ms / config.frame_ms.max(1)).max(1) as usize;
+
+    let mut run = 0;
+    for (i, &level) in levels.iter().enumerate() {
+        if level < threshold {
+            run += 1;
+            if run >= needed {
+                // Cut in the middle of the pause
+                let mid = i + 1 - run / 2;
+                return Some(min_len + mid * frame_len);
+            }
+        } else {
+            run = 0;
+        }
+    }
+
+    if samples.len() < max_len {
+        return None;
+    }
+
+    let quietest = levels
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    Some(min_len + quietest * frame_len)
+}
+
+/// Fraction of the audio covered by speech regions
+pub fn speech_ratio(regions: &[SpeechRegion], total_samples: usize) -> f32 {
+    if total_samples == 0 {
+        return 0.0;
+    }
+    let speech: usize = regions.iter().map(|r| r.len()).sum();
+    speech as f32 / total_samples as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(seconds: f32) -> Vec<f32> {
+        let n = (seconds * WHISPER_SAMPLE_RATE as f32) as usize;
+        (0..n).map(|i| (i as f32 * 0.05).sin() * 0.3).collect()
+    }
+
+    fn silence(seconds: f32) -> Vec<f32> {
+        vec![0.0; (seconds * WHISPER_SAMPLE_RATE as f32) as usize]
+    }
+
+    #[test]
+    fn test_detects_speech_between_silence() {
+        let mut samples = silence(2.0);
+        samples.extend(tone(1.0));
+        samples.extend(silence(3.0));
+        samples.extend(tone(1.0));
+        samples.extend(silence(1.0));
+
+        let regions = detect_speech(&samples, &VadConfig::default());
+        assert_eq!(regions.len(), 2);
+        assert!(regions[0].start_ms() >= 1700 && regions[0].start_ms() <= 2000);
+        assert!(regions[1].start_ms() >= 5700 && regions[1].start_ms() <= 6000);
+    }
+
+    #[test]
+    fn test_short_pause_does_not_split() {
+        let mut samples = tone(1.0);
+        samples.extend(silence(0.2));
+        samples.extend(tone(1.0));
+
+        let regions = detect_speech(&samples, &VadConfig::default());
+        assert_eq!(regions.len(), 1);
+    }
+
+    #[test]
+    fn test_mostly_speech() {
+        let mut samples = tone(10.0);
+        samples.extend(silence(1.0));
+        samples.extend(tone(10.0));
+
+        let regions = detect_speech(&samples, &VadConfig::default());
+        assert_eq!(regions.len(), 2);
+    }
+
+    #[test]
+    fn test_all_silence() {
+        let regions = detect_speech(&silence(2.0), &VadConfig::default());
+        assert!(regions.is_empty());
+    }
+
+    #[test]
+    fn test_find_silence_cut_at_pause() {
+        let mut samples = tone(21.0);
+        samples.extend(silence(1.0));
+        samples.extend(tone(2.0));
+
+        let cut = find_silence_cut(&samples, 20_000, 30_000, &VadConfig::default()).unwrap();
+        let cut_ms = crate::audio::calculate_duration_ms(cut);
+        assert!(cut_ms > 21_000 && cut_ms < 22_000);
+    }
+
+    #[test]
+    fn test_find_silence_cut_waits_then_forces() {
+        let samples = tone(25.0);
+        assert!(find_silence_cut(&samples, 20_000, 30_000, &VadConfig::default()).is_none());
+
+        let samples = tone(31.0);
+        let cut = find_silence_cut(&samples, 20_000, 30_000, &VadConfig::default()).unwrap();
+        assert!(cut >= 20 * WHISPER_SAMPLE_RATE as usize && cut <= 30 * WHISPER_SAMPLE_RATE as usize);
+    }
+
+    #[test]
+    fn test_group_regions() {
+        let regions = vec![
+            SpeechRegion { start: 0, end: 16000 },
+            SpeechRegion { start: 32000, end: 48000 },
+            SpeechRegion { start: 600000, end: 610000 },
+        ];
+        let grouped = group_regions(&regions, 30_000);
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0], SpeechRegion { start: 0, end: 48000 });
+    }
+}
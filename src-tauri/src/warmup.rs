@@ -0,0 +1,157 @@
+//! Startup warm-up
+//!
+//! The STT model and LLM used by the last dictation are remembered, and
+//! loaded in the background after startup so the first dictation after login
+//! does not pay for model loading. A local whisper model stays loaded for
+//! the dictations that follow. With `warm_start_when_idle` the warm-up
+//! waits until the system load drops, to stay out of the way of login.
+
+use crate::error::{AppError, Result};
+use crate::modes::LlmFallback;
+use crate::providers::{llm, stt};
+use crate::state::SharedState;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Wait after startup before warming, so the tray and hotkey come up first
+const STARTUP_DELAY: Duration = Duration::from_secs(5);
+
+/// How often the load average is checked while waiting for idle
+const IDLE_POLL: Duration = Duration::from_secs(30);
+
+/// Warm up anyway after waiting this long for idle
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// 1-minute load per CPU under which the system counts as idle
+const IDLE_LOAD_PER_CPU: f64 = 0.5;
+
+/// Models and providers used by the last dictation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WarmCache {
+    #[serde(default)]
+    pub stt: Option<stt::SttSelection>,
+    #[serde(default)]
+    pub llm: Option<LlmFallback>,
+}
+
+fn cache_path() -> Result<PathBuf> {
+    let data_dir = directories::ProjectDirs::from("com", "whispertray", "WhisperTray")
+        .ok_or_else(|| AppError::Config("Could not determine data directory".to_string()))?
+        .data_dir()
+        .to_path_buf();
+    Ok(data_dir.join("warm_cache.json"))
+}
+
+/// Load the remembered models, if any
+pub fn load() -> WarmCache {
+    cache_path()
+        .and_then(|path| Ok(std::fs::read_to_string(path)?))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Remember the models used by a dictation
+pub fn save(cache: &WarmCache) -> Result<()> {
+    let path = cache_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(cache)?)?;
+    Ok(())
+}
+
+/// Whether a `/proc/loadavg` line shows the system idle for `cpus` CPUs
+fn is_idle(loadavg: &str, cpus: usize) -> bool {
+    loadavg
+        .split_whitespace()
+        .next()
+        .and_then(|load| load.parse::<f64>().ok())
+        .is_some_and(|load| load < cpus as f64 * IDLE_LOAD_PER_CPU)
+}
+
+async fn wait_for_idle() {
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let started = std::time::Instant::now();
+    while started.elapsed() < IDLE_TIMEOUT {
+        match tokio::fs::read_to_string("/proc/loadavg").await {
+            Ok(loadavg) if !is_idle(&loadavg, cpus) => tokio::time::sleep(IDLE_POLL).await,
+            _ => return,
+        }
+    }
+    log::info!("System still busy, warming up anyway");
+}
+
+/// Warm the remembered models in the background, if enabled
+pub fn spawn(state: SharedState) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_DELAY).await;
+
+        let (enabled, when_idle) = {
+            let state = state.lock().await;
+            (state.settings.warm_start, state.settings.warm_start_when_idle)
+        };
+        if !enabled {
+            return;
+        }
+        if when_idle {
+            wait_for_idle().await;
+        }
+
+        if let Err(e) = warm(&state).await {
+            log::warn!("Warm-up failed: {}", e);
+        }
+    });
+}
+
+async fn warm(state: &SharedState) -> Result<()> {
+    let cache = load();
+
+    // Only hold the lock to read keys and URLs; loading can take a while
+    let (stt_key, llm_key, server_url, ollama_url) = {
+        let state = state.lock().await;
+        let stt_key = match &cache.stt {
+            Some(selection) => state.get_stt_api_key(&selection.provider)?,
+            None => None,
+        };
+        let llm_key = match &cache.llm {
+            Some(llm) => state.get_api_key(&llm.provider)?,
+            None => None,
+        };
+        (
+            stt_key,
+            llm_key,
+            cache.stt.as_ref().and_then(|s| state.settings.stt_endpoint(&s.provider)),
+            state.settings.ollama_url.clone(),
+        )
+    };
+
+    if let Some(selection) = &cache.stt {
+        let started = std::time::Instant::now();
+        let provider = stt::create_stt_provider(&selection.provider, &selection.model, stt_key, server_url).await?;
+        provider.health_check().await?;
+        log::info!("Warmed up {} ({}) in {:?}", provider.name(), selection.model, started.elapsed());
+    }
+
+    if let Some(fallback) = &cache.llm {
+        let started = std::time::Instant::now();
+        let provider = llm::create_llm_provider(&fallback.provider, &fallback.model, llm_key.as_deref(), ollama_url)?;
+        provider.warm_up().await?;
+        log::info!("Warmed up {} ({}) in {:?}", provider.name(), fallback.model, started.elapsed());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_idle() {
+        assert!(is_idle("0.52 0.80 0.91 2/1234 5678", 4));
+        assert!(!is_idle("3.10 2.00 1.00 5/1234 5678", 4));
+        assert!(!is_idle("", 4));
+    }
+}
@@ -0,0 +1,442 @@
+//! Transcribing audio files dropped into a watched folder
+//!
+//! Voice memos synced from a phone land in a folder; each new audio file there
+//! is transcribed with the watch folder's mode and saved to history like a
+//! dictation, optionally with the output written next to it as a sidecar
+//! `.txt` or `.md`. The folder is polled, and a file is only taken once its
+//! size and modification time are unchanged between two scans, so files still
+//! being synced are left alone. Handled files are recorded in the history
+//! database, so nothing is transcribed twice across restarts. A file that
+//! fails is retried a few times with growing delays (the provider may be
+//! offline) before it is recorded as failed. Nothing is taken while
+//! read-only mode or incognito is on.
+
+use crate::cancel::CancelToken;
+use crate::database::{get_audio_dir, Database, HistoryItem};
+use crate::state::SharedState;
+use crate::text_stats::TextStats;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often the watched folder is scanned
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Delays before retrying a file that failed; it is recorded as failed after the last
+const RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_secs(60),
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(30 * 60),
+];
+
+/// Extensions `load_audio` decodes
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "flac", "opus", "ogg", "m4a", "mp3", "aac", "amr"];
+
+/// Output written next to a transcribed file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SidecarFormat {
+    /// The output alone
+    Txt,
+    /// The output under a heading with the file name and date
+    Md,
+}
+
+impl SidecarFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            SidecarFormat::Txt => "txt",
+            SidecarFormat::Md => "md",
+        }
+    }
+}
+
+/// Size and modification time (ms since the epoch) of a file
+type Stamp = (u64, i64);
+
+/// Audio files directly in `dir`
+///
+/// Hidden files are skipped: sync tools write partial downloads as dotfiles.
+fn scan(dir: &Path) -> Vec<(PathBuf, Stamp)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter(|entry| {
+            entry.path().extension().is_some_and(|ext| {
+                AUDIO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+            })
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+            Some((entry.path(), (metadata.len(), modified.as_millis() as i64)))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Files of `current` seen unchanged in the `previous` scan, so done being written
+fn settled(
+    previous: &HashMap<PathBuf, Stamp>,
+    current: &[(PathBuf, Stamp)],
+) -> Vec<(PathBuf, Stamp)> {
+    current
+        .iter()
+        .filter(|(path, stamp)| previous.get(path) == Some(stamp))
+        .cloned()
+        .collect()
+}
+
+/// Sidecar files for a transcribed `audio` file, in order of preference
+///
+/// `memo.txt` may already be the user's own note, so `memo.m4a.txt` is next.
+fn sidecar_paths(audio: &Path, format: SidecarFormat) -> [PathBuf; 2] {
+    let mut full = audio.as_os_str().to_owned();
+    full.push(".");
+    full.push(format.extension());
+    [
+        audio.with_extension(format.extension()),
+        PathBuf::from(full),
+    ]
+}
+
+/// Write the sidecar to the first of `sidecar_paths` that does not exist yet
+fn write_sidecar(audio: &Path, content: &str, format: SidecarFormat) -> Result<(), String> {
+    use std::io::Write;
+    for path in sidecar_paths(audio, format) {
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                return file
+                    .write_all(content.as_bytes())
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to write {}: {}", path.display(), e)),
+        }
+    }
+    log::warn!(
+        "Not writing a sidecar for {}: its sidecar files already exist",
+        audio.display()
+    );
+    Ok(())
+}
+
+fn sidecar_content(audio: &Path, item: &HistoryItem, format: SidecarFormat) -> String {
+    match format {
+        SidecarFormat::Txt => format!("{}\n", item.output_final),
+        SidecarFormat::Md => format!(
+            "# {}\n\n*{}*\n\n{}\n",
+            audio.file_stem().unwrap_or_default().to_string_lossy(),
+            item.created_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M"),
+            item.output_final
+        ),
+    }
+}
+
+/// Watch the configured folder for as long as the app runs
+///
+/// Settings are read on every scan, so the folder, mode and sidecar format
+/// can be changed without a restart.
+pub fn spawn(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut previous: HashMap<PathBuf, Stamp> = HashMap::new();
+        let mut watched: Option<PathBuf> = None;
+        // Failed files by path: the stamp that failed, failures so far and when to retry
+        let mut retries: HashMap<PathBuf, (Stamp, usize, Instant)> = HashMap::new();
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let Some(state) = app.try_state::<SharedState>() else {
+                continue;
+            };
+            let state = state.inner().clone();
+            let (dir, db, paused) = {
+                let state = state.lock().await;
+                (
+                    state.settings.watch_folder.clone().map(PathBuf::from),
+                    state.database.clone(),
+                    state.is_read_only() || state.incognito,
+                )
+            };
+            let (Some(dir), Some(db)) = (dir, db) else {
+                previous.clear();
+                retries.clear();
+                watched = None;
+                continue;
+            };
+            // Files arriving meanwhile are taken once it is off again
+            if paused {
+                continue;
+            }
+            if watched.as_ref() != Some(&dir) {
+                log::info!("Watching {} for audio files", dir.display());
+                previous.clear();
+                retries.clear();
+                watched = Some(dir.clone());
+            }
+
+            let current = scan(&dir);
+            for (path, stamp) in settled(&previous, &current) {
+                let key = path.to_string_lossy().to_string();
+                let modified_ms = stamp.1;
+                let processed = {
+                    let key = key.clone();
+                    db.run(move |db| db.is_watched_file_processed(&key, modified_ms))
+                        .await
+                };
+                if !matches!(processed, Ok(false)) {
+                    continue;
+                }
+                let failures = match retries.get(&path) {
+                    Some((failed, _, _)) if *failed != stamp => 0,
+                    Some((_, _, retry_at)) if Instant::now() < *retry_at => continue,
+                    Some((_, failures, _)) => *failures,
+                    None => 0,
+                };
+
+                log::info!("Transcribing {} from the watch folder", path.display());
+                let result =
+                    crate::priority::background(process(&state, &db, &path, modified_ms)).await;
+                let (history_id, error) = match &result {
+                    Ok(item) => {
+                        let _ = app.emit("watch-folder-transcribed", item);
+                        (Some(item.id.clone()), None)
+                    }
+                    Err(e) if failures < RETRY_DELAYS.len() => {
+                        let delay = RETRY_DELAYS[failures];
+                        log::warn!(
+                            "Failed to transcribe {}, retrying in {}s: {}",
+                            path.display(),
+                            delay.as_secs(),
+                            e
+                        );
+                        retries.insert(path, (stamp, failures + 1, Instant::now() + delay));
+                        continue;
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to transcribe {}: {}", path.display(), e);
+                        (None, Some(e.clone()))
+                    }
+                };
+                retries.remove(&path);
+                if let Err(e) = db
+                    .run(move |db| {
+                        db.record_watched_file(
+                            &key,
+                            modified_ms,
+                            history_id.as_deref(),
+                            error.as_deref(),
+                        )
+                    })
+                    .await
+                {
+                    log::warn!("Failed to record watch folder file: {}", e);
+                }
+            }
+            previous = current.into_iter().collect();
+        }
+    });
+}
+
+/// Transcribe one file, run the watch folder's mode on it and save it
+async fn process(
+    state: &SharedState,
+    db: &Arc<Database>,
+    path: &Path,
+    modified_ms: i64,
+) -> Result<HistoryItem, String> {
+    let (mode_key, sidecar) = {
+        let state = state.lock().await;
+        (
+            state.settings.watch_folder_mode.clone(),
+            state.settings.watch_folder_sidecar,
+        )
+    };
+    // When the memo was recorded, not when it synced
+    let created_at = DateTime::<Utc>::from_timestamp_millis(modified_ms).unwrap_or_else(Utc::now);
+    let item = transcribe_to_history(state, db, path, mode_key.as_deref(), created_at).await?;
+
+    if let Some(format) = sidecar {
+        write_sidecar(path, &sidecar_content(path, &item, format), format)?;
+    }
+    Ok(item)
+}
+
+/// Transcribe an audio file with a mode (the active one when None) and save it to history
+///
+/// History keeps its own copy of the audio, so the file may be removed
+/// afterwards. The item is returned unredacted, whatever the mode stores.
+/// Refused in read-only mode; in incognito the item is only returned, and
+/// neither it nor the audio is kept.
+pub(crate) async fn transcribe_to_history(
+    state: &SharedState,
+    db: &Arc<Database>,
+    path: &Path,
+    mode_key: Option<&str>,
+    created_at: DateTime<Utc>,
+) -> Result<HistoryItem, String> {
+    let (mode, stt, retranscriber, incognito) = {
+        let state = state.lock().await;
+        state
+            .ensure_writable("transcribing files into history")
+            .map_err(|e| e.to_string())?;
+        let mode = match mode_key {
+            Some(key) => state.modes.get(key),
+            None => state.get_active_mode(),
+        }
+        .cloned()
+        .ok_or_else(|| "Mode not found".to_string())?;
+        let stt = state.stt_selection(&mode);
+        let retranscriber = state.retranscriber(&mode, &stt).map_err(|e| e.to_string())?;
+        (mode, stt, retranscriber, state.incognito)
+    };
+    let retranscriber = retranscriber.load().await.map_err(|e| e.to_string())?;
+
+    let source = path.to_path_buf();
+    let samples = tokio::task::spawn_blocking(move || crate::audio_codec::load_audio(&source))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    let cancel = CancelToken::new();
+    let scored = retranscriber
+        .transcribe(&samples, &cancel)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    let audio_path = if incognito {
+        None
+    } else {
+        get_audio_dir()
+            .and_then(|dir| {
+                std::fs::create_dir_all(&dir)?;
+                let copy = dir.join(format!("{}.{}", id, extension));
+                std::fs::copy(path, &copy)?;
+                Ok(copy)
+            })
+            .map_err(|e| log::warn!("Failed to keep a copy of {}: {}", path.display(), e))
+            .ok()
+    };
+
+    let mut item = HistoryItem {
+        id,
+        created_at,
+        mode_key: mode.key.clone(),
+        audio_path: audio_path.map(|path| path.to_string_lossy().to_string()),
+        transcript_raw: scored.text,
+        output_final: String::new(),
+        stt_provider: format!("{:?}", stt.provider).to_lowercase(),
+        stt_model: stt.model,
+        llm_provider: None,
+        llm_model: None,
+        duration_ms: crate::audio::calculate_duration_ms(samples.len()),
+        error: None,
+        latency: Vec::new(),
+        bookmarks: Vec::new(),
+        prompt_tokens: None,
+        completion_tokens: None,
+        cost_usd: None,
+        stats: TextStats::default(),
+        truncated: false,
+        window_class: None,
+        window_title: None,
+        cost_saved_usd: None,
+        deleted_at: None,
+        degraded: false,
+        verbatim: mode.verbatim,
+        summary_only: false,
+        source_url: None,
+        clipped: false,
+    };
+    crate::commands::apply_mode(state, &mut item, mode.key.clone(), &cancel).await?;
+
+    if incognito {
+        return Ok(item);
+    }
+    if let Some(stored) = crate::redact::for_storage(item.clone(), mode.history_storage) {
+        db.run(move |db| db.insert_history(&stored))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_scan_settled() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("memo.wav"), b"RIFF").unwrap();
+        std::fs::write(dir.path().join("memo.txt"), b"sidecar").unwrap();
+        std::fs::write(dir.path().join("call.m4a"), b"").unwrap();
+        std::fs::write(dir.path().join(".memo2.flac.part"), b"").unwrap();
+        std::fs::write(dir.path().join(".memo3.flac"), b"").unwrap();
+
+        let first = scan(dir.path());
+        assert_eq!(first.len(), 2);
+        assert!(first[0].0.ends_with("call.m4a"));
+        assert!(first[1].0.ends_with("memo.wav"));
+        // Only a file seen unchanged twice is taken
+        assert!(settled(&HashMap::new(), &first).is_empty());
+        let previous = first.iter().cloned().collect();
+        assert_eq!(settled(&previous, &scan(dir.path())), first);
+
+        std::fs::write(dir.path().join("memo.wav"), b"RIFF....").unwrap();
+        assert!(settled(&previous, &scan(dir.path())).is_empty());
+    }
+
+    #[test]
+    fn test_sidecar() {
+        let audio = Path::new("/memos/Shopping list.flac");
+        assert_eq!(
+            sidecar_paths(audio, SidecarFormat::Md),
+            [
+                PathBuf::from("/memos/Shopping list.md"),
+                PathBuf::from("/memos/Shopping list.flac.md")
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(SidecarFormat::Txt).unwrap(),
+            serde_json::json!("txt")
+        );
+    }
+
+    #[test]
+    fn test_sidecar_keeps_existing_notes() {
+        let dir = tempdir().unwrap();
+        let audio = dir.path().join("memo.m4a");
+        std::fs::write(dir.path().join("memo.txt"), "my note").unwrap();
+
+        write_sidecar(&audio, "first\n", SidecarFormat::Txt).unwrap();
+        write_sidecar(&audio, "second\n", SidecarFormat::Txt).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("memo.txt")).unwrap(),
+            "my note"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("memo.m4a.txt")).unwrap(),
+            "first\n"
+        );
+    }
+}
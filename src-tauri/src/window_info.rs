@@ -0,0 +1,294 @@
+//! Focused window detection
+//!
+//! Reports the class and title of the focused window. Wayland compositors
+//! are asked over their IPC (swaymsg on Sway, hyprctl on Hyprland, kdotool,
+//! which runs KWin scripts, on Plasma); X11 and XWayland windows are read
+//! with xprop. The focused window can also be remembered and given focus
+//! back, e.g. after a picker window of ours took it.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::process::Command;
+
+/// The focused window
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowInfo {
+    /// Window class, or app id on Wayland (e.g. "firefox")
+    pub class: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Where the focused window is looked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowBackend {
+    Sway,
+    Hyprland,
+    KWin,
+    X11,
+}
+
+/// Pick the lookup for this session, if its tool is installed
+pub fn detect_backend() -> Option<WindowBackend> {
+    let has_env = |name: &str| std::env::var_os(name).is_some();
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+
+    if has_env("SWAYSOCK") && crate::paste::is_command_available("swaymsg") {
+        Some(WindowBackend::Sway)
+    } else if has_env("HYPRLAND_INSTANCE_SIGNATURE") && crate::paste::is_command_available("hyprctl") {
+        Some(WindowBackend::Hyprland)
+    } else if desktop.contains("KDE") && crate::paste::is_wayland() && crate::paste::is_command_available("kdotool") {
+        Some(WindowBackend::KWin)
+    } else if has_env("DISPLAY") && crate::paste::is_command_available("xprop") {
+        // Under other Wayland compositors this only sees XWayland windows
+        Some(WindowBackend::X11)
+    } else {
+        None
+    }
+}
+
+/// The focused window, if it can be determined
+pub fn active_window() -> Option<WindowInfo> {
+    let info = match detect_backend()? {
+        WindowBackend::Sway => {
+            let tree = run("swaymsg", &["-t", "get_tree", "--raw"])?;
+            parse_sway_tree(&serde_json::from_str(&tree).ok()?)
+        }
+        WindowBackend::Hyprland => {
+            let window = run("hyprctl", &["activewindow", "-j"])?;
+            parse_hyprland_window(&serde_json::from_str(&window).ok()?)
+        }
+        WindowBackend::KWin => Some(WindowInfo {
+            class: run("kdotool", &["getactivewindow", "getwindowclassname"]),
+            title: run("kdotool", &["getactivewindow", "getwindowname"]),
+        }),
+        WindowBackend::X11 => {
+            let root = run("xprop", &["-root", "_NET_ACTIVE_WINDOW"])?;
+            let id = parse_xprop_window_id(&root)?;
+            parse_xprop_window(&run("xprop", &["-id", &id, "WM_CLASS", "_NET_WM_NAME"])?)
+        }
+    }?;
+
+    (info.class.is_some() || info.title.is_some()).then_some(info)
+}
+
+/// The focused window, to give focus back to with `activate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusedWindow {
+    backend: WindowBackend,
+    id: String,
+}
+
+/// Remember the focused window, if it can be told
+pub fn focused_window() -> Option<FocusedWindow> {
+    let backend = detect_backend()?;
+    let id = match backend {
+        WindowBackend::Sway => {
+            let tree: Value = serde_json::from_str(&run("swaymsg", &["-t", "get_tree", "--raw"])?).ok()?;
+            sway_focused(&tree)?["id"].as_u64()?.to_string()
+        }
+        WindowBackend::Hyprland => {
+            let window: Value = serde_json::from_str(&run("hyprctl", &["activewindow", "-j"])?).ok()?;
+            non_empty(window["address"].as_str())?
+        }
+        WindowBackend::KWin => run("kdotool", &["getactivewindow"])?,
+        WindowBackend::X11 => parse_xprop_window_id(&run("xprop", &["-root", "_NET_ACTIVE_WINDOW"])?)?,
+    };
+    Some(FocusedWindow { backend, id })
+}
+
+/// Give focus back to a remembered window; false if that failed
+pub fn activate(window: &FocusedWindow) -> bool {
+    let (cmd, args) = match window.backend {
+        WindowBackend::Sway => ("swaymsg", vec![format!("[con_id={}]", window.id), "focus".to_string()]),
+        WindowBackend::Hyprland => ("hyprctl", vec!["dispatch".to_string(), format!("focuswindow address:{}", window.id)]),
+        WindowBackend::KWin => ("kdotool", vec!["windowactivate".to_string(), window.id.clone()]),
+        WindowBackend::X11 => ("xdotool", vec!["windowactivate".to_string(), window.id.clone()]),
+    };
+    Command::new(cmd)
+        .args(&args)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Whether the pointer is over the focused window, when that can be told
+///
+/// Checked on X11 (with xdotool) and Hyprland; other compositors do not
+/// expose the pointer position.
+pub fn pointer_in_focused_window() -> Option<bool> {
+    let (point, rect) = match detect_backend()? {
+        WindowBackend::Hyprland => {
+            let cursor = parse_hyprland_cursor(&run("hyprctl", &["cursorpos"])?)?;
+            let window: Value = serde_json::from_str(&run("hyprctl", &["activewindow", "-j"])?).ok()?;
+            let coord = |key: &str, i: usize| window[key].get(i).and_then(Value::as_i64);
+            let rect = (coord("at", 0)?, coord("at", 1)?, coord("size", 0)?, coord("size", 1)?);
+            (cursor, rect)
+        }
+        WindowBackend::X11 => {
+            let mouse = parse_shell_vars(&run("xdotool", &["getmouselocation", "--shell"])?);
+            let window = parse_shell_vars(&run("xdotool", &["getactivewindow", "getwindowgeometry", "--shell"])?);
+            let get = |vars: &[(String, i64)], key: &str| vars.iter().find(|(k, _)| k == key).map(|(_, v)| *v);
+            (
+                (get(&mouse, "X")?, get(&mouse, "Y")?),
+                (get(&window, "X")?, get(&window, "Y")?, get(&window, "WIDTH")?, get(&window, "HEIGHT")?),
+            )
+        }
+        WindowBackend::Sway | WindowBackend::KWin => return None,
+    };
+    Some(contains(rect, point))
+}
+
+/// Whether `(x, y)` lies inside the `(x, y, width, height)` rectangle
+fn contains(rect: (i64, i64, i64, i64), point: (i64, i64)) -> bool {
+    let (x, y, width, height) = rect;
+    (x..x + width).contains(&point.0) && (y..y + height).contains(&point.1)
+}
+
+/// Numeric `KEY=VALUE` lines, as printed by `xdotool ... --shell`
+fn parse_shell_vars(output: &str) -> Vec<(String, i64)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            Some((key.to_string(), value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Pointer position from `hyprctl cursorpos`, e.g. "1200, 640"
+fn parse_hyprland_cursor(output: &str) -> Option<(i64, i64)> {
+    let (x, y) = output.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+/// Run a command, returning its trimmed stdout when it succeeds with output
+fn run(cmd: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(cmd).args(args).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !stdout.is_empty()).then_some(stdout)
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+/// Find the focused node in `swaymsg -t get_tree` output
+fn sway_focused(node: &Value) -> Option<&Value> {
+    if node["focused"].as_bool() == Some(true) && node["type"] == "con" {
+        return Some(node);
+    }
+
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[key].as_array())
+        .flatten()
+        .find_map(sway_focused)
+}
+
+/// Read the focused window from `swaymsg -t get_tree` output
+fn parse_sway_tree(tree: &Value) -> Option<WindowInfo> {
+    let node = sway_focused(tree)?;
+    Some(WindowInfo {
+        // Native Wayland windows have an app_id, XWayland ones a class
+        class: non_empty(node["app_id"].as_str()).or_else(|| non_empty(node["window_properties"]["class"].as_str())),
+        title: non_empty(node["name"].as_str()),
+    })
+}
+
+/// Read `hyprctl activewindow -j` output
+fn parse_hyprland_window(window: &Value) -> Option<WindowInfo> {
+    Some(WindowInfo {
+        class: non_empty(window.get("class")?.as_str()),
+        title: non_empty(window["title"].as_str()),
+    })
+}
+
+/// Window id from `xprop -root _NET_ACTIVE_WINDOW`, e.g. "0x3e00003"
+fn parse_xprop_window_id(output: &str) -> Option<String> {
+    let id = output.rsplit('#').next()?.trim();
+    // 0x0 means no window has focus
+    (id.starts_with("0x") && id != "0x0").then(|| id.to_string())
+}
+
+/// Class and title from `xprop -id <id> WM_CLASS _NET_WM_NAME`
+fn parse_xprop_window(output: &str) -> Option<WindowInfo> {
+    let mut info = WindowInfo::default();
+    for line in output.lines() {
+        let Some((name, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        // Quoted strings, e.g. "navigator", "firefox"
+        let strings: Vec<&str> = value.split('"').skip(1).step_by(2).collect();
+        if name.starts_with("WM_CLASS") {
+            // Instance first, then class
+            info.class = non_empty(strings.last().copied());
+        } else if name.starts_with("_NET_WM_NAME") {
+            info.title = non_empty(strings.first().copied());
+        }
+    }
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_sway_tree() {
+        let tree = json!({
+            "type": "root",
+            "focused": false,
+            "nodes": [{
+                "type": "workspace",
+                "nodes": [
+                    { "type": "con", "focused": false, "app_id": "foot", "name": "~" },
+                    { "type": "con", "focused": true, "id": 12, "app_id": null, "name": "Figma",
+                      "window_properties": { "class": "figma-linux" } }
+                ],
+                "floating_nodes": []
+            }]
+        });
+        let info = parse_sway_tree(&tree).unwrap();
+        assert_eq!(info.class.as_deref(), Some("figma-linux"));
+        assert_eq!(info.title.as_deref(), Some("Figma"));
+        assert_eq!(sway_focused(&tree).unwrap()["id"], 12);
+    }
+
+    #[test]
+    fn test_parse_hyprland_window() {
+        let info = parse_hyprland_window(&json!({ "class": "code", "title": "main.rs - crate" })).unwrap();
+        assert_eq!(info.class.as_deref(), Some("code"));
+        assert_eq!(info.title.as_deref(), Some("main.rs - crate"));
+
+        // No focused window
+        assert_eq!(parse_hyprland_window(&json!({})), None);
+    }
+
+    #[test]
+    fn test_pointer_position() {
+        let mouse = parse_shell_vars("X=500\nY=320\nSCREEN=0\nWINDOW=62914563");
+        assert!(mouse.contains(&("X".to_string(), 500)));
+        assert_eq!(parse_hyprland_cursor("1200, 640"), Some((1200, 640)));
+
+        let rect = (100, 100, 800, 600);
+        assert!(contains(rect, (500, 320)));
+        assert!(!contains(rect, (900, 320)));
+        assert!(!contains(rect, (50, 320)));
+    }
+
+    #[test]
+    fn test_parse_xprop() {
+        assert_eq!(
+            parse_xprop_window_id("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3e00003").as_deref(),
+            Some("0x3e00003")
+        );
+        assert_eq!(parse_xprop_window_id("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x0"), None);
+
+        let info = parse_xprop_window(
+            "WM_CLASS(STRING) = \"navigator\", \"firefox\"\n_NET_WM_NAME(UTF8_STRING) = \"Inbox - Mail\"",
+        )
+        .unwrap();
+        assert_eq!(info.class.as_deref(), Some("firefox"));
+        assert_eq!(info.title.as_deref(), Some("Inbox - Mail"));
+    }
+}
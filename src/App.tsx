@@ -7,6 +7,7 @@ import HistoryPage from "./pages/HistoryPage";
 import SettingsPage from "./pages/SettingsPage";
 import ModesPage from "./pages/ModesPage";
 import RecordingIndicator from "./pages/RecordingIndicator";
+import CandidatePicker from "./pages/CandidatePicker";
 
 function App() {
   const navigate = useNavigate();
@@ -28,6 +29,11 @@ function App() {
     return <RecordingIndicator />;
   }
 
+  // Multi-take picker window has no layout either
+  if (location.pathname === "/picker") {
+    return <CandidatePicker />;
+  }
+
   return (
     <Layout>
       <Routes>
@@ -0,0 +1,153 @@
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import * as api from "../lib/api";
+import type { BatchJob, BatchTask, Mode } from "../types";
+
+interface BatchPanelProps {
+  modes: Mode[];
+  // Local whisper models to re-transcribe with
+  localModels: string[];
+  // Called as batch items finish, so the history list can refresh
+  onProgress: () => void;
+}
+
+function describeTask(task: BatchTask): string {
+  return task.kind === "reprocess"
+    ? `Reprocess with ${task.mode_key}`
+    : `Re-transcribe with ${task.stt.model}`;
+}
+
+// Background reprocessing of the whole history, with progress and controls
+export default function BatchPanel({ modes, localModels, onProgress }: BatchPanelProps) {
+  const [batches, setBatches] = useState<BatchJob[]>([]);
+  // "mode:<key>" or "model:<name>"
+  const [choice, setChoice] = useState("");
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    api.getBatches().then(setBatches).catch(() => setBatches([]));
+    const unlisten = listen<BatchJob>("batch-progress", (event) => {
+      const job = event.payload;
+      setBatches((prev) =>
+        prev.some((b) => b.id === job.id)
+          ? prev.map((b) => (b.id === job.id ? job : b))
+          : [job, ...prev]
+      );
+      onProgress();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [onProgress]);
+
+  const update = (job: BatchJob) =>
+    setBatches((prev) => prev.map((b) => (b.id === job.id ? job : b)));
+
+  const run = async (action: () => Promise<unknown>) => {
+    setError(null);
+    try {
+      await action();
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const handleStart = () =>
+    run(async () => {
+      const [kind, value] = [choice.slice(0, choice.indexOf(":")), choice.slice(choice.indexOf(":") + 1)];
+      const task: BatchTask =
+        kind === "mode"
+          ? { kind: "reprocess", mode_key: value }
+          : { kind: "retranscribe", stt: { provider: "whispercpp", model: value } };
+      if (!confirm(`${describeTask(task)} for every history item? This runs in the background.`)) return;
+      const job = await api.startBatch(task);
+      setBatches((prev) => [job, ...prev]);
+      setChoice("");
+    });
+
+  return (
+    <div className="mb-4 space-y-2">
+      <div className="flex gap-2">
+        <select
+          value={choice}
+          onChange={(e) => setChoice(e.target.value)}
+          className="flex-1 bg-gray-700 border border-gray-600 rounded px-2 py-1 text-sm text-white"
+        >
+          <option value="">Batch: process all history...</option>
+          <optgroup label="Reprocess with mode">
+            {modes.map((mode) => (
+              <option key={mode.key} value={`mode:${mode.key}`}>
+                {mode.name}
+              </option>
+            ))}
+          </optgroup>
+          {localModels.length > 0 && (
+            <optgroup label="Re-transcribe with model">
+              {localModels.map((model) => (
+                <option key={model} value={`model:${model}`}>
+                  {model}
+                </option>
+              ))}
+            </optgroup>
+          )}
+        </select>
+        <button
+          onClick={handleStart}
+          disabled={!choice}
+          className="px-3 py-1 bg-blue-600 text-white rounded text-sm hover:bg-blue-700 disabled:opacity-50 disabled:cursor-not-allowed"
+        >
+          Start
+        </button>
+      </div>
+      {error && <p className="text-xs text-red-400">{error}</p>}
+
+      {batches.map((job) => (
+        <div key={job.id} className="bg-gray-800 rounded p-2 text-xs text-gray-300">
+          <div className="flex items-center justify-between mb-1">
+            <span className="truncate">{describeTask(job.task)}</span>
+            <span className="text-gray-500">
+              {job.done}/{job.total}
+              {job.failed > 0 && <span className="text-red-400"> • {job.failed} failed</span>} • {job.status}
+            </span>
+          </div>
+          <div className="h-1 bg-gray-700 rounded mb-1">
+            <div
+              className="h-1 bg-blue-500 rounded"
+              style={{ width: `${job.total ? (job.done / job.total) * 100 : 0}%` }}
+            />
+          </div>
+          <div className="flex gap-3">
+            {job.status === "running" && (
+              <button onClick={() => run(async () => update(await api.pauseBatch(job.id)))} className="text-blue-400 hover:text-blue-300">
+                Pause
+              </button>
+            )}
+            {job.status === "paused" && (
+              <button onClick={() => run(async () => update(await api.resumeBatch(job.id)))} className="text-blue-400 hover:text-blue-300">
+                Resume
+              </button>
+            )}
+            {(job.status === "running" || job.status === "paused") && (
+              <button onClick={() => run(async () => update(await api.cancelBatch(job.id)))} className="text-red-400 hover:text-red-300">
+                Cancel
+              </button>
+            )}
+            {job.status !== "running" && (
+              <button
+                onClick={() =>
+                  run(async () => {
+                    await api.deleteBatch(job.id);
+                    setBatches((prev) => prev.filter((b) => b.id !== job.id));
+                  })
+                }
+                className="text-gray-400 hover:text-gray-300"
+              >
+                Remove
+              </button>
+            )}
+          </div>
+        </div>
+      ))}
+    </div>
+  );
+}
@@ -9,8 +9,16 @@ interface LayoutProps {
 }
 
 export default function Layout({ children }: LayoutProps) {
-  const { initialize, isLoading, error, clearError, status, activeMode } =
-    useAppStore();
+  const {
+    initialize,
+    isLoading,
+    error,
+    clearError,
+    status,
+    activeMode,
+    incognito,
+    setIncognito,
+  } = useAppStore();
 
   useEffect(() => {
     initialize();
@@ -43,11 +51,25 @@ export default function Layout({ children }: LayoutProps) {
             <h1 className="text-xl font-semibold text-white">WhisperTray</h1>
             <StatusIndicator status={status} />
           </div>
-          {activeMode && (
-            <div className="text-sm text-gray-400">
-              Mode: <span className="text-white">{activeMode.name}</span>
-            </div>
-          )}
+          <div className="flex items-center gap-4">
+            <button
+              onClick={() => setIncognito(!incognito)}
+              title="Incognito: no recording, history or journal entry is saved (Ctrl+Alt+I)"
+              className={clsx(
+                "text-xs px-2 py-1 rounded border",
+                incognito
+                  ? "bg-purple-700 border-purple-500 text-white"
+                  : "border-gray-600 text-gray-400 hover:text-white"
+              )}
+            >
+              {incognito ? "Incognito: nothing saved" : "Incognito"}
+            </button>
+            {activeMode && (
+              <div className="text-sm text-gray-400">
+                Mode: <span className="text-white">{activeMode.name}</span>
+              </div>
+            )}
+          </div>
         </div>
       </header>
 
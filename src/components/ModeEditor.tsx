@@ -0,0 +1,440 @@
+import { useEffect, useState, type ReactNode } from "react";
+import * as api from "../lib/api";
+import type { DryRunResult, HistoryStorage, Mode, ModeIssue, PipelineStep, PostProcessor } from "../types";
+
+// Starting point for a new mode: plain transcription with the local model
+export const NEW_MODE: Mode = {
+  key: "",
+  name: "",
+  description: "",
+  stt_provider: "whispercpp",
+  stt_model: "base.en",
+  ai_processing: false,
+  llm_provider: "ollama",
+  llm_model: "",
+  prompt_template: "",
+  output_format: "plain",
+  builtin: false,
+};
+
+interface ModeEditorProps {
+  mode: Mode;
+  // Key of the mode being edited; null when creating one
+  originalKey: string | null;
+  onSaved: (mode: Mode) => void;
+  onCancel: () => void;
+}
+
+// Defaults for a step added to the pipeline
+const NEW_STEPS: Record<PipelineStep["kind"], PipelineStep> = {
+  regex: { kind: "regex", pattern: "", replacement: "" },
+  cleanup: { kind: "cleanup" },
+  llm: { kind: "llm", provider: "ollama", model: "", prompt: "{{transcript}}" },
+};
+
+// Form for creating or editing a mode, validated by the backend as it is filled in
+export default function ModeEditor({ mode: initial, originalKey, onSaved, onCancel }: ModeEditorProps) {
+  const [mode, setMode] = useState<Mode>(initial);
+  const [issues, setIssues] = useState<ModeIssue[]>([]);
+  const [saving, setSaving] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+  const [sample, setSample] = useState("");
+  const [dryRun, setDryRun] = useState<DryRunResult | null>(null);
+  const [dryRunning, setDryRunning] = useState(false);
+  const [dryRunError, setDryRunError] = useState<string | null>(null);
+
+  useEffect(() => {
+    api.validateMode(mode).then(setIssues).catch(() => setIssues([]));
+  }, [mode]);
+
+  const processor: PostProcessor = mode.verbatim
+    ? "none"
+    : mode.post_processor ?? (mode.ai_processing ? "llm" : "none");
+
+  const steps = mode.steps ?? [];
+  const setStep = (index: number, step: PipelineStep) =>
+    setMode({ ...mode, steps: steps.map((s, i) => (i === index ? step : s)) });
+  const moveStep = (index: number, by: number) => {
+    const moved = [...steps];
+    [moved[index], moved[index + by]] = [moved[index + by], moved[index]];
+    setMode({ ...mode, steps: moved });
+  };
+
+  const issueFor = (field: string) =>
+    issues
+      .filter((issue) => issue.field === field || issue.field.startsWith(`${field}[`))
+      .map((issue) => issue.message)
+      .join("; ");
+
+  const handleSave = async () => {
+    setSaving(true);
+    setError(null);
+    try {
+      const saved = originalKey === null ? await api.createMode(mode) : await api.updateMode(originalKey, mode);
+      onSaved(saved);
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  const handleDryRun = async () => {
+    setDryRunning(true);
+    setDryRunError(null);
+    try {
+      setDryRun(await api.dryRunMode(mode, sample));
+    } catch (e) {
+      setDryRun(null);
+      setDryRunError(String(e));
+    } finally {
+      setDryRunning(false);
+    }
+  };
+
+  const field = (label: string, name: string, input: ReactNode) => (
+    <div>
+      <label className="block text-sm font-medium text-gray-300 mb-1">{label}</label>
+      {input}
+      {issueFor(name) && <p className="text-xs text-red-400 mt-1">{issueFor(name)}</p>}
+    </div>
+  );
+
+  const inputClass = "w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white text-sm";
+
+  return (
+    <section className="bg-gray-800 rounded-lg p-4 space-y-4">
+      <h2 className="text-lg font-medium text-white">
+        {originalKey === null ? "New Mode" : `Edit ${initial.name}`}
+      </h2>
+
+      <div className="grid grid-cols-2 gap-4">
+        {field(
+          "Key",
+          "key",
+          <input
+            type="text"
+            value={mode.key}
+            disabled={mode.builtin}
+            onChange={(e) => setMode({ ...mode, key: e.target.value })}
+            placeholder="my_mode"
+            className={inputClass}
+          />
+        )}
+        {field(
+          "Name",
+          "name",
+          <input
+            type="text"
+            value={mode.name}
+            onChange={(e) => setMode({ ...mode, name: e.target.value })}
+            className={inputClass}
+          />
+        )}
+      </div>
+
+      {field(
+        "Description",
+        "description",
+        <input
+          type="text"
+          value={mode.description}
+          onChange={(e) => setMode({ ...mode, description: e.target.value })}
+          className={inputClass}
+        />
+      )}
+
+      <div className="grid grid-cols-2 gap-4">
+        {field(
+          "Speech-to-text provider",
+          "stt_provider",
+          <select
+            value={mode.stt_provider}
+            onChange={(e) => setMode({ ...mode, stt_provider: e.target.value })}
+            className={inputClass}
+          >
+            <option value="whispercpp">Local (whisper.cpp)</option>
+            <option value="whisperserver">Whisper server</option>
+            <option value="openai">OpenAI</option>
+            <option value="groq">Groq</option>
+            <option value="deepgram">Deepgram</option>
+            <option value="wyoming">Wyoming</option>
+          </select>
+        )}
+        {field(
+          "Speech-to-text model",
+          "stt_model",
+          <input
+            type="text"
+            value={mode.stt_model}
+            onChange={(e) => setMode({ ...mode, stt_model: e.target.value })}
+            className={inputClass}
+          />
+        )}
+      </div>
+
+      {field(
+        "Processing",
+        "post_processor",
+        <select
+          value={processor}
+          disabled={mode.verbatim}
+          onChange={(e) => {
+            const value = e.target.value as PostProcessor;
+            setMode({ ...mode, post_processor: value, ai_processing: value === "llm" });
+          }}
+          className={inputClass}
+        >
+          <option value="none">None (transcript as is)</option>
+          <option value="rules">Local cleanup rules</option>
+          <option value="llm">AI (LLM prompt)</option>
+        </select>
+      )}
+
+      <div className="space-y-2">
+        <div className="flex items-center justify-between">
+          <label className="text-sm font-medium text-gray-300">Pipeline steps</label>
+          <select
+            value=""
+            disabled={mode.verbatim}
+            onChange={(e) => {
+              const kind = e.target.value as PipelineStep["kind"];
+              if (kind) setMode({ ...mode, steps: [...steps, NEW_STEPS[kind]] });
+            }}
+            className="bg-gray-700 border border-gray-600 rounded-lg px-2 py-1 text-white text-sm"
+          >
+            <option value="">Add step...</option>
+            <option value="regex">Regex replace</option>
+            <option value="cleanup">Local cleanup</option>
+            <option value="llm">LLM prompt</option>
+          </select>
+        </div>
+        {steps.length > 0 && (
+          <p className="text-xs text-gray-400">Steps run in order and replace the processing above.</p>
+        )}
+        {steps.map((step, index) => (
+          <div key={index} className="bg-gray-900 rounded-lg p-3 space-y-2">
+            <div className="flex items-center justify-between text-sm text-gray-300">
+              <span>
+                {index + 1}. {step.kind === "regex" ? "Regex replace" : step.kind === "cleanup" ? "Local cleanup" : "LLM prompt"}
+              </span>
+              <div className="flex gap-2">
+                <button disabled={index === 0} onClick={() => moveStep(index, -1)} className="disabled:opacity-30">
+                  ↑
+                </button>
+                <button
+                  disabled={index === steps.length - 1}
+                  onClick={() => moveStep(index, 1)}
+                  className="disabled:opacity-30"
+                >
+                  ↓
+                </button>
+                <button
+                  onClick={() => setMode({ ...mode, steps: steps.filter((_, i) => i !== index) })}
+                  className="text-red-400"
+                >
+                  Remove
+                </button>
+              </div>
+            </div>
+            {step.kind === "regex" && (
+              <div className="grid grid-cols-2 gap-2">
+                <input
+                  type="text"
+                  value={step.pattern}
+                  onChange={(e) => setStep(index, { ...step, pattern: e.target.value })}
+                  placeholder="(\d+) percent"
+                  className={`${inputClass} font-mono`}
+                />
+                <input
+                  type="text"
+                  value={step.replacement}
+                  onChange={(e) => setStep(index, { ...step, replacement: e.target.value })}
+                  placeholder="$1%"
+                  className={`${inputClass} font-mono`}
+                />
+              </div>
+            )}
+            {step.kind === "llm" && (
+              <>
+                <div className="grid grid-cols-2 gap-2">
+                  <select
+                    value={step.provider}
+                    onChange={(e) => setStep(index, { ...step, provider: e.target.value })}
+                    className={inputClass}
+                  >
+                    <option value="ollama">Ollama</option>
+                    <option value="openai">OpenAI</option>
+                    <option value="anthropic">Anthropic</option>
+                  </select>
+                  <input
+                    type="text"
+                    value={step.model}
+                    onChange={(e) => setStep(index, { ...step, model: e.target.value })}
+                    placeholder="Model"
+                    className={inputClass}
+                  />
+                </div>
+                <textarea
+                  value={step.prompt}
+                  onChange={(e) => setStep(index, { ...step, prompt: e.target.value })}
+                  rows={3}
+                  className={`${inputClass} font-mono`}
+                />
+              </>
+            )}
+            {issueFor(`steps[${index}]`) && <p className="text-xs text-red-400">{issueFor(`steps[${index}]`)}</p>}
+          </div>
+        ))}
+      </div>
+
+      {processor === "llm" && steps.length === 0 && (
+        <>
+          <div className="grid grid-cols-2 gap-4">
+            {field(
+              "LLM provider",
+              "llm_provider",
+              <select
+                value={mode.llm_provider}
+                onChange={(e) => setMode({ ...mode, llm_provider: e.target.value })}
+                className={inputClass}
+              >
+                <option value="ollama">Ollama</option>
+                <option value="openai">OpenAI</option>
+                <option value="anthropic">Anthropic</option>
+              </select>
+            )}
+            {field(
+              "LLM model",
+              "llm_model",
+              <input
+                type="text"
+                value={mode.llm_model}
+                onChange={(e) => setMode({ ...mode, llm_model: e.target.value })}
+                className={inputClass}
+              />
+            )}
+          </div>
+          {issueFor("llm_fallbacks") && (
+            <p className="text-xs text-red-400">Fallback LLMs: {issueFor("llm_fallbacks")}</p>
+          )}
+          {issueFor("compare_models") && (
+            <p className="text-xs text-red-400">Compared LLMs: {issueFor("compare_models")}</p>
+          )}
+          {field(
+            "Prompt",
+            "prompt_template",
+            <textarea
+              value={mode.prompt_template}
+              onChange={(e) => setMode({ ...mode, prompt_template: e.target.value })}
+              rows={6}
+              placeholder="Rewrite this as a short message: {{transcript}}"
+              className={`${inputClass} font-mono`}
+            />
+          )}
+        </>
+      )}
+
+      {field(
+        "Output command",
+        "output_command",
+        <>
+          <input
+            type="text"
+            value={mode.output_command ?? ""}
+            onChange={(e) => setMode({ ...mode, output_command: e.target.value || null })}
+            placeholder="pandoc -f markdown -t html"
+            className={`${inputClass} font-mono`}
+          />
+          <p className="text-xs text-gray-400 mt-1">
+            The final text is piped through this shell command before pasting. Needs shell commands to be allowed
+            in Settings. Long dictations pasted in segments while recording are not piped through it, and text
+            the command fails on is pasted unfiltered.
+          </p>
+        </>
+      )}
+
+      {field(
+        "History",
+        "history_storage",
+        <select
+          value={mode.history_storage ?? "full"}
+          onChange={(e) => setMode({ ...mode, history_storage: e.target.value as HistoryStorage })}
+          className={inputClass}
+        >
+          <option value="full">Keep everything</option>
+          <option value="redacted">Redact personal data</option>
+          <option value="summary">Keep only the AI output</option>
+          <option value="off">Keep nothing</option>
+        </select>
+      )}
+
+      {issueFor("json_schema") && <p className="text-xs text-red-400">JSON schema: {issueFor("json_schema")}</p>}
+      {issueFor("max_output_chars") && (
+        <p className="text-xs text-red-400">Length limit: {issueFor("max_output_chars")}</p>
+      )}
+      <div className="space-y-2">
+        <label className="block text-sm font-medium text-gray-300">Try it</label>
+        <textarea
+          value={sample}
+          onChange={(e) => setSample(e.target.value)}
+          rows={3}
+          placeholder="Type a transcript to run through this mode, without recording or pasting"
+          className={inputClass}
+        />
+        <button
+          onClick={handleDryRun}
+          disabled={dryRunning || !sample.trim() || issues.length > 0}
+          className="px-3 py-1.5 bg-gray-700 text-white rounded-lg text-sm hover:bg-gray-600 disabled:opacity-50 disabled:cursor-not-allowed"
+        >
+          {dryRunning ? "Running..." : "Dry Run"}
+        </button>
+        {dryRunError && <p className="text-xs text-red-400">{dryRunError}</p>}
+        {dryRun && (
+          <div className="space-y-2">
+            {dryRun.stages.map((stage, index) => (
+              <div key={index} className="bg-gray-900 rounded-lg p-3 text-sm">
+                <div className="flex justify-between text-xs text-gray-400 mb-1">
+                  <span>{stage.name}</span>
+                  <span>{stage.duration_ms} ms</span>
+                </div>
+                {stage.prompt && (
+                  <details className="text-xs text-gray-500 mb-1">
+                    <summary>Prompt</summary>
+                    <pre className="whitespace-pre-wrap">{stage.prompt}</pre>
+                  </details>
+                )}
+                {stage.error && <p className="text-xs text-red-400 mb-1">Skipped: {stage.error}</p>}
+                <p className="text-gray-200 whitespace-pre-wrap">{stage.text}</p>
+              </div>
+            ))}
+            <p className="text-xs text-gray-400">
+              {dryRun.truncated && "Shortened to fit the length limit. "}
+              {dryRun.prompt_tokens !== null &&
+                `${dryRun.prompt_tokens} + ${dryRun.completion_tokens} tokens`}
+              {dryRun.cost_usd !== null && dryRun.cost_usd > 0 && `, $${dryRun.cost_usd.toFixed(4)}`}
+            </p>
+          </div>
+        )}
+      </div>
+
+      {error && <p className="text-sm text-red-400">{error}</p>}
+
+      <div className="flex justify-end gap-2">
+        <button
+          onClick={onCancel}
+          className="px-4 py-2 bg-gray-700 text-white rounded-lg text-sm hover:bg-gray-600"
+        >
+          Cancel
+        </button>
+        <button
+          onClick={handleSave}
+          disabled={saving || issues.length > 0}
+          className="px-4 py-2 bg-blue-600 text-white rounded-lg text-sm hover:bg-blue-700 disabled:opacity-50 disabled:cursor-not-allowed"
+        >
+          {saving ? "Saving..." : "Save Mode"}
+        </button>
+      </div>
+    </section>
+  );
+}
@@ -1,27 +1,87 @@
 import { invoke } from "@tauri-apps/api/core";
 import type {
   Mode,
+  ModeIssue,
   AudioDevice,
+  AudioHost,
+  Calibration,
   HistoryItem,
+  PlaybackState,
+  BatchJob,
+  BatchTask,
+  BundleImport,
+  HistoryRevision,
+  SegmentAlternatives,
+  ErrorStage,
+  MonthlySpend,
+  HistoryStats,
+  PhraseSuggestion,
+  StatsRange,
+  PickRequest,
+  ModelRecommendation,
+  JournalEntry,
+  StyleProfile,
+  ConflictPolicy,
+  ImportReport,
+  TemplatePreview,
+  TemplateVars,
+  Pacing,
+  PacingPreset,
   Settings,
   RecordingStatusResponse,
   HistoryQuery,
   ExportFormat,
+  CaptureSource,
+  HealthStatus,
+  Capability,
+  FeatureFlag,
+  WhatsNew,
+  Degradation,
+  SttSelection,
+  SttSwapResult,
+  PasteInfo,
+  SampleClip,
+  ModeTestResult,
+  DryRunResult,
 } from "../types";
 
 // Recording
-export async function startRecording(): Promise<void> {
-  return invoke("start_recording");
+export async function startRecording(source?: CaptureSource): Promise<void> {
+  return invoke("start_recording", { source });
 }
 
 export async function stopRecording(): Promise<string> {
   return invoke("stop_recording");
 }
 
+export async function cancelRecording(): Promise<void> {
+  return invoke("cancel_recording");
+}
+
+export async function requestMicAccess(): Promise<void> {
+  return invoke("request_mic_access");
+}
+
+export async function addBookmark(): Promise<number> {
+  return invoke("add_bookmark");
+}
+
+export async function pauseRecording(): Promise<void> {
+  return invoke("pause_recording");
+}
+
+export async function resumeRecording(): Promise<void> {
+  return invoke("resume_recording");
+}
+
 export async function getRecordingStatus(): Promise<RecordingStatusResponse> {
   return invoke("get_recording_status");
 }
 
+export async function setIncognito(enabled: boolean): Promise<void> {
+  return invoke("set_incognito", { enabled });
+}
+
 // Modes
 export async function getModes(): Promise<Mode[]> {
   return invoke("get_modes");
@@ -31,13 +91,53 @@ export async function setActiveMode(modeKey: string): Promise<void> {
   return invoke("set_active_mode", { modeKey });
 }
 
+export async function validateMode(mode: Mode): Promise<ModeIssue[]> {
+  return invoke("validate_mode", { mode });
+}
+
+export async function createMode(mode: Mode): Promise<Mode> {
+  return invoke("create_mode", { mode });
+}
+
+// A custom mode may be given a new key
+export async function updateMode(key: string, mode: Mode): Promise<Mode> {
+  return invoke("update_mode", { key, mode });
+}
+
+export async function deleteMode(key: string): Promise<void> {
+  return invoke("delete_mode", { key });
+}
+
+// Without a new key, the copy is saved as <key>_copy
+export async function duplicateMode(key: string, newKey?: string): Promise<Mode> {
+  return invoke("duplicate_mode", { key, newKey: newKey ?? null });
+}
+
 export async function getActiveMode(): Promise<Mode | null> {
   return invoke("get_active_mode");
 }
 
 // Devices
-export async function getInputDevices(): Promise<AudioDevice[]> {
-  return invoke("get_input_devices");
+// Devices of the configured backend, or of one cpal host when given
+export async function getInputDevices(host?: AudioHost): Promise<AudioDevice[]> {
+  return invoke("get_input_devices", { host });
+}
+
+export async function getAudioBackends(): Promise<AudioBackend[]> {
+  return invoke("get_audio_backends");
+}
+
+export async function getAudioHosts(): Promise<AudioHost[]> {
+  return invoke("get_audio_hosts");
+}
+
+// Records for three seconds from the selected microphone; returns frame levels
+export async function recordCalibrationClip(): Promise<number[]> {
+  return invoke("record_calibration_clip");
+}
+
+export async function computeCalibration(silence: number[], speech: number[]): Promise<Calibration> {
+  return invoke("compute_calibration", { silence, speech });
 }
 
 export async function setInputDevice(deviceName: string): Promise<void> {
@@ -49,15 +149,57 @@ export async function transcribeFile(filePath: string): Promise<string> {
   return invoke("transcribe_file", { filePath });
 }
 
+// Switch STT provider between dictations (null restores each mode's own)
+export async function swapSttProvider(selection: SttSelection | null): Promise<SttSwapResult> {
+  return invoke("swap_stt_provider", { selection });
+}
+
+// Mode tests
+export async function listSamples(): Promise<SampleClip[]> {
+  return invoke("list_samples");
+}
+
+export async function testMode(modeKey: string, sampleId: string): Promise<ModeTestResult> {
+  return invoke("test_mode", { modeKey, sampleId });
+}
+
+export async function dryRunMode(mode: Mode, transcript: string): Promise<DryRunResult> {
+  return invoke("dry_run_mode", { mode, transcript });
+}
+
 // History
 export async function getHistory(query?: HistoryQuery): Promise<HistoryItem[]> {
   return invoke("get_history", { query });
 }
 
+export async function getFailedHistory(
+  stage?: ErrorStage,
+  limit?: number,
+  offset?: number
+): Promise<HistoryItem[]> {
+  return invoke("get_failed_history", { stage: stage ?? null, limit: limit ?? null, offset: offset ?? null });
+}
+
 export async function getHistoryItem(id: string): Promise<HistoryItem | null> {
   return invoke("get_history_item", { id });
 }
 
+export async function playHistoryAudio(id: string): Promise<PlaybackState> {
+  return invoke("play_history_audio", { id });
+}
+
+export async function pauseHistoryAudio(): Promise<PlaybackState | null> {
+  return invoke("pause_history_audio");
+}
+
+export async function seekHistoryAudio(ms: number): Promise<PlaybackState | null> {
+  return invoke("seek_history_audio", { ms });
+}
+
+export async function stopHistoryAudio(): Promise<void> {
+  return invoke("stop_history_audio");
+}
+
 export async function reprocessHistoryItem(
   id: string,
   modeKey: string
@@ -65,10 +207,149 @@ export async function reprocessHistoryItem(
   return invoke("reprocess_history_item", { id, modeKey });
 }
 
+export async function retranscribeHistoryItem(
+  id: string,
+  stt: SttSelection
+): Promise<HistoryItem> {
+  return invoke("retranscribe_history_item", { id, stt });
+}
+
+export async function getDownloadedModels(): Promise<string[]> {
+  return invoke("get_downloaded_models");
+}
+
+// Without ids, the batch covers the whole history
+export async function startBatch(task: BatchTask, ids?: string[]): Promise<BatchJob> {
+  return invoke("start_batch", { task, ids: ids ?? null });
+}
+
+export async function getBatches(): Promise<BatchJob[]> {
+  return invoke("get_batches");
+}
+
+export async function pauseBatch(id: string): Promise<BatchJob> {
+  return invoke("pause_batch", { id });
+}
+
+export async function resumeBatch(id: string): Promise<BatchJob> {
+  return invoke("resume_batch", { id });
+}
+
+export async function cancelBatch(id: string): Promise<BatchJob> {
+  return invoke("cancel_batch", { id });
+}
+
+export async function deleteBatch(id: string): Promise<void> {
+  return invoke("delete_batch", { id });
+}
+
 export async function deleteHistoryItem(id: string): Promise<void> {
   return invoke("delete_history_item", { id });
 }
 
+export async function editHistoryItem(id: string, output: string): Promise<HistoryItem> {
+  return invoke("edit_history_item", { id, output });
+}
+
+export async function getRevisions(id: string): Promise<HistoryRevision[]> {
+  return invoke("get_revisions", { id });
+}
+
+export async function getHistoryAlternatives(id: string): Promise<SegmentAlternatives[]> {
+  return invoke("get_history_alternatives", { id });
+}
+
+export async function restoreHistoryItem(id: string): Promise<void> {
+  return invoke("restore_history_item", { id });
+}
+
+export async function getTrash(limit?: number, offset?: number): Promise<HistoryItem[]> {
+  return invoke("get_trash", { limit: limit ?? null, offset: offset ?? null });
+}
+
+// Permanently delete one trashed item, or the whole trash when no id is given
+export async function purgeHistory(id?: string): Promise<void> {
+  return invoke("purge_history", { id: id ?? null });
+}
+
+export async function getMonthlySpend(): Promise<MonthlySpend[]> {
+  return invoke("get_monthly_spend");
+}
+
+export async function getHistoryStats(range?: StatsRange): Promise<HistoryStats> {
+  return invoke("get_history_stats", { range: range ?? null });
+}
+
+export async function suggestPhrases(prefix: string, limit?: number): Promise<PhraseSuggestion[]> {
+  return invoke("suggest_phrases", { prefix, limit: limit ?? null });
+}
+
+export async function isHistoryEncryptionAvailable(): Promise<boolean> {
+  return invoke("is_history_encryption_available");
+}
+
+export async function rotateHistoryKey(): Promise<void> {
+  return invoke("rotate_history_key");
+}
+
+// Multi-take picker
+export async function getPickRequest(): Promise<PickRequest | null> {
+  return invoke("get_pick_request");
+}
+
+export async function chooseCandidate(index?: number): Promise<void> {
+  return invoke("choose_candidate", { index: index ?? null });
+}
+
+export async function getModelRecommendation(modeKey?: string): Promise<ModelRecommendation | null> {
+  return invoke("get_model_recommendation", { modeKey: modeKey ?? null });
+}
+
+export async function previewPromptTemplate(
+  template: string,
+  vars?: TemplateVars
+): Promise<TemplatePreview> {
+  return invoke("preview_prompt_template", { template, vars: vars ?? null });
+}
+
+export async function getPacingPreset(preset: PacingPreset): Promise<Pacing> {
+  return invoke("get_pacing_preset", { preset });
+}
+
+export async function getJournalEntry(date?: string): Promise<JournalEntry | null> {
+  return invoke("get_journal_entry", { date: date ?? null });
+}
+
+export async function getStyleProfile(): Promise<StyleProfile | null> {
+  return invoke("get_style_profile");
+}
+
+export async function refreshStyleProfile(): Promise<StyleProfile | null> {
+  return invoke("refresh_style_profile");
+}
+
+export async function exportDictionary(path: string): Promise<void> {
+  return invoke("export_dictionary", { path });
+}
+
+// With dryRun the merge is only reported, not saved
+export async function importDictionary(
+  path: string,
+  policy: ConflictPolicy,
+  dryRun: boolean
+): Promise<ImportReport> {
+  return invoke("import_dictionary", { path, policy, dryRun });
+}
+
+// Settings and modes in one file, without API keys or tokens
+export async function exportSettings(path: string): Promise<void> {
+  return invoke("export_settings", { path });
+}
+
+export async function importSettings(path: string): Promise<BundleImport> {
+  return invoke("import_settings", { path });
+}
+
 export async function exportHistoryItem(
   id: string,
   format: ExportFormat
@@ -85,6 +366,38 @@ export async function updateSettings(settings: Settings): Promise<void> {
   return invoke("update_settings", { settings });
 }
 
+export async function isReadOnly(): Promise<boolean> {
+  return invoke("is_read_only");
+}
+
+export async function getHealth(): Promise<HealthStatus> {
+  return invoke("get_health");
+}
+
+export async function getCapabilities(): Promise<Capability[]> {
+  return invoke("get_capabilities");
+}
+
+export async function getFeatureFlags(): Promise<FeatureFlag[]> {
+  return invoke("get_feature_flags");
+}
+
+export async function getWhatsNew(): Promise<WhatsNew> {
+  return invoke("get_whats_new");
+}
+
+export async function dismissWhatsNew(): Promise<void> {
+  return invoke("dismiss_whats_new");
+}
+
+export async function getDegradations(): Promise<Degradation[]> {
+  return invoke("get_degradations");
+}
+
+export async function getPasteInfo(): Promise<PasteInfo> {
+  return invoke("get_paste_info");
+}
+
 // API Keys
 export async function saveApiKey(provider: string, key: string): Promise<void> {
   return invoke("save_api_key", { provider, key });
@@ -0,0 +1,69 @@
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { chooseCandidate, getPickRequest } from "../lib/api";
+import type { PickRequest } from "../types";
+
+export default function CandidatePicker() {
+  const [request, setRequest] = useState<PickRequest | null>(null);
+
+  useEffect(() => {
+    // The window may open after the candidates were emitted
+    getPickRequest().then(setRequest).catch(() => setRequest(null));
+
+    const unlisten = listen<PickRequest>("picker-candidates", (event) => {
+      setRequest(event.payload);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  useEffect(() => {
+    if (!request) return;
+
+    const onKey = (event: KeyboardEvent) => {
+      const index = Number(event.key) - 1;
+      if (index >= 0 && index < request.candidates.length) {
+        choose(index);
+      } else if (event.key === "Escape") {
+        choose(undefined);
+      }
+    };
+
+    window.addEventListener("keydown", onKey);
+    return () => window.removeEventListener("keydown", onKey);
+  }, [request]);
+
+  const choose = (index: number | undefined) => {
+    setRequest(null);
+    chooseCandidate(index).catch((e) => console.error("Failed to choose candidate:", e));
+  };
+
+  if (!request) {
+    return <div className="h-screen bg-gray-900" />;
+  }
+
+  return (
+    <div className="h-screen bg-gray-900 text-white p-4 flex flex-col gap-3 overflow-y-auto">
+      <p className="text-sm text-gray-400">
+        Press 1–{request.candidates.length} to insert an output, Esc to keep the first
+      </p>
+      {request.candidates.map((candidate, i) => (
+        <button
+          key={i}
+          onClick={() => choose(i)}
+          className="text-left bg-gray-800 hover:bg-gray-700 rounded-lg p-3 focus:outline-none focus:ring-2 focus:ring-blue-500"
+        >
+          <div className="flex items-center gap-2 mb-1 text-xs text-gray-400">
+            <span className="bg-gray-700 text-white rounded px-1.5 py-0.5 font-mono">{i + 1}</span>
+            <span>
+              {candidate.provider} / {candidate.model}
+            </span>
+          </div>
+          <p className="text-sm whitespace-pre-wrap">{candidate.text}</p>
+        </button>
+      ))}
+    </div>
+  );
+}
@@ -1,9 +1,12 @@
-import { useEffect, useState } from "react";
+import { useCallback, useEffect, useState } from "react";
 import { format } from "date-fns";
+import { open } from "@tauri-apps/plugin-shell";
+import { listen } from "@tauri-apps/api/event";
 import { useAppStore } from "../stores/appStore";
 import * as api from "../lib/api";
-import type { ExportFormat } from "../types";
+import type { ExportFormat, HistoryRevision, PlaybackState, SegmentAlternatives } from "../types";
 import clsx from "clsx";
+import BatchPanel from "../components/BatchPanel";
 
 export default function HistoryPage() {
   const {
@@ -13,16 +16,89 @@ export default function HistoryPage() {
     loadHistory,
     selectHistoryItem,
     reprocessHistoryItem,
+    retranscribeHistoryItem,
+    editHistoryItem,
     deleteHistoryItem,
   } = useAppStore();
 
   const [searchQuery, setSearchQuery] = useState("");
   const [reprocessMode, setReprocessMode] = useState("");
+  // Local whisper models to re-transcribe with, largest first
+  const [localModels, setLocalModels] = useState<string[]>([]);
+  const [retranscribeModel, setRetranscribeModel] = useState("");
+  const [editedOutput, setEditedOutput] = useState<string | null>(null);
+  const [revisions, setRevisions] = useState<HistoryRevision[]>([]);
+  const [alternatives, setAlternatives] = useState<SegmentAlternatives[]>([]);
+  const [playback, setPlayback] = useState<PlaybackState | null>(null);
+  const [playbackError, setPlaybackError] = useState<string | null>(null);
 
   useEffect(() => {
     loadHistory();
   }, [loadHistory]);
 
+  const refreshHistory = useCallback(() => {
+    loadHistory(searchQuery || undefined);
+  }, [loadHistory, searchQuery]);
+
+  // Files from the watch folder arrive while the page is open
+  useEffect(() => {
+    const unlisten = listen("watch-folder-transcribed", refreshHistory);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [refreshHistory]);
+
+  useEffect(() => {
+    api.getDownloadedModels().then(setLocalModels).catch(() => setLocalModels([]));
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen<PlaybackState>("playback-state", (event) => {
+      setPlayback(event.payload);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+      api.stopHistoryAudio().catch(() => {});
+    };
+  }, []);
+
+  useEffect(() => {
+    setEditedOutput(null);
+    if (!selectedHistoryItem) {
+      setRevisions([]);
+      setAlternatives([]);
+      return;
+    }
+    api.getRevisions(selectedHistoryItem.id).then(setRevisions).catch(() => setRevisions([]));
+    api
+      .getHistoryAlternatives(selectedHistoryItem.id)
+      .then(setAlternatives)
+      .catch(() => setAlternatives([]));
+  }, [selectedHistoryItem]);
+
+  // Playback state of the selected item, if it is the one loaded
+  const selectedPlayback =
+    playback && playback.id === selectedHistoryItem?.id ? playback : null;
+
+  const handlePlayPause = async () => {
+    if (!selectedHistoryItem) return;
+    setPlaybackError(null);
+    try {
+      if (selectedPlayback?.playing) {
+        setPlayback(await api.pauseHistoryAudio());
+      } else {
+        setPlayback(await api.playHistoryAudio(selectedHistoryItem.id));
+      }
+    } catch (e) {
+      setPlaybackError(String(e));
+    }
+  };
+
+  const handleSeek = async (ms: number) => {
+    const state = await api.seekHistoryAudio(ms);
+    if (state) setPlayback(state);
+  };
+
   const handleSearch = () => {
     loadHistory(searchQuery || undefined);
   };
@@ -50,6 +126,32 @@ export default function HistoryPage() {
     setReprocessMode("");
   };
 
+  const handleRetranscribe = async () => {
+    if (!selectedHistoryItem || !retranscribeModel) return;
+    await retranscribeHistoryItem(selectedHistoryItem.id, {
+      provider: "whispercpp",
+      model: retranscribeModel,
+    });
+    setRetranscribeModel("");
+  };
+
+  const handleSaveEdit = async () => {
+    if (!selectedHistoryItem || editedOutput === null) return;
+    await editHistoryItem(selectedHistoryItem.id, editedOutput);
+  };
+
+  // Swap a segment of the output for another reading, kept as an edit
+  const handleCorrection = async (segment: string, replacement: string) => {
+    if (!selectedHistoryItem) return;
+    const output = selectedHistoryItem.output_final.replace(segment, replacement);
+    await editHistoryItem(selectedHistoryItem.id, output);
+  };
+
+  // Segments still in the output as transcribed; edited or rewritten ones have nothing to offer
+  const suggestions = selectedHistoryItem
+    ? alternatives.filter((segment) => selectedHistoryItem.output_final.includes(segment.text))
+    : [];
+
   const handleDelete = async () => {
     if (!selectedHistoryItem) return;
     if (confirm("Are you sure you want to delete this item?")) {
@@ -68,6 +170,8 @@ export default function HistoryPage() {
     <div className="flex gap-4 h-[calc(100vh-200px)]">
       {/* History list */}
       <div className="w-1/3 flex flex-col">
+        <BatchPanel modes={modes} localModels={localModels} onProgress={refreshHistory} />
+
         {/* Search */}
         <div className="flex gap-2 mb-4">
           <input
@@ -117,8 +221,31 @@ export default function HistoryPage() {
                   <span className="text-xs px-2 py-0.5 bg-gray-700 rounded">
                     {item.mode_key}
                   </span>
+                  {item.verbatim && (
+                    <span className="text-xs text-amber-400" title="Dictated word for word, unprocessed">
+                      verbatim
+                    </span>
+                  )}
+                  {item.summary_only && (
+                    <span className="text-xs text-gray-400" title="Transcript and recording were destroyed">
+                      summary only
+                    </span>
+                  )}
+                  {item.clipped && (
+                    <span className="text-xs text-orange-400" title="The recording was too loud">
+                      clipped
+                    </span>
+                  )}
+                  {item.degraded && (
+                    <span className="text-xs text-yellow-400" title="Processed locally while offline">
+                      offline
+                    </span>
+                  )}
                   {item.error && (
-                    <span className="text-xs text-red-400">Error</span>
+                    <span className="text-xs text-red-400" title={item.error.message}>
+                      {item.error.stage} error
+                      {item.error.retries ? ` after ${item.error.retries} retries` : ""}
+                    </span>
                   )}
                 </div>
               </button>
@@ -147,6 +274,15 @@ export default function HistoryPage() {
                   {selectedHistoryItem.llm_provider &&
                     ` • LLM: ${selectedHistoryItem.llm_provider}/${selectedHistoryItem.llm_model}`}
                 </p>
+                {selectedHistoryItem.source_url && (
+                  <button
+                    onClick={() => open(selectedHistoryItem.source_url!)}
+                    className="text-xs text-blue-400 hover:text-blue-300 truncate max-w-md block"
+                    title={selectedHistoryItem.source_url}
+                  >
+                    {selectedHistoryItem.source_url}
+                  </button>
+                )}
               </div>
               <button
                 onClick={handleDelete}
@@ -156,6 +292,34 @@ export default function HistoryPage() {
               </button>
             </div>
 
+            {/* Recording */}
+            {selectedHistoryItem.audio_path && (
+              <div className="flex items-center gap-3 mb-4">
+                <button
+                  onClick={handlePlayPause}
+                  className="px-3 py-1 bg-gray-700 hover:bg-gray-600 rounded text-sm text-white w-16"
+                >
+                  {selectedPlayback?.playing ? "Pause" : "Play"}
+                </button>
+                <input
+                  type="range"
+                  min={0}
+                  max={selectedPlayback?.duration_ms ?? selectedHistoryItem.duration_ms}
+                  value={selectedPlayback && !selectedPlayback.finished ? selectedPlayback.position_ms : 0}
+                  onChange={(e) => handleSeek(Number(e.target.value))}
+                  disabled={!selectedPlayback || selectedPlayback.finished}
+                  className="flex-1"
+                />
+                <span className="text-xs text-gray-400 w-20 text-right">
+                  {formatDuration(
+                    selectedPlayback && !selectedPlayback.finished ? selectedPlayback.position_ms : 0
+                  )}{" "}
+                  / {formatDuration(selectedPlayback?.duration_ms ?? selectedHistoryItem.duration_ms)}
+                </span>
+                {playbackError && <span className="text-xs text-red-400">{playbackError}</span>}
+              </div>
+            )}
+
             {/* Content */}
             <div className="flex-1 overflow-auto space-y-4">
               {/* Raw transcript */}
@@ -164,7 +328,9 @@ export default function HistoryPage() {
                   Raw Transcript
                 </h3>
                 <div className="bg-gray-900 rounded p-3 text-sm text-gray-300 whitespace-pre-wrap">
-                  {selectedHistoryItem.transcript_raw}
+                  {selectedHistoryItem.summary_only
+                    ? "Discarded: this mode keeps only the summary."
+                    : selectedHistoryItem.transcript_raw}
                 </div>
               </div>
 
@@ -174,21 +340,157 @@ export default function HistoryPage() {
                   <h3 className="text-sm font-medium text-gray-400">
                     Final Output
                   </h3>
-                  <button
-                    onClick={() =>
-                      navigator.clipboard.writeText(
-                        selectedHistoryItem.output_final
-                      )
-                    }
-                    className="text-xs text-blue-400 hover:text-blue-300"
-                  >
-                    Copy
-                  </button>
-                </div>
-                <div className="bg-gray-900 rounded p-3 text-sm text-white whitespace-pre-wrap">
-                  {selectedHistoryItem.output_final}
+                  <div className="flex gap-3">
+                    {editedOutput === null ? (
+                      <button
+                        onClick={() => setEditedOutput(selectedHistoryItem.output_final)}
+                        className="text-xs text-blue-400 hover:text-blue-300"
+                      >
+                        Edit
+                      </button>
+                    ) : (
+                      <>
+                        <button
+                          onClick={handleSaveEdit}
+                          className="text-xs text-blue-400 hover:text-blue-300"
+                        >
+                          Save
+                        </button>
+                        <button
+                          onClick={() => setEditedOutput(null)}
+                          className="text-xs text-gray-400 hover:text-gray-300"
+                        >
+                          Cancel
+                        </button>
+                      </>
+                    )}
+                    <button
+                      onClick={() =>
+                        navigator.clipboard.writeText(
+                          selectedHistoryItem.output_final
+                        )
+                      }
+                      className="text-xs text-blue-400 hover:text-blue-300"
+                    >
+                      Copy
+                    </button>
+                  </div>
                 </div>
+                {editedOutput === null ? (
+                  <div className="bg-gray-900 rounded p-3 text-sm text-white whitespace-pre-wrap">
+                    {selectedHistoryItem.output_final}
+                  </div>
+                ) : (
+                  <textarea
+                    value={editedOutput}
+                    onChange={(e) => setEditedOutput(e.target.value)}
+                    rows={6}
+                    className="w-full bg-gray-900 border border-gray-700 rounded p-3 text-sm text-white focus:outline-none focus:ring-2 focus:ring-blue-500"
+                  />
+                )}
               </div>
+
+              {/* Other readings of uncertain segments */}
+              {suggestions.length > 0 && editedOutput === null && (
+                <div>
+                  <h3 className="text-sm font-medium text-gray-400 mb-2">
+                    Did You Mean
+                  </h3>
+                  <div className="space-y-2">
+                    {suggestions.map((segment) => (
+                      <div
+                        key={segment.text}
+                        className="bg-gray-900 rounded p-3 text-sm text-gray-300"
+                      >
+                        <p className="text-xs text-gray-500 mb-2">"{segment.text}"</p>
+                        <div className="flex flex-wrap gap-2">
+                          {segment.alternatives.map((alternative) => (
+                            <button
+                              key={alternative}
+                              onClick={() => handleCorrection(segment.text, alternative)}
+                              className="px-2 py-1 bg-gray-700 hover:bg-gray-600 rounded text-white text-xs"
+                            >
+                              {alternative}
+                            </button>
+                          ))}
+                        </div>
+                      </div>
+                    ))}
+                  </div>
+                </div>
+              )}
+
+              {/* Earlier outputs */}
+              {revisions.length > 0 && (
+                <div>
+                  <h3 className="text-sm font-medium text-gray-400 mb-2">
+                    Earlier Versions
+                  </h3>
+                  <div className="space-y-2">
+                    {revisions.map((revision) => (
+                      <div
+                        key={revision.revision}
+                        className="bg-gray-900 rounded p-3 text-sm text-gray-300"
+                      >
+                        <p className="text-xs text-gray-500 mb-1">
+                          {revision.mode_key} • replaced by {revision.replaced_by} on{" "}
+                          {format(new Date(revision.replaced_at), "MMM d, h:mm a")}
+                        </p>
+                        <p className="whitespace-pre-wrap">{revision.output_final}</p>
+                        {revision.transcript_raw !== null && (
+                          <p className="text-xs text-gray-500 mt-1 whitespace-pre-wrap">
+                            Transcript ({revision.stt_provider}/{revision.stt_model}):{" "}
+                            {revision.transcript_raw}
+                          </p>
+                        )}
+                      </div>
+                    ))}
+                  </div>
+                </div>
+              )}
+
+              {/* Latency waterfall */}
+              {selectedHistoryItem.latency &&
+                selectedHistoryItem.latency.length > 0 && (
+                  <div>
+                    <h3 className="text-sm font-medium text-gray-400 mb-2">
+                      Timing
+                    </h3>
+                    <div className="bg-gray-900 rounded p-3 space-y-1">
+                      {selectedHistoryItem.latency.map((span, i) => {
+                        const total = Math.max(
+                          selectedHistoryItem.latency![0].duration_ms,
+                          1
+                        );
+                        return (
+                          <div
+                            key={i}
+                            className="flex items-center gap-2 text-xs text-gray-300"
+                          >
+                            <span
+                              className="w-28 truncate"
+                              style={{ paddingLeft: span.depth * 8 }}
+                            >
+                              {span.name}
+                            </span>
+                            <div className="flex-1 relative h-2">
+                              <div
+                                className="absolute h-2 bg-blue-500 rounded"
+                                style={{
+                                  left: `${(span.start_ms / total) * 100}%`,
+                                  width: `${Math.max((span.duration_ms / total) * 100, 0.5)}%`,
+                                }}
+                              />
+                            </div>
+                            <span className="w-16 text-right text-gray-400">
+                              {span.duration_ms} ms
+                            </span>
+                          </div>
+                        );
+                      })}
+                    </div>
+                  </div>
+                )}
             </div>
 
             {/* Actions */}
@@ -209,13 +511,38 @@ export default function HistoryPage() {
                 </select>
                 <button
                   onClick={handleReprocess}
-                  disabled={!reprocessMode}
+                  disabled={!reprocessMode || selectedHistoryItem.summary_only}
                   className="px-3 py-1 bg-blue-600 text-white rounded text-sm hover:bg-blue-700 disabled:opacity-50 disabled:cursor-not-allowed"
                 >
                   Reprocess
                 </button>
               </div>
 
+              {/* Re-transcribe */}
+              {selectedHistoryItem.audio_path && localModels.length > 0 && (
+                <div className="flex items-center gap-2">
+                  <select
+                    value={retranscribeModel}
+                    onChange={(e) => setRetranscribeModel(e.target.value)}
+                    className="bg-gray-700 border border-gray-600 rounded px-2 py-1 text-sm text-white"
+                  >
+                    <option value="">Re-transcribe with...</option>
+                    {localModels.map((model) => (
+                      <option key={model} value={model}>
+                        {model}
+                      </option>
+                    ))}
+                  </select>
+                  <button
+                    onClick={handleRetranscribe}
+                    disabled={!retranscribeModel}
+                    className="px-3 py-1 bg-blue-600 text-white rounded text-sm hover:bg-blue-700 disabled:opacity-50 disabled:cursor-not-allowed"
+                  >
+                    Re-transcribe
+                  </button>
+                </div>
+              )}
+
               {/* Export */}
               <div className="flex items-center gap-2">
                 <span className="text-sm text-gray-400">Export:</span>
@@ -1,11 +1,25 @@
 import { useAppStore } from "../stores/appStore";
+import * as api from "../lib/api";
 import clsx from "clsx";
 
 export default function HomePage() {
   const {
     status,
     isRecording,
+    isPaused,
+    togglePause,
+    timeWarning,
     lastOutput,
+    preview,
+    degraded,
+    micConflict,
+    micBlocked,
+    deviceChange,
+    micDiagnostic,
+    configIssues,
+    clipping,
+    whatsNew,
+    dismissWhatsNew,
     activeMode,
     modes,
     startRecording,
@@ -23,6 +37,36 @@ export default function HomePage() {
 
   return (
     <div className="max-w-2xl mx-auto space-y-6">
+      {/* Release notes after an update */}
+      {whatsNew && whatsNew.releases.length > 0 && (
+        <div className="bg-blue-900/40 border border-blue-700 rounded-lg p-3 text-sm text-blue-100 space-y-2">
+          <div className="flex items-center justify-between">
+            <p className="font-medium">
+              What's new in {whatsNew.version}
+              {whatsNew.channel !== "stable" && ` (${whatsNew.channel})`}
+            </p>
+            <button
+              onClick={dismissWhatsNew}
+              className="text-xs text-blue-300 hover:text-blue-200"
+            >
+              Dismiss
+            </button>
+          </div>
+          {whatsNew.releases.map((release) => (
+            <div key={release.version}>
+              {whatsNew.releases.length > 1 && (
+                <p className="text-xs text-blue-300">{release.version}</p>
+              )}
+              <ul className="list-disc list-inside">
+                {release.notes.map((note) => (
+                  <li key={note}>{note}</li>
+                ))}
+              </ul>
+            </div>
+          ))}
+        </div>
+      )}
+
       {/* Recording button */}
       <div className="text-center">
         <button
@@ -31,7 +75,9 @@ export default function HomePage() {
           className={clsx(
             "w-32 h-32 rounded-full text-white font-semibold text-lg transition-all",
             "focus:outline-none focus:ring-4",
-            isRecording
+            isRecording && isPaused
+              ? "bg-red-800 hover:bg-red-700 focus:ring-red-500/50"
+              : isRecording
               ? "bg-red-600 hover:bg-red-700 focus:ring-red-500/50 animate-pulse"
               : status === "processing"
               ? "bg-blue-600 cursor-not-allowed"
@@ -49,10 +95,20 @@ export default function HomePage() {
             : "Record"}
         </button>
         <p className="mt-4 text-gray-400 text-sm">
-          {isRecording
+          {isRecording && isPaused
+            ? "Paused — nothing is recorded until you resume"
+            : isRecording
             ? "Click to stop recording"
             : "Click to start recording, or use the tray icon"}
         </p>
+        {isRecording && (
+          <button
+            onClick={togglePause}
+            className="mt-2 px-4 py-1 bg-gray-700 hover:bg-gray-600 rounded-lg text-sm text-gray-200"
+          >
+            {isPaused ? "Resume" : "Pause"}
+          </button>
+        )}
       </div>
 
       {/* Mode selector */}
@@ -73,13 +129,121 @@ export default function HomePage() {
         </select>
         {activeMode && (
           <p className="mt-2 text-sm text-gray-400">
-            {activeMode.ai_processing
+            {activeMode.verbatim
+              ? "Strict verbatim (no AI, cleanup or replacements)"
+              : activeMode.post_processor === "rules"
+              ? "Local rule-based cleanup (no AI processing)"
+              : activeMode.post_processor !== "none" && activeMode.ai_processing
               ? `AI processing enabled (${activeMode.llm_provider})`
               : "Direct transcription (no AI processing)"}
           </p>
         )}
       </div>
 
+      {/* The recording is about to reach its maximum length */}
+      {timeWarning !== null && isRecording && (
+        <div className="bg-yellow-900/40 border border-yellow-700 rounded-lg p-3 text-sm text-yellow-200">
+          Maximum length in {Math.ceil(timeWarning / 1000)} seconds — the recording will then
+          stop and be transcribed
+        </div>
+      )}
+
+      {/* Another app took the microphone while recording */}
+      {micConflict && isRecording && (
+        <div className="bg-orange-900/40 border border-orange-700 rounded-lg p-3 text-sm text-orange-200">
+          {micConflict.apps.join(", ")} {micConflict.apps.length === 1 ? "is" : "are"} using the microphone
+          {micConflict.action === "pause" && " — recording paused until it is released"}
+        </div>
+      )}
+
+      {/* An input device went away while recording */}
+      {deviceChange && (
+        <div className="bg-orange-900/40 border border-orange-700 rounded-lg p-3 text-sm text-orange-200">
+          {deviceChange.kind === "fell_back" &&
+            `${deviceChange.lost} disconnected — recording continues on ${deviceChange.now}`}
+          {deviceChange.kind === "dropped" &&
+            `${deviceChange.device} stopped — recording continues from the microphone`}
+          {deviceChange.kind === "lost" &&
+            `${deviceChange.device} disconnected — the recording was stopped`}
+        </div>
+      )}
+
+      {/* The microphone is held by another app, or the wrong source is recorded */}
+      {micDiagnostic && (
+        <div className="bg-orange-900/40 border border-orange-700 rounded-lg p-3 text-sm text-orange-200">
+          {micDiagnostic.kind === "busy" &&
+            `${["", "default"].includes(micDiagnostic.device) ? "The microphone" : micDiagnostic.device} is busy` +
+              (micDiagnostic.apps.length > 0 ? `: used by ${micDiagnostic.apps.join(", ")}` : "")}
+          {micDiagnostic.kind === "wrong_source" &&
+            `Recording from ${micDiagnostic.actual} instead of ${micDiagnostic.expected} — change WhisperTray's input in your sound settings`}
+        </div>
+      )}
+
+      {/* Settings or mode files edited on disk have problems */}
+      {configIssues.length > 0 && (
+        <div className="bg-orange-900/40 border border-orange-700 rounded-lg p-3 text-sm text-orange-200 space-y-1">
+          <p>Problems in config files edited outside the app:</p>
+          <ul className="list-disc list-inside">
+            {configIssues.map((issue, i) => (
+              <li key={i}>
+                <span className="font-mono">{issue.file}</span>: {issue.message}
+              </li>
+            ))}
+          </ul>
+        </div>
+      )}
+
+      {/* The last recording was too loud */}
+      {clipping && !isRecording && (
+        <div className="bg-orange-900/40 border border-orange-700 rounded-lg p-3 text-sm text-orange-200">
+          {clipping.percent.toFixed(1)}% of the recording was clipped — lower the input gain in
+          Settings for better transcripts
+        </div>
+      )}
+
+      {/* The microphone delivered only silence at the start of the recording */}
+      {micBlocked && (
+        <div className="bg-red-900/40 border border-red-700 rounded-lg p-3 text-sm text-red-200 space-y-2">
+          <p>
+            {micBlocked.sandbox ? "Microphone access may be blocked" : "No sound from the microphone"}.
+            {micBlocked.requested && " Access was requested, answer the dialog and record again."}
+          </p>
+          <p className="text-xs text-red-300">{micBlocked.hint}</p>
+          {micBlocked.sandbox === "flatpak" && (
+            <button
+              onClick={() => api.requestMicAccess().catch(console.error)}
+              className="px-3 py-1 bg-red-800 hover:bg-red-700 rounded text-white text-xs"
+            >
+              Request access
+            </button>
+          )}
+        </div>
+      )}
+
+      {/* Local fallbacks used while offline */}
+      {degraded && (
+        <div className="bg-yellow-900/40 border border-yellow-700 rounded-lg p-3 text-sm text-yellow-200">
+          Offline: dictated with{" "}
+          {[
+            degraded.stt && `whisper.cpp ${degraded.stt.model}`,
+            degraded.llm && `Ollama ${degraded.llm.model}`,
+            degraded.rules && "rule-based cleanup",
+          ]
+            .filter(Boolean)
+            .join(", ")}
+        </div>
+      )}
+
+      {/* Output being generated */}
+      {status === "processing" && preview && (
+        <div className="bg-gray-800 rounded-lg p-4">
+          <h3 className="text-sm font-medium text-gray-300 mb-2">Rewriting...</h3>
+          <div className="bg-gray-900 rounded p-3 text-sm text-gray-100 whitespace-pre-wrap max-h-64 overflow-auto">
+            {preview}
+          </div>
+        </div>
+      )}
+
       {/* Last output */}
       {lastOutput && (
         <div className="bg-gray-800 rounded-lg p-4">
@@ -1,33 +1,82 @@
+import { useState } from "react";
 import { useAppStore } from "../stores/appStore";
+import * as api from "../lib/api";
+import type { Mode } from "../types";
+import ModeEditor, { NEW_MODE } from "../components/ModeEditor";
 import clsx from "clsx";
 
 export default function ModesPage() {
-  const { modes, activeMode, setActiveMode } = useAppStore();
+  const { modes, activeMode, setActiveMode, loadModes } = useAppStore();
+  // Mode in the editor, with its key before editing (null for a new mode)
+  const [editing, setEditing] = useState<{ mode: Mode; originalKey: string | null } | null>(null);
+  const [error, setError] = useState<string | null>(null);
 
   const modesList = Object.values(modes);
   const builtinModes = modesList.filter((m) => m.builtin);
   const customModes = modesList.filter((m) => !m.builtin);
 
+  const run = async (action: () => Promise<unknown>) => {
+    setError(null);
+    try {
+      await action();
+      await loadModes();
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  const handleDuplicate = (mode: Mode) =>
+    run(async () => {
+      const copy = await api.duplicateMode(mode.key);
+      setEditing({ mode: copy, originalKey: copy.key });
+    });
+
+  const handleDelete = (mode: Mode) => {
+    if (!confirm(`Delete the mode "${mode.name}"?`)) return;
+    run(() => api.deleteMode(mode.key));
+  };
+
+  const cardProps = (mode: Mode) => ({
+    mode,
+    isActive: activeMode?.key === mode.key,
+    onActivate: () => setActiveMode(mode.key),
+    onEdit: () => setEditing({ mode, originalKey: mode.key }),
+    onDuplicate: () => handleDuplicate(mode),
+  });
+
   return (
     <div className="max-w-4xl mx-auto space-y-6">
       <div className="flex items-center justify-between">
         <h1 className="text-2xl font-semibold text-white">Modes</h1>
-        <button className="px-4 py-2 bg-blue-600 text-white rounded-lg text-sm hover:bg-blue-700">
+        <button
+          onClick={() => setEditing({ mode: NEW_MODE, originalKey: null })}
+          className="px-4 py-2 bg-blue-600 text-white rounded-lg text-sm hover:bg-blue-700"
+        >
           + Create Mode
         </button>
       </div>
 
+      {error && <p className="text-sm text-red-400">{error}</p>}
+
+      {editing && (
+        <ModeEditor
+          key={editing.originalKey ?? "new"}
+          mode={editing.mode}
+          originalKey={editing.originalKey}
+          onSaved={() => {
+            setEditing(null);
+            loadModes();
+          }}
+          onCancel={() => setEditing(null)}
+        />
+      )}
+
       {/* Built-in modes */}
       <section>
         <h2 className="text-lg font-medium text-gray-300 mb-3">Built-in Modes</h2>
         <div className="grid gap-3">
           {builtinModes.map((mode) => (
-            <ModeCard
-              key={mode.key}
-              mode={mode}
-              isActive={activeMode?.key === mode.key}
-              onActivate={() => setActiveMode(mode.key)}
-            />
+            <ModeCard key={mode.key} {...cardProps(mode)} />
           ))}
         </div>
       </section>
@@ -38,13 +87,7 @@ export default function ModesPage() {
           <h2 className="text-lg font-medium text-gray-300 mb-3">Custom Modes</h2>
           <div className="grid gap-3">
             {customModes.map((mode) => (
-              <ModeCard
-                key={mode.key}
-                mode={mode}
-                isActive={activeMode?.key === mode.key}
-                onActivate={() => setActiveMode(mode.key)}
-                editable
-              />
+              <ModeCard key={mode.key} {...cardProps(mode)} onDelete={() => handleDelete(mode)} />
             ))}
           </div>
         </section>
@@ -56,7 +99,8 @@ export default function ModesPage() {
           Creating Custom Modes
         </h3>
         <p className="text-sm text-gray-400 mb-3">
-          Custom modes are stored as JSON files in{" "}
+          Create, edit or duplicate modes here, or write them by hand. Custom
+          modes are stored as JSON files in{" "}
           <code className="bg-gray-700 px-1 rounded">
             ~/.config/whispertray/modes/
           </code>
@@ -75,9 +119,21 @@ export default function ModesPage() {
   "output_format": "plain"
 }`}
         </pre>
+        <p className="text-xs text-gray-500 mt-2">
+          Set <code>"post_processor": "rules"</code> instead of AI processing
+          to clean up transcripts locally, with no LLM involved.
+        </p>
+        <p className="text-xs text-gray-500 mt-2">
+          Set <code>"history_storage": "redacted"</code> to keep emails, phone
+          and card numbers out of history, <code>"summary"</code> to keep only
+          the AI output, or <code>"off"</code> to store nothing. All three
+          destroy the recording after processing.
+        </p>
         <p className="text-xs text-gray-500 mt-2">
           Available template variables: <code>{"{{transcript}}"}</code>,{" "}
-          <code>{"{{context}}"}</code>, <code>{"{{language}}"}</code>
+          <code>{"{{context}}"}</code>, <code>{"{{language}}"}</code>,{" "}
+          <code>{"{{time_of_day}}"}</code>, <code>{"{{weekday}}"}</code>,{" "}
+          <code>{"{{signature}}"}</code>, <code>{"{{tone}}"}</code>
         </p>
       </section>
     </div>
@@ -85,22 +141,19 @@ export default function ModesPage() {
 }
 
 interface ModeCardProps {
-  mode: {
-    key: string;
-    name: string;
-    description: string;
-    ai_processing: boolean;
-    stt_provider: string;
-    stt_model: string;
-    llm_provider: string;
-    llm_model: string;
-  };
+  mode: Mode;
   isActive: boolean;
   onActivate: () => void;
-  editable?: boolean;
+  onEdit: () => void;
+  onDuplicate: () => void;
+  // Custom modes only; built-in ones can be disabled but not deleted
+  onDelete?: () => void;
 }
 
-function ModeCard({ mode, isActive, onActivate, editable }: ModeCardProps) {
+function ModeCard({ mode, isActive, onActivate, onEdit, onDuplicate, onDelete }: ModeCardProps) {
+  const processor = mode.verbatim
+    ? "none"
+    : mode.post_processor ?? (mode.ai_processing ? "llm" : "none");
   return (
     <div
       className={clsx(
@@ -117,18 +170,43 @@ function ModeCard({ mode, isActive, onActivate, editable }: ModeCardProps) {
                 Active
               </span>
             )}
-            {mode.ai_processing && (
+            {processor === "llm" && (
               <span className="text-xs bg-purple-600 text-white px-2 py-0.5 rounded">
                 AI
               </span>
             )}
+            {processor === "rules" && (
+              <span className="text-xs bg-green-700 text-white px-2 py-0.5 rounded">
+                Rules
+              </span>
+            )}
+            {mode.verbatim && (
+              <span className="text-xs bg-amber-700 text-white px-2 py-0.5 rounded">
+                Verbatim
+              </span>
+            )}
+            {mode.history_storage === "redacted" && (
+              <span className="text-xs bg-gray-600 text-white px-2 py-0.5 rounded">
+                Redacted history
+              </span>
+            )}
+            {mode.history_storage === "summary" && (
+              <span className="text-xs bg-gray-600 text-white px-2 py-0.5 rounded">
+                Summary only
+              </span>
+            )}
+            {mode.history_storage === "off" && (
+              <span className="text-xs bg-gray-600 text-white px-2 py-0.5 rounded">
+                No history
+              </span>
+            )}
           </div>
           <p className="text-sm text-gray-400 mt-1">{mode.description}</p>
           <div className="flex items-center gap-4 mt-2 text-xs text-gray-500">
             <span>
               STT: {mode.stt_provider}/{mode.stt_model}
             </span>
-            {mode.ai_processing && (
+            {processor === "llm" && (
               <span>
                 LLM: {mode.llm_provider}/{mode.llm_model}
               </span>
@@ -144,9 +222,24 @@ function ModeCard({ mode, isActive, onActivate, editable }: ModeCardProps) {
               Activate
             </button>
           )}
-          {editable && (
-            <button className="px-3 py-1 bg-gray-700 text-white rounded text-sm hover:bg-gray-600">
-              Edit
+          <button
+            onClick={onEdit}
+            className="px-3 py-1 bg-gray-700 text-white rounded text-sm hover:bg-gray-600"
+          >
+            Edit
+          </button>
+          <button
+            onClick={onDuplicate}
+            className="px-3 py-1 bg-gray-700 text-white rounded text-sm hover:bg-gray-600"
+          >
+            Duplicate
+          </button>
+          {onDelete && (
+            <button
+              onClick={onDelete}
+              className="px-3 py-1 bg-gray-700 text-red-400 rounded text-sm hover:bg-gray-600"
+            >
+              Delete
             </button>
           )}
         </div>
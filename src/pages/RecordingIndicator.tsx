@@ -1,15 +1,34 @@
 import { useEffect, useRef, useState } from 'react';
 import { listen } from '@tauri-apps/api/event';
+import { getRecordingStatus } from '../lib/api';
 
 interface AudioLevel {
   level: number; // 0.0 to 1.0
   peak: number;  // 0.0 to 1.0
 }
 
+interface AudioWaveform {
+  peaks: number[]; // 0.0 to 1.0, oldest first
+  rate: number;    // peaks per second
+}
+
+// One pixel column per peak: 180 columns are 3.6s at 50 peaks/s
+const WAVEFORM_COLUMNS = 180;
+
+interface TranscriptionProgress {
+  job: string;
+  percent: number;
+}
+
 export default function RecordingIndicator() {
   const canvasRef = useRef<HTMLCanvasElement>(null);
   const [levels, setLevels] = useState<number[]>(new Array(30).fill(0));
+  // Scrolling waveform, drawn instead of the level bars once peaks arrive
+  const [peaks, setPeaks] = useState<number[] | null>(null);
   const [isProcessing, setIsProcessing] = useState(false);
+  // Known for long transcriptions only
+  const [progress, setProgress] = useState<number | null>(null);
+  const [incognito, setIncognito] = useState(false);
 
   useEffect(() => {
     // Listen for audio level updates
@@ -20,14 +39,35 @@ export default function RecordingIndicator() {
       });
     });
 
+    const unlistenWaveform = listen<AudioWaveform>('audio-waveform', (event) => {
+      setPeaks(prev => {
+        const previous = prev ?? new Array(WAVEFORM_COLUMNS).fill(0);
+        return [...previous, ...event.payload.peaks].slice(-WAVEFORM_COLUMNS);
+      });
+    });
+
     // Listen for processing state
     const unlistenProcessing = listen<boolean>('recording-processing', (event) => {
       setIsProcessing(event.payload);
+      setProgress(null);
+      setPeaks(null);
+    });
+
+    const unlistenProgress = listen<TranscriptionProgress>('transcription-progress', (event) => {
+      setProgress(event.payload.percent);
+    });
+
+    getRecordingStatus().then(status => setIncognito(status.incognito ?? false)).catch(() => {});
+    const unlistenIncognito = listen<boolean>('incognito-changed', (event) => {
+      setIncognito(event.payload);
     });
 
     return () => {
       unlisten.then(fn => fn());
+      unlistenWaveform.then(fn => fn());
       unlistenProcessing.then(fn => fn());
+      unlistenProgress.then(fn => fn());
+      unlistenIncognito.then(fn => fn());
     };
   }, []);
 
@@ -46,7 +86,17 @@ export default function RecordingIndicator() {
     // Clear canvas
     ctx.clearRect(0, 0, width, height);
 
-    if (isProcessing) {
+    if (isProcessing && progress !== null) {
+      // Fill bars up to the transcription progress
+      const filled = Math.round((progress / 100) * levels.length);
+      for (let i = 0; i < levels.length; i++) {
+        ctx.fillStyle = i < filled ? '#3b82f6' : '#374151';
+        const h = height * 0.4;
+        const x = i * barWidth + gap / 2;
+        const y = (height - h) / 2;
+        ctx.fillRect(x, y, barWidth - gap, h);
+      }
+    } else if (isProcessing) {
       // Show processing animation
       ctx.fillStyle = '#3b82f6';
       const time = Date.now() / 200;
@@ -56,8 +106,16 @@ export default function RecordingIndicator() {
         const y = (height - h) / 2;
         ctx.fillRect(x, y, barWidth - gap, h);
       }
+    } else if (peaks) {
+      // Scrolling waveform, newest peaks on the right
+      ctx.fillStyle = '#ef4444';
+      const columnWidth = width / peaks.length;
+      peaks.forEach((peak, i) => {
+        const h = Math.max(1, peak * height * 0.95);
+        ctx.fillRect(i * columnWidth, (height - h) / 2, columnWidth, h);
+      });
     } else {
-      // Show audio waveform
+      // Show audio levels
       ctx.fillStyle = '#ef4444';
       levels.forEach((level, i) => {
         const h = Math.max(4, level * height * 0.9);
@@ -66,7 +124,7 @@ export default function RecordingIndicator() {
         ctx.fillRect(x, y, barWidth - gap, h);
       });
     }
-  }, [levels, isProcessing]);
+  }, [levels, peaks, isProcessing, progress]);
 
   // Animation loop for processing state
   useEffect(() => {
@@ -85,7 +143,10 @@ export default function RecordingIndicator() {
       style={{
         background: 'rgba(0, 0, 0, 0.85)',
         backdropFilter: 'blur(10px)',
+        // Nothing from this recording is saved
+        boxShadow: incognito ? 'inset 0 0 0 2px #a855f7' : undefined,
       }}
+      title={incognito ? 'Incognito: nothing is saved' : undefined}
       data-tauri-drag-region
     >
       <canvas
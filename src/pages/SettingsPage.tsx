@@ -1,26 +1,73 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { open, save } from "@tauri-apps/plugin-dialog";
 import { useAppStore } from "../stores/appStore";
 import * as api from "../lib/api";
+import type {
+  AudioBackend,
+  AudioDevice,
+  AudioHost,
+  Calibration,
+  ChannelSelect,
+  Degradation,
+  FeatureFlag,
+  GpuFallback,
+  Hooks,
+  Hotkeys,
+  ImportReport,
+  MicConflictAction,
+  PunctuationRestore,
+  RecordingFormat,
+  SidecarFormat,
+} from "../types";
+
+// Select value for a channel setting: "mix", "left", "right" or a channel index
+function channelValue(channel: ChannelSelect | undefined): string {
+  if (channel === undefined) return "mix";
+  return typeof channel === "string" ? channel : String(channel.channel);
+}
+
+function parseChannel(value: string): ChannelSelect {
+  if (value === "mix" || value === "left" || value === "right") return value;
+  return { channel: Number(value) };
+}
 
 export default function SettingsPage() {
-  const { settings, devices, updateSettings, saveApiKey, deleteApiKey } =
+  const { settings, devices, modes, updateSettings, saveApiKey, deleteApiKey } =
     useAppStore();
 
   const [localSettings, setLocalSettings] = useState(settings);
   const [apiKeys, setApiKeys] = useState({
     openai: "",
     anthropic: "",
+    groq: "",
   });
   const [hasKeys, setHasKeys] = useState({
     openai: false,
     anthropic: false,
+    groq: false,
   });
   const [saving, setSaving] = useState(false);
   const [testingConnection, setTestingConnection] = useState(false);
   const [connectionStatus, setConnectionStatus] = useState<"idle" | "success" | "error">("idle");
   const [testingOllama, setTestingOllama] = useState(false);
   const [ollamaStatus, setOllamaStatus] = useState<"idle" | "success" | "error">("idle");
+  const [degradations, setDegradations] = useState<Degradation[]>([]);
+  const [featureFlags, setFeatureFlags] = useState<FeatureFlag[]>([]);
+  // Microphone calibration wizard: the silent clip's levels once recorded
+  const [calibrationStep, setCalibrationStep] = useState<"idle" | "silence" | "ready" | "speech">("idle");
+  const [silenceLevels, setSilenceLevels] = useState<number[]>([]);
+  const [calibration, setCalibration] = useState<Calibration | null>(null);
+  const [calibrationError, setCalibrationError] = useState<string | null>(null);
+  const [settingsImport, setSettingsImport] = useState<string | null>(null);
+  const [audioBackends, setAudioBackends] = useState<AudioBackend[]>(["cpal"]);
+  const [audioHosts, setAudioHosts] = useState<AudioHost[]>(["default"]);
+  // Devices of a cpal host chosen but not saved yet
+  const [hostDevices, setHostDevices] = useState<AudioDevice[] | null>(null);
+  // Dry-run result of a dictionary import awaiting confirmation
+  const [pendingImport, setPendingImport] = useState<{ path: string; report: ImportReport } | null>(null);
+  const [encryptionAvailable, setEncryptionAvailable] = useState(false);
+  const [keyRotation, setKeyRotation] = useState<string | null>(null);
 
   useEffect(() => {
     if (settings) {
@@ -28,13 +75,112 @@ export default function SettingsPage() {
     }
 
     // Check for existing API keys
-    Promise.all([api.hasApiKey("openai"), api.hasApiKey("anthropic")]).then(
-      ([openai, anthropic]) => {
-        setHasKeys({ openai, anthropic });
+    Promise.all([api.hasApiKey("openai"), api.hasApiKey("anthropic"), api.hasApiKey("groq")]).then(
+      ([openai, anthropic, groq]) => {
+        setHasKeys({ openai, anthropic, groq });
       }
     );
+
+    // Re-check after saving, since settings decide which tools are needed
+    api.getDegradations().then(setDegradations).catch(() => setDegradations([]));
+    api.getFeatureFlags().then(setFeatureFlags).catch(() => setFeatureFlags([]));
+    api.getAudioBackends().then(setAudioBackends).catch(() => setAudioBackends(["cpal"]));
+    api.getAudioHosts().then(setAudioHosts).catch(() => setAudioHosts(["default"]));
+    api.isHistoryEncryptionAvailable().then(setEncryptionAvailable).catch(() => setEncryptionAvailable(false));
   }, [settings]);
 
+  useEffect(() => {
+    const host = localSettings?.audio_host ?? "default";
+    if ((localSettings?.audio_backend ?? "cpal") !== "cpal" || host === (settings?.audio_host ?? "default")) {
+      setHostDevices(null);
+      return;
+    }
+    api.getInputDevices(host).then(setHostDevices).catch(() => setHostDevices([]));
+  }, [localSettings?.audio_host, localSettings?.audio_backend, settings?.audio_host]);
+
+  const handleExportDictionary = async () => {
+    const path = await save({
+      defaultPath: "dictionary.json",
+      filters: [{ name: "Dictionary", extensions: ["json"] }],
+    });
+    if (path) await api.exportDictionary(path);
+  };
+
+  const handleImportDictionary = async () => {
+    const path = await open({ filters: [{ name: "Dictionary", extensions: ["json"] }] });
+    if (typeof path !== "string") return;
+    const report = await api.importDictionary(path, "keep", true);
+    setPendingImport({ path, report });
+  };
+
+  const confirmImport = async (policy: "keep" | "overwrite") => {
+    if (!pendingImport) return;
+    await api.importDictionary(pendingImport.path, policy, false);
+    setPendingImport(null);
+    useAppStore.setState({ settings: await api.getSettings() });
+  };
+
+  const handleExportSettings = async () => {
+    const path = await save({
+      defaultPath: "whispertray-settings.json",
+      filters: [{ name: "Settings", extensions: ["json"] }],
+    });
+    if (path) await api.exportSettings(path);
+  };
+
+  const handleImportSettings = async () => {
+    const path = await open({ filters: [{ name: "Settings", extensions: ["json"] }] });
+    if (typeof path !== "string") return;
+    if (!confirm("Replace all settings and add the file's modes? API keys are kept.")) return;
+    setSettingsImport(null);
+    try {
+      const result = await api.importSettings(path);
+      const [settings, modes, activeMode] = await Promise.all([
+        api.getSettings(),
+        api.getModes(),
+        api.getActiveMode(),
+      ]);
+      useAppStore.setState({ settings, modes, activeMode });
+      setSettingsImport(`Imported settings and ${result.modes.length} modes (from version ${result.app_version})`);
+    } catch (e) {
+      setSettingsImport(`Import failed: ${e}`);
+    }
+  };
+
+  const handleRotateHistoryKey = async () => {
+    setKeyRotation(null);
+    try {
+      await api.rotateHistoryKey();
+      setKeyRotation("The history database was re-encrypted with a new key");
+    } catch (e) {
+      setKeyRotation(`Key rotation failed: ${e}`);
+    }
+  };
+
+  const recordSilence = async () => {
+    setCalibration(null);
+    setCalibrationError(null);
+    setCalibrationStep("silence");
+    try {
+      setSilenceLevels(await api.recordCalibrationClip());
+      setCalibrationStep("ready");
+    } catch (error) {
+      setCalibrationError(String(error));
+      setCalibrationStep("idle");
+    }
+  };
+
+  const recordSpeech = async () => {
+    setCalibrationStep("speech");
+    try {
+      const speech = await api.recordCalibrationClip();
+      setCalibration(await api.computeCalibration(silenceLevels, speech));
+    } catch (error) {
+      setCalibrationError(String(error));
+    }
+    setCalibrationStep("idle");
+  };
+
   const handleSave = async () => {
     if (!localSettings) return;
 
@@ -53,12 +199,17 @@ export default function SettingsPage() {
         setHasKeys((prev) => ({ ...prev, anthropic: true }));
         setApiKeys((prev) => ({ ...prev, anthropic: "" }));
       }
+      if (apiKeys.groq) {
+        await saveApiKey("groq", apiKeys.groq);
+        setHasKeys((prev) => ({ ...prev, groq: true }));
+        setApiKeys((prev) => ({ ...prev, groq: "" }));
+      }
     } finally {
       setSaving(false);
     }
   };
 
-  const handleDeleteKey = async (provider: "openai" | "anthropic") => {
+  const handleDeleteKey = async (provider: "openai" | "anthropic" | "groq") => {
     if (confirm(`Delete ${provider} API key?`)) {
       await deleteApiKey(provider);
       setHasKeys((prev) => ({ ...prev, [provider]: false }));
@@ -82,6 +233,23 @@ export default function SettingsPage() {
     }
   };
 
+  const handleTestWyoming = async () => {
+    const url = localSettings?.wyoming_url;
+    if (!url) return;
+
+    setTestingConnection(true);
+    setConnectionStatus("idle");
+
+    try {
+      const success = await invoke<boolean>("test_wyoming_connection", { url });
+      setConnectionStatus(success ? "success" : "error");
+    } catch {
+      setConnectionStatus("error");
+    } finally {
+      setTestingConnection(false);
+    }
+  };
+
   const handleTestOllama = async () => {
     const url = localSettings?.ollama_url;
     if (!url) return;
@@ -107,11 +275,83 @@ export default function SettingsPage() {
     <div className="max-w-2xl mx-auto space-y-6">
       <h1 className="text-2xl font-semibold text-white">Settings</h1>
 
+      {/* Features degraded by missing binaries */}
+      {degradations.length > 0 && (
+        <section className="bg-yellow-900/30 border border-yellow-700 rounded-lg p-4">
+          <h2 className="text-lg font-medium text-yellow-300 mb-3">Limited features</h2>
+          <ul className="space-y-3">
+            {degradations.map((d) => (
+              <li key={d.key}>
+                <p className="text-white text-sm">
+                  {d.feature}: <span className="text-gray-300">{d.impact}</span>
+                </p>
+                <p className="text-xs text-gray-400">{d.hint}</p>
+              </li>
+            ))}
+          </ul>
+        </section>
+      )}
+
       {/* Audio settings */}
       <section className="bg-gray-800 rounded-lg p-4">
         <h2 className="text-lg font-medium text-white mb-4">Audio</h2>
 
         <div className="space-y-4">
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              Audio Backend
+            </label>
+            <select
+              value={localSettings.audio_backend ?? "cpal"}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  audio_backend: e.target.value as AudioBackend,
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value="cpal">ALSA (cpal)</option>
+              {(audioBackends.includes("pipewire") || localSettings.audio_backend === "pipewire") && (
+                <option value="pipewire">PipeWire (native)</option>
+              )}
+            </select>
+            <p className="text-xs text-gray-500 mt-1">
+              PipeWire lists devices by their real names and follows devices being
+              plugged in. Requires a build with the pipewire feature and the
+              PipeWire backend feature flag.
+            </p>
+          </div>
+
+          {(localSettings.audio_backend ?? "cpal") === "cpal" && (
+            <div>
+              <label className="block text-sm font-medium text-gray-300 mb-1">
+                Audio Host
+              </label>
+              <select
+                value={localSettings.audio_host ?? "default"}
+                onChange={(e) =>
+                  setLocalSettings({
+                    ...localSettings,
+                    audio_host: e.target.value as AudioHost,
+                  })
+                }
+                className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+              >
+                <option value="default">Default</option>
+                {audioHosts.includes("alsa") && <option value="alsa">ALSA</option>}
+                {(audioHosts.includes("jack") || localSettings.audio_host === "jack") && (
+                  <option value="jack">JACK</option>
+                )}
+              </select>
+              <p className="text-xs text-gray-500 mt-1">
+                Pick JACK if you run a JACK server, or ALSA to list the hardware
+                devices directly when the default shows the wrong ones. JACK
+                requires a build with the jack feature and a running server.
+              </p>
+            </div>
+          )}
+
           <div>
             <label className="block text-sm font-medium text-gray-300 mb-1">
               Input Device
@@ -124,12 +364,187 @@ export default function SettingsPage() {
               className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
             >
               <option value="">Default</option>
-              {devices.map((device) => (
+              {(hostDevices ?? devices).map((device) => (
                 <option key={device.name} value={device.name}>
-                  {device.name} {device.is_default && "(System Default)"}
+                  {device.label ?? device.name} {device.is_default && "(System Default)"}
+                </option>
+              ))}
+            </select>
+          </div>
+
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              Input Gain ({localSettings.input_gain_db?.[localSettings.input_device] ?? 0} dB)
+            </label>
+            <input
+              type="range"
+              min={-20}
+              max={30}
+              step={1}
+              value={localSettings.input_gain_db?.[localSettings.input_device] ?? 0}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  input_gain_db: {
+                    ...localSettings.input_gain_db,
+                    [localSettings.input_device]: Number(e.target.value),
+                  },
+                })
+              }
+              className="w-full"
+            />
+            <p className="text-xs text-gray-500 mt-1">
+              Applied to the selected device only. Recordings with more than{" "}
+              {localSettings.clipping_warn_percent ?? 0.5}% of samples at full
+              scale are flagged as clipped in history.
+            </p>
+          </div>
+
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              Input Channel
+            </label>
+            <select
+              value={channelValue(localSettings.input_channels?.[localSettings.input_device])}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  input_channels: {
+                    ...localSettings.input_channels,
+                    [localSettings.input_device]: parseChannel(e.target.value),
+                  },
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value="mix">Mix all channels</option>
+              <option value="left">Left (channel 1)</option>
+              <option value="right">Right (channel 2)</option>
+              {[3, 4, 5, 6, 7, 8].map((n) => (
+                <option key={n} value={String(n - 1)}>
+                  Channel {n}
                 </option>
               ))}
             </select>
+            <p className="text-xs text-gray-500 mt-1">
+              For audio interfaces with the microphone on one input: record only
+              that channel instead of averaging in the others. Applies to the
+              selected device with the cpal backend.
+            </p>
+          </div>
+
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              Microphone Calibration
+            </label>
+            {calibrationStep === "ready" ? (
+              <button
+                onClick={recordSpeech}
+                className="px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded-lg text-sm"
+              >
+                Record Speech
+              </button>
+            ) : (
+              <button
+                onClick={recordSilence}
+                disabled={calibrationStep !== "idle"}
+                className="px-4 py-2 bg-gray-700 hover:bg-gray-600 disabled:opacity-50 rounded-lg text-sm"
+              >
+                {calibrationStep === "idle" ? "Calibrate Microphone" : "Recording…"}
+              </button>
+            )}
+            <p className="text-xs text-gray-500 mt-1">
+              {calibrationStep === "silence" && "Stay quiet for 3 seconds…"}
+              {calibrationStep === "ready" &&
+                "Now click Record Speech and talk normally for 3 seconds."}
+              {calibrationStep === "speech" && "Keep talking…"}
+              {calibrationStep === "idle" &&
+                `Measures your room's noise and your voice to set when speech is detected. Threshold: ${
+                  localSettings.vad_threshold?.toFixed(4) ?? "default"
+                }`}
+            </p>
+            {calibrationError && <p className="text-xs text-red-400 mt-1">{calibrationError}</p>}
+            {calibration && (
+              <div className="text-xs text-gray-400 mt-2 space-y-1">
+                <p>
+                  Noise floor {calibration.noise_floor.toFixed(4)}, speech{" "}
+                  {calibration.speech_level.toFixed(4)} ({calibration.snr_db.toFixed(0)} dB apart)
+                </p>
+                <button
+                  onClick={() =>
+                    setLocalSettings({
+                      ...localSettings,
+                      vad_threshold: calibration.suggested_threshold,
+                    })
+                  }
+                  className="text-blue-400 hover:text-blue-300"
+                >
+                  Use threshold {calibration.suggested_threshold.toFixed(4)}
+                </button>
+              </div>
+            )}
+          </div>
+
+          {localSettings.pacing && (
+            <div>
+              <label className="block text-sm font-medium text-gray-300 mb-1">
+                Maximum Recording Length
+              </label>
+              <select
+                value={localSettings.pacing.max_recording_secs ?? 0}
+                onChange={(e) =>
+                  setLocalSettings({
+                    ...localSettings,
+                    pacing: localSettings.pacing && {
+                      ...localSettings.pacing,
+                      max_recording_secs: Number(e.target.value) || null,
+                    },
+                  })
+                }
+                disabled={localSettings.pacing.no_time_limits}
+                className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white disabled:opacity-50"
+              >
+                <option value={0}>Unlimited</option>
+                <option value={120}>2 minutes</option>
+                <option value={300}>5 minutes</option>
+                <option value={600}>10 minutes</option>
+                <option value={1800}>30 minutes</option>
+                <option value={3600}>1 hour</option>
+              </select>
+              <p className="text-xs text-gray-500 mt-1">
+                Recording stops and is transcribed at this length, with a warning
+                15 seconds before, so a hotkey pressed by accident does not keep
+                the microphone open for hours.
+                {localSettings.pacing.no_time_limits && " Off while the pacing has no time limits."}
+              </p>
+            </div>
+          )}
+
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              Pre-roll
+            </label>
+            <select
+              value={localSettings.pre_roll_ms ?? 0}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  pre_roll_ms: Number(e.target.value),
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value={0}>Off</option>
+              <option value={1000}>1 second</option>
+              <option value={1500}>1.5 seconds</option>
+              <option value={2000}>2 seconds</option>
+            </select>
+            <p className="text-xs text-gray-500 mt-1">
+              Keeps the microphone open and includes audio from just before the
+              hotkey, so the first word is never cut off. The buffer stays in
+              memory and is never saved, but your desktop will show the
+              microphone as in use.
+            </p>
           </div>
 
           <div>
@@ -154,6 +569,30 @@ export default function SettingsPage() {
               <option value="zh">Chinese</option>
             </select>
           </div>
+
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              Recording Format
+            </label>
+            <select
+              value={localSettings.recording_format ?? "wav"}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  recording_format: e.target.value as RecordingFormat,
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value="wav">WAV (uncompressed)</option>
+              <option value="flac">FLAC (lossless, about half the size)</option>
+              <option value="opus">Opus (speech quality, about a tenth)</option>
+            </select>
+            <p className="text-xs text-gray-500 mt-1">
+              Format recordings are kept in for history. Verbatim modes always
+              keep lossless WAV. Opus requires a build with the opus feature.
+            </p>
+          </div>
         </div>
       </section>
 
@@ -181,6 +620,8 @@ export default function SettingsPage() {
               <option value="whispercpp">whisper.cpp (Local)</option>
               <option value="whisperserver">Self-hosted Whisper Server</option>
               <option value="openai">OpenAI Cloud</option>
+              <option value="groq">Groq Cloud</option>
+              <option value="wyoming">Wyoming Server (Home Assistant)</option>
               <option value="deepgram">Deepgram</option>
             </select>
           </div>
@@ -220,7 +661,48 @@ export default function SettingsPage() {
                 </button>
               </div>
               <p className="text-xs text-gray-500 mt-1">
-                URL of your self-hosted whisper server (Speaches, faster-whisper-server, etc.)
+                URL of your self-hosted whisper server (Speaches, faster-whisper-server, etc.); for
+                whisper.cpp's server, include its path, e.g. http://192.168.1.100:8080/inference
+              </p>
+            </div>
+          )}
+
+          {/* Wyoming server address - shown when wyoming is selected */}
+          {localSettings.default_stt_provider === "wyoming" && (
+            <div>
+              <label className="block text-sm font-medium text-gray-300 mb-1">
+                Wyoming Server
+                {connectionStatus === "success" && (
+                  <span className="ml-2 text-green-400 text-xs">✓ Connected</span>
+                )}
+                {connectionStatus === "error" && (
+                  <span className="ml-2 text-red-400 text-xs">✗ Connection failed</span>
+                )}
+              </label>
+              <div className="flex gap-2">
+                <input
+                  type="text"
+                  value={localSettings.wyoming_url || ""}
+                  onChange={(e) => {
+                    setLocalSettings({
+                      ...localSettings,
+                      wyoming_url: e.target.value || null,
+                    });
+                    setConnectionStatus("idle");
+                  }}
+                  placeholder="192.168.1.100:10300"
+                  className="flex-1 bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+                />
+                <button
+                  onClick={handleTestWyoming}
+                  disabled={testingConnection || !localSettings.wyoming_url}
+                  className="px-3 py-2 bg-gray-600 text-white rounded-lg text-sm hover:bg-gray-500 disabled:opacity-50"
+                >
+                  {testingConnection ? "Testing..." : "Test"}
+                </button>
+              </div>
+              <p className="text-xs text-gray-500 mt-1">
+                Host and port of a Wyoming speech-to-text server (wyoming-faster-whisper)
               </p>
             </div>
           )}
@@ -229,7 +711,8 @@ export default function SettingsPage() {
             <label className="block text-sm font-medium text-gray-300 mb-1">
               Default Model
             </label>
-            {localSettings.default_stt_provider === "whisperserver" ? (
+            {localSettings.default_stt_provider === "whisperserver" ||
+            localSettings.default_stt_provider === "wyoming" ? (
               <input
                 type="text"
                 value={localSettings.default_stt_model}
@@ -239,7 +722,11 @@ export default function SettingsPage() {
                     default_stt_model: e.target.value,
                   })
                 }
-                placeholder="e.g., distil-whisper/distil-large-v3.5-ct2"
+                placeholder={
+                  localSettings.default_stt_provider === "wyoming"
+                    ? "Leave empty for the server's model"
+                    : "e.g., distil-whisper/distil-large-v3.5-ct2"
+                }
                 className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
               />
             ) : localSettings.default_stt_provider === "openai" ? (
@@ -258,6 +745,21 @@ export default function SettingsPage() {
                 <option value="whisper-1">whisper-1 (original)</option>
                 <option value="gpt-4o-transcribe-diarize">gpt-4o-transcribe-diarize (speaker labels)</option>
               </select>
+            ) : localSettings.default_stt_provider === "groq" ? (
+              <select
+                value={localSettings.default_stt_model}
+                onChange={(e) =>
+                  setLocalSettings({
+                    ...localSettings,
+                    default_stt_model: e.target.value,
+                  })
+                }
+                className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+              >
+                <option value="whisper-large-v3">whisper-large-v3 (best, multilingual)</option>
+                <option value="whisper-large-v3-turbo">whisper-large-v3-turbo (faster, cheaper)</option>
+                <option value="distil-whisper-large-v3-en">distil-whisper-large-v3-en (fastest, English only)</option>
+              </select>
             ) : (
               <select
                 value={localSettings.default_stt_model}
@@ -279,47 +781,139 @@ export default function SettingsPage() {
             <p className="text-xs text-gray-500 mt-1">
               {localSettings.default_stt_provider === "whisperserver"
                 ? "Model name from your server (e.g., check its /v1/models endpoint)"
+                : localSettings.default_stt_provider === "wyoming"
+                ? "The server transcribes with the model it was started with unless one is named"
                 : localSettings.default_stt_provider === "openai"
                 ? "Diarize adds speaker labels but may need chunking for audio > 30s"
+                : localSettings.default_stt_provider === "groq"
+                ? "Needs a Groq API key (see API Keys below)"
                 : "Models are downloaded automatically on first use"}
             </p>
           </div>
-        </div>
-      </section>
-
-      {/* LLM settings */}
-      <section className="bg-gray-800 rounded-lg p-4">
-        <h2 className="text-lg font-medium text-white mb-4">
-          AI Processing (LLM)
-        </h2>
 
-        <div className="space-y-4">
           <div>
             <label className="block text-sm font-medium text-gray-300 mb-1">
-              Default Provider
+              Punctuation Restoration
             </label>
             <select
-              value={localSettings.default_llm_provider}
+              value={localSettings.punctuation ?? "off"}
               onChange={(e) =>
                 setLocalSettings({
                   ...localSettings,
-                  default_llm_provider: e.target.value,
+                  punctuation: e.target.value as PunctuationRestore,
                 })
               }
               className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
             >
-              <option value="ollama">Ollama (Local)</option>
-              <option value="openai">OpenAI</option>
-              <option value="anthropic">Anthropic Claude</option>
+              <option value="off">Off</option>
+              <option value="rules">Rules (commas, sentence ends, capitals)</option>
+              <option value="model">Local punctuation model</option>
             </select>
+            {localSettings.punctuation === "model" && (
+              <input
+                type="text"
+                value={localSettings.punctuation_command ?? ""}
+                onChange={(e) =>
+                  setLocalSettings({
+                    ...localSettings,
+                    punctuation_command: e.target.value || null,
+                  })
+                }
+                placeholder="punctuate --model small"
+                className="w-full mt-2 bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+              />
+            )}
+            <p className="text-xs text-gray-500 mt-1">
+              {localSettings.punctuation === "model"
+                ? "Command reading the transcript on stdin and printing it punctuated. Needs shell hooks allowed; rules are used when it fails or changes words."
+                : "Adds commas and capitals to poorly punctuated transcripts (tiny, base) without an LLM. Words are never changed."}
+            </p>
           </div>
 
-          {/* Ollama URL - shown when ollama is selected */}
-          {localSettings.default_llm_provider === "ollama" && (
-            <div>
-              <label className="block text-sm font-medium text-gray-300 mb-1">
-                Ollama Server URL
-                {ollamaStatus === "success" && (
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              When a Model Does Not Fit in GPU Memory
+            </label>
+            <select
+              value={localSettings.gpu_fallback ?? "cpu"}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  gpu_fallback: e.target.value as GpuFallback,
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value="cpu">Run it on the CPU</option>
+              <option value="smaller">Use a smaller downloaded model</option>
+            </select>
+            <p className="text-xs text-gray-500 mt-1">
+              Free VRAM is checked before each load in builds with GPU support
+              (cuda or opencl feature). You are notified when it falls back.
+            </p>
+          </div>
+
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              Local Whisper Beam Search
+            </label>
+            <select
+              value={localSettings.whisper_beam_size ?? 0}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  whisper_beam_size: Number(e.target.value),
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value={0}>Off (fastest)</option>
+              <option value={2}>2 beams</option>
+              <option value={3}>3 beams</option>
+              <option value={5}>5 beams</option>
+            </select>
+            <p className="text-xs text-gray-500 mt-1">
+              More accurate but slower. Uncertain phrases are also decoded a
+              few more times, and the other readings are offered in History
+              as one-click corrections.
+            </p>
+          </div>
+        </div>
+      </section>
+
+      {/* LLM settings */}
+      <section className="bg-gray-800 rounded-lg p-4">
+        <h2 className="text-lg font-medium text-white mb-4">
+          AI Processing (LLM)
+        </h2>
+
+        <div className="space-y-4">
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              Default Provider
+            </label>
+            <select
+              value={localSettings.default_llm_provider}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  default_llm_provider: e.target.value,
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value="ollama">Ollama (Local)</option>
+              <option value="openai">OpenAI</option>
+              <option value="anthropic">Anthropic Claude</option>
+            </select>
+          </div>
+
+          {/* Ollama URL - shown when ollama is selected */}
+          {localSettings.default_llm_provider === "ollama" && (
+            <div>
+              <label className="block text-sm font-medium text-gray-300 mb-1">
+                Ollama Server URL
+                {ollamaStatus === "success" && (
                   <span className="ml-2 text-green-400 text-xs">✓ Connected</span>
                 )}
                 {ollamaStatus === "error" && (
@@ -439,7 +1033,97 @@ export default function SettingsPage() {
               )}
             </div>
           </div>
+
+          {/* Groq */}
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              Groq API Key
+              {hasKeys.groq && (
+                <span className="ml-2 text-green-400 text-xs">✓ Configured</span>
+              )}
+            </label>
+            <div className="flex gap-2">
+              <input
+                type="password"
+                value={apiKeys.groq}
+                onChange={(e) =>
+                  setApiKeys({ ...apiKeys, groq: e.target.value })
+                }
+                placeholder={hasKeys.groq ? "••••••••" : "gsk_..."}
+                className="flex-1 bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+              />
+              {hasKeys.groq && (
+                <button
+                  onClick={() => handleDeleteKey("groq")}
+                  className="px-3 py-2 bg-red-600 text-white rounded-lg text-sm hover:bg-red-700"
+                >
+                  Delete
+                </button>
+              )}
+            </div>
+          </div>
+        </div>
+      </section>
+
+      {/* Dictionary */}
+      <section className="bg-gray-800 rounded-lg p-4">
+        <h2 className="text-lg font-medium text-white mb-4">Dictionary</h2>
+        <p className="text-sm text-gray-400 mb-3">
+          {localSettings.dictionary?.replacements.length ?? 0} replacements,{" "}
+          {localSettings.dictionary?.glossary.length ?? 0} glossary terms. Share them with your team as a file.
+        </p>
+        <div className="flex gap-2">
+          <button
+            onClick={handleExportDictionary}
+            className="px-3 py-1.5 bg-gray-700 text-white rounded hover:bg-gray-600 text-sm"
+          >
+            Export...
+          </button>
+          <button
+            onClick={handleImportDictionary}
+            className="px-3 py-1.5 bg-gray-700 text-white rounded hover:bg-gray-600 text-sm"
+          >
+            Import...
+          </button>
         </div>
+
+        {pendingImport && (
+          <div className="mt-3 bg-gray-900 rounded p-3 text-sm">
+            <p className="text-gray-300">
+              {pendingImport.report.added} new, {pendingImport.report.unchanged} already present,{" "}
+              {pendingImport.report.conflicts.length} conflicting
+            </p>
+            {pendingImport.report.conflicts.length > 0 && (
+              <ul className="mt-2 space-y-1 text-gray-400">
+                {pendingImport.report.conflicts.map((conflict) => (
+                  <li key={`${conflict.kind}-${conflict.key}`}>
+                    <span className="text-white">{conflict.key}</span>: "{conflict.existing}" here, "
+                    {conflict.incoming}" in the file
+                  </li>
+                ))}
+              </ul>
+            )}
+            <div className="flex gap-2 mt-3">
+              <button
+                onClick={() => confirmImport("keep")}
+                className="px-3 py-1 bg-blue-600 text-white rounded hover:bg-blue-700"
+              >
+                Import, keep mine
+              </button>
+              {pendingImport.report.conflicts.length > 0 && (
+                <button
+                  onClick={() => confirmImport("overwrite")}
+                  className="px-3 py-1 bg-gray-700 text-white rounded hover:bg-gray-600"
+                >
+                  Import, use theirs
+                </button>
+              )}
+              <button onClick={() => setPendingImport(null)} className="px-3 py-1 text-gray-400 hover:text-white">
+                Cancel
+              </button>
+            </div>
+          </div>
+        )}
       </section>
 
       {/* Behavior */}
@@ -486,7 +1170,498 @@ export default function SettingsPage() {
               </p>
             </div>
           </label>
+
+          <label className="flex items-center gap-3">
+            <input
+              type="checkbox"
+              checked={localSettings.remember_window ?? false}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  remember_window: e.target.checked,
+                })
+              }
+              className="w-4 h-4 rounded bg-gray-700 border-gray-600 text-blue-600 focus:ring-blue-500"
+            />
+            <div>
+              <span className="text-white">Remember the focused app</span>
+              <p className="text-xs text-gray-500">
+                Store the app and window title you dictated into with each history item
+              </p>
+            </div>
+          </label>
+
+          <label className="flex items-center gap-3">
+            <input
+              type="checkbox"
+              checked={localSettings.learn_style ?? false}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  learn_style: e.target.checked,
+                })
+              }
+              className="w-4 h-4 rounded bg-gray-700 border-gray-600 text-blue-600 focus:ring-blue-500"
+            />
+            <div>
+              <span className="text-white">Learn my writing style</span>
+              <p className="text-xs text-gray-500">
+                Derive sentence length, vocabulary and tone from outputs you corrected, so AI rewrites sound like you
+              </p>
+            </div>
+          </label>
+
+          <label className="flex items-center gap-3">
+            <input
+              type="checkbox"
+              checked={localSettings.capture_browser_url ?? false}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  capture_browser_url: e.target.checked,
+                })
+              }
+              className="w-4 h-4 rounded bg-gray-700 border-gray-600 text-blue-600 focus:ring-blue-500"
+            />
+            <div>
+              <span className="text-white">Attach browser tab URL to history</span>
+              <p className="text-xs text-gray-500">
+                Store the tab you dictated in, as reported by the companion browser
+                extension to the local HTTP API (needs the health port set)
+              </p>
+            </div>
+          </label>
+
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">
+              When another app uses the microphone
+            </label>
+            <select
+              value={localSettings.mic_conflict ?? "notify"}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  mic_conflict: e.target.value as MicConflictAction,
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value="notify">Notify me and keep recording</option>
+              <option value="pause">Pause recording until it stops</option>
+              <option value="stop">Stop and transcribe what was dictated</option>
+              <option value="ignore">Do nothing</option>
+            </select>
+            <p className="text-xs text-gray-500 mt-1">
+              Avoids recording a video call by accident (needs PulseAudio or PipeWire)
+            </p>
+          </div>
+
+          <label className="flex items-center gap-3">
+            <input
+              type="checkbox"
+              checked={localSettings.pause_media ?? false}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  pause_media: e.target.checked,
+                })
+              }
+              className="w-4 h-4 rounded bg-gray-700 border-gray-600 text-blue-600 focus:ring-blue-500"
+            />
+            <div>
+              <span className="text-white">Pause media while recording</span>
+              <p className="text-xs text-gray-500">
+                Pause Spotify, mpv, browser videos and other MPRIS players when recording starts, and resume
+                them once the text is pasted
+              </p>
+            </div>
+          </label>
+
+          <label className="flex items-center gap-3">
+            <input
+              type="checkbox"
+              checked={localSettings.feedback_sounds?.enabled ?? false}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  feedback_sounds: { ...localSettings.feedback_sounds, enabled: e.target.checked },
+                })
+              }
+              className="w-4 h-4 rounded bg-gray-700 border-gray-600 text-blue-600 focus:ring-blue-500"
+            />
+            <div>
+              <span className="text-white">Feedback sounds</span>
+              <p className="text-xs text-gray-500">
+                Beep when recording starts and stops, chime when the text is pasted and buzz when a
+                dictation fails
+              </p>
+            </div>
+          </label>
+
+          {localSettings.feedback_sounds?.enabled && (
+            <div className="ml-7 space-y-3">
+              <div>
+                <label className="block text-sm font-medium text-gray-300 mb-1">
+                  Volume ({Math.round((localSettings.feedback_sounds?.volume ?? 0.5) * 100)}%)
+                </label>
+                <input
+                  type="range"
+                  min={0}
+                  max={100}
+                  step={5}
+                  value={Math.round((localSettings.feedback_sounds?.volume ?? 0.5) * 100)}
+                  onChange={(e) =>
+                    setLocalSettings({
+                      ...localSettings,
+                      feedback_sounds: { ...localSettings.feedback_sounds, volume: Number(e.target.value) / 100 },
+                    })
+                  }
+                  className="w-full"
+                />
+              </div>
+              <label className="flex items-center gap-3">
+                <input
+                  type="checkbox"
+                  checked={localSettings.feedback_sounds?.respect_dnd ?? true}
+                  onChange={(e) =>
+                    setLocalSettings({
+                      ...localSettings,
+                      feedback_sounds: { ...localSettings.feedback_sounds, respect_dnd: e.target.checked },
+                    })
+                  }
+                  className="w-4 h-4 rounded bg-gray-700 border-gray-600 text-blue-600 focus:ring-blue-500"
+                />
+                <span className="text-white">Quiet during do-not-disturb</span>
+              </label>
+            </div>
+          )}
+        </div>
+      </section>
+
+      {/* Watch folder */}
+      <section className="bg-gray-800 rounded-lg p-4">
+        <h2 className="text-lg font-medium text-white mb-4">Watch Folder</h2>
+
+        <div className="space-y-4">
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">Folder</label>
+            <div className="flex gap-2">
+              <input
+                type="text"
+                value={localSettings.watch_folder ?? ""}
+                onChange={(e) =>
+                  setLocalSettings({
+                    ...localSettings,
+                    watch_folder: e.target.value || null,
+                  })
+                }
+                placeholder="Not watching"
+                className="flex-1 bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+              />
+              <button
+                onClick={async () => {
+                  const path = await open({ directory: true });
+                  if (typeof path === "string") {
+                    setLocalSettings({ ...localSettings, watch_folder: path });
+                  }
+                }}
+                className="px-3 py-2 bg-gray-700 text-white rounded-lg text-sm hover:bg-gray-600"
+              >
+                Browse...
+              </button>
+            </div>
+            <p className="text-xs text-gray-500 mt-1">
+              New audio files dropped here (WAV, FLAC, Opus, and M4A, MP3 or AMR with ffmpeg),
+              such as voice memos synced from a phone, are transcribed and saved to history
+            </p>
+          </div>
+
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">Mode</label>
+            <select
+              value={localSettings.watch_folder_mode ?? ""}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  watch_folder_mode: e.target.value || null,
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value="">Active mode</option>
+              {modes.map((mode) => (
+                <option key={mode.key} value={mode.key}>
+                  {mode.name}
+                </option>
+              ))}
+            </select>
+          </div>
+
+          <div>
+            <label className="block text-sm font-medium text-gray-300 mb-1">Write output next to the file</label>
+            <select
+              value={localSettings.watch_folder_sidecar ?? ""}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  watch_folder_sidecar: (e.target.value || null) as SidecarFormat | null,
+                })
+              }
+              className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white"
+            >
+              <option value="">No</option>
+              <option value="txt">As .txt</option>
+              <option value="md">As .md</option>
+            </select>
+          </div>
+        </div>
+      </section>
+
+      {/* Optional hotkeys */}
+      <section className="bg-gray-800 rounded-lg p-4">
+        <h2 className="text-lg font-medium text-white mb-4">Hotkeys</h2>
+
+        <div className="space-y-4">
+          {(
+            [
+              ["bookmark", "Drop a bookmark", "Ctrl+Shift+B"],
+              ["cancel", "Cancel the dictation", "Ctrl+Shift+Escape"],
+              ["pause", "Pause and resume", "Ctrl+Shift+Space"],
+              ["incognito", "Toggle incognito", "Ctrl+Alt+I"],
+            ] as [keyof Hotkeys, string, string][]
+          ).map(([key, label, placeholder]) => (
+            <div key={key}>
+              <label className="block text-sm font-medium text-gray-300 mb-1">
+                {label}
+              </label>
+              <input
+                type="text"
+                value={localSettings.hotkeys?.[key] ?? ""}
+                onChange={(e) =>
+                  setLocalSettings({
+                    ...localSettings,
+                    hotkeys: { ...localSettings.hotkeys, [key]: e.target.value || null },
+                  })
+                }
+                placeholder={placeholder}
+                className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white font-mono text-sm"
+              />
+            </div>
+          ))}
+          <p className="text-xs text-gray-500">
+            Recording is always on Ctrl+Space. These hotkeys are off until set, so they
+            don't take shortcuts from other apps, and apply after a restart.
+          </p>
+        </div>
+      </section>
+
+      {/* Event hooks */}
+      <section className="bg-gray-800 rounded-lg p-4">
+        <h2 className="text-lg font-medium text-white mb-4">Event Hooks</h2>
+
+        <div className="space-y-4">
+          <label className="flex items-center gap-3">
+            <input
+              type="checkbox"
+              checked={localSettings.allow_shell_hooks ?? false}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  allow_shell_hooks: e.target.checked,
+                })
+              }
+              className="w-4 h-4 rounded bg-gray-700 border-gray-600 text-blue-600 focus:ring-blue-500"
+            />
+            <div>
+              <span className="text-white">Allow shell commands</span>
+              <p className="text-xs text-gray-500">
+                Hooks and the local punctuation model only run when this is on
+              </p>
+            </div>
+          </label>
+
+          {(
+            [
+              ["on_record_start", "When recording starts", "notify-send Recording {{mode}}"],
+              ["on_result", "When the output is ready", "cp {{transcript_path}} ~/last-dictation.txt"],
+              ["on_error", "When a dictation fails", "logger -t whispertray {{error}}"],
+            ] as [keyof Hooks, string, string][]
+          ).map(([key, label, placeholder]) => (
+            <div key={key}>
+              <label className="block text-sm font-medium text-gray-300 mb-1">
+                {label}
+              </label>
+              <input
+                type="text"
+                value={(localSettings.hooks?.[key] as string | null | undefined) ?? ""}
+                onChange={(e) =>
+                  setLocalSettings({
+                    ...localSettings,
+                    hooks: { ...localSettings.hooks, [key]: e.target.value || null },
+                  })
+                }
+                placeholder={placeholder}
+                className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white font-mono text-sm"
+              />
+            </div>
+          ))}
+          <p className="text-xs text-gray-500">
+            Commands run with sh in the background. {"{{event}}"}, {"{{id}}"},{" "}
+            {"{{mode}}"}, {"{{status}}"}, {"{{transcript_path}}"} and {"{{error}}"}{" "}
+            are replaced by quoted values, also available as WHISPERTRAY_* variables.
+            Hooks are stopped after {localSettings.hooks?.timeout_secs ?? 10} seconds.
+          </p>
+        </div>
+      </section>
+
+      {/* HTTP API */}
+      <section className="bg-gray-800 rounded-lg p-4">
+        <h2 className="text-lg font-medium text-white mb-4">HTTP API</h2>
+
+        <div className="space-y-4">
+          <div className="grid grid-cols-3 gap-4">
+            <div>
+              <label className="block text-sm font-medium text-gray-300 mb-1">Port</label>
+              <input
+                type="number"
+                min={1024}
+                max={65535}
+                value={localSettings.api_port ?? ""}
+                onChange={(e) =>
+                  setLocalSettings({
+                    ...localSettings,
+                    api_port: e.target.value ? Number(e.target.value) : null,
+                  })
+                }
+                placeholder="Off"
+                className="w-full bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white text-sm"
+              />
+            </div>
+            <div className="col-span-2">
+              <label className="block text-sm font-medium text-gray-300 mb-1">Token</label>
+              <div className="flex gap-2">
+                <input
+                  type="text"
+                  value={localSettings.api_token ?? ""}
+                  onChange={(e) =>
+                    setLocalSettings({ ...localSettings, api_token: e.target.value || null })
+                  }
+                  className="flex-1 bg-gray-700 border border-gray-600 rounded-lg px-3 py-2 text-white font-mono text-sm"
+                />
+                <button
+                  onClick={() =>
+                    setLocalSettings({ ...localSettings, api_token: crypto.randomUUID().replace(/-/g, "") })
+                  }
+                  className="px-3 py-2 bg-gray-700 text-white rounded-lg text-sm hover:bg-gray-600"
+                >
+                  Generate
+                </button>
+              </div>
+            </div>
+          </div>
+          <p className="text-xs text-gray-500">
+            Serves http://127.0.0.1:{localSettings.api_port ?? "PORT"} for launchers and scripts to start and stop
+            recording, transcribe files, read history and follow events, and for AI assistants at /mcp. Requests must send{" "}
+            <code>Authorization: Bearer TOKEN</code>. Takes effect after a restart.
+          </p>
+        </div>
+      </section>
+
+      {/* Feature flags */}
+      {featureFlags.length > 0 && (
+        <section className="bg-gray-800 rounded-lg p-4">
+          <h2 className="text-lg font-medium text-white mb-4">Experimental Features</h2>
+
+          <div className="space-y-4">
+            {featureFlags.map((flag) => (
+              <label key={flag.key} className="flex items-center gap-3">
+                <input
+                  type="checkbox"
+                  checked={localSettings.feature_flags?.[flag.key] ?? flag.default}
+                  onChange={(e) => {
+                    // Back at the default, the flag follows the release channel again
+                    const flags = { ...localSettings.feature_flags };
+                    if (e.target.checked === flag.default) {
+                      delete flags[flag.key];
+                    } else {
+                      flags[flag.key] = e.target.checked;
+                    }
+                    setLocalSettings({ ...localSettings, feature_flags: flags });
+                  }}
+                  className="w-4 h-4 rounded bg-gray-700 border-gray-600 text-blue-600 focus:ring-blue-500"
+                />
+                <div>
+                  <span className="text-white">{flag.name}</span>
+                  <p className="text-xs text-gray-500">
+                    {flag.description} ({flag.default ? "on" : "off"} by default)
+                  </p>
+                </div>
+              </label>
+            ))}
+          </div>
+        </section>
+      )}
+
+      {/* History encryption, only in builds with SQLCipher */}
+      {encryptionAvailable && (
+        <section className="bg-gray-800 rounded-lg p-4">
+          <h2 className="text-lg font-medium text-white mb-4">History Encryption</h2>
+          <label className="flex items-center gap-3">
+            <input
+              type="checkbox"
+              checked={localSettings.encrypt_history ?? false}
+              onChange={(e) =>
+                setLocalSettings({
+                  ...localSettings,
+                  encrypt_history: e.target.checked,
+                })
+              }
+              className="w-4 h-4 rounded bg-gray-700 border-gray-600 text-blue-600 focus:ring-blue-500"
+            />
+            <div>
+              <span className="text-white">Encrypt the history database</span>
+              <p className="text-xs text-gray-500">
+                Applied at the next start, with a key kept in the keyring. Only the database is encrypted:
+                kept recordings, journal Markdown files and the event sink stay plain files.
+              </p>
+            </div>
+          </label>
+          {settings?.encrypt_history && (
+            <div className="mt-3">
+              <button
+                onClick={handleRotateHistoryKey}
+                className="px-3 py-1.5 bg-gray-700 text-white rounded hover:bg-gray-600 text-sm"
+              >
+                Rotate Key
+              </button>
+              {keyRotation && <p className="text-xs text-gray-400 mt-2">{keyRotation}</p>}
+            </div>
+          )}
+        </section>
+      )}
+
+      {/* Settings file */}
+      <section className="bg-gray-800 rounded-lg p-4">
+        <h2 className="text-lg font-medium text-white mb-2">Settings File</h2>
+        <p className="text-xs text-gray-500 mb-3">
+          Copy your settings and modes to another machine. API keys and tokens are not exported,
+          and importing keeps this machine's.
+        </p>
+        <div className="flex gap-2">
+          <button
+            onClick={handleExportSettings}
+            className="px-3 py-1.5 bg-gray-700 text-white rounded hover:bg-gray-600 text-sm"
+          >
+            Export...
+          </button>
+          <button
+            onClick={handleImportSettings}
+            className="px-3 py-1.5 bg-gray-700 text-white rounded hover:bg-gray-600 text-sm"
+          >
+            Import...
+          </button>
         </div>
+        {settingsImport && <p className="text-xs text-gray-400 mt-2">{settingsImport}</p>}
       </section>
 
       {/* Save button */}
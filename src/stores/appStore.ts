@@ -5,15 +5,48 @@ import type {
   Mode,
   AudioDevice,
   HistoryItem,
+  SttSelection,
   Settings,
   RecordingStatus,
+  LlmPreview,
+  OfflineFallback,
+  MicConflict,
+  MicBlocked,
+  MicDiagnostic,
+  Clipping,
+  DeviceChange,
+  ConfigReload,
+  ConfigIssue,
+  WhatsNew,
 } from "../types";
 
 interface AppState {
   // Recording state
   status: RecordingStatus;
   isRecording: boolean;
+  // The user paused the current recording
+  isPaused: boolean;
+  // Time left (ms) when the recording neared its maximum length
+  timeWarning: number | null;
   lastOutput: string | null;
+  // Rewritten text while the LLM is still generating it
+  preview: string | null;
+  // Local stand-ins used by the last dictation while offline
+  degraded: OfflineFallback | null;
+  // Other apps using the microphone during the current recording
+  micConflict: MicConflict | null;
+  // The microphone delivered only silence, so the last recording was discarded
+  micBlocked: MicBlocked | null;
+  // The last input device change during the current recording
+  deviceChange: DeviceChange | null;
+  micDiagnostic: MicDiagnostic | null;
+  configIssues: ConfigIssue[];
+  // The last recording was too loud
+  clipping: Clipping | null;
+  // New dictations are kept off disk
+  incognito: boolean;
+  // Release notes to show after an update
+  whatsNew: WhatsNew | null;
 
   // Modes
   modes: Mode[];
@@ -39,10 +72,16 @@ interface AppState {
   startRecording: () => Promise<void>;
   stopRecording: () => Promise<void>;
   setActiveMode: (modeKey: string) => Promise<void>;
+  loadModes: () => Promise<void>;
   setInputDevice: (deviceName: string) => Promise<void>;
+  togglePause: () => Promise<void>;
+  setIncognito: (enabled: boolean) => Promise<void>;
+  dismissWhatsNew: () => Promise<void>;
   loadHistory: (search?: string) => Promise<void>;
   selectHistoryItem: (item: HistoryItem | null) => void;
   reprocessHistoryItem: (id: string, modeKey: string) => Promise<void>;
+  retranscribeHistoryItem: (id: string, stt: SttSelection) => Promise<void>;
+  editHistoryItem: (id: string, output: string) => Promise<void>;
   deleteHistoryItem: (id: string) => Promise<void>;
   updateSettings: (settings: Settings) => Promise<void>;
   saveApiKey: (provider: string, key: string) => Promise<void>;
@@ -54,7 +93,19 @@ export const useAppStore = create<AppState>((set, get) => ({
   // Initial state
   status: "loading",
   isRecording: false,
+  isPaused: false,
+  timeWarning: null,
   lastOutput: null,
+  preview: null,
+  degraded: null,
+  micConflict: null,
+  micBlocked: null,
+  deviceChange: null,
+  micDiagnostic: null,
+  configIssues: [],
+  clipping: null,
+  incognito: false,
+  whatsNew: null,
   modes: [],
   activeMode: null,
   devices: [],
@@ -88,15 +139,25 @@ export const useAppStore = create<AppState>((set, get) => ({
         selectedDevice: settings.input_device,
         status: statusResponse.status,
         isRecording: statusResponse.is_recording,
+        incognito: statusResponse.incognito ?? false,
+        isPaused: statusResponse.paused ?? false,
         isLoading: false,
       });
 
+      // Release notes are not worth failing startup over
+      api
+        .getWhatsNew()
+        .then((whatsNew) => set({ whatsNew }))
+        .catch(() => set({ whatsNew: null }));
+
       // Set up event listeners
       listen<string>("recording-complete", (event) => {
         set({
           status: "ready",
           isRecording: false,
           lastOutput: event.payload,
+          preview: null,
+          micConflict: null,
         });
         // Refresh history
         get().loadHistory();
@@ -107,11 +168,83 @@ export const useAppStore = create<AppState>((set, get) => ({
           status: "ready",
           isRecording: false,
           error: event.payload,
+          preview: null,
         });
       });
 
+      listen<LlmPreview>("llm-preview", (event) => {
+        set({ preview: event.payload.text });
+      });
+
+      listen<OfflineFallback>("degraded-mode", (event) => {
+        set({ degraded: event.payload });
+      });
+
+      listen<MicConflict>("mic-conflict", (event) => {
+        set({ micConflict: event.payload });
+      });
+
+      listen("mic-released", () => {
+        set({ micConflict: null });
+      });
+
+      listen<MicBlocked>("mic-blocked", (event) => {
+        set({ micBlocked: event.payload });
+      });
+
+      listen<number>("recording-time-warning", (event) => {
+        set({ timeWarning: event.payload });
+      });
+
+      listen<boolean>("recording-paused", (event) => {
+        set({ isPaused: event.payload });
+      });
+
+      listen<boolean>("incognito-changed", (event) => {
+        set({ incognito: event.payload });
+      });
+
+      listen<MicDiagnostic>("mic-diagnostic", (event) => {
+        set({ micDiagnostic: event.payload });
+      });
+
+      listen<DeviceChange>("audio-device-changed", (event) => {
+        set({ deviceChange: event.payload });
+      });
+
+      listen<Clipping>("audio-clipping", (event) => {
+        set({ clipping: event.payload });
+      });
+
+      listen<ConfigReload>("config-reloaded", async (event) => {
+        set({ configIssues: event.payload.issues });
+        if (event.payload.settings || event.payload.modes) {
+          const [settings, modes, activeMode] = await Promise.all([
+            api.getSettings(),
+            api.getModes(),
+            api.getActiveMode(),
+          ]);
+          set({ settings, modes, activeMode, selectedDevice: settings.input_device });
+        }
+      });
+
+      listen("audio-devices-changed", async () => {
+        set({ devices: await api.getInputDevices() });
+      });
+
       listen("recording-started", () => {
-        set({ status: "recording", isRecording: true });
+        set({
+          status: "recording",
+          isRecording: true,
+          isPaused: false,
+          timeWarning: null,
+          degraded: null,
+          micConflict: null,
+          micBlocked: null,
+          micDiagnostic: null,
+          deviceChange: null,
+          clipping: null,
+        });
       });
     } catch (error) {
       set({
@@ -138,12 +271,14 @@ export const useAppStore = create<AppState>((set, get) => ({
   // Stop recording
   stopRecording: async () => {
     try {
-      set({ status: "processing", error: null });
+      set({ status: "processing", error: null, preview: null });
       const output = await api.stopRecording();
       set({
         status: "ready",
         isRecording: false,
+        isPaused: false,
         lastOutput: output,
+        preview: null,
       });
       // Refresh history
       get().loadHistory();
@@ -172,6 +307,18 @@ export const useAppStore = create<AppState>((set, get) => ({
     }
   },
 
+  // Reload modes after one was created, changed or deleted
+  loadModes: async () => {
+    try {
+      const [modes, activeMode] = await Promise.all([api.getModes(), api.getActiveMode()]);
+      set({ modes, activeMode });
+    } catch (error) {
+      set({
+        error: error instanceof Error ? error.message : "Failed to load modes",
+      });
+    }
+  },
+
   // Set input device
   setInputDevice: async (deviceName: string) => {
     try {
@@ -185,6 +332,36 @@ export const useAppStore = create<AppState>((set, get) => ({
     }
   },
 
+  // Pause or resume the current recording (the "recording-paused" event updates the store)
+  togglePause: async () => {
+    try {
+      set({ error: null });
+      await (get().isPaused ? api.resumeRecording() : api.pauseRecording());
+    } catch (error) {
+      set({
+        error: error instanceof Error ? error.message : "Failed to pause recording",
+      });
+    }
+  },
+
+  // Turn incognito on or off (the "incognito-changed" event updates the store)
+  setIncognito: async (enabled: boolean) => {
+    try {
+      set({ error: null });
+      await api.setIncognito(enabled);
+    } catch (error) {
+      set({
+        error: error instanceof Error ? error.message : "Failed to change incognito",
+      });
+    }
+  },
+
+  // Hide the release notes until the next update
+  dismissWhatsNew: async () => {
+    set({ whatsNew: null });
+    await api.dismissWhatsNew().catch(console.error);
+  },
+
   // Load history
   loadHistory: async (search?: string) => {
     try {
@@ -219,6 +396,42 @@ export const useAppStore = create<AppState>((set, get) => ({
     }
   },
 
+  // Transcribe a history item's recording again with another STT model
+  retranscribeHistoryItem: async (id: string, stt: SttSelection) => {
+    try {
+      set({ error: null, status: "processing" });
+      const item = await api.retranscribeHistoryItem(id, stt);
+      set((state) => ({
+        status: "ready",
+        history: state.history.map((h) => (h.id === id ? item : h)),
+        selectedHistoryItem:
+          state.selectedHistoryItem?.id === id ? item : state.selectedHistoryItem,
+      }));
+    } catch (error) {
+      set({
+        error: error instanceof Error ? error.message : "Failed to re-transcribe",
+        status: "ready",
+      });
+    }
+  },
+
+  // Edit a history item's output
+  editHistoryItem: async (id: string, output: string) => {
+    try {
+      set({ error: null });
+      const item = await api.editHistoryItem(id, output);
+      set((state) => ({
+        history: state.history.map((h) => (h.id === id ? item : h)),
+        selectedHistoryItem:
+          state.selectedHistoryItem?.id === id ? item : state.selectedHistoryItem,
+      }));
+    } catch (error) {
+      set({
+        error: error instanceof Error ? error.message : "Failed to save edit",
+      });
+    }
+  },
+
   // Delete history item
   deleteHistoryItem: async (id: string) => {
     try {
@@ -241,8 +454,15 @@ export const useAppStore = create<AppState>((set, get) => ({
   updateSettings: async (settings: Settings) => {
     try {
       set({ error: null });
+      const previous = get().settings;
+      const backendChanged =
+        settings.audio_backend !== previous?.audio_backend || settings.audio_host !== previous?.audio_host;
       await api.updateSettings(settings);
       set({ settings });
+      // Device names differ between backends and hosts
+      if (backendChanged) {
+        set({ devices: await api.getInputDevices() });
+      }
     } catch (error) {
       set({
         error: error instanceof Error ? error.message : "Failed to save settings",
@@ -2,7 +2,7 @@
 export type RecordingStatus = "loading" | "recording" | "processing" | "ready" | "error";
 
 // STT provider types
-export type SttProvider = "whispercpp" | "whisperserver" | "openai" | "deepgram" | string;
+export type SttProvider = "whispercpp" | "whisperserver" | "openai" | "groq" | "deepgram" | "wyoming" | string;
 
 // LLM provider types
 export type LlmProvider = "openai" | "anthropic" | "ollama" | string;
@@ -10,6 +10,18 @@ export type LlmProvider = "openai" | "anthropic" | "ollama" | string;
 // Output format
 export type OutputFormat = "plain" | "markdown";
 
+// Clipboard tool ("auto" uses wl-clipboard on Wayland when installed)
+export type ClipboardBackend = "auto" | "arboard" | "wlclipboard";
+
+// Desktop text a mode captures before recording ("auto" follows context awareness)
+export type ContextSource = "auto" | "clipboard" | "selection" | "none";
+
+// LLM tried when the ones before it are unreachable
+export interface LlmFallback {
+  provider: LlmProvider;
+  model: string;
+}
+
 // Mode definition
 export interface Mode {
   key: string;
@@ -20,17 +32,69 @@ export interface Mode {
   ai_processing: boolean;
   llm_provider: LlmProvider;
   llm_model: string;
+  llm_fallbacks?: LlmFallback[];
   prompt_template: string;
   output_format: OutputFormat;
+  json_schema?: Record<string, unknown> | null;
+  max_output_chars?: number | null;
+  context_source?: ContextSource;
+  compare_models?: LlmFallback[];
   builtin: boolean;
+  auto_segment?: boolean;
+  journal?: boolean;
+  skip_llm?: SkipLlmRule | null;
+  // Unset follows ai_processing ("llm" or "none")
+  post_processor?: PostProcessor | null;
+  // No LLM, cleanup, replacements or typography; lossless audio
+  verbatim?: boolean;
+  // What history keeps; the text is pasted unredacted either way
+  history_storage?: HistoryStorage;
+  // Run in order after transcription instead of the post-processor
+  steps?: PipelineStep[];
+  // Shell filter (stdin to stdout) run on the final text; needs allow_shell_hooks
+  output_command?: string | null;
+}
+
+// One step of a mode's pipeline; an LLM step's {{transcript}} is the previous step's output
+export type PipelineStep =
+  | { kind: "regex"; pattern: string; replacement: string }
+  | { kind: "cleanup" }
+  | { kind: "llm"; provider: LlmProvider; model: string; prompt: string };
+
+// A problem keeping a mode from being saved
+export interface ModeIssue {
+  // Field at fault, e.g. "llm_model" or "llm_fallbacks[1]"
+  field: string;
+  message: string;
+}
+
+// What a mode's dictations leave in history ("redacted" drops the recording)
+export type HistoryStorage = "full" | "redacted" | "summary" | "off";
+
+// How a mode turns the transcript into output; "rules" is local cleanup
+export type PostProcessor = "none" | "rules" | "llm";
+
+// When a transcript is short and confident enough to skip AI processing
+export interface SkipLlmRule {
+  max_words: number;
+  min_confidence: number;
 }
 
 // Audio device
 export interface AudioDevice {
   name: string;
+  // Human-readable name from PipeWire
+  label?: string | null;
   is_default: boolean;
+  is_monitor?: boolean;
 }
 
+// Where recorded audio comes from
+export type CaptureSource = "microphone" | "system" | "both";
+
+// Joiner between successive dictations appended to the same output
+export type OutputDelimiter = "space" | "newline" | "separator" | "timestamp";
+
 // History item
 export interface HistoryItem {
   id: string;
@@ -44,7 +108,337 @@ export interface HistoryItem {
   llm_provider: string | null;
   llm_model: string | null;
   duration_ms: number;
-  error: string | null;
+  error: ErrorRecord | null;
+  latency?: LatencySpan[];
+  bookmarks?: Bookmark[];
+  prompt_tokens?: number | null;
+  completion_tokens?: number | null;
+  cost_usd?: number | null;
+  word_count?: number;
+  char_count?: number;
+  reading_time_secs?: number;
+  truncated?: boolean;
+  window_class?: string | null;
+  window_title?: string | null;
+  cost_saved_usd?: number | null;
+  deleted_at?: string | null;
+  // Processed by local fallbacks because the network was down
+  degraded?: boolean;
+  // Dictated in a strict verbatim mode
+  verbatim?: boolean;
+  // Only the LLM output was kept; transcript and recording were destroyed
+  summary_only?: boolean;
+  // Browser tab the dictation was made in
+  source_url?: string | null;
+  // Too much of the recording was at full scale
+  clipped?: boolean;
+}
+
+// Payload of the "playback-state" event, for a history recording being played
+export interface PlaybackState {
+  id: string;
+  position_ms: number;
+  duration_ms: number;
+  playing: boolean;
+  // Played to the end or stopped; playing again starts over
+  finished: boolean;
+}
+
+// Payload of the "transcription-progress" event (job is a history item ID or "file")
+export interface TranscriptionProgress {
+  job: string;
+  percent: number;
+}
+
+// Payload of the "llm-preview" event: LLM output so far while it streams
+export interface LlmPreview {
+  job: string;
+  text: string;
+}
+
+// Payload of the "degraded-mode" event: local providers standing in while offline
+export interface OfflineFallback {
+  mode_key: string;
+  stt: SttSelection | null;
+  llm: LlmFallback | null;
+  rules: boolean;
+}
+
+// How punctuation is restored in transcripts without an LLM
+export type PunctuationRestore = "off" | "rules" | "model";
+
+// What runs instead when a local model does not fit in free VRAM
+export type GpuFallback = "cpu" | "smaller";
+
+// Format recordings are kept in once processed
+export type RecordingFormat = "wav" | "flac" | "opus";
+
+// How audio devices are listed and opened
+export type AudioBackend = "cpal" | "pipewire";
+
+// Channel recorded from a multi-channel input; { channel } counts from 0
+export type ChannelSelect = "mix" | "left" | "right" | { channel: number };
+
+// cpal host the cpal backend lists and opens devices through
+export type AudioHost = "default" | "alsa" | "jack";
+
+// What happens when another app starts using the microphone while recording
+export type MicConflictAction = "ignore" | "notify" | "pause" | "stop";
+
+// Payload of the "mic-conflict" event
+export interface MicConflict {
+  apps: string[];
+  action: MicConflictAction;
+}
+
+// A problem found in a config file edited on disk
+export interface ConfigIssue {
+  file: string;
+  message: string;
+}
+
+// Payload of the "config-reloaded" event
+export interface ConfigReload {
+  settings: boolean;
+  modes: boolean;
+  issues: ConfigIssue[];
+}
+
+// Payload of the "mic-diagnostic" event: why the microphone records nothing, or the wrong one
+export type MicDiagnostic =
+  | { kind: "busy"; device: string; apps: string[] }
+  | { kind: "wrong_source"; expected: string; actual: string };
+
+// Payload of the "audio-device-changed" event: an input device went away mid-recording
+export type DeviceChange =
+  | { kind: "fell_back"; lost: string; now: string }
+  | { kind: "dropped"; device: string }
+  | { kind: "lost"; device: string };
+
+// Sandbox the app runs in
+export type Sandbox = "flatpak" | "snap";
+
+// Result of the microphone calibration wizard (levels are frame RMS, 0-1)
+export interface Calibration {
+  noise_floor: number;
+  speech_level: number;
+  snr_db: number;
+  suggested_threshold: number;
+}
+
+// Payload of the "audio-clipping" event
+export interface Clipping {
+  percent: number;
+}
+
+// Payload of the "mic-blocked" event: the microphone delivered only silence
+export interface MicBlocked {
+  sandbox: Sandbox | null;
+  // Whether access was requested through the Device portal
+  requested: boolean;
+  hint: string;
+}
+
+// Pipeline stage a dictation failed in
+export type ErrorStage = "audio" | "stt" | "llm" | "output" | "other";
+
+// Why a dictation failed
+export interface ErrorRecord {
+  stage: ErrorStage;
+  provider?: string | null;
+  code: string;
+  message: string;
+  retryable: boolean;
+  retries?: number;
+  at: string;
+}
+
+// Earlier output of a history item, kept when it was edited, reprocessed or re-transcribed
+export interface HistoryRevision {
+  revision: number;
+  output_final: string;
+  mode_key: string;
+  llm_provider: string | null;
+  llm_model: string | null;
+  replaced_at: string;
+  replaced_by: "edit" | "reprocess" | "retranscribe";
+  // The transcript and STT model replaced, for re-transcriptions
+  transcript_raw: string | null;
+  stt_provider: string | null;
+  stt_model: string | null;
+}
+
+// Work a batch does to each of its history items
+export type BatchTask =
+  | { kind: "reprocess"; mode_key: string }
+  | { kind: "retranscribe"; stt: SttSelection };
+
+export type BatchStatus = "running" | "paused" | "cancelled" | "done";
+
+// A batch of history items being reprocessed; payload of "batch-progress"
+export interface BatchJob {
+  id: string;
+  task: BatchTask;
+  status: BatchStatus;
+  created_at: string;
+  total: number;
+  // Items finished, failed ones included
+  done: number;
+  failed: number;
+}
+
+// Other readings of one uncertain segment, stored when local whisper uses beam search
+export interface SegmentAlternatives {
+  text: string;
+  alternatives: string[];
+  start_ms: number;
+  end_ms: number;
+}
+
+// Speech pacing presets; "accessible" suits users with speech differences
+export type PacingPreset = "standard" | "accessible";
+
+// Pause, auto-stop and filler-word options
+export interface Pacing {
+  preset: PacingPreset;
+  vad_min_silence_ms: number;
+  keep_filler_words: boolean;
+  auto_stop_silence_ms: number | null;
+  max_recording_secs: number | null;
+  no_time_limits: boolean;
+}
+
+// Values for prompt template variables (unset ones get sample values in previews)
+export interface TemplateVars {
+  transcript?: string;
+  clipboard?: string | null;
+  selection?: string | null;
+  date?: string;
+  app_name?: string | null;
+  language?: string;
+  context?: string | null;
+  time?: string;
+  time_of_day?: string;
+  weekday?: string;
+  user_name?: string | null;
+  role?: string | null;
+  tone?: string | null;
+  signature?: string | null;
+}
+
+// Static user details available to every prompt
+export interface PromptProfile {
+  user_name?: string | null;
+  role?: string | null;
+  tone?: string | null;
+  signature?: string | null;
+}
+
+// Dry-run render of a prompt template
+export interface TemplatePreview {
+  rendered: string;
+  unknown_variables: string[];
+}
+
+// How formal the user's writing is (null outside English)
+export type Formality = "casual" | "neutral" | "formal";
+
+// Style learned from corrected dictations and added to LLM prompts
+export interface StyleProfile {
+  samples: number;
+  avg_sentence_words: number;
+  avg_word_length: number;
+  terms: string[];
+  formality: Formality | null;
+  updated_at: string;
+}
+
+// A day's aggregated journal (date is YYYY-MM-DD, local time)
+export interface JournalEntry {
+  date: string;
+  content: string;
+  entry_count: number;
+  summary: string | null;
+  updated_at: string;
+}
+
+// LLM usage and estimated spend for one month (YYYY-MM)
+export interface MonthlySpend {
+  month: string;
+  cost_usd: number;
+  prompt_tokens: number;
+  completion_tokens: number;
+  items: number;
+  saved_usd?: number;
+  skipped?: number;
+}
+
+// Date range for history stats (ISO timestamps, `to` exclusive)
+export interface StatsRange {
+  from?: string | null;
+  to?: string | null;
+}
+
+// Dictation totals for one mode or STT provider
+export interface GroupStats {
+  key: string;
+  recordings: number;
+  audio_minutes: number;
+  words: number;
+  words_per_minute: number | null;
+}
+
+// Frequently dictated phrase continuing a prefix
+export interface PhraseSuggestion {
+  phrase: string;
+  completion: string;
+  count: number;
+}
+
+export interface HistoryStats {
+  recordings: number;
+  audio_minutes: number;
+  words: number;
+  avg_latency_ms: number | null;
+  words_per_minute: number | null;
+  time_saved_minutes: number;
+  by_mode: GroupStats[];
+  by_provider: GroupStats[];
+}
+
+// One output offered in the multi-take picker
+export interface Candidate {
+  provider: string;
+  model: string;
+  text: string;
+}
+
+// Candidates waiting for a choice in the picker
+export interface PickRequest {
+  mode_key: string;
+  candidates: Candidate[];
+}
+
+// Default model suggested by past picker choices
+export interface ModelRecommendation {
+  provider: string;
+  model: string;
+  win_rate: number;
+  comparisons: number;
+}
+
+// Point of interest marked during a recording
+export interface Bookmark {
+  offset_ms: number;
+  label: string | null;
+}
+
+// Pipeline stage timing for a history item
+export interface LatencySpan {
+  name: string;
+  start_ms: number;
+  duration_ms: number;
+  depth: number;
 }
 
 // Settings
@@ -60,12 +454,179 @@ export interface Settings {
   language: string;
   whisper_server_url?: string;
   ollama_url?: string;
+  groq_url?: string | null;
+  // Wyoming server as host:port
+  wyoming_url?: string | null;
+  typography?: boolean;
+  read_only?: boolean;
+  capture_source?: CaptureSource;
+  monitor_device?: string;
+  save_two_track?: boolean;
+  vad_skip_silence?: boolean;
+  bookmark_labels?: boolean;
+  hotkeys?: Hotkeys;
+  output_delimiter?: OutputDelimiter;
+  health_port?: number | null;
+  allow_evdev_input?: boolean;
+  allow_shell_hooks?: boolean;
+  allow_screenshots?: boolean;
+  pacing?: Pacing;
+  journal_dir?: string | null;
+  journal_summaries?: boolean;
+  app_char_limits?: Record<string, number>;
+  remember_window?: boolean;
+  clipboard_backend?: ClipboardBackend;
+  event_sink?: string | null;
+  manage_ydotoold?: boolean;
+  paste?: PasteSettings;
+  paste_rules?: PasteRule[];
+  warm_start?: boolean;
+  warm_start_when_idle?: boolean;
+  remote_output?: RemoteTarget | null;
+  bridge_port?: number | null;
+  bridge_token?: string | null;
+  // Accept paired instances from the network, not only localhost (unencrypted)
+  bridge_lan?: boolean;
+  // Localhost HTTP API, only served when both are set
+  api_port?: number | null;
+  api_token?: string | null;
+  prompt_profile?: PromptProfile;
+  encrypt_history?: boolean;
+  shutdown_deadline_secs?: number;
+  trash_retention_days?: number | null;
+  retry?: RetryPolicy;
+  offline_fallback?: boolean;
+  // Ollama model used offline by modes without a local fallback
+  offline_llm_model?: string | null;
+  learn_style?: boolean;
+  dictionary?: Dictionary;
+  mic_conflict?: MicConflictAction;
+  // Pause playing media players while recording, resuming them after
+  pause_media?: boolean;
+  feedback_sounds?: FeedbackSounds;
+  audio_backend?: AudioBackend;
+  // cpal host used by the cpal backend
+  audio_host?: AudioHost;
+  punctuation?: PunctuationRestore;
+  // Local punctuation model command (needs allow_shell_hooks)
+  punctuation_command?: string | null;
+  gpu_fallback?: GpuFallback;
+  recording_format?: RecordingFormat;
+  // Shell commands run on dictation events (need allow_shell_hooks)
+  hooks?: Hooks;
+  // Store the browser tab URL reported by the companion extension
+  capture_browser_url?: boolean;
+  // Microphone audio kept from before each recording starts (0 = off)
+  pre_roll_ms?: number;
+  // Beam width for local whisper (0 = greedy); a beam keeps other readings in history
+  whisper_beam_size?: number;
+  // Feature flags switched on or off, by key; unset ones follow the release channel
+  feature_flags?: Record<string, boolean>;
+  // Software gain in dB per input device name ("" = default device)
+  input_gain_db?: Record<string, number>;
+  // Channel recorded per input device name, for multi-channel interfaces
+  input_channels?: Record<string, ChannelSelect>;
+  // Flag recordings with more than this percent of samples at full scale
+  clipping_warn_percent?: number;
+  // VAD speech threshold from microphone calibration; unset uses the default
+  vad_threshold?: number | null;
+  // Folder whose new audio files are transcribed into history
+  watch_folder?: string | null;
+  // Mode run on watch folder files; unset uses the active mode
+  watch_folder_mode?: string | null;
+  // Write each watch folder file's output next to it
+  watch_folder_sidecar?: SidecarFormat | null;
+}
+
+export type SidecarFormat = "txt" | "md";
+
+// Short sounds on recording start and stop, and when a dictation is done or fails
+export interface FeedbackSounds {
+  enabled?: boolean;
+  // 0.0 to 1.0
+  volume?: number;
+  // Stay quiet while the desktop's do-not-disturb mode is on
+  respect_dnd?: boolean;
+}
+
+// Shell commands for dictation events; {{event}}, {{id}}, {{mode}},
+// {{status}}, {{transcript_path}} and {{error}} are substituted
+// Optional global hotkeys, e.g. "Ctrl+Shift+B"; unset ones are not taken
+export interface Hotkeys {
+  bookmark?: string | null;
+  cancel?: string | null;
+  incognito?: string | null;
+  pause?: string | null;
+}
+
+export interface Hooks {
+  on_record_start?: string | null;
+  on_result?: string | null;
+  on_error?: string | null;
+  timeout_secs?: number | null;
+}
+
+// Word or phrase replaced in every transcript (whole words, any case)
+export interface Replacement {
+  from: string;
+  to: string;
+}
+
+// Domain term whose spelling the LLM keeps
+export interface GlossaryTerm {
+  term: string;
+  description?: string | null;
+}
+
+export interface Dictionary {
+  replacements: Replacement[];
+  glossary: GlossaryTerm[];
+}
+
+// What an import does with entries that differ from existing ones
+export type ConflictPolicy = "keep" | "overwrite";
+
+export interface DictionaryConflict {
+  kind: "replacement" | "glossary";
+  key: string;
+  existing: string;
+  incoming: string;
+}
+
+export interface ImportReport {
+  added: number;
+  unchanged: number;
+  conflicts: DictionaryConflict[];
+  policy: ConflictPolicy;
+}
+
+// What importing a settings file changed
+export interface BundleImport {
+  // Keys of the modes written
+  modes: string[];
+  // Version of the app that exported the file
+  app_version: string;
+}
+
+// Transient failures a retry policy can retry
+export type RetryClass = "rate_limited" | "server_error" | "network";
+
+export interface RetryPolicy {
+  attempts: number;
+  initial_backoff_ms: number;
+  max_backoff_ms: number;
+  jitter: boolean;
+  retry_on: RetryClass[];
 }
 
 // Recording status response
 export interface RecordingStatusResponse {
   status: RecordingStatus;
   is_recording: boolean;
+  // New dictations are kept off disk (no recording, history or journal)
+  incognito?: boolean;
+  // The user paused the current recording
+  paused?: boolean;
 }
 
 // Export format
@@ -77,3 +638,165 @@ export interface HistoryQuery {
   offset?: number;
   search?: string;
 }
+
+// Watchdog health
+export interface ComponentHealth {
+  healthy: boolean;
+  detail?: string;
+  repaired: boolean;
+}
+
+export interface HealthStatus {
+  hotkey: ComponentHealth;
+  audio: ComponentHealth;
+  tray: ComponentHealth;
+  checked_at: string | null;
+}
+
+// Optional integration and its opt-in state
+export interface Capability {
+  key: string;
+  name: string;
+  active: boolean;
+  requires_opt_in: boolean;
+  opted_in: boolean;
+  detail: string;
+}
+
+// Experimental subsystem that can be switched on or off
+export interface FeatureFlag {
+  key: string;
+  name: string;
+  description: string;
+  enabled: boolean;
+  // State in this release channel when not set
+  default: boolean;
+}
+
+export type ReleaseChannel = "stable" | "beta" | "nightly";
+
+// Notes for one release
+export interface Release {
+  version: string;
+  // Null until it is released
+  date: string | null;
+  notes: string[];
+}
+
+// Release notes not yet dismissed, newest first
+export interface WhatsNew {
+  version: string;
+  channel: ReleaseChannel;
+  releases: Release[];
+}
+
+// STT provider swapped in for the session
+export interface SttSelection {
+  provider: SttProvider;
+  model: string;
+}
+
+// Payload of the "stt-swapped" event
+export interface SttSwapResult {
+  selection: SttSelection | null;
+  ready: boolean;
+  error: string | null;
+}
+
+// Feature reduced by a missing optional binary, with how to fix it
+export interface Degradation {
+  key: string;
+  feature: string;
+  impact: string;
+  hint: string;
+}
+
+// ydotool setup diagnostics (hint is null when it is ready)
+export interface YdotoolStatus {
+  installed: boolean;
+  daemon_running: boolean;
+  socket_path: string | null;
+  uinput_writable: boolean;
+  allowed: boolean;
+  hint: string | null;
+}
+
+// Paste/typing timing (chunk_size 0 types everything at once)
+export interface PasteSettings {
+  pre_delay_ms: number;
+  per_key_delay_ms: number;
+  chunk_size: number;
+}
+
+export type PasteStrategy = "auto" | "paste" | "type" | "middle_click" | "clipboard_only";
+export type PasteShortcut = "ctrl_v" | "ctrl_shift_v" | "shift_insert";
+
+// Per-app paste behavior; class/title are case-insensitive regexes
+export interface PasteRule {
+  class?: string | null;
+  title?: string | null;
+  strategy?: PasteStrategy;
+  pre_delay_ms?: number | null;
+  shortcut?: PasteShortcut;
+}
+
+// Another machine receiving output instead of this one
+export type RemoteTarget =
+  | { kind: "ssh"; host: string; command: string }
+  | { kind: "paired"; url: string; token: string };
+
+// Paste backend capabilities
+export interface PasteInfo {
+  is_wayland: boolean;
+  paste_supported: boolean;
+  type_supported: boolean;
+  clipboard_supported: boolean;
+  clipboard_backend: string;
+  notes: string;
+  ydotool: YdotoolStatus | null;
+}
+
+// Sample clip for mode tests
+export interface SampleClip {
+  id: string;
+  path: string;
+  expected: string | null;
+  builtin: boolean;
+}
+
+// Stage outputs of a mode test run
+export interface ModeTestResult {
+  mode_key: string;
+  sample_id: string;
+  transcript: string;
+  llm_output: string | null;
+  llm_provider: string | null;
+  llm_error: string | null;
+  output: string;
+  truncated?: boolean;
+  expected: string | null;
+  word_error_rate: number | null;
+  latency: LatencySpan[];
+}
+
+// Text after one processing stage, e.g. "step 2: llm" or "typography"
+export interface StepOutput {
+  name: string;
+  // Prompt sent, for LLM stages
+  prompt: string | null;
+  text: string;
+  duration_ms: number;
+  // Why the stage was skipped, passing its input on
+  error: string | null;
+}
+
+// A mode's processing run on a typed transcript
+export interface DryRunResult {
+  transcript: string;
+  stages: StepOutput[];
+  output: string;
+  truncated: boolean;
+  prompt_tokens: number | null;
+  completion_tokens: number | null;
+  cost_usd: number | null;
+}